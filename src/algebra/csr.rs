@@ -0,0 +1,549 @@
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use std::ops::Range;
+
+/// Describes which entries of a sparse matrix are stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Storage {
+    /// Every non zero entry of the matrix is stored
+    General,
+    /// Only the upper triangle (diagonal included) of a symmetric matrix is stored
+    Upper,
+}
+
+/// Structure representing a sparse matrix in compressed sparse row format
+///
+/// # Generics
+///
+/// * DataType: the type of the values stored in the matrix
+///
+/// # Explanation
+///
+/// The column indices of each row are stored contiguously and sorted in increasing order. The
+/// entries of row `i` are found in the range `row_offsets[i]..row_offsets[i + 1]` of the column
+/// indices and values. With `Storage::Upper` only the entries with `column >= row` are kept and
+/// the lower triangle is implied by symmetry.
+#[derive(Clone, Debug)]
+pub struct CsrMatrix<DataType> {
+    number_of_columns: usize,
+    row_offsets: Vec<usize>,
+    column_indices: Vec<usize>,
+    values: Vec<DataType>,
+    storage: Storage,
+}
+
+impl<DataType: Scalar> CsrMatrix<DataType> {
+    /// Constructor of a zero valued matrix with a given sparsity pattern
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_columns`: the number of columns of the matrix
+    /// * `row_offsets`: the offsets of each row in the column indices (of length number of rows + 1)
+    /// * `column_indices`: the sorted column indices of each row
+    /// * `storage`: which part of the matrix the pattern describes
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the matrix or an error if the pattern is not consistent
+    pub fn from_pattern(
+        number_of_columns: usize,
+        row_offsets: Vec<usize>,
+        column_indices: Vec<usize>,
+        storage: Storage,
+    ) -> Result<CsrMatrix<DataType>, Error> {
+        if row_offsets.is_empty() || row_offsets[0] != 0 {
+            return Err(Error::InvalidArgument("Row offsets should start with 0"));
+        }
+        if *row_offsets.last().unwrap() != column_indices.len() {
+            return Err(Error::SizeMismatch {
+                context: "Last row offset should be the number of non zeros",
+                expected: column_indices.len(),
+                actual: *row_offsets.last().unwrap(),
+            });
+        }
+        if storage == Storage::Upper && row_offsets.len() - 1 != number_of_columns {
+            return Err(Error::InvalidArgument(
+                "Upper storage is only available for square matrices",
+            ));
+        }
+        if row_offsets.windows(2).any(|bounds| bounds[0] > bounds[1])
+            || row_offsets
+                .iter()
+                .any(|&offset| offset > column_indices.len())
+        {
+            return Err(Error::InvalidArgument(
+                "Row offsets should be increasing and within the column indices",
+            ));
+        }
+        for (row, bounds) in row_offsets.windows(2).enumerate() {
+            let columns = &column_indices[bounds[0]..bounds[1]];
+            if columns.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(Error::InvalidArgument(
+                    "Column indices should be strictly increasing in each row",
+                ));
+            }
+            if let Some(&column) = columns.iter().find(|&&column| column >= number_of_columns) {
+                return Err(Error::OutOfBounds {
+                    context: "Column index out of bounds",
+                    index: column,
+                    bound: number_of_columns,
+                });
+            }
+            if storage == Storage::Upper && columns.iter().any(|&column| column < row) {
+                return Err(Error::InvalidArgument(
+                    "Upper storage can not hold lower triangle entries",
+                ));
+            }
+        }
+        let values = vec![DataType::zero(); column_indices.len()];
+        Ok(CsrMatrix {
+            number_of_columns,
+            row_offsets,
+            column_indices,
+            values,
+            storage,
+        })
+    }
+
+    /// Constructor from a list of `(row, column, value)` triplets
+    ///
+    /// Values of duplicated triplets are summed together.
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_rows`: the number of rows of the matrix
+    /// * `number_of_columns`: the number of columns of the matrix
+    /// * `triplets`: the entries of the matrix
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the matrix or an error if a triplet is out of bounds
+    pub fn from_triplets(
+        number_of_rows: usize,
+        number_of_columns: usize,
+        triplets: &[(usize, usize, DataType)],
+    ) -> Result<CsrMatrix<DataType>, Error> {
+        let mut rows = vec![Vec::new(); number_of_rows];
+        for &(row, column, value) in triplets {
+            if row >= number_of_rows {
+                return Err(Error::OutOfBounds {
+                    context: "Triplet row out of bounds",
+                    index: row,
+                    bound: number_of_rows,
+                });
+            }
+            if column >= number_of_columns {
+                return Err(Error::OutOfBounds {
+                    context: "Triplet column out of bounds",
+                    index: column,
+                    bound: number_of_columns,
+                });
+            }
+            rows[row].push((column, value));
+        }
+        let mut row_offsets = Vec::with_capacity(number_of_rows + 1);
+        let mut column_indices = Vec::with_capacity(triplets.len());
+        let mut values = Vec::with_capacity(triplets.len());
+        row_offsets.push(0);
+        for row in rows.iter_mut() {
+            row.sort_by_key(|&(column, _)| column);
+            for &(column, value) in row.iter() {
+                if column_indices.len() > *row_offsets.last().unwrap()
+                    && *column_indices.last().unwrap() == column
+                {
+                    let last = values.len() - 1;
+                    values[last] = values[last] + value;
+                } else {
+                    column_indices.push(column);
+                    values.push(value);
+                }
+            }
+            row_offsets.push(column_indices.len());
+        }
+        Ok(CsrMatrix {
+            number_of_columns,
+            row_offsets,
+            column_indices,
+            values,
+            storage: Storage::General,
+        })
+    }
+
+    /// Get a copy of the matrix with every entry stored, expanding `Storage::Upper`
+    pub fn to_general(&self) -> CsrMatrix<DataType> {
+        if self.storage == Storage::General {
+            return self.clone();
+        }
+        let mut triplets = Vec::with_capacity(2 * self.values.len());
+        for (row, bounds) in self.row_offsets.windows(2).enumerate() {
+            for position in bounds[0]..bounds[1] {
+                let column = self.column_indices[position];
+                triplets.push((row, column, self.values[position]));
+                if column != row {
+                    triplets.push((column, row, self.values[position]));
+                }
+            }
+        }
+        CsrMatrix::from_triplets(self.get_number_of_rows(), self.number_of_columns, &triplets)
+            .unwrap()
+    }
+
+    /// Get the transpose of the matrix, a copy of it in `Storage::Upper`
+    pub fn transpose(&self) -> CsrMatrix<DataType> {
+        if self.storage == Storage::Upper {
+            return self.clone();
+        }
+        let mut triplets = Vec::with_capacity(self.values.len());
+        for (row, bounds) in self.row_offsets.windows(2).enumerate() {
+            for position in bounds[0]..bounds[1] {
+                triplets.push((self.column_indices[position], row, self.values[position]));
+            }
+        }
+        CsrMatrix::from_triplets(self.number_of_columns, self.get_number_of_rows(), &triplets)
+            .unwrap()
+    }
+
+    /// Get a copy of the matrix with its values transformed, typically to change their precision
+    ///
+    /// # Arguments
+    ///
+    /// * `f`: the transformation applied to every stored value
+    pub fn map<OtherType: Scalar>(
+        &self,
+        f: impl Fn(DataType) -> OtherType,
+    ) -> CsrMatrix<OtherType> {
+        CsrMatrix {
+            number_of_columns: self.number_of_columns,
+            row_offsets: self.row_offsets.clone(),
+            column_indices: self.column_indices.clone(),
+            values: self.values.iter().map(|&v| f(v)).collect(),
+            storage: self.storage,
+        }
+    }
+
+    /// Extract a sub-block of the matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `rows`: the range of rows of the block
+    /// * `columns`: the range of columns of the block
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the block, in general storage, or an error if a range is out of
+    ///   bounds
+    pub fn get_block(
+        &self,
+        rows: Range<usize>,
+        columns: Range<usize>,
+    ) -> Result<CsrMatrix<DataType>, Error> {
+        if rows.end > self.get_number_of_rows() {
+            return Err(Error::OutOfBounds {
+                context: "Block rows out of bounds",
+                index: rows.end,
+                bound: self.get_number_of_rows(),
+            });
+        }
+        if columns.end > self.number_of_columns {
+            return Err(Error::OutOfBounds {
+                context: "Block columns out of bounds",
+                index: columns.end,
+                bound: self.number_of_columns,
+            });
+        }
+        let general = self.to_general();
+        let mut triplets = Vec::new();
+        for row in rows.clone() {
+            for position in general.row_offsets[row]..general.row_offsets[row + 1] {
+                let column = general.column_indices[position];
+                if columns.contains(&column) {
+                    triplets.push((
+                        row - rows.start,
+                        column - columns.start,
+                        general.values[position],
+                    ));
+                }
+            }
+        }
+        CsrMatrix::from_triplets(rows.len(), columns.len(), &triplets)
+    }
+
+    /// Get the number of rows
+    pub fn get_number_of_rows(&self) -> usize {
+        self.row_offsets.len() - 1
+    }
+
+    /// Get the number of columns
+    pub fn get_number_of_columns(&self) -> usize {
+        self.number_of_columns
+    }
+
+    /// Get the number of stored entries
+    pub fn get_number_of_nonzeros(&self) -> usize {
+        self.column_indices.len()
+    }
+
+    /// Get the storage layout of the matrix
+    pub fn get_storage(&self) -> Storage {
+        self.storage
+    }
+
+    /// Get the row offsets
+    pub fn get_row_offsets(&self) -> &[usize] {
+        &self.row_offsets
+    }
+
+    /// Get the column indices
+    pub fn get_column_indices(&self) -> &[usize] {
+        &self.column_indices
+    }
+
+    /// Get the stored values
+    pub fn get_values(&self) -> &[DataType] {
+        &self.values
+    }
+
+    /// Get the stored values mutably
+    pub fn get_values_mut(&mut self) -> &mut [DataType] {
+        &mut self.values
+    }
+
+    /// Set all the stored values to zero, keeping the sparsity pattern
+    pub fn set_zero(&mut self) {
+        self.values.iter_mut().for_each(|v| *v = DataType::zero());
+    }
+
+    /// Get the position of the entry `(row, column)` in the stored values
+    ///
+    /// # Returns
+    ///
+    /// * An option holding the position of the entry or None if the entry is not stored
+    pub fn get_position(&self, row: usize, column: usize) -> Option<usize> {
+        if row >= self.get_number_of_rows() {
+            return None;
+        }
+        let start = self.row_offsets[row];
+        let end = self.row_offsets[row + 1];
+        self.column_indices[start..end]
+            .binary_search(&column)
+            .ok()
+            .map(|position| start + position)
+    }
+
+    /// Get the value of the entry `(row, column)`, zero if the entry is not stored
+    pub fn get(&self, row: usize, column: usize) -> DataType {
+        let (row, column) = match self.storage {
+            Storage::Upper if column < row => (column, row),
+            _ => (row, column),
+        };
+        self.get_position(row, column)
+            .map_or(DataType::zero(), |position| self.values[position])
+    }
+
+    /// Get the diagonal of the matrix
+    pub fn get_diagonal(&self) -> Vec<DataType> {
+        (0..self.get_number_of_rows())
+            .map(|row| self.get(row, row))
+            .collect()
+    }
+
+    /// Get the sums of the rows of the matrix, the lumped diagonal of a mass matrix
+    pub fn get_row_sums(&self) -> Vec<DataType> {
+        self.apply(&vec![DataType::one(); self.number_of_columns])
+    }
+
+    /// Apply the matrix to a vector
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: the vector to multiply (of length number of columns)
+    ///
+    /// # Returns
+    ///
+    /// * the product of the matrix with x
+    pub fn apply(&self, x: &[DataType]) -> Vec<DataType> {
+        let mut y = vec![DataType::zero(); self.get_number_of_rows()];
+        self.apply_into(x, &mut y);
+        y
+    }
+
+    /// Same as apply above but writing the product in a preallocated vector y
+    pub fn apply_into(&self, x: &[DataType], y: &mut [DataType]) {
+        assert_eq!(x.len(), self.number_of_columns, "Incorrect length of x");
+        assert_eq!(y.len(), self.get_number_of_rows(), "Incorrect length of y");
+        y.iter_mut().for_each(|v| *v = DataType::zero());
+        for (row, bounds) in self.row_offsets.windows(2).enumerate() {
+            let mut sum = DataType::zero();
+            for position in bounds[0]..bounds[1] {
+                let column = self.column_indices[position];
+                let value = self.values[position];
+                sum = sum + value * x[column];
+                if self.storage == Storage::Upper && column != row {
+                    y[column] = y[column] + value * x[row];
+                }
+            }
+            y[row] = y[row] + sum;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CsrMatrix, Storage};
+    use crate::error::Error;
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_from_triplets() {
+        let mat = CsrMatrix::from_triplets(
+            2,
+            3,
+            &[(1, 2, 1.0_f64), (0, 1, 2.0), (1, 2, 3.0), (1, 0, 4.0)],
+        )
+        .unwrap();
+        assert_eq!(mat.get_number_of_rows(), 2, "Incorrect number of rows");
+        assert_eq!(
+            mat.get_number_of_columns(),
+            3,
+            "Incorrect number of columns"
+        );
+        assert_eq!(
+            mat.get_number_of_nonzeros(),
+            3,
+            "Duplicates were not summed"
+        );
+        assert_eq!(mat.get_row_offsets(), &[0, 1, 3], "Incorrect row offsets");
+        assert_eq!(
+            mat.get_column_indices(),
+            &[1, 0, 2],
+            "Incorrect column indices"
+        );
+        assert!((mat.get(1, 2) - 4.0).abs() < TOL, "Incorrect summed value");
+        assert!(mat.get(0, 0).abs() < TOL, "Unstored value should be zero");
+        assert!(
+            CsrMatrix::from_triplets(2, 2, &[(2, 0, 1.0)]).is_err(),
+            "Out of bounds triplet accepted"
+        );
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mat = CsrMatrix::from_triplets(2, 3, &[(0, 2, 1.0_f64), (1, 0, 2.0), (1, 2, 3.0)])
+            .unwrap()
+            .transpose();
+        assert_eq!(mat.get_number_of_rows(), 3, "Incorrect number of rows");
+        assert_eq!(
+            mat.get_row_offsets(),
+            &[0, 1, 1, 3],
+            "Incorrect row offsets"
+        );
+        assert!(
+            (mat.get(0, 1) - 2.0).abs() < TOL && (mat.get(2, 1) - 3.0).abs() < TOL,
+            "Incorrect transposed values"
+        );
+    }
+
+    #[test]
+    fn test_from_pattern() {
+        let mat = CsrMatrix::<f64>::from_pattern(2, vec![0, 2, 3], vec![0, 1, 1], Storage::Upper)
+            .unwrap();
+        assert_eq!(mat.get_position(0, 1), Some(1), "Incorrect position");
+        assert_eq!(
+            mat.get_position(1, 0),
+            None,
+            "Lower entry should not be stored"
+        );
+        assert!(
+            CsrMatrix::<f64>::from_pattern(2, vec![0, 1, 2], vec![0, 0], Storage::Upper).is_err(),
+            "Lower triangle entry accepted in upper storage"
+        );
+        assert!(
+            CsrMatrix::<f64>::from_pattern(2, vec![0, 2], vec![1, 0], Storage::General).is_err(),
+            "Unsorted columns accepted"
+        );
+        assert!(
+            matches!(
+                CsrMatrix::<f64>::from_pattern(3, vec![0, 5, 3], vec![0, 1, 2], Storage::General),
+                Err(Error::InvalidArgument(_))
+            ),
+            "Decreasing row offsets accepted"
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let mat = CsrMatrix::from_triplets(
+            2,
+            3,
+            &[(0, 0, 1.0_f64), (0, 2, 2.0), (1, 1, 3.0), (1, 2, -1.0)],
+        )
+        .unwrap();
+        let y = mat.apply(&[1.0, 2.0, 3.0]);
+        assert!((y[0] - 7.0).abs() < TOL, "Incorrect first value");
+        assert!((y[1] - 3.0).abs() < TOL, "Incorrect second value");
+    }
+
+    #[test]
+    fn test_apply_upper() {
+        let mut mat =
+            CsrMatrix::from_pattern(3, vec![0, 2, 4, 5], vec![0, 1, 1, 2, 2], Storage::Upper)
+                .unwrap();
+        mat.get_values_mut()
+            .copy_from_slice(&[2.0_f64, -1.0, 2.0, -1.0, 2.0]);
+        assert!(
+            (mat.get(1, 0) + 1.0).abs() < TOL,
+            "Incorrect symmetric value"
+        );
+        let y = mat.apply(&[1.0, 2.0, 3.0]);
+        assert!(y[0].abs() < TOL, "Incorrect first value");
+        assert!(y[1].abs() < TOL, "Incorrect second value");
+        assert!((y[2] - 4.0).abs() < TOL, "Incorrect third value");
+        let general = mat.to_general();
+        assert_eq!(general.get_storage(), Storage::General, "Incorrect storage");
+        assert_eq!(
+            general.get_number_of_nonzeros(),
+            7,
+            "Incorrect number of non zeros"
+        );
+        assert!(
+            (general.get(1, 0) + 1.0).abs() < TOL,
+            "Incorrect expanded value"
+        );
+        let diag = mat.get_diagonal();
+        assert!(
+            diag.iter().all(|d| (d - 2.0).abs() < TOL),
+            "Incorrect diagonal"
+        );
+    }
+
+    #[test]
+    fn test_get_block() {
+        let mut mat =
+            CsrMatrix::from_pattern(3, vec![0, 2, 4, 5], vec![0, 2, 1, 2, 2], Storage::Upper)
+                .unwrap();
+        mat.get_values_mut()
+            .copy_from_slice(&[1.0_f64, 2.0, 3.0, 4.0, 5.0]);
+        let block = mat.get_block(1..3, 0..2).unwrap();
+        assert_eq!(block.get_number_of_rows(), 2, "Incorrect number of rows");
+        assert_eq!(
+            block.get_number_of_columns(),
+            2,
+            "Incorrect number of columns"
+        );
+        assert!((block.get(0, 1) - 3.0).abs() < TOL, "Incorrect block entry");
+        assert!(
+            (block.get(1, 0) - 2.0).abs() < TOL,
+            "Incorrect mirrored block entry"
+        );
+        assert!(block.get(0, 0).abs() < TOL, "Unstored value should be zero");
+        assert!(mat.get_block(0..4, 0..1).is_err(), "Out of bounds block");
+    }
+
+    #[test]
+    fn test_row_sums() {
+        let mat =
+            CsrMatrix::from_triplets(2, 2, &[(0, 0, 2.0_f64), (0, 1, 1.0), (1, 1, 3.0)]).unwrap();
+        assert_eq!(mat.get_row_sums(), vec![3.0, 3.0], "Incorrect row sums");
+    }
+}
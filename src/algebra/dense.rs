@@ -0,0 +1,183 @@
+use crate::algebra::scalar::Scalar;
+use num::Float;
+
+/// Solve a small dense system by Gaussian elimination with partial pivoting
+///
+/// # Arguments
+///
+/// * `matrix`: the square matrix in row major ordering
+/// * `rhs`: the right hand side, of the size of the matrix
+///
+/// # Returns
+///
+/// * the solution, or None if the matrix is singular
+pub fn solve_dense<DataType: Scalar + Float>(
+    matrix: &[DataType],
+    rhs: &[DataType],
+) -> Option<Vec<DataType>> {
+    let n = rhs.len();
+    let mut a = matrix.to_vec();
+    let mut x = rhs.to_vec();
+    for k in 0..n {
+        let pivot = (k..n).max_by(|&i, &j| {
+            a[i * n + k]
+                .abs()
+                .partial_cmp(&a[j * n + k].abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+        if a[pivot * n + k] == DataType::zero() || a[pivot * n + k].is_nan() {
+            return None;
+        }
+        if pivot != k {
+            for j in 0..n {
+                a.swap(k * n + j, pivot * n + j);
+            }
+            x.swap(k, pivot);
+        }
+        for i in k + 1..n {
+            let factor = a[i * n + k] / a[k * n + k];
+            for j in k..n {
+                a[i * n + j] = a[i * n + j] - factor * a[k * n + j];
+            }
+            x[i] = x[i] - factor * x[k];
+        }
+    }
+    for k in (0..n).rev() {
+        let sum = (k + 1..n).fold(x[k], |sum, j| sum - a[k * n + j] * x[j]);
+        x[k] = sum / a[k * n + k];
+    }
+    Some(x)
+}
+
+/// Compute the determinant of a small dense matrix by Gaussian elimination
+///
+/// # Arguments
+///
+/// * `matrix`: the square matrix in row major ordering
+/// * `n`: the size of the matrix
+pub fn determinant<DataType: Scalar + Float>(matrix: &[DataType], n: usize) -> DataType {
+    let mut a = matrix.to_vec();
+    let mut determinant = DataType::one();
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|&i, &j| {
+                a[i * n + k]
+                    .abs()
+                    .partial_cmp(&a[j * n + k].abs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap();
+        if a[pivot * n + k] == DataType::zero() {
+            return DataType::zero();
+        }
+        if pivot != k {
+            for j in 0..n {
+                a.swap(k * n + j, pivot * n + j);
+            }
+            determinant = -determinant;
+        }
+        determinant = determinant * a[k * n + k];
+        for i in k + 1..n {
+            let factor = a[i * n + k] / a[k * n + k];
+            for j in k..n {
+                a[i * n + j] = a[i * n + j] - factor * a[k * n + j];
+            }
+        }
+    }
+    determinant
+}
+
+/// Invert a matrix of at most three rows by its cofactors, without allocating
+///
+/// # Arguments
+///
+/// * `matrix`: the square matrix in row major ordering
+/// * `n`: the number of rows of the matrix, at most three
+///
+/// # Returns
+///
+/// * the inverse in row major ordering in the first `n * n` values of an array and the
+///   determinant, or None if the matrix is singular
+pub fn invert_small<DataType: Scalar + Float>(
+    matrix: &[DataType],
+    n: usize,
+) -> Option<([DataType; 9], DataType)> {
+    let mut inverse = [DataType::zero(); 9];
+    let m = |i: usize, j: usize| matrix[i * n + j];
+    let determinant = match n {
+        1 => {
+            inverse[0] = DataType::one();
+            m(0, 0)
+        }
+        2 => {
+            inverse[..4].copy_from_slice(&[m(1, 1), -m(0, 1), -m(1, 0), m(0, 0)]);
+            m(0, 0) * m(1, 1) - m(0, 1) * m(1, 0)
+        }
+        3 => {
+            for i in 0..3 {
+                for j in 0..3 {
+                    let (r0, r1) = ((j + 1) % 3, (j + 2) % 3);
+                    let (c0, c1) = ((i + 1) % 3, (i + 2) % 3);
+                    inverse[i * 3 + j] = m(r0, c0) * m(r1, c1) - m(r0, c1) * m(r1, c0);
+                }
+            }
+            (0..3).fold(DataType::zero(), |sum, j| sum + m(0, j) * inverse[j * 3])
+        }
+        _ => panic!("invert_small supports matrices of at most three rows"),
+    };
+    if determinant == DataType::zero() || !determinant.is_finite() {
+        return None;
+    }
+    for value in inverse[..n * n].iter_mut() {
+        *value = *value / determinant;
+    }
+    Some((inverse, determinant))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{determinant, invert_small, solve_dense};
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_solve_dense() {
+        let matrix = [0.0_f64, 2.0, 1.0, 1.0, 1.0, 0.0, 3.0, 0.0, 1.0];
+        let x = solve_dense(&matrix, &[5.0, 3.0, 4.0]).unwrap();
+        for (value, expected) in x.iter().zip([1.0, 2.0, 1.0]) {
+            assert!((value - expected).abs() < TOL, "Incorrect solution");
+        }
+        assert!(
+            (determinant(&matrix, 3) + 5.0).abs() < TOL,
+            "Incorrect determinant"
+        );
+        assert!(
+            solve_dense(&[1.0, 2.0, 2.0, 4.0], &[1.0, 1.0]).is_none(),
+            "Singular matrix solved"
+        );
+    }
+
+    #[test]
+    fn test_invert_small() {
+        let matrix = [0.0_f64, 2.0, 1.0, 1.0, 1.0, 0.0, 3.0, 0.0, 1.0];
+        let (inverse, det) = invert_small(&matrix, 3).unwrap();
+        assert!((det + 5.0).abs() < TOL, "Incorrect determinant");
+        for i in 0..3 {
+            for j in 0..3 {
+                let product =
+                    (0..3).fold(0.0, |sum, k| sum + matrix[i * 3 + k] * inverse[k * 3 + j]);
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product - expected).abs() < TOL, "Incorrect inverse");
+            }
+        }
+        let (inverse, det) = invert_small(&[2.0_f64, 1.0, 1.0, 3.0], 2).unwrap();
+        assert!((det - 5.0).abs() < TOL, "Incorrect determinant");
+        for (value, expected) in inverse.iter().zip([0.6, -0.2, -0.2, 0.4]) {
+            assert!((value - expected).abs() < TOL, "Incorrect inverse");
+        }
+        assert!(
+            invert_small(&[1.0_f64, 2.0, 2.0, 4.0], 2).is_none(),
+            "Singular matrix inverted"
+        );
+    }
+}
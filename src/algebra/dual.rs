@@ -0,0 +1,605 @@
+use crate::algebra::scalar::Scalar;
+use num::traits::{Num, NumCast, One, ToPrimitive, Zero};
+use num::{Float, FromPrimitive};
+use std::cmp::Ordering;
+use std::num::FpCategory;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+/// Dual number carrying a value and its derivatives with respect to `N` variables
+///
+/// # Generics
+///
+/// * DataType: the type of unit the value and the derivatives are encoded with
+/// * N: the number of independent variables
+///
+/// # Explanation
+///
+/// Every operation applies the chain rule to the derivatives alongside the value, so that a
+/// function written generically over `Scalar + Float` and evaluated on dual numbers seeded
+/// with `Dual::variable` returns its exact gradient, without finite difference truncation. The
+/// comparisons and the classification methods only look at the value, so that branches taken by
+/// the function are the ones taken on plain numbers. Functions that are not differentiable at a
+/// point (`abs` at zero, `floor` on integers) return one of the one sided derivatives.
+#[derive(Clone, Copy, Debug)]
+pub struct Dual<DataType, const N: usize> {
+    value: DataType,
+    derivatives: [DataType; N],
+}
+
+impl<DataType: Float, const N: usize> Dual<DataType, N> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: the value
+    /// * `derivatives`: the derivatives with respect to each variable
+    pub fn new(value: DataType, derivatives: [DataType; N]) -> Dual<DataType, N> {
+        Dual { value, derivatives }
+    }
+
+    /// Constructor of a constant, of null derivatives
+    pub fn constant(value: DataType) -> Dual<DataType, N> {
+        Dual::new(value, [DataType::zero(); N])
+    }
+
+    /// Constructor of the independent variable `index`, of unit derivative with respect to itself
+    pub fn variable(value: DataType, index: usize) -> Dual<DataType, N> {
+        let mut derivatives = [DataType::zero(); N];
+        derivatives[index] = DataType::one();
+        Dual::new(value, derivatives)
+    }
+
+    /// Get the value
+    pub fn get_value(&self) -> DataType {
+        self.value
+    }
+
+    /// Get the derivatives with respect to each variable
+    pub fn get_derivatives(&self) -> &[DataType; N] {
+        &self.derivatives
+    }
+
+    /// Get the derivative with respect to the variable `index`
+    pub fn get_derivative(&self, index: usize) -> DataType {
+        self.derivatives[index]
+    }
+
+    /// Apply a function of derivative `derivative` at the value
+    fn chain(&self, value: DataType, derivative: DataType) -> Dual<DataType, N> {
+        Dual::new(value, self.derivatives.map(|d| d * derivative))
+    }
+
+    /// Combine the derivatives of two dual numbers with the partial derivatives of a function
+    fn chain2(
+        &self,
+        other: &Dual<DataType, N>,
+        value: DataType,
+        partial: DataType,
+        other_partial: DataType,
+    ) -> Dual<DataType, N> {
+        let mut derivatives = self.derivatives;
+        for (d, &o) in derivatives.iter_mut().zip(&other.derivatives) {
+            *d = *d * partial + o * other_partial;
+        }
+        Dual::new(value, derivatives)
+    }
+}
+
+impl<DataType: Scalar + Float, const N: usize> Scalar for Dual<DataType, N> {}
+
+impl<DataType: Float, const N: usize> From<DataType> for Dual<DataType, N> {
+    fn from(value: DataType) -> Self {
+        Dual::constant(value)
+    }
+}
+
+impl<DataType: Float, const N: usize> PartialEq for Dual<DataType, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<DataType: Float, const N: usize> PartialOrd for Dual<DataType, N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<DataType: Float, const N: usize> Add for Dual<DataType, N> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let one = DataType::one();
+        self.chain2(&other, self.value + other.value, one, one)
+    }
+}
+
+impl<DataType: Float, const N: usize> Sub for Dual<DataType, N> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        let one = DataType::one();
+        self.chain2(&other, self.value - other.value, one, -one)
+    }
+}
+
+impl<DataType: Float, const N: usize> Mul for Dual<DataType, N> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        self.chain2(&other, self.value * other.value, other.value, self.value)
+    }
+}
+
+impl<DataType: Float, const N: usize> Div for Dual<DataType, N> {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let inverse = other.value.recip();
+        let value = self.value * inverse;
+        self.chain2(&other, value, inverse, -value * inverse)
+    }
+}
+
+impl<DataType: Float, const N: usize> Rem for Dual<DataType, N> {
+    type Output = Self;
+
+    fn rem(self, other: Self) -> Self {
+        let quotient = (self.value / other.value).trunc();
+        self.chain2(&other, self.value % other.value, DataType::one(), -quotient)
+    }
+}
+
+impl<DataType: Float, const N: usize> Neg for Dual<DataType, N> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Dual::new(-self.value, self.derivatives.map(|d| -d))
+    }
+}
+
+impl<DataType: Float, const N: usize> AddAssign for Dual<DataType, N> {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<DataType: Float, const N: usize> SubAssign for Dual<DataType, N> {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl<DataType: Float, const N: usize> MulAssign for Dual<DataType, N> {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl<DataType: Float, const N: usize> DivAssign for Dual<DataType, N> {
+    fn div_assign(&mut self, other: Self) {
+        *self = *self / other;
+    }
+}
+
+impl<DataType: Float, const N: usize> Zero for Dual<DataType, N> {
+    fn zero() -> Self {
+        Dual::constant(DataType::zero())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+}
+
+impl<DataType: Float, const N: usize> One for Dual<DataType, N> {
+    fn one() -> Self {
+        Dual::constant(DataType::one())
+    }
+}
+
+impl<DataType: Float, const N: usize> Num for Dual<DataType, N> {
+    type FromStrRadixErr = DataType::FromStrRadixErr;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        DataType::from_str_radix(s, radix).map(Dual::constant)
+    }
+}
+
+impl<DataType: Float, const N: usize> ToPrimitive for Dual<DataType, N> {
+    fn to_i64(&self) -> Option<i64> {
+        self.value.to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.value.to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        self.value.to_f64()
+    }
+}
+
+impl<DataType: Float, const N: usize> NumCast for Dual<DataType, N> {
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        <DataType as NumCast>::from(n).map(Dual::constant)
+    }
+}
+
+impl<DataType: Float + FromPrimitive, const N: usize> FromPrimitive for Dual<DataType, N> {
+    fn from_i64(n: i64) -> Option<Self> {
+        DataType::from_i64(n).map(Dual::constant)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        DataType::from_u64(n).map(Dual::constant)
+    }
+
+    fn from_f64(n: f64) -> Option<Self> {
+        DataType::from_f64(n).map(Dual::constant)
+    }
+}
+
+impl<DataType: Float, const N: usize> Float for Dual<DataType, N> {
+    fn nan() -> Self {
+        Dual::constant(DataType::nan())
+    }
+
+    fn infinity() -> Self {
+        Dual::constant(DataType::infinity())
+    }
+
+    fn neg_infinity() -> Self {
+        Dual::constant(DataType::neg_infinity())
+    }
+
+    fn neg_zero() -> Self {
+        Dual::constant(DataType::neg_zero())
+    }
+
+    fn min_value() -> Self {
+        Dual::constant(DataType::min_value())
+    }
+
+    fn min_positive_value() -> Self {
+        Dual::constant(DataType::min_positive_value())
+    }
+
+    fn epsilon() -> Self {
+        Dual::constant(DataType::epsilon())
+    }
+
+    fn max_value() -> Self {
+        Dual::constant(DataType::max_value())
+    }
+
+    fn is_nan(self) -> bool {
+        self.value.is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.value.is_infinite()
+    }
+
+    fn is_finite(self) -> bool {
+        self.value.is_finite()
+    }
+
+    fn is_normal(self) -> bool {
+        self.value.is_normal()
+    }
+
+    fn classify(self) -> FpCategory {
+        self.value.classify()
+    }
+
+    fn floor(self) -> Self {
+        Dual::constant(self.value.floor())
+    }
+
+    fn ceil(self) -> Self {
+        Dual::constant(self.value.ceil())
+    }
+
+    fn round(self) -> Self {
+        Dual::constant(self.value.round())
+    }
+
+    fn trunc(self) -> Self {
+        Dual::constant(self.value.trunc())
+    }
+
+    fn fract(self) -> Self {
+        Dual::new(self.value.fract(), self.derivatives)
+    }
+
+    fn abs(self) -> Self {
+        if self.value.is_sign_negative() {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn signum(self) -> Self {
+        Dual::constant(self.value.signum())
+    }
+
+    fn is_sign_positive(self) -> bool {
+        self.value.is_sign_positive()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.value.is_sign_negative()
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        self * a + b
+    }
+
+    fn recip(self) -> Self {
+        let inverse = self.value.recip();
+        self.chain(inverse, -inverse * inverse)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Self::one();
+        }
+        let power = self.value.powi(n - 1);
+        self.chain(power * self.value, DataType::from(n).unwrap() * power)
+    }
+
+    fn powf(self, n: Self) -> Self {
+        let value = self.value.powf(n.value);
+        let partial = n.value * self.value.powf(n.value - DataType::one());
+        if n.derivatives.iter().all(|d| d.is_zero()) {
+            return self.chain(value, partial);
+        }
+        self.chain2(&n, value, partial, value * self.value.ln())
+    }
+
+    fn sqrt(self) -> Self {
+        let value = self.value.sqrt();
+        self.chain(value, (value + value).recip())
+    }
+
+    fn exp(self) -> Self {
+        let value = self.value.exp();
+        self.chain(value, value)
+    }
+
+    fn exp2(self) -> Self {
+        let value = self.value.exp2();
+        self.chain(value, value * DataType::from(2.0).unwrap().ln())
+    }
+
+    fn ln(self) -> Self {
+        self.chain(self.value.ln(), self.value.recip())
+    }
+
+    fn log(self, base: Self) -> Self {
+        self.ln() / base.ln()
+    }
+
+    fn log2(self) -> Self {
+        let ln2 = DataType::from(2.0).unwrap().ln();
+        self.chain(self.value.log2(), (self.value * ln2).recip())
+    }
+
+    fn log10(self) -> Self {
+        let ln10 = DataType::from(10.0).unwrap().ln();
+        self.chain(self.value.log10(), (self.value * ln10).recip())
+    }
+
+    fn max(self, other: Self) -> Self {
+        if other.value.is_nan() || self.value >= other.value {
+            self
+        } else {
+            other
+        }
+    }
+
+    fn min(self, other: Self) -> Self {
+        if other.value.is_nan() || self.value <= other.value {
+            self
+        } else {
+            other
+        }
+    }
+
+    fn abs_sub(self, other: Self) -> Self {
+        if self.value <= other.value {
+            Self::zero()
+        } else {
+            self - other
+        }
+    }
+
+    fn cbrt(self) -> Self {
+        let value = self.value.cbrt();
+        self.chain(
+            value,
+            (DataType::from(3.0).unwrap() * value * value).recip(),
+        )
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        let value = self.value.hypot(other.value);
+        if value.is_zero() {
+            return Dual::new(value, self.derivatives);
+        }
+        self.chain2(&other, value, self.value / value, other.value / value)
+    }
+
+    fn sin(self) -> Self {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+
+    fn cos(self) -> Self {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+
+    fn tan(self) -> Self {
+        let value = self.value.tan();
+        self.chain(value, DataType::one() + value * value)
+    }
+
+    fn asin(self) -> Self {
+        let one = DataType::one();
+        self.chain(
+            self.value.asin(),
+            (one - self.value * self.value).sqrt().recip(),
+        )
+    }
+
+    fn acos(self) -> Self {
+        let one = DataType::one();
+        self.chain(
+            self.value.acos(),
+            -(one - self.value * self.value).sqrt().recip(),
+        )
+    }
+
+    fn atan(self) -> Self {
+        let one = DataType::one();
+        self.chain(self.value.atan(), (one + self.value * self.value).recip())
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        let square = self.value * self.value + other.value * other.value;
+        self.chain2(
+            &other,
+            self.value.atan2(other.value),
+            other.value / square,
+            -self.value / square,
+        )
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        (self.sin(), self.cos())
+    }
+
+    fn exp_m1(self) -> Self {
+        self.chain(self.value.exp_m1(), self.value.exp())
+    }
+
+    fn ln_1p(self) -> Self {
+        self.chain(self.value.ln_1p(), (DataType::one() + self.value).recip())
+    }
+
+    fn sinh(self) -> Self {
+        self.chain(self.value.sinh(), self.value.cosh())
+    }
+
+    fn cosh(self) -> Self {
+        self.chain(self.value.cosh(), self.value.sinh())
+    }
+
+    fn tanh(self) -> Self {
+        let value = self.value.tanh();
+        self.chain(value, DataType::one() - value * value)
+    }
+
+    fn asinh(self) -> Self {
+        let one = DataType::one();
+        self.chain(
+            self.value.asinh(),
+            (self.value * self.value + one).sqrt().recip(),
+        )
+    }
+
+    fn acosh(self) -> Self {
+        let one = DataType::one();
+        self.chain(
+            self.value.acosh(),
+            (self.value * self.value - one).sqrt().recip(),
+        )
+    }
+
+    fn atanh(self) -> Self {
+        let one = DataType::one();
+        self.chain(self.value.atanh(), (one - self.value * self.value).recip())
+    }
+
+    fn integer_decode(self) -> (u64, i16, i8) {
+        self.value.integer_decode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dual;
+    use crate::algebra::scalar::Scalar;
+    use num::Float;
+
+    const TOL: f64 = 1e-12;
+
+    /// Named function evaluated on dual and plain numbers
+    type Function = (
+        &'static str,
+        fn(Dual<f64, 1>) -> Dual<f64, 1>,
+        fn(f64) -> f64,
+    );
+
+    /// Function written once for any scalar, as the element kernels are
+    fn function<ScalarT: Scalar + Float>(x: ScalarT, y: ScalarT) -> ScalarT {
+        (x * y).sin() + x.powi(3) / y + (x * x + y * y).sqrt().ln() - y.exp() * x.atan()
+    }
+
+    #[test]
+    fn test_gradient() {
+        let (x, y) = (0.7_f64, 1.3_f64);
+        let value = function(Dual::<f64, 2>::variable(x, 0), Dual::variable(y, 1));
+        let r2 = x * x + y * y;
+        let dx = y * (x * y).cos() + 3.0 * x * x / y + x / r2 - y.exp() / (1.0 + x * x);
+        let dy = x * (x * y).cos() - x.powi(3) / (y * y) + y / r2 - y.exp() * x.atan();
+        assert!(
+            (value.get_value() - function(x, y)).abs() < TOL,
+            "Incorrect value"
+        );
+        assert!(
+            (value.get_derivative(0) - dx).abs() < TOL,
+            "Incorrect derivative with respect to x"
+        );
+        assert!(
+            (value.get_derivative(1) - dy).abs() < TOL,
+            "Incorrect derivative with respect to y"
+        );
+    }
+
+    #[test]
+    fn test_derivatives() {
+        let x = 0.4_f64;
+        let h = 1e-6;
+        let functions: [Function; 12] = [
+            ("tan", |u| u.tan(), |u| u.tan()),
+            ("asin", |u| u.asin(), |u| u.asin()),
+            ("acos", |u| u.acos(), |u| u.acos()),
+            ("cbrt", |u| u.cbrt(), |u| u.cbrt()),
+            ("exp2", |u| u.exp2(), |u| u.exp2()),
+            ("log10", |u| u.log10(), |u| u.log10()),
+            ("tanh", |u| u.tanh(), |u| u.tanh()),
+            ("asinh", |u| u.asinh(), |u| u.asinh()),
+            ("atanh", |u| u.atanh(), |u| u.atanh()),
+            ("recip", |u| u.recip(), |u| u.recip()),
+            ("powf", |u| u.powf(u), |u| u.powf(u)),
+            ("hypot", |u| u.hypot(u * u), |u| u.hypot(u * u)),
+        ];
+        for (name, dual, plain) in functions {
+            let derivative = dual(Dual::variable(x, 0)).get_derivative(0);
+            let difference = (plain(x + h) - plain(x - h)) / (2.0 * h);
+            assert!(
+                (derivative - difference).abs() < 1e-8,
+                "Incorrect derivative of {}",
+                name
+            );
+        }
+        let a = Dual::<f64, 1>::variable(-2.0, 0);
+        assert!(a < Dual::from(1.0), "Incorrect comparison");
+        assert!(
+            (a.abs().get_derivative(0) + 1.0).abs() < TOL,
+            "Incorrect derivative of abs"
+        );
+    }
+}
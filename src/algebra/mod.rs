@@ -0,0 +1,17 @@
+/// Module for compressed sparse row matrices
+pub mod csr;
+
+/// Module for dense vector operations
+pub mod vector;
+
+/// Module for fill reducing orderings
+pub mod ordering;
+
+/// Module for dual numbers of forward mode automatic differentiation
+pub mod dual;
+
+/// Module for small dense matrix operations
+pub mod dense;
+
+/// Module for the scalar types the library computes with
+pub mod scalar;
@@ -0,0 +1,103 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use std::collections::VecDeque;
+
+/// Compute the reverse Cuthill-McKee ordering of a square matrix
+///
+/// # Arguments
+///
+/// * `matrix`: the matrix, only its sparsity pattern symmetrized as `A + A^T` is used
+///
+/// # Returns
+///
+/// * the permutation as the list of the original indices in their new order
+///
+/// # Explanation
+///
+/// The ordering numbers the indices by a breadth first traversal of the graph of the matrix,
+/// visiting neighbours by increasing degree and starting each connected component from a node of
+/// minimum degree, then reverses the numbering. This clusters the entries around the diagonal,
+/// which limits the fill-in of direct factorizations.
+pub fn reverse_cuthill_mckee<DataType: Scalar>(matrix: &CsrMatrix<DataType>) -> Vec<usize> {
+    let n = matrix.get_number_of_rows();
+    let offsets = matrix.get_row_offsets();
+    let columns = matrix.get_column_indices();
+    let mut neighbours = vec![Vec::new(); n];
+    for row in 0..n {
+        for &column in &columns[offsets[row]..offsets[row + 1]] {
+            if column != row && column < n {
+                neighbours[row].push(column);
+                neighbours[column].push(row);
+            }
+        }
+    }
+    for list in neighbours.iter_mut() {
+        list.sort_unstable();
+        list.dedup();
+    }
+    let degree = |node: usize| neighbours[node].len();
+    let mut visited = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    let mut roots: Vec<usize> = (0..n).collect();
+    roots.sort_by_key(|&node| degree(node));
+    for root in roots {
+        if visited[root] {
+            continue;
+        }
+        visited[root] = true;
+        let mut queue = VecDeque::from([root]);
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            let mut next: Vec<usize> = neighbours[node]
+                .iter()
+                .cloned()
+                .filter(|&neighbour| !visited[neighbour])
+                .collect();
+            next.sort_by_key(|&neighbour| degree(neighbour));
+            for neighbour in next {
+                visited[neighbour] = true;
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    order.reverse();
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reverse_cuthill_mckee;
+    use crate::algebra::csr::CsrMatrix;
+
+    #[test]
+    fn test_bandwidth() {
+        // tridiagonal matrix with scrambled numbering
+        let scramble = [3, 7, 0, 5, 9, 1, 8, 2, 6, 4];
+        let mut triplets = Vec::new();
+        for i in 0..10 {
+            triplets.push((scramble[i], scramble[i], 2.0));
+            if i + 1 < 10 {
+                triplets.push((scramble[i], scramble[i + 1], -1.0));
+                triplets.push((scramble[i + 1], scramble[i], -1.0));
+            }
+        }
+        let mat = CsrMatrix::from_triplets(10, 10, &triplets).unwrap();
+        let order = reverse_cuthill_mckee(&mat);
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..10).collect::<Vec<usize>>(), "Not a permutation");
+        let mut position = [0; 10];
+        for (new, &old) in order.iter().enumerate() {
+            position[old] = new;
+        }
+        let bandwidth = triplets
+            .iter()
+            .map(|&(r, c, _)| (position[r] as i64 - position[c] as i64).abs())
+            .max()
+            .unwrap();
+        assert_eq!(
+            bandwidth, 1,
+            "Ordering did not recover the tridiagonal profile"
+        );
+    }
+}
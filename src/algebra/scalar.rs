@@ -0,0 +1,63 @@
+use num::{Complex, Num, One, Zero};
+use std::ops::{Add, Div, Mul, Sub};
+
+/// Unit type the coordinates and the fields of the library are encoded with
+///
+/// Copyable values closed under the four arithmetic operations, with a zero and a one, compared
+/// to tell the non finite ones apart. Implemented for the real, complex and dual numbers.
+pub trait Scalar:
+    Copy
+    + Zero
+    + One
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+{
+}
+
+impl Scalar for f32 {}
+
+impl Scalar for f64 {}
+
+impl<RealType: Scalar + Num> Scalar for Complex<RealType> {}
+
+#[cfg(test)]
+mod tests {
+    use super::Scalar;
+    use crate::algebra::dual::Dual;
+    use num::Complex;
+
+    fn sum_of_products<ScalarT: Scalar>(a: &[ScalarT], b: &[ScalarT]) -> ScalarT {
+        a.iter()
+            .zip(b)
+            .fold(ScalarT::zero(), |sum, (&x, &y)| sum + x * y)
+    }
+
+    #[test]
+    fn test_implementations() {
+        assert_eq!(
+            sum_of_products(&[1.0f32, 2.0], &[3.0, 4.0]),
+            11.0,
+            "Incorrect single precision product"
+        );
+        assert_eq!(
+            sum_of_products(&[1.0f64, 2.0], &[3.0, 4.0]),
+            11.0,
+            "Incorrect double precision product"
+        );
+        let i = Complex::new(0.0f64, 1.0);
+        assert_eq!(
+            sum_of_products(&[i, Complex::new(1.0, 0.0)], &[i, i]),
+            Complex::new(-1.0, 1.0),
+            "Incorrect complex product"
+        );
+        let x = Dual::<f64, 1>::variable(2.0, 0);
+        let product = sum_of_products(&[x, Dual::constant(1.0)], &[x, x]);
+        assert!(
+            product.get_value() == 6.0 && product.get_derivative(0) == 5.0,
+            "Incorrect dual product"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use crate::algebra::scalar::Scalar;
+use num::Float;
+
+/// Compute the dot product of two vectors
+pub fn dot<DataType: Scalar>(x: &[DataType], y: &[DataType]) -> DataType {
+    x.iter()
+        .zip(y.iter())
+        .fold(DataType::zero(), |sum, (&a, &b)| sum + a * b)
+}
+
+/// Compute the euclidean norm of a vector
+pub fn norm<DataType: Scalar + Float>(x: &[DataType]) -> DataType {
+    dot(x, x).sqrt()
+}
+
+/// Compute `y = y + alpha * x`
+pub fn axpy<DataType: Scalar>(alpha: DataType, x: &[DataType], y: &mut [DataType]) {
+    y.iter_mut()
+        .zip(x.iter())
+        .for_each(|(b, &a)| *b = *b + alpha * a);
+}
+
+/// Compute `y = x + beta * y`
+pub fn xpby<DataType: Scalar>(x: &[DataType], beta: DataType, y: &mut [DataType]) {
+    y.iter_mut()
+        .zip(x.iter())
+        .for_each(|(b, &a)| *b = a + beta * *b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{axpy, dot, norm, xpby};
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_dot_norm() {
+        assert!(
+            (dot(&[1.0, 2.0], &[3.0, -1.0]) - 1.0_f64).abs() < TOL,
+            "Incorrect dot"
+        );
+        assert!((norm(&[3.0, 4.0]) - 5.0_f64).abs() < TOL, "Incorrect norm");
+    }
+
+    #[test]
+    fn test_updates() {
+        let mut y = [1.0, 1.0];
+        axpy(2.0, &[1.0, -1.0], &mut y);
+        assert!(
+            (y[0] - 3.0_f64).abs() < TOL && (y[1] + 1.0).abs() < TOL,
+            "Incorrect axpy"
+        );
+        xpby(&[1.0, 1.0], 0.5, &mut y);
+        assert!(
+            (y[0] - 2.5).abs() < TOL && (y[1] - 0.5).abs() < TOL,
+            "Incorrect xpby"
+        );
+    }
+}
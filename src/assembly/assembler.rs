@@ -0,0 +1,1468 @@
+use crate::algebra::csr::{CsrMatrix, Storage};
+use crate::algebra::scalar::Scalar;
+use crate::assembly::cell_block::{BatchData, CellBlock};
+use crate::assembly::constraints::Constraints;
+use crate::assembly::options::{AssemblyOptions, AssemblyPhase};
+use crate::element::operator_trait::Operator;
+use crate::element::residual_trait::ResidualKernel;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::parallel::Parallelism;
+use num::Float;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Copy of the global matrix filled by a thread, or the failing cell and its error, along with the
+/// time the thread spent computing and scattering
+type PartialAssembly<DataType> = (
+    Result<CsrMatrix<DataType>, (usize, Error)>,
+    Duration,
+    Duration,
+);
+
+/// Assembles global sparse matrices from the local matrices of an operator
+///
+/// # Explanation
+///
+/// The assembler first builds the sparsity pattern of the global matrix from the cell to degree of
+/// freedom connectivity and then scatters the local matrices computed by the operator on each
+/// cell into it. Operators flagged as symmetric are assembled in `Storage::Upper`, only filling
+/// the upper triangle of the global matrix, unless symmetric storage is disabled. The assembly
+/// loops can be instrumented through `AssemblyOptions`, and the parallel ones are configured by a
+/// `Parallelism`, the global one unless set.
+pub struct Assembler<'a> {
+    number_of_dofs: usize,
+    symmetric_storage: bool,
+    options: AssemblyOptions<'a>,
+    parallelism: Option<Parallelism>,
+}
+
+impl<'a> Assembler<'a> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_dofs`: the total number of degrees of freedom (rows of the global matrix)
+    pub fn new(number_of_dofs: usize) -> Assembler<'a> {
+        Assembler {
+            number_of_dofs,
+            symmetric_storage: true,
+            options: AssemblyOptions::new(),
+            parallelism: None,
+        }
+    }
+
+    /// Set the instrumentation of the assembly loops
+    pub fn set_options(&mut self, options: AssemblyOptions<'a>) {
+        self.options = options;
+    }
+
+    /// Get the instrumentation of the assembly loops
+    pub fn get_options(&self) -> &AssemblyOptions<'a> {
+        &self.options
+    }
+
+    /// Set the configuration of the parallel assembly loops, overriding the global one
+    pub fn set_parallelism(&mut self, parallelism: Parallelism) {
+        self.parallelism = Some(parallelism);
+    }
+
+    /// Get the configuration of the parallel assembly loops
+    pub fn get_parallelism(&self) -> Parallelism {
+        self.parallelism.unwrap_or_else(Parallelism::get_global)
+    }
+
+    /// Get the total number of degrees of freedom
+    pub fn get_number_of_dofs(&self) -> usize {
+        self.number_of_dofs
+    }
+
+    /// Enable or disable upper triangle storage for symmetric operators
+    pub fn set_symmetric_storage(&mut self, symmetric_storage: bool) {
+        self.symmetric_storage = symmetric_storage;
+    }
+
+    /// Get the storage the assembler uses for an operator
+    pub fn get_storage<CoordType, DataType, OperatorT>(&self, operator: &OperatorT) -> Storage
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        if self.symmetric_storage && operator.is_symmetric() {
+            Storage::Upper
+        } else {
+            Storage::General
+        }
+    }
+
+    /// Create a zero valued matrix holding the sparsity pattern induced by a block of cells
+    ///
+    /// # Arguments
+    ///
+    /// * `block`: the cells coupling the degrees of freedom
+    /// * `storage`: which part of the matrix to keep
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the matrix or an error if a cell references an unknown dof
+    pub fn create_matrix<CoordType, DataType: Scalar>(
+        &self,
+        block: &CellBlock<CoordType, DataType>,
+        storage: Storage,
+    ) -> Result<CsrMatrix<DataType>, Error> {
+        let mut rows = vec![Vec::new(); self.number_of_dofs];
+        for cell in 0..block.get_number_of_cells() {
+            let dofs = block.get_cell_dofs(cell);
+            if let Some(&dof) = dofs.iter().find(|&&dof| dof >= self.number_of_dofs) {
+                return Err(Error::OutOfBounds {
+                    context: "Cell dof out of bounds",
+                    index: dof,
+                    bound: self.number_of_dofs,
+                });
+            }
+            for &row in dofs {
+                rows[row].extend(
+                    dofs.iter()
+                        .filter(|&&column| storage == Storage::General || column >= row),
+                );
+            }
+        }
+        compress_rows(rows, storage)
+    }
+
+    /// Assemble the global matrix of an operator over a block of cells
+    ///
+    /// # Arguments
+    ///
+    /// * `operator`: the operator computing the local matrices
+    /// * `block`: the cells to assemble over
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the global matrix or an error if the local matrices or the
+    ///   connectivity are not consistent
+    pub fn assemble<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+    ) -> Result<CsrMatrix<DataType>, Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        let mut matrix = self.timed(AssemblyPhase::Pattern, || {
+            self.create_matrix(block, self.get_storage(operator))
+        })?;
+        self.reassemble_values(operator, block, &mut matrix)?;
+        Ok(matrix)
+    }
+
+    /// Refill the values of a previously assembled matrix in place
+    ///
+    /// The sparsity pattern of the matrix is reused as is: no symbolic work nor allocation of the
+    /// global matrix is performed. This is meant for repeated assemblies over the same cells, as in
+    /// time stepping or Newton iterations, where only the values of the operator change.
+    ///
+    /// # Arguments
+    ///
+    /// * `operator`: the operator computing the local matrices
+    /// * `block`: the cells to assemble over (same connectivity as for the original assembly)
+    /// * `matrix`: the matrix to refill
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the matrix does not match the operator and the connectivity
+    pub fn reassemble_values<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+        matrix: &mut CsrMatrix<DataType>,
+    ) -> Result<(), Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        self.check_matrix(operator, matrix)?;
+        matrix.set_zero();
+        let mut data = HashMap::new();
+        self.run_cells(
+            block.get_number_of_cells(),
+            1,
+            |cell, workspace, local| {
+                block.get_cell_data_into(cell, &mut data);
+                operator.compute_into(block.get_cell_coordinates(cell), &data, workspace, local)
+            },
+            |cell, _, local| {
+                scatter(matrix, cell, block.get_cell_dofs(cell), local.len(), |i| {
+                    local[i]
+                })
+            },
+        )
+    }
+
+    /// Assemble the global matrix of an operator processing the cells in batches of `LANES`
+    ///
+    /// The cells are gathered `LANES` at a time in SOA ordering, into buffers reused from one
+    /// batch to the next, and handed to `Operator::compute_batch`, so that operators implementing
+    /// it with loops over the lanes get their quadrature loops vectorized. Typical batch sizes are
+    /// 4 or 8: with those the diffusion and elasticity operators of the models assemble about two
+    /// and three times faster than cell by cell on biquadratic quadrilaterals in release builds.
+    ///
+    /// # Arguments
+    ///
+    /// * `operator`: the operator computing the local matrices
+    /// * `block`: the cells to assemble over
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the global matrix or an error if the local matrices or the
+    ///   connectivity are not consistent
+    pub fn assemble_batched<const LANES: usize, CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+    ) -> Result<CsrMatrix<DataType>, Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        let mut matrix = self.timed(AssemblyPhase::Pattern, || {
+            self.create_matrix(block, self.get_storage(operator))
+        })?;
+        self.reassemble_values_batched::<LANES, _, _, _>(operator, block, &mut matrix)?;
+        Ok(matrix)
+    }
+
+    /// Same as reassemble_values above but processing the cells in batches of `LANES`
+    pub fn reassemble_values_batched<const LANES: usize, CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+        matrix: &mut CsrMatrix<DataType>,
+    ) -> Result<(), Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        if LANES == 0 {
+            return Err(Error::InvalidArgument(
+                "Batches should hold at least one cell",
+            ));
+        }
+        self.check_matrix(operator, matrix)?;
+        matrix.set_zero();
+        let mut batch: BatchData<CoordType, DataType, LANES> = (Vec::new(), HashMap::new());
+        self.run_cells(
+            block.get_number_of_cells(),
+            LANES,
+            |first_cell, _: &mut Workspace<DataType>, local| {
+                block.get_batch_data_into(first_cell, &mut batch);
+                let (coordinates, fields) = &batch;
+                let data = fields
+                    .iter()
+                    .map(|(name, values)| (name.clone(), &values[..]))
+                    .collect();
+                *local = operator.compute_batch(coordinates, &data)
+            },
+            |cell, lane, local| {
+                scatter(matrix, cell, block.get_cell_dofs(cell), local.len(), |i| {
+                    local[i][lane]
+                })
+            },
+        )
+    }
+
+    /// Assemble the global matrix of an operator computing the cells on several threads
+    ///
+    /// The cells are split in chunks as configured by `get_parallelism`. By default each thread
+    /// scatters the local matrices of its chunks into its own copy of the global matrix and the
+    /// copies are summed at the end. In deterministic mode the local matrices of the chunks are
+    /// scattered into the global matrix on the calling thread in the order of the cells, so that
+    /// the result is bitwise the one of `assemble`. The pattern phase, and the compute and scatter
+    /// phases summed over the threads, the latter including the sum of the copies, are reported to
+    /// the phase callback; the progress callback is not called.
+    ///
+    /// # Arguments
+    ///
+    /// * `operator`: the operator computing the local matrices
+    /// * `block`: the cells to assemble over
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the global matrix or an error if the local matrices or the
+    ///   connectivity are not consistent
+    pub fn assemble_parallel<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+    ) -> Result<CsrMatrix<DataType>, Error>
+    where
+        CoordType: Scalar + Sync,
+        DataType: Scalar + Send + Sync,
+        OperatorT: Operator<CoordType, DataType> + Sync,
+    {
+        let mut matrix = self.timed(AssemblyPhase::Pattern, || {
+            self.create_matrix(block, self.get_storage(operator))
+        })?;
+        self.reassemble_values_parallel(operator, block, &mut matrix)?;
+        Ok(matrix)
+    }
+
+    /// Same as reassemble_values above but computing the cells on several threads
+    pub fn reassemble_values_parallel<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+        matrix: &mut CsrMatrix<DataType>,
+    ) -> Result<(), Error>
+    where
+        CoordType: Scalar + Sync,
+        DataType: Scalar + Send + Sync,
+        OperatorT: Operator<CoordType, DataType> + Sync,
+    {
+        self.check_matrix(operator, matrix)?;
+        matrix.set_zero();
+        let parallelism = self.get_parallelism();
+        let timing = self.options.is_timing();
+        let elapsed =
+            |start: Option<Instant>| start.map_or(Duration::ZERO, |start| start.elapsed());
+        let compute_cells = |cells: Range<usize>| -> (Vec<Vec<DataType>>, Duration) {
+            let start = timing.then(Instant::now);
+            let mut workspace = Workspace::new();
+            let mut data = HashMap::new();
+            let locals = cells
+                .map(|cell| {
+                    let mut local = Vec::new();
+                    block.get_cell_data_into(cell, &mut data);
+                    operator.compute_into(
+                        block.get_cell_coordinates(cell),
+                        &data,
+                        &mut workspace,
+                        &mut local,
+                    );
+                    local
+                })
+                .collect();
+            (locals, elapsed(start))
+        };
+        if parallelism.is_deterministic() {
+            let mut compute_time = Duration::ZERO;
+            let mut scatter_time = Duration::ZERO;
+            let result = parallelism.for_each_ordered(
+                block.get_number_of_cells(),
+                compute_cells,
+                |cells, (locals, cells_time)| {
+                    compute_time += cells_time;
+                    let start = timing.then(Instant::now);
+                    for (cell, local) in cells.zip(locals) {
+                        scatter(matrix, cell, block.get_cell_dofs(cell), local.len(), |i| {
+                            local[i]
+                        })
+                        .map_err(|error| self.reported(cell, error))?;
+                    }
+                    scatter_time += elapsed(start);
+                    Ok(())
+                },
+            );
+            self.options
+                .report_phase(AssemblyPhase::Compute, compute_time);
+            self.options
+                .report_phase(AssemblyPhase::Scatter, scatter_time);
+            return result;
+        }
+        let zero = matrix.clone();
+        let (values, compute_time, scatter_time) = parallelism.fold(
+            block.get_number_of_cells(),
+            || (Ok(zero.clone()), Duration::ZERO, Duration::ZERO),
+            |(copy, compute_time, scatter_time): &mut PartialAssembly<DataType>, cells| {
+                let Ok(copy_matrix) = copy else {
+                    return;
+                };
+                let (locals, cells_time) = compute_cells(cells.clone());
+                *compute_time += cells_time;
+                let start = timing.then(Instant::now);
+                for (cell, local) in cells.zip(locals) {
+                    if let Err(error) = scatter(
+                        copy_matrix,
+                        cell,
+                        block.get_cell_dofs(cell),
+                        local.len(),
+                        |i| local[i],
+                    ) {
+                        *copy = Err((cell, error));
+                        return;
+                    }
+                }
+                *scatter_time += elapsed(start);
+            },
+            |(first, first_compute, first_scatter), (second, second_compute, second_scatter)| {
+                let start = timing.then(Instant::now);
+                let merged = first.and_then(|mut first| {
+                    for (a, &b) in first.get_values_mut().iter_mut().zip(second?.get_values()) {
+                        *a = *a + b;
+                    }
+                    Ok(first)
+                });
+                let merge_time = elapsed(start);
+                (
+                    merged,
+                    first_compute + second_compute,
+                    first_scatter + second_scatter + merge_time,
+                )
+            },
+        );
+        let start = timing.then(Instant::now);
+        let result = match values {
+            Ok(values) => {
+                matrix.get_values_mut().copy_from_slice(values.get_values());
+                Ok(())
+            }
+            Err((cell, error)) => Err(self.reported(cell, error)),
+        };
+        self.options
+            .report_phase(AssemblyPhase::Compute, compute_time);
+        self.options
+            .report_phase(AssemblyPhase::Scatter, scatter_time + elapsed(start));
+        result
+    }
+
+    /// Create a zero valued matrix holding the sparsity pattern of the system condensed by a set of
+    /// constraints
+    ///
+    /// Constrained dofs are replaced by their masters in the couplings and keep their diagonal
+    /// entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `block`: the cells coupling the degrees of freedom
+    /// * `constraints`: the constraints to condense
+    /// * `storage`: which part of the matrix to keep
+    pub fn create_constrained_matrix<CoordType, DataType: Scalar>(
+        &self,
+        block: &CellBlock<CoordType, DataType>,
+        constraints: &Constraints<DataType>,
+        storage: Storage,
+    ) -> Result<CsrMatrix<DataType>, Error> {
+        let mut rows = vec![Vec::new(); self.number_of_dofs];
+        for cell in 0..block.get_number_of_cells() {
+            let dofs = block.get_cell_dofs(cell);
+            let mut masters = Vec::with_capacity(dofs.len());
+            for &dof in dofs {
+                if dof >= self.number_of_dofs {
+                    return Err(Error::OutOfBounds {
+                        context: "Cell dof out of bounds",
+                        index: dof,
+                        bound: self.number_of_dofs,
+                    });
+                }
+                if constraints.is_constrained(dof) {
+                    rows[dof].push(dof);
+                }
+                masters.extend(constraints.expand(dof).map(|(master, _)| master));
+            }
+            if let Some(&master) = masters
+                .iter()
+                .find(|&&master| master >= self.number_of_dofs)
+            {
+                return Err(Error::OutOfBounds {
+                    context: "Constraint master out of bounds",
+                    index: master,
+                    bound: self.number_of_dofs,
+                });
+            }
+            for &row in masters.iter() {
+                rows[row].extend(
+                    masters
+                        .iter()
+                        .filter(|&&column| storage == Storage::General || column >= row),
+                );
+            }
+        }
+        compress_rows(rows, storage)
+    }
+
+    /// Assemble the global matrix of an operator condensing a set of constraints on the fly
+    ///
+    /// Every local matrix is transformed by the constraints before being scattered, so that the
+    /// global matrix is `C^T K C` on the unconstrained dofs, with the local diagonal entries of the
+    /// constrained dofs kept on their global diagonal. A symmetric positive definite operator hence
+    /// gives a symmetric positive definite condensed matrix. Once the condensed system is solved
+    /// the constrained values are recovered with `Constraints::distribute`.
+    ///
+    /// # Arguments
+    ///
+    /// * `operator`: the operator computing the local matrices
+    /// * `block`: the cells to assemble over
+    /// * `constraints`: the constraints to condense
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the condensed matrix along with the contribution of the
+    ///   inhomogeneities to the right hand side, or an error if the inputs are not consistent
+    pub fn assemble_constrained<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+        constraints: &Constraints<DataType>,
+    ) -> Result<(CsrMatrix<DataType>, Vec<DataType>), Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        let mut matrix = self.timed(AssemblyPhase::Pattern, || {
+            self.create_constrained_matrix(block, constraints, self.get_storage(operator))
+        })?;
+        let mut rhs = vec![DataType::zero(); self.number_of_dofs];
+        self.reassemble_constrained_values(operator, block, constraints, &mut matrix, &mut rhs)?;
+        Ok((matrix, rhs))
+    }
+
+    /// Same as reassemble_values above but condensing a set of constraints, the right hand side
+    /// contribution of the inhomogeneities is written in rhs
+    pub fn reassemble_constrained_values<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+        constraints: &Constraints<DataType>,
+        matrix: &mut CsrMatrix<DataType>,
+        rhs: &mut [DataType],
+    ) -> Result<(), Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        self.check_matrix(operator, matrix)?;
+        if rhs.len() != self.number_of_dofs {
+            return Err(Error::SizeMismatch {
+                context: "Right hand side size does not match the number of dofs",
+                expected: self.number_of_dofs,
+                actual: rhs.len(),
+            });
+        }
+        matrix.set_zero();
+        rhs.iter_mut().for_each(|v| *v = DataType::zero());
+        let mut data = HashMap::new();
+        self.run_cells(
+            block.get_number_of_cells(),
+            1,
+            |cell, workspace, local| {
+                block.get_cell_data_into(cell, &mut data);
+                operator.compute_into(block.get_cell_coordinates(cell), &data, workspace, local)
+            },
+            |cell, _, local| {
+                scatter_constrained(
+                    matrix,
+                    rhs,
+                    cell,
+                    block.get_cell_dofs(cell),
+                    local,
+                    constraints,
+                )
+            },
+        )
+    }
+
+    /// Assemble the diagonal of the global matrix of an operator without forming the matrix
+    ///
+    /// Only the diagonal entries of the local matrices are accumulated, which provides the
+    /// diagonal needed by Jacobi preconditioning in matrix free solves.
+    ///
+    /// # Arguments
+    ///
+    /// * `operator`: the operator computing the local matrices
+    /// * `block`: the cells to assemble over
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the diagonal or an error if the inputs are not consistent
+    pub fn assemble_diagonal<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+    ) -> Result<Vec<DataType>, Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        self.assemble_constrained_diagonal(operator, block, &Constraints::new())
+    }
+
+    /// Same as assemble_diagonal above but for the matrix condensed by a set of constraints, as
+    /// given by assemble_constrained
+    pub fn assemble_constrained_diagonal<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        block: &CellBlock<CoordType, DataType>,
+        constraints: &Constraints<DataType>,
+    ) -> Result<Vec<DataType>, Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        let mut diagonal = vec![DataType::zero(); self.number_of_dofs];
+        let mut data = HashMap::new();
+        self.run_cells(
+            block.get_number_of_cells(),
+            1,
+            |cell, workspace, local| {
+                block.get_cell_data_into(cell, &mut data);
+                operator.compute_into(block.get_cell_coordinates(cell), &data, workspace, local)
+            },
+            |cell, _, local| {
+                let dofs = block.get_cell_dofs(cell);
+                let n = dofs.len();
+                check_local_matrix(local.len(), n)?;
+                check_finite(cell, local.len(), |i| local[i])?;
+                if let Some(dof) = dofs
+                    .iter()
+                    .flat_map(|&dof| constraints.expand(dof).map(|(master, _)| master))
+                    .chain(dofs.iter().copied())
+                    .find(|&dof| dof >= self.number_of_dofs)
+                {
+                    return Err(Error::OutOfBounds {
+                        context: "Cell dof out of bounds",
+                        index: dof,
+                        bound: self.number_of_dofs,
+                    });
+                }
+                for (a, &row) in dofs.iter().enumerate() {
+                    if constraints.is_constrained(row) {
+                        diagonal[row] = diagonal[row] + local[a * n + a];
+                    }
+                    for (b, &column) in dofs.iter().enumerate() {
+                        for (master_row, row_weight) in constraints.expand(row) {
+                            for (master_column, column_weight) in constraints.expand(column) {
+                                if master_row == master_column {
+                                    diagonal[master_row] = diagonal[master_row]
+                                        + row_weight * column_weight * local[a * n + b];
+                                }
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        Ok(diagonal)
+    }
+
+    /// Assemble the global residual of a residual kernel over a block of cells
+    ///
+    /// The local state of each cell is gathered from the global state through the connectivity,
+    /// and the local residuals are summed at the global degrees of freedom.
+    ///
+    /// # Arguments
+    ///
+    /// * `kernel`: the kernel computing the local residuals
+    /// * `block`: the cells to assemble over
+    /// * `state`: the global state
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the residual or an error if the inputs are not consistent
+    pub fn assemble_residual<CoordType, DataType, KernelT>(
+        &self,
+        kernel: &KernelT,
+        block: &CellBlock<CoordType, DataType>,
+        state: &[DataType],
+    ) -> Result<Vec<DataType>, Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar + Float,
+        KernelT: ResidualKernel<CoordType, DataType>,
+    {
+        if state.len() != self.number_of_dofs {
+            return Err(Error::SizeMismatch {
+                context: "State size does not match the number of dofs",
+                expected: self.number_of_dofs,
+                actual: state.len(),
+            });
+        }
+        if block.get_dofs_per_cell() != kernel.get_number_of_dofs() {
+            return Err(Error::SizeMismatch {
+                context: "Dofs per cell do not match the kernel",
+                expected: kernel.get_number_of_dofs(),
+                actual: block.get_dofs_per_cell(),
+            });
+        }
+        let cell_state = block.gather(state)?;
+        let n = block.get_dofs_per_cell();
+        let mut residual = vec![DataType::zero(); self.number_of_dofs];
+        let mut data = HashMap::new();
+        self.run_cells(
+            block.get_number_of_cells(),
+            1,
+            |cell, _: &mut Workspace<DataType>, local| {
+                block.get_cell_data_into(cell, &mut data);
+                *local = kernel.compute_residual(
+                    block.get_cell_coordinates(cell),
+                    &data,
+                    &cell_state[cell * n..(cell + 1) * n],
+                )
+            },
+            |cell, _, local| {
+                if local.len() != n {
+                    return Err(Error::SizeMismatch {
+                        context: "Local residual size does not match the dofs per cell",
+                        expected: n,
+                        actual: local.len(),
+                    });
+                }
+                check_finite(cell, n, |i| local[i])?;
+                for (&dof, &value) in block.get_cell_dofs(cell).iter().zip(local) {
+                    residual[dof] = residual[dof] + value;
+                }
+                Ok(())
+            },
+        )?;
+        Ok(residual)
+    }
+
+    /// Check that a matrix to refill matches the number of dofs and the storage of an operator
+    fn check_matrix<CoordType, DataType, OperatorT>(
+        &self,
+        operator: &OperatorT,
+        matrix: &CsrMatrix<DataType>,
+    ) -> Result<(), Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        OperatorT: Operator<CoordType, DataType>,
+    {
+        if matrix.get_number_of_rows() != self.number_of_dofs {
+            return Err(Error::SizeMismatch {
+                context: "Matrix size does not match the number of dofs",
+                expected: self.number_of_dofs,
+                actual: matrix.get_number_of_rows(),
+            });
+        }
+        if matrix.get_storage() != self.get_storage(operator) {
+            return Err(Error::InvalidArgument(
+                "Matrix storage does not match the operator",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Report the failure of a cell to the error callback and pass the error on
+    fn reported(&self, cell: usize, error: Error) -> Error {
+        self.options.report_error(cell, &error);
+        error
+    }
+
+    /// Run a closure reporting its duration as a phase when timings are requested
+    fn timed<T>(&self, phase: AssemblyPhase, f: impl FnOnce() -> T) -> T {
+        if !self.options.is_timing() {
+            return f();
+        }
+        let start = Instant::now();
+        let result = f();
+        self.options.report_phase(phase, start.elapsed());
+        result
+    }
+
+    /// Instrumented assembly loop over batches of cells
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_cells`: the number of cells to loop over
+    /// * `batch_size`: the number of cells computed at once
+    /// * `compute`: overwrites the local matrices with the ones of the batch starting at a cell,
+    ///   given the workspace shared by the loop
+    /// * `scatter`: scatters the local matrix of a cell given its index and lane in the batch
+    fn run_cells<DataType: Scalar, LocalT: Default>(
+        &self,
+        number_of_cells: usize,
+        batch_size: usize,
+        mut compute: impl FnMut(usize, &mut Workspace<DataType>, &mut LocalT),
+        mut scatter: impl FnMut(usize, usize, &LocalT) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut workspace = Workspace::new();
+        let mut local = LocalT::default();
+        let timing = self.options.is_timing();
+        let interval = self.options.get_progress_interval();
+        let mut compute_time = Duration::ZERO;
+        let mut scatter_time = Duration::ZERO;
+        let mut next_report = interval;
+        for first_cell in (0..number_of_cells).step_by(batch_size) {
+            let start = timing.then(Instant::now);
+            compute(first_cell, &mut workspace, &mut local);
+            if let Some(start) = start {
+                compute_time += start.elapsed();
+            }
+            let start = timing.then(Instant::now);
+            let last_cell = (first_cell + batch_size).min(number_of_cells);
+            for (lane, cell) in (first_cell..last_cell).enumerate() {
+                if let Err(error) = scatter(cell, lane, &local) {
+                    self.options.report_error(cell, &error);
+                    return Err(error);
+                }
+            }
+            if let Some(start) = start {
+                scatter_time += start.elapsed();
+            }
+            if last_cell >= next_report || last_cell == number_of_cells {
+                self.options.report_progress(last_cell, number_of_cells);
+                next_report = (last_cell / interval + 1) * interval;
+            }
+        }
+        self.options
+            .report_phase(AssemblyPhase::Compute, compute_time);
+        self.options
+            .report_phase(AssemblyPhase::Scatter, scatter_time);
+        Ok(())
+    }
+}
+
+/// Build a zero valued matrix from the unsorted columns of each row
+fn compress_rows<DataType: Scalar>(
+    mut rows: Vec<Vec<usize>>,
+    storage: Storage,
+) -> Result<CsrMatrix<DataType>, Error> {
+    let mut row_offsets = Vec::with_capacity(rows.len() + 1);
+    let mut column_indices = Vec::new();
+    row_offsets.push(0);
+    for row in rows.iter_mut() {
+        row.sort_unstable();
+        row.dedup();
+        column_indices.extend_from_slice(row);
+        row_offsets.push(column_indices.len());
+    }
+    CsrMatrix::from_pattern(rows.len(), row_offsets, column_indices, storage)
+}
+
+/// Check that a local matrix has an entry per pair of the dofs of its cell
+fn check_local_matrix(number_of_entries: usize, number_of_dofs: usize) -> Result<(), Error> {
+    if number_of_entries != number_of_dofs * number_of_dofs {
+        return Err(Error::SizeMismatch {
+            context: "Local matrix size does not match the dofs per cell",
+            expected: number_of_dofs * number_of_dofs,
+            actual: number_of_entries,
+        });
+    }
+    Ok(())
+}
+
+/// Check that the local values computed on a cell, given by their number and an accessor to them,
+/// are finite
+///
+/// Operators and kernels signal a failure on a cell, a degenerate map for instance, by returning
+/// non finite values. A value is finite when its product with zero is zero, which holds for the
+/// real, complex and dual scalars alike.
+fn check_finite<DataType: Scalar>(
+    cell: usize,
+    number_of_entries: usize,
+    local: impl Fn(usize) -> DataType,
+) -> Result<(), Error> {
+    if (0..number_of_entries)
+        .map(local)
+        .any(|value| value * DataType::zero() != DataType::zero())
+    {
+        return Err(Error::NotFiniteCell { cell });
+    }
+    Ok(())
+}
+
+/// Add the local matrix of a cell, given by the number of its entries and an accessor to them, into
+/// the global matrix, skipping the lower triangle in `Storage::Upper`
+fn scatter<DataType: Scalar>(
+    matrix: &mut CsrMatrix<DataType>,
+    cell: usize,
+    dofs: &[usize],
+    number_of_entries: usize,
+    local: impl Fn(usize) -> DataType,
+) -> Result<(), Error> {
+    let n = dofs.len();
+    check_local_matrix(number_of_entries, n)?;
+    check_finite(cell, number_of_entries, &local)?;
+    let upper = matrix.get_storage() == Storage::Upper;
+    for (a, &row) in dofs.iter().enumerate() {
+        for (b, &column) in dofs.iter().enumerate() {
+            if upper && column < row {
+                continue;
+            }
+            let position = matrix
+                .get_position(row, column)
+                .ok_or(Error::MissingEntry { row, column })?;
+            let values = matrix.get_values_mut();
+            values[position] = values[position] + local(a * n + b);
+        }
+    }
+    Ok(())
+}
+
+/// Add the local matrix of a cell transformed by a set of constraints into the global matrix and
+/// right hand side
+fn scatter_constrained<DataType: Scalar>(
+    matrix: &mut CsrMatrix<DataType>,
+    rhs: &mut [DataType],
+    cell: usize,
+    dofs: &[usize],
+    local: &[DataType],
+    constraints: &Constraints<DataType>,
+) -> Result<(), Error> {
+    let n = dofs.len();
+    check_local_matrix(local.len(), n)?;
+    check_finite(cell, local.len(), |i| local[i])?;
+    let upper = matrix.get_storage() == Storage::Upper;
+    let mut add = |row: usize, column: usize, value: DataType| -> Result<(), Error> {
+        let position = matrix
+            .get_position(row, column)
+            .ok_or(Error::MissingEntry { row, column })?;
+        let values = matrix.get_values_mut();
+        values[position] = values[position] + value;
+        Ok(())
+    };
+    for (a, &row) in dofs.iter().enumerate() {
+        for (b, &column) in dofs.iter().enumerate() {
+            let value = local[a * n + b];
+            if a == b && constraints.is_constrained(row) {
+                add(row, row, value)?;
+            }
+            if let Some(line) = constraints.get_line(column) {
+                for (master, weight) in constraints.expand(row) {
+                    rhs[master] = rhs[master] - weight * value * line.get_inhomogeneity();
+                }
+            }
+            for (master_row, row_weight) in constraints.expand(row) {
+                for (master_column, column_weight) in constraints.expand(column) {
+                    if upper && master_column < master_row {
+                        continue;
+                    }
+                    add(
+                        master_row,
+                        master_column,
+                        row_weight * column_weight * value,
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Assembler;
+    use crate::algebra::csr::Storage;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::assembly::constraints::Constraints;
+    use crate::assembly::options::{AssemblyOptions, AssemblyPhase};
+    use crate::element::residual_trait::AutomaticTangent;
+    use crate::error::Error;
+    use crate::parallel::Parallelism;
+    use crate::test_utils::{uniform_segments, Advection, CubicReaction, Laplacian};
+    use crate::timer::TimerReport;
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_assemble_general() {
+        let (dofs, coords) = uniform_segments(4);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let assembler = Assembler::new(5);
+        let mat = assembler.assemble(&Advection, &block).unwrap();
+        assert_eq!(mat.get_storage(), Storage::General, "Incorrect storage");
+        assert_eq!(
+            mat.get_number_of_nonzeros(),
+            13,
+            "Incorrect number of non zeros"
+        );
+        assert!((mat.get(1, 0) + 1.0).abs() < TOL, "Incorrect lower value");
+        assert!(mat.get(0, 1).abs() < TOL, "Incorrect upper value");
+        assert!(
+            (mat.get(2, 2) - 1.0).abs() < TOL,
+            "Incorrect diagonal value"
+        );
+    }
+
+    #[test]
+    fn test_assemble_symmetric() {
+        let (dofs, coords) = uniform_segments(4);
+        let conductivity = [1.0, 1.0, 2.0, 2.0];
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let mut assembler = Assembler::new(5);
+        let upper = assembler.assemble(&Laplacian, &block).unwrap();
+        assembler.set_symmetric_storage(false);
+        let general = assembler.assemble(&Laplacian, &block).unwrap();
+        assert_eq!(upper.get_storage(), Storage::Upper, "Incorrect storage");
+        assert_eq!(
+            upper.get_number_of_nonzeros(),
+            9,
+            "Incorrect number of non zeros"
+        );
+        assert_eq!(
+            general.get_number_of_nonzeros(),
+            13,
+            "Incorrect number of non zeros"
+        );
+        assert!(
+            (upper.get(2, 2) - 12.0).abs() < TOL,
+            "Incorrect diagonal value"
+        );
+        for row in 0..5 {
+            for column in 0..5 {
+                assert!(
+                    (upper.get(row, column) - general.get(row, column)).abs() < TOL,
+                    "Storages differ at ({}, {})",
+                    row,
+                    column
+                );
+            }
+        }
+        let x = [1.0, -2.0, 0.5, 3.0, 1.5];
+        let y_upper = upper.apply(&x);
+        let y_general = general.apply(&x);
+        for (u, g) in y_upper.iter().zip(y_general.iter()) {
+            assert!((u - g).abs() < TOL, "Symmetric product differs");
+        }
+    }
+
+    #[test]
+    fn test_reassemble_values() {
+        let (dofs, coords) = uniform_segments(4);
+        let conductivity = [1.0, 1.0, 1.0, 1.0];
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let assembler = Assembler::new(5);
+        let mut mat = assembler.assemble(&Laplacian, &block).unwrap();
+        let pattern = mat.get_column_indices().to_vec();
+        let conductivity = [3.0, 3.0, 3.0, 3.0];
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        assembler
+            .reassemble_values(&Laplacian, &block, &mut mat)
+            .unwrap();
+        let reference = assembler.assemble(&Laplacian, &block).unwrap();
+        assert_eq!(mat.get_column_indices(), &pattern[..], "Pattern changed");
+        for (v, r) in mat.get_values().iter().zip(reference.get_values().iter()) {
+            assert!((v - r).abs() < TOL, "Incorrect reassembled value");
+        }
+        assert!(
+            assembler
+                .reassemble_values(&Advection, &block, &mut mat)
+                .is_err(),
+            "Non symmetric operator reassembled in upper storage"
+        );
+    }
+
+    #[test]
+    fn test_assemble_batched() {
+        let (dofs, coords) = uniform_segments(10);
+        let conductivity: Vec<f64> = (0..10).map(|c| 1.0 + c as f64).collect();
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let assembler = Assembler::new(11);
+        let reference = assembler.assemble(&Laplacian, &block).unwrap();
+        let batched_4 = assembler
+            .assemble_batched::<4, _, _, _>(&Laplacian, &block)
+            .unwrap();
+        let batched_8 = assembler
+            .assemble_batched::<8, _, _, _>(&Laplacian, &block)
+            .unwrap();
+        for ((r, b4), b8) in reference
+            .get_values()
+            .iter()
+            .zip(batched_4.get_values().iter())
+            .zip(batched_8.get_values().iter())
+        {
+            assert!((r - b4).abs() < TOL, "Incorrect batch of 4 value");
+            assert!((r - b8).abs() < TOL, "Incorrect batch of 8 value");
+        }
+        let reference = assembler.assemble(&Advection, &block).unwrap();
+        let fallback = assembler
+            .assemble_batched::<4, _, _, _>(&Advection, &block)
+            .unwrap();
+        for (r, f) in reference
+            .get_values()
+            .iter()
+            .zip(fallback.get_values().iter())
+        {
+            assert!((r - f).abs() < TOL, "Incorrect fallback batch value");
+        }
+    }
+
+    #[test]
+    fn test_assemble_dirichlet() {
+        let (dofs, coords) = uniform_segments(4);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let mut constraints = Constraints::new();
+        constraints.add_dirichlet(0, 0.0).unwrap();
+        constraints.add_dirichlet(4, 1.0).unwrap();
+        let assembler = Assembler::new(5);
+        let (mat, rhs) = assembler
+            .assemble_constrained(&Laplacian, &block, &constraints)
+            .unwrap();
+        assert_eq!(mat.get_storage(), Storage::Upper, "Incorrect storage");
+        assert_eq!(mat.get_position(0, 1), None, "Constrained coupling stored");
+        assert!(
+            (mat.get(0, 0) - 4.0).abs() < TOL,
+            "Incorrect constrained diagonal"
+        );
+        assert!(
+            (rhs[3] - 4.0).abs() < TOL,
+            "Incorrect inhomogeneity contribution"
+        );
+        let mut x = [0.0, 0.25, 0.5, 0.75, 0.0];
+        let y = mat.apply(&x);
+        for (v, r) in y.iter().zip(rhs.iter()) {
+            assert!(
+                (v - r).abs() < TOL,
+                "Linear solution does not solve the system"
+            );
+        }
+        constraints.distribute(&mut x);
+        assert!((x[4] - 1.0).abs() < TOL, "Incorrect distributed value");
+    }
+
+    #[test]
+    fn test_assemble_periodic() {
+        let (dofs, coords) = uniform_segments(4);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let mut constraints = Constraints::new();
+        constraints.add_line(4, &[(0, 1.0)], 0.0).unwrap();
+        let assembler = Assembler::new(5);
+        let (mat, rhs) = assembler
+            .assemble_constrained(&Laplacian, &block, &constraints)
+            .unwrap();
+        assert!(
+            (mat.get(0, 0) - 8.0).abs() < TOL,
+            "Incorrect periodic diagonal"
+        );
+        assert!(
+            (mat.get(0, 3) + 4.0).abs() < TOL,
+            "Incorrect periodic coupling"
+        );
+        assert!(
+            (mat.get(3, 0) + 4.0).abs() < TOL,
+            "Incorrect symmetric coupling"
+        );
+        assert!(
+            rhs.iter().all(|v| v.abs() < TOL),
+            "Homogeneous constraint gave a rhs"
+        );
+    }
+
+    #[test]
+    fn test_assemble_diagonal() {
+        let (dofs, coords) = uniform_segments(4);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let mut constraints = Constraints::new();
+        constraints.add_dirichlet(0, 0.0).unwrap();
+        constraints.add_line(4, &[(1, 0.5), (3, 0.5)], 0.0).unwrap();
+        let assembler = Assembler::new(5);
+        let mat = assembler.assemble(&Advection, &block).unwrap();
+        let diagonal = assembler.assemble_diagonal(&Advection, &block).unwrap();
+        for (d, m) in diagonal.iter().zip(mat.get_diagonal().iter()) {
+            assert!((d - m).abs() < TOL, "Incorrect diagonal value");
+        }
+        let (mat, _) = assembler
+            .assemble_constrained(&Laplacian, &block, &constraints)
+            .unwrap();
+        let diagonal = assembler
+            .assemble_constrained_diagonal(&Laplacian, &block, &constraints)
+            .unwrap();
+        for (d, m) in diagonal.iter().zip(mat.get_diagonal().iter()) {
+            assert!((d - m).abs() < TOL, "Incorrect constrained diagonal value");
+        }
+    }
+
+    #[test]
+    fn test_instrumentation() {
+        let (dofs, coords) = uniform_segments(10);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let phases = RefCell::new(Vec::new());
+        let progress = RefCell::new(Vec::new());
+        let mut options = AssemblyOptions::new();
+        options.set_phase_callback(|phase, _| phases.borrow_mut().push(phase));
+        options.set_progress_callback(4, |done, total| progress.borrow_mut().push((done, total)));
+        let mut assembler = Assembler::new(11);
+        assembler.set_options(options);
+        assembler.assemble(&Laplacian, &block).unwrap();
+        assembler
+            .assemble_batched::<8, _, _, _>(&Laplacian, &block)
+            .unwrap();
+        drop(assembler);
+        assert_eq!(
+            phases.into_inner(),
+            vec![
+                AssemblyPhase::Pattern,
+                AssemblyPhase::Compute,
+                AssemblyPhase::Scatter,
+                AssemblyPhase::Pattern,
+                AssemblyPhase::Compute,
+                AssemblyPhase::Scatter
+            ],
+            "Incorrect reported phases"
+        );
+        assert_eq!(
+            progress.into_inner(),
+            vec![(4, 10), (8, 10), (10, 10), (8, 10), (10, 10)],
+            "Incorrect reported progress"
+        );
+    }
+
+    #[test]
+    fn test_parallel_instrumentation() {
+        let (dofs, coords) = uniform_segments(1000);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let mut parallelism = Parallelism::new();
+        parallelism.set_number_of_threads(2);
+        parallelism.set_chunk_size(50);
+        for deterministic in [false, true] {
+            let phases = RefCell::new(Vec::new());
+            let mut options = AssemblyOptions::new();
+            options
+                .set_phase_callback(|phase, duration| phases.borrow_mut().push((phase, duration)));
+            parallelism.set_deterministic(deterministic);
+            let mut assembler = Assembler::new(1001);
+            assembler.set_options(options);
+            assembler.set_parallelism(parallelism);
+            assembler.assemble_parallel(&Laplacian, &block).unwrap();
+            drop(assembler);
+            let phases = phases.into_inner();
+            assert_eq!(
+                phases.iter().map(|&(phase, _)| phase).collect::<Vec<_>>(),
+                vec![
+                    AssemblyPhase::Pattern,
+                    AssemblyPhase::Compute,
+                    AssemblyPhase::Scatter
+                ],
+                "Incorrect reported parallel phases"
+            );
+            assert!(
+                phases[2].1 > Duration::ZERO,
+                "Parallel scatter phase not measured"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parallel_assembly() {
+        let (dofs, coords) = uniform_segments(1000);
+        let conductivity: Vec<f64> = (0..1000).map(|cell| 1.0 + 0.1 * cell as f64).collect();
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let sequential = Assembler::new(1001).assemble(&Laplacian, &block).unwrap();
+        let mut parallelism = Parallelism::new();
+        parallelism.set_number_of_threads(4);
+        parallelism.set_chunk_size(7);
+        let mut assembler = Assembler::new(1001);
+        assembler.set_parallelism(parallelism);
+        let matrix = assembler.assemble_parallel(&Laplacian, &block).unwrap();
+        assert_eq!(
+            matrix.get_column_indices(),
+            sequential.get_column_indices(),
+            "Incorrect parallel pattern"
+        );
+        for (a, b) in matrix.get_values().iter().zip(sequential.get_values()) {
+            assert!((a - b).abs() < TOL, "Incorrect parallel value");
+        }
+        parallelism.set_deterministic(true);
+        assembler.set_parallelism(parallelism);
+        let matrix = assembler.assemble_parallel(&Laplacian, &block).unwrap();
+        assert!(
+            matrix
+                .get_values()
+                .iter()
+                .zip(sequential.get_values())
+                .all(|(a, b)| a.to_bits() == b.to_bits()),
+            "Deterministic assembly differs from the sequential one"
+        );
+        let (dofs, coords) = uniform_segments(4);
+        let block = CellBlock::new(1, &dofs, &coords).unwrap();
+        let failures = RefCell::new(Vec::new());
+        let mut options = AssemblyOptions::new();
+        options.set_error_callback(|cell, _| failures.borrow_mut().push(cell));
+        let mut assembler = Assembler::new(5);
+        assembler.set_options(options);
+        assembler.set_parallelism(parallelism);
+        assert!(
+            assembler.assemble_parallel(&Advection, &block).is_err(),
+            "Inconsistent local matrix accepted"
+        );
+        drop(assembler);
+        assert_eq!(failures.into_inner(), vec![0], "Incorrect failing cell");
+    }
+
+    #[test]
+    fn test_timer_report() {
+        let (dofs, coords) = uniform_segments(10);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let report = TimerReport::new();
+        let mut options = AssemblyOptions::new();
+        options.set_timer_report(&report);
+        let mut assembler = Assembler::new(11);
+        assembler.set_options(options);
+        assembler.assemble(&Laplacian, &block).unwrap();
+        assembler.assemble(&Laplacian, &block).unwrap();
+        drop(assembler);
+        assert_eq!(
+            report.get_names(),
+            vec!["assembly/pattern", "assembly/compute", "assembly/scatter"],
+            "Incorrect reported phases"
+        );
+        assert_eq!(
+            report.get_number_of_calls("assembly/compute"),
+            2,
+            "Incorrect number of reported assemblies"
+        );
+    }
+
+    #[test]
+    fn test_error_cell() {
+        let (dofs, coords) = uniform_segments(4);
+        let block = CellBlock::new(1, &dofs, &coords).unwrap();
+        let failures = RefCell::new(Vec::new());
+        let mut options = AssemblyOptions::new();
+        options.set_error_callback(|cell, message| {
+            failures.borrow_mut().push((cell, message.to_string()))
+        });
+        let mut assembler = Assembler::new(5);
+        assembler.set_options(options);
+        assert!(
+            assembler.assemble(&Advection, &block).is_err(),
+            "Inconsistent local matrix accepted"
+        );
+        drop(assembler);
+        let failures = failures.into_inner();
+        assert_eq!(failures.len(), 1, "Incorrect number of reported failures");
+        assert_eq!(failures[0].0, 0, "Incorrect failing cell");
+    }
+
+    #[test]
+    fn test_non_finite_cell() {
+        let (dofs, mut coords) = uniform_segments(4);
+        coords[5] = coords[4];
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let failures = RefCell::new(Vec::new());
+        let mut options = AssemblyOptions::new();
+        options.set_error_callback(|cell, _| failures.borrow_mut().push(cell));
+        let mut assembler = Assembler::new(5);
+        assembler.set_options(options);
+        assert!(
+            matches!(
+                assembler.assemble(&Laplacian, &block),
+                Err(Error::NotFiniteCell { cell: 2 })
+            ),
+            "Non finite local matrix assembled"
+        );
+        assert!(
+            matches!(
+                assembler.assemble_batched::<4, _, _, _>(&Laplacian, &block),
+                Err(Error::NotFiniteCell { cell: 2 })
+            ),
+            "Non finite batched local matrix assembled"
+        );
+        let mut parallelism = Parallelism::new();
+        parallelism.set_number_of_threads(2);
+        parallelism.set_chunk_size(1);
+        assembler.set_parallelism(parallelism);
+        assert!(
+            matches!(
+                assembler.assemble_parallel(&Laplacian, &block),
+                Err(Error::NotFiniteCell { cell: 2 })
+            ),
+            "Non finite local matrix assembled in parallel"
+        );
+        assert!(
+            matches!(
+                assembler.assemble_diagonal(&Laplacian, &block),
+                Err(Error::NotFiniteCell { cell: 2 })
+            ),
+            "Non finite local diagonal assembled"
+        );
+        drop(assembler);
+        assert_eq!(
+            failures.into_inner(),
+            vec![2; 4],
+            "Incorrect reported failing cells"
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let (dofs, coords) = uniform_segments(4);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        assert!(
+            matches!(
+                Assembler::new(4).assemble(&Laplacian, &block),
+                Err(Error::OutOfBounds {
+                    index: 4,
+                    bound: 4,
+                    ..
+                })
+            ),
+            "Out of bounds dof accepted"
+        );
+        let block = CellBlock::new(1, &dofs, &coords).unwrap();
+        assert!(
+            matches!(
+                Assembler::new(5).assemble(&Advection, &block),
+                Err(Error::SizeMismatch {
+                    expected: 1,
+                    actual: 4,
+                    ..
+                })
+            ),
+            "Inconsistent local matrix accepted"
+        );
+    }
+
+    #[test]
+    fn test_assemble_residual() {
+        let (dofs, coords) = uniform_segments(4);
+        let state: Vec<f64> = (0..5).map(|i| 0.1 * i as f64).collect();
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let cell_state = block.gather(&state).unwrap();
+        block.add_field("state", &cell_state).unwrap();
+        let assembler = Assembler::new(5);
+        let residual = assembler
+            .assemble_residual(&CubicReaction, &block, &state)
+            .unwrap();
+        let tangent = AutomaticTangent::<_, _, _, 2>::new(CubicReaction, "state").unwrap();
+        let jacobian = assembler.assemble(&tangent, &block).unwrap();
+        let stiffness = assembler.assemble(&Laplacian, &block).unwrap();
+        let mut expected = stiffness.apply(&state);
+        let mut expected_jacobian = stiffness.to_general();
+        for cell in 0..4 {
+            let u = 0.5 * (state[cell] + state[cell + 1]);
+            for (row, value) in expected.iter_mut().enumerate().skip(cell).take(2) {
+                *value += 0.125 * u.powi(3);
+                for column in cell..cell + 2 {
+                    let position = expected_jacobian.get_position(row, column).unwrap();
+                    expected_jacobian.get_values_mut()[position] += 0.1875 * u.powi(2);
+                }
+            }
+        }
+        for (value, expected) in residual.iter().zip(&expected) {
+            assert!((value - expected).abs() < TOL, "Incorrect residual");
+        }
+        for (value, expected) in jacobian
+            .get_values()
+            .iter()
+            .zip(expected_jacobian.get_values())
+        {
+            assert!((value - expected).abs() < TOL, "Incorrect jacobian");
+        }
+        assert!(
+            assembler
+                .assemble_residual(&CubicReaction, &block, &state[..4])
+                .is_err(),
+            "Wrong state size accepted"
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use crate::error::Error;
+use std::collections::HashMap;
+
+/// Coordinates and data fields of a batch of `LANES` cells in SOA ordering, as given by
+/// `CellBlock::get_batch_data`
+pub type BatchData<CoordType, DataType, const LANES: usize> = (
+    Vec<[CoordType; LANES]>,
+    HashMap<String, Vec<[DataType; LANES]>>,
+);
+
+/// Describes a set of cells of the same type to assemble over
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the cell data is encoded with
+///
+/// # Explanation
+///
+/// All the cells of a block share the same element, hence the same number of degrees of freedom
+/// and of coordinates per cell. The cell to degree of freedom connectivity, the cell coordinates
+/// and the cell data fields are all stored flattened cell after cell.
+pub struct CellBlock<'a, CoordType, DataType> {
+    number_of_cells: usize,
+    dofs_per_cell: usize,
+    cell_dofs: &'a [usize],
+    coordinates_per_cell: usize,
+    cell_coordinates: &'a [CoordType],
+    fields: HashMap<String, &'a [DataType]>,
+}
+
+impl<'a, CoordType, DataType> CellBlock<'a, CoordType, DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `dofs_per_cell`: the number of degrees of freedom of each cell
+    /// * `cell_dofs`: the global degree of freedom indices of each cell
+    /// * `cell_coordinates`: the real coordinates of each cell in AOS ordering
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the block or an error if the slice lengths are not consistent
+    pub fn new(
+        dofs_per_cell: usize,
+        cell_dofs: &'a [usize],
+        cell_coordinates: &'a [CoordType],
+    ) -> Result<CellBlock<'a, CoordType, DataType>, Error> {
+        if dofs_per_cell == 0 || !cell_dofs.len().is_multiple_of(dofs_per_cell) {
+            return Err(Error::InvalidArgument(
+                "Cell dofs length is not a multiple of the dofs per cell",
+            ));
+        }
+        let number_of_cells = cell_dofs.len() / dofs_per_cell;
+        if number_of_cells == 0 || !cell_coordinates.len().is_multiple_of(number_of_cells) {
+            return Err(Error::InvalidArgument(
+                "Cell coordinates length is not a multiple of the number of cells",
+            ));
+        }
+        Ok(CellBlock {
+            number_of_cells,
+            dofs_per_cell,
+            cell_dofs,
+            coordinates_per_cell: cell_coordinates.len() / number_of_cells,
+            cell_coordinates,
+            fields: HashMap::new(),
+        })
+    }
+
+    /// Attach a data field to the cells, values are split evenly between cells
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the name the operators will find the field under
+    /// * `values`: the values of the field for each cell
+    pub fn add_field(&mut self, name: &str, values: &'a [DataType]) -> Result<(), Error> {
+        if !values.len().is_multiple_of(self.number_of_cells) {
+            return Err(Error::InvalidArgument(
+                "Field length is not a multiple of the number of cells",
+            ));
+        }
+        self.fields.insert(name.to_string(), values);
+        Ok(())
+    }
+
+    /// Get the number of cells
+    pub fn get_number_of_cells(&self) -> usize {
+        self.number_of_cells
+    }
+
+    /// Get the number of degrees of freedom per cell
+    pub fn get_dofs_per_cell(&self) -> usize {
+        self.dofs_per_cell
+    }
+
+    /// Get the number of coordinate values per cell
+    pub fn get_coordinates_per_cell(&self) -> usize {
+        self.coordinates_per_cell
+    }
+
+    /// Get the global degrees of freedom of a cell
+    pub fn get_cell_dofs(&self, cell: usize) -> &'a [usize] {
+        &self.cell_dofs[cell * self.dofs_per_cell..(cell + 1) * self.dofs_per_cell]
+    }
+
+    /// Get the real coordinates of a cell
+    pub fn get_cell_coordinates(&self, cell: usize) -> &'a [CoordType] {
+        &self.cell_coordinates
+            [cell * self.coordinates_per_cell..(cell + 1) * self.coordinates_per_cell]
+    }
+
+    /// Get the global degrees of freedom of all the cells, cell after cell
+    pub fn get_connectivity(&self) -> &'a [usize] {
+        self.cell_dofs
+    }
+
+    /// Get the real coordinates of all the cells, cell after cell
+    pub fn get_coordinates(&self) -> &'a [CoordType] {
+        self.cell_coordinates
+    }
+
+    /// Get the sorted names of the data fields of the cells
+    pub fn get_field_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.fields.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Get the values of a data field for all the cells, None if the field is not attached
+    pub fn get_field(&self, name: &str) -> Option<&'a [DataType]> {
+        self.fields.get(name).copied()
+    }
+
+    /// Gather global degree of freedom values into a field of the cells, in the ordering of the
+    /// connectivity, for instance to provide the local state to `AutomaticTangent`
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the gathered values or an error if a cell dof is out of bounds
+    pub fn gather(&self, values: &[DataType]) -> Result<Vec<DataType>, Error>
+    where
+        DataType: Copy,
+    {
+        self.cell_dofs
+            .iter()
+            .map(|&dof| {
+                values.get(dof).copied().ok_or(Error::OutOfBounds {
+                    context: "Cell dof out of bounds",
+                    index: dof,
+                    bound: values.len(),
+                })
+            })
+            .collect()
+    }
+
+    /// Get the data fields of a cell indexed by name
+    pub fn get_cell_data(&self, cell: usize) -> HashMap<String, &'a [DataType]> {
+        self.fields
+            .iter()
+            .map(|(name, values)| {
+                let stride = values.len() / self.number_of_cells;
+                (name.clone(), &values[cell * stride..(cell + 1) * stride])
+            })
+            .collect()
+    }
+
+    /// Same as get_cell_data above but refilling a map, whose names are kept from one cell to
+    /// the next so that a loop over the cells does not allocate
+    pub fn get_cell_data_into(&self, cell: usize, data: &mut HashMap<String, &'a [DataType]>) {
+        data.retain(|name, _| self.fields.contains_key(name));
+        for (name, values) in self.fields.iter() {
+            let stride = values.len() / self.number_of_cells;
+            let values = &values[cell * stride..(cell + 1) * stride];
+            match data.get_mut(name) {
+                Some(slot) => *slot = values,
+                None => {
+                    data.insert(name.clone(), values);
+                }
+            }
+        }
+    }
+
+    /// Get the coordinates and data of the `LANES` cells starting at `first_cell` in SOA ordering
+    ///
+    /// Lanes past the last cell of the block repeat the last cell.
+    ///
+    /// # Returns
+    ///
+    /// * the coordinates with one entry per coordinate value and the data fields with one entry
+    ///   per data value
+    pub fn get_batch_data<const LANES: usize>(
+        &self,
+        first_cell: usize,
+    ) -> BatchData<CoordType, DataType, LANES>
+    where
+        CoordType: Copy,
+        DataType: Copy,
+    {
+        let mut batch = (Vec::new(), HashMap::new());
+        self.get_batch_data_into(first_cell, &mut batch);
+        batch
+    }
+
+    /// Same as get_batch_data above but overwriting the coordinates and the fields of a previous
+    /// batch, whose buffers keep their capacity from one batch to the next
+    pub fn get_batch_data_into<const LANES: usize>(
+        &self,
+        first_cell: usize,
+        batch: &mut BatchData<CoordType, DataType, LANES>,
+    ) where
+        CoordType: Copy,
+        DataType: Copy,
+    {
+        let cell = |lane: usize| (first_cell + lane).min(self.number_of_cells - 1);
+        let (coordinates, fields) = batch;
+        coordinates.clear();
+        coordinates.extend(
+            (0..self.coordinates_per_cell)
+                .map(|i| std::array::from_fn(|lane| self.get_cell_coordinates(cell(lane))[i])),
+        );
+        fields.retain(|name, _| self.fields.contains_key(name));
+        for (name, values) in self.fields.iter() {
+            let stride = values.len() / self.number_of_cells;
+            let soa =
+                (0..stride).map(|i| std::array::from_fn(|lane| values[cell(lane) * stride + i]));
+            match fields.get_mut(name) {
+                Some(slot) => {
+                    slot.clear();
+                    slot.extend(soa);
+                }
+                None => {
+                    fields.insert(name.clone(), soa.collect());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CellBlock;
+
+    #[test]
+    fn test_new() {
+        let dofs = [0, 1, 1, 2];
+        let coords = [0.0, 0.5, 0.5, 1.0];
+        let block = CellBlock::<f64, f64>::new(2, &dofs, &coords).unwrap();
+        assert_eq!(block.get_number_of_cells(), 2, "Incorrect number of cells");
+        assert_eq!(
+            block.get_coordinates_per_cell(),
+            2,
+            "Incorrect coordinates per cell"
+        );
+        assert_eq!(block.get_cell_dofs(1), &[1, 2], "Incorrect cell dofs");
+        assert_eq!(
+            block.get_cell_coordinates(1),
+            &[0.5, 1.0],
+            "Incorrect cell coordinates"
+        );
+        assert!(
+            CellBlock::<f64, f64>::new(3, &dofs, &coords).is_err(),
+            "Inconsistent dofs accepted"
+        );
+        assert!(
+            CellBlock::<f64, f64>::new(2, &dofs, &coords[..3]).is_err(),
+            "Inconsistent coordinates accepted"
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let dofs = [0, 1, 1, 2];
+        let coords = [0.0, 0.5, 0.5, 1.0];
+        let conductivity = [1.0, 2.0];
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        assert!(
+            block.add_field("wrong", &conductivity[..1]).is_err(),
+            "Inconsistent field accepted"
+        );
+        let mut data = block.get_cell_data(1);
+        assert_eq!(data["conductivity"], &[2.0], "Incorrect cell data");
+        data.insert("stale".to_string(), &conductivity[..1]);
+        block.get_cell_data_into(0, &mut data);
+        assert!(
+            data.len() == 1 && data["conductivity"] == [1.0],
+            "Incorrect refilled cell data"
+        );
+        assert_eq!(
+            block.get_field_names(),
+            vec!["conductivity"],
+            "Incorrect field names"
+        );
+        assert_eq!(
+            block.get_field("conductivity"),
+            Some(&conductivity[..]),
+            "Incorrect field"
+        );
+    }
+
+    #[test]
+    fn test_batch_data() {
+        let dofs = [0, 1, 1, 2, 2, 3];
+        let coords = [0.0, 0.5, 0.5, 1.0, 1.0, 1.5];
+        let conductivity = [1.0, 2.0, 3.0];
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let (coordinates, fields) = block.get_batch_data::<4>(1);
+        assert_eq!(
+            coordinates.len(),
+            2,
+            "Incorrect number of coordinate entries"
+        );
+        assert_eq!(
+            coordinates[0],
+            [0.5, 1.0, 1.0, 1.0],
+            "Incorrect first coordinates"
+        );
+        assert_eq!(
+            coordinates[1],
+            [1.0, 1.5, 1.5, 1.5],
+            "Incorrect second coordinates"
+        );
+        assert_eq!(
+            fields["conductivity"],
+            vec![[2.0, 3.0, 3.0, 3.0]],
+            "Incorrect batch data"
+        );
+        let mut batch = block.get_batch_data::<4>(1);
+        let capacity = batch.0.capacity();
+        batch.1.insert("stale".to_string(), Vec::new());
+        block.get_batch_data_into(0, &mut batch);
+        assert_eq!(
+            batch.0,
+            vec![[0.0, 0.5, 1.0, 1.0], [0.5, 1.0, 1.5, 1.5]],
+            "Incorrect refilled coordinates"
+        );
+        assert!(
+            batch.1.len() == 1 && batch.1["conductivity"] == vec![[1.0, 2.0, 3.0, 3.0]],
+            "Incorrect refilled batch data"
+        );
+        assert_eq!(batch.0.capacity(), capacity, "Batch reallocated");
+    }
+}
@@ -0,0 +1,231 @@
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Structure representing a single linear constraint `u_dof = sum_j w_j u_j + g`
+#[derive(Clone, Debug)]
+pub struct ConstraintLine<DataType> {
+    entries: Vec<(usize, DataType)>,
+    inhomogeneity: DataType,
+}
+
+impl<DataType: Scalar> ConstraintLine<DataType> {
+    /// Get the `(master dof, weight)` pairs of the constraint
+    pub fn get_entries(&self) -> &[(usize, DataType)] {
+        &self.entries
+    }
+
+    /// Get the constant part of the constraint
+    pub fn get_inhomogeneity(&self) -> DataType {
+        self.inhomogeneity
+    }
+}
+
+/// Set of linear constraints on the degrees of freedom
+///
+/// # Generics
+///
+/// * DataType: the type of unit the constraints are encoded with
+///
+/// # Explanation
+///
+/// Each constrained dof is expressed as an affine combination of unconstrained master dofs. This
+/// covers Dirichlet conditions (no masters), hanging nodes (interpolation weights of the coarse
+/// dofs) and periodicity (a single master of weight one). Masters of a constraint can not be
+/// constrained themselves, the set of the masters being kept to check it.
+#[derive(Clone, Debug, Default)]
+pub struct Constraints<DataType> {
+    lines: BTreeMap<usize, ConstraintLine<DataType>>,
+    masters: BTreeSet<usize>,
+}
+
+impl<DataType: Scalar> Constraints<DataType> {
+    /// Constructor of an empty set of constraints
+    pub fn new() -> Constraints<DataType> {
+        Constraints {
+            lines: BTreeMap::new(),
+            masters: BTreeSet::new(),
+        }
+    }
+
+    /// Constrain a dof to a fixed value
+    ///
+    /// # Arguments
+    ///
+    /// * `dof`: the constrained dof
+    /// * `value`: the value imposed on the dof
+    pub fn add_dirichlet(&mut self, dof: usize, value: DataType) -> Result<(), Error> {
+        self.add_line(dof, &[], value)
+    }
+
+    /// Constrain a dof to an affine combination of other dofs
+    ///
+    /// # Arguments
+    ///
+    /// * `dof`: the constrained dof
+    /// * `entries`: the `(master dof, weight)` pairs of the combination
+    /// * `inhomogeneity`: the constant part of the combination
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the dof is already constrained or couples to constrained dofs
+    pub fn add_line(
+        &mut self,
+        dof: usize,
+        entries: &[(usize, DataType)],
+        inhomogeneity: DataType,
+    ) -> Result<(), Error> {
+        if self.lines.contains_key(&dof) {
+            return Err(Error::InvalidArgument("Dof is already constrained"));
+        }
+        if entries
+            .iter()
+            .any(|(master, _)| *master == dof || self.lines.contains_key(master))
+        {
+            return Err(Error::InvalidArgument(
+                "Constraint masters can not be constrained",
+            ));
+        }
+        if self.masters.contains(&dof) {
+            return Err(Error::InvalidArgument(
+                "Dof is already the master of a constraint",
+            ));
+        }
+        self.masters
+            .extend(entries.iter().map(|&(master, _)| master));
+        self.lines.insert(
+            dof,
+            ConstraintLine {
+                entries: entries.to_vec(),
+                inhomogeneity,
+            },
+        );
+        Ok(())
+    }
+
+    /// Get the number of constrained dofs
+    pub fn get_number_of_constraints(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Whether a dof is constrained
+    pub fn is_constrained(&self, dof: usize) -> bool {
+        self.lines.contains_key(&dof)
+    }
+
+    /// Get the constraint of a dof if any
+    pub fn get_line(&self, dof: usize) -> Option<&ConstraintLine<DataType>> {
+        self.lines.get(&dof)
+    }
+
+    /// Get the `(master dof, weight)` pairs a dof expands into, itself if it is not constrained
+    ///
+    /// The pairs are borrowed from the constraint, so that expanding the dofs of each cell does
+    /// not allocate.
+    pub fn expand(&self, dof: usize) -> impl Iterator<Item = (usize, DataType)> + Clone + '_ {
+        let line = self.lines.get(&dof);
+        line.map_or(&[][..], |line| &line.entries[..])
+            .iter()
+            .copied()
+            .chain(line.is_none().then_some((dof, DataType::one())))
+    }
+
+    /// Condense a global vector (typically a right hand side) in place by moving the values of the
+    /// constrained dofs onto their masters
+    pub fn condense(&self, values: &mut [DataType]) {
+        for (&dof, line) in self.lines.iter() {
+            let value = values[dof];
+            for &(master, weight) in line.entries.iter() {
+                values[master] = values[master] + weight * value;
+            }
+            values[dof] = DataType::zero();
+        }
+    }
+
+    /// Set the values of the constrained dofs of a vector from the values of their masters
+    ///
+    /// This recovers the full solution from the solution of a condensed system.
+    pub fn distribute(&self, values: &mut [DataType]) {
+        for (&dof, line) in self.lines.iter() {
+            values[dof] = line
+                .entries
+                .iter()
+                .fold(line.inhomogeneity, |sum, &(master, weight)| {
+                    sum + weight * values[master]
+                });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Constraints;
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_add() {
+        let mut constraints = Constraints::<f64>::new();
+        constraints.add_dirichlet(0, 1.0).unwrap();
+        constraints.add_line(2, &[(1, 0.5), (3, 0.5)], 0.0).unwrap();
+        assert_eq!(
+            constraints.get_number_of_constraints(),
+            2,
+            "Incorrect number of constraints"
+        );
+        assert!(constraints.is_constrained(2), "Dof 2 should be constrained");
+        assert!(!constraints.is_constrained(1), "Dof 1 should be free");
+        assert_eq!(
+            constraints.expand(1).collect::<Vec<_>>(),
+            vec![(1, 1.0)],
+            "Incorrect free expansion"
+        );
+        assert!(
+            constraints.add_dirichlet(0, 2.0).is_err(),
+            "Constrained twice"
+        );
+        assert!(
+            constraints.add_line(4, &[(2, 1.0)], 0.0).is_err(),
+            "Constrained master accepted"
+        );
+        assert!(
+            constraints.add_dirichlet(3, 0.0).is_err(),
+            "Master constrained after the fact"
+        );
+    }
+
+    #[test]
+    fn test_distribute() {
+        let mut constraints = Constraints::<f64>::new();
+        constraints.add_dirichlet(0, 1.0).unwrap();
+        constraints
+            .add_line(2, &[(1, 0.5), (3, 0.5)], 0.25)
+            .unwrap();
+        let mut values = [0.0, 2.0, 0.0, 4.0];
+        constraints.distribute(&mut values);
+        assert!((values[0] - 1.0).abs() < TOL, "Incorrect dirichlet value");
+        assert!(
+            (values[2] - 3.25).abs() < TOL,
+            "Incorrect combination value"
+        );
+    }
+
+    #[test]
+    fn test_condense() {
+        let mut constraints = Constraints::<f64>::new();
+        constraints.add_dirichlet(0, 1.0).unwrap();
+        constraints.add_line(2, &[(1, 0.5), (3, 0.5)], 0.0).unwrap();
+        let mut values = [1.0, 1.0, 2.0, 1.0];
+        constraints.condense(&mut values);
+        assert!(values[0].abs() < TOL, "Incorrect dirichlet value");
+        assert!(
+            (values[1] - 2.0).abs() < TOL,
+            "Incorrect first master value"
+        );
+        assert!(values[2].abs() < TOL, "Incorrect constrained value");
+        assert!(
+            (values[3] - 2.0).abs() < TOL,
+            "Incorrect second master value"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::operator_trait::Operator;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::solver::solver_traits::LinearMap;
+use std::collections::HashMap;
+
+/// Global operator applied cell by cell without assembling a matrix
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the base space
+/// * DataType: the type of unit the data is encoded with
+/// * OperatorT: the local operator
+///
+/// # Explanation
+///
+/// Each product `y = A x` gathers the values of `x` on the dofs of every cell, multiplies them by
+/// the local matrix computed by the operator and scatters the result into `y`. Only the diagonal,
+/// computed once at construction for Jacobi preconditioning, is stored: memory stays linear in the
+/// number of dofs whatever the order of the elements. With constraints the operator is the
+/// condensed operator of `Assembler::assemble_constrained`: the product only couples
+/// unconstrained dofs and constrained dofs keep their local diagonal.
+pub struct MatrixFreeOperator<'a, CoordType, DataType, OperatorT> {
+    number_of_dofs: usize,
+    operator: &'a OperatorT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    constraints: Constraints<DataType>,
+    diagonal: Vec<DataType>,
+}
+
+impl<'a, CoordType, DataType, OperatorT> MatrixFreeOperator<'a, CoordType, DataType, OperatorT>
+where
+    CoordType: Scalar,
+    DataType: Scalar,
+    OperatorT: Operator<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_dofs`: the number of global dofs
+    /// * `operator`: the local operator
+    /// * `block`: the cells the operator is applied on
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the operator or an error if a local matrix does not match the
+    ///   dofs of its cell
+    pub fn new(
+        number_of_dofs: usize,
+        operator: &'a OperatorT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+    ) -> Result<MatrixFreeOperator<'a, CoordType, DataType, OperatorT>, Error> {
+        MatrixFreeOperator::new_constrained(number_of_dofs, operator, block, &Constraints::new())
+    }
+
+    /// Same as new above but for the operator condensed by a set of constraints
+    pub fn new_constrained(
+        number_of_dofs: usize,
+        operator: &'a OperatorT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        constraints: &Constraints<DataType>,
+    ) -> Result<MatrixFreeOperator<'a, CoordType, DataType, OperatorT>, Error> {
+        let diagonal = Assembler::new(number_of_dofs).assemble_constrained_diagonal(
+            operator,
+            block,
+            constraints,
+        )?;
+        Ok(MatrixFreeOperator {
+            number_of_dofs,
+            operator,
+            block,
+            constraints: constraints.clone(),
+            diagonal,
+        })
+    }
+
+    /// Get the constraints the operator is condensed by
+    pub fn get_constraints(&self) -> &Constraints<DataType> {
+        &self.constraints
+    }
+}
+
+impl<'a, CoordType, DataType, OperatorT> LinearMap<DataType>
+    for MatrixFreeOperator<'a, CoordType, DataType, OperatorT>
+where
+    CoordType: Scalar,
+    DataType: Scalar,
+    OperatorT: Operator<CoordType, DataType>,
+{
+    fn get_number_of_rows(&self) -> usize {
+        self.number_of_dofs
+    }
+
+    fn get_number_of_columns(&self) -> usize {
+        self.number_of_dofs
+    }
+
+    fn apply(&self, x: &[DataType], y: &mut [DataType]) {
+        y.iter_mut().for_each(|v| *v = DataType::zero());
+        let mut workspace = Workspace::new();
+        let mut local = Vec::new();
+        let mut local_x = Vec::new();
+        let mut data = HashMap::new();
+        for cell in 0..self.block.get_number_of_cells() {
+            let dofs = self.block.get_cell_dofs(cell);
+            let n = dofs.len();
+            self.block.get_cell_data_into(cell, &mut data);
+            self.operator.compute_into(
+                self.block.get_cell_coordinates(cell),
+                &data,
+                &mut workspace,
+                &mut local,
+            );
+            local_x.clear();
+            local_x.extend(dofs.iter().map(|&dof| {
+                self.constraints
+                    .expand(dof)
+                    .fold(DataType::zero(), |sum, (master, weight)| {
+                        sum + weight * x[master]
+                    })
+            }));
+            for (a, &row) in dofs.iter().enumerate() {
+                let value = (0..n).fold(DataType::zero(), |sum, b| {
+                    sum + local[a * n + b] * local_x[b]
+                });
+                for (master, weight) in self.constraints.expand(row) {
+                    y[master] = y[master] + weight * value;
+                }
+                if self.constraints.is_constrained(row) {
+                    y[row] = y[row] + local[a * n + a] * x[row];
+                }
+            }
+        }
+    }
+
+    fn get_diagonal(&self) -> Option<Vec<DataType>> {
+        Some(self.diagonal.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MatrixFreeOperator;
+    use crate::assembly::assembler::Assembler;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::assembly::constraints::Constraints;
+    use crate::solver::cg::ConjugateGradient;
+    use crate::solver::preconditioners::JacobiPreconditioner;
+    use crate::solver::solver_traits::{IterationControl, LinearMap};
+    use crate::test_utils::{poisson_solution, uniform_segments, Advection, Laplacian};
+
+    const TOL: f64 = 1e-10;
+
+    #[test]
+    fn test_matches_assembled() {
+        let (dofs, coords) = uniform_segments(8);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let mut constraints = Constraints::new();
+        constraints.add_dirichlet(0, 0.0).unwrap();
+        constraints.add_line(8, &[(4, 0.5), (5, 0.5)], 0.0).unwrap();
+        let (mat, _) = Assembler::new(9)
+            .assemble_constrained(&Laplacian, &block, &constraints)
+            .unwrap();
+        let operator =
+            MatrixFreeOperator::new_constrained(9, &Laplacian, &block, &constraints).unwrap();
+        let x: Vec<f64> = (0..9).map(|i| (i as f64).cos()).collect();
+        let mut y = vec![0.0; 9];
+        operator.apply(&x, &mut y);
+        for (v, e) in y.iter().zip(mat.apply(&x).iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect matrix free product");
+        }
+        for (v, e) in operator
+            .get_diagonal()
+            .unwrap()
+            .iter()
+            .zip(mat.get_diagonal().iter())
+        {
+            assert!((v - e).abs() < TOL, "Incorrect matrix free diagonal");
+        }
+        let general = MatrixFreeOperator::new(9, &Advection, &block).unwrap();
+        let mat = Assembler::new(9).assemble(&Advection, &block).unwrap();
+        general.apply(&x, &mut y);
+        for (v, e) in y.iter().zip(mat.apply(&x).iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect non symmetric product");
+        }
+    }
+
+    #[test]
+    fn test_matrix_free_solve() {
+        let (dofs, coords) = uniform_segments(16);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let mut constraints = Constraints::new();
+        constraints.add_dirichlet(0, 0.0).unwrap();
+        constraints.add_dirichlet(16, 0.0).unwrap();
+        let operator =
+            MatrixFreeOperator::new_constrained(17, &Laplacian, &block, &constraints).unwrap();
+        let preconditioner = JacobiPreconditioner::from_map(&operator).unwrap();
+        let mut rhs = vec![1.0 / 16.0; 17];
+        constraints.condense(&mut rhs);
+        let mut x = vec![0.0; 17];
+        let result = ConjugateGradient::new(IterationControl::new(1e-12, 0.0, 100)).solve(
+            &operator,
+            &preconditioner,
+            &rhs,
+            &mut x,
+        );
+        assert!(result.is_converged(), "Matrix free CG did not converge");
+        for (v, e) in x.iter().zip(poisson_solution(16).iter()) {
+            assert!((v - e).abs() < 1e-8, "Incorrect solution value");
+        }
+    }
+}
@@ -0,0 +1,14 @@
+/// Module describing the blocks of cells assembly iterates over
+pub mod cell_block;
+
+/// Module for the assembly of global matrices
+pub mod assembler;
+
+/// Module for the linear constraints condensed during assembly
+pub mod constraints;
+
+/// Module for the instrumentation of the assembly loops
+pub mod options;
+
+/// Module for the operators applied without assembling a matrix
+pub mod matrix_free;
@@ -0,0 +1,129 @@
+use crate::error::Error;
+use crate::timer::TimerReport;
+use std::time::Duration;
+
+/// The phases of an assembly
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssemblyPhase {
+    /// Construction of the sparsity pattern of the global matrix
+    Pattern,
+    /// Computation of the local matrices by the operator
+    Compute,
+    /// Scatter of the local matrices into the global matrix
+    Scatter,
+}
+
+impl AssemblyPhase {
+    /// Get the name of the phase in a timer report
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            AssemblyPhase::Pattern => "assembly/pattern",
+            AssemblyPhase::Compute => "assembly/compute",
+            AssemblyPhase::Scatter => "assembly/scatter",
+        }
+    }
+}
+
+/// Callback receiving the time spent in a phase
+type PhaseCallback<'a> = Box<dyn Fn(AssemblyPhase, Duration) + 'a>;
+
+/// Callback receiving the index of a failing cell and the reason of the failure
+type ErrorCallback<'a> = Box<dyn Fn(usize, &Error) + 'a>;
+
+/// Callback receiving the number of processed cells and the total number of cells
+type ProgressCallback<'a> = Box<dyn Fn(usize, usize) + 'a>;
+
+/// Optional instrumentation of the assembly loop
+///
+/// # Explanation
+///
+/// All the hooks are disabled by default and cost nothing when unset:
+///
+/// * the phase callback receives the wall time spent in each `AssemblyPhase`
+/// * the error callback receives the index of the cell whose local matrix could not be scattered
+///   and the reason, before the assembly returns the error
+/// * the progress callback receives the number of cells processed and the total number of cells,
+///   every `progress_interval` cells and once at the end of the loop
+pub struct AssemblyOptions<'a> {
+    phase_callback: Option<PhaseCallback<'a>>,
+    error_callback: Option<ErrorCallback<'a>>,
+    progress_callback: Option<ProgressCallback<'a>>,
+    progress_interval: usize,
+}
+
+impl<'a> Default for AssemblyOptions<'a> {
+    fn default() -> Self {
+        AssemblyOptions::new()
+    }
+}
+
+impl<'a> AssemblyOptions<'a> {
+    /// Constructor with all hooks disabled
+    pub fn new() -> AssemblyOptions<'a> {
+        AssemblyOptions {
+            phase_callback: None,
+            error_callback: None,
+            progress_callback: None,
+            progress_interval: 1,
+        }
+    }
+
+    /// Set the callback receiving the time spent in each phase
+    pub fn set_phase_callback(&mut self, callback: impl Fn(AssemblyPhase, Duration) + 'a) {
+        self.phase_callback = Some(Box::new(callback));
+    }
+
+    /// Record the time spent in each phase in a timer report, under the name of the phase
+    ///
+    /// This sets the phase callback, replacing a previously set one.
+    pub fn set_timer_report(&mut self, report: &'a TimerReport) {
+        self.set_phase_callback(move |phase, duration| report.add(phase.get_name(), duration));
+    }
+
+    /// Set the callback receiving the cell index and the reason of a failure
+    pub fn set_error_callback(&mut self, callback: impl Fn(usize, &Error) + 'a) {
+        self.error_callback = Some(Box::new(callback));
+    }
+
+    /// Set the callback receiving the progress of the assembly loop
+    ///
+    /// # Arguments
+    ///
+    /// * `interval`: the number of cells between two calls (at least one)
+    /// * `callback`: the callback receiving the number of processed cells and the number of cells
+    pub fn set_progress_callback(&mut self, interval: usize, callback: impl Fn(usize, usize) + 'a) {
+        self.progress_interval = interval.max(1);
+        self.progress_callback = Some(Box::new(callback));
+    }
+
+    /// Whether phase timings should be measured
+    pub fn is_timing(&self) -> bool {
+        self.phase_callback.is_some()
+    }
+
+    /// Get the number of cells between two progress reports
+    pub fn get_progress_interval(&self) -> usize {
+        self.progress_interval
+    }
+
+    /// Report the time spent in a phase
+    pub fn report_phase(&self, phase: AssemblyPhase, duration: Duration) {
+        if let Some(callback) = &self.phase_callback {
+            callback(phase, duration);
+        }
+    }
+
+    /// Report a failure on a cell
+    pub fn report_error(&self, cell: usize, error: &Error) {
+        if let Some(callback) = &self.error_callback {
+            callback(cell, error);
+        }
+    }
+
+    /// Report the progress of the assembly loop
+    pub fn report_progress(&self, processed_cells: usize, number_of_cells: usize) {
+        if let Some(callback) = &self.progress_callback {
+            callback(processed_cells, number_of_cells);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::element::lagrange::{CellType, LagrangeElement};
+use crate::error::Error;
+
+/// Highest polynomial order of the elements, the equispaced nodes of higher orders giving ill
+/// conditioned bases
+pub const MAX_ORDER: usize = 10;
+
+/// Builder of the Lagrange elements on tensor product cells
+///
+/// # Explanation
+///
+/// The order defaults to one and the quadrature degree to twice the order, which integrates the
+/// mass matrices of affinely mapped cells exactly. The degree is rounded up to the odd degree of
+/// the Gauss-Legendre rule with `degree / 2 + 1` points per direction. A lower degree would leave
+/// spurious zero energy modes in the stiffness matrices and is rejected when building, along with
+/// the orders out of `1..=MAX_ORDER`.
+#[derive(Clone, Copy, Debug)]
+pub struct ElementBuilder {
+    cell_type: CellType,
+    order: usize,
+    quadrature_degree: Option<usize>,
+}
+
+impl ElementBuilder {
+    /// Constructor of a builder of linear elements
+    pub fn new(cell_type: CellType) -> ElementBuilder {
+        ElementBuilder {
+            cell_type,
+            order: 1,
+            quadrature_degree: None,
+        }
+    }
+
+    /// Set the polynomial order in each direction
+    pub fn order(mut self, order: usize) -> ElementBuilder {
+        self.order = order;
+        self
+    }
+
+    /// Set the polynomial degree in each direction the quadrature should integrate exactly
+    pub fn quadrature_degree(mut self, degree: usize) -> ElementBuilder {
+        self.quadrature_degree = Some(degree);
+        self
+    }
+
+    /// Build the element
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the element or an error if the order or the quadrature degree
+    ///   are not admissible
+    pub fn build(&self) -> Result<LagrangeElement, Error> {
+        if self.order == 0 || self.order > MAX_ORDER {
+            return Err(Error::InvalidArgument(
+                "Element order should be between one and MAX_ORDER",
+            ));
+        }
+        let degree = self.quadrature_degree.unwrap_or(2 * self.order);
+        if degree < 2 * self.order {
+            return Err(Error::InvalidArgument(
+                "Quadrature degree should be at least twice the element order",
+            ));
+        }
+        Ok(LagrangeElement::new(
+            self.cell_type,
+            self.order,
+            degree / 2 + 1,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ElementBuilder;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+    use crate::element::lagrange::CellType;
+    use crate::models::poisson::PoissonProblem;
+    use crate::post::boundary::FacetGroup;
+
+    const TOL: f64 = 1e-10;
+
+    #[test]
+    fn test_build() {
+        let element = ElementBuilder::new(CellType::Hex)
+            .order(4)
+            .quadrature_degree(9)
+            .build()
+            .unwrap();
+        assert_eq!(
+            element.get_shape_basis().get_number_of_bases(),
+            125,
+            "Incorrect number of bases"
+        );
+        assert_eq!(
+            element.get_integrator().get_number_of_points(),
+            125,
+            "Incorrect number of points"
+        );
+        let element = ElementBuilder::new(CellType::Quad).build().unwrap();
+        assert_eq!(
+            (
+                element.get_order(),
+                element.get_integrator().get_number_of_points()
+            ),
+            (1, 4),
+            "Incorrect defaults"
+        );
+        assert!(
+            ElementBuilder::new(CellType::Line)
+                .order(0)
+                .build()
+                .is_err(),
+            "Null order accepted"
+        );
+        assert!(
+            ElementBuilder::new(CellType::Quad)
+                .order(2)
+                .quadrature_degree(3)
+                .build()
+                .is_err(),
+            "Underintegrated element accepted"
+        );
+    }
+
+    #[test]
+    fn test_quadratic_poisson() {
+        let element = ElementBuilder::new(CellType::Quad)
+            .order(2)
+            .build()
+            .unwrap();
+        let facets = element.get_reference_facets().unwrap();
+        let (dofs, coords) = element.create_uniform_mesh(2);
+        let block = CellBlock::<f64, f64>::new(9, &dofs, &coords).unwrap();
+        let exact = |x: &[f64]| x[0] * x[0] + x[1];
+        let mut problem = PoissonProblem::new(&element, &block, &facets).unwrap();
+        problem.set_source(|_| -2.0);
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("boundary", &facets, &block, |_| true),
+            exact,
+        );
+        let solution = problem.solve().unwrap();
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            assert!(
+                (solution.get_coefficients()[dof] - exact(x)).abs() < TOL,
+                "Incorrect solution"
+            );
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::algebra::scalar::Scalar;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use num::complex::Complex;
+use num::Num;
+use std::marker::PhantomData;
+
+/// Integration rule of a real rule with its weights lifted to complex values
+pub struct ComplexIntegrationRule<'a, DataType, RuleT> {
+    rule: &'a RuleT,
+    weights: Vec<Complex<DataType>>,
+}
+
+impl<CoordType, DataType, RuleT> IntegrationRule<CoordType, Complex<DataType>>
+    for ComplexIntegrationRule<'_, DataType, RuleT>
+where
+    DataType: Scalar + Num,
+    RuleT: IntegrationRule<CoordType, DataType>,
+{
+    fn get_dimension(&self) -> usize {
+        self.rule.get_dimension()
+    }
+
+    fn get_weights(&self) -> &[Complex<DataType>] {
+        &self.weights
+    }
+
+    fn get_points(&self) -> &[CoordType] {
+        self.rule.get_points()
+    }
+
+    fn get_number_of_points(&self) -> usize {
+        self.rule.get_number_of_points()
+    }
+}
+
+/// Shape basis of a real basis with its values lifted to complex values
+pub struct ComplexShapeBasis<'a, DataType, BasisT> {
+    basis: &'a BasisT,
+    data: PhantomData<DataType>,
+}
+
+impl<CoordType, DataType, BasisT> ShapeBasis<CoordType, Complex<DataType>>
+    for ComplexShapeBasis<'_, DataType, BasisT>
+where
+    DataType: Scalar + Num,
+    BasisT: ShapeBasis<CoordType, DataType>,
+{
+    fn get_dimension(&self) -> usize {
+        self.basis.get_dimension()
+    }
+
+    fn get_shape_cardinality(&self) -> usize {
+        self.basis.get_shape_cardinality()
+    }
+
+    fn get_derivative_cardinality(&self) -> usize {
+        self.basis.get_derivative_cardinality()
+    }
+
+    fn get_number_of_bases(&self) -> usize {
+        self.basis.get_number_of_bases()
+    }
+
+    fn interpolate_basis(&self, coord: &[CoordType]) -> Vec<Complex<DataType>> {
+        lift(&self.basis.interpolate_basis(coord))
+    }
+
+    fn interpolate_basis_derivative(&self, coord: &[CoordType]) -> Vec<Complex<DataType>> {
+        lift(&self.basis.interpolate_basis_derivative(coord))
+    }
+}
+
+/// Element describing complex valued fields with the shape functions of a real element
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the element space
+/// * DataType: the type of the real and imaginary parts of the fields
+/// * ElementT: the real element
+///
+/// # Explanation
+///
+/// The shape functions of the finite element method are real, only the coefficients of the fields
+/// are complex in frequency domain problems. This element lifts the values of a real element to
+/// complex values with a zero imaginary part, so that operators and assemblies generic over the
+/// DataType may work with `Complex<DataType>` while the geometry of the cells is computed with the
+/// real element, available through get_element.
+pub struct ComplexElement<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar,
+    ElementT: Element<CoordType, DataType>,
+{
+    element: &'a ElementT,
+    integrator: ComplexIntegrationRule<'a, DataType, ElementT::IntegratorT>,
+    basis: ComplexShapeBasis<'a, DataType, ElementT::ShapeBasisT>,
+    shapes: Vec<Complex<DataType>>,
+    shape_derivatives: Vec<Complex<DataType>>,
+}
+
+impl<'a, CoordType, DataType, ElementT> ComplexElement<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Num,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the real element
+    pub fn new(element: &'a ElementT) -> ComplexElement<'a, CoordType, DataType, ElementT> {
+        ComplexElement {
+            element,
+            integrator: ComplexIntegrationRule {
+                rule: element.get_integrator(),
+                weights: lift(element.get_integrator().get_weights()),
+            },
+            basis: ComplexShapeBasis {
+                basis: element.get_shape_basis(),
+                data: PhantomData,
+            },
+            shapes: lift(element.get_shapes_for_integration()),
+            shape_derivatives: lift(element.get_shape_derivatives_for_integration()),
+        }
+    }
+
+    /// Get the real element
+    pub fn get_element(&self) -> &'a ElementT {
+        self.element
+    }
+}
+
+impl<'a, CoordType, DataType, ElementT> Element<CoordType, Complex<DataType>>
+    for ComplexElement<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Num,
+    ElementT: Element<CoordType, DataType>,
+{
+    type GeometryT = ElementT::GeometryT;
+    type IntegratorT = ComplexIntegrationRule<'a, DataType, ElementT::IntegratorT>;
+    type ShapeBasisT = ComplexShapeBasis<'a, DataType, ElementT::ShapeBasisT>;
+
+    fn get_geometry(&self) -> &Self::GeometryT {
+        self.element.get_geometry()
+    }
+
+    fn get_integrator(&self) -> &Self::IntegratorT {
+        &self.integrator
+    }
+
+    fn get_shape_basis(&self) -> &Self::ShapeBasisT {
+        &self.basis
+    }
+
+    fn get_shapes_for_integration(&self) -> &[Complex<DataType>] {
+        &self.shapes
+    }
+
+    fn get_shape_derivatives_for_integration(&self) -> &[Complex<DataType>] {
+        &self.shape_derivatives
+    }
+
+    fn get_geometry_derivatives_for_integration(
+        &self,
+        coords: &[CoordType],
+    ) -> Vec<Complex<DataType>> {
+        lift(
+            &self
+                .element
+                .get_geometry_derivatives_for_integration(coords),
+        )
+    }
+}
+
+/// Lift real values to complex values with a zero imaginary part
+fn lift<DataType: Copy + Num>(values: &[DataType]) -> Vec<Complex<DataType>> {
+    values
+        .iter()
+        .map(|&v| Complex::new(v, DataType::zero()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ComplexElement;
+    use crate::element::element_traits::{Element, ShapeBasis};
+    use crate::test_utils::BilinearQuadrilateralElement;
+    use num::complex::Complex;
+
+    const TOL: f64 = 1e-14;
+
+    #[test]
+    fn test_lift() {
+        let element = BilinearQuadrilateralElement::new();
+        let complex = ComplexElement::new(&element);
+        for (z, &x) in complex
+            .get_shapes_for_integration()
+            .iter()
+            .zip(element.get_shapes_for_integration())
+        {
+            assert!((z - Complex::new(x, 0.0)).norm() < TOL, "Incorrect shapes");
+        }
+        let basis = complex.get_shape_basis();
+        for (z, x) in basis.interpolate_basis_derivative(&[0.3, -0.2]).iter().zip(
+            element
+                .get_shape_basis()
+                .interpolate_basis_derivative(&[0.3, -0.2]),
+        ) {
+            assert!(
+                (z - Complex::new(x, 0.0)).norm() < TOL,
+                "Incorrect shape derivatives"
+            );
+        }
+        let coords = [0.0, 0.0, 2.0, 0.0, 2.0, 1.0, 0.0, 1.0];
+        assert_eq!(
+            complex.get_geometry_derivatives_for_integration(&coords)[0],
+            Complex::new(1.0, 0.0),
+            "Incorrect geometry derivatives"
+        );
+    }
+}
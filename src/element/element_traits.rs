@@ -1,9 +1,7 @@
+use crate::algebra::scalar::Scalar;
+use crate::element::workspace::Workspace;
 use crate::geometry::geometry_traits::Geometry;
 
-use ndarray::Array;
-use ndarray::ArrayView;
-use ndarray::LinalgScalar;
-
 /// Provides weights and points for discrete integration operations
 ///
 /// # Generics
@@ -17,7 +15,7 @@ use ndarray::LinalgScalar;
 /// of the integrand at specific points in the integration space. The combination of weights and
 /// points is called a quadrature or cubature rule. An object implementing this trait should
 /// implement something like a discrete cubature rule.
-pub trait IntegrationRule<CoordType, DataType: LinalgScalar> {
+pub trait IntegrationRule<CoordType, DataType: Scalar> {
     /// Get the underlying dimension of the point space
     fn get_dimension(&self) -> usize;
 
@@ -32,10 +30,12 @@ pub trait IntegrationRule<CoordType, DataType: LinalgScalar> {
 
     /// Integrate a range of values
     fn integrate(&self, values: &[DataType]) -> DataType {
-        let values_map = ArrayView::from_shape(self.get_number_of_points(), values).unwrap();
-        let weights_map =
-            ArrayView::from_shape(self.get_number_of_points(), self.get_weights()).unwrap();
-        weights_map.dot(&values_map)
+        self.get_weights()
+            .iter()
+            .zip(values)
+            .fold(DataType::zero(), |sum, (&weight, &value)| {
+                sum + weight * value
+            })
     }
 }
 
@@ -52,7 +52,7 @@ pub trait IntegrationRule<CoordType, DataType: LinalgScalar> {
 /// functions called shape functions. Objects implementing this trait should provide a distinct set
 /// of shape functions through the ability to interpolate there values at a given coordinate inside
 /// the element.
-pub trait ShapeBasis<CoordType, DataType: LinalgScalar> {
+pub trait ShapeBasis<CoordType, DataType: Scalar> {
     /// Get the underlying dimension of the space the shapes are defined on
     fn get_dimension(&self) -> usize;
 
@@ -75,30 +75,62 @@ pub trait ShapeBasis<CoordType, DataType: LinalgScalar> {
     /// Interpolate the basis functions' derivatives at a given coordinate
     fn interpolate_basis_derivative(&self, coord: &[CoordType]) -> Vec<DataType>;
 
+    /// Same as interpolate_basis above but writing the values into out, of length
+    /// `number_of_bases * shape_cardinality`
+    fn interpolate_basis_into(&self, coord: &[CoordType], out: &mut [DataType]) {
+        out.copy_from_slice(&self.interpolate_basis(coord));
+    }
+
+    /// Same as interpolate_basis_derivative above but writing the values into out, of length
+    /// `number_of_bases * derivative_cardinality`
+    fn interpolate_basis_derivative_into(&self, coord: &[CoordType], out: &mut [DataType]) {
+        out.copy_from_slice(&self.interpolate_basis_derivative(coord));
+    }
+
     /// Interpolate the value of the function defined weighting each of basis function using
     /// the values argument at the point in the element defined by coord
     fn interpolate(&self, coord: &[CoordType], values: &[DataType]) -> Vec<DataType> {
-        let shapes = Array::from_shape_vec(
-            (self.get_number_of_bases(), self.get_shape_cardinality()),
-            self.interpolate_basis(coord),
-        )
-        .unwrap();
-        let val_view = ArrayView::from(values);
-        shapes.dot(&val_view).to_vec()
+        let mut out = vec![DataType::zero(); self.get_shape_cardinality()];
+        self.interpolate_into(coord, values, &mut Workspace::new(), &mut out);
+        out
     }
 
     ///Same as interpolate above but for the derivative of the function
     fn interpolate_derivative(&self, coord: &[CoordType], values: &[DataType]) -> Vec<DataType> {
-        let shape_derives = Array::from_shape_vec(
-            (
-                self.get_number_of_bases(),
-                self.get_derivative_cardinality(),
-            ),
-            self.interpolate_basis_derivative(coord),
-        )
-        .unwrap();
-        let values_view = ArrayView::from(values);
-        values_view.dot(&shape_derives).to_vec()
+        let mut out = vec![DataType::zero(); self.get_derivative_cardinality()];
+        self.interpolate_derivative_into(coord, values, &mut Workspace::new(), &mut out);
+        out
+    }
+
+    /// Same as interpolate above but writing the value into out, of length `shape_cardinality`,
+    /// and holding the values of the basis functions in the workspace
+    fn interpolate_into(
+        &self,
+        coord: &[CoordType],
+        values: &[DataType],
+        workspace: &mut Workspace<DataType>,
+        out: &mut [DataType],
+    ) {
+        let cardinality = self.get_shape_cardinality();
+        let [shapes] = workspace.get_buffers([self.get_number_of_bases() * cardinality]);
+        self.interpolate_basis_into(coord, shapes);
+        weight_bases(values, shapes, out);
+    }
+
+    /// Same as interpolate_derivative above but writing the derivative into out, of length
+    /// `derivative_cardinality`, and holding the derivatives of the basis functions in the
+    /// workspace
+    fn interpolate_derivative_into(
+        &self,
+        coord: &[CoordType],
+        values: &[DataType],
+        workspace: &mut Workspace<DataType>,
+        out: &mut [DataType],
+    ) {
+        let cardinality = self.get_derivative_cardinality();
+        let [shape_derives] = workspace.get_buffers([self.get_number_of_bases() * cardinality]);
+        self.interpolate_basis_derivative_into(coord, shape_derives);
+        weight_bases(values, shape_derives, out);
     }
 }
 
@@ -120,7 +152,7 @@ pub trait ShapeBasis<CoordType, DataType: LinalgScalar> {
 /// integration rule. The geometry of the element is implicit in the shape basis and integration
 /// rule. An object implementing this trait should provide access to an interpolator and integrator
 /// as well as some precomputed values of the shape functions on the integration points.
-pub trait Element<CoordType: LinalgScalar, DataType: LinalgScalar> {
+pub trait Element<CoordType: Scalar, DataType: Scalar> {
     type GeometryT: Geometry<CoordType>;
     type IntegratorT: IntegrationRule<CoordType, DataType>;
     type ShapeBasisT: ShapeBasis<CoordType, DataType>;
@@ -149,14 +181,94 @@ pub trait Element<CoordType: LinalgScalar, DataType: LinalgScalar> {
     /// `(number_integration_points, number_dimensions, derivative_order)`
     fn get_geometry_derivatives_for_integration(&self, coords: &[CoordType]) -> Vec<DataType>;
 
+    /// Same as get_geometry_derivatives_for_integration above but writing the jacobian matrices
+    /// into out, of length `number_integration_points * number_dimensions * derivative_order`
+    fn get_geometry_derivatives_for_integration_into(
+        &self,
+        coords: &[CoordType],
+        out: &mut [DataType],
+    ) {
+        out.copy_from_slice(&self.get_geometry_derivatives_for_integration(coords));
+    }
+
     /// Integrate a field inside the element with shape weights equal to values
     fn integrate(&self, values: &[DataType]) -> DataType {
+        self.integrate_with(values, &mut Workspace::new())
+    }
+
+    /// Same as integrate above but holding the values of the field at the integration points in
+    /// the workspace
+    fn integrate_with(&self, values: &[DataType], workspace: &mut Workspace<DataType>) -> DataType {
         let nips = self.get_integrator().get_number_of_points();
         let nbases = self.get_shape_basis().get_number_of_bases();
-        let shapes =
-            ArrayView::from_shape((nips, nbases), self.get_shapes_for_integration()).unwrap();
-        let ip_values = shapes.dot(&ArrayView::from_shape(nbases, values).unwrap());
-        self.get_integrator()
-            .integrate(ip_values.as_slice().unwrap())
+        let [ip_values] = workspace.get_buffers([nips]);
+        for (ip_value, shapes) in ip_values
+            .iter_mut()
+            .zip(self.get_shapes_for_integration().chunks(nbases))
+        {
+            *ip_value = shapes
+                .iter()
+                .zip(values)
+                .fold(DataType::zero(), |sum, (&shape, &value)| {
+                    sum + shape * value
+                });
+        }
+        self.get_integrator().integrate(ip_values)
+    }
+}
+
+/// Weight the rows of a `(number_of_bases, cardinality)` array of basis values by the values of
+/// each basis function and sum them into out
+fn weight_bases<DataType: Scalar>(values: &[DataType], bases: &[DataType], out: &mut [DataType]) {
+    out.iter_mut().for_each(|v| *v = DataType::zero());
+    for (&value, basis) in values.iter().zip(bases.chunks(out.len().max(1))) {
+        for (o, &b) in out.iter_mut().zip(basis) {
+            *o = *o + value * b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Element, ShapeBasis};
+    use crate::element::workspace::Workspace;
+    use crate::test_utils::BilinearQuadrilateralElement;
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_into_variants() {
+        let element = BilinearQuadrilateralElement::new();
+        let basis = element.get_shape_basis();
+        // nodal values of 1 + 2x + 3y
+        let values = [-4.0, 0.0, 6.0, 2.0];
+        let coord = [0.5, -0.25];
+        let mut workspace = Workspace::new();
+        let mut value = [0.0];
+        basis.interpolate_into(&coord, &values, &mut workspace, &mut value);
+        assert!(
+            (value[0] - 1.25).abs() < TOL,
+            "Incorrect interpolated value"
+        );
+        assert!(
+            (basis.interpolate(&coord, &values)[0] - value[0]).abs() < TOL,
+            "Inconsistent allocating interpolation"
+        );
+        let mut derivative = [0.0; 2];
+        basis.interpolate_derivative_into(&coord, &values, &mut workspace, &mut derivative);
+        assert!(
+            (derivative[0] - 2.0).abs() < TOL && (derivative[1] - 3.0).abs() < TOL,
+            "Incorrect interpolated derivative"
+        );
+        let integral = element.integrate_with(&values, &mut workspace);
+        assert!((integral - 4.0).abs() < TOL, "Incorrect integral");
+        assert!(
+            (element.integrate(&values) - integral).abs() < TOL,
+            "Inconsistent allocating integration"
+        );
+        let capacity = workspace.get_capacity();
+        basis.interpolate_derivative_into(&coord, &values, &mut workspace, &mut derivative);
+        element.integrate_with(&values, &mut workspace);
+        assert_eq!(workspace.get_capacity(), capacity, "Workspace reallocated");
     }
 }
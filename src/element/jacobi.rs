@@ -31,7 +31,7 @@ impl Jacobi {
     /// # Returns
     ///
     /// * An option either holding the structure or a None if the arguments passed to it were not
-    /// acceptable
+    ///   acceptable
     pub fn new(degree: usize, alpha: i32, beta: i32) -> Option<Jacobi> {
         if alpha < -1 || beta < -1 {
             return None;
@@ -45,12 +45,12 @@ impl Jacobi {
                 degree.to_bigint().unwrap() - k.to_bigint().unwrap(),
             )
         };
-        let coeffs = (0..=degree).into_iter().map(coeff).collect();
+        let coeffs = (0..=degree).map(coeff).collect();
         Some(Jacobi {
-            degree: degree,
-            alpha: alpha,
-            beta: beta,
-            coeffs: coeffs,
+            degree,
+            alpha,
+            beta,
+            coeffs,
             normalizer: 1.0 / (2_i32.pow(degree.try_into().unwrap()) as f64),
         })
     }
@@ -84,7 +84,6 @@ impl Jacobi {
             (x - 1.0).powf((self.degree - deg) as f64) * (x + 1.0).powf(deg as f64)
         };
         (0..=self.degree)
-            .into_iter()
             .map(monome)
             .zip(self.coeffs.iter())
             .map(|(m, c)| m * (c.to_f64().unwrap()))
@@ -0,0 +1,567 @@
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::error::Error;
+use crate::geometry::geometry_traits::Geometry;
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::jacobian_from_coordinates;
+use num::integer::binomial;
+
+/// Reference cells of the tensor product elements, `[-1, 1]` to the power of their dimension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellType {
+    /// The reference segment
+    Line,
+    /// The reference square
+    Quad,
+    /// The reference cube
+    Hex,
+}
+
+impl Geometry<f64> for CellType {
+    fn get_dimension(&self) -> usize {
+        match self {
+            CellType::Line => 1,
+            CellType::Quad => 2,
+            CellType::Hex => 3,
+        }
+    }
+
+    fn get_number_of_elements(&self, dimension: usize) -> usize {
+        let cell_dimension = self.get_dimension();
+        if dimension > cell_dimension {
+            return 0;
+        }
+        binomial(cell_dimension, dimension) << (cell_dimension - dimension)
+    }
+
+    fn get_coordinates(&self) {}
+
+    fn get_connectivity(&self, _target_dimension: usize, _represented_dimension: usize) {}
+}
+
+/// Tensor product of Gauss-Legendre rules on the reference cell of a dimension
+///
+/// # Explanation
+///
+/// A rule of `n` points per direction integrates exactly the polynomials of degree `2 n - 1` in
+/// each direction. The points are ordered lexicographically, the first coordinate varying the
+/// fastest.
+pub struct GaussLegendre {
+    dimension: usize,
+    points: Vec<f64>,
+    weights: Vec<f64>,
+}
+
+impl GaussLegendre {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `dimension`: the dimension of the reference cell
+    /// * `points_per_direction`: the number of points of the one dimensional rule
+    pub fn new(dimension: usize, points_per_direction: usize) -> GaussLegendre {
+        let (abscissae, weights) = compute_gauss_legendre(points_per_direction);
+        let mut rule = GaussLegendre {
+            dimension,
+            points: Vec::new(),
+            weights: Vec::new(),
+        };
+        for index in 0..points_per_direction.pow(dimension as u32) {
+            let digits = get_digits(index, points_per_direction, dimension);
+            rule.points.extend(digits.iter().map(|&i| abscissae[i]));
+            rule.weights
+                .push(digits.iter().map(|&i| weights[i]).product::<f64>());
+        }
+        rule
+    }
+}
+
+impl IntegrationRule<f64, f64> for GaussLegendre {
+    fn get_dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn get_weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    fn get_points(&self) -> &[f64] {
+        &self.points
+    }
+
+    fn get_number_of_points(&self) -> usize {
+        self.weights.len()
+    }
+}
+
+/// Lagrange basis of an order on a reference cell, with equispaced nodes
+///
+/// # Explanation
+///
+/// The shape functions are the tensor products of the one dimensional Lagrange polynomials of the
+/// `order + 1` equispaced nodes of `[-1, 1]`. The nodes are ordered lexicographically, the first
+/// coordinate varying the fastest, so that the corners of the bilinear quadrilateral come as
+/// `(-1, -1), (1, -1), (-1, 1), (1, 1)`.
+pub struct LagrangeBasis {
+    cell_type: CellType,
+    order: usize,
+    abscissae: Vec<f64>,
+    nodes: Vec<f64>,
+}
+
+impl LagrangeBasis {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `cell_type`: the reference cell
+    /// * `order`: the polynomial order in each direction, at least one
+    pub fn new(cell_type: CellType, order: usize) -> LagrangeBasis {
+        let abscissae: Vec<f64> = (0..=order)
+            .map(|i| -1.0 + 2.0 * i as f64 / order as f64)
+            .collect();
+        let dimension = cell_type.get_dimension();
+        let nodes = (0..(order + 1).pow(dimension as u32))
+            .flat_map(|node| {
+                get_digits(node, order + 1, dimension)
+                    .into_iter()
+                    .map(|i| abscissae[i])
+                    .collect::<Vec<f64>>()
+            })
+            .collect();
+        LagrangeBasis {
+            cell_type,
+            order,
+            abscissae,
+            nodes,
+        }
+    }
+
+    /// Get the reference cell
+    pub fn get_cell_type(&self) -> CellType {
+        self.cell_type
+    }
+
+    /// Get the polynomial order in each direction
+    pub fn get_order(&self) -> usize {
+        self.order
+    }
+
+    /// Get the coordinates of the nodes on the reference cell in AOS ordering
+    pub fn get_reference_nodes(&self) -> &[f64] {
+        &self.nodes
+    }
+
+    /// Evaluate the one dimensional Lagrange polynomials and their derivatives at a coordinate
+    fn interpolate_line(&self, x: f64) -> (Vec<f64>, Vec<f64>) {
+        let nodes = &self.abscissae;
+        let values = (0..nodes.len())
+            .map(|i| {
+                (0..nodes.len())
+                    .filter(|&j| j != i)
+                    .map(|j| (x - nodes[j]) / (nodes[i] - nodes[j]))
+                    .product()
+            })
+            .collect();
+        let derivatives = (0..nodes.len())
+            .map(|i| {
+                (0..nodes.len())
+                    .filter(|&k| k != i)
+                    .map(|k| {
+                        (0..nodes.len())
+                            .filter(|&j| j != i && j != k)
+                            .map(|j| (x - nodes[j]) / (nodes[i] - nodes[j]))
+                            .product::<f64>()
+                            / (nodes[i] - nodes[k])
+                    })
+                    .sum()
+            })
+            .collect();
+        (values, derivatives)
+    }
+}
+
+impl ShapeBasis<f64, f64> for LagrangeBasis {
+    fn get_dimension(&self) -> usize {
+        self.cell_type.get_dimension()
+    }
+
+    fn get_number_of_bases(&self) -> usize {
+        (self.order + 1).pow(self.get_dimension() as u32)
+    }
+
+    fn interpolate_basis(&self, coord: &[f64]) -> Vec<f64> {
+        let lines: Vec<Vec<f64>> = coord.iter().map(|&x| self.interpolate_line(x).0).collect();
+        (0..self.get_number_of_bases())
+            .map(|node| {
+                get_digits(node, self.order + 1, self.get_dimension())
+                    .iter()
+                    .zip(&lines)
+                    .map(|(&i, line)| line[i])
+                    .product()
+            })
+            .collect()
+    }
+
+    fn interpolate_basis_derivative(&self, coord: &[f64]) -> Vec<f64> {
+        let dimension = self.get_dimension();
+        let lines: Vec<(Vec<f64>, Vec<f64>)> =
+            coord.iter().map(|&x| self.interpolate_line(x)).collect();
+        (0..self.get_number_of_bases())
+            .flat_map(|node| {
+                let digits = get_digits(node, self.order + 1, dimension);
+                (0..dimension)
+                    .map(|direction| {
+                        digits
+                            .iter()
+                            .zip(&lines)
+                            .enumerate()
+                            .map(|(k, (&i, (values, derivatives)))| {
+                                if k == direction {
+                                    derivatives[i]
+                                } else {
+                                    values[i]
+                                }
+                            })
+                            .product()
+                    })
+                    .collect::<Vec<f64>>()
+            })
+            .collect()
+    }
+}
+
+/// Lagrange element of an order on a tensor product cell integrated with a Gauss-Legendre rule
+///
+/// # Explanation
+///
+/// The elements are configured and validated by an `ElementBuilder`. The element provides the
+/// facets of its reference cell and uniform meshes of the unit cell in the ordering of its nodes,
+/// so that it may be handed to the ready-made problems without further wiring.
+pub struct LagrangeElement {
+    cell_type: CellType,
+    integrator: GaussLegendre,
+    basis: LagrangeBasis,
+    shapes: Vec<f64>,
+    shape_derivatives: Vec<f64>,
+}
+
+impl LagrangeElement {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `cell_type`: the reference cell
+    /// * `order`: the polynomial order in each direction, at least one
+    /// * `points_per_direction`: the number of points of the one dimensional Gauss-Legendre rule
+    pub(crate) fn new(
+        cell_type: CellType,
+        order: usize,
+        points_per_direction: usize,
+    ) -> LagrangeElement {
+        let dimension = cell_type.get_dimension();
+        let integrator = GaussLegendre::new(dimension, points_per_direction);
+        let basis = LagrangeBasis::new(cell_type, order);
+        let shapes = integrator
+            .get_points()
+            .chunks(dimension)
+            .flat_map(|p| basis.interpolate_basis(p))
+            .collect();
+        let shape_derivatives = integrator
+            .get_points()
+            .chunks(dimension)
+            .flat_map(|p| basis.interpolate_basis_derivative(p))
+            .collect();
+        LagrangeElement {
+            cell_type,
+            integrator,
+            basis,
+            shapes,
+            shape_derivatives,
+        }
+    }
+
+    /// Get the reference cell
+    pub fn get_cell_type(&self) -> CellType {
+        self.cell_type
+    }
+
+    /// Get the polynomial order in each direction
+    pub fn get_order(&self) -> usize {
+        self.basis.get_order()
+    }
+
+    /// Get the facets of the reference cell
+    ///
+    /// The edges list all their nodes in order and the faces of the cube the nodes of their edges
+    /// in cyclic order, which are the ones shared by two neighbouring cells.
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the facets or an error if they do not match the shape basis
+    pub fn get_reference_facets(&self) -> Result<ReferenceFacets<f64>, Error> {
+        let m = self.get_order() + 1;
+        let facets = match self.cell_type {
+            CellType::Line => vec![vec![0], vec![m - 1]],
+            CellType::Quad => {
+                let node = |i: usize, j: usize| i + m * j;
+                (0..4)
+                    .map(|side| {
+                        (0..m)
+                            .map(|s| match side {
+                                0 => node(s, 0),
+                                1 => node(m - 1, s),
+                                2 => node(m - 1 - s, m - 1),
+                                _ => node(0, m - 1 - s),
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+            CellType::Hex => {
+                let node = |i: usize, j: usize, k: usize| i + m * (j + m * k);
+                let cycle: Vec<(usize, usize)> = (0..m - 1)
+                    .map(|s| (s, 0))
+                    .chain((0..m - 1).map(|s| (m - 1, s)))
+                    .chain((0..m - 1).map(|s| (m - 1 - s, m - 1)))
+                    .chain((0..m - 1).map(|s| (0, m - 1 - s)))
+                    .collect();
+                (0..6)
+                    .map(|face| {
+                        let (side, last) = (face / 2, (face % 2) * (m - 1));
+                        cycle
+                            .iter()
+                            .map(|&(a, b)| match side {
+                                0 => node(last, a, b),
+                                1 => node(a, last, b),
+                                _ => node(a, b, last),
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+        };
+        ReferenceFacets::new(self, self.basis.get_reference_nodes(), facets)
+    }
+
+    /// Create a uniform mesh of the unit cell `[0, 1]` to the power of the dimension
+    ///
+    /// # Arguments
+    ///
+    /// * `cells_per_direction`: the number of cells along each axis
+    ///
+    /// # Returns
+    ///
+    /// * the dofs and the real coordinates of the cells, one dof per node numbered
+    ///   lexicographically over the whole mesh, to be given to `CellBlock::new`
+    pub fn create_uniform_mesh(&self, cells_per_direction: usize) -> (Vec<usize>, Vec<f64>) {
+        let dimension = self.cell_type.get_dimension();
+        let order = self.get_order();
+        let nodes_per_direction = cells_per_direction * order + 1;
+        let spacing = 1.0 / (cells_per_direction * order) as f64;
+        let mut dofs = Vec::new();
+        let mut coords = Vec::new();
+        for cell in 0..cells_per_direction.pow(dimension as u32) {
+            let cell_digits = get_digits(cell, cells_per_direction, dimension);
+            for node in 0..self.basis.get_number_of_bases() {
+                let global: Vec<usize> = get_digits(node, order + 1, dimension)
+                    .iter()
+                    .zip(&cell_digits)
+                    .map(|(&local, &offset)| offset * order + local)
+                    .collect();
+                dofs.push(
+                    global
+                        .iter()
+                        .rev()
+                        .fold(0, |dof, &i| dof * nodes_per_direction + i),
+                );
+                coords.extend(global.iter().map(|&i| i as f64 * spacing));
+            }
+        }
+        (dofs, coords)
+    }
+}
+
+impl Element<f64, f64> for LagrangeElement {
+    type GeometryT = CellType;
+    type IntegratorT = GaussLegendre;
+    type ShapeBasisT = LagrangeBasis;
+
+    fn get_geometry(&self) -> &CellType {
+        &self.cell_type
+    }
+
+    fn get_integrator(&self) -> &GaussLegendre {
+        &self.integrator
+    }
+
+    fn get_shape_basis(&self) -> &LagrangeBasis {
+        &self.basis
+    }
+
+    fn get_shapes_for_integration(&self) -> &[f64] {
+        &self.shapes
+    }
+
+    fn get_shape_derivatives_for_integration(&self) -> &[f64] {
+        &self.shape_derivatives
+    }
+
+    fn get_geometry_derivatives_for_integration(&self, coords: &[f64]) -> Vec<f64> {
+        let dimension = self.cell_type.get_dimension();
+        let nbases = self.basis.get_number_of_bases();
+        let embedding = coords.len() / nbases;
+        self.shape_derivatives
+            .chunks(nbases * dimension)
+            .flat_map(|derivatives| {
+                jacobian_from_coordinates(coords, embedding, dimension, derivatives)
+            })
+            .collect()
+    }
+}
+
+/// Get the digits of an index in a base, the first one being the least significant
+fn get_digits(mut index: usize, base: usize, count: usize) -> Vec<usize> {
+    (0..count)
+        .map(|_| {
+            let digit = index % base;
+            index /= base;
+            digit
+        })
+        .collect()
+}
+
+/// Compute the points in increasing order and the weights of the Gauss-Legendre rule of a number
+/// of points on `[-1, 1]`
+///
+/// The points are the roots of the Legendre polynomial of degree the number of points, found by
+/// Newton iterations from the Chebyshev approximations of the roots.
+fn compute_gauss_legendre(number_of_points: usize) -> (Vec<f64>, Vec<f64>) {
+    let n = number_of_points;
+    (0..n)
+        .map(|i| {
+            let mut x = -(std::f64::consts::PI * (i as f64 + 0.75) / (n as f64 + 0.5)).cos();
+            let mut derivative = 1.0;
+            for _ in 0..100 {
+                let (value, d) = evaluate_legendre(n, x);
+                derivative = d;
+                let step = value / d;
+                x -= step;
+                if step.abs() < 1e-15 {
+                    derivative = evaluate_legendre(n, x).1;
+                    break;
+                }
+            }
+            (x, 2.0 / ((1.0 - x * x) * derivative * derivative))
+        })
+        .unzip()
+}
+
+/// Evaluate the Legendre polynomial of a degree and its derivative inside `(-1, 1)` by their three
+/// term recurrence
+fn evaluate_legendre(degree: usize, x: f64) -> (f64, f64) {
+    let (mut previous, mut value) = (1.0, x);
+    if degree == 0 {
+        return (1.0, 0.0);
+    }
+    for n in 1..degree {
+        let n = n as f64;
+        (previous, value) = (
+            value,
+            ((2.0 * n + 1.0) * x * value - n * previous) / (n + 1.0),
+        );
+    }
+    let derivative = degree as f64 * (x * value - previous) / (x * x - 1.0);
+    (value, derivative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CellType, GaussLegendre, LagrangeBasis, LagrangeElement};
+    use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+    use crate::geometry::geometry_traits::Geometry;
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_gauss_legendre() {
+        let rule = GaussLegendre::new(2, 3);
+        assert_eq!(rule.get_number_of_points(), 9, "Incorrect number of points");
+        let values: Vec<f64> = rule
+            .get_points()
+            .chunks(2)
+            .map(|p| p[0].powi(4) * p[1].powi(2))
+            .collect();
+        assert!(
+            (rule.integrate(&values) - 4.0 / 15.0).abs() < TOL,
+            "Incorrect integral"
+        );
+        let rule = GaussLegendre::new(1, 6);
+        let values: Vec<f64> = rule.get_points().iter().map(|x| x.powi(10)).collect();
+        assert!(
+            (rule.integrate(&values) - 2.0 / 11.0).abs() < TOL,
+            "Incorrect high degree integral"
+        );
+        assert_eq!(
+            CellType::Hex.get_number_of_elements(1),
+            12,
+            "Incorrect number of edges"
+        );
+    }
+
+    #[test]
+    fn test_lagrange_basis() {
+        let basis = LagrangeBasis::new(CellType::Hex, 3);
+        assert_eq!(basis.get_number_of_bases(), 64, "Incorrect number of bases");
+        for (a, node) in basis.get_reference_nodes().chunks(3).enumerate() {
+            for (b, value) in basis.interpolate_basis(node).iter().enumerate() {
+                let expected = if a == b { 1.0 } else { 0.0 };
+                assert!((value - expected).abs() < TOL, "Incorrect nodal value");
+            }
+        }
+        let point = [0.3, -0.7, 0.1];
+        let sum: f64 = basis.interpolate_basis(&point).iter().sum();
+        assert!((sum - 1.0).abs() < TOL, "Incorrect partition of unity");
+        let derivatives = basis.interpolate_basis_derivative(&point);
+        for direction in 0..3 {
+            let slope: f64 = derivatives
+                .chunks(3)
+                .zip(basis.get_reference_nodes().chunks(3))
+                .map(|(d, node)| d[direction] * node[direction].powi(3))
+                .sum();
+            let expected = 3.0 * point[direction] * point[direction];
+            assert!((slope - expected).abs() < TOL, "Incorrect derivative");
+        }
+    }
+
+    #[test]
+    fn test_element() {
+        let element = LagrangeElement::new(CellType::Quad, 2, 3);
+        let (dofs, coords) = element.create_uniform_mesh(2);
+        assert_eq!(dofs.len(), 36, "Incorrect number of cell dofs");
+        assert_eq!(dofs.iter().max(), Some(&24), "Incorrect number of dofs");
+        let jacobians = element.get_geometry_derivatives_for_integration(&coords[18..36]);
+        for jacobian in jacobians.chunks(4) {
+            for (value, expected) in jacobian.iter().zip([0.25, 0.0, 0.0, 0.25]) {
+                assert!((value - expected).abs() < TOL, "Incorrect jacobian");
+            }
+        }
+        let facets = element.get_reference_facets().unwrap();
+        assert_eq!(facets.get_facet(2), &[8, 7, 6], "Incorrect top edge");
+        let element = LagrangeElement::new(CellType::Hex, 2, 3);
+        let facets = element.get_reference_facets().unwrap();
+        assert_eq!(
+            facets.get_number_of_facets(),
+            6,
+            "Incorrect number of faces"
+        );
+        assert_eq!(
+            facets.get_facet(4),
+            &[0, 1, 2, 5, 8, 7, 6, 3],
+            "Incorrect bottom face"
+        );
+        assert!(
+            facets.contains(&[0.5, -0.5, 0.9], TOL) && !facets.contains(&[0.5, 1.1, 0.0], TOL),
+            "Incorrect reference cell"
+        );
+    }
+}
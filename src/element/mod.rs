@@ -4,5 +4,20 @@ pub mod jacobi;
 /// Module for all traits at element level
 pub mod element_traits;
 
+/// Module for the scratch buffers reused by the element loops
+pub mod workspace;
+
 /// Module for the operator triats at the element level
 pub mod operator_trait;
+
+/// Module for the residual kernels at the element level and their automatic tangents
+pub mod residual_trait;
+
+/// Module for the complex valued elements built on real elements
+pub mod complex;
+
+/// Module for the Lagrange elements of arbitrary order on tensor product cells
+pub mod lagrange;
+
+/// Module for the builders configuring and validating the elements
+pub mod builder;
@@ -1,6 +1,7 @@
-use ndarray::LinalgScalar;
-use std::collections::HashMap;
+use crate::algebra::scalar::Scalar;
 use crate::element::element_traits::Element;
+use crate::element::workspace::Workspace;
+use std::collections::HashMap;
 
 /// Computes a discrete matrix operator
 ///
@@ -13,17 +14,95 @@ use crate::element::element_traits::Element;
 ///
 /// * ElementT: the type of element this operator should use to describe the cell
 ///
-/// # Traits
-///
-/// * Fn(geometry , data): makes the operator callable
-/// by passing the real geometry of a cell and the data associated to the cell in a HashMap. Should
-/// return a flattened matrix.
-///
 /// # Explanation
 /// Given the geometry of a cell and its associated data, compute a local matrix that embodies the
 /// discretized operator
-pub trait Operator<CoordType: LinalgScalar, DataType: LinalgScalar>:
-    Fn(&[CoordType], &HashMap<String, &[DataType]>) -> Vec<DataType>
-{
+pub trait Operator<CoordType: Scalar, DataType: Scalar> {
     type ElementT: Element<CoordType, DataType>;
+
+    /// Compute the local matrix of the operator
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry`: the real coordinates of the cell in AOS ordering
+    /// * `data`: the data associated to the cell indexed by name
+    ///
+    /// # Returns
+    ///
+    /// * the local matrix flattened in row major ordering
+    fn compute(&self, geometry: &[CoordType], data: &HashMap<String, &[DataType]>)
+        -> Vec<DataType>;
+
+    /// Same as compute above but writing the local matrix into local and holding the intermediate
+    /// values in the workspace
+    ///
+    /// The local matrix keeps its capacity from one cell to the next, so that operators
+    /// implementing this method with the `*_into` variants of the element methods do not allocate
+    /// in the assembly loops, as the operators of the models do. The default implementation falls
+    /// back to calling compute, and allocates as much.
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry`: the real coordinates of the cell in AOS ordering
+    /// * `data`: the data associated to the cell indexed by name
+    /// * `workspace`: the scratch buffers of the intermediate values
+    /// * `local`: overwritten by the local matrix flattened in row major ordering
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+        _workspace: &mut Workspace<DataType>,
+        local: &mut Vec<DataType>,
+    ) {
+        local.clear();
+        local.extend(self.compute(geometry, data));
+    }
+
+    /// Compute the local matrices of a batch of `LANES` cells at once
+    ///
+    /// Inputs and outputs are in SOA ordering: each entry holds the values of all the cells of the
+    /// batch, so that a loop over the lanes vectorizes. The default implementation falls back to
+    /// calling compute on each cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry`: the real coordinates of the cells, one entry per coordinate value
+    /// * `data`: the data associated to the cells indexed by name, one entry per data value
+    ///
+    /// # Returns
+    ///
+    /// * the local matrices flattened in row major ordering, one entry per matrix value
+    fn compute_batch<const LANES: usize>(
+        &self,
+        geometry: &[[CoordType; LANES]],
+        data: &HashMap<String, &[[DataType; LANES]]>,
+    ) -> Vec<[DataType; LANES]> {
+        let mut local = Vec::new();
+        for lane in 0..LANES {
+            let lane_geometry: Vec<CoordType> = geometry.iter().map(|g| g[lane]).collect();
+            let lane_values: HashMap<&String, Vec<DataType>> = data
+                .iter()
+                .map(|(name, values)| (name, values.iter().map(|v| v[lane]).collect()))
+                .collect();
+            let lane_data = lane_values
+                .iter()
+                .map(|(name, values)| ((*name).clone(), &values[..]))
+                .collect();
+            let lane_local = self.compute(&lane_geometry, &lane_data);
+            if lane == 0 {
+                local = vec![[DataType::zero(); LANES]; lane_local.len()];
+            }
+            for (entry, value) in local.iter_mut().zip(lane_local) {
+                entry[lane] = value;
+            }
+        }
+        local
+    }
+
+    /// Whether the local matrices computed by the operator are symmetric
+    ///
+    /// Symmetric operators may be assembled storing only the upper triangle of the global matrix.
+    fn is_symmetric(&self) -> bool {
+        false
+    }
 }
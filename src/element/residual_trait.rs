@@ -0,0 +1,246 @@
+use crate::algebra::dual::Dual;
+use crate::algebra::scalar::Scalar;
+use crate::element::element_traits::Element;
+use crate::element::operator_trait::Operator;
+use crate::error::Error;
+use num::Float;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Computes the local residual of a nonlinear discrete operator
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the base space
+/// * DataType: the type of unit the data is encoded with
+///
+/// # Types
+///
+/// * ElementT: the type of element this kernel should use to describe the cell
+///
+/// # Explanation
+/// Given the geometry of a cell, its associated data and the local values of the state, compute
+/// the local residual vector. The residual is written once, generically over the scalar type of the
+/// state: evaluated on plain numbers it gives the residual, evaluated on dual numbers through
+/// `AutomaticTangent` it gives the exact local jacobian as well.
+pub trait ResidualKernel<CoordType: Scalar, DataType: Scalar> {
+    type ElementT: Element<CoordType, DataType>;
+
+    /// Get the number of degrees of freedom of a cell, the size of the local state and residual
+    fn get_number_of_dofs(&self) -> usize;
+
+    /// Compute the local residual
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry`: the real coordinates of the cell in AOS ordering
+    /// * `data`: the data associated to the cell indexed by name
+    /// * `state`: the local values of the state
+    ///
+    /// # Returns
+    ///
+    /// * the local residual
+    fn compute_residual<ScalarT: Scalar + Float + From<DataType>>(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+        state: &[ScalarT],
+    ) -> Vec<ScalarT>;
+}
+
+/// Operator computing the local jacobian of a residual kernel by forward automatic differentiation
+///
+/// # Generics
+///
+/// * KernelT: the residual kernel
+/// * N: the number of degrees of freedom of a cell
+///
+/// # Explanation
+///
+/// The local state is read from a data field of the cells, gathered for instance with
+/// `CellBlock::gather`, and seeded as the `N` variables of dual numbers so that a single
+/// evaluation of the kernel gives the residual and all the columns of the jacobian. The tangent
+/// at the null state is computed when the cells hold no state field, which for linear kernels is
+/// their stiffness matrix.
+pub struct AutomaticTangent<CoordType, DataType, KernelT, const N: usize> {
+    kernel: KernelT,
+    state_field: String,
+    phantom: PhantomData<(CoordType, DataType)>,
+}
+
+impl<CoordType, DataType, KernelT, const N: usize> AutomaticTangent<CoordType, DataType, KernelT, N>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float,
+    KernelT: ResidualKernel<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `kernel`: the residual kernel
+    /// * `state_field`: the name of the data field holding the local state
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the operator or an error if the kernel does not have `N` degrees
+    ///   of freedom
+    pub fn new(
+        kernel: KernelT,
+        state_field: &str,
+    ) -> Result<AutomaticTangent<CoordType, DataType, KernelT, N>, Error> {
+        if kernel.get_number_of_dofs() != N {
+            return Err(Error::SizeMismatch {
+                context: "Number of variables does not match the dofs of the kernel",
+                expected: kernel.get_number_of_dofs(),
+                actual: N,
+            });
+        }
+        Ok(AutomaticTangent {
+            kernel,
+            state_field: state_field.to_string(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Get the residual kernel
+    pub fn get_kernel(&self) -> &KernelT {
+        &self.kernel
+    }
+
+    /// Get the name of the data field holding the local state
+    pub fn get_state_field(&self) -> &str {
+        &self.state_field
+    }
+
+    /// Compute the local residual and jacobian
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry`: the real coordinates of the cell in AOS ordering
+    /// * `data`: the data associated to the cell indexed by name, holding the local state
+    ///
+    /// # Returns
+    ///
+    /// * the local residual and the local jacobian flattened in row major ordering
+    pub fn compute_residual_and_tangent(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> (Vec<DataType>, Vec<DataType>) {
+        let state: Vec<Dual<DataType, N>> = match data.get(&self.state_field) {
+            Some(values) => values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| Dual::variable(value, i))
+                .collect(),
+            None => (0..N)
+                .map(|i| Dual::variable(DataType::zero(), i))
+                .collect(),
+        };
+        let residual = self.kernel.compute_residual(geometry, data, &state);
+        let values = residual.iter().map(|r| r.get_value()).collect();
+        let tangent = residual
+            .iter()
+            .flat_map(|r| r.get_derivatives().iter().copied())
+            .collect();
+        (values, tangent)
+    }
+}
+
+impl<CoordType, DataType, KernelT, const N: usize> Operator<CoordType, DataType>
+    for AutomaticTangent<CoordType, DataType, KernelT, N>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float,
+    KernelT: ResidualKernel<CoordType, DataType>,
+{
+    type ElementT = KernelT::ElementT;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> Vec<DataType> {
+        self.compute_residual_and_tangent(geometry, data).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutomaticTangent, ResidualKernel};
+    use crate::algebra::scalar::Scalar;
+    use crate::element::operator_trait::Operator;
+    use crate::test_utils::LinearSegmentElement;
+    use num::Float;
+    use std::collections::HashMap;
+
+    const TOL: f64 = 1e-10;
+
+    /// Residual of `-d/dx((1 + u^2) du/dx) + u^3` on linear segments with a one point quadrature
+    struct NonlinearDiffusion;
+
+    impl ResidualKernel<f64, f64> for NonlinearDiffusion {
+        type ElementT = LinearSegmentElement;
+
+        fn get_number_of_dofs(&self) -> usize {
+            2
+        }
+
+        fn compute_residual<ScalarT: Scalar + Float + From<f64>>(
+            &self,
+            geometry: &[f64],
+            _data: &HashMap<String, &[f64]>,
+            state: &[ScalarT],
+        ) -> Vec<ScalarT> {
+            let h: ScalarT = (geometry[1] - geometry[0]).into();
+            let half: ScalarT = 0.5.into();
+            let u = half * (state[0] + state[1]);
+            let flux = (ScalarT::one() + u * u) * (state[1] - state[0]) / h;
+            let source = half * h * u.powi(3);
+            vec![source - flux, source + flux]
+        }
+    }
+
+    #[test]
+    fn test_automatic_tangent() {
+        let tangent = AutomaticTangent::<_, _, _, 2>::new(NonlinearDiffusion, "state").unwrap();
+        let geometry = [0.2, 0.7];
+        let state = [0.3, -1.1];
+        let data = HashMap::from([("state".to_string(), &state[..])]);
+        let (residual, jacobian) = tangent.compute_residual_and_tangent(&geometry, &data);
+        let plain = NonlinearDiffusion.compute_residual(&geometry, &data, &state);
+        for (value, expected) in residual.iter().zip(&plain) {
+            assert!((value - expected).abs() < TOL, "Incorrect residual");
+        }
+        let h = 1e-6;
+        for column in 0..2 {
+            let mut forward = state;
+            let mut backward = state;
+            forward[column] += h;
+            backward[column] -= h;
+            let forward = NonlinearDiffusion.compute_residual(&geometry, &data, &forward);
+            let backward = NonlinearDiffusion.compute_residual(&geometry, &data, &backward);
+            for row in 0..2 {
+                let difference = (forward[row] - backward[row]) / (2.0 * h);
+                assert!(
+                    (jacobian[row * 2 + column] - difference).abs() < 1e-6,
+                    "Incorrect jacobian entry ({}, {})",
+                    row,
+                    column
+                );
+            }
+        }
+        let linear = tangent.compute(&geometry, &HashMap::new());
+        for (value, expected) in linear.iter().zip([2.0, -2.0, -2.0, 2.0]) {
+            assert!(
+                (value - expected).abs() < TOL,
+                "Incorrect tangent at the null state"
+            );
+        }
+        assert!(
+            AutomaticTangent::<_, _, _, 3>::new(NonlinearDiffusion, "state").is_err(),
+            "Wrong number of variables accepted"
+        );
+    }
+}
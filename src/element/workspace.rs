@@ -0,0 +1,81 @@
+use crate::algebra::scalar::Scalar;
+
+/// Scratch buffers reused by the element computations of a loop over cells
+///
+/// # Generics
+///
+/// * DataType: the type of unit the buffers hold
+///
+/// # Explanation
+///
+/// The `*_into` and `*_with` variants of the element, shape basis and operator methods take a
+/// workspace to hold their intermediate values, the values of the shape functions at a point or
+/// at the integration points for instance. The buffers keep their capacity from one call to the
+/// next, so that once the first cell has been computed the following ones do not allocate. A
+/// workspace is meant to be owned by a loop, one per thread, and lent to the computations of
+/// each cell in turn.
+pub struct Workspace<DataType> {
+    buffers: Vec<Vec<DataType>>,
+}
+
+impl<DataType: Scalar> Default for Workspace<DataType> {
+    fn default() -> Self {
+        Workspace::new()
+    }
+}
+
+impl<DataType: Scalar> Workspace<DataType> {
+    /// Constructor of a workspace without buffers
+    pub fn new() -> Workspace<DataType> {
+        Workspace {
+            buffers: Vec::new(),
+        }
+    }
+
+    /// Get distinct zero valued buffers of given lengths
+    ///
+    /// # Arguments
+    ///
+    /// * `lengths`: the length of each buffer
+    ///
+    /// # Returns
+    ///
+    /// * the buffers, valid until the next request to the workspace
+    pub fn get_buffers<const N: usize>(&mut self, lengths: [usize; N]) -> [&mut [DataType]; N] {
+        if self.buffers.len() < N {
+            self.buffers.resize_with(N, Vec::new);
+        }
+        let mut buffers = self.buffers.iter_mut();
+        lengths.map(|length| {
+            let buffer = buffers.next().unwrap();
+            buffer.clear();
+            buffer.resize(length, DataType::zero());
+            &mut buffer[..]
+        })
+    }
+
+    /// Get the total number of values the buffers can hold without allocating
+    pub fn get_capacity(&self) -> usize {
+        self.buffers.iter().map(|buffer| buffer.capacity()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Workspace;
+
+    #[test]
+    fn test_buffers() {
+        let mut workspace = Workspace::<f64>::new();
+        let [a, b] = workspace.get_buffers([3, 2]);
+        a[0] = 1.0;
+        b[1] = 2.0;
+        assert_eq!((a.len(), b.len()), (3, 2), "Incorrect buffer lengths");
+        let capacity = workspace.get_capacity();
+        let [a] = workspace.get_buffers([2]);
+        assert_eq!(a, &[0.0, 0.0], "Buffer not reset");
+        let [a, b] = workspace.get_buffers([1, 2]);
+        assert_eq!((a.len(), b.len()), (1, 2), "Incorrect buffer lengths");
+        assert_eq!(workspace.get_capacity(), capacity, "Buffers reallocated");
+    }
+}
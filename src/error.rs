@@ -0,0 +1,145 @@
+use std::fmt;
+
+/// Error of the fallible operations of the library
+///
+/// # Explanation
+///
+/// The variants describe the kind of failure and carry what is known at the point it occurred:
+/// the offending cell, the expected and actual lengths or dimensions, or the index out of its
+/// bounds. The `context` and message fields name the quantity that was checked, so that the
+/// displayed error reads as a sentence, for instance
+/// `State size does not match the number of dofs: expected 12, got 10`.
+#[derive(Debug)]
+pub enum Error {
+    /// A slice, a vector or a matrix does not have the expected length
+    SizeMismatch {
+        context: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// A point, a field or a cell does not have the expected dimension
+    DimensionMismatch {
+        context: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// An index is out of the bounds of what it indexes
+    OutOfBounds {
+        context: &'static str,
+        index: usize,
+        bound: usize,
+    },
+    /// An entry is missing from the sparsity pattern of a matrix
+    MissingEntry { row: usize, column: usize },
+    /// The map from the reference cell to a cell of a block is degenerate
+    DegenerateCell { cell: usize },
+    /// The local values computed by an operator or a kernel on a cell are not finite
+    NotFiniteCell { cell: usize },
+    /// An argument, or a combination of arguments, is not admissible
+    InvalidArgument(&'static str),
+    /// A matrix can not be factorized
+    Singular(&'static str),
+    /// An iterative method did not reach its stopping criterion
+    NotConverged(&'static str),
+    /// Computed values are not finite
+    NotFinite(&'static str),
+    /// Data read from an input is malformed
+    InvalidData(&'static str),
+    /// Reading from or writing to a file or a stream failed
+    Io {
+        context: &'static str,
+        source: std::io::Error,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::SizeMismatch {
+                context,
+                expected,
+                actual,
+            } => write!(f, "{}: expected {}, got {}", context, expected, actual),
+            Error::DimensionMismatch {
+                context,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: expected dimension {}, got {}",
+                context, expected, actual
+            ),
+            Error::OutOfBounds {
+                context,
+                index,
+                bound,
+            } => write!(f, "{}: index {} out of bounds {}", context, index, bound),
+            Error::MissingEntry { row, column } => write!(
+                f,
+                "Entry ({}, {}) missing from the sparsity pattern",
+                row, column
+            ),
+            Error::DegenerateCell { cell } => write!(f, "Degenerate cell map of cell {}", cell),
+            Error::NotFiniteCell { cell } => {
+                write!(f, "Non finite local values computed on cell {}", cell)
+            }
+            Error::InvalidArgument(message)
+            | Error::Singular(message)
+            | Error::NotConverged(message)
+            | Error::NotFinite(message)
+            | Error::InvalidData(message) => write!(f, "{}", message),
+            Error::Io { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn test_display() {
+        let error = Error::SizeMismatch {
+            context: "State size does not match the number of dofs",
+            expected: 12,
+            actual: 10,
+        };
+        assert_eq!(
+            error.to_string(),
+            "State size does not match the number of dofs: expected 12, got 10",
+            "Incorrect size mismatch message"
+        );
+        assert_eq!(
+            Error::DegenerateCell { cell: 3 }.to_string(),
+            "Degenerate cell map of cell 3",
+            "Incorrect degenerate cell message"
+        );
+        assert_eq!(
+            Error::NotFiniteCell { cell: 2 }.to_string(),
+            "Non finite local values computed on cell 2",
+            "Incorrect non finite cell message"
+        );
+        let error = Error::Io {
+            context: "Could not create the VTU file",
+            source: std::io::Error::new(std::io::ErrorKind::NotFound, "missing directory"),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Could not create the VTU file: missing directory",
+            "Incorrect input/output message"
+        );
+        assert!(
+            std::error::Error::source(&error).is_some(),
+            "Input/output error not chained"
+        );
+    }
+}
@@ -1,7 +1,43 @@
 //! A crate providing a framework for writing finite element codes
 
+/// Module providing the error type of the fallible operations of the library
+pub mod error;
+
 /// Module providing descriptions of geometry for the library
 pub mod geometry;
 
 /// Module providing base elements for assembly
 pub mod element;
+
+/// Module providing sparse linear algebra structures
+pub mod algebra;
+
+/// Module providing the assembly of global systems from element operators
+pub mod assembly;
+
+/// Module providing iterative solvers for the assembled systems
+pub mod solver;
+
+/// Module providing nonlinear solvers for the residual equations of the discretized problems
+pub mod nonlinear;
+
+/// Module providing time integration schemes for the semi-discrete systems
+pub mod time;
+
+/// Module providing the post-processing and output of the discrete solutions
+pub mod post;
+
+/// Module providing ready-made models wiring the whole stack for classic problems
+pub mod models;
+
+/// Module providing wall time reports of the phases of a simulation
+pub mod timer;
+
+/// Module providing the configuration of the parallel loops of the library
+pub mod parallel;
+
+/// Module re-exporting the traits and types needed to write and assemble element kernels
+pub mod prelude;
+
+#[cfg(test)]
+mod test_utils;
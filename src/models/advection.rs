@@ -0,0 +1,746 @@
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::element::operator_trait::Operator;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::models::poisson::compute_dirichlet_constraints;
+use crate::post::boundary::FacetGroup;
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::{check_block, compute_shape_gradients_into, FEFunction};
+use crate::solver::registry::{SolverConfiguration, SolverRegistry};
+use crate::time::implicit::{ImplicitIntegrator, ImplicitScheme};
+use num::Float;
+use std::collections::HashMap;
+
+/// Data of the problem given as a function of the real coordinates
+type PointFunction<'a, DataType> = Box<dyn Fn(&[DataType]) -> DataType + 'a>;
+
+/// Data of the problem given as a function of the time and of the real coordinates
+type TransientFunction<'a, DataType> = Box<dyn Fn(DataType, &[DataType]) -> DataType + 'a>;
+
+/// Velocity field given as a function of the real coordinates
+type VelocityField<'a, DataType> = dyn Fn(&[DataType]) -> Vec<DataType> + 'a;
+
+/// Values at the integration points of a cell held in a workspace: the real gradients of the
+/// shapes, the derivatives of the shapes along the velocity, the stabilization parameters and the
+/// integration weights scaled by the measure of the map
+type StreamlinePoints<'w, DataType> = [&'w [DataType]; 4];
+
+/// Stiffness matrix of `-κ Δu + b . grad u` stabilized by the streamline upwind Petrov-Galerkin
+/// method, computed with the shape functions of an element
+///
+/// # Explanation
+///
+/// The test functions `v` are augmented by `τ b . grad v` on the advective terms, which adds the
+/// artificial diffusion `τ (b . grad u) (b . grad v)` along the streamlines only. The second order
+/// part of the residual is dropped, as it vanishes for linear elements. The parameter
+/// `τ = h / (2 |b|) (coth(Pe) - 1 / Pe)`, with the cell Péclet number `Pe = |b| h / (2 κ)` and
+/// the length `h = 2 |b| / sum_a |b . grad N_a|` of the cell along the velocity at each
+/// integration point, makes linear elements nodally exact in one dimension. The local matrix of a
+/// degenerate cell holds NaN values.
+pub struct SupgOperator<'a, DataType, ElementT> {
+    element: &'a ElementT,
+    diffusivity: DataType,
+    velocity: &'a VelocityField<'a, DataType>,
+    stabilized: bool,
+}
+
+impl<'a, DataType, ElementT> SupgOperator<'a, DataType, ElementT> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `diffusivity`: the diffusivity `κ`
+    /// * `velocity`: the velocity `b` as a function of the real coordinates
+    /// * `stabilized`: whether the streamline stabilization is added, for a comparison with the
+    ///   Galerkin method
+    pub fn new(
+        element: &'a ElementT,
+        diffusivity: DataType,
+        velocity: &'a VelocityField<'a, DataType>,
+        stabilized: bool,
+    ) -> SupgOperator<'a, DataType, ElementT> {
+        SupgOperator {
+            element,
+            diffusivity,
+            velocity,
+            stabilized,
+        }
+    }
+}
+
+/// Builder of a SupgOperator
+///
+/// # Explanation
+///
+/// The diffusivity defaults to one and should be positive, and the streamline stabilization is
+/// added unless disabled.
+pub struct SupgOperatorBuilder<'a, DataType, ElementT> {
+    element: &'a ElementT,
+    velocity: &'a VelocityField<'a, DataType>,
+    diffusivity: DataType,
+    stabilized: bool,
+}
+
+impl<'a, DataType: Float, ElementT> SupgOperatorBuilder<'a, DataType, ElementT> {
+    /// Constructor of a builder of a stabilized operator of unit diffusivity
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `velocity`: the velocity `b` as a function of the real coordinates
+    pub fn new(
+        element: &'a ElementT,
+        velocity: &'a VelocityField<'a, DataType>,
+    ) -> SupgOperatorBuilder<'a, DataType, ElementT> {
+        SupgOperatorBuilder {
+            element,
+            velocity,
+            diffusivity: DataType::one(),
+            stabilized: true,
+        }
+    }
+
+    /// Set the diffusivity `κ`
+    pub fn diffusivity(mut self, diffusivity: DataType) -> Self {
+        self.diffusivity = diffusivity;
+        self
+    }
+
+    /// Set whether the streamline stabilization is added
+    pub fn stabilized(mut self, stabilized: bool) -> Self {
+        self.stabilized = stabilized;
+        self
+    }
+
+    /// Build the operator
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the operator or an error if the diffusivity is not positive
+    pub fn build(&self) -> Result<SupgOperator<'a, DataType, ElementT>, Error> {
+        if !(self.diffusivity > DataType::zero() && self.diffusivity.is_finite()) {
+            return Err(Error::InvalidArgument("Diffusivity should be positive"));
+        }
+        Ok(SupgOperator::new(
+            self.element,
+            self.diffusivity,
+            self.velocity,
+            self.stabilized,
+        ))
+    }
+}
+
+impl<'a, DataType, ElementT> SupgOperator<'a, DataType, ElementT> {
+    /// Get the mass operator `(u, v + τ b . grad v)` consistent with the stabilization, for
+    /// transient problems
+    pub fn get_mass_operator(&self) -> SupgMassOperator<'_, 'a, DataType, ElementT> {
+        SupgMassOperator { operator: self }
+    }
+}
+
+impl<CoordType, DataType, ElementT> Operator<CoordType, DataType>
+    for SupgOperator<'_, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    type ElementT = ElementT;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> Vec<DataType> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        _data: &HashMap<String, &[DataType]>,
+        workspace: &mut Workspace<DataType>,
+        local: &mut Vec<DataType>,
+    ) {
+        let n = self.element.get_shape_basis().get_number_of_bases();
+        let embedding = geometry.len() / n;
+        local.clear();
+        let Some([gradients, advection, taus, weights]) =
+            self.compute_streamline_points(geometry, workspace)
+        else {
+            local.resize(n * n, DataType::nan());
+            return;
+        };
+        local.resize(n * n, DataType::zero());
+        for ((((shapes, point_gradients), point_advection), &tau), &weight) in self
+            .element
+            .get_shapes_for_integration()
+            .chunks(n)
+            .zip(gradients.chunks(geometry.len()))
+            .zip(advection.chunks(n))
+            .zip(taus)
+            .zip(weights)
+        {
+            for (a, ga) in point_gradients.chunks(embedding).enumerate() {
+                for (b, gb) in point_gradients.chunks(embedding).enumerate() {
+                    let product = ga
+                        .iter()
+                        .zip(gb)
+                        .fold(DataType::zero(), |sum, (&x, &y)| sum + x * y);
+                    let value = self.diffusivity * product
+                        + (shapes[a] + tau * point_advection[a]) * point_advection[b];
+                    local[a * n + b] = local[a * n + b] + weight * value;
+                }
+            }
+        }
+    }
+}
+
+impl<DataType, ElementT> SupgOperator<'_, DataType, ElementT> {
+    /// Compute the values needed by the stabilized terms at the integration points of a cell into
+    /// the buffers of a workspace, None if the map of the cell is degenerate
+    fn compute_streamline_points<'w, CoordType>(
+        &self,
+        geometry: &[CoordType],
+        workspace: &'w mut Workspace<DataType>,
+    ) -> Option<StreamlinePoints<'w, DataType>>
+    where
+        CoordType: Scalar,
+        DataType: Scalar + Float + From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let n = self.element.get_shape_basis().get_number_of_bases();
+        let nips = self.element.get_integrator().get_number_of_points();
+        let embedding = geometry.len() / n;
+        let [gradients, advection, taus, weights] =
+            workspace.get_buffers([nips * geometry.len(), nips * n, nips, nips]);
+        if !compute_shape_gradients_into(self.element, geometry, gradients, weights) {
+            return None;
+        }
+        let two = DataType::one() + DataType::one();
+        for (((shapes, point_gradients), point_advection), tau) in self
+            .element
+            .get_shapes_for_integration()
+            .chunks(n)
+            .zip(gradients.chunks(geometry.len()))
+            .zip(advection.chunks_mut(n))
+            .zip(taus.iter_mut())
+        {
+            let mut point = [DataType::zero(); 3];
+            for (node, &shape) in geometry.chunks(embedding).zip(shapes) {
+                for (x, &coordinate) in point.iter_mut().zip(node) {
+                    *x = *x + shape * coordinate.into();
+                }
+            }
+            let velocity = (self.velocity)(&point[..embedding]);
+            for (a, g) in point_advection
+                .iter_mut()
+                .zip(point_gradients.chunks(embedding))
+            {
+                *a = g
+                    .iter()
+                    .zip(&velocity)
+                    .fold(DataType::zero(), |sum, (&x, &y)| sum + x * y);
+            }
+            let speed = velocity
+                .iter()
+                .fold(DataType::zero(), |sum, &v| sum + v * v)
+                .sqrt();
+            let spread = point_advection
+                .iter()
+                .fold(DataType::zero(), |sum, &a| sum + a.abs());
+            *tau = if self.stabilized && speed > DataType::zero() && spread > DataType::zero() {
+                let h = two * speed / spread;
+                let peclet = speed * h / (two * self.diffusivity);
+                let three = two + DataType::one();
+                // expansion of coth(Pe) - 1 / Pe, which cancels for small numbers
+                let upwinding = if peclet < num::cast(1e-3).unwrap() {
+                    peclet / three
+                } else {
+                    DataType::one() / peclet.tanh() - peclet.recip()
+                };
+                h / (two * speed) * upwinding
+            } else {
+                DataType::zero()
+            };
+        }
+        Some([&*gradients, &*advection, &*taus, &*weights])
+    }
+}
+
+/// Mass matrix `(u, v + τ b . grad v)` of the stabilized test functions of a SupgOperator
+pub struct SupgMassOperator<'o, 'a, DataType, ElementT> {
+    operator: &'o SupgOperator<'a, DataType, ElementT>,
+}
+
+impl<CoordType, DataType, ElementT> Operator<CoordType, DataType>
+    for SupgMassOperator<'_, '_, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    type ElementT = ElementT;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> Vec<DataType> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        _data: &HashMap<String, &[DataType]>,
+        workspace: &mut Workspace<DataType>,
+        local: &mut Vec<DataType>,
+    ) {
+        let element = self.operator.element;
+        let n = element.get_shape_basis().get_number_of_bases();
+        local.clear();
+        let Some([_, advection, taus, weights]) =
+            self.operator.compute_streamline_points(geometry, workspace)
+        else {
+            local.resize(n * n, DataType::nan());
+            return;
+        };
+        local.resize(n * n, DataType::zero());
+        for (((shapes, point_advection), &tau), &weight) in element
+            .get_shapes_for_integration()
+            .chunks(n)
+            .zip(advection.chunks(n))
+            .zip(taus)
+            .zip(weights)
+        {
+            for a in 0..n {
+                for b in 0..n {
+                    local[a * n + b] = local[a * n + b]
+                        + weight * (shapes[a] + tau * point_advection[a]) * shapes[b];
+                }
+            }
+        }
+    }
+}
+
+/// Ready-made steady and transient advection-diffusion problem `du/dt - κ Δu + b . grad u = f`
+/// stabilized by the streamline upwind Petrov-Galerkin method
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the solution is encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// The velocity `b` is prescribed as a function of the real coordinates and the operators are
+/// those of SupgOperator, the source being tested against the same stabilized test functions.
+/// The Dirichlet values are constant in time and imposed at the nodes of the facets of their
+/// groups, typically the inflow boundary, the other facets being free of diffusive flux. The
+/// steady system is solved by GMRES preconditioned by ILU(0) unless configured otherwise and the
+/// transient one by an `ImplicitIntegrator` with the Crank-Nicolson scheme unless configured
+/// otherwise.
+pub struct AdvectionDiffusionProblem<'a, CoordType, DataType, ElementT> {
+    element: &'a ElementT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    reference_facets: &'a ReferenceFacets<CoordType>,
+    diffusivity: DataType,
+    velocity: Box<VelocityField<'a, DataType>>,
+    source: TransientFunction<'a, DataType>,
+    dirichlet: Vec<(FacetGroup, PointFunction<'a, DataType>)>,
+    stabilized: bool,
+    solver: SolverConfiguration,
+    scheme: ImplicitScheme,
+}
+
+impl<'a, CoordType, DataType, ElementT> AdvectionDiffusionProblem<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType> + 'static,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor of the problem without source nor boundary data
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells of the domain
+    /// * `reference_facets`: the facets of the reference element
+    /// * `diffusivity`: the diffusivity `κ`
+    /// * `velocity`: the velocity `b` as a function of the real coordinates
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the problem or an error if the block does not match the element
+    ///   or the diffusivity is not positive
+    pub fn new(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        reference_facets: &'a ReferenceFacets<CoordType>,
+        diffusivity: DataType,
+        velocity: impl Fn(&[DataType]) -> Vec<DataType> + 'a,
+    ) -> Result<AdvectionDiffusionProblem<'a, CoordType, DataType, ElementT>, Error> {
+        check_block(element, block)?;
+        if diffusivity <= DataType::zero() {
+            return Err(Error::InvalidArgument("Diffusivity should be positive"));
+        }
+        Ok(AdvectionDiffusionProblem {
+            element,
+            block,
+            reference_facets,
+            diffusivity,
+            velocity: Box::new(velocity),
+            source: Box::new(|_, _| DataType::zero()),
+            dirichlet: Vec::new(),
+            stabilized: true,
+            solver: SolverConfiguration::new("gmres", "ilu0"),
+            scheme: ImplicitScheme::CrankNicolson,
+        })
+    }
+
+    /// Set the source `f` as a function of the time and of the real coordinates
+    pub fn set_source(&mut self, source: impl Fn(DataType, &[DataType]) -> DataType + 'a) {
+        self.source = Box::new(source);
+    }
+
+    /// Impose the value of the solution on a group of facets
+    pub fn add_dirichlet(
+        &mut self,
+        group: FacetGroup,
+        value: impl Fn(&[DataType]) -> DataType + 'a,
+    ) {
+        self.dirichlet.push((group, Box::new(value)));
+    }
+
+    /// Set whether the streamline stabilization is added, the Galerkin method being used otherwise
+    pub fn set_stabilization(&mut self, stabilized: bool) {
+        self.stabilized = stabilized;
+    }
+
+    /// Set the selection of the solver and preconditioner of the registry for steady problems
+    pub fn set_solver(&mut self, solver: SolverConfiguration) {
+        self.solver = solver;
+    }
+
+    /// Set the time integration scheme for transient problems
+    pub fn set_scheme(&mut self, scheme: ImplicitScheme) {
+        self.scheme = scheme;
+    }
+
+    /// Get the number of dofs of the problem
+    pub fn get_number_of_dofs(&self) -> usize {
+        self.block
+            .get_connectivity()
+            .iter()
+            .max()
+            .map_or(0, |&dof| dof + 1)
+    }
+
+    /// Get the operator of the problem
+    pub fn get_operator(&self) -> SupgOperator<'_, DataType, ElementT> {
+        SupgOperator::new(
+            self.element,
+            self.diffusivity,
+            &*self.velocity,
+            self.stabilized,
+        )
+    }
+
+    /// Get the Dirichlet constraints imposed by the groups of facets
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the constraints or an error if a facet is out of the block
+    pub fn get_constraints(&self) -> Result<Constraints<DataType>, Error> {
+        let groups: Vec<&FacetGroup> = self.dirichlet.iter().map(|(group, _)| group).collect();
+        compute_dirichlet_constraints(
+            self.element,
+            self.block,
+            self.reference_facets,
+            &groups,
+            |g, x| (self.dirichlet[g].1)(x),
+        )
+    }
+
+    /// Assemble the load vector of the source at a time tested against the stabilized test
+    /// functions, without constraints
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the load or an error if a cell is degenerate
+    pub fn assemble_load(&self, time: DataType) -> Result<Vec<DataType>, Error> {
+        let operator = self.get_operator();
+        let n = self.element.get_shape_basis().get_number_of_bases();
+        let mut load = vec![DataType::zero(); self.get_number_of_dofs()];
+        let mut workspace = Workspace::new();
+        for cell in 0..self.block.get_number_of_cells() {
+            let geometry = self.block.get_cell_coordinates(cell);
+            let embedding = geometry.len() / n;
+            let [_, advection, taus, weights] = operator
+                .compute_streamline_points(geometry, &mut workspace)
+                .ok_or(Error::DegenerateCell { cell })?;
+            for (((shapes, point_advection), &tau), &weight) in self
+                .element
+                .get_shapes_for_integration()
+                .chunks(n)
+                .zip(advection.chunks(n))
+                .zip(taus)
+                .zip(weights)
+            {
+                let mut point = vec![DataType::zero(); embedding];
+                for (node, &shape) in geometry.chunks(embedding).zip(shapes) {
+                    for (x, &coordinate) in point.iter_mut().zip(node) {
+                        *x = *x + shape * coordinate.into();
+                    }
+                }
+                let source = weight * (self.source)(time, &point);
+                for ((&dof, &shape), &a) in self
+                    .block
+                    .get_cell_dofs(cell)
+                    .iter()
+                    .zip(shapes)
+                    .zip(point_advection)
+                {
+                    load[dof] = load[dof] + source * (shape + tau * a);
+                }
+            }
+        }
+        Ok(load)
+    }
+
+    /// Solve the steady problem, the source being taken at time zero
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the solution, named "u", or an error if the assembly failed, the
+    ///   solver is unknown or the solve did not converge
+    pub fn solve_steady(&self) -> Result<FEFunction<'a, CoordType, DataType, ElementT>, Error> {
+        let constraints = self.get_constraints()?;
+        let (matrix, mut rhs) = Assembler::new(self.get_number_of_dofs()).assemble_constrained(
+            &self.get_operator(),
+            self.block,
+            &constraints,
+        )?;
+        let mut load = self.assemble_load(DataType::zero())?;
+        constraints.condense(&mut load);
+        for (r, &l) in rhs.iter_mut().zip(&load) {
+            *r = *r + l;
+        }
+        let registry = SolverRegistry::new();
+        let solver = registry.build(&self.solver, &matrix)?;
+        let mut solution = vec![DataType::zero(); rhs.len()];
+        if !solver.solve(&rhs, &mut solution).is_converged() {
+            return Err(Error::NotConverged("Solve did not converge"));
+        }
+        constraints.distribute(&mut solution);
+        FEFunction::new("u", self.element, self.block, solution)
+    }
+
+    /// Solve the transient problem over a number of constant time steps
+    ///
+    /// # Arguments
+    ///
+    /// * `initial`: the initial solution as a function of the real coordinates
+    /// * `time_step`: the time step
+    /// * `number_of_steps`: the number of steps from time zero
+    /// * `observer`: called with the step, the time and the solution after each step and for the
+    ///   initial state at step zero
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the final solution, named "u", or an error if the assembly
+    ///   failed, a cell is degenerate or a step did not converge
+    pub fn solve_transient(
+        &self,
+        initial: impl Fn(&[DataType]) -> DataType,
+        time_step: DataType,
+        number_of_steps: usize,
+        mut observer: impl FnMut(usize, DataType, &FEFunction<'a, CoordType, DataType, ElementT>),
+    ) -> Result<FEFunction<'a, CoordType, DataType, ElementT>, Error> {
+        let constraints = self.get_constraints()?;
+        let assembler = Assembler::new(self.get_number_of_dofs());
+        let operator = self.get_operator();
+        let (mass, _) = assembler.assemble_constrained(
+            &operator.get_mass_operator(),
+            self.block,
+            &constraints,
+        )?;
+        let (stiffness, inhomogeneity) =
+            assembler.assemble_constrained(&operator, self.block, &constraints)?;
+        let mut values = vec![DataType::zero(); self.get_number_of_dofs()];
+        for cell in 0..self.block.get_number_of_cells() {
+            let geometry = self.block.get_cell_coordinates(cell);
+            let dofs = self.block.get_cell_dofs(cell);
+            for (&dof, x) in dofs
+                .iter()
+                .zip(geometry.chunks(geometry.len() / dofs.len()))
+            {
+                let point: Vec<DataType> = x.iter().map(|&x| x.into()).collect();
+                values[dof] = initial(&point);
+            }
+        }
+        constraints.distribute(&mut values);
+        let mut integrator = ImplicitIntegrator::new(self.scheme, &mass, &stiffness)?;
+        let mut time = DataType::zero();
+        let mut function = FEFunction::new("u", self.element, self.block, values)?;
+        observer(0, time, &function);
+        for step in 1..=number_of_steps {
+            let mut values = function.get_coefficients().to_vec();
+            let force = |t, f: &mut [DataType]| match self.assemble_load(t) {
+                Ok(mut load) => {
+                    constraints.condense(&mut load);
+                    for ((f, &l), &r) in f.iter_mut().zip(&load).zip(&inhomogeneity) {
+                        *f = l + r;
+                    }
+                }
+                Err(_) => f.fill(DataType::nan()),
+            };
+            integrator.step(force, time, time_step, &mut values)?;
+            constraints.distribute(&mut values);
+            if values.iter().any(|value| !value.is_finite()) {
+                return Err(Error::NotFinite("Solution is not finite"));
+            }
+            time = time + time_step;
+            function.set_coefficients(&values)?;
+            observer(step, time, &function);
+        }
+        Ok(function)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdvectionDiffusionProblem, SupgOperator, SupgOperatorBuilder};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::element::operator_trait::Operator;
+    use crate::post::boundary::FacetGroup;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+    use std::collections::HashMap;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_operator_builder() {
+        let (dofs, coords) = uniform_quadrilaterals(1);
+        let block = CellBlock::<f64, f64>::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let velocity = |_: &[f64]| vec![1.0, 0.5];
+        let operator = SupgOperatorBuilder::new(&element, &velocity)
+            .diffusivity(0.1)
+            .build()
+            .unwrap();
+        let reference = SupgOperator::new(&element, 0.1, &velocity, true);
+        let geometry = block.get_cell_coordinates(0);
+        assert_eq!(
+            operator.compute(geometry, &HashMap::new()),
+            reference.compute(geometry, &HashMap::new()),
+            "Incorrect built operator"
+        );
+        assert!(
+            SupgOperatorBuilder::new(&element, &velocity)
+                .diffusivity(0.0)
+                .build()
+                .is_err(),
+            "Null diffusivity accepted"
+        );
+    }
+
+    /// Boundary layer of `-κ u'' + u' = 0` on the unit square, `u = 0` on the left and `u = 1` on
+    /// the right side, of solution `(exp(x / κ) - 1) / (exp(1 / κ) - 1)`
+    #[test]
+    fn test_boundary_layer() {
+        let (dofs, coords) = uniform_quadrilaterals(10);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let kappa = 0.01;
+        let mut problem =
+            AdvectionDiffusionProblem::new(&element, &block, &facets, kappa, |_| vec![1.0, 0.0])
+                .unwrap();
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("inflow", &facets, &block, |x| x[0] < 1e-12),
+            |_| 0.0,
+        );
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("outflow", &facets, &block, |x| x[0] > 1.0 - 1e-12),
+            |_| 1.0,
+        );
+        let u = problem.solve_steady().unwrap();
+        let exact = |x: f64| ((x / kappa).exp() - 1.0) / ((1.0 / kappa).exp() - 1.0);
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            assert!(
+                (u.get_coefficients()[dof] - exact(x[0])).abs() < 1e-6,
+                "Stabilized solution not nodally exact"
+            );
+        }
+        problem.set_stabilization(false);
+        let galerkin = problem.solve_steady().unwrap();
+        assert!(
+            galerkin.get_coefficients().iter().any(|&v| v < -0.1),
+            "Galerkin solution does not oscillate"
+        );
+    }
+
+    /// Rotating hill advected by the rigid rotation about the centre of the unit square during a
+    /// quarter of a turn
+    #[test]
+    fn test_rotating_hill() {
+        let n = 16;
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let velocity = |x: &[f64]| vec![-2.0 * PI * (x[1] - 0.5), 2.0 * PI * (x[0] - 0.5)];
+        let mut problem =
+            AdvectionDiffusionProblem::new(&element, &block, &facets, 1e-6, velocity).unwrap();
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("boundary", &facets, &block, |_| true),
+            |_| 0.0,
+        );
+        let hill = |x: &[f64]| (-((x[0] - 0.75).powi(2) + (x[1] - 0.5).powi(2)) / 0.02).exp();
+        let mut masses = Vec::new();
+        let u = problem
+            .solve_transient(hill, 0.01, 25, |_, _, u| {
+                masses.push(u.integrate().unwrap())
+            })
+            .unwrap();
+        let (peak, value) =
+            u.get_coefficients()
+                .iter()
+                .enumerate()
+                .fold(
+                    (0, f64::MIN),
+                    |best, (dof, &v)| {
+                        if v > best.1 {
+                            (dof, v)
+                        } else {
+                            best
+                        }
+                    },
+                );
+        let x = [
+            (peak % (n + 1)) as f64 / n as f64,
+            (peak / (n + 1)) as f64 / n as f64,
+        ];
+        assert!(
+            (x[0] - 0.5).abs() < 0.1 && (x[1] - 0.75).abs() < 0.1 && value > 0.7,
+            "Hill not advected"
+        );
+        assert!(
+            (masses[25] - masses[0]).abs() < 0.03 * masses[0],
+            "Mass of the hill not conserved"
+        );
+    }
+}
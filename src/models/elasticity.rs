@@ -0,0 +1,840 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::element::operator_trait::Operator;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::post::boundary::FacetGroup;
+use crate::post::derived::DerivedField;
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::{
+    check_block, compute_shape_gradients, compute_shape_gradients_batch,
+    compute_shape_gradients_into, flag_degenerate_lanes, get_embedding_dimension, map_to_physical,
+    FEFunction,
+};
+use crate::solver::registry::{SolverConfiguration, SolverRegistry};
+use num::Float;
+use std::collections::HashMap;
+
+/// Vector data of the problem given as a function of the real coordinates
+type VectorFunction<'a, DataType> = Box<dyn Fn(&[DataType]) -> Vec<DataType> + 'a>;
+
+/// Kinematic and constitutive hypothesis of the elastic model
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElasticityHypothesis {
+    /// Two dimensional model of a body with no deformation out of the plane
+    PlaneStrain,
+    /// Two dimensional model of a thin plate with no stress out of the plane
+    PlaneStress,
+    /// Three dimensional model
+    ThreeDimensional,
+}
+
+impl ElasticityHypothesis {
+    /// Get the dimension of the displacement
+    pub fn get_dimension(&self) -> usize {
+        match self {
+            ElasticityHypothesis::ThreeDimensional => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// Isotropic linear elastic material
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IsotropicMaterial<DataType> {
+    young_modulus: DataType,
+    poisson_ratio: DataType,
+}
+
+impl<DataType: Float> IsotropicMaterial<DataType> {
+    /// Constructor
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the material or an error if the Young modulus is not positive or
+    ///   the Poisson ratio is not in `(-1, 1/2)`
+    pub fn new(
+        young_modulus: DataType,
+        poisson_ratio: DataType,
+    ) -> Result<IsotropicMaterial<DataType>, Error> {
+        let half = DataType::from(0.5).unwrap();
+        if young_modulus <= DataType::zero()
+            || poisson_ratio <= -DataType::one()
+            || poisson_ratio >= half
+        {
+            return Err(Error::InvalidArgument(
+                "Material parameters are not admissible",
+            ));
+        }
+        Ok(IsotropicMaterial {
+            young_modulus,
+            poisson_ratio,
+        })
+    }
+
+    /// Get the Young modulus
+    pub fn get_young_modulus(&self) -> DataType {
+        self.young_modulus
+    }
+
+    /// Get the Poisson ratio
+    pub fn get_poisson_ratio(&self) -> DataType {
+        self.poisson_ratio
+    }
+
+    /// Get the Lamé coefficients `(λ, μ)` of a hypothesis, the first coefficient being the reduced
+    /// `2 λ μ / (λ + 2 μ)` in plane stress
+    pub fn get_lame_coefficients(&self, hypothesis: ElasticityHypothesis) -> (DataType, DataType) {
+        let one = DataType::one();
+        let two = one + one;
+        let (e, nu) = (self.young_modulus, self.poisson_ratio);
+        let mu = e / (two * (one + nu));
+        let lambda = e * nu / ((one + nu) * (one - two * nu));
+        match hypothesis {
+            ElasticityHypothesis::PlaneStress => (two * lambda * mu / (lambda + two * mu), mu),
+            _ => (lambda, mu),
+        }
+    }
+}
+
+/// Stiffness matrix of small strain linear isotropic elasticity computed with the shape functions
+/// of an element
+///
+/// # Explanation
+///
+/// The dofs of the cells interleave the components of the displacement at each node, the dof of
+/// component `i` of node `a` being `a * dimension + i`. The local matrix of a degenerate cell holds
+/// NaN values.
+pub struct ElasticityOperator<'a, DataType, ElementT> {
+    element: &'a ElementT,
+    dimension: usize,
+    lambda: DataType,
+    mu: DataType,
+}
+
+impl<'a, DataType: Float, ElementT> ElasticityOperator<'a, DataType, ElementT> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `material`: the material of the cells
+    /// * `hypothesis`: the hypothesis of the model
+    pub fn new(
+        element: &'a ElementT,
+        material: &IsotropicMaterial<DataType>,
+        hypothesis: ElasticityHypothesis,
+    ) -> ElasticityOperator<'a, DataType, ElementT> {
+        let (lambda, mu) = material.get_lame_coefficients(hypothesis);
+        ElasticityOperator {
+            element,
+            dimension: hypothesis.get_dimension(),
+            lambda,
+            mu,
+        }
+    }
+}
+
+impl<CoordType, DataType, ElementT> Operator<CoordType, DataType>
+    for ElasticityOperator<'_, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    type ElementT = ElementT;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> Vec<DataType> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        _data: &HashMap<String, &[DataType]>,
+        workspace: &mut Workspace<DataType>,
+        local: &mut Vec<DataType>,
+    ) {
+        let d = self.dimension;
+        let nbases = self.element.get_shape_basis().get_number_of_bases();
+        let nips = self.element.get_integrator().get_number_of_points();
+        let n = nbases * d;
+        let [gradients, weights] = workspace.get_buffers([nips * geometry.len(), nips]);
+        local.clear();
+        if !compute_shape_gradients_into(self.element, geometry, gradients, weights) {
+            local.resize(n * n, DataType::nan());
+            return;
+        }
+        local.resize(n * n, DataType::zero());
+        for (point_gradients, &weight) in gradients.chunks(geometry.len()).zip(weights.iter()) {
+            for (a, ga) in point_gradients.chunks(d).enumerate() {
+                for (b, gb) in point_gradients.chunks(d).enumerate() {
+                    let product = ga
+                        .iter()
+                        .zip(gb)
+                        .fold(DataType::zero(), |sum, (&x, &y)| sum + x * y);
+                    for i in 0..d {
+                        for j in 0..d {
+                            let mut value = self.lambda * ga[i] * gb[j] + self.mu * ga[j] * gb[i];
+                            if i == j {
+                                value = value + self.mu * product;
+                            }
+                            let entry = (a * d + i) * n + b * d + j;
+                            local[entry] = local[entry] + weight * value;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn compute_batch<const LANES: usize>(
+        &self,
+        geometry: &[[CoordType; LANES]],
+        _data: &HashMap<String, &[[DataType; LANES]]>,
+    ) -> Vec<[DataType; LANES]> {
+        let d = self.dimension;
+        let nbases = self.element.get_shape_basis().get_number_of_bases();
+        let nips = self.element.get_integrator().get_number_of_points();
+        let n = nbases * d;
+        let mut gradients = vec![[DataType::zero(); LANES]; nips * geometry.len()];
+        let mut weights = vec![[DataType::zero(); LANES]; nips];
+        let regular =
+            compute_shape_gradients_batch(self.element, geometry, &mut gradients, &mut weights);
+        let mut local = vec![[DataType::zero(); LANES]; n * n];
+        for (point_gradients, weight) in gradients.chunks(geometry.len()).zip(&weights) {
+            for (a, ga) in point_gradients.chunks(d).enumerate() {
+                for (b, gb) in point_gradients.chunks(d).enumerate() {
+                    let mut product = [DataType::zero(); LANES];
+                    for (x, y) in ga.iter().zip(gb) {
+                        for lane in 0..LANES {
+                            product[lane] = product[lane] + x[lane] * y[lane];
+                        }
+                    }
+                    for i in 0..d {
+                        for j in 0..d {
+                            let entry = &mut local[(a * d + i) * n + b * d + j];
+                            for lane in 0..LANES {
+                                let mut value = self.lambda * ga[i][lane] * gb[j][lane]
+                                    + self.mu * ga[j][lane] * gb[i][lane];
+                                if i == j {
+                                    value = value + self.mu * product[lane];
+                                }
+                                entry[lane] = entry[lane] + weight[lane] * value;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        flag_degenerate_lanes(&mut local, regular);
+        local
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Builder of an ElasticityOperator
+///
+/// # Explanation
+///
+/// The material is required. The hypothesis defaults to plane strain on two dimensional elements
+/// and to the three dimensional model on three dimensional ones, and should match the dimension
+/// of the element.
+pub struct ElasticityOperatorBuilder<'a, DataType, ElementT> {
+    element: &'a ElementT,
+    material: Option<IsotropicMaterial<DataType>>,
+    hypothesis: Option<ElasticityHypothesis>,
+}
+
+impl<'a, DataType: Float, ElementT> ElasticityOperatorBuilder<'a, DataType, ElementT> {
+    /// Constructor of a builder without material
+    pub fn new(element: &'a ElementT) -> ElasticityOperatorBuilder<'a, DataType, ElementT> {
+        ElasticityOperatorBuilder {
+            element,
+            material: None,
+            hypothesis: None,
+        }
+    }
+
+    /// Set the material of the cells
+    pub fn material(mut self, material: IsotropicMaterial<DataType>) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Set the hypothesis of the model
+    pub fn hypothesis(mut self, hypothesis: ElasticityHypothesis) -> Self {
+        self.hypothesis = Some(hypothesis);
+        self
+    }
+
+    /// Build the operator
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the operator or an error if the material is missing or the
+    ///   hypothesis does not match the dimension of the element
+    pub fn build<CoordType>(&self) -> Result<ElasticityOperator<'a, DataType, ElementT>, Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let material = self.material.ok_or(Error::InvalidArgument(
+            "Elasticity operator built without a material",
+        ))?;
+        let dimension = self.element.get_shape_basis().get_dimension();
+        let hypothesis = match (self.hypothesis, dimension) {
+            (Some(hypothesis), _) => hypothesis,
+            (None, 2) => ElasticityHypothesis::PlaneStrain,
+            (None, _) => ElasticityHypothesis::ThreeDimensional,
+        };
+        if hypothesis.get_dimension() != dimension {
+            return Err(Error::InvalidArgument(
+                "Elasticity hypothesis does not match the dimension of the element",
+            ));
+        }
+        Ok(ElasticityOperator::new(self.element, &material, hypothesis))
+    }
+}
+
+/// Displacement and recovered stress of an elastic problem
+pub struct ElasticitySolution<'a, CoordType, DataType, ElementT> {
+    displacement: Vec<FEFunction<'a, CoordType, DataType, ElementT>>,
+    stress: Vec<FEFunction<'a, CoordType, DataType, ElementT>>,
+}
+
+impl<'a, CoordType, DataType, ElementT> ElasticitySolution<'a, CoordType, DataType, ElementT> {
+    /// Get the components of the displacement, named `displacement_<component>`
+    pub fn get_displacement(&self) -> &[FEFunction<'a, CoordType, DataType, ElementT>] {
+        &self.displacement
+    }
+
+    /// Get the components of the stress recovered at the nodes in Voigt ordering, `xx, yy, xy` in
+    /// 2D and `xx, yy, zz, yz, xz, xy` in 3D, named `stress_<component>`
+    pub fn get_stress(&self) -> &[FEFunction<'a, CoordType, DataType, ElementT>] {
+        &self.stress
+    }
+}
+
+/// Ready-made small strain linear elasticity problem with body forces, tractions and supports
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the solution is encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// The cells are given by a block of scalar dofs, one per node, from which the problem builds the
+/// block of the interleaved displacement dofs, the dof of component `i` of node `a` being `a *
+/// dimension + i`. The supports impose components of the displacement at the nodes of the facets
+/// of their groups, the tractions and body forces are integrated with the quadrature of the facets
+/// and the integration rule of the element respectively. The stress is recovered at the nodes by
+/// superconvergent patch recovery. The linear system is solved by conjugate gradients
+/// preconditioned by Jacobi unless configured otherwise.
+pub struct ElasticityProblem<'a, CoordType, DataType, ElementT> {
+    element: &'a ElementT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    reference_facets: &'a ReferenceFacets<CoordType>,
+    material: IsotropicMaterial<DataType>,
+    hypothesis: ElasticityHypothesis,
+    body_force: VectorFunction<'a, DataType>,
+    supports: Vec<(FacetGroup, Vec<usize>, VectorFunction<'a, DataType>)>,
+    tractions: Vec<(FacetGroup, VectorFunction<'a, DataType>)>,
+    solver: SolverConfiguration,
+}
+
+impl<'a, CoordType, DataType, ElementT> ElasticityProblem<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar + Float,
+    DataType: Scalar + Float + From<CoordType> + 'static,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor of the problem without loads nor supports
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells of the domain with one dof per node
+    /// * `reference_facets`: the facets of the reference element
+    /// * `material`: the material of the body
+    /// * `hypothesis`: the hypothesis of the model
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the problem or an error if the block does not match the element
+    ///   or the embedding dimension does not match the hypothesis
+    pub fn new(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        reference_facets: &'a ReferenceFacets<CoordType>,
+        material: IsotropicMaterial<DataType>,
+        hypothesis: ElasticityHypothesis,
+    ) -> Result<ElasticityProblem<'a, CoordType, DataType, ElementT>, Error> {
+        check_block(element, block)?;
+        let dimension = hypothesis.get_dimension();
+        if get_embedding_dimension(element, block) != dimension {
+            return Err(Error::DimensionMismatch {
+                context: "Embedding dimension does not match the hypothesis",
+                expected: dimension,
+                actual: get_embedding_dimension(element, block),
+            });
+        }
+        Ok(ElasticityProblem {
+            element,
+            block,
+            reference_facets,
+            material,
+            hypothesis,
+            body_force: Box::new(move |_| vec![DataType::zero(); dimension]),
+            supports: Vec::new(),
+            tractions: Vec::new(),
+            solver: SolverConfiguration::new("cg", "jacobi"),
+        })
+    }
+
+    /// Set the body force per unit volume as a function of the real coordinates
+    pub fn set_body_force(&mut self, force: impl Fn(&[DataType]) -> Vec<DataType> + 'a) {
+        self.body_force = Box::new(force);
+    }
+
+    /// Clamp a group of facets, fixing all the components of the displacement to zero
+    pub fn add_fixed_support(&mut self, group: FacetGroup) {
+        let dimension = self.hypothesis.get_dimension();
+        self.supports.push((
+            group,
+            (0..dimension).collect(),
+            Box::new(move |_| vec![DataType::zero(); dimension]),
+        ));
+    }
+
+    /// Impose some components of the displacement on a group of facets, as a roller support
+    ///
+    /// # Arguments
+    ///
+    /// * `group`: the facets of the support
+    /// * `components`: the imposed components
+    /// * `displacement`: the displacement as a function of the real coordinates, of which only the
+    ///   imposed components are used
+    pub fn add_displacement(
+        &mut self,
+        group: FacetGroup,
+        components: &[usize],
+        displacement: impl Fn(&[DataType]) -> Vec<DataType> + 'a,
+    ) {
+        self.supports
+            .push((group, components.to_vec(), Box::new(displacement)));
+    }
+
+    /// Apply a traction, the force per unit surface, on a group of facets
+    pub fn add_traction(
+        &mut self,
+        group: FacetGroup,
+        traction: impl Fn(&[DataType]) -> Vec<DataType> + 'a,
+    ) {
+        self.tractions.push((group, Box::new(traction)));
+    }
+
+    /// Set the selection of the solver and preconditioner of the registry
+    pub fn set_solver(&mut self, solver: SolverConfiguration) {
+        self.solver = solver;
+    }
+
+    /// Get the element describing the cells
+    pub fn get_element(&self) -> &'a ElementT {
+        self.element
+    }
+
+    /// Get the cells of the domain with one dof per node
+    pub fn get_block(&self) -> &'a CellBlock<'a, CoordType, DataType> {
+        self.block
+    }
+
+    /// Get the facets of the reference element
+    pub fn get_reference_facets(&self) -> &'a ReferenceFacets<CoordType> {
+        self.reference_facets
+    }
+
+    /// Get the material of the body
+    pub fn get_material(&self) -> &IsotropicMaterial<DataType> {
+        &self.material
+    }
+
+    /// Get the hypothesis of the model
+    pub fn get_hypothesis(&self) -> ElasticityHypothesis {
+        self.hypothesis
+    }
+
+    /// Get the number of nodes of the cells
+    pub fn get_number_of_nodes(&self) -> usize {
+        self.block
+            .get_connectivity()
+            .iter()
+            .max()
+            .map_or(0, |&dof| dof + 1)
+    }
+
+    /// Get the number of displacement dofs
+    pub fn get_number_of_dofs(&self) -> usize {
+        self.get_number_of_nodes() * self.hypothesis.get_dimension()
+    }
+
+    /// Get the interleaved displacement dofs of the cells, flattened cell after cell
+    pub fn get_displacement_dofs(&self) -> Vec<usize> {
+        let dimension = self.hypothesis.get_dimension();
+        self.block
+            .get_connectivity()
+            .iter()
+            .flat_map(|&node| (0..dimension).map(move |i| node * dimension + i))
+            .collect()
+    }
+
+    /// Get the constraints imposed by the supports on the displacement dofs
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the constraints or an error if a facet is out of the block or a
+    ///   component out of the dimension
+    pub fn get_constraints(&self) -> Result<Constraints<DataType>, Error> {
+        let dimension = self.hypothesis.get_dimension();
+        let mut constraints = Constraints::new();
+        for (group, components, displacement) in &self.supports {
+            if let Some(&component) = components.iter().find(|&&c| c >= dimension) {
+                return Err(Error::OutOfBounds {
+                    context: "Component out of the dimension",
+                    index: component,
+                    bound: dimension,
+                });
+            }
+            for &(cell, facet) in group.get_facets() {
+                self.reference_facets.check_facet(self.block, cell, facet)?;
+                let nodes = self.block.get_cell_dofs(cell);
+                let coordinates = self.block.get_cell_coordinates(cell);
+                for &node in self.reference_facets.get_facet(facet) {
+                    let point: Vec<DataType> = coordinates
+                        [node * dimension..(node + 1) * dimension]
+                        .iter()
+                        .map(|&x| x.into())
+                        .collect();
+                    let values = displacement(&point);
+                    for &c in components {
+                        let dof = nodes[node] * dimension + c;
+                        if !constraints.is_constrained(dof) {
+                            constraints.add_dirichlet(dof, values[c])?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// Assemble the load vector of the body force and of the tractions without constraints
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the load or an error if a cell or a facet is degenerate or a
+    ///   force does not match the dimension
+    pub fn assemble_load(&self) -> Result<Vec<DataType>, Error> {
+        let dimension = self.hypothesis.get_dimension();
+        let basis = self.element.get_shape_basis();
+        let nbases = basis.get_number_of_bases();
+        let mut load = vec![DataType::zero(); self.get_number_of_dofs()];
+        let mut add = |nodes: &[usize], shapes: &[DataType], force: Vec<DataType>, scale| {
+            if force.len() != dimension {
+                return Err(Error::DimensionMismatch {
+                    context: "Force does not match the dimension",
+                    expected: dimension,
+                    actual: force.len(),
+                });
+            }
+            for (&node, &shape) in nodes.iter().zip(shapes) {
+                for (c, &f) in force.iter().enumerate() {
+                    let dof = node * dimension + c;
+                    load[dof] = load[dof] + scale * shape * f;
+                }
+            }
+            Ok(())
+        };
+        for cell in 0..self.block.get_number_of_cells() {
+            let nodes = self.block.get_cell_dofs(cell);
+            let points =
+                compute_shape_gradients(self.element, self.block.get_cell_coordinates(cell))
+                    .ok_or(Error::DegenerateCell { cell })?;
+            for (shapes, (_, weight)) in self
+                .element
+                .get_shapes_for_integration()
+                .chunks(nbases)
+                .zip(points)
+            {
+                let point = map_to_physical(self.element, self.block, cell, shapes);
+                add(nodes, shapes, (self.body_force)(&point), weight)?;
+            }
+        }
+        for (group, traction) in &self.tractions {
+            for &(cell, facet) in group.get_facets() {
+                self.reference_facets.check_facet(self.block, cell, facet)?;
+                let nodes = self.block.get_cell_dofs(cell);
+                for point in self.reference_facets.compute_quadrature(
+                    self.element,
+                    self.block,
+                    cell,
+                    facet,
+                )? {
+                    add(nodes, &point.shapes, traction(&point.point), point.weight)?;
+                }
+            }
+        }
+        Ok(load)
+    }
+
+    /// Solve the problem
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the displacement and the recovered stress, or an error if the
+    ///   assembly failed, the solver is unknown or the solve did not converge
+    pub fn solve(&self) -> Result<ElasticitySolution<'a, CoordType, DataType, ElementT>, Error> {
+        let dimension = self.hypothesis.get_dimension();
+        let constraints = self.get_constraints()?;
+        let operator = ElasticityOperator::new(self.element, &self.material, self.hypothesis);
+        let (matrix, mut rhs) = self.assemble_constrained(&operator, &constraints)?;
+        let mut load = self.assemble_load()?;
+        constraints.condense(&mut load);
+        for (r, &l) in rhs.iter_mut().zip(&load) {
+            *r = *r + l;
+        }
+        let registry = SolverRegistry::new();
+        let solver = registry.build(&self.solver, &matrix)?;
+        let mut solution = vec![DataType::zero(); rhs.len()];
+        if !solver.solve(&rhs, &mut solution).is_converged() {
+            return Err(Error::NotConverged("Solve did not converge"));
+        }
+        constraints.distribute(&mut solution);
+        let displacement = (0..dimension)
+            .map(|c| {
+                let coefficients = solution
+                    .iter()
+                    .skip(c)
+                    .step_by(dimension)
+                    .copied()
+                    .collect();
+                FEFunction::new(
+                    &format!("displacement_{}", c),
+                    self.element,
+                    self.block,
+                    coefficients,
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let (lambda, mu) = self.material.get_lame_coefficients(self.hypothesis);
+        let components: Vec<&FEFunction<'a, CoordType, DataType, ElementT>> =
+            displacement.iter().collect();
+        let stress = DerivedField::stress("stress", dimension, lambda, mu)?.project(&components)?;
+        Ok(ElasticitySolution {
+            displacement,
+            stress,
+        })
+    }
+
+    /// Assemble an operator on the interleaved displacement dofs, condensing a set of constraints
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the condensed matrix along with the contribution of the
+    ///   inhomogeneities to the right hand side, or an error if the inputs are not consistent
+    pub(crate) fn assemble_constrained<OperatorT: Operator<CoordType, DataType>>(
+        &self,
+        operator: &OperatorT,
+        constraints: &Constraints<DataType>,
+    ) -> Result<(CsrMatrix<DataType>, Vec<DataType>), Error> {
+        let displacement_dofs = self.get_displacement_dofs();
+        let vector_block = CellBlock::new(
+            self.block.get_dofs_per_cell() * self.hypothesis.get_dimension(),
+            &displacement_dofs,
+            self.block.get_coordinates(),
+        )?;
+        Assembler::new(self.get_number_of_dofs()).assemble_constrained(
+            operator,
+            &vector_block,
+            constraints,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ElasticityHypothesis, ElasticityOperator, ElasticityOperatorBuilder, ElasticityProblem,
+        IsotropicMaterial,
+    };
+    use crate::assembly::cell_block::CellBlock;
+    use crate::element::operator_trait::Operator;
+    use crate::post::boundary::FacetGroup;
+    use crate::solver::registry::SolverConfiguration;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+
+    use std::collections::HashMap;
+
+    const TOL: f64 = 1e-9;
+
+    #[test]
+    fn test_material() {
+        let material = IsotropicMaterial::new(2.5_f64, 0.25).unwrap();
+        let (lambda, mu) = material.get_lame_coefficients(ElasticityHypothesis::PlaneStrain);
+        assert!(
+            (lambda - 1.0).abs() < TOL && (mu - 1.0).abs() < TOL,
+            "Incorrect Lamé coefficients"
+        );
+        let (lambda, _) = material.get_lame_coefficients(ElasticityHypothesis::PlaneStress);
+        assert!(
+            (lambda - 2.0 / 3.0).abs() < TOL,
+            "Incorrect plane stress coefficient"
+        );
+        assert!(
+            IsotropicMaterial::new(1.0, 0.5).is_err(),
+            "Incompressible material accepted"
+        );
+    }
+
+    /// Uniaxial tension of the unit square under a traction `σ` on its right side, held by rollers
+    /// on its left and bottom sides
+    fn check_tension(hypothesis: ElasticityHypothesis, ex: f64, ey: f64) {
+        let n = 3;
+        let sigma = 2.0;
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let material = IsotropicMaterial::new(2.5, 0.25).unwrap();
+        let mut problem =
+            ElasticityProblem::new(&element, &block, &facets, material, hypothesis).unwrap();
+        let group = |name, predicate: fn(&[f64]) -> bool| {
+            FacetGroup::from_boundary(name, &facets, &block, predicate)
+        };
+        problem.add_displacement(group("left", |x| x[0] < 1e-12), &[0], |_| vec![0.0; 2]);
+        problem.add_displacement(group("bottom", |x| x[1] < 1e-12), &[1], |_| vec![0.0; 2]);
+        problem.add_traction(group("right", |x| x[0] > 1.0 - 1e-12), move |_| {
+            vec![sigma, 0.0]
+        });
+        let mut solver = SolverConfiguration::new("cg", "jacobi");
+        solver.set_parameter("relative_tolerance", 1e-14);
+        problem.set_solver(solver);
+        assert_eq!(problem.get_number_of_dofs(), 32, "Incorrect number of dofs");
+        let solution = problem.solve().unwrap();
+        let displacement = solution.get_displacement();
+        assert_eq!(
+            displacement[1].get_name(),
+            "displacement_1",
+            "Incorrect name"
+        );
+        for (&node, x) in dofs.iter().zip(coords.chunks(2)) {
+            assert!(
+                (displacement[0].get_coefficients()[node] - ex * x[0]).abs() < TOL
+                    && (displacement[1].get_coefficients()[node] - ey * x[1]).abs() < TOL,
+                "Incorrect displacement"
+            );
+        }
+        for (component, expected) in solution.get_stress().iter().zip([sigma, 0.0, 0.0]) {
+            assert!(
+                component
+                    .get_coefficients()
+                    .iter()
+                    .all(|&s| (s - expected).abs() < TOL),
+                "Incorrect stress"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_batch() {
+        let (dofs, mut coords) = uniform_quadrilaterals(3);
+        for x in coords.chunks_mut(2) {
+            x[1] += 0.1 * x[0] * x[0];
+        }
+        let block = CellBlock::<f64, f64>::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let material = IsotropicMaterial::new(2.5, 0.25).unwrap();
+        let operator =
+            ElasticityOperator::new(&element, &material, ElasticityHypothesis::PlaneStrain);
+        for cell in 0..block.get_number_of_cells() {
+            let geometry = block.get_cell_coordinates(cell);
+            let reference = operator.compute(geometry, &HashMap::new());
+            let batch: Vec<[f64; 8]> = geometry.iter().map(|&x| [x; 8]).collect();
+            let local = operator.compute_batch(&batch, &HashMap::new());
+            assert_eq!(local.len(), 64, "Incorrect local matrix size");
+            for (r, entry) in reference.iter().zip(&local) {
+                assert!(
+                    entry.iter().all(|b| (r - b).abs() < TOL),
+                    "Incorrect batched value"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_operator_builder() {
+        let (dofs, coords) = uniform_quadrilaterals(1);
+        let element = BilinearQuadrilateralElement::new();
+        let material = IsotropicMaterial::new(2.5, 0.25).unwrap();
+        let operator = ElasticityOperatorBuilder::new(&element)
+            .material(material)
+            .build()
+            .unwrap();
+        let reference =
+            ElasticityOperator::new(&element, &material, ElasticityHypothesis::PlaneStrain);
+        let block = CellBlock::<f64, f64>::new(4, &dofs, &coords).unwrap();
+        let geometry = block.get_cell_coordinates(0);
+        assert_eq!(
+            operator.compute(geometry, &HashMap::new()),
+            reference.compute(geometry, &HashMap::new()),
+            "Incorrect default hypothesis"
+        );
+        assert!(
+            ElasticityOperatorBuilder::<f64, _>::new(&element)
+                .build()
+                .is_err(),
+            "Missing material accepted"
+        );
+        assert!(
+            ElasticityOperatorBuilder::new(&element)
+                .material(material)
+                .hypothesis(ElasticityHypothesis::ThreeDimensional)
+                .build()
+                .is_err(),
+            "Mismatched hypothesis accepted"
+        );
+    }
+
+    #[test]
+    fn test_tension() {
+        let (e, nu, sigma) = (2.5, 0.25, 2.0);
+        check_tension(
+            ElasticityHypothesis::PlaneStress,
+            sigma / e,
+            -nu * sigma / e,
+        );
+        check_tension(
+            ElasticityHypothesis::PlaneStrain,
+            (1.0 - nu * nu) * sigma / e,
+            -nu * (1.0 + nu) * sigma / e,
+        );
+    }
+}
@@ -0,0 +1,660 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::assembly::constraints::Constraints;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::element::operator_trait::Operator;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::models::elasticity::{ElasticityOperator, ElasticityProblem};
+use crate::post::boundary::FacetGroup;
+use crate::post::function::{compute_shape_gradients_into, locate_degenerate_cell, FEFunction};
+use crate::time::explicit::{ButcherTableau, ExplicitRungeKutta};
+use crate::time::newmark::{NewmarkIntegrator, NewmarkParameters};
+use num::Float;
+use std::collections::HashMap;
+
+/// Time dependence of the loads
+type LoadHistory<'a, DataType> = Box<dyn Fn(DataType) -> DataType + 'a>;
+
+/// Vector mass matrix of `ρ u` computed with the shape functions of an element
+///
+/// # Explanation
+///
+/// The dofs of the cells interleave the components of the field at each node as for the
+/// ElasticityOperator. The local matrix of a degenerate cell holds NaN values.
+pub struct VectorMassOperator<'a, DataType, ElementT> {
+    element: &'a ElementT,
+    dimension: usize,
+    density: DataType,
+}
+
+impl<'a, DataType, ElementT> VectorMassOperator<'a, DataType, ElementT> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `dimension`: the number of components of the field
+    /// * `density`: the density `ρ`
+    pub fn new(
+        element: &'a ElementT,
+        dimension: usize,
+        density: DataType,
+    ) -> VectorMassOperator<'a, DataType, ElementT> {
+        VectorMassOperator {
+            element,
+            dimension,
+            density,
+        }
+    }
+}
+
+impl<CoordType, DataType, ElementT> Operator<CoordType, DataType>
+    for VectorMassOperator<'_, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    type ElementT = ElementT;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> Vec<DataType> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        _data: &HashMap<String, &[DataType]>,
+        workspace: &mut Workspace<DataType>,
+        local: &mut Vec<DataType>,
+    ) {
+        let d = self.dimension;
+        let nbases = self.element.get_shape_basis().get_number_of_bases();
+        let nips = self.element.get_integrator().get_number_of_points();
+        let n = nbases * d;
+        let [gradients, weights] = workspace.get_buffers([nips * geometry.len(), nips]);
+        local.clear();
+        if !compute_shape_gradients_into(self.element, geometry, gradients, weights) {
+            local.resize(n * n, DataType::nan());
+            return;
+        }
+        local.resize(n * n, DataType::zero());
+        for (shapes, &weight) in self
+            .element
+            .get_shapes_for_integration()
+            .chunks(nbases)
+            .zip(weights.iter())
+        {
+            for a in 0..nbases {
+                for b in 0..nbases {
+                    let value = weight * self.density * shapes[a] * shapes[b];
+                    for i in 0..d {
+                        let entry = (a * d + i) * n + b * d + i;
+                        local[entry] = local[entry] + value;
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Time integration scheme of an elastodynamics problem
+#[derive(Clone, Debug)]
+pub enum DynamicsScheme<DataType> {
+    /// A scheme of the Newmark family, implicit unless it is the central difference scheme with a
+    /// lumped mass and no absorbing boundary
+    Newmark(NewmarkParameters<DataType>),
+    /// An explicit Runge-Kutta method on the first order system of the displacement and velocity,
+    /// solving with the lumped mass
+    Explicit(ButcherTableau<DataType>),
+}
+
+/// Solution of an elastodynamics problem
+pub struct ElastodynamicsSolution<'a, CoordType, DataType, ElementT> {
+    displacement: Vec<FEFunction<'a, CoordType, DataType, ElementT>>,
+    velocity: Vec<FEFunction<'a, CoordType, DataType, ElementT>>,
+}
+
+impl<'a, CoordType, DataType, ElementT> ElastodynamicsSolution<'a, CoordType, DataType, ElementT> {
+    /// Get one field per component of the displacement, named "displacement_c"
+    pub fn get_displacement(&self) -> &[FEFunction<'a, CoordType, DataType, ElementT>] {
+        &self.displacement
+    }
+
+    /// Get one field per component of the velocity, named "velocity_c"
+    pub fn get_velocity(&self) -> &[FEFunction<'a, CoordType, DataType, ElementT>] {
+        &self.velocity
+    }
+}
+
+/// Ready-made linear elastodynamics problem `ρ ü - div σ(u) = f(t)`
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the solution is encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// The body, its supports and its loads are those of an ElasticityProblem, the supports being
+/// constant in time and the loads scaled by a load history, one by default. The absorbing
+/// boundaries are the dashpots of Lysmer and Kuhlemeyer, the traction `-ρ (c_p (v . n) n + c_s
+/// (v - (v . n) n))` with the speeds `c_p = sqrt((λ + 2μ) / ρ)` and `c_s = sqrt(μ / ρ)` of the
+/// pressure and shear waves, which absorbs the waves of normal incidence. Their damping matrix is
+/// integrated with the vertices of the facets.
+///
+/// The mass matrix is consistent unless lumped by the sums of its rows, the explicit schemes
+/// always using the lumped mass. The state is advanced with a NewmarkIntegrator, by default with
+/// the unconditionally stable average acceleration scheme, or with an ExplicitRungeKutta whose
+/// time step is limited by the time the pressure waves take to cross the smallest cell.
+pub struct ElastodynamicsProblem<'a, CoordType, DataType, ElementT> {
+    elasticity: ElasticityProblem<'a, CoordType, DataType, ElementT>,
+    density: DataType,
+    load_history: LoadHistory<'a, DataType>,
+    absorbing: Vec<FacetGroup>,
+    lumped: bool,
+    scheme: DynamicsScheme<DataType>,
+}
+
+impl<'a, CoordType, DataType, ElementT> ElastodynamicsProblem<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar + Float,
+    DataType: Scalar + Float + From<CoordType> + 'static,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `elasticity`: the body with its supports and loads
+    /// * `density`: the density `ρ` of the body
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the problem or an error if the density is not positive
+    pub fn new(
+        elasticity: ElasticityProblem<'a, CoordType, DataType, ElementT>,
+        density: DataType,
+    ) -> Result<ElastodynamicsProblem<'a, CoordType, DataType, ElementT>, Error> {
+        if density <= DataType::zero() {
+            return Err(Error::InvalidArgument("Density should be positive"));
+        }
+        Ok(ElastodynamicsProblem {
+            elasticity,
+            density,
+            load_history: Box::new(|_| DataType::one()),
+            absorbing: Vec::new(),
+            lumped: false,
+            scheme: DynamicsScheme::Newmark(NewmarkParameters::average_acceleration()),
+        })
+    }
+
+    /// Set the factor of the loads of the body as a function of the time
+    pub fn set_load_history(&mut self, history: impl Fn(DataType) -> DataType + 'a) {
+        self.load_history = Box::new(history);
+    }
+
+    /// Make a group of facets absorbing
+    pub fn add_absorbing_boundary(&mut self, group: FacetGroup) {
+        self.absorbing.push(group);
+    }
+
+    /// Set whether the mass matrix of the Newmark schemes is lumped
+    pub fn set_lumped_mass(&mut self, lumped: bool) {
+        self.lumped = lumped;
+    }
+
+    /// Set the time integration scheme
+    pub fn set_scheme(&mut self, scheme: DynamicsScheme<DataType>) {
+        self.scheme = scheme;
+    }
+
+    /// Get the underlying elasticity problem
+    pub fn get_elasticity(&self) -> &ElasticityProblem<'a, CoordType, DataType, ElementT> {
+        &self.elasticity
+    }
+
+    /// Get the speeds `(c_p, c_s)` of the pressure and shear waves
+    pub fn get_wave_speeds(&self) -> (DataType, DataType) {
+        let (lambda, mu) = self
+            .elasticity
+            .get_material()
+            .get_lame_coefficients(self.elasticity.get_hypothesis());
+        let two = DataType::one() + DataType::one();
+        (
+            ((lambda + two * mu) / self.density).sqrt(),
+            (mu / self.density).sqrt(),
+        )
+    }
+
+    /// Compute the kinetic and strain energies `(v^T M v / 2, u^T K u / 2)` of a state
+    ///
+    /// # Arguments
+    ///
+    /// * `u`: the interleaved displacement dofs
+    /// * `v`: the interleaved velocity dofs
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the energies or an error if the assembly failed
+    pub fn compute_energies(
+        &self,
+        u: &[DataType],
+        v: &[DataType],
+    ) -> Result<(DataType, DataType), Error> {
+        let constraints = Constraints::new();
+        let (mass, _) = self
+            .elasticity
+            .assemble_constrained(&self.get_mass_operator(), &constraints)?;
+        let (stiffness, _) = self
+            .elasticity
+            .assemble_constrained(&self.get_stiffness_operator(), &constraints)?;
+        let half = DataType::one() / (DataType::one() + DataType::one());
+        let energy = |matrix: &CsrMatrix<DataType>, x: &[DataType]| {
+            matrix
+                .apply(x)
+                .iter()
+                .zip(x)
+                .fold(DataType::zero(), |sum, (&y, &x)| sum + x * y)
+                * half
+        };
+        Ok((energy(&mass, v), energy(&stiffness, u)))
+    }
+
+    /// Solve the problem over a number of constant time steps
+    ///
+    /// # Arguments
+    ///
+    /// * `initial_displacement`: the initial displacement as a function of the real coordinates
+    /// * `initial_velocity`: the initial velocity as a function of the real coordinates
+    /// * `time_step`: the time step
+    /// * `number_of_steps`: the number of steps from time zero
+    /// * `observer`: called with the step, the time and the interleaved displacement and velocity
+    ///   dofs after each step and for the initial state at step zero
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the final displacement and velocity or an error if the assembly
+    ///   failed, a cell is degenerate or a step did not converge
+    pub fn solve(
+        &self,
+        initial_displacement: impl Fn(&[DataType]) -> Vec<DataType>,
+        initial_velocity: impl Fn(&[DataType]) -> Vec<DataType>,
+        time_step: DataType,
+        number_of_steps: usize,
+        mut observer: impl FnMut(usize, DataType, &[DataType], &[DataType]),
+    ) -> Result<ElastodynamicsSolution<'a, CoordType, DataType, ElementT>, Error> {
+        let constraints = self.elasticity.get_constraints()?;
+        let (mut mass, _) = self
+            .elasticity
+            .assemble_constrained(&self.get_mass_operator(), &constraints)?;
+        let (stiffness, inhomogeneity) = self
+            .elasticity
+            .assemble_constrained(&self.get_stiffness_operator(), &constraints)?;
+        let damping = self.assemble_damping(&constraints)?;
+        if mass
+            .get_values()
+            .iter()
+            .chain(stiffness.get_values())
+            .chain(damping.get_values())
+            .any(|value| !value.is_finite())
+        {
+            return Err(locate_degenerate_cell(
+                self.elasticity.get_element(),
+                self.elasticity.get_block(),
+            ));
+        }
+        let lumped = matches!(self.scheme, DynamicsScheme::Explicit(_));
+        if self.lumped || lumped {
+            mass = lump(&mass)?;
+        }
+        let mut load = self.elasticity.assemble_load()?;
+        constraints.condense(&mut load);
+        let force = |t: DataType, f: &mut [DataType]| {
+            let history = (self.load_history)(t);
+            for ((f, &l), &r) in f.iter_mut().zip(&load).zip(&inhomogeneity) {
+                *f = history * l + r;
+            }
+        };
+        let mut u = self.interpolate(initial_displacement)?;
+        let mut v = self.interpolate(initial_velocity)?;
+        let clear = |x: &mut [DataType]| {
+            x.iter_mut()
+                .enumerate()
+                .filter(|(dof, _)| constraints.is_constrained(*dof))
+                .for_each(|(_, x)| *x = DataType::zero());
+        };
+        let reset = |u: &mut [DataType], v: &mut [DataType]| {
+            constraints.distribute(u);
+            clear(v);
+        };
+        reset(&mut u, &mut v);
+        let mut time = DataType::zero();
+        observer(0, time, &u, &v);
+        match &self.scheme {
+            DynamicsScheme::Newmark(parameters) => {
+                let mut integrator = NewmarkIntegrator::new(*parameters, &mass, &stiffness)?;
+                if !self.absorbing.is_empty() {
+                    integrator.set_damping(&damping)?;
+                }
+                let mut a = vec![DataType::zero(); u.len()];
+                integrator.compute_initial_acceleration(force, time, &u, &v, &mut a)?;
+                for step in 1..=number_of_steps {
+                    integrator.step(force, time, time_step, &mut u, &mut v, &mut a)?;
+                    reset(&mut u, &mut v);
+                    clear(&mut a);
+                    time = time + time_step;
+                    observer(step, time, &u, &v);
+                }
+            }
+            DynamicsScheme::Explicit(tableau) => {
+                let integrator = ExplicitRungeKutta::new(tableau.clone())?;
+                let n = u.len();
+                let inverse_mass: Vec<DataType> =
+                    mass.get_diagonal().iter().map(|&m| m.recip()).collect();
+                let rate = |t: DataType, state: &[DataType], rate: &mut [DataType]| {
+                    let (u, v) = state.split_at(n);
+                    let (velocity, acceleration) = rate.split_at_mut(n);
+                    velocity.copy_from_slice(v);
+                    force(t, acceleration);
+                    let (ku, cv) = (stiffness.apply(u), damping.apply(v));
+                    for (dof, a) in acceleration.iter_mut().enumerate() {
+                        *a = if constraints.is_constrained(dof) {
+                            velocity[dof] = DataType::zero();
+                            DataType::zero()
+                        } else {
+                            (*a - ku[dof] - cv[dof]) * inverse_mass[dof]
+                        };
+                    }
+                };
+                let mut state: Vec<DataType> = u.iter().chain(&v).copied().collect();
+                for step in 1..=number_of_steps {
+                    integrator.step(&rate, time, time_step, &mut state);
+                    let (u_state, v_state) = state.split_at_mut(n);
+                    reset(u_state, v_state);
+                    u.copy_from_slice(u_state);
+                    v.copy_from_slice(v_state);
+                    time = time + time_step;
+                    observer(step, time, &u, &v);
+                }
+            }
+        }
+        if u.iter().chain(&v).any(|value| !value.is_finite()) {
+            return Err(Error::NotFinite("Solution is not finite"));
+        }
+        Ok(ElastodynamicsSolution {
+            displacement: self.split("displacement", &u)?,
+            velocity: self.split("velocity", &v)?,
+        })
+    }
+
+    /// Get the mass operator of the body
+    fn get_mass_operator(&self) -> VectorMassOperator<'a, DataType, ElementT> {
+        VectorMassOperator::new(
+            self.elasticity.get_element(),
+            self.elasticity.get_hypothesis().get_dimension(),
+            self.density,
+        )
+    }
+
+    /// Get the stiffness operator of the body
+    fn get_stiffness_operator(&self) -> ElasticityOperator<'a, DataType, ElementT> {
+        ElasticityOperator::new(
+            self.elasticity.get_element(),
+            self.elasticity.get_material(),
+            self.elasticity.get_hypothesis(),
+        )
+    }
+
+    /// Assemble the condensed damping matrix of the absorbing boundaries
+    fn assemble_damping(
+        &self,
+        constraints: &Constraints<DataType>,
+    ) -> Result<CsrMatrix<DataType>, Error> {
+        let element = self.elasticity.get_element();
+        let block = self.elasticity.get_block();
+        let reference_facets = self.elasticity.get_reference_facets();
+        let d = self.elasticity.get_hypothesis().get_dimension();
+        let (pressure, shear) = self.get_wave_speeds();
+        let mut triplets = Vec::new();
+        for group in &self.absorbing {
+            for &(cell, facet) in group.get_facets() {
+                let geometry = reference_facets.compute_geometry(element, block, cell, facet)?;
+                let vertices = reference_facets.get_facet(facet);
+                let count: DataType = num::cast(vertices.len()).unwrap();
+                let scale = self.density * geometry.measure / count;
+                let normal = &geometry.normal;
+                for &vertex in vertices {
+                    let node = block.get_cell_dofs(cell)[vertex];
+                    for i in 0..d {
+                        for j in 0..d {
+                            let mut value = (pressure - shear) * normal[i] * normal[j];
+                            if i == j {
+                                value = value + shear;
+                            }
+                            for (row, row_weight) in constraints.expand(node * d + i) {
+                                for (column, column_weight) in constraints.expand(node * d + j) {
+                                    triplets.push((
+                                        row,
+                                        column,
+                                        scale * value * row_weight * column_weight,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let n = self.elasticity.get_number_of_dofs();
+        CsrMatrix::from_triplets(n, n, &triplets)
+    }
+
+    /// Interpolate a vector field at the nodes of the cells
+    fn interpolate(
+        &self,
+        field: impl Fn(&[DataType]) -> Vec<DataType>,
+    ) -> Result<Vec<DataType>, Error> {
+        let block = self.elasticity.get_block();
+        let d = self.elasticity.get_hypothesis().get_dimension();
+        let mut values = vec![DataType::zero(); self.elasticity.get_number_of_dofs()];
+        for cell in 0..block.get_number_of_cells() {
+            let geometry = block.get_cell_coordinates(cell);
+            for (&node, x) in block.get_cell_dofs(cell).iter().zip(geometry.chunks(d)) {
+                let point: Vec<DataType> = x.iter().map(|&x| x.into()).collect();
+                let value = field(&point);
+                if value.len() != d {
+                    return Err(Error::DimensionMismatch {
+                        context: "Field does not match the dimension",
+                        expected: d,
+                        actual: value.len(),
+                    });
+                }
+                values[node * d..(node + 1) * d].copy_from_slice(&value);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Split interleaved dofs into one field per component
+    fn split(
+        &self,
+        name: &str,
+        values: &[DataType],
+    ) -> Result<Vec<FEFunction<'a, CoordType, DataType, ElementT>>, Error> {
+        let d = self.elasticity.get_hypothesis().get_dimension();
+        (0..d)
+            .map(|c| {
+                FEFunction::new(
+                    &format!("{}_{}", name, c),
+                    self.elasticity.get_element(),
+                    self.elasticity.get_block(),
+                    values.iter().skip(c).step_by(d).copied().collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Get the diagonal lumped matrix of the row sums of a matrix
+fn lump<DataType: Scalar>(matrix: &CsrMatrix<DataType>) -> Result<CsrMatrix<DataType>, Error> {
+    let triplets: Vec<(usize, usize, DataType)> = matrix
+        .get_row_sums()
+        .into_iter()
+        .enumerate()
+        .map(|(dof, value)| (dof, dof, value))
+        .collect();
+    CsrMatrix::from_triplets(triplets.len(), triplets.len(), &triplets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DynamicsScheme, ElastodynamicsProblem};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::models::elasticity::{ElasticityHypothesis, ElasticityProblem, IsotropicMaterial};
+    use crate::post::boundary::FacetGroup;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+    use crate::time::explicit::ButcherTableau;
+    use crate::time::newmark::NewmarkParameters;
+
+    const TOL: f64 = 1e-10;
+
+    /// Free body translating at a uniform velocity, which every scheme should follow exactly
+    #[test]
+    fn test_translation() {
+        let (dofs, coords) = uniform_quadrilaterals(4);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let schemes = [
+            (
+                DynamicsScheme::Newmark(NewmarkParameters::average_acceleration()),
+                false,
+            ),
+            (
+                DynamicsScheme::Newmark(NewmarkParameters::central_difference()),
+                true,
+            ),
+            (DynamicsScheme::Explicit(ButcherTableau::rk4()), true),
+        ];
+        for (scheme, lumped) in schemes {
+            let material = IsotropicMaterial::new(1.0, 0.3).unwrap();
+            let elasticity = ElasticityProblem::new(
+                &element,
+                &block,
+                &facets,
+                material,
+                ElasticityHypothesis::PlaneStrain,
+            )
+            .unwrap();
+            let mut problem = ElastodynamicsProblem::new(elasticity, 2.0).unwrap();
+            problem.set_scheme(scheme);
+            problem.set_lumped_mass(lumped);
+            let solution = problem
+                .solve(
+                    |_| vec![0.0, 0.0],
+                    |_| vec![1.0, -0.5],
+                    0.05,
+                    10,
+                    |_, _, _, _| {},
+                )
+                .unwrap();
+            let (ux, uy) = (
+                solution.get_displacement()[0].get_coefficients(),
+                solution.get_displacement()[1].get_coefficients(),
+            );
+            assert!(
+                ux.iter().all(|&u| (u - 0.5).abs() < TOL)
+                    && uy.iter().all(|&u| (u + 0.25).abs() < TOL),
+                "Incorrect translation"
+            );
+            assert!(
+                solution.get_velocity()[0]
+                    .get_coefficients()
+                    .iter()
+                    .all(|&v| (v - 1.0).abs() < TOL),
+                "Incorrect velocity"
+            );
+        }
+    }
+
+    /// Energy left in the unit square after a pressure pulse travelling along `x` reached its right
+    /// side, relative to the initial energy
+    fn remaining_energy(absorbing: bool, scheme: DynamicsScheme<f64>) -> f64 {
+        let (dofs, coords) = uniform_quadrilaterals(20);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let material = IsotropicMaterial::new(1.0, 0.0).unwrap();
+        let mut elasticity = ElasticityProblem::new(
+            &element,
+            &block,
+            &facets,
+            material,
+            ElasticityHypothesis::PlaneStrain,
+        )
+        .unwrap();
+        let group = |name, predicate: fn(&[f64]) -> bool| {
+            FacetGroup::from_boundary(name, &facets, &block, predicate)
+        };
+        elasticity.add_displacement(group("bottom", |x| x[1] < 1e-12), &[1], |_| vec![0.0; 2]);
+        elasticity.add_displacement(group("top", |x| x[1] > 1.0 - 1e-12), &[1], |_| vec![0.0; 2]);
+        let mut problem = ElastodynamicsProblem::new(elasticity, 1.0).unwrap();
+        if absorbing {
+            problem.add_absorbing_boundary(group("left", |x| x[0] < 1e-12));
+            problem.add_absorbing_boundary(group("right", |x| x[0] > 1.0 - 1e-12));
+        }
+        problem.set_scheme(scheme);
+        let (speed, _) = problem.get_wave_speeds();
+        assert!((speed - 1.0).abs() < TOL, "Incorrect wave speed");
+        let pulse = |x: f64| (-((x - 0.5) / 0.1).powi(2)).exp();
+        let slope = |x: f64| -2.0 * (x - 0.5) / 0.01 * pulse(x);
+        let mut energies = Vec::new();
+        problem
+            .solve(
+                |x| vec![pulse(x[0]), 0.0],
+                |x| vec![-speed * slope(x[0]), 0.0],
+                0.01,
+                100,
+                |step, _, u, v| {
+                    if step == 0 || step == 100 {
+                        let (kinetic, strain) = problem.compute_energies(u, v).unwrap();
+                        energies.push(kinetic + strain);
+                    }
+                },
+            )
+            .unwrap();
+        energies[1] / energies[0]
+    }
+
+    #[test]
+    fn test_absorbing_boundary() {
+        let average = || DynamicsScheme::Newmark(NewmarkParameters::average_acceleration());
+        assert!(
+            (remaining_energy(false, average()) - 1.0).abs() < 1e-8,
+            "Energy not conserved by the average acceleration scheme"
+        );
+        assert!(
+            remaining_energy(true, average()) < 0.05,
+            "Pulse reflected by the absorbing boundary"
+        );
+        assert!(
+            remaining_energy(true, DynamicsScheme::Explicit(ButcherTableau::rk4())) < 0.05,
+            "Pulse reflected by the absorbing boundary of the explicit scheme"
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::element::operator_trait::Operator;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::models::poisson::{
+    assemble_scalar_load, compute_dirichlet_constraints, DiffusionOperator,
+};
+use crate::post::boundary::FacetGroup;
+use crate::post::estimators::get_conductivity;
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::{
+    check_block, compute_shape_gradients_into, get_embedding_dimension, FEFunction,
+};
+use crate::time::implicit::{ImplicitIntegrator, ImplicitScheme};
+use num::Float;
+use std::collections::HashMap;
+
+/// Data of the problem given as a function of the real coordinates
+type PointFunction<'a, DataType> = Box<dyn Fn(&[DataType]) -> DataType + 'a>;
+
+/// Data of the problem given as a function of the time and of the real coordinates
+type TransientFunction<'a, DataType> = Box<dyn Fn(DataType, &[DataType]) -> DataType + 'a>;
+
+/// Conductivity as a function of the temperature
+type ConductivityLaw<'a, DataType> = Box<dyn Fn(DataType) -> DataType + 'a>;
+
+/// Mass matrix of `ρc u` computed with the shape functions of an element
+///
+/// # Explanation
+///
+/// The heat capacity `ρc` is read from the "capacity" data of the cells and is unit when absent.
+/// The local matrix of a degenerate cell holds NaN values.
+pub struct MassOperator<'a, ElementT> {
+    element: &'a ElementT,
+}
+
+impl<'a, ElementT> MassOperator<'a, ElementT> {
+    /// Constructor
+    pub fn new(element: &'a ElementT) -> MassOperator<'a, ElementT> {
+        MassOperator { element }
+    }
+}
+
+impl<CoordType, DataType, ElementT> Operator<CoordType, DataType> for MassOperator<'_, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    type ElementT = ElementT;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> Vec<DataType> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+        workspace: &mut Workspace<DataType>,
+        local: &mut Vec<DataType>,
+    ) {
+        let n = self.element.get_shape_basis().get_number_of_bases();
+        let nips = self.element.get_integrator().get_number_of_points();
+        let capacity = data
+            .get("capacity")
+            .map_or(DataType::one(), |values| values[0]);
+        let [gradients, weights] = workspace.get_buffers([nips * geometry.len(), nips]);
+        local.clear();
+        if !compute_shape_gradients_into(self.element, geometry, gradients, weights) {
+            local.resize(n * n, DataType::nan());
+            return;
+        }
+        local.resize(n * n, DataType::zero());
+        for (shapes, &weight) in self
+            .element
+            .get_shapes_for_integration()
+            .chunks(n)
+            .zip(weights.iter())
+        {
+            for (a, &sa) in shapes.iter().enumerate() {
+                for (b, &sb) in shapes.iter().enumerate() {
+                    local[a * n + b] = local[a * n + b] + weight * capacity * sa * sb;
+                }
+            }
+        }
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Ready-made transient heat conduction problem `ρc du/dt - div(κ grad u) = f`
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the temperature is encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// The problem wires the mass and diffusion operators, the boundary data and an
+/// `ImplicitIntegrator` of the semi-discrete system. The heat capacity `ρc` and the conductivity
+/// `κ` are read from the "capacity" and "conductivity" data of the cells and are unit when absent.
+/// A temperature dependent conductivity is given by a law `κ(u)`, evaluated at the mean temperature
+/// of the nodes of each cell at the beginning of each step: the "conductivity" data of the cells is
+/// replaced by these values and the stiffness reassembled in place, the coefficient being lagged by
+/// one step. The Dirichlet temperatures are constant in time and imposed at the nodes of the
+/// facets of their groups, the Neumann fluxes `κ grad u . n` and the source may depend on time.
+/// The steps use the implicit Euler scheme unless configured otherwise.
+pub struct HeatProblem<'a, CoordType, DataType, ElementT> {
+    element: &'a ElementT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    reference_facets: &'a ReferenceFacets<CoordType>,
+    source: TransientFunction<'a, DataType>,
+    dirichlet: Vec<(FacetGroup, PointFunction<'a, DataType>)>,
+    neumann: Vec<(FacetGroup, TransientFunction<'a, DataType>)>,
+    conductivity: Option<ConductivityLaw<'a, DataType>>,
+    scheme: ImplicitScheme,
+}
+
+impl<'a, CoordType, DataType, ElementT> HeatProblem<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar + Float,
+    DataType: Scalar + Float + From<CoordType> + 'static,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor of the problem without source nor boundary data
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells of the domain
+    /// * `reference_facets`: the facets of the reference element
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the problem or an error if the block does not match the element
+    pub fn new(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        reference_facets: &'a ReferenceFacets<CoordType>,
+    ) -> Result<HeatProblem<'a, CoordType, DataType, ElementT>, Error> {
+        check_block(element, block)?;
+        Ok(HeatProblem {
+            element,
+            block,
+            reference_facets,
+            source: Box::new(|_, _| DataType::zero()),
+            dirichlet: Vec::new(),
+            neumann: Vec::new(),
+            conductivity: None,
+            scheme: ImplicitScheme::ImplicitEuler,
+        })
+    }
+
+    /// Set the source `f` as a function of the time and of the real coordinates
+    pub fn set_source(&mut self, source: impl Fn(DataType, &[DataType]) -> DataType + 'a) {
+        self.source = Box::new(source);
+    }
+
+    /// Impose the temperature on a group of facets
+    pub fn add_dirichlet(
+        &mut self,
+        group: FacetGroup,
+        value: impl Fn(&[DataType]) -> DataType + 'a,
+    ) {
+        self.dirichlet.push((group, Box::new(value)));
+    }
+
+    /// Impose the flux `κ grad u . n`, as a function of the time and of the real coordinates, on a
+    /// group of facets
+    pub fn add_neumann(
+        &mut self,
+        group: FacetGroup,
+        flux: impl Fn(DataType, &[DataType]) -> DataType + 'a,
+    ) {
+        self.neumann.push((group, Box::new(flux)));
+    }
+
+    /// Set the conductivity as a function of the temperature, replacing the "conductivity" data of
+    /// the cells
+    pub fn set_conductivity_law(&mut self, law: impl Fn(DataType) -> DataType + 'a) {
+        self.conductivity = Some(Box::new(law));
+    }
+
+    /// Set the time integration scheme
+    pub fn set_scheme(&mut self, scheme: ImplicitScheme) {
+        self.scheme = scheme;
+    }
+
+    /// Get the number of dofs of the problem
+    pub fn get_number_of_dofs(&self) -> usize {
+        self.block
+            .get_connectivity()
+            .iter()
+            .max()
+            .map_or(0, |&dof| dof + 1)
+    }
+
+    /// Get the Dirichlet constraints imposed by the groups of facets
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the constraints or an error if a facet is out of the block
+    pub fn get_constraints(&self) -> Result<Constraints<DataType>, Error> {
+        let groups: Vec<&FacetGroup> = self.dirichlet.iter().map(|(group, _)| group).collect();
+        compute_dirichlet_constraints(
+            self.element,
+            self.block,
+            self.reference_facets,
+            &groups,
+            |g, x| (self.dirichlet[g].1)(x),
+        )
+    }
+
+    /// Get the conductivity of each cell for a temperature
+    ///
+    /// # Returns
+    ///
+    /// * The law evaluated at the mean temperature of the nodes of each cell, or the
+    ///   "conductivity" data of the cells without a law
+    pub fn get_cell_conductivity(&self, temperature: &[DataType]) -> Vec<DataType> {
+        let Some(law) = &self.conductivity else {
+            return get_conductivity(self.block);
+        };
+        let count: DataType = num::cast(self.block.get_dofs_per_cell()).unwrap();
+        (0..self.block.get_number_of_cells())
+            .map(|cell| {
+                let sum = self
+                    .block
+                    .get_cell_dofs(cell)
+                    .iter()
+                    .fold(DataType::zero(), |sum, &dof| sum + temperature[dof]);
+                law(sum / count)
+            })
+            .collect()
+    }
+
+    /// Assemble the load vector of the source and of the Neumann fluxes at a time without
+    /// constraints
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the load or an error if a cell or a facet is degenerate
+    pub fn assemble_load(&self, time: DataType) -> Result<Vec<DataType>, Error> {
+        let groups: Vec<&FacetGroup> = self.neumann.iter().map(|(group, _)| group).collect();
+        assemble_scalar_load(
+            self.element,
+            self.block,
+            self.reference_facets,
+            |x| (self.source)(time, x),
+            &groups,
+            |g, x| (self.neumann[g].1)(time, x),
+        )
+    }
+
+    /// Solve the problem over a number of constant time steps
+    ///
+    /// # Arguments
+    ///
+    /// * `initial`: the initial temperature as a function of the real coordinates
+    /// * `time_step`: the time step
+    /// * `number_of_steps`: the number of steps from time zero
+    /// * `observer`: called with the step, the time and the temperature after each step and for
+    ///   the initial state at step zero
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the final temperature, named "temperature", or an error if the
+    ///   assembly failed, a cell is degenerate or a step did not converge
+    pub fn solve(
+        &self,
+        initial: impl Fn(&[DataType]) -> DataType,
+        time_step: DataType,
+        number_of_steps: usize,
+        mut observer: impl FnMut(usize, DataType, &FEFunction<'a, CoordType, DataType, ElementT>),
+    ) -> Result<FEFunction<'a, CoordType, DataType, ElementT>, Error> {
+        let constraints = self.get_constraints()?;
+        let assembler = Assembler::new(self.get_number_of_dofs());
+        let (mass, _) = assembler.assemble_constrained(
+            &MassOperator::new(self.element),
+            self.block,
+            &constraints,
+        )?;
+        let mut temperature = self.interpolate(initial);
+        constraints.distribute(&mut temperature);
+        let diffusion = DiffusionOperator::new(self.element);
+        let conductivity = self.get_cell_conductivity(&temperature);
+        let (mut stiffness, mut inhomogeneity) = assembler.assemble_constrained(
+            &diffusion,
+            &self.get_conducting_block(&conductivity)?,
+            &constraints,
+        )?;
+        self.assemble_load(DataType::zero())?;
+        let initial_stiffness = stiffness.clone();
+        let mut integrator = ImplicitIntegrator::new(self.scheme, &mass, &initial_stiffness)?;
+        let mut time = DataType::zero();
+        let mut function = FEFunction::new("temperature", self.element, self.block, temperature)?;
+        observer(0, time, &function);
+        for step in 1..=number_of_steps {
+            let mut temperature = function.get_coefficients().to_vec();
+            if self.conductivity.is_some() && step > 1 {
+                let conductivity = self.get_cell_conductivity(&temperature);
+                assembler.reassemble_constrained_values(
+                    &diffusion,
+                    &self.get_conducting_block(&conductivity)?,
+                    &constraints,
+                    &mut stiffness,
+                    &mut inhomogeneity,
+                )?;
+                integrator.set_matrix_values(1, stiffness.get_values())?;
+            }
+            let force = |t, f: &mut [DataType]| match self.assemble_load(t) {
+                Ok(mut load) => {
+                    constraints.condense(&mut load);
+                    for ((f, &l), &r) in f.iter_mut().zip(&load).zip(&inhomogeneity) {
+                        *f = l + r;
+                    }
+                }
+                Err(_) => f.fill(DataType::nan()),
+            };
+            integrator.step(force, time, time_step, &mut temperature)?;
+            constraints.distribute(&mut temperature);
+            if temperature.iter().any(|value| !value.is_finite()) {
+                return Err(Error::NotFinite("Temperature is not finite"));
+            }
+            time = time + time_step;
+            function.set_coefficients(&temperature)?;
+            observer(step, time, &function);
+        }
+        Ok(function)
+    }
+
+    /// Interpolate a function of the real coordinates at the nodes
+    fn interpolate(&self, value: impl Fn(&[DataType]) -> DataType) -> Vec<DataType> {
+        let embedding = get_embedding_dimension(self.element, self.block);
+        let mut values = vec![DataType::zero(); self.get_number_of_dofs()];
+        for cell in 0..self.block.get_number_of_cells() {
+            let coordinates = self.block.get_cell_coordinates(cell);
+            for (&dof, x) in self
+                .block
+                .get_cell_dofs(cell)
+                .iter()
+                .zip(coordinates.chunks(embedding))
+            {
+                let point: Vec<DataType> = x.iter().map(|&x| x.into()).collect();
+                values[dof] = value(&point);
+            }
+        }
+        values
+    }
+
+    /// Copy of the block whose "conductivity" data is replaced by the conductivity of the cells
+    fn get_conducting_block<'b>(
+        &'b self,
+        conductivity: &'b [DataType],
+    ) -> Result<CellBlock<'b, CoordType, DataType>, Error> {
+        let mut block = CellBlock::new(
+            self.block.get_dofs_per_cell(),
+            self.block.get_connectivity(),
+            self.block.get_coordinates(),
+        )?;
+        for name in self.block.get_field_names() {
+            if name != "conductivity" {
+                block.add_field(name, self.block.get_field(name).unwrap())?;
+            }
+        }
+        block.add_field("conductivity", conductivity)?;
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeatProblem;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::boundary::FacetGroup;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+    use crate::time::implicit::ImplicitScheme;
+    use std::f64::consts::PI;
+
+    const TOL: f64 = 1e-9;
+
+    #[test]
+    fn test_steady_state() {
+        let (dofs, coords) = uniform_quadrilaterals(4);
+        let capacity = vec![3.0; 16];
+        let mut block = CellBlock::new(4, &dofs, &coords).unwrap();
+        block.add_field("capacity", &capacity).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let mut problem = HeatProblem::new(&element, &block, &facets).unwrap();
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("left", &facets, &block, |x| x[0] < 1e-12),
+            |_| 0.0,
+        );
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("right", &facets, &block, |x| x[0] > 1.0 - 1e-12),
+            |_| 1.0,
+        );
+        let mut times = Vec::new();
+        let temperature = problem
+            .solve(|x| x[0], 0.1, 5, |step, time, _| times.push((step, time)))
+            .unwrap();
+        assert_eq!(times.len(), 6, "Incorrect number of observations");
+        assert!(
+            times[5].0 == 5 && (times[5].1 - 0.5).abs() < TOL,
+            "Incorrect observed time"
+        );
+        assert_eq!(temperature.get_name(), "temperature", "Incorrect name");
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            assert!(
+                (temperature.get_coefficients()[dof] - x[0]).abs() < TOL,
+                "Steady state not preserved"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decay() {
+        let n = 8;
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let mut problem = HeatProblem::new(&element, &block, &facets).unwrap();
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("boundary", &facets, &block, |_| true),
+            |_| 0.0,
+        );
+        problem.set_scheme(ImplicitScheme::CrankNicolson);
+        let temperature = problem
+            .solve(
+                |x| (PI * x[0]).sin() * (PI * x[1]).sin(),
+                0.005,
+                20,
+                |_, _, _| {},
+            )
+            .unwrap();
+        let centre = (n / 2) * (n + 1) + n / 2;
+        let exact = (-2.0 * PI * PI * 0.1).exp();
+        assert!(
+            (temperature.get_coefficients()[centre] - exact).abs() < 0.05 * exact,
+            "Incorrect decay of the fundamental mode"
+        );
+    }
+
+    #[test]
+    fn test_temperature_dependent_conductivity() {
+        let (dofs, coords) = uniform_quadrilaterals(4);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let mut problem = HeatProblem::new(&element, &block, &facets).unwrap();
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("left", &facets, &block, |x| x[0] < 1e-12),
+            |_| 0.0,
+        );
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("right", &facets, &block, |x| x[0] > 1.0 - 1e-12),
+            |_| 1.0,
+        );
+        problem.set_conductivity_law(|u| 1.0 + u);
+        problem.set_scheme(ImplicitScheme::Bdf2);
+        let conductivity = problem.get_cell_conductivity(&[0.5; 25]);
+        assert!(
+            conductivity.iter().all(|&k| (k - 1.5).abs() < TOL),
+            "Incorrect cell conductivity"
+        );
+        let temperature = problem.solve(|x| x[0], 0.1, 100, |_, _, _| {}).unwrap();
+        // The Kirchhoff transform u + u²/2 of the steady state is linear, which the cell means of
+        // the conductivity reproduce exactly at the nodes
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            assert!(
+                (temperature.get_coefficients()[dof] - ((1.0 + 3.0 * x[0]).sqrt() - 1.0)).abs()
+                    < 1e-6,
+                "Incorrect nonlinear steady state"
+            );
+        }
+    }
+}
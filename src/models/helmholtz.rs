@@ -0,0 +1,528 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::complex::ComplexElement;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::element::operator_trait::Operator;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::models::poisson::compute_dirichlet_constraints;
+use crate::post::boundary::FacetGroup;
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::{
+    check_block, compute_shape_gradients, compute_shape_gradients_into, locate_degenerate_cell,
+    map_to_physical, FEFunction,
+};
+use crate::solver::registry::{SolverConfiguration, SolverRegistry};
+use num::complex::Complex;
+use num::Float;
+use std::collections::HashMap;
+
+/// Complex data of the problem given as a function of the real coordinates
+type ComplexFunction<'a, DataType> = Box<dyn Fn(&[DataType]) -> Complex<DataType> + 'a>;
+
+/// Matrix of `-Δp - k² p` computed with the shape functions of the real element of a complex
+/// element
+///
+/// # Explanation
+///
+/// The local matrix `∇N_a . ∇N_b - k² N_a N_b` is real valued for a real wavenumber `k` and is
+/// lifted to complex values, so that it may be assembled along with the complex impedance terms
+/// and constraints of a frequency domain problem. The local matrix of a degenerate cell holds NaN
+/// values.
+pub struct HelmholtzOperator<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar,
+    ElementT: Element<CoordType, DataType>,
+{
+    element: &'a ComplexElement<'a, CoordType, DataType, ElementT>,
+    wavenumber: DataType,
+}
+
+impl<'a, CoordType, DataType, ElementT> HelmholtzOperator<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the complex element describing the cells
+    /// * `wavenumber`: the wavenumber `k`
+    pub fn new(
+        element: &'a ComplexElement<'a, CoordType, DataType, ElementT>,
+        wavenumber: DataType,
+    ) -> HelmholtzOperator<'a, CoordType, DataType, ElementT> {
+        HelmholtzOperator {
+            element,
+            wavenumber,
+        }
+    }
+}
+
+impl<'a, CoordType, DataType, ElementT> Operator<CoordType, Complex<DataType>>
+    for HelmholtzOperator<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    type ElementT = ComplexElement<'a, CoordType, DataType, ElementT>;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[Complex<DataType>]>,
+    ) -> Vec<Complex<DataType>> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        _data: &HashMap<String, &[Complex<DataType>]>,
+        workspace: &mut Workspace<Complex<DataType>>,
+        local: &mut Vec<Complex<DataType>>,
+    ) {
+        let element = self.element.get_element();
+        let n = element.get_shape_basis().get_number_of_bases();
+        let nips = element.get_integrator().get_number_of_points();
+        let embedding = geometry.len() / n;
+        let [gradients, weights] = workspace.get_buffers([nips * geometry.len(), nips]);
+        local.clear();
+        if !compute_shape_gradients_into(element, geometry, gradients, weights) {
+            local.resize(n * n, Complex::new(DataType::nan(), DataType::nan()));
+            return;
+        }
+        local.resize(n * n, Complex::new(DataType::zero(), DataType::zero()));
+        let squared = self.wavenumber * self.wavenumber;
+        for ((shapes, point_gradients), weight) in element
+            .get_shapes_for_integration()
+            .chunks(n)
+            .zip(gradients.chunks(geometry.len()))
+            .zip(weights.iter())
+        {
+            for (a, ga) in point_gradients.chunks(embedding).enumerate() {
+                for (b, gb) in point_gradients.chunks(embedding).enumerate() {
+                    let product = ga
+                        .iter()
+                        .zip(gb)
+                        .fold(DataType::zero(), |sum, (x, y)| sum + x.re * y.re);
+                    local[a * n + b].re = local[a * n + b].re
+                        + weight.re * (product - squared * shapes[a] * shapes[b]);
+                }
+            }
+        }
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Solution of a Helmholtz problem
+pub struct HelmholtzSolution<'a, CoordType, DataType, ElementT> {
+    values: Vec<Complex<DataType>>,
+    real_part: FEFunction<'a, CoordType, DataType, ElementT>,
+    imaginary_part: FEFunction<'a, CoordType, DataType, ElementT>,
+}
+
+impl<'a, CoordType, DataType, ElementT> HelmholtzSolution<'a, CoordType, DataType, ElementT> {
+    /// Get the complex values of the solution at the dofs
+    pub fn get_values(&self) -> &[Complex<DataType>] {
+        &self.values
+    }
+
+    /// Get the real part of the solution, named "pressure_real"
+    pub fn get_real_part(&self) -> &FEFunction<'a, CoordType, DataType, ElementT> {
+        &self.real_part
+    }
+
+    /// Get the imaginary part of the solution, named "pressure_imaginary"
+    pub fn get_imaginary_part(&self) -> &FEFunction<'a, CoordType, DataType, ElementT> {
+        &self.imaginary_part
+    }
+}
+
+/// Ready-made frequency domain Helmholtz problem `-Δp - k² p = f` of the complex amplitude of the
+/// acoustic pressure
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of the real and imaginary parts of the solution
+/// * ElementT: the real element describing the cells
+///
+/// # Explanation
+///
+/// The time dependence `exp(-iωt)` is factored out of the pressure. The impedance `Z`, normalized
+/// by the characteristic impedance of the medium, of a group of facets imposes
+/// `∂p/∂n = i k p / Z`, integrated with the values at the nodes of the facets: a unit impedance
+/// absorbs the plane waves of normal incidence and approximates a radiation condition. The other
+/// facets without Dirichlet values are rigid walls `∂p/∂n = 0`.
+///
+/// The complex system is assembled with a ComplexElement lifting the real element, and condensed
+/// with complex constraints. It is then solved as the real system
+/// `[Re(A) -Im(A); Im(A) Re(A)] [Re(p); Im(p)] = [Re(b); Im(b)]` of twice the size, by the sparse
+/// LU factorization unless configured otherwise.
+pub struct HelmholtzProblem<'a, CoordType, DataType, ElementT> {
+    element: &'a ElementT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    reference_facets: &'a ReferenceFacets<CoordType>,
+    wavenumber: DataType,
+    source: ComplexFunction<'a, DataType>,
+    dirichlet: Vec<(FacetGroup, ComplexFunction<'a, DataType>)>,
+    impedances: Vec<(FacetGroup, Complex<DataType>)>,
+    solver: SolverConfiguration,
+}
+
+impl<'a, CoordType, DataType, ElementT> HelmholtzProblem<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType> + 'static,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor of the problem without source nor boundary data
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the real element describing the cells
+    /// * `block`: the cells of the domain
+    /// * `reference_facets`: the facets of the reference element
+    /// * `wavenumber`: the wavenumber `k = ω / c`
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the problem or an error if the block does not match the element
+    ///   or the wavenumber is not positive
+    pub fn new(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        reference_facets: &'a ReferenceFacets<CoordType>,
+        wavenumber: DataType,
+    ) -> Result<HelmholtzProblem<'a, CoordType, DataType, ElementT>, Error> {
+        check_block(element, block)?;
+        if wavenumber <= DataType::zero() {
+            return Err(Error::InvalidArgument("Wavenumber should be positive"));
+        }
+        Ok(HelmholtzProblem {
+            element,
+            block,
+            reference_facets,
+            wavenumber,
+            source: Box::new(|_| Complex::new(DataType::zero(), DataType::zero())),
+            dirichlet: Vec::new(),
+            impedances: Vec::new(),
+            solver: SolverConfiguration::new("preonly", "lu"),
+        })
+    }
+
+    /// Set the complex source `f` as a function of the real coordinates
+    pub fn set_source(&mut self, source: impl Fn(&[DataType]) -> Complex<DataType> + 'a) {
+        self.source = Box::new(source);
+    }
+
+    /// Impose the complex value of the pressure on a group of facets
+    pub fn add_dirichlet(
+        &mut self,
+        group: FacetGroup,
+        value: impl Fn(&[DataType]) -> Complex<DataType> + 'a,
+    ) {
+        self.dirichlet.push((group, Box::new(value)));
+    }
+
+    /// Impose a normalized impedance on a group of facets
+    pub fn add_impedance(&mut self, group: FacetGroup, impedance: Complex<DataType>) {
+        self.impedances.push((group, impedance));
+    }
+
+    /// Set the selection of the solver and preconditioner of the registry for the real equivalent
+    /// system
+    pub fn set_solver(&mut self, solver: SolverConfiguration) {
+        self.solver = solver;
+    }
+
+    /// Get the number of complex dofs of the problem
+    pub fn get_number_of_dofs(&self) -> usize {
+        self.block
+            .get_connectivity()
+            .iter()
+            .max()
+            .map_or(0, |&dof| dof + 1)
+    }
+
+    /// Get the complex Dirichlet constraints imposed by the groups of facets
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the constraints or an error if a facet is out of the block
+    pub fn get_constraints(&self) -> Result<Constraints<Complex<DataType>>, Error> {
+        let groups: Vec<&FacetGroup> = self.dirichlet.iter().map(|(group, _)| group).collect();
+        compute_dirichlet_constraints(
+            self.element,
+            self.block,
+            self.reference_facets,
+            &groups,
+            |g, x| (self.dirichlet[g].1)(x),
+        )
+    }
+
+    /// Assemble the load vector of the source, without constraints
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the load or an error if a cell is degenerate
+    pub fn assemble_load(&self) -> Result<Vec<Complex<DataType>>, Error> {
+        let n = self.element.get_shape_basis().get_number_of_bases();
+        let zero = Complex::new(DataType::zero(), DataType::zero());
+        let mut load = vec![zero; self.get_number_of_dofs()];
+        for cell in 0..self.block.get_number_of_cells() {
+            let points =
+                compute_shape_gradients(self.element, self.block.get_cell_coordinates(cell))
+                    .ok_or(Error::DegenerateCell { cell })?;
+            for (shapes, (_, weight)) in self
+                .element
+                .get_shapes_for_integration()
+                .chunks(n)
+                .zip(points)
+            {
+                let point = map_to_physical(self.element, self.block, cell, shapes);
+                let value = (self.source)(&point) * weight;
+                for (&dof, &shape) in self.block.get_cell_dofs(cell).iter().zip(shapes) {
+                    load[dof] = load[dof] + value * shape;
+                }
+            }
+        }
+        Ok(load)
+    }
+
+    /// Solve the problem
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the solution or an error if the assembly failed, an impedance
+    ///   vanishes, the solver is unknown or the solve did not converge
+    pub fn solve(&self) -> Result<HelmholtzSolution<'a, CoordType, DataType, ElementT>, Error> {
+        let constraints = self.get_constraints()?;
+        let complex_element = ComplexElement::new(self.element);
+        let complex_block: CellBlock<CoordType, Complex<DataType>> = CellBlock::new(
+            self.block.get_dofs_per_cell(),
+            self.block.get_connectivity(),
+            self.block.get_coordinates(),
+        )?;
+        let operator = HelmholtzOperator::new(&complex_element, self.wavenumber);
+        let (matrix, mut rhs) = Assembler::new(self.get_number_of_dofs()).assemble_constrained(
+            &operator,
+            &complex_block,
+            &constraints,
+        )?;
+        let mut matrix = matrix.to_general();
+        self.add_impedances(&constraints, &mut matrix, &mut rhs)?;
+        if matrix
+            .get_values()
+            .iter()
+            .any(|value| !value.re.is_finite() || !value.im.is_finite())
+        {
+            return Err(locate_degenerate_cell(self.element, self.block));
+        }
+        let mut load = self.assemble_load()?;
+        constraints.condense(&mut load);
+        for (r, &l) in rhs.iter_mut().zip(&load) {
+            *r = *r + l;
+        }
+        let (real_matrix, real_rhs) = to_real_system(&matrix, &rhs)?;
+        let registry = SolverRegistry::new();
+        let solver = registry.build(&self.solver, &real_matrix)?;
+        let mut real_solution = vec![DataType::zero(); real_rhs.len()];
+        if !solver.solve(&real_rhs, &mut real_solution).is_converged() {
+            return Err(Error::NotConverged("Solve did not converge"));
+        }
+        let (real, imaginary) = real_solution.split_at(rhs.len());
+        let mut values: Vec<Complex<DataType>> = real
+            .iter()
+            .zip(imaginary)
+            .map(|(&re, &im)| Complex::new(re, im))
+            .collect();
+        constraints.distribute(&mut values);
+        Ok(HelmholtzSolution {
+            real_part: FEFunction::new(
+                "pressure_real",
+                self.element,
+                self.block,
+                values.iter().map(|value| value.re).collect(),
+            )?,
+            imaginary_part: FEFunction::new(
+                "pressure_imaginary",
+                self.element,
+                self.block,
+                values.iter().map(|value| value.im).collect(),
+            )?,
+            values,
+        })
+    }
+
+    /// Add the terms `-i k / Z (p, v)` of the impedances to a condensed system, the facet
+    /// integrals being lumped at the nodes of the facets
+    fn add_impedances(
+        &self,
+        constraints: &Constraints<Complex<DataType>>,
+        matrix: &mut CsrMatrix<Complex<DataType>>,
+        rhs: &mut [Complex<DataType>],
+    ) -> Result<(), Error> {
+        let zero = Complex::new(DataType::zero(), DataType::zero());
+        for (group, impedance) in self.impedances.iter() {
+            if *impedance == zero {
+                return Err(Error::InvalidArgument("Impedance should not vanish"));
+            }
+            let factor = Complex::new(DataType::zero(), -self.wavenumber) / impedance;
+            for &(cell, facet) in group.get_facets() {
+                let geometry = self.reference_facets.compute_geometry(
+                    self.element,
+                    self.block,
+                    cell,
+                    facet,
+                )?;
+                let nodes = self.reference_facets.get_facet(facet);
+                let count: DataType = num::cast(nodes.len()).unwrap();
+                let value = factor * (geometry.measure / count);
+                let dofs = self.block.get_cell_dofs(cell);
+                for &node in nodes {
+                    let dof = dofs[node];
+                    let expansion = constraints.expand(dof);
+                    for (row, row_weight) in expansion.clone() {
+                        for (column, column_weight) in expansion.clone() {
+                            let position = matrix
+                                .get_position(row, column)
+                                .ok_or(Error::MissingEntry { row, column })?;
+                            let values = matrix.get_values_mut();
+                            values[position] =
+                                values[position] + value * row_weight * column_weight;
+                        }
+                        if let Some(line) = constraints.get_line(dof) {
+                            rhs[row] = rhs[row] - value * row_weight * line.get_inhomogeneity();
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get the real system of twice the size equivalent to a complex system
+fn to_real_system<DataType: Scalar + Float>(
+    matrix: &CsrMatrix<Complex<DataType>>,
+    rhs: &[Complex<DataType>],
+) -> Result<(CsrMatrix<DataType>, Vec<DataType>), Error> {
+    let n = matrix.get_number_of_rows();
+    let mut triplets = Vec::with_capacity(4 * matrix.get_number_of_nonzeros());
+    for (row, bounds) in matrix.get_row_offsets().windows(2).enumerate() {
+        for position in bounds[0]..bounds[1] {
+            let column = matrix.get_column_indices()[position];
+            let value = matrix.get_values()[position];
+            triplets.push((row, column, value.re));
+            triplets.push((row + n, column + n, value.re));
+            if value.im != DataType::zero() {
+                triplets.push((row, column + n, -value.im));
+                triplets.push((row + n, column, value.im));
+            }
+        }
+    }
+    let real_rhs = rhs
+        .iter()
+        .map(|value| value.re)
+        .chain(rhs.iter().map(|value| value.im))
+        .collect();
+    Ok((CsrMatrix::from_triplets(2 * n, 2 * n, &triplets)?, real_rhs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HelmholtzProblem;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::boundary::FacetGroup;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+    use num::complex::Complex;
+    use std::f64::consts::PI;
+
+    /// Largest nodal error of the plane wave `exp(ikx)` in a duct of unit length closed by a unit
+    /// impedance, on `n` by `n` quadrilaterals
+    fn duct_error(n: usize) -> f64 {
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let k = 2.0 * PI;
+        let mut problem = HelmholtzProblem::new(&element, &block, &facets, k).unwrap();
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("inlet", &facets, &block, |x| x[0] < 1e-12),
+            |_| Complex::new(1.0, 0.0),
+        );
+        problem.add_impedance(
+            FacetGroup::from_boundary("outlet", &facets, &block, |x| x[0] > 1.0 - 1e-12),
+            Complex::new(1.0, 0.0),
+        );
+        let solution = problem.solve().unwrap();
+        dofs.iter()
+            .zip(coords.chunks(2))
+            .map(|(&dof, x)| {
+                let exact = Complex::new(0.0, k * x[0]).exp();
+                let real = solution.get_real_part().get_coefficients()[dof];
+                let imaginary = solution.get_imaginary_part().get_coefficients()[dof];
+                assert_eq!(
+                    solution.get_values()[dof],
+                    Complex::new(real, imaginary),
+                    "Incorrect parts"
+                );
+                (solution.get_values()[dof] - exact).norm()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_absorbing_impedance() {
+        let coarse = duct_error(16);
+        let fine = duct_error(32);
+        assert!(fine < 0.02, "Plane wave reflected by the impedance");
+        assert!(coarse / fine > 3.5, "Incorrect convergence rate");
+    }
+
+    /// Manufactured complex solution `(1 + 2i) sin(πx) sin(πy)` with its source
+    #[test]
+    fn test_complex_source() {
+        let (dofs, coords) = uniform_quadrilaterals(16);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let k = 3.0;
+        let amplitude = Complex::new(1.0, 2.0);
+        let exact = |x: &[f64]| amplitude * ((PI * x[0]).sin() * (PI * x[1]).sin());
+        let mut problem = HelmholtzProblem::new(&element, &block, &facets, k).unwrap();
+        problem.set_source(move |x| exact(x) * (2.0 * PI * PI - k * k));
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("walls", &facets, &block, |_| true),
+            |_| Complex::new(0.0, 0.0),
+        );
+        let solution = problem.solve().unwrap();
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            assert!(
+                (solution.get_values()[dof] - exact(x)).norm() < 2e-2,
+                "Incorrect solution"
+            );
+        }
+        problem.add_impedance(
+            FacetGroup::from_boundary("walls", &facets, &block, |_| true),
+            Complex::new(0.0, 0.0),
+        );
+        assert!(problem.solve().is_err(), "Vanishing impedance accepted");
+    }
+}
@@ -0,0 +1,524 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::element_traits::{Element, ShapeBasis};
+use crate::element::residual_trait::{AutomaticTangent, ResidualKernel};
+use crate::error::Error;
+use crate::models::elasticity::{ElasticityHypothesis, ElasticityProblem};
+use crate::nonlinear::newton::NewtonSolver;
+use crate::nonlinear::nonlinear_traits::{NonlinearControl, NonlinearProblem, NonlinearResult};
+use crate::post::function::{compute_shape_gradients, FEFunction};
+use num::Float;
+use std::collections::HashMap;
+
+/// Residual of the internal forces of a compressible Neo-Hookean material at finite strain
+///
+/// # Explanation
+///
+/// The stored energy `W = μ/2 (tr(F^T F) - 3) - μ ln(J) + λ/2 ln(J)²` of the deformation gradient
+/// `F = I + ∇u`, with `J = det(F)`, gives the first Piola-Kirchhoff stress
+/// `P = μ (F - F^{-T}) + λ ln(J) F^{-T}`, which reduces to the stress of linear elasticity with
+/// the Lamé coefficients `λ` and `μ` for small displacements. The local residual is
+/// `∫ P ∇N_a` over the reference cell, on the displacement dofs interleaved as for the
+/// ElasticityOperator; plane strain cells have a unit out of plane stretch. The residual of a
+/// degenerate cell or of a cell inverted by the displacement, `J ≤ 0`, holds NaN values.
+pub struct NeoHookeanKernel<'a, DataType, ElementT> {
+    element: &'a ElementT,
+    dimension: usize,
+    lambda: DataType,
+    mu: DataType,
+}
+
+impl<'a, DataType, ElementT> NeoHookeanKernel<'a, DataType, ElementT> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the reference cells
+    /// * `dimension`: the dimension of the displacement, 2 for plane strain
+    /// * `lambda`: the first Lamé coefficient `λ`
+    /// * `mu`: the shear modulus `μ`
+    pub fn new(
+        element: &'a ElementT,
+        dimension: usize,
+        lambda: DataType,
+        mu: DataType,
+    ) -> NeoHookeanKernel<'a, DataType, ElementT> {
+        NeoHookeanKernel {
+            element,
+            dimension,
+            lambda,
+            mu,
+        }
+    }
+}
+
+impl<CoordType, DataType, ElementT> ResidualKernel<CoordType, DataType>
+    for NeoHookeanKernel<'_, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    type ElementT = ElementT;
+
+    fn get_number_of_dofs(&self) -> usize {
+        self.element.get_shape_basis().get_number_of_bases() * self.dimension
+    }
+
+    fn compute_residual<ScalarT: Scalar + Float + From<DataType>>(
+        &self,
+        geometry: &[CoordType],
+        _data: &HashMap<String, &[DataType]>,
+        state: &[ScalarT],
+    ) -> Vec<ScalarT> {
+        let d = self.dimension;
+        let n = self.element.get_shape_basis().get_number_of_bases() * d;
+        let nan = vec![ScalarT::nan(); n];
+        let Some(points) = compute_shape_gradients(self.element, geometry) else {
+            return nan;
+        };
+        let (lambda, mu): (ScalarT, ScalarT) = (self.lambda.into(), self.mu.into());
+        let mut residual = vec![ScalarT::zero(); n];
+        for (gradients, weight) in points {
+            let mut deformation = vec![ScalarT::zero(); d * d];
+            for i in 0..d {
+                deformation[i * d + i] = ScalarT::one();
+            }
+            for (g, u) in gradients.chunks(d).zip(state.chunks(d)) {
+                for i in 0..d {
+                    for j in 0..d {
+                        let gradient: ScalarT = g[j].into();
+                        deformation[i * d + j] = deformation[i * d + j] + u[i] * gradient;
+                    }
+                }
+            }
+            let Some((inverse, jacobian)) = invert(&deformation, d) else {
+                return nan;
+            };
+            if jacobian <= ScalarT::zero() {
+                return nan;
+            }
+            let log = jacobian.ln();
+            let weight: ScalarT = weight.into();
+            for (a, g) in gradients.chunks(d).enumerate() {
+                for i in 0..d {
+                    for j in 0..d {
+                        let inverse_transpose = inverse[j * d + i];
+                        let stress = mu * (deformation[i * d + j] - inverse_transpose)
+                            + lambda * log * inverse_transpose;
+                        let gradient: ScalarT = g[j].into();
+                        residual[a * d + i] = residual[a * d + i] + weight * stress * gradient;
+                    }
+                }
+            }
+        }
+        residual
+    }
+}
+
+/// Solution of a hyperelastic problem
+pub struct HyperelasticSolution<'a, CoordType, DataType, ElementT> {
+    displacement: Vec<FEFunction<'a, CoordType, DataType, ElementT>>,
+    results: Vec<NonlinearResult<DataType>>,
+}
+
+impl<'a, CoordType, DataType, ElementT> HyperelasticSolution<'a, CoordType, DataType, ElementT> {
+    /// Get one field per component of the displacement, named "displacement_c"
+    pub fn get_displacement(&self) -> &[FEFunction<'a, CoordType, DataType, ElementT>] {
+        &self.displacement
+    }
+
+    /// Get the convergence information of the Newton iterations of each load step
+    pub fn get_results(&self) -> &[NonlinearResult<DataType>] {
+        &self.results
+    }
+}
+
+/// Ready-made finite strain problem of a compressible Neo-Hookean body
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the solution is encoded with
+/// * ElementT: the element describing the cells
+/// * N: the number of displacement dofs of a cell, the number of nodes times the dimension
+///
+/// # Explanation
+///
+/// The body, its supports and its loads are those of an ElasticityProblem, the Lamé coefficients
+/// of its material being those of the NeoHookeanKernel, under the plane strain or three
+/// dimensional hypothesis. The loads are dead loads on the reference configuration. They and the
+/// imposed displacements are applied in equal increments, each solved by a NewtonSolver with the
+/// exact tangents of the kernel computed by an AutomaticTangent. The line search of the solver
+/// shortens the updates inverting cells.
+pub struct HyperelasticProblem<'a, CoordType, DataType, ElementT, const N: usize> {
+    elasticity: ElasticityProblem<'a, CoordType, DataType, ElementT>,
+    number_of_load_steps: usize,
+    control: NonlinearControl<DataType>,
+}
+
+impl<'a, CoordType, DataType, ElementT, const N: usize>
+    HyperelasticProblem<'a, CoordType, DataType, ElementT, N>
+where
+    CoordType: Scalar + Float,
+    DataType: Scalar + Float + From<CoordType> + 'static,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor applying the loads in one step
+    ///
+    /// # Arguments
+    ///
+    /// * `elasticity`: the body with its supports and loads
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the problem or an error if the hypothesis is plane stress or `N`
+    ///   does not match the dofs of a cell
+    pub fn new(
+        elasticity: ElasticityProblem<'a, CoordType, DataType, ElementT>,
+    ) -> Result<HyperelasticProblem<'a, CoordType, DataType, ElementT, N>, Error> {
+        let hypothesis = elasticity.get_hypothesis();
+        if hypothesis == ElasticityHypothesis::PlaneStress {
+            return Err(Error::InvalidArgument(
+                "Plane stress is not supported at finite strain",
+            ));
+        }
+        if elasticity.get_block().get_dofs_per_cell() * hypothesis.get_dimension() != N {
+            return Err(Error::SizeMismatch {
+                context: "Number of variables does not match the dofs of a cell",
+                expected: elasticity.get_block().get_dofs_per_cell() * hypothesis.get_dimension(),
+                actual: N,
+            });
+        }
+        Ok(HyperelasticProblem {
+            elasticity,
+            number_of_load_steps: 1,
+            control: NonlinearControl::new(num::cast(1e-10).unwrap(), DataType::zero(), 20),
+        })
+    }
+
+    /// Set the number of equal increments of the loads and imposed displacements
+    pub fn set_number_of_load_steps(&mut self, number_of_load_steps: usize) {
+        self.number_of_load_steps = number_of_load_steps.max(1);
+    }
+
+    /// Set the stopping criterion of the Newton iterations of each load step
+    pub fn set_control(&mut self, control: NonlinearControl<DataType>) {
+        self.control = control;
+    }
+
+    /// Get the underlying elasticity problem
+    pub fn get_elasticity(&self) -> &ElasticityProblem<'a, CoordType, DataType, ElementT> {
+        &self.elasticity
+    }
+
+    /// Get the residual kernel of the body
+    pub fn get_kernel(&self) -> NeoHookeanKernel<'a, DataType, ElementT> {
+        let hypothesis = self.elasticity.get_hypothesis();
+        let (lambda, mu) = self
+            .elasticity
+            .get_material()
+            .get_lame_coefficients(hypothesis);
+        NeoHookeanKernel::new(
+            self.elasticity.get_element(),
+            hypothesis.get_dimension(),
+            lambda,
+            mu,
+        )
+    }
+
+    /// Solve the problem
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the displacement and the convergence information of the load
+    ///   steps, or an error if the assembly failed or the Newton iterations of a step did not
+    ///   converge
+    pub fn solve(&self) -> Result<HyperelasticSolution<'a, CoordType, DataType, ElementT>, Error> {
+        let constraints = self.elasticity.get_constraints()?;
+        let number_of_dofs = self.elasticity.get_number_of_dofs();
+        let mut homogeneous = Constraints::new();
+        for dof in 0..number_of_dofs {
+            if let Some(line) = constraints.get_line(dof) {
+                homogeneous.add_line(dof, line.get_entries(), DataType::zero())?;
+            }
+        }
+        let displacement_dofs = self.elasticity.get_displacement_dofs();
+        let block = self.elasticity.get_block();
+        let vector_block = CellBlock::new(N, &displacement_dofs, block.get_coordinates())?;
+        let tangent = AutomaticTangent::<_, _, _, N>::new(self.get_kernel(), "displacement")?;
+        let load = self.elasticity.assemble_load()?;
+        let newton = NewtonSolver::new(self.control);
+        let mut u = vec![DataType::zero(); number_of_dofs];
+        let mut results = Vec::with_capacity(self.number_of_load_steps);
+        let steps: DataType = num::cast(self.number_of_load_steps).unwrap();
+        for step in 1..=self.number_of_load_steps {
+            let factor = num::cast::<_, DataType>(step).unwrap() / steps;
+            let mut scaled = Constraints::new();
+            for dof in 0..number_of_dofs {
+                if let Some(line) = constraints.get_line(dof) {
+                    scaled.add_line(dof, line.get_entries(), factor * line.get_inhomogeneity())?;
+                }
+            }
+            let system = HyperelasticSystem {
+                tangent: &tangent,
+                block: &vector_block,
+                constraints: &scaled,
+                homogeneous: &homogeneous,
+                load: load.iter().map(|&f| factor * f).collect(),
+            };
+            let result = newton.solve(&system, &mut u)?;
+            if !result.is_converged() {
+                return Err(Error::NotConverged("Newton iterations did not converge"));
+            }
+            scaled.distribute(&mut u);
+            results.push(result);
+        }
+        let d = self.elasticity.get_hypothesis().get_dimension();
+        let displacement = (0..d)
+            .map(|c| {
+                FEFunction::new(
+                    &format!("displacement_{}", c),
+                    self.elasticity.get_element(),
+                    block,
+                    u.iter().skip(c).step_by(d).copied().collect(),
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(HyperelasticSolution {
+            displacement,
+            results,
+        })
+    }
+}
+
+/// Nonlinear system of a load step, the state being the displacement with its constrained dofs
+/// given by the constraints of the step
+struct HyperelasticSystem<'s, CoordType, DataType, KernelT, const N: usize> {
+    tangent: &'s AutomaticTangent<CoordType, DataType, KernelT, N>,
+    block: &'s CellBlock<'s, CoordType, DataType>,
+    constraints: &'s Constraints<DataType>,
+    homogeneous: &'s Constraints<DataType>,
+    load: Vec<DataType>,
+}
+
+impl<CoordType, DataType, KernelT, const N: usize> NonlinearProblem<DataType>
+    for HyperelasticSystem<'_, CoordType, DataType, KernelT, N>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float,
+    KernelT: ResidualKernel<CoordType, DataType>,
+{
+    fn get_size(&self) -> usize {
+        self.load.len()
+    }
+
+    fn compute_residual(&self, u: &[DataType], residual: &mut [DataType]) -> Result<(), Error> {
+        let mut state = u.to_vec();
+        self.constraints.distribute(&mut state);
+        let internal = Assembler::new(self.get_size()).assemble_residual(
+            self.tangent.get_kernel(),
+            self.block,
+            &state,
+        )?;
+        for ((r, &f), &l) in residual.iter_mut().zip(&internal).zip(&self.load) {
+            *r = f - l;
+        }
+        self.homogeneous.condense(residual);
+        if residual.iter().any(|value| !value.is_finite()) {
+            return Err(Error::NotFinite(
+                "Residual is not finite, a cell is degenerate or inverted",
+            ));
+        }
+        Ok(())
+    }
+
+    fn compute_jacobian(&self, u: &[DataType]) -> Result<CsrMatrix<DataType>, Error> {
+        let mut state = u.to_vec();
+        self.constraints.distribute(&mut state);
+        let cell_state = self.block.gather(&state)?;
+        let mut state_block = CellBlock::new(
+            self.block.get_dofs_per_cell(),
+            self.block.get_connectivity(),
+            self.block.get_coordinates(),
+        )?;
+        state_block.add_field(self.tangent.get_state_field(), &cell_state)?;
+        let (jacobian, _) = Assembler::new(self.get_size()).assemble_constrained(
+            self.tangent,
+            &state_block,
+            self.homogeneous,
+        )?;
+        Ok(jacobian)
+    }
+}
+
+/// Compute the inverse and the determinant of a square matrix of dimension 1, 2 or 3, None if it
+/// is singular
+fn invert<DataType: Float>(
+    matrix: &[DataType],
+    dimension: usize,
+) -> Option<(Vec<DataType>, DataType)> {
+    let m = matrix;
+    let (cofactors, determinant) = match dimension {
+        1 => (vec![DataType::one()], m[0]),
+        2 => (vec![m[3], -m[1], -m[2], m[0]], m[0] * m[3] - m[1] * m[2]),
+        3 => {
+            let cofactors = vec![
+                m[4] * m[8] - m[5] * m[7],
+                m[2] * m[7] - m[1] * m[8],
+                m[1] * m[5] - m[2] * m[4],
+                m[5] * m[6] - m[3] * m[8],
+                m[0] * m[8] - m[2] * m[6],
+                m[2] * m[3] - m[0] * m[5],
+                m[3] * m[7] - m[4] * m[6],
+                m[1] * m[6] - m[0] * m[7],
+                m[0] * m[4] - m[1] * m[3],
+            ];
+            let determinant = m[0] * cofactors[0] + m[1] * cofactors[3] + m[2] * cofactors[6];
+            (cofactors, determinant)
+        }
+        _ => return None,
+    };
+    if determinant == DataType::zero() {
+        return None;
+    }
+    Some((
+        cofactors.into_iter().map(|c| c / determinant).collect(),
+        determinant,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HyperelasticProblem, NeoHookeanKernel};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::element::operator_trait::Operator;
+    use crate::element::residual_trait::{AutomaticTangent, ResidualKernel};
+    use crate::models::elasticity::{
+        ElasticityHypothesis, ElasticityOperator, ElasticityProblem, IsotropicMaterial,
+    };
+    use crate::post::boundary::FacetGroup;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+    use std::collections::HashMap;
+
+    const TOL: f64 = 1e-9;
+
+    #[test]
+    fn test_kernel() {
+        let element = BilinearQuadrilateralElement::new();
+        let material = IsotropicMaterial::new(2.5, 0.25).unwrap();
+        let hypothesis = ElasticityHypothesis::PlaneStrain;
+        let (lambda, mu) = material.get_lame_coefficients(hypothesis);
+        let kernel = NeoHookeanKernel::new(&element, 2, lambda, mu);
+        let geometry = [0.0, 0.0, 1.0, 0.1, 1.2, 0.9, -0.1, 1.0];
+        let tangent = AutomaticTangent::<_, _, _, 8>::new(kernel, "displacement").unwrap();
+        let linear = ElasticityOperator::new(&element, &material, hypothesis)
+            .compute(&geometry, &HashMap::new());
+        for (value, expected) in tangent
+            .compute(&geometry, &HashMap::new())
+            .iter()
+            .zip(&linear)
+        {
+            assert!(
+                (value - expected).abs() < TOL,
+                "Tangent at the reference configuration is not the linear stiffness"
+            );
+        }
+        let state = [0.0, 0.0, 0.2, -0.05, 0.15, 0.1, -0.05, 0.1];
+        let data = HashMap::from([("displacement".to_string(), &state[..])]);
+        let (_, jacobian) = tangent.compute_residual_and_tangent(&geometry, &data);
+        let kernel = tangent.get_kernel();
+        let h = 1e-6;
+        for column in 0..8 {
+            let (mut forward, mut backward) = (state, state);
+            forward[column] += h;
+            backward[column] -= h;
+            let forward = kernel.compute_residual(&geometry, &data, &forward);
+            let backward = kernel.compute_residual(&geometry, &data, &backward);
+            for row in 0..8 {
+                let difference = (forward[row] - backward[row]) / (2.0 * h);
+                assert!(
+                    (jacobian[row * 8 + column] - difference).abs() < 1e-6,
+                    "Incorrect jacobian entry ({}, {})",
+                    row,
+                    column
+                );
+            }
+        }
+        let inverted = [0.0f64, 0.0, -2.0, 0.0, -2.0, 0.0, 0.0, 0.0];
+        assert!(
+            kernel
+                .compute_residual(&geometry, &data, &inverted)
+                .iter()
+                .all(|r| r.is_nan()),
+            "Inverted cell not detected"
+        );
+    }
+
+    /// Plane strain stretch of the unit square by `δ` along `x`, held by rollers on its left and
+    /// bottom sides, of homogeneous deformation `F = diag(1 + δ, s)` with `P_yy = 0`
+    #[test]
+    fn test_uniaxial_stretch() {
+        let stretch = 0.5;
+        let (dofs, coords) = uniform_quadrilaterals(3);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let material = IsotropicMaterial::new(2.5, 0.25).unwrap();
+        let hypothesis = ElasticityHypothesis::PlaneStrain;
+        let (lambda, mu) = material.get_lame_coefficients(hypothesis);
+        let mut elasticity =
+            ElasticityProblem::new(&element, &block, &facets, material, hypothesis).unwrap();
+        let group = |name, predicate: fn(&[f64]) -> bool| {
+            FacetGroup::from_boundary(name, &facets, &block, predicate)
+        };
+        elasticity.add_displacement(group("left", |x| x[0] < 1e-12), &[0], |_| vec![0.0; 2]);
+        elasticity.add_displacement(group("bottom", |x| x[1] < 1e-12), &[1], |_| vec![0.0; 2]);
+        elasticity.add_displacement(group("right", |x| x[0] > 1.0 - 1e-12), &[0], move |_| {
+            vec![stretch, 0.0]
+        });
+        let mut problem = HyperelasticProblem::<_, _, _, 8>::new(elasticity).unwrap();
+        problem.set_number_of_load_steps(2);
+        let solution = problem.solve().unwrap();
+        let stress = |s: f64| mu * (s - 1.0 / s) + lambda * ((1.0 + stretch) * s).ln() / s;
+        let mut s = 1.0;
+        for _ in 0..50 {
+            let h = 1e-7;
+            s -= stress(s) * 2.0 * h / (stress(s + h) - stress(s - h));
+        }
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            assert!(
+                (solution.get_displacement()[0].get_coefficients()[dof] - stretch * x[0]).abs()
+                    < TOL
+                    && (solution.get_displacement()[1].get_coefficients()[dof] - (s - 1.0) * x[1])
+                        .abs()
+                        < TOL,
+                "Incorrect homogeneous deformation"
+            );
+        }
+        for result in solution.get_results() {
+            assert!(
+                result.get_iterations() <= 6,
+                "Newton iterations not quadratically convergent"
+            );
+        }
+        let history = solution.get_results()[0].get_history();
+        let n = history.len();
+        assert!(
+            history[n - 1] / history[n - 2] < 1e-3,
+            "Newton iterations not quadratically convergent"
+        );
+        assert!(
+            HyperelasticProblem::<_, _, _, 6>::new(
+                ElasticityProblem::new(&element, &block, &facets, material, hypothesis).unwrap()
+            )
+            .is_err(),
+            "Wrong number of variables accepted"
+        );
+    }
+}
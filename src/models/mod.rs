@@ -0,0 +1,23 @@
+/// Module for the ready-made Poisson problem
+pub mod poisson;
+
+/// Module for the ready-made small strain linear elasticity problem
+pub mod elasticity;
+
+/// Module for the ready-made transient heat conduction problem
+pub mod heat;
+
+/// Module for the ready-made Stokes problem on mixed velocity and pressure elements
+pub mod stokes;
+
+/// Module for the ready-made advection-diffusion problem stabilized along the streamlines
+pub mod advection;
+
+/// Module for the ready-made frequency domain Helmholtz problem with complex values
+pub mod helmholtz;
+
+/// Module for the ready-made linear elastodynamics problem with absorbing boundaries
+pub mod elastodynamics;
+
+/// Module for the ready-made finite strain hyperelastic problem
+pub mod hyperelasticity;
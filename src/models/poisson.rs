@@ -0,0 +1,582 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::element::operator_trait::Operator;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::post::boundary::FacetGroup;
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::{
+    check_block, compute_shape_gradients, compute_shape_gradients_batch,
+    compute_shape_gradients_into, flag_degenerate_lanes, get_embedding_dimension, map_to_physical,
+    FEFunction,
+};
+use crate::solver::registry::{SolverConfiguration, SolverRegistry};
+use num::Float;
+use std::collections::HashMap;
+
+/// Data of the problem given as a function of the real coordinates
+type PointFunction<'a, DataType> = Box<dyn Fn(&[DataType]) -> DataType + 'a>;
+
+/// Condensed matrix and right hand side of a linear system along with its constraints
+type CondensedSystem<DataType> = (CsrMatrix<DataType>, Vec<DataType>, Constraints<DataType>);
+
+/// Stiffness matrix of `-div(κ grad u)` computed with the shape functions of an element
+///
+/// # Explanation
+///
+/// The conductivity `κ` is read from the "conductivity" data of the cells and is unit when absent.
+/// The local matrix of a degenerate cell holds NaN values.
+pub struct DiffusionOperator<'a, ElementT> {
+    element: &'a ElementT,
+}
+
+impl<'a, ElementT> DiffusionOperator<'a, ElementT> {
+    /// Constructor
+    pub fn new(element: &'a ElementT) -> DiffusionOperator<'a, ElementT> {
+        DiffusionOperator { element }
+    }
+}
+
+impl<CoordType, DataType, ElementT> Operator<CoordType, DataType>
+    for DiffusionOperator<'_, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    type ElementT = ElementT;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> Vec<DataType> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+        workspace: &mut Workspace<DataType>,
+        local: &mut Vec<DataType>,
+    ) {
+        let n = self.element.get_shape_basis().get_number_of_bases();
+        let nips = self.element.get_integrator().get_number_of_points();
+        let embedding = geometry.len() / n;
+        let conductivity = data
+            .get("conductivity")
+            .map_or(DataType::one(), |values| values[0]);
+        let [gradients, weights] = workspace.get_buffers([nips * geometry.len(), nips]);
+        local.clear();
+        if !compute_shape_gradients_into(self.element, geometry, gradients, weights) {
+            local.resize(n * n, DataType::nan());
+            return;
+        }
+        local.resize(n * n, DataType::zero());
+        for (point_gradients, &weight) in gradients.chunks(geometry.len()).zip(weights.iter()) {
+            for (a, ga) in point_gradients.chunks(embedding).enumerate() {
+                for (b, gb) in point_gradients.chunks(embedding).enumerate() {
+                    let product = ga
+                        .iter()
+                        .zip(gb)
+                        .fold(DataType::zero(), |sum, (&x, &y)| sum + x * y);
+                    local[a * n + b] = local[a * n + b] + weight * conductivity * product;
+                }
+            }
+        }
+    }
+
+    fn compute_batch<const LANES: usize>(
+        &self,
+        geometry: &[[CoordType; LANES]],
+        data: &HashMap<String, &[[DataType; LANES]]>,
+    ) -> Vec<[DataType; LANES]> {
+        let n = self.element.get_shape_basis().get_number_of_bases();
+        let nips = self.element.get_integrator().get_number_of_points();
+        let embedding = geometry.len() / n;
+        let conductivity = data
+            .get("conductivity")
+            .map_or([DataType::one(); LANES], |values| values[0]);
+        let mut gradients = vec![[DataType::zero(); LANES]; nips * geometry.len()];
+        let mut weights = vec![[DataType::zero(); LANES]; nips];
+        let regular =
+            compute_shape_gradients_batch(self.element, geometry, &mut gradients, &mut weights);
+        let mut local = vec![[DataType::zero(); LANES]; n * n];
+        for (point_gradients, weight) in gradients.chunks(geometry.len()).zip(&weights) {
+            let scale: [DataType; LANES] =
+                std::array::from_fn(|lane| weight[lane] * conductivity[lane]);
+            for (a, ga) in point_gradients.chunks(embedding).enumerate() {
+                for (b, gb) in point_gradients.chunks(embedding).enumerate() {
+                    let entry = &mut local[a * n + b];
+                    for (x, y) in ga.iter().zip(gb) {
+                        for lane in 0..LANES {
+                            entry[lane] = entry[lane] + scale[lane] * x[lane] * y[lane];
+                        }
+                    }
+                }
+            }
+        }
+        flag_degenerate_lanes(&mut local, regular);
+        local
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Ready-made Poisson problem `-div(κ grad u) = f` with Dirichlet and Neumann boundary data
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the solution is encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// The problem wires the assembly of the diffusion operator and of the load, the Dirichlet
+/// constraints and the solve through the solver registry behind a small configuration. The
+/// conductivity `κ` is read from the "conductivity" data of the cells and is unit when absent.
+/// The Dirichlet values are imposed at the nodes of the facets of their groups. The Neumann fluxes
+/// `κ grad u . n` and the source are integrated with the quadrature of the facets and the
+/// integration rule of the element respectively. The linear system is solved by conjugate
+/// gradients preconditioned by Jacobi unless configured otherwise.
+pub struct PoissonProblem<'a, CoordType, DataType, ElementT> {
+    element: &'a ElementT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    reference_facets: &'a ReferenceFacets<CoordType>,
+    source: PointFunction<'a, DataType>,
+    dirichlet: Vec<(FacetGroup, PointFunction<'a, DataType>)>,
+    neumann: Vec<(FacetGroup, PointFunction<'a, DataType>)>,
+    solver: SolverConfiguration,
+}
+
+impl<'a, CoordType, DataType, ElementT> PoissonProblem<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar + Float,
+    DataType: Scalar + Float + From<CoordType> + 'static,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor of the problem without source nor boundary data
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells of the domain
+    /// * `reference_facets`: the facets of the reference element
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the problem or an error if the block does not match the element
+    pub fn new(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        reference_facets: &'a ReferenceFacets<CoordType>,
+    ) -> Result<PoissonProblem<'a, CoordType, DataType, ElementT>, Error> {
+        check_block(element, block)?;
+        Ok(PoissonProblem {
+            element,
+            block,
+            reference_facets,
+            source: Box::new(|_| DataType::zero()),
+            dirichlet: Vec::new(),
+            neumann: Vec::new(),
+            solver: SolverConfiguration::new("cg", "jacobi"),
+        })
+    }
+
+    /// Set the source `f` as a function of the real coordinates
+    pub fn set_source(&mut self, source: impl Fn(&[DataType]) -> DataType + 'a) {
+        self.source = Box::new(source);
+    }
+
+    /// Impose the value of the solution on a group of facets
+    pub fn add_dirichlet(
+        &mut self,
+        group: FacetGroup,
+        value: impl Fn(&[DataType]) -> DataType + 'a,
+    ) {
+        self.dirichlet.push((group, Box::new(value)));
+    }
+
+    /// Impose the flux `κ grad u . n` on a group of facets
+    pub fn add_neumann(&mut self, group: FacetGroup, flux: impl Fn(&[DataType]) -> DataType + 'a) {
+        self.neumann.push((group, Box::new(flux)));
+    }
+
+    /// Set the selection of the solver and preconditioner of the registry
+    pub fn set_solver(&mut self, solver: SolverConfiguration) {
+        self.solver = solver;
+    }
+
+    /// Get the number of dofs of the problem
+    pub fn get_number_of_dofs(&self) -> usize {
+        self.block
+            .get_connectivity()
+            .iter()
+            .max()
+            .map_or(0, |&dof| dof + 1)
+    }
+
+    /// Get the Dirichlet constraints imposed by the groups of facets
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the constraints or an error if a facet is out of the block
+    pub fn get_constraints(&self) -> Result<Constraints<DataType>, Error> {
+        let groups: Vec<&FacetGroup> = self.dirichlet.iter().map(|(group, _)| group).collect();
+        compute_dirichlet_constraints(
+            self.element,
+            self.block,
+            self.reference_facets,
+            &groups,
+            |g, x| (self.dirichlet[g].1)(x),
+        )
+    }
+
+    /// Assemble the load vector of the source and of the Neumann fluxes without constraints
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the load or an error if a cell or a facet is degenerate
+    pub fn assemble_load(&self) -> Result<Vec<DataType>, Error> {
+        let groups: Vec<&FacetGroup> = self.neumann.iter().map(|(group, _)| group).collect();
+        assemble_scalar_load(
+            self.element,
+            self.block,
+            self.reference_facets,
+            &self.source,
+            &groups,
+            |g, x| (self.neumann[g].1)(x),
+        )
+    }
+
+    /// Assemble the linear system condensed by the Dirichlet constraints
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the condensed matrix, right hand side and constraints, or an
+    ///   error if the inputs are not consistent or a cell is degenerate
+    pub fn assemble(&self) -> Result<CondensedSystem<DataType>, Error> {
+        let constraints = self.get_constraints()?;
+        let assembler = Assembler::new(self.get_number_of_dofs());
+        let (matrix, mut rhs) = assembler.assemble_constrained(
+            &DiffusionOperator::new(self.element),
+            self.block,
+            &constraints,
+        )?;
+        let mut load = self.assemble_load()?;
+        constraints.condense(&mut load);
+        for (r, &l) in rhs.iter_mut().zip(&load) {
+            *r = *r + l;
+        }
+        Ok((matrix, rhs, constraints))
+    }
+
+    /// Solve the problem
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the solution, named "u", or an error if the assembly failed, the
+    ///   solver is unknown or the solve did not converge
+    pub fn solve(&self) -> Result<FEFunction<'a, CoordType, DataType, ElementT>, Error> {
+        let (matrix, rhs, constraints) = self.assemble()?;
+        let registry = SolverRegistry::new();
+        let solver = registry.build(&self.solver, &matrix)?;
+        let mut solution = vec![DataType::zero(); rhs.len()];
+        if !solver.solve(&rhs, &mut solution).is_converged() {
+            return Err(Error::NotConverged("Solve did not converge"));
+        }
+        constraints.distribute(&mut solution);
+        FEFunction::new("u", self.element, self.block, solution)
+    }
+}
+
+/// Constrain the dofs of the nodes of groups of facets to values given by their coordinates
+///
+/// # Arguments
+///
+/// * `groups`: the groups of facets
+/// * `value`: computes the value imposed by a group, given by its index, at a point, the values
+///   may be of another type than the coordinates, such as complex values
+///
+/// # Returns
+///
+/// * A result either holding the constraints or an error if a facet is out of the block
+pub(crate) fn compute_dirichlet_constraints<CoordType, DataType, ValueType, ElementT>(
+    element: &ElementT,
+    block: &CellBlock<CoordType, DataType>,
+    reference_facets: &ReferenceFacets<CoordType>,
+    groups: &[&FacetGroup],
+    value: impl Fn(usize, &[DataType]) -> ValueType,
+) -> Result<Constraints<ValueType>, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ValueType: Scalar,
+    ElementT: Element<CoordType, DataType>,
+{
+    let embedding = get_embedding_dimension(element, block);
+    let mut constraints = Constraints::new();
+    for (g, group) in groups.iter().enumerate() {
+        for &(cell, facet) in group.get_facets() {
+            reference_facets.check_facet(block, cell, facet)?;
+            let dofs = block.get_cell_dofs(cell);
+            let coordinates = block.get_cell_coordinates(cell);
+            for &node in reference_facets.get_facet(facet) {
+                if constraints.is_constrained(dofs[node]) {
+                    continue;
+                }
+                let point: Vec<DataType> = coordinates[node * embedding..(node + 1) * embedding]
+                    .iter()
+                    .map(|&x| x.into())
+                    .collect();
+                constraints.add_dirichlet(dofs[node], value(g, &point))?;
+            }
+        }
+    }
+    Ok(constraints)
+}
+
+/// Assemble the load vector of a source and of fluxes through groups of facets
+///
+/// # Arguments
+///
+/// * `source`: the source as a function of the real coordinates
+/// * `groups`: the groups of facets
+/// * `flux`: computes the flux through a group, given by its index, at a point
+///
+/// # Returns
+///
+/// * A result either holding the load or an error if a cell or a facet is degenerate
+pub(crate) fn assemble_scalar_load<CoordType, DataType, ElementT>(
+    element: &ElementT,
+    block: &CellBlock<CoordType, DataType>,
+    reference_facets: &ReferenceFacets<CoordType>,
+    source: impl Fn(&[DataType]) -> DataType,
+    groups: &[&FacetGroup],
+    flux: impl Fn(usize, &[DataType]) -> DataType,
+) -> Result<Vec<DataType>, Error>
+where
+    CoordType: Scalar + Float,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let basis = element.get_shape_basis();
+    let nbases = basis.get_number_of_bases();
+    let number_of_dofs = block
+        .get_connectivity()
+        .iter()
+        .max()
+        .map_or(0, |&dof| dof + 1);
+    let mut load = vec![DataType::zero(); number_of_dofs];
+    for cell in 0..block.get_number_of_cells() {
+        let dofs = block.get_cell_dofs(cell);
+        let points = compute_shape_gradients(element, block.get_cell_coordinates(cell))
+            .ok_or(Error::DegenerateCell { cell })?;
+        for (shapes, (_, weight)) in element
+            .get_shapes_for_integration()
+            .chunks(nbases)
+            .zip(points)
+        {
+            let point = map_to_physical(element, block, cell, shapes);
+            let value = weight * source(&point);
+            for (&dof, &shape) in dofs.iter().zip(shapes) {
+                load[dof] = load[dof] + value * shape;
+            }
+        }
+    }
+    for (g, group) in groups.iter().enumerate() {
+        for &(cell, facet) in group.get_facets() {
+            reference_facets.check_facet(block, cell, facet)?;
+            let dofs = block.get_cell_dofs(cell);
+            for point in reference_facets.compute_quadrature(element, block, cell, facet)? {
+                let value = point.weight * flux(g, &point.point);
+                for (&dof, &shape) in dofs.iter().zip(&point.shapes) {
+                    load[dof] = load[dof] + value * shape;
+                }
+            }
+        }
+    }
+    Ok(load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffusionOperator, PoissonProblem};
+    use crate::assembly::assembler::Assembler;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::element::operator_trait::Operator;
+    use crate::element::workspace::Workspace;
+    use crate::post::boundary::FacetGroup;
+    use crate::post::norms::{compute_convergence_rate, compute_errors};
+    use crate::solver::registry::SolverConfiguration;
+    use crate::test_utils::{
+        biquadratic_quadrilateral_facets, quadrilateral_facets, uniform_quadrilaterals,
+        BilinearQuadrilateralElement, BiquadraticQuadrilateral, BiquadraticQuadrilateralElement,
+    };
+    use std::collections::HashMap;
+    use std::f64::consts::PI;
+
+    const TOL: f64 = 1e-10;
+
+    /// L2 error of the solution of `-Δu = 2π² sin(πx) sin(πy)` on `n` by `n` quadrilaterals
+    fn sine_error(n: usize) -> f64 {
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let mut problem = PoissonProblem::new(&element, &block, &facets).unwrap();
+        problem.set_source(|x| 2.0 * PI * PI * (PI * x[0]).sin() * (PI * x[1]).sin());
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("boundary", &facets, &block, |_| true),
+            |_| 0.0,
+        );
+        let mut solver = SolverConfiguration::new("cg", "jacobi");
+        solver.set_parameter("relative_tolerance", 1e-12);
+        problem.set_solver(solver);
+        let u = problem.solve().unwrap();
+        compute_errors(
+            &u,
+            |x| (PI * x[0]).sin() * (PI * x[1]).sin(),
+            |x| {
+                vec![
+                    PI * (PI * x[0]).cos() * (PI * x[1]).sin(),
+                    PI * (PI * x[0]).sin() * (PI * x[1]).cos(),
+                ]
+            },
+        )
+        .unwrap()
+        .get_l2_error()
+    }
+
+    #[test]
+    fn test_linear_solution() {
+        let n = 4;
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let conductivity = vec![2.0; n * n];
+        let mut block = CellBlock::new(4, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let mut problem = PoissonProblem::new(&element, &block, &facets).unwrap();
+        let exact = |x: &[f64]| 1.0 + x[0] + 2.0 * x[1];
+        problem.add_dirichlet(
+            FacetGroup::from_boundary("sides", &facets, &block, |x| {
+                x[0] < 1e-12 || x[0] > 1.0 - 1e-12 || x[1] < 1e-12
+            }),
+            exact,
+        );
+        problem.add_neumann(
+            FacetGroup::from_boundary("top", &facets, &block, |x| x[1] > 1.0 - 1e-12),
+            |_| 4.0,
+        );
+        assert_eq!(problem.get_number_of_dofs(), 25, "Incorrect number of dofs");
+        let mut solver = SolverConfiguration::new("preonly", "cholesky");
+        solver.set_parameter("relative_tolerance", 1e-12);
+        problem.set_solver(solver);
+        let u = problem.solve().unwrap();
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            assert!(
+                (u.get_coefficients()[dof] - exact(x)).abs() < TOL,
+                "Incorrect solution"
+            );
+        }
+        problem.set_solver(SolverConfiguration::new("unknown", "none"));
+        assert!(problem.solve().is_err(), "Unknown solver accepted");
+    }
+
+    #[test]
+    fn test_compute_into() {
+        let (dofs, coords) = uniform_quadrilaterals(2);
+        let block = CellBlock::<f64, f64>::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let operator = DiffusionOperator::new(&element);
+        let mut workspace = Workspace::new();
+        let mut local = Vec::new();
+        let mut capacity = 0;
+        for cell in 0..block.get_number_of_cells() {
+            let geometry = block.get_cell_coordinates(cell);
+            operator.compute_into(geometry, &HashMap::new(), &mut workspace, &mut local);
+            for (a, b, expected) in [(0, 0, 2.0 / 3.0), (0, 1, -1.0 / 6.0), (0, 2, -1.0 / 3.0)] {
+                assert!(
+                    (local[a * 4 + b] - expected).abs() < TOL,
+                    "Incorrect local matrix"
+                );
+            }
+            if cell == 0 {
+                capacity = workspace.get_capacity();
+            }
+            assert_eq!(workspace.get_capacity(), capacity, "Workspace reallocated");
+        }
+        let collapsed = [0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        operator.compute_into(&collapsed, &HashMap::new(), &mut workspace, &mut local);
+        assert!(
+            local.len() == 16 && local.iter().all(|value| value.is_nan()),
+            "Degenerate cell not flagged"
+        );
+    }
+
+    #[test]
+    fn test_compute_batch() {
+        let (dofs, mut coords) = uniform_quadrilaterals(3);
+        for x in coords.chunks_mut(2) {
+            x[0] += 0.1 * x[1] * x[1];
+        }
+        let conductivity: Vec<f64> = (0..9).map(|c| 1.0 + c as f64).collect();
+        let mut block = CellBlock::new(4, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let operator = DiffusionOperator::new(&element);
+        let assembler = Assembler::new(16);
+        let reference = assembler.assemble(&operator, &block).unwrap();
+        let batched = assembler
+            .assemble_batched::<4, _, _, _>(&operator, &block)
+            .unwrap();
+        for (r, b) in reference.get_values().iter().zip(batched.get_values()) {
+            assert!((r - b).abs() < TOL, "Incorrect batched value");
+        }
+        let mut geometry = vec![[0.0; 2]; 8];
+        for (i, x) in block.get_cell_coordinates(0).iter().enumerate() {
+            geometry[i][0] = *x;
+        }
+        let local = operator.compute_batch(&geometry, &HashMap::new());
+        assert!(
+            local
+                .iter()
+                .all(|entry| entry[0].is_finite() && entry[1].is_nan()),
+            "Degenerate lane not flagged"
+        );
+    }
+
+    #[test]
+    fn test_quadratic_neumann_load() {
+        let element = BiquadraticQuadrilateralElement::new();
+        let facets = biquadratic_quadrilateral_facets();
+        let nodes: Vec<f64> = BiquadraticQuadrilateral::NODES.concat();
+        let dofs: Vec<usize> = (0..9).collect();
+        let coords: Vec<f64> = nodes.iter().map(|&x| 0.5 * (x + 1.0)).collect();
+        let block = CellBlock::new(9, &dofs, &coords).unwrap();
+        let mut problem = PoissonProblem::new(&element, &block, &facets).unwrap();
+        problem.add_neumann(FacetGroup::new("bottom", vec![(0, 0)]), |x| x[0]);
+        let load = problem.assemble_load().unwrap();
+        let expected = [0.0, 1.0 / 6.0, 0.0, 0.0, 1.0 / 3.0, 0.0, 0.0, 0.0, 0.0];
+        for (&l, &e) in load.iter().zip(&expected) {
+            assert!((l - e).abs() < TOL, "Incorrect quadratic Neumann load");
+        }
+    }
+
+    #[test]
+    fn test_convergence() {
+        let rate = compute_convergence_rate(sine_error(8), sine_error(16), 2.0);
+        assert!((rate - 2.0).abs() < 0.1, "Incorrect convergence rate");
+    }
+}
@@ -0,0 +1,724 @@
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::element::operator_trait::Operator;
+use crate::element::workspace::Workspace;
+use crate::error::Error;
+use crate::models::heat::MassOperator;
+use crate::post::boundary::FacetGroup;
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::{
+    check_block, compute_shape_gradients, compute_shape_gradients_into, get_embedding_dimension,
+    map_to_physical, FEFunction,
+};
+use crate::solver::block::{BlockStructure, BlockTriangularPreconditioner, Triangle};
+use crate::solver::direct::SparseLu;
+use crate::solver::gmres::Gmres;
+use crate::solver::solver_traits::{IterationControl, Preconditioner, SolverResult};
+use num::Float;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Vector data of the problem given as a function of the real coordinates
+type VectorFunction<'a, DataType> = Box<dyn Fn(&[DataType]) -> Vec<DataType> + 'a>;
+
+/// Local matrix `[A B^T; B 0]` of the Stokes equations `-μ Δu + grad p = f`, `-div u = 0` computed
+/// with a velocity and a pressure element sharing their cells
+///
+/// # Explanation
+///
+/// The local dofs hold the interleaved components of the velocity at the nodes of the velocity
+/// element, the dof of component `i` of node `a` being `a * dimension + i`, followed by the
+/// pressure at the nodes of the pressure element. The geometry of the cells is given by the nodes
+/// of the velocity element, whose integration rule is used with the pressure shapes evaluated at
+/// its points. The local matrix of a degenerate cell holds NaN values.
+pub struct StokesOperator<'a, CoordType, DataType, VelocityT, PressureT> {
+    velocity: &'a VelocityT,
+    pressure: &'a PressureT,
+    viscosity: DataType,
+    pressure_shapes: Vec<DataType>,
+    coordinates: PhantomData<CoordType>,
+}
+
+impl<'a, CoordType, DataType, VelocityT, PressureT>
+    StokesOperator<'a, CoordType, DataType, VelocityT, PressureT>
+where
+    CoordType: Scalar,
+    DataType: Scalar,
+    VelocityT: Element<CoordType, DataType>,
+    PressureT: Element<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `velocity`: the element of the velocity
+    /// * `pressure`: the element of the pressure
+    /// * `viscosity`: the dynamic viscosity `μ`
+    pub fn new(
+        velocity: &'a VelocityT,
+        pressure: &'a PressureT,
+        viscosity: DataType,
+    ) -> StokesOperator<'a, CoordType, DataType, VelocityT, PressureT> {
+        let integrator = velocity.get_integrator();
+        let pressure_shapes = integrator
+            .get_points()
+            .chunks(integrator.get_dimension())
+            .flat_map(|point| pressure.get_shape_basis().interpolate_basis(point))
+            .collect();
+        StokesOperator {
+            velocity,
+            pressure,
+            viscosity,
+            pressure_shapes,
+            coordinates: PhantomData,
+        }
+    }
+}
+
+impl<CoordType, DataType, VelocityT, PressureT> Operator<CoordType, DataType>
+    for StokesOperator<'_, CoordType, DataType, VelocityT, PressureT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    VelocityT: Element<CoordType, DataType>,
+    PressureT: Element<CoordType, DataType>,
+{
+    type ElementT = VelocityT;
+
+    fn compute(
+        &self,
+        geometry: &[CoordType],
+        data: &HashMap<String, &[DataType]>,
+    ) -> Vec<DataType> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[CoordType],
+        _data: &HashMap<String, &[DataType]>,
+        workspace: &mut Workspace<DataType>,
+        local: &mut Vec<DataType>,
+    ) {
+        let nu = self.velocity.get_shape_basis().get_number_of_bases();
+        let np = self.pressure.get_shape_basis().get_number_of_bases();
+        let nips = self.velocity.get_integrator().get_number_of_points();
+        let d = geometry.len() / nu;
+        let n = nu * d + np;
+        let [gradients, weights] = workspace.get_buffers([nips * geometry.len(), nips]);
+        local.clear();
+        if !compute_shape_gradients_into(self.velocity, geometry, gradients, weights) {
+            local.resize(n * n, DataType::nan());
+            return;
+        }
+        local.resize(n * n, DataType::zero());
+        for ((point_gradients, &weight), shapes) in gradients
+            .chunks(geometry.len())
+            .zip(weights.iter())
+            .zip(self.pressure_shapes.chunks(np))
+        {
+            for (a, ga) in point_gradients.chunks(d).enumerate() {
+                for (b, gb) in point_gradients.chunks(d).enumerate() {
+                    let product = ga
+                        .iter()
+                        .zip(gb)
+                        .fold(DataType::zero(), |sum, (&x, &y)| sum + x * y);
+                    for i in 0..d {
+                        let entry = (a * d + i) * n + b * d + i;
+                        local[entry] = local[entry] + weight * self.viscosity * product;
+                    }
+                }
+                for (q, &shape) in shapes.iter().enumerate() {
+                    for (i, &g) in ga.iter().enumerate() {
+                        let value = weight * shape * g;
+                        let (row, column) = (nu * d + q, a * d + i);
+                        local[row * n + column] = local[row * n + column] - value;
+                        local[column * n + row] = local[column * n + row] - value;
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Builder of a StokesOperator
+///
+/// # Explanation
+///
+/// The viscosity defaults to one and should be positive. The elements should share their
+/// dimension, and the pressure element should have fewer nodes than the velocity element, as the
+/// equal order pairs are not stable and give singular or oscillating pressures.
+pub struct StokesOperatorBuilder<'a, DataType, VelocityT, PressureT> {
+    velocity: &'a VelocityT,
+    pressure: &'a PressureT,
+    viscosity: DataType,
+}
+
+impl<'a, DataType: Float, VelocityT, PressureT>
+    StokesOperatorBuilder<'a, DataType, VelocityT, PressureT>
+{
+    /// Constructor of a builder of unit viscosity
+    ///
+    /// # Arguments
+    ///
+    /// * `velocity`: the element of the velocity
+    /// * `pressure`: the element of the pressure
+    pub fn new(
+        velocity: &'a VelocityT,
+        pressure: &'a PressureT,
+    ) -> StokesOperatorBuilder<'a, DataType, VelocityT, PressureT> {
+        StokesOperatorBuilder {
+            velocity,
+            pressure,
+            viscosity: DataType::one(),
+        }
+    }
+
+    /// Set the dynamic viscosity `μ`
+    pub fn viscosity(mut self, viscosity: DataType) -> Self {
+        self.viscosity = viscosity;
+        self
+    }
+
+    /// Build the operator
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the operator or an error if the viscosity is not positive or the
+    ///   elements are not a stable pair
+    pub fn build<CoordType>(
+        &self,
+    ) -> Result<StokesOperator<'a, CoordType, DataType, VelocityT, PressureT>, Error>
+    where
+        CoordType: Scalar,
+        DataType: Scalar,
+        VelocityT: Element<CoordType, DataType>,
+        PressureT: Element<CoordType, DataType>,
+    {
+        if !(self.viscosity > DataType::zero() && self.viscosity.is_finite()) {
+            return Err(Error::InvalidArgument("Viscosity should be positive"));
+        }
+        let (velocity, pressure) = (
+            self.velocity.get_shape_basis(),
+            self.pressure.get_shape_basis(),
+        );
+        if velocity.get_dimension() != pressure.get_dimension() {
+            return Err(Error::InvalidArgument(
+                "Velocity and pressure elements do not share their dimension",
+            ));
+        }
+        if pressure.get_number_of_bases() >= velocity.get_number_of_bases() {
+            return Err(Error::InvalidArgument(
+                "Pressure element should have fewer nodes than the velocity element",
+            ));
+        }
+        Ok(StokesOperator::new(
+            self.velocity,
+            self.pressure,
+            self.viscosity,
+        ))
+    }
+}
+
+/// Velocity and pressure of a Stokes problem along with the convergence of the solve
+pub struct StokesSolution<'a, CoordType, DataType, VelocityT, PressureT> {
+    velocity: Vec<FEFunction<'a, CoordType, DataType, VelocityT>>,
+    pressure: FEFunction<'a, CoordType, DataType, PressureT>,
+    result: SolverResult<DataType>,
+}
+
+impl<'a, CoordType, DataType, VelocityT, PressureT>
+    StokesSolution<'a, CoordType, DataType, VelocityT, PressureT>
+{
+    /// Get the components of the velocity, named `velocity_<component>`
+    pub fn get_velocity(&self) -> &[FEFunction<'a, CoordType, DataType, VelocityT>] {
+        &self.velocity
+    }
+
+    /// Get the pressure, named "pressure"
+    pub fn get_pressure(&self) -> &FEFunction<'a, CoordType, DataType, PressureT> {
+        &self.pressure
+    }
+
+    /// Get the convergence information of the solve
+    pub fn get_solver_result(&self) -> &SolverResult<DataType> {
+        &self.result
+    }
+}
+
+/// Ready-made Stokes problem `-μ Δu + grad p = f`, `div u = 0` on a stable velocity and pressure
+/// pair
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the solution is encoded with
+/// * VelocityT: the element of the velocity
+/// * PressureT: the element of the pressure
+///
+/// # Explanation
+///
+/// The cells are given twice, by a block of the nodes of the velocity element and by a block of
+/// the nodes of the pressure element listing the cells in the same order, as for the Taylor-Hood
+/// pair of quadratic velocities and linear pressures. The problem builds the block of the mixed
+/// dofs, numbered field by field: the interleaved velocity components first, the dof of component
+/// `i` of node `a` being `a * dimension + i`, then the pressures. The velocity supports impose the
+/// velocity at the nodes of the facets of their groups, which should list all the nodes of the
+/// facets of the velocity element; the other facets are free of the pseudo-traction
+/// `μ grad u . n - p n`. The saddle point system is solved by GMRES preconditioned by the upper
+/// block triangular preconditioner with the sparse LU factorization of the velocity block and
+/// the pressure mass matrix scaled by `-1 / μ` as approximation of the Schur complement, which
+/// bounds the number of iterations under mesh refinement. When the velocity is imposed on the
+/// whole boundary, the pressure is only defined up to a constant and should be normalized to
+/// zero mean.
+pub struct StokesProblem<'a, CoordType, DataType, VelocityT, PressureT> {
+    velocity: &'a VelocityT,
+    velocity_block: &'a CellBlock<'a, CoordType, DataType>,
+    pressure: &'a PressureT,
+    pressure_block: &'a CellBlock<'a, CoordType, DataType>,
+    viscosity: DataType,
+    body_force: VectorFunction<'a, DataType>,
+    supports: Vec<(FacetGroup, VectorFunction<'a, DataType>)>,
+    zero_mean_pressure: bool,
+    control: IterationControl<DataType>,
+    restart: usize,
+}
+
+impl<'a, CoordType, DataType, VelocityT, PressureT>
+    StokesProblem<'a, CoordType, DataType, VelocityT, PressureT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType> + 'static,
+    VelocityT: Element<CoordType, DataType>,
+    PressureT: Element<CoordType, DataType>,
+{
+    /// Constructor of the problem of unit viscosity without loads nor supports
+    ///
+    /// # Arguments
+    ///
+    /// * `velocity`: the element of the velocity
+    /// * `velocity_block`: the cells of the domain with the nodes of the velocity element
+    /// * `pressure`: the element of the pressure
+    /// * `pressure_block`: the same cells with the nodes of the pressure element
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the problem or an error if the blocks do not match the elements
+    ///   or each other
+    pub fn new(
+        velocity: &'a VelocityT,
+        velocity_block: &'a CellBlock<'a, CoordType, DataType>,
+        pressure: &'a PressureT,
+        pressure_block: &'a CellBlock<'a, CoordType, DataType>,
+    ) -> Result<StokesProblem<'a, CoordType, DataType, VelocityT, PressureT>, Error> {
+        check_block(velocity, velocity_block)?;
+        check_block(pressure, pressure_block)?;
+        if velocity_block.get_number_of_cells() != pressure_block.get_number_of_cells() {
+            return Err(Error::SizeMismatch {
+                context: "Velocity and pressure blocks do not have the same cells",
+                expected: velocity_block.get_number_of_cells(),
+                actual: pressure_block.get_number_of_cells(),
+            });
+        }
+        let dimension = get_embedding_dimension(velocity, velocity_block);
+        if dimension != velocity.get_shape_basis().get_dimension() {
+            return Err(Error::DimensionMismatch {
+                context: "Embedding dimension does not match the element",
+                expected: velocity.get_shape_basis().get_dimension(),
+                actual: dimension,
+            });
+        }
+        Ok(StokesProblem {
+            velocity,
+            velocity_block,
+            pressure,
+            pressure_block,
+            viscosity: DataType::one(),
+            body_force: Box::new(move |_| vec![DataType::zero(); dimension]),
+            supports: Vec::new(),
+            zero_mean_pressure: false,
+            control: IterationControl::new(num::cast(1e-10).unwrap(), DataType::zero(), 200),
+            restart: 100,
+        })
+    }
+
+    /// Set the dynamic viscosity `μ`
+    pub fn set_viscosity(&mut self, viscosity: DataType) {
+        self.viscosity = viscosity;
+    }
+
+    /// Set the body force as a function of the real coordinates
+    pub fn set_body_force(&mut self, force: impl Fn(&[DataType]) -> Vec<DataType> + 'a) {
+        self.body_force = Box::new(force);
+    }
+
+    /// Impose the velocity, as a function of the real coordinates, on a group of facets of the
+    /// velocity block
+    pub fn add_velocity(
+        &mut self,
+        group: FacetGroup,
+        velocity: impl Fn(&[DataType]) -> Vec<DataType> + 'a,
+    ) {
+        self.supports.push((group, Box::new(velocity)));
+    }
+
+    /// Set whether the pressure is normalized to zero mean, for velocities imposed on the whole
+    /// boundary
+    pub fn set_zero_mean_pressure(&mut self, zero_mean_pressure: bool) {
+        self.zero_mean_pressure = zero_mean_pressure;
+    }
+
+    /// Set the stopping criterion and the restart length of GMRES
+    pub fn set_control(&mut self, control: IterationControl<DataType>, restart: usize) {
+        self.control = control;
+        self.restart = restart;
+    }
+
+    /// Get the number of velocity dofs, numbered first
+    pub fn get_number_of_velocity_dofs(&self) -> usize {
+        let nodes = self
+            .velocity_block
+            .get_connectivity()
+            .iter()
+            .max()
+            .map_or(0, |&node| node + 1);
+        nodes * self.get_dimension()
+    }
+
+    /// Get the number of pressure dofs, numbered after the velocity dofs
+    pub fn get_number_of_pressure_dofs(&self) -> usize {
+        self.pressure_block
+            .get_connectivity()
+            .iter()
+            .max()
+            .map_or(0, |&dof| dof + 1)
+    }
+
+    /// Get the mixed dofs of the cells, flattened cell after cell
+    pub fn get_mixed_dofs(&self) -> Vec<usize> {
+        let d = self.get_dimension();
+        let offset = self.get_number_of_velocity_dofs();
+        (0..self.velocity_block.get_number_of_cells())
+            .flat_map(|cell| {
+                let velocity = self
+                    .velocity_block
+                    .get_cell_dofs(cell)
+                    .iter()
+                    .flat_map(move |&node| (0..d).map(move |i| node * d + i));
+                let pressure = self
+                    .pressure_block
+                    .get_cell_dofs(cell)
+                    .iter()
+                    .map(move |&dof| offset + dof);
+                velocity.chain(pressure)
+            })
+            .collect()
+    }
+
+    /// Get the constraints imposed by the velocity supports on the mixed dofs
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the constraints or an error if a facet is out of the block or a
+    ///   velocity does not match the dimension
+    pub fn get_constraints(
+        &self,
+        reference_facets: &ReferenceFacets<CoordType>,
+    ) -> Result<Constraints<DataType>, Error> {
+        let d = self.get_dimension();
+        let mut constraints = Constraints::new();
+        for (group, velocity) in &self.supports {
+            for &(cell, facet) in group.get_facets() {
+                reference_facets.check_facet(self.velocity_block, cell, facet)?;
+                let nodes = self.velocity_block.get_cell_dofs(cell);
+                let coordinates = self.velocity_block.get_cell_coordinates(cell);
+                for &node in reference_facets.get_facet(facet) {
+                    let point: Vec<DataType> = coordinates[node * d..(node + 1) * d]
+                        .iter()
+                        .map(|&x| x.into())
+                        .collect();
+                    let values = velocity(&point);
+                    if values.len() != d {
+                        return Err(Error::DimensionMismatch {
+                            context: "Velocity does not match the dimension",
+                            expected: d,
+                            actual: values.len(),
+                        });
+                    }
+                    for (i, &value) in values.iter().enumerate() {
+                        let dof = nodes[node] * d + i;
+                        if !constraints.is_constrained(dof) {
+                            constraints.add_dirichlet(dof, value)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// Assemble the load vector of the body force on the mixed dofs without constraints
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the load or an error if a cell is degenerate or the force does
+    ///   not match the dimension
+    pub fn assemble_load(&self) -> Result<Vec<DataType>, Error> {
+        let d = self.get_dimension();
+        let nbases = self.velocity.get_shape_basis().get_number_of_bases();
+        let mut load = vec![
+            DataType::zero();
+            self.get_number_of_velocity_dofs() + self.get_number_of_pressure_dofs()
+        ];
+        for cell in 0..self.velocity_block.get_number_of_cells() {
+            let nodes = self.velocity_block.get_cell_dofs(cell);
+            let points = compute_shape_gradients(
+                self.velocity,
+                self.velocity_block.get_cell_coordinates(cell),
+            )
+            .ok_or(Error::DegenerateCell { cell })?;
+            for (shapes, (_, weight)) in self
+                .velocity
+                .get_shapes_for_integration()
+                .chunks(nbases)
+                .zip(points)
+            {
+                let point = map_to_physical(self.velocity, self.velocity_block, cell, shapes);
+                let force = (self.body_force)(&point);
+                if force.len() != d {
+                    return Err(Error::DimensionMismatch {
+                        context: "Force does not match the dimension",
+                        expected: d,
+                        actual: force.len(),
+                    });
+                }
+                for (&node, &shape) in nodes.iter().zip(shapes) {
+                    for (i, &f) in force.iter().enumerate() {
+                        load[node * d + i] = load[node * d + i] + weight * shape * f;
+                    }
+                }
+            }
+        }
+        Ok(load)
+    }
+
+    /// Solve the problem
+    ///
+    /// # Arguments
+    ///
+    /// * `reference_facets`: the facets of the reference velocity element the groups of the
+    ///   supports refer to
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the velocity and pressure, or an error if the assembly or a
+    ///   factorization failed or GMRES did not converge
+    pub fn solve(
+        &self,
+        reference_facets: &ReferenceFacets<CoordType>,
+    ) -> Result<StokesSolution<'a, CoordType, DataType, VelocityT, PressureT>, Error> {
+        let d = self.get_dimension();
+        let nv = self.get_number_of_velocity_dofs();
+        let np = self.get_number_of_pressure_dofs();
+        let constraints = self.get_constraints(reference_facets)?;
+        let mixed_dofs = self.get_mixed_dofs();
+        let mixed_block = CellBlock::new(
+            self.velocity_block.get_dofs_per_cell() * d + self.pressure_block.get_dofs_per_cell(),
+            &mixed_dofs,
+            self.velocity_block.get_coordinates(),
+        )?;
+        let operator = StokesOperator::new(self.velocity, self.pressure, self.viscosity);
+        let (matrix, mut rhs) =
+            Assembler::new(nv + np).assemble_constrained(&operator, &mixed_block, &constraints)?;
+        let mass =
+            Assembler::new(np).assemble(&MassOperator::new(self.pressure), self.pressure_block)?;
+        let mut load = self.assemble_load()?;
+        constraints.condense(&mut load);
+        for (r, &l) in rhs.iter_mut().zip(&load) {
+            *r = *r + l;
+        }
+        let velocity_factor = SparseLu::new(&matrix.get_block(0..nv, 0..nv)?)?;
+        let mass_factor = SparseLu::new(&mass)?;
+        let scale = -DataType::one() / self.viscosity;
+        let mut preconditioner = BlockTriangularPreconditioner::new(
+            BlockStructure::new(vec![0, nv, nv + np])?,
+            Triangle::Upper,
+        );
+        preconditioner.set_diagonal_block(0, velocity_factor)?;
+        preconditioner.set_diagonal_block(1, move |r: &[DataType], z: &mut [DataType]| {
+            mass_factor.apply(r, z);
+            z.iter_mut().for_each(|v| *v = scale * *v);
+        })?;
+        preconditioner.add_off_diagonal_block(0, 1, matrix.get_block(0..nv, nv..nv + np)?)?;
+        let mut solution = vec![DataType::zero(); nv + np];
+        let result = Gmres::new(self.control, self.restart).solve(
+            &matrix,
+            &preconditioner,
+            &rhs,
+            &mut solution,
+        );
+        if !result.is_converged() {
+            return Err(Error::NotConverged("Solve did not converge"));
+        }
+        constraints.distribute(&mut solution);
+        let velocity = (0..d)
+            .map(|i| {
+                let coefficients = solution[..nv].iter().skip(i).step_by(d).copied().collect();
+                FEFunction::new(
+                    &format!("velocity_{}", i),
+                    self.velocity,
+                    self.velocity_block,
+                    coefficients,
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut pressure = FEFunction::new(
+            "pressure",
+            self.pressure,
+            self.pressure_block,
+            solution[nv..].to_vec(),
+        )?;
+        if self.zero_mean_pressure {
+            let ones = FEFunction::new(
+                "one",
+                self.pressure,
+                self.pressure_block,
+                vec![DataType::one(); np],
+            )?;
+            let mean = pressure.integrate()? / ones.integrate()?;
+            let shifted: Vec<DataType> = pressure
+                .get_coefficients()
+                .iter()
+                .map(|&p| p - mean)
+                .collect();
+            pressure.set_coefficients(&shifted)?;
+        }
+        Ok(StokesSolution {
+            velocity,
+            pressure,
+            result,
+        })
+    }
+
+    /// Get the dimension of the velocity
+    fn get_dimension(&self) -> usize {
+        get_embedding_dimension(self.velocity, self.velocity_block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StokesOperator, StokesOperatorBuilder, StokesProblem};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::element::operator_trait::Operator;
+    use crate::post::boundary::FacetGroup;
+    use crate::post::facets::ReferenceFacets;
+    use crate::solver::solver_traits::IterationControl;
+    use crate::test_utils::{
+        uniform_biquadratic_quadrilaterals, uniform_quadrilaterals, BilinearQuadrilateralElement,
+        BiquadraticQuadrilateral, BiquadraticQuadrilateralElement,
+    };
+
+    use std::collections::HashMap;
+
+    const TOL: f64 = 1e-8;
+
+    /// Solve the flow of velocity `(x², -2 x y)` and pressure `x + y - 1` on `n` by `n` Taylor-Hood
+    /// quadrilaterals, checking the nodal values and returning the number of GMRES iterations
+    fn polynomial_flow(n: usize) -> usize {
+        let (velocity_dofs, velocity_coords) = uniform_biquadratic_quadrilaterals(n);
+        let (pressure_dofs, pressure_coords) = uniform_quadrilaterals(n);
+        let velocity_block = CellBlock::new(9, &velocity_dofs, &velocity_coords).unwrap();
+        let pressure_block = CellBlock::new(4, &pressure_dofs, &pressure_coords).unwrap();
+        let velocity = BiquadraticQuadrilateralElement::new();
+        let pressure = BilinearQuadrilateralElement::new();
+        let nodes: Vec<f64> = BiquadraticQuadrilateral::NODES.concat();
+        let edges = vec![vec![0, 1, 4], vec![1, 2, 5], vec![2, 3, 6], vec![3, 0, 7]];
+        let facets = ReferenceFacets::new(&velocity, &nodes, edges).unwrap();
+        let mut problem =
+            StokesProblem::new(&velocity, &velocity_block, &pressure, &pressure_block).unwrap();
+        let exact = |x: &[f64]| vec![x[0] * x[0], -2.0 * x[0] * x[1]];
+        problem.set_viscosity(2.0);
+        problem.set_body_force(|_| vec![-3.0, 1.0]);
+        problem.add_velocity(
+            FacetGroup::from_boundary("walls", &facets, &velocity_block, |_| true),
+            exact,
+        );
+        problem.set_zero_mean_pressure(true);
+        problem.set_control(IterationControl::new(1e-14, 0.0, 100), 100);
+        assert_eq!(
+            problem.get_number_of_velocity_dofs(),
+            2 * (2 * n + 1) * (2 * n + 1),
+            "Incorrect number of velocity dofs"
+        );
+        let solution = problem.solve(&facets).unwrap();
+        let [u, v] = solution.get_velocity() else {
+            panic!("Incorrect number of velocity components");
+        };
+        for (&node, x) in velocity_dofs.iter().zip(velocity_coords.chunks(2)) {
+            let expected = exact(x);
+            assert!(
+                (u.get_coefficients()[node] - expected[0]).abs() < TOL
+                    && (v.get_coefficients()[node] - expected[1]).abs() < TOL,
+                "Incorrect velocity"
+            );
+        }
+        let p = solution.get_pressure();
+        for (&dof, x) in pressure_dofs.iter().zip(pressure_coords.chunks(2)) {
+            assert!(
+                (p.get_coefficients()[dof] - (x[0] + x[1] - 1.0)).abs() < TOL,
+                "Incorrect pressure"
+            );
+        }
+        solution.get_solver_result().get_iterations()
+    }
+
+    #[test]
+    fn test_operator_builder() {
+        let velocity = BiquadraticQuadrilateralElement::new();
+        let pressure = BilinearQuadrilateralElement::new();
+        let operator = StokesOperatorBuilder::new(&velocity, &pressure)
+            .viscosity(2.0)
+            .build()
+            .unwrap();
+        let nodes: Vec<f64> = BiquadraticQuadrilateral::NODES.concat();
+        let reference = StokesOperator::new(&velocity, &pressure, 2.0);
+        assert_eq!(
+            operator.compute(&nodes, &HashMap::new()),
+            reference.compute(&nodes, &HashMap::new()),
+            "Incorrect built operator"
+        );
+        assert!(
+            StokesOperatorBuilder::new(&pressure, &pressure)
+                .build()
+                .is_err(),
+            "Equal order pair accepted"
+        );
+        assert!(
+            StokesOperatorBuilder::new(&velocity, &pressure)
+                .viscosity(-1.0)
+                .build()
+                .is_err(),
+            "Negative viscosity accepted"
+        );
+    }
+
+    #[test]
+    fn test_polynomial_flow() {
+        let coarse = polynomial_flow(4);
+        let fine = polynomial_flow(8);
+        assert!(
+            fine <= coarse + 10 && fine < 30,
+            "Number of iterations of the Schur complement preconditioner not bounded"
+        );
+    }
+}
@@ -0,0 +1,8 @@
+/// Module for the traits and configuration shared by the nonlinear solvers
+pub mod nonlinear_traits;
+
+/// Module for the Newton-Raphson solver
+pub mod newton;
+
+/// Module for the Picard fixed point solver with Anderson acceleration
+pub mod picard;
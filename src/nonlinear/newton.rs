@@ -0,0 +1,427 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::norm;
+use crate::error::Error;
+use crate::nonlinear::nonlinear_traits::{NonlinearControl, NonlinearProblem, NonlinearResult};
+use crate::solver::direct::SparseLu;
+use crate::solver::registry::PreconditionerOnly;
+use crate::solver::solver_traits::{LinearSolver, Preconditioner};
+use num::Float;
+
+/// Boxed preconditioner built for a jacobian
+type BoxedPreconditioner<'a, DataType> = Box<dyn Preconditioner<DataType> + 'a>;
+
+/// Constructor of the preconditioner of a jacobian
+type PreconditionerFactory<'a, DataType> =
+    Box<dyn Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error> + 'a>;
+
+/// Parameters of a backtracking line search
+///
+/// # Explanation
+///
+/// The Newton update `δu` is scaled by `λ = 1, ρ, ρ², ...` until the merit `||F||²` decreases
+/// enough, `||F(u + λ δu)||² ≤ (1 - 2 c λ) ||F(u)||²` (the Armijo condition for the directional
+/// derivative `-||F||²` of the merit along the Newton direction), or a residual evaluation failing
+/// is treated as an insufficient decrease. Without backtracking the full step is always taken.
+#[derive(Clone, Copy, Debug)]
+pub struct LineSearch<DataType> {
+    sufficient_decrease: DataType,
+    contraction: DataType,
+    maximum_backtracks: usize,
+}
+
+impl<DataType: Scalar + Float> Default for LineSearch<DataType> {
+    fn default() -> Self {
+        LineSearch::new(
+            DataType::from(1e-4).unwrap(),
+            DataType::from(0.5).unwrap(),
+            10,
+        )
+    }
+}
+
+impl<DataType: Scalar + Float> LineSearch<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `sufficient_decrease`: the coefficient `c` of the Armijo condition
+    /// * `contraction`: the factor `ρ` of the step between two trials
+    /// * `maximum_backtracks`: the maximum number of step reductions
+    pub fn new(
+        sufficient_decrease: DataType,
+        contraction: DataType,
+        maximum_backtracks: usize,
+    ) -> LineSearch<DataType> {
+        LineSearch {
+            sufficient_decrease,
+            contraction,
+            maximum_backtracks,
+        }
+    }
+
+    /// The full Newton step without backtracking
+    pub fn none() -> LineSearch<DataType> {
+        LineSearch::new(DataType::zero(), DataType::one(), 0)
+    }
+
+    /// Get the coefficient of the Armijo condition
+    pub fn get_sufficient_decrease(&self) -> DataType {
+        self.sufficient_decrease
+    }
+
+    /// Get the factor of the step between two trials
+    pub fn get_contraction(&self) -> DataType {
+        self.contraction
+    }
+
+    /// Get the maximum number of step reductions
+    pub fn get_maximum_backtracks(&self) -> usize {
+        self.maximum_backtracks
+    }
+}
+
+/// Newton-Raphson solver of nonlinear systems
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// Each iteration solves the tangent system `J(u) δu = -F(u)` with a `LinearSolver` preconditioned
+/// by a preconditioner built from the jacobian (by default a sparse LU factorization used as a
+/// direct solver) and updates the state along `δu` with a backtracking line search. Inexact
+/// tangent solves, an iterative solver stopped at a loose tolerance, are accepted: the line search
+/// safeguards the update. The iterations stop without convergence if the line search fails or the
+/// residual is not finite.
+pub struct NewtonSolver<'a, DataType> {
+    control: NonlinearControl<DataType>,
+    line_search: LineSearch<DataType>,
+    solver: Box<dyn LinearSolver<DataType> + 'a>,
+    preconditioner_factory: PreconditionerFactory<'a, DataType>,
+}
+
+impl<'a, DataType: Scalar + Float + 'a> NewtonSolver<'a, DataType> {
+    /// Constructor using a sparse direct solve of the tangent systems and the default line search
+    ///
+    /// # Arguments
+    ///
+    /// * `control`: the stopping criterion
+    pub fn new(control: NonlinearControl<DataType>) -> NewtonSolver<'a, DataType> {
+        NewtonSolver {
+            control,
+            line_search: LineSearch::default(),
+            solver: Box::new(PreconditionerOnly::default()),
+            preconditioner_factory: Box::new(|matrix| Ok(Box::new(SparseLu::new(matrix)?))),
+        }
+    }
+
+    /// Set the solver of the tangent systems
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the linear solver
+    /// * `preconditioner_factory`: builds the preconditioner from the jacobian
+    pub fn set_linear_solver(
+        &mut self,
+        solver: impl LinearSolver<DataType> + 'a,
+        preconditioner_factory: impl Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error>
+            + 'a,
+    ) {
+        self.solver = Box::new(solver);
+        self.preconditioner_factory = Box::new(preconditioner_factory);
+    }
+
+    /// Set the line search
+    pub fn set_line_search(&mut self, line_search: LineSearch<DataType>) {
+        self.line_search = line_search;
+    }
+
+    /// Get the stopping criterion
+    pub fn get_control(&self) -> &NonlinearControl<DataType> {
+        &self.control
+    }
+
+    /// Get the line search
+    pub fn get_line_search(&self) -> &LineSearch<DataType> {
+        &self.line_search
+    }
+
+    /// Solve `F(u) = 0`
+    ///
+    /// # Arguments
+    ///
+    /// * `problem`: the nonlinear system
+    /// * `u`: the initial guess, overwritten by the last iterate
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the convergence information or an error if the size of the state
+    ///   does not match, the residual at the initial guess or a jacobian could not be computed or a
+    ///   preconditioner could not be built
+    pub fn solve<ProblemT: NonlinearProblem<DataType> + ?Sized>(
+        &self,
+        problem: &ProblemT,
+        u: &mut [DataType],
+    ) -> Result<NonlinearResult<DataType>, Error> {
+        let n = problem.get_size();
+        if u.len() != n {
+            return Err(Error::SizeMismatch {
+                context: "State size does not match the problem",
+                expected: n,
+                actual: u.len(),
+            });
+        }
+        let zero = DataType::zero();
+        let one = DataType::one();
+        let mut residual = vec![zero; n];
+        problem.compute_residual(u, &mut residual)?;
+        let mut residual_norm = norm(&residual);
+        let target = self.control.get_target(residual_norm);
+        let mut history = vec![residual_norm];
+        let mut linear_iterations = 0;
+        let mut rhs = vec![zero; n];
+        let mut update = vec![zero; n];
+        let mut trial = vec![zero; n];
+        let mut trial_residual = vec![zero; n];
+        for iteration in 0..self.control.get_maximum_iterations() {
+            if !residual_norm.is_finite() {
+                return Ok(NonlinearResult::new(
+                    false,
+                    iteration,
+                    linear_iterations,
+                    history,
+                ));
+            }
+            if residual_norm <= target {
+                return Ok(NonlinearResult::new(
+                    true,
+                    iteration,
+                    linear_iterations,
+                    history,
+                ));
+            }
+            let jacobian = problem.compute_jacobian(u)?;
+            let preconditioner = (self.preconditioner_factory)(&jacobian)?;
+            rhs.iter_mut()
+                .zip(&residual)
+                .for_each(|(b, &r)| *b = zero - r);
+            update.fill(zero);
+            linear_iterations += self
+                .solver
+                .solve(&jacobian, preconditioner.as_ref(), &rhs, &mut update)
+                .get_iterations();
+            let mut lambda = one;
+            let mut accepted = None;
+            for backtrack in 0..=self.line_search.maximum_backtracks {
+                if backtrack > 0 {
+                    lambda = lambda * self.line_search.contraction;
+                }
+                trial
+                    .iter_mut()
+                    .zip(u.iter().zip(&update))
+                    .for_each(|(t, (&u, &du))| *t = u + lambda * du);
+                if problem
+                    .compute_residual(&trial, &mut trial_residual)
+                    .is_err()
+                {
+                    continue;
+                }
+                let trial_norm = norm(&trial_residual);
+                let decrease = one
+                    - (self.line_search.sufficient_decrease + self.line_search.sufficient_decrease)
+                        * lambda;
+                if self.line_search.maximum_backtracks == 0
+                    || trial_norm * trial_norm <= decrease * residual_norm * residual_norm
+                {
+                    accepted = Some(trial_norm);
+                    break;
+                }
+            }
+            let Some(trial_norm) = accepted else {
+                return Ok(NonlinearResult::new(
+                    false,
+                    iteration,
+                    linear_iterations,
+                    history,
+                ));
+            };
+            u.copy_from_slice(&trial);
+            residual.copy_from_slice(&trial_residual);
+            residual_norm = trial_norm;
+            history.push(residual_norm);
+            if self
+                .control
+                .is_step_converged(lambda.abs() * norm(&update), norm(u))
+            {
+                return Ok(NonlinearResult::new(
+                    true,
+                    iteration + 1,
+                    linear_iterations,
+                    history,
+                ));
+            }
+        }
+        let converged = residual_norm <= target;
+        Ok(NonlinearResult::new(
+            converged,
+            self.control.get_maximum_iterations(),
+            linear_iterations,
+            history,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineSearch, NewtonSolver};
+    use crate::algebra::csr::CsrMatrix;
+    use crate::error::Error;
+    use crate::nonlinear::nonlinear_traits::{NonlinearControl, NonlinearProblem};
+    use crate::solver::cg::ConjugateGradient;
+    use crate::solver::preconditioners::JacobiPreconditioner;
+    use crate::solver::solver_traits::IterationControl;
+
+    /// Scalar equation `f(u) = 0` given with its derivative
+    struct Scalar<F, G> {
+        f: F,
+        df: G,
+    }
+
+    impl<F: Fn(f64) -> Result<f64, Error>, G: Fn(f64) -> f64> NonlinearProblem<f64> for Scalar<F, G> {
+        fn get_size(&self) -> usize {
+            1
+        }
+
+        fn compute_residual(&self, u: &[f64], residual: &mut [f64]) -> Result<(), Error> {
+            residual[0] = (self.f)(u[0])?;
+            Ok(())
+        }
+
+        fn compute_jacobian(&self, u: &[f64]) -> Result<CsrMatrix<f64>, Error> {
+            CsrMatrix::from_triplets(1, 1, &[(0, 0, (self.df)(u[0]))])
+        }
+    }
+
+    /// Finite difference discretization of `-u'' + u³ = 1` on `(0, 1)` with `u(0) = u(1) = 0`
+    struct Reaction {
+        size: usize,
+    }
+
+    impl NonlinearProblem<f64> for Reaction {
+        fn get_size(&self) -> usize {
+            self.size
+        }
+
+        fn compute_residual(&self, u: &[f64], residual: &mut [f64]) -> Result<(), Error> {
+            let h = 1.0 / (self.size + 1) as f64;
+            for i in 0..self.size {
+                let left = if i > 0 { u[i - 1] } else { 0.0 };
+                let right = if i + 1 < self.size { u[i + 1] } else { 0.0 };
+                residual[i] = (2.0 * u[i] - left - right) / (h * h) + u[i].powi(3) - 1.0;
+            }
+            Ok(())
+        }
+
+        fn compute_jacobian(&self, u: &[f64]) -> Result<CsrMatrix<f64>, Error> {
+            let h = 1.0 / (self.size + 1) as f64;
+            let mut triplets = Vec::new();
+            for (i, &value) in u.iter().enumerate() {
+                triplets.push((i, i, 2.0 / (h * h) + 3.0 * value * value));
+                if i > 0 {
+                    triplets.push((i, i - 1, -1.0 / (h * h)));
+                }
+                if i + 1 < self.size {
+                    triplets.push((i, i + 1, -1.0 / (h * h)));
+                }
+            }
+            CsrMatrix::from_triplets(self.size, self.size, &triplets)
+        }
+    }
+
+    #[test]
+    fn test_quadratic_convergence() {
+        let problem = Reaction { size: 20 };
+        let newton = NewtonSolver::new(NonlinearControl::new(1e-12, 0.0, 20));
+        let mut u = vec![1.0; 20];
+        let result = newton.solve(&problem, &mut u).unwrap();
+        assert!(result.is_converged(), "Newton did not converge");
+        assert!(result.get_iterations() <= 6, "Too many Newton iterations");
+        let history = result.get_history();
+        assert!(
+            history[1..]
+                .windows(2)
+                .all(|pair| pair[1] < 1e-2 * pair[0] * pair[0]),
+            "Convergence is not quadratic"
+        );
+    }
+
+    #[test]
+    fn test_line_search() {
+        // atan(u) = 0 diverges with full Newton steps from u = 3
+        let problem = Scalar {
+            f: |u: f64| Ok(u.atan()),
+            df: |u: f64| 1.0 / (1.0 + u * u),
+        };
+        let mut newton = NewtonSolver::new(NonlinearControl::new(0.0, 1e-12, 30));
+        newton.set_line_search(LineSearch::none());
+        let mut u = [3.0];
+        // The jacobian vanishes numerically as the iterates blow up
+        let result = newton.solve(&problem, &mut u);
+        assert!(
+            result.map_or(true, |result| !result.is_converged()),
+            "Full Newton steps should diverge"
+        );
+        newton.set_line_search(LineSearch::default());
+        let mut u = [3.0];
+        let result = newton.solve(&problem, &mut u).unwrap();
+        assert!(
+            result.is_converged(),
+            "Newton with line search did not converge"
+        );
+        assert!(u[0].abs() < 1e-12, "Incorrect root");
+        // ln(u) = 0 from u = 3, the full step leaving the domain of the logarithm
+        let problem = Scalar {
+            f: |u: f64| {
+                if u > 0.0 {
+                    Ok(u.ln())
+                } else {
+                    Err(Error::InvalidArgument("Logarithm of a non positive value"))
+                }
+            },
+            df: |u: f64| 1.0 / u,
+        };
+        let mut u = [3.0];
+        let result = newton.solve(&problem, &mut u).unwrap();
+        assert!(
+            result.is_converged() && (u[0] - 1.0).abs() < 1e-12,
+            "Failed evaluations not backtracked"
+        );
+    }
+
+    #[test]
+    fn test_iterative_tangent() {
+        let problem = Reaction { size: 50 };
+        let mut newton = NewtonSolver::new(NonlinearControl::new(1e-10, 0.0, 20));
+        newton.set_linear_solver(
+            ConjugateGradient::new(IterationControl::new(1e-12, 0.0, 200)),
+            |matrix| Ok(Box::new(JacobiPreconditioner::from_matrix(matrix)?)),
+        );
+        let mut u = vec![0.0; 50];
+        let result = newton.solve(&problem, &mut u).unwrap();
+        assert!(result.is_converged(), "Newton did not converge");
+        assert!(
+            result.get_linear_iterations() > result.get_iterations(),
+            "Linear iterations not counted"
+        );
+        let mut direct = vec![0.0; 50];
+        NewtonSolver::new(NonlinearControl::new(1e-10, 0.0, 20))
+            .solve(&problem, &mut direct)
+            .unwrap();
+        assert!(
+            u.iter().zip(&direct).all(|(a, b)| (a - b).abs() < 1e-8),
+            "Iterative and direct tangent solves differ"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use num::Float;
+
+/// Provides the residual of a nonlinear system `F(u) = 0` and its jacobian
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The residual is typically assembled from element residual kernels and the jacobian from their
+/// tangents. The evaluations may fail, for instance when a state inverts an element, the solvers
+/// then reporting the error or, for the line searches, trying a shorter step.
+pub trait NonlinearProblem<DataType> {
+    /// Get the number of unknowns
+    fn get_size(&self) -> usize;
+
+    /// Compute the residual `F(u)`
+    ///
+    /// # Arguments
+    ///
+    /// * `u`: the state
+    /// * `residual`: the residual to fill
+    fn compute_residual(&self, u: &[DataType], residual: &mut [DataType]) -> Result<(), Error>;
+
+    /// Compute the jacobian `dF/du (u)`
+    fn compute_jacobian(&self, u: &[DataType]) -> Result<CsrMatrix<DataType>, Error>;
+}
+
+/// Provides the linearization `A(u) u = b(u)` of a nonlinear system for fixed point iterations
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// Mildly nonlinear problems, a diffusion with a coefficient depending on the solution for
+/// instance, are naturally written with the coefficients frozen at the current state. Assembling
+/// this linearized system is usually much simpler than the exact jacobian, at the price of a
+/// linear convergence of the iterations. The residual of the system is `F(u) = A(u) u - b(u)`.
+pub trait PicardProblem<DataType> {
+    /// Get the number of unknowns
+    fn get_size(&self) -> usize;
+
+    /// Compute the matrix `A(u)` and the right hand side `b(u)` with the coefficients frozen at `u`
+    fn compute_linearization(
+        &self,
+        u: &[DataType],
+    ) -> Result<(CsrMatrix<DataType>, Vec<DataType>), Error>;
+}
+
+/// Controls the stopping criterion of nonlinear solvers
+///
+/// # Explanation
+///
+/// Iterations stop as soon as the residual norm is below the largest of the absolute tolerance and
+/// the relative tolerance times the initial residual norm, or when the norm of the update is below
+/// the step tolerance times the norm of the state (disabled by default), or when the maximum
+/// number of iterations is reached.
+#[derive(Clone, Copy, Debug)]
+pub struct NonlinearControl<DataType> {
+    relative_tolerance: DataType,
+    absolute_tolerance: DataType,
+    step_tolerance: DataType,
+    maximum_iterations: usize,
+}
+
+impl<DataType: Scalar + Float> Default for NonlinearControl<DataType> {
+    fn default() -> Self {
+        NonlinearControl::new(DataType::from(1e-8).unwrap(), DataType::zero(), 50)
+    }
+}
+
+impl<DataType: Scalar + Float> NonlinearControl<DataType> {
+    /// Constructor without step criterion
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_tolerance`: the tolerance relative to the initial residual norm
+    /// * `absolute_tolerance`: the tolerance on the residual norm
+    /// * `maximum_iterations`: the maximum number of iterations
+    pub fn new(
+        relative_tolerance: DataType,
+        absolute_tolerance: DataType,
+        maximum_iterations: usize,
+    ) -> NonlinearControl<DataType> {
+        NonlinearControl {
+            relative_tolerance,
+            absolute_tolerance,
+            step_tolerance: DataType::zero(),
+            maximum_iterations,
+        }
+    }
+
+    /// Set the tolerance on the norm of the update relative to the norm of the state
+    pub fn set_step_tolerance(&mut self, step_tolerance: DataType) {
+        self.step_tolerance = step_tolerance;
+    }
+
+    /// Get the tolerance relative to the initial residual norm
+    pub fn get_relative_tolerance(&self) -> DataType {
+        self.relative_tolerance
+    }
+
+    /// Get the tolerance on the residual norm
+    pub fn get_absolute_tolerance(&self) -> DataType {
+        self.absolute_tolerance
+    }
+
+    /// Get the tolerance on the norm of the update relative to the norm of the state
+    pub fn get_step_tolerance(&self) -> DataType {
+        self.step_tolerance
+    }
+
+    /// Get the maximum number of iterations
+    pub fn get_maximum_iterations(&self) -> usize {
+        self.maximum_iterations
+    }
+
+    /// Get the residual norm to reach for an initial residual of norm `initial_norm`
+    pub fn get_target(&self, initial_norm: DataType) -> DataType {
+        self.absolute_tolerance
+            .max(self.relative_tolerance * initial_norm)
+    }
+
+    /// Whether an update of norm `step_norm` of a state of norm `state_norm` is small enough
+    pub fn is_step_converged(&self, step_norm: DataType, state_norm: DataType) -> bool {
+        self.step_tolerance > DataType::zero() && step_norm <= self.step_tolerance * state_norm
+    }
+}
+
+/// Outcome of a nonlinear solve
+#[derive(Clone, Debug)]
+pub struct NonlinearResult<DataType> {
+    converged: bool,
+    iterations: usize,
+    linear_iterations: usize,
+    history: Vec<DataType>,
+}
+
+impl<DataType: Copy> NonlinearResult<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `converged`: whether the stopping criterion was met
+    /// * `iterations`: the number of nonlinear iterations performed
+    /// * `linear_iterations`: the total number of iterations of the linear solves
+    /// * `history`: the residual norms at each iteration, starting with the initial one
+    pub fn new(
+        converged: bool,
+        iterations: usize,
+        linear_iterations: usize,
+        history: Vec<DataType>,
+    ) -> NonlinearResult<DataType> {
+        NonlinearResult {
+            converged,
+            iterations,
+            linear_iterations,
+            history,
+        }
+    }
+
+    /// Whether the stopping criterion was met
+    pub fn is_converged(&self) -> bool {
+        self.converged
+    }
+
+    /// Get the number of nonlinear iterations performed
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Get the total number of iterations of the linear solves
+    pub fn get_linear_iterations(&self) -> usize {
+        self.linear_iterations
+    }
+
+    /// Get the norm of the initial residual
+    pub fn get_initial_residual_norm(&self) -> DataType {
+        self.history[0]
+    }
+
+    /// Get the norm of the final residual
+    pub fn get_residual_norm(&self) -> DataType {
+        self.history[self.history.len() - 1]
+    }
+
+    /// Get the residual norms at each iteration, starting with the initial one
+    pub fn get_history(&self) -> &[DataType] {
+        &self.history
+    }
+}
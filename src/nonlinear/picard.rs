@@ -0,0 +1,382 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::{dot, norm};
+use crate::error::Error;
+use crate::nonlinear::nonlinear_traits::{NonlinearControl, NonlinearResult, PicardProblem};
+use crate::solver::direct::SparseLu;
+use crate::solver::registry::PreconditionerOnly;
+use crate::solver::solver_traits::{LinearSolver, Preconditioner};
+use num::Float;
+use std::collections::VecDeque;
+
+/// Boxed preconditioner built for a linearized system
+type BoxedPreconditioner<'a, DataType> = Box<dyn Preconditioner<DataType> + 'a>;
+
+/// Constructor of the preconditioner of a linearized system
+type PreconditionerFactory<'a, DataType> =
+    Box<dyn Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error> + 'a>;
+
+/// Picard fixed point solver of nonlinear systems `A(u) u = b(u)`
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// Each iteration solves the linearized system `A(u_k) g_k = b(u_k)` for the fixed point map
+/// `g_k = G(u_k)`. Without acceleration the state is relaxed towards it,
+/// `u_{k+1} = u_k + β (g_k - u_k)`. With Anderson acceleration of depth `m` the last `m`
+/// differences of the fixed point residuals `f_k = g_k - u_k` are combined to minimize the
+/// residual of the update in the least squares sense, `γ = argmin ||f_k - ΔF γ||`, and
+/// `u_{k+1} = u_k - ΔU γ + β (f_k - ΔF γ)` where `ΔU` holds the differences of the iterates. The
+/// acceleration, a multisecant method, often recovers superlinear convergence without jacobian.
+/// The history is dropped when the least squares problem becomes ill conditioned. The stopping
+/// criterion is shared with the `NewtonSolver`, on the residual `A(u) u - b(u)` of the
+/// linearization.
+pub struct PicardSolver<'a, DataType> {
+    control: NonlinearControl<DataType>,
+    depth: usize,
+    relaxation: DataType,
+    solver: Box<dyn LinearSolver<DataType> + 'a>,
+    preconditioner_factory: PreconditionerFactory<'a, DataType>,
+}
+
+impl<'a, DataType: Scalar + Float + 'a> PicardSolver<'a, DataType> {
+    /// Constructor of the plain Picard iteration using a sparse direct solve of the linearized
+    /// systems
+    ///
+    /// # Arguments
+    ///
+    /// * `control`: the stopping criterion
+    pub fn new(control: NonlinearControl<DataType>) -> PicardSolver<'a, DataType> {
+        PicardSolver {
+            control,
+            depth: 0,
+            relaxation: DataType::one(),
+            solver: Box::new(PreconditionerOnly::default()),
+            preconditioner_factory: Box::new(|matrix| Ok(Box::new(SparseLu::new(matrix)?))),
+        }
+    }
+
+    /// Set the solver of the linearized systems
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the linear solver
+    /// * `preconditioner_factory`: builds the preconditioner from the linearized matrix
+    pub fn set_linear_solver(
+        &mut self,
+        solver: impl LinearSolver<DataType> + 'a,
+        preconditioner_factory: impl Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error>
+            + 'a,
+    ) {
+        self.solver = Box::new(solver);
+        self.preconditioner_factory = Box::new(preconditioner_factory);
+    }
+
+    /// Set the depth of the Anderson acceleration, zero disabling it
+    pub fn set_anderson_depth(&mut self, depth: usize) {
+        self.depth = depth;
+    }
+
+    /// Set the relaxation `β` of the fixed point residual
+    pub fn set_relaxation(&mut self, relaxation: DataType) {
+        self.relaxation = relaxation;
+    }
+
+    /// Get the stopping criterion
+    pub fn get_control(&self) -> &NonlinearControl<DataType> {
+        &self.control
+    }
+
+    /// Get the depth of the Anderson acceleration
+    pub fn get_anderson_depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the relaxation of the fixed point residual
+    pub fn get_relaxation(&self) -> DataType {
+        self.relaxation
+    }
+
+    /// Solve `A(u) u = b(u)`
+    ///
+    /// # Arguments
+    ///
+    /// * `problem`: the nonlinear system
+    /// * `u`: the initial guess, overwritten by the last iterate
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the convergence information or an error if the size of the state
+    ///   does not match, a linearization could not be computed or a preconditioner could not be
+    ///   built
+    pub fn solve<ProblemT: PicardProblem<DataType> + ?Sized>(
+        &self,
+        problem: &ProblemT,
+        u: &mut [DataType],
+    ) -> Result<NonlinearResult<DataType>, Error> {
+        let n = problem.get_size();
+        if u.len() != n {
+            return Err(Error::SizeMismatch {
+                context: "State size does not match the problem",
+                expected: n,
+                actual: u.len(),
+            });
+        }
+        let zero = DataType::zero();
+        let mut history = Vec::new();
+        let mut target = zero;
+        let mut linear_iterations = 0;
+        let mut residual = vec![zero; n];
+        let mut fixed_point = vec![zero; n];
+        let mut differences: VecDeque<(Vec<DataType>, Vec<DataType>)> = VecDeque::new();
+        let mut previous: Option<(Vec<DataType>, Vec<DataType>)> = None;
+        for iteration in 0..=self.control.get_maximum_iterations() {
+            let (matrix, rhs) = problem.compute_linearization(u)?;
+            matrix.apply_into(u, &mut residual);
+            residual
+                .iter_mut()
+                .zip(&rhs)
+                .for_each(|(r, &b)| *r = *r - b);
+            let residual_norm = norm(&residual);
+            if iteration == 0 {
+                target = self.control.get_target(residual_norm);
+            }
+            history.push(residual_norm);
+            if !residual_norm.is_finite() {
+                return Ok(NonlinearResult::new(
+                    false,
+                    iteration,
+                    linear_iterations,
+                    history,
+                ));
+            }
+            if residual_norm <= target {
+                return Ok(NonlinearResult::new(
+                    true,
+                    iteration,
+                    linear_iterations,
+                    history,
+                ));
+            }
+            if iteration == self.control.get_maximum_iterations() {
+                break;
+            }
+            let preconditioner = (self.preconditioner_factory)(&matrix)?;
+            fixed_point.copy_from_slice(u);
+            linear_iterations += self
+                .solver
+                .solve(&matrix, preconditioner.as_ref(), &rhs, &mut fixed_point)
+                .get_iterations();
+            let f: Vec<DataType> = fixed_point
+                .iter()
+                .zip(u.iter())
+                .map(|(&g, &u)| g - u)
+                .collect();
+            if self.depth > 0 {
+                if let Some((previous_u, previous_f)) = previous.take() {
+                    let delta_u = u.iter().zip(&previous_u).map(|(&a, &b)| a - b).collect();
+                    let delta_f = f.iter().zip(&previous_f).map(|(&a, &b)| a - b).collect();
+                    differences.push_back((delta_u, delta_f));
+                    if differences.len() > self.depth {
+                        differences.pop_front();
+                    }
+                }
+                previous = Some((u.to_vec(), f.clone()));
+            }
+            let coefficients = match least_squares(&differences, &f) {
+                Some(coefficients) => coefficients,
+                None => {
+                    differences.clear();
+                    Vec::new()
+                }
+            };
+            let mut update = zero;
+            for (i, (u, &f)) in u.iter_mut().zip(&f).enumerate() {
+                let (mut delta_u, mut delta_f) = (zero, zero);
+                for ((du, df), &gamma) in differences.iter().zip(&coefficients) {
+                    delta_u = delta_u + gamma * du[i];
+                    delta_f = delta_f + gamma * df[i];
+                }
+                let step = self.relaxation * (f - delta_f) - delta_u;
+                update = update + step * step;
+                *u = *u + step;
+            }
+            if self.control.is_step_converged(update.sqrt(), norm(u)) {
+                return Ok(NonlinearResult::new(
+                    true,
+                    iteration + 1,
+                    linear_iterations,
+                    history,
+                ));
+            }
+        }
+        Ok(NonlinearResult::new(
+            false,
+            self.control.get_maximum_iterations(),
+            linear_iterations,
+            history,
+        ))
+    }
+}
+
+/// Solve `min ||f - ΔF γ||` by a modified Gram-Schmidt QR factorization of `ΔF`
+///
+/// Returns `None` when a column is numerically dependent on the previous ones.
+fn least_squares<DataType: Scalar + Float>(
+    differences: &VecDeque<(Vec<DataType>, Vec<DataType>)>,
+    f: &[DataType],
+) -> Option<Vec<DataType>> {
+    let m = differences.len();
+    let tolerance = DataType::from(1e-10).unwrap();
+    let mut q: Vec<Vec<DataType>> = Vec::with_capacity(m);
+    let mut r = vec![DataType::zero(); m * m];
+    for (j, (_, column)) in differences.iter().enumerate() {
+        let mut v = column.clone();
+        for (i, qi) in q.iter().enumerate() {
+            let projection = dot(qi, &v);
+            r[i * m + j] = projection;
+            v.iter_mut()
+                .zip(qi)
+                .for_each(|(v, &q)| *v = *v - projection * q);
+        }
+        let length = norm(&v);
+        if length.is_nan() || length <= tolerance * norm(column) {
+            return None;
+        }
+        r[j * m + j] = length;
+        v.iter_mut().for_each(|v| *v = *v / length);
+        q.push(v);
+    }
+    let mut gamma: Vec<DataType> = q.iter().map(|qi| dot(qi, f)).collect();
+    for j in (0..m).rev() {
+        let sum = ((j + 1)..m).fold(gamma[j], |sum, k| sum - r[j * m + k] * gamma[k]);
+        gamma[j] = sum / r[j * m + j];
+    }
+    Some(gamma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PicardSolver;
+    use crate::algebra::csr::CsrMatrix;
+    use crate::error::Error;
+    use crate::nonlinear::nonlinear_traits::{NonlinearControl, PicardProblem};
+
+    /// Finite difference discretization of `-((1 + u²) u')' = 10` on `(0, 1)` with
+    /// `u(0) = u(1) = 0`
+    struct Diffusion {
+        size: usize,
+    }
+
+    impl PicardProblem<f64> for Diffusion {
+        fn get_size(&self) -> usize {
+            self.size
+        }
+
+        fn compute_linearization(&self, u: &[f64]) -> Result<(CsrMatrix<f64>, Vec<f64>), Error> {
+            let h = 1.0 / (self.size + 1) as f64;
+            let value = |i: usize| {
+                if i == 0 || i > self.size {
+                    0.0
+                } else {
+                    u[i - 1]
+                }
+            };
+            // conductivity between the nodes i and i + 1 of the full grid
+            let conductivity = |i: usize| 1.0 + (0.5 * (value(i) + value(i + 1))).powi(2);
+            let mut triplets = Vec::new();
+            for row in 0..self.size {
+                let (left, right) = (conductivity(row), conductivity(row + 1));
+                triplets.push((row, row, (left + right) / (h * h)));
+                if row > 0 {
+                    triplets.push((row, row - 1, -left / (h * h)));
+                }
+                if row + 1 < self.size {
+                    triplets.push((row, row + 1, -right / (h * h)));
+                }
+            }
+            Ok((
+                CsrMatrix::from_triplets(self.size, self.size, &triplets)?,
+                vec![10.0; self.size],
+            ))
+        }
+    }
+
+    /// Linearization `1 u = 3 - 2 u` whose fixed point map `G(u) = 3 - 2 u` is expanding
+    struct Expanding;
+
+    impl PicardProblem<f64> for Expanding {
+        fn get_size(&self) -> usize {
+            1
+        }
+
+        fn compute_linearization(&self, u: &[f64]) -> Result<(CsrMatrix<f64>, Vec<f64>), Error> {
+            Ok((
+                CsrMatrix::from_triplets(1, 1, &[(0, 0, 1.0)])?,
+                vec![3.0 - 2.0 * u[0]],
+            ))
+        }
+    }
+
+    #[test]
+    fn test_anderson_acceleration() {
+        let problem = Diffusion { size: 30 };
+        let control = NonlinearControl::new(1e-10, 0.0, 200);
+        let mut plain = vec![0.0; 30];
+        let plain_result = PicardSolver::new(control)
+            .solve(&problem, &mut plain)
+            .unwrap();
+        assert!(plain_result.is_converged(), "Picard did not converge");
+        let mut picard = PicardSolver::new(control);
+        picard.set_anderson_depth(5);
+        let mut accelerated = vec![0.0; 30];
+        let accelerated_result = picard.solve(&problem, &mut accelerated).unwrap();
+        assert!(
+            accelerated_result.is_converged(),
+            "Accelerated Picard did not converge"
+        );
+        assert!(
+            accelerated_result.get_iterations() < plain_result.get_iterations(),
+            "Anderson acceleration did not reduce the number of iterations"
+        );
+        assert!(
+            plain
+                .iter()
+                .zip(&accelerated)
+                .all(|(a, b)| (a - b).abs() < 1e-8),
+            "Plain and accelerated solutions differ"
+        );
+    }
+
+    #[test]
+    fn test_relaxation() {
+        let control = NonlinearControl::new(0.0, 1e-12, 100);
+        let mut u = [0.0];
+        let result = PicardSolver::new(control)
+            .solve(&Expanding, &mut u)
+            .unwrap();
+        assert!(
+            !result.is_converged(),
+            "Expanding fixed point map converged"
+        );
+        let mut relaxed = PicardSolver::new(control);
+        relaxed.set_relaxation(0.5);
+        let mut u = [0.0];
+        let result = relaxed.solve(&Expanding, &mut u).unwrap();
+        assert!(
+            result.is_converged() && (u[0] - 1.0).abs() < 1e-12,
+            "Relaxed Picard did not converge"
+        );
+        let mut accelerated = PicardSolver::new(control);
+        accelerated.set_anderson_depth(1);
+        let mut u = [0.0];
+        let result = accelerated.solve(&Expanding, &mut u).unwrap();
+        assert!(
+            result.is_converged() && result.get_iterations() <= 2,
+            "Anderson acceleration should solve a linear fixed point in two iterations"
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use crate::error::Error;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::thread;
+
+/// Configuration used by the parallel loops that are not given one
+static GLOBAL: RwLock<Parallelism> = RwLock::new(Parallelism::new());
+
+/// Number of chunks computed by each thread between two ordered consumptions
+const CHUNKS_PER_WAVE: usize = 4;
+
+/// Configuration of the parallel loops over items, cells for instance
+///
+/// # Explanation
+///
+/// The items are split into contiguous chunks of `chunk_size` items, that the threads claim one
+/// after the other. Which thread handles a chunk depends on the scheduling, so that by default
+/// the partial results of the threads, sums of floating point values for instance, are combined
+/// in an order that changes from one run to the next, and so do the rounding errors.
+///
+/// In deterministic mode the partial results are kept per chunk and combined in the order of the
+/// chunks, so that the results are bitwise reproducible across runs and across numbers of
+/// threads, for a given chunk size. This costs one partial result per chunk instead of one per
+/// thread.
+///
+/// A number of threads of zero stands for all the available parallelism. The configuration used
+/// by default is the global one, see `set_global`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parallelism {
+    number_of_threads: usize,
+    chunk_size: usize,
+    deterministic: bool,
+}
+
+impl Default for Parallelism {
+    fn default() -> Self {
+        Parallelism::new()
+    }
+}
+
+impl Parallelism {
+    /// Constructor using all the available threads, chunks of 256 items, not deterministic
+    pub const fn new() -> Parallelism {
+        Parallelism {
+            number_of_threads: 0,
+            chunk_size: 256,
+            deterministic: false,
+        }
+    }
+
+    /// Constructor of a sequential configuration, running on the calling thread only
+    pub const fn sequential() -> Parallelism {
+        Parallelism {
+            number_of_threads: 1,
+            chunk_size: 256,
+            deterministic: false,
+        }
+    }
+
+    /// Get the global configuration
+    pub fn get_global() -> Parallelism {
+        *GLOBAL.read().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Set the global configuration, used by the parallel loops that are not given one
+    pub fn set_global(parallelism: Parallelism) {
+        *GLOBAL.write().unwrap_or_else(|error| error.into_inner()) = parallelism;
+    }
+
+    /// Set the number of threads, zero standing for all the available parallelism
+    pub fn set_number_of_threads(&mut self, number_of_threads: usize) {
+        self.number_of_threads = number_of_threads;
+    }
+
+    /// Get the number of threads the loops run on
+    pub fn get_number_of_threads(&self) -> usize {
+        match self.number_of_threads {
+            0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        }
+    }
+
+    /// Set the number of items of a chunk (at least one)
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(1);
+    }
+
+    /// Get the number of items of a chunk
+    pub fn get_chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Set whether the partial results are combined in a reproducible order
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// Whether the partial results are combined in a reproducible order
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Fold chunks of items into accumulators and combine them
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_items`: the number of items to loop over
+    /// * `init`: creates an empty accumulator
+    /// * `fold`: accumulates the items of a chunk, given by their range
+    /// * `combine`: combines two accumulators, the first one holding the earlier results
+    ///
+    /// # Returns
+    ///
+    /// * the combination of the accumulators, starting from an empty one
+    ///
+    /// # Explanation
+    ///
+    /// There is one accumulator per thread, or one per chunk in deterministic mode.
+    pub fn fold<AccT, InitT, FoldT, CombineT>(
+        &self,
+        number_of_items: usize,
+        init: InitT,
+        fold: FoldT,
+        combine: CombineT,
+    ) -> AccT
+    where
+        AccT: Send,
+        InitT: Fn() -> AccT + Sync,
+        FoldT: Fn(&mut AccT, Range<usize>) + Sync,
+        CombineT: Fn(AccT, AccT) -> AccT,
+    {
+        let number_of_chunks = number_of_items.div_ceil(self.chunk_size);
+        let partials = if self.deterministic {
+            self.map_chunks(number_of_items, 0..number_of_chunks, |items| {
+                let mut accumulator = init();
+                fold(&mut accumulator, items);
+                accumulator
+            })
+        } else {
+            let next_chunk = AtomicUsize::new(0);
+            let worker = || {
+                let mut accumulator = init();
+                while let Some(items) = self.claim(&next_chunk, number_of_items, number_of_chunks) {
+                    fold(&mut accumulator, items);
+                }
+                accumulator
+            };
+            self.run(number_of_chunks, &worker)
+        };
+        partials.into_iter().fold(init(), combine)
+    }
+
+    /// Map chunks of items in parallel and consume the results on the calling thread in the order
+    /// of the chunks
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_items`: the number of items to loop over
+    /// * `map`: computes the result of a chunk, given by the range of its items
+    /// * `consume`: consumes the result of a chunk along with the range of its items
+    ///
+    /// # Returns
+    ///
+    /// * A result holding the first error of the consumption, after which no chunk is consumed
+    ///
+    /// # Explanation
+    ///
+    /// The chunks are mapped in waves of a few chunks per thread, so that only the results of one
+    /// wave are held at a time. The consumption is the same whatever the number of threads and
+    /// the chunk size, as long as the consumption of a chunk is the same as the consumption of
+    /// its items one after the other.
+    pub fn for_each_ordered<T, MapT, ConsumeT>(
+        &self,
+        number_of_items: usize,
+        map: MapT,
+        mut consume: ConsumeT,
+    ) -> Result<(), Error>
+    where
+        T: Send,
+        MapT: Fn(Range<usize>) -> T + Sync,
+        ConsumeT: FnMut(Range<usize>, T) -> Result<(), Error>,
+    {
+        let number_of_chunks = number_of_items.div_ceil(self.chunk_size);
+        let wave_size = self.get_number_of_threads() * CHUNKS_PER_WAVE;
+        for first_chunk in (0..number_of_chunks).step_by(wave_size) {
+            let chunks = first_chunk..(first_chunk + wave_size).min(number_of_chunks);
+            let results = self.map_chunks(number_of_items, chunks.clone(), &map);
+            for (chunk, result) in chunks.zip(results) {
+                consume(self.get_chunk(chunk, number_of_items), result)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the range of items of a chunk
+    fn get_chunk(&self, chunk: usize, number_of_items: usize) -> Range<usize> {
+        chunk * self.chunk_size..((chunk + 1) * self.chunk_size).min(number_of_items)
+    }
+
+    /// Claim the next chunk of a loop, if any is left
+    fn claim(
+        &self,
+        next_chunk: &AtomicUsize,
+        number_of_items: usize,
+        number_of_chunks: usize,
+    ) -> Option<Range<usize>> {
+        let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+        (chunk < number_of_chunks).then(|| self.get_chunk(chunk, number_of_items))
+    }
+
+    /// Map a range of chunks in parallel, returning the results in the order of the chunks
+    fn map_chunks<T: Send>(
+        &self,
+        number_of_items: usize,
+        chunks: Range<usize>,
+        map: impl Fn(Range<usize>) -> T + Sync,
+    ) -> Vec<T> {
+        let next_chunk = AtomicUsize::new(chunks.start);
+        let worker = || {
+            let mut results = Vec::new();
+            loop {
+                let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+                if chunk >= chunks.end {
+                    return results;
+                }
+                results.push((chunk, map(self.get_chunk(chunk, number_of_items))));
+            }
+        };
+        let mut results: Vec<(usize, T)> = self
+            .run(chunks.len(), &worker)
+            .into_iter()
+            .flatten()
+            .collect();
+        results.sort_unstable_by_key(|&(chunk, _)| chunk);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Run a worker on as many threads as useful for a number of chunks, the calling thread
+    /// being one of them, and collect their results in the order of the threads
+    fn run<T: Send>(&self, number_of_chunks: usize, worker: &(impl Fn() -> T + Sync)) -> Vec<T> {
+        let number_of_threads = self.get_number_of_threads().min(number_of_chunks).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (1..number_of_threads)
+                .map(|_| scope.spawn(worker))
+                .collect();
+            let mut results = vec![worker()];
+            results.extend(handles.into_iter().map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            }));
+            results
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parallelism;
+    use crate::error::Error;
+
+    fn harmonic_sum(parallelism: &Parallelism, n: usize) -> f64 {
+        parallelism.fold(
+            n,
+            || 0.0,
+            |sum, items| {
+                for i in items {
+                    *sum += 1.0 / (i + 1) as f64;
+                }
+            },
+            |a, b| a + b,
+        )
+    }
+
+    #[test]
+    fn test_fold() {
+        let n = 10_000;
+        let sequential = harmonic_sum(&Parallelism::sequential(), n);
+        let mut parallelism = Parallelism::new();
+        parallelism.set_chunk_size(37);
+        parallelism.set_deterministic(true);
+        let reference = harmonic_sum(&parallelism, n);
+        for threads in [1, 2, 3, 8] {
+            parallelism.set_number_of_threads(threads);
+            assert_eq!(
+                harmonic_sum(&parallelism, n).to_bits(),
+                reference.to_bits(),
+                "Deterministic sum not reproducible"
+            );
+        }
+        parallelism.set_deterministic(false);
+        parallelism.set_number_of_threads(4);
+        assert!(
+            (harmonic_sum(&parallelism, n) - sequential).abs() < 1e-12,
+            "Incorrect parallel sum"
+        );
+        assert_eq!(harmonic_sum(&parallelism, 0), 0.0, "Incorrect empty sum");
+    }
+
+    #[test]
+    fn test_for_each_ordered() {
+        let mut parallelism = Parallelism::new();
+        parallelism.set_number_of_threads(3);
+        parallelism.set_chunk_size(5);
+        let mut consumed = Vec::new();
+        parallelism
+            .for_each_ordered(
+                103,
+                |items| items.map(|i| i * i).collect::<Vec<_>>(),
+                |items, squares| {
+                    assert_eq!(items.len(), squares.len(), "Incorrect chunk results");
+                    consumed.extend(squares);
+                    Ok(())
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            consumed,
+            (0..103).map(|i| i * i).collect::<Vec<_>>(),
+            "Incorrect consumption order"
+        );
+        let mut count = 0;
+        let result = parallelism.for_each_ordered(
+            103,
+            |items| items.start,
+            |_, start| {
+                count += 1;
+                match start {
+                    20 => Err(Error::InvalidArgument("stop")),
+                    _ => Ok(()),
+                }
+            },
+        );
+        assert!(result.is_err(), "Consumption error not returned");
+        assert_eq!(count, 5, "Chunks consumed after an error");
+    }
+
+    #[test]
+    fn test_configuration() {
+        let mut parallelism = Parallelism::new();
+        assert!(
+            parallelism.get_number_of_threads() >= 1,
+            "Incorrect available threads"
+        );
+        parallelism.set_number_of_threads(3);
+        parallelism.set_chunk_size(0);
+        assert_eq!(parallelism.get_number_of_threads(), 3, "Incorrect threads");
+        assert_eq!(parallelism.get_chunk_size(), 1, "Incorrect chunk size");
+        let global = Parallelism::get_global();
+        Parallelism::set_global(parallelism);
+        assert_eq!(Parallelism::get_global(), parallelism, "Global not set");
+        Parallelism::set_global(global);
+    }
+}
@@ -0,0 +1,348 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::assembly::cell_block::CellBlock;
+use crate::element::element_traits::Element;
+use crate::error::Error;
+use crate::post::estimators::get_conductivity;
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::FEFunction;
+use num::Float;
+
+/// Group of facets of a block of cells tagged by a name, as a boundary where fluxes are measured
+pub struct FacetGroup {
+    name: String,
+    facets: Vec<(usize, usize)>,
+}
+
+impl FacetGroup {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the tag of the group
+    /// * `facets`: the `(cell, facet)` pairs of the group, the facet being the local index of the
+    ///   facet on the reference element
+    pub fn new(name: &str, facets: Vec<(usize, usize)>) -> FacetGroup {
+        FacetGroup {
+            name: name.to_string(),
+            facets,
+        }
+    }
+
+    /// Constructor of the group of the boundary facets of a block whose vertices all satisfy a
+    /// predicate on their real coordinates
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the tag of the group
+    /// * `reference_facets`: the facets of the reference element
+    /// * `block`: the cells
+    /// * `predicate`: the selection of the vertices, `|x| x[0] < 1e-12` for a left boundary
+    pub fn from_boundary<CoordType, DataType>(
+        name: &str,
+        reference_facets: &ReferenceFacets<CoordType>,
+        block: &CellBlock<CoordType, DataType>,
+        predicate: impl Fn(&[CoordType]) -> bool,
+    ) -> FacetGroup
+    where
+        CoordType: Scalar,
+    {
+        let embedding = block.get_coordinates_per_cell() / block.get_dofs_per_cell();
+        let facets = reference_facets
+            .find_boundary_facets(block)
+            .into_iter()
+            .filter(|&(cell, facet)| {
+                let coordinates = block.get_cell_coordinates(cell);
+                reference_facets
+                    .get_facet(facet)
+                    .iter()
+                    .all(|&node| predicate(&coordinates[node * embedding..(node + 1) * embedding]))
+            })
+            .collect();
+        FacetGroup::new(name, facets)
+    }
+
+    /// Get the tag of the group
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the `(cell, facet)` pairs of the group
+    pub fn get_facets(&self) -> &[(usize, usize)] {
+        &self.facets
+    }
+
+    /// Get the sorted global dofs of the vertices of the facets of the group
+    pub fn get_dofs<CoordType: Scalar, DataType>(
+        &self,
+        reference_facets: &ReferenceFacets<CoordType>,
+        block: &CellBlock<CoordType, DataType>,
+    ) -> Vec<usize> {
+        let mut dofs: Vec<usize> = self
+            .facets
+            .iter()
+            .flat_map(|&(cell, facet)| reference_facets.get_facet_dofs(block, cell, facet))
+            .collect();
+        dofs.sort_unstable();
+        dofs.dedup();
+        dofs
+    }
+}
+
+/// Integrate a quantity of a field over a group of facets
+///
+/// # Arguments
+///
+/// * `function`: the field
+/// * `reference_facets`: the facets of the reference element
+/// * `group`: the facets to integrate over
+/// * `integrand`: the quantity as a function of the real coordinates, the value and gradient of
+///   the field, the unit normal pointing out of the cell and the index of the cell
+///
+/// # Returns
+///
+/// * A result either holding the integral or an error if a facet or a cell is degenerate
+///
+/// # Explanation
+///
+/// The gradient is the one of the cell the facet belongs to. The integrand is evaluated at the
+/// vertices of the facets, which integrates exactly the values and fluxes of linear simplices and
+/// multilinear cells with straight facets.
+pub fn integrate_over_facets<CoordType, DataType, ElementT>(
+    function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    reference_facets: &ReferenceFacets<CoordType>,
+    group: &FacetGroup,
+    integrand: impl Fn(&[DataType], DataType, &[DataType], &[DataType], usize) -> DataType,
+) -> Result<DataType, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let element = function.get_element();
+    let block = function.get_block();
+    let mut integral = DataType::zero();
+    for &(cell, facet) in group.get_facets() {
+        let geometry = reference_facets.compute_geometry(element, block, cell, facet)?;
+        let nodes = reference_facets.get_facet(facet);
+        let mut sum = DataType::zero();
+        for (&node, vertex) in nodes.iter().zip(&geometry.vertices) {
+            let reference = reference_facets.get_reference_node(node);
+            let value = function.evaluate(cell, reference);
+            let gradient = function.evaluate_gradient(cell, reference)?;
+            sum = sum + integrand(vertex, value, &gradient, &geometry.normal, cell);
+        }
+        let count: DataType = num::cast(nodes.len()).unwrap();
+        integral = integral + geometry.measure * sum / count;
+    }
+    Ok(integral)
+}
+
+/// Compute the measure of a group of facets
+pub fn compute_measure<CoordType, DataType, ElementT>(
+    function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    reference_facets: &ReferenceFacets<CoordType>,
+    group: &FacetGroup,
+) -> Result<DataType, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    integrate_over_facets(function, reference_facets, group, |_, _, _, _, _| {
+        DataType::one()
+    })
+}
+
+/// Compute the average value of a field over a group of facets
+///
+/// # Returns
+///
+/// * A result either holding the average or an error if the group has no measure or a facet is
+///   degenerate
+pub fn compute_average<CoordType, DataType, ElementT>(
+    function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    reference_facets: &ReferenceFacets<CoordType>,
+    group: &FacetGroup,
+) -> Result<DataType, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let measure = compute_measure(function, reference_facets, group)?;
+    if measure <= DataType::zero() {
+        return Err(Error::InvalidArgument("Facet group has no measure"));
+    }
+    let integral =
+        integrate_over_facets(function, reference_facets, group, |_, value, _, _, _| value)?;
+    Ok(integral / measure)
+}
+
+/// Compute the outward flux `∫ κ grad u . n` of a field over a group of facets from its gradient
+///
+/// The conductivity is read from the "conductivity" data of the cells and is unit when absent. The
+/// gradient of the discrete solution converges one order slower than the solution, prefer
+/// compute_consistent_flux when the system is available.
+pub fn compute_flux<CoordType, DataType, ElementT>(
+    function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    reference_facets: &ReferenceFacets<CoordType>,
+    group: &FacetGroup,
+) -> Result<DataType, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let conductivity = get_conductivity(function.get_block());
+    integrate_over_facets(
+        function,
+        reference_facets,
+        group,
+        |_, _, gradient, normal, cell| {
+            gradient
+                .iter()
+                .zip(normal)
+                .fold(DataType::zero(), |flux, (&g, &n)| {
+                    flux + conductivity[cell] * g * n
+                })
+        },
+    )
+}
+
+/// Compute the consistent outward flux over a set of dofs from the residual of the system
+///
+/// # Arguments
+///
+/// * `matrix`: the matrix `K` of the system assembled without constraints
+/// * `load`: the load vector `F` assembled without constraints nor boundary terms
+/// * `solution`: the discrete solution
+/// * `dofs`: the dofs of the boundary, as given by FacetGroup::get_dofs, or the dofs of one
+///   component of a vector field for a resultant traction
+///
+/// # Returns
+///
+/// * A result either holding the flux or an error if the sizes do not match
+///
+/// # Explanation
+///
+/// Testing the weak form with the shape functions of the boundary dofs shows that the residual `K
+/// u - F` at these dofs is the boundary flux weighted by the shape functions. Their sum is the
+/// total flux, with the accuracy of the solution values rather than the one of their gradient,
+/// and the reaction forces for mechanical problems.
+pub fn compute_consistent_flux<DataType: Scalar>(
+    matrix: &CsrMatrix<DataType>,
+    load: &[DataType],
+    solution: &[DataType],
+    dofs: &[usize],
+) -> Result<DataType, Error> {
+    if load.len() != matrix.get_number_of_rows() {
+        return Err(Error::SizeMismatch {
+            context: "Load does not match the size of the matrix",
+            expected: matrix.get_number_of_rows(),
+            actual: load.len(),
+        });
+    }
+    if solution.len() != matrix.get_number_of_columns() {
+        return Err(Error::SizeMismatch {
+            context: "Solution does not match the size of the matrix",
+            expected: matrix.get_number_of_columns(),
+            actual: solution.len(),
+        });
+    }
+    let product = matrix.apply(solution);
+    dofs.iter().try_fold(DataType::zero(), |flux, &dof| {
+        if dof >= load.len() {
+            return Err(Error::OutOfBounds {
+                context: "Dof out of bounds",
+                index: dof,
+                bound: load.len(),
+            });
+        }
+        Ok(flux + product[dof] - load[dof])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compute_average, compute_consistent_flux, compute_flux, compute_measure, FacetGroup,
+    };
+    use crate::assembly::assembler::Assembler;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::facets::ReferenceFacets;
+    use crate::post::function::FEFunction;
+    use crate::test_utils::{
+        poisson_solution, quadrilateral_facets, uniform_quadrilaterals, uniform_segments,
+        BilinearQuadrilateralElement, Laplacian, LinearSegmentElement,
+    };
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_quadrilateral_boundary() {
+        let n = 4;
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let conductivity = vec![3.0; n * n];
+        let mut block = CellBlock::new(4, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let left = FacetGroup::from_boundary("left", &facets, &block, |x| x[0] < TOL);
+        assert_eq!(left.get_facets().len(), n, "Incorrect number of facets");
+        assert_eq!(
+            left.get_dofs(&facets, &block),
+            (0..=n).map(|j| j * (n + 1)).collect::<Vec<usize>>(),
+            "Incorrect dofs"
+        );
+        let values = (0..(n + 1) * (n + 1))
+            .map(|dof| {
+                let (x, y) = ((dof % (n + 1)) as f64, (dof / (n + 1)) as f64);
+                (x - 2.0 * y) / n as f64
+            })
+            .collect();
+        let u = FEFunction::new("u", &element, &block, values).unwrap();
+        assert!(
+            (compute_measure(&u, &facets, &left).unwrap() - 1.0).abs() < TOL,
+            "Incorrect measure"
+        );
+        assert!(
+            (compute_average(&u, &facets, &left).unwrap() + 1.0).abs() < TOL,
+            "Incorrect average"
+        );
+        assert!(
+            (compute_flux(&u, &facets, &left).unwrap() + 3.0).abs() < TOL,
+            "Incorrect flux"
+        );
+        let top = FacetGroup::from_boundary("top", &facets, &block, |x| x[1] > 1.0 - TOL);
+        assert!(
+            (compute_flux(&u, &facets, &top).unwrap() + 6.0).abs() < TOL,
+            "Incorrect flux"
+        );
+    }
+
+    #[test]
+    fn test_consistent_flux() {
+        let n = 4;
+        let (dofs, coords) = uniform_segments(n);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let element = LinearSegmentElement::new();
+        let facets = ReferenceFacets::new(&element, &[-1.0, 1.0], vec![vec![0], vec![1]]).unwrap();
+        let left = FacetGroup::from_boundary("left", &facets, &block, |x| x[0] < TOL);
+        let mat = Assembler::new(n + 1).assemble(&Laplacian, &block).unwrap();
+        let h = 1.0 / n as f64;
+        let mut load = vec![h; n + 1];
+        load[0] = 0.5 * h;
+        load[n] = 0.5 * h;
+        let solution = poisson_solution(n);
+        let boundary = left.get_dofs(&facets, &block);
+        let consistent = compute_consistent_flux(&mat, &load, &solution, &boundary).unwrap();
+        assert!((consistent + 0.5).abs() < TOL, "Incorrect consistent flux");
+        let u = FEFunction::new("u", &element, &block, solution).unwrap();
+        let direct = compute_flux(&u, &facets, &left).unwrap();
+        assert!(
+            (direct + 0.5 * (1.0 - h)).abs() < TOL,
+            "Incorrect flux from the gradient"
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use crate::assembly::cell_block::CellBlock;
+use crate::error::Error;
+use crate::timer::{scope, TimerReport};
+use num::Float;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Identifier at the start of the checkpoint files
+const MAGIC: &[u8; 8] = b"RFXCKPT\0";
+
+/// Version of the checkpoint format
+const VERSION: u64 = 1;
+
+/// Cells of a block owned by a checkpoint
+struct BlockState<CoordType, DataType> {
+    dofs_per_cell: usize,
+    cell_dofs: Vec<usize>,
+    cell_coordinates: Vec<CoordType>,
+    fields: Vec<(String, Vec<DataType>)>,
+}
+
+/// State of a simulation saved to restart it
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the state is encoded with
+///
+/// # Explanation
+///
+/// A checkpoint holds the time and step counters, the cell blocks with their data fields, named
+/// vectors of coefficients (solutions, velocities, history of multistep schemes) and free
+/// metadata describing the spaces, as the element or the ordering of the dofs. It is written in a
+/// compact little endian binary format where all the values are stored as `f64`. Files are
+/// written to a temporary file first and moved in place, so that a run killed while writing leaves
+/// the previous checkpoint intact.
+pub struct Checkpoint<CoordType, DataType> {
+    time: DataType,
+    step: usize,
+    metadata: Vec<(String, String)>,
+    blocks: Vec<BlockState<CoordType, DataType>>,
+    vectors: Vec<(String, Vec<DataType>)>,
+    report: Option<TimerReport>,
+}
+
+impl<CoordType: Float, DataType: Float> Checkpoint<CoordType, DataType> {
+    /// Constructor of an empty checkpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `time`: the time of the state
+    /// * `step`: the number of the step of the state
+    pub fn new(time: DataType, step: usize) -> Checkpoint<CoordType, DataType> {
+        Checkpoint {
+            time,
+            step,
+            metadata: Vec::new(),
+            blocks: Vec::new(),
+            vectors: Vec::new(),
+            report: None,
+        }
+    }
+
+    /// Get the time of the state
+    pub fn get_time(&self) -> DataType {
+        self.time
+    }
+
+    /// Get the number of the step of the state
+    pub fn get_step(&self) -> usize {
+        self.step
+    }
+
+    /// Add a metadata entry, replacing a previous value of the key
+    pub fn add_metadata(&mut self, key: &str, value: &str) {
+        self.metadata.retain(|(k, _)| k != key);
+        self.metadata.push((key.to_string(), value.to_string()));
+    }
+
+    /// Get the value of a metadata entry
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Add a copy of a block of cells along with its data fields
+    pub fn add_block(&mut self, block: &CellBlock<'_, CoordType, DataType>) {
+        let fields = block
+            .get_field_names()
+            .into_iter()
+            .map(|name| (name.to_string(), block.get_field(name).unwrap().to_vec()))
+            .collect();
+        self.blocks.push(BlockState {
+            dofs_per_cell: block.get_dofs_per_cell(),
+            cell_dofs: block.get_connectivity().to_vec(),
+            cell_coordinates: block.get_coordinates().to_vec(),
+            fields,
+        });
+    }
+
+    /// Get the number of blocks of cells
+    pub fn get_number_of_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Get a block of cells with its data fields, borrowing the checkpoint
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the block or an error if there is no such block
+    pub fn get_block(&self, block: usize) -> Result<CellBlock<'_, CoordType, DataType>, Error> {
+        let state = self.blocks.get(block).ok_or(Error::OutOfBounds {
+            context: "No such block in the checkpoint",
+            index: block,
+            bound: self.blocks.len(),
+        })?;
+        let mut block = CellBlock::new(
+            state.dofs_per_cell,
+            &state.cell_dofs,
+            &state.cell_coordinates,
+        )?;
+        for (name, values) in &state.fields {
+            block.add_field(name, values)?;
+        }
+        Ok(block)
+    }
+
+    /// Add a copy of a named vector, replacing a previous vector of the same name
+    pub fn add_vector(&mut self, name: &str, values: &[DataType]) {
+        self.vectors.retain(|(n, _)| n != name);
+        self.vectors.push((name.to_string(), values.to_vec()));
+    }
+
+    /// Get a named vector
+    pub fn get_vector(&self, name: &str) -> Option<&[DataType]> {
+        self.vectors
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    /// Record the time spent writing the checkpoint in a timer report, as the "output/checkpoint"
+    /// phase
+    pub fn set_timer_report(&mut self, report: &TimerReport) {
+        self.report = Some(report.clone());
+    }
+
+    /// Write the checkpoint in binary format
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the output could not be written
+    pub fn write(&self, out: &mut impl Write) -> Result<(), Error> {
+        let _timer = scope(&self.report, "output/checkpoint");
+        self.write_document(out)
+    }
+
+    /// Write the checkpoint in binary format to a file, replacing it only once fully written
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let _timer = scope(&self.report, "output/checkpoint");
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let file = File::create(&temporary).map_err(|source| Error::Io {
+            context: "Could not create the checkpoint file",
+            source,
+        })?;
+        let mut out = BufWriter::new(file);
+        self.write_document(&mut out)?;
+        out.into_inner()
+            .map_err(|error| error.into_error())
+            .and_then(|file| file.sync_all())
+            .map_err(|source| Error::Io {
+                context: "Could not write the checkpoint",
+                source,
+            })?;
+        std::fs::rename(&temporary, path).map_err(|source| Error::Io {
+            context: "Could not move the checkpoint in place",
+            source,
+        })
+    }
+
+    /// Read a checkpoint in binary format
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the checkpoint or an error if the input is not a valid checkpoint
+    pub fn read(input: &mut impl Read) -> Result<Checkpoint<CoordType, DataType>, Error> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic).map_err(|source| Error::Io {
+            context: "Could not read the checkpoint",
+            source,
+        })?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidData("Input is not a checkpoint"));
+        }
+        Checkpoint::read_binary(input).map_err(|source| Error::Io {
+            context: "Truncated or corrupted checkpoint",
+            source,
+        })?
+    }
+
+    /// Read a checkpoint in binary format from a file
+    pub fn read_file(path: impl AsRef<Path>) -> Result<Checkpoint<CoordType, DataType>, Error> {
+        let file = File::open(path).map_err(|source| Error::Io {
+            context: "Could not open the checkpoint file",
+            source,
+        })?;
+        Checkpoint::read(&mut BufReader::new(file))
+    }
+
+    /// Write the checkpoint, without timing it
+    fn write_document(&self, out: &mut impl Write) -> Result<(), Error> {
+        self.write_binary(out).map_err(|source| Error::Io {
+            context: "Could not write the checkpoint",
+            source,
+        })
+    }
+
+    /// Write the content of the checkpoint
+    fn write_binary(&self, out: &mut impl Write) -> std::io::Result<()> {
+        out.write_all(MAGIC)?;
+        write_u64(out, VERSION)?;
+        write_float(out, self.time)?;
+        write_u64(out, self.step as u64)?;
+        write_u64(out, self.metadata.len() as u64)?;
+        for (key, value) in &self.metadata {
+            write_string(out, key)?;
+            write_string(out, value)?;
+        }
+        write_u64(out, self.blocks.len() as u64)?;
+        for block in &self.blocks {
+            write_u64(out, block.dofs_per_cell as u64)?;
+            write_u64(out, block.cell_dofs.len() as u64)?;
+            for &dof in &block.cell_dofs {
+                write_u64(out, dof as u64)?;
+            }
+            write_floats(out, &block.cell_coordinates)?;
+            write_u64(out, block.fields.len() as u64)?;
+            for (name, values) in &block.fields {
+                write_string(out, name)?;
+                write_floats(out, values)?;
+            }
+        }
+        write_u64(out, self.vectors.len() as u64)?;
+        for (name, values) in &self.vectors {
+            write_string(out, name)?;
+            write_floats(out, values)?;
+        }
+        Ok(())
+    }
+
+    /// Read the content of a checkpoint after its identifier
+    fn read_binary(
+        input: &mut impl Read,
+    ) -> std::io::Result<Result<Checkpoint<CoordType, DataType>, Error>> {
+        if read_u64(input)? != VERSION {
+            return Ok(Err(Error::InvalidData("Unsupported checkpoint version")));
+        }
+        let mut checkpoint = Checkpoint::new(read_float(input)?, read_u64(input)? as usize);
+        for _ in 0..read_u64(input)? {
+            let key = read_string(input)?;
+            let value = read_string(input)?;
+            checkpoint.metadata.push((key, value));
+        }
+        for _ in 0..read_u64(input)? {
+            let dofs_per_cell = read_u64(input)? as usize;
+            let cell_dofs = (0..read_u64(input)?)
+                .map(|_| read_u64(input).map(|dof| dof as usize))
+                .collect::<std::io::Result<Vec<usize>>>()?;
+            let cell_coordinates = read_floats(input)?;
+            let mut fields = Vec::new();
+            for _ in 0..read_u64(input)? {
+                let name = read_string(input)?;
+                fields.push((name, read_floats(input)?));
+            }
+            checkpoint.blocks.push(BlockState {
+                dofs_per_cell,
+                cell_dofs,
+                cell_coordinates,
+                fields,
+            });
+        }
+        for _ in 0..read_u64(input)? {
+            let name = read_string(input)?;
+            checkpoint.vectors.push((name, read_floats(input)?));
+        }
+        Ok(Ok(checkpoint))
+    }
+}
+
+fn write_u64(out: &mut impl Write, value: u64) -> std::io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_float<ValueT: Float>(out: &mut impl Write, value: ValueT) -> std::io::Result<()> {
+    out.write_all(&value.to_f64().unwrap_or(f64::NAN).to_le_bytes())
+}
+
+fn write_floats<ValueT: Float>(out: &mut impl Write, values: &[ValueT]) -> std::io::Result<()> {
+    write_u64(out, values.len() as u64)?;
+    values.iter().try_for_each(|&value| write_float(out, value))
+}
+
+fn write_string(out: &mut impl Write, value: &str) -> std::io::Result<()> {
+    write_u64(out, value.len() as u64)?;
+    out.write_all(value.as_bytes())
+}
+
+fn read_u64(input: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_float<ValueT: Float>(input: &mut impl Read) -> std::io::Result<ValueT> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(ValueT::from(f64::from_le_bytes(bytes)).unwrap_or(ValueT::nan()))
+}
+
+fn read_floats<ValueT: Float>(input: &mut impl Read) -> std::io::Result<Vec<ValueT>> {
+    (0..read_u64(input)?).map(|_| read_float(input)).collect()
+}
+
+fn read_string(input: &mut impl Read) -> std::io::Result<String> {
+    let mut bytes = Vec::new();
+    let length = read_u64(input)?;
+    if input.take(length).read_to_end(&mut bytes)? as u64 != length {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|_| std::io::ErrorKind::InvalidData.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Checkpoint;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::test_utils::uniform_segments;
+    use crate::timer::TimerReport;
+
+    #[test]
+    fn test_round_trip() {
+        let (dofs, coords) = uniform_segments(3);
+        let conductivity = [1.0, 2.0, 3.0];
+        let mut block = CellBlock::new(2, &dofs, &coords).unwrap();
+        block.add_field("conductivity", &conductivity).unwrap();
+        let mut checkpoint = Checkpoint::new(1.5, 30);
+        checkpoint.add_metadata("element", "linear segment");
+        checkpoint.add_block(&block);
+        checkpoint.add_vector("u", &[0.0, 0.25, 0.5, 0.0]);
+        checkpoint.add_vector("v", &[1.0]);
+        checkpoint.add_vector("v", &[2.0, 3.0]);
+        let mut bytes = Vec::new();
+        checkpoint.write(&mut bytes).unwrap();
+        let restarted = Checkpoint::<f64, f64>::read(&mut bytes.as_slice()).unwrap();
+        assert!(
+            restarted.get_time() == 1.5 && restarted.get_step() == 30,
+            "Incorrect counters"
+        );
+        assert_eq!(
+            restarted.get_metadata("element"),
+            Some("linear segment"),
+            "Incorrect metadata"
+        );
+        assert_eq!(
+            restarted.get_vector("u"),
+            Some(&[0.0, 0.25, 0.5, 0.0][..]),
+            "Incorrect vector"
+        );
+        assert_eq!(
+            restarted.get_vector("v"),
+            Some(&[2.0, 3.0][..]),
+            "Vector not replaced"
+        );
+        let block = restarted.get_block(0).unwrap();
+        assert!(
+            block.get_connectivity() == dofs.as_slice()
+                && block.get_coordinates() == coords.as_slice()
+                && block.get_field("conductivity") == Some(&conductivity[..]),
+            "Incorrect block"
+        );
+        assert!(restarted.get_block(1).is_err(), "Missing block returned");
+        assert!(
+            Checkpoint::<f64, f64>::read(&mut &bytes[..bytes.len() - 4]).is_err(),
+            "Truncated checkpoint accepted"
+        );
+        assert!(
+            Checkpoint::<f64, f64>::read(&mut &b"not a checkpoint"[..]).is_err(),
+            "Invalid input accepted"
+        );
+    }
+
+    #[test]
+    fn test_file() {
+        let path =
+            std::env::temp_dir().join(format!("rustyfox_checkpoint_{}.bin", std::process::id()));
+        let mut checkpoint = Checkpoint::<f64, f64>::new(0.0, 0);
+        checkpoint.add_vector("u", &[1.0, 2.0]);
+        let report = TimerReport::new();
+        checkpoint.set_timer_report(&report);
+        checkpoint.write_file(&path).unwrap();
+        assert_eq!(
+            report.get_number_of_calls("output/checkpoint"),
+            1,
+            "Incorrect timed checkpoint writes"
+        );
+        let restarted = Checkpoint::<f64, f64>::read_file(&path).unwrap();
+        assert_eq!(
+            restarted.get_vector("u"),
+            Some(&[1.0, 2.0][..]),
+            "Incorrect vector"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
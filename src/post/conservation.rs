@@ -0,0 +1,241 @@
+use crate::algebra::scalar::Scalar;
+use crate::element::element_traits::Element;
+use crate::error::Error;
+use crate::post::boundary::{compute_flux, FacetGroup};
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::FEFunction;
+use crate::post::probes::ProbeTable;
+use num::Float;
+
+/// Monitor of the balance of a conserved quantity over the steps of a transient computation
+///
+/// # Generics
+///
+/// * DataType: the type of the quantity and of the times
+///
+/// # Explanation
+///
+/// Each record holds the total `Q` of the quantity over the domain and its rate of supply `S`, the
+/// inflow through the boundary plus the volume sources. The drift `Q(t) - Q(t_0) - ∫ S dt`, with
+/// the supply integrated by the trapezoidal rule between the records, vanishes up to the time
+/// discretization error for a conservative scheme, a growing drift revealing a bug. Mass is the
+/// integral of a density field, energies and momenta are computed by the user and recorded
+/// through their own monitors, one per component of the momentum.
+pub struct ConservationMonitor<DataType> {
+    name: String,
+    table: ProbeTable<DataType>,
+    initial: Option<DataType>,
+    previous_supply: DataType,
+    supplied: DataType,
+    drift: DataType,
+}
+
+impl<DataType: Scalar + Float> ConservationMonitor<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the name of the quantity, the columns of the table being `<name>`,
+    ///   `<name>_supply` and `<name>_drift`
+    pub fn new(name: &str) -> ConservationMonitor<DataType> {
+        ConservationMonitor {
+            name: name.to_string(),
+            table: ProbeTable::new(vec![
+                name.to_string(),
+                format!("{}_supply", name),
+                format!("{}_drift", name),
+            ]),
+            initial: None,
+            previous_supply: DataType::zero(),
+            supplied: DataType::zero(),
+            drift: DataType::zero(),
+        }
+    }
+
+    /// Get the name of the quantity
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the history of the totals, supplies and drifts
+    pub fn get_table(&self) -> &ProbeTable<DataType> {
+        &self.table
+    }
+
+    /// Get the drift at the last record
+    pub fn get_drift(&self) -> DataType {
+        self.drift
+    }
+
+    /// Get the drift at the last record relative to the initial total, or to one if it vanishes
+    pub fn get_relative_drift(&self) -> DataType {
+        let scale = self
+            .initial
+            .map_or(DataType::one(), |initial| initial.abs());
+        if scale > DataType::zero() {
+            self.drift / scale
+        } else {
+            self.drift
+        }
+    }
+
+    /// Get the largest drift in magnitude over the records
+    pub fn get_maximum_drift(&self) -> DataType {
+        self.table
+            .get_column(&format!("{}_drift", self.name))
+            .unwrap()
+            .iter()
+            .fold(DataType::zero(), |max, &drift| max.max(drift.abs()))
+    }
+
+    /// Record the total of the quantity and its rate of supply at a time
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the drift or an error if the time does not increase
+    pub fn record(
+        &mut self,
+        time: DataType,
+        total: DataType,
+        supply: DataType,
+    ) -> Result<DataType, Error> {
+        if let Some(&last) = self.table.get_times().last() {
+            if time <= last {
+                return Err(Error::InvalidArgument("Records are not ordered in time"));
+            }
+            let half: DataType = num::cast(0.5).unwrap();
+            self.supplied = self.supplied + half * (time - last) * (supply + self.previous_supply);
+        }
+        let initial = *self.initial.get_or_insert(total);
+        self.previous_supply = supply;
+        self.drift = total - initial - self.supplied;
+        self.table.add_row(time, vec![total, supply, self.drift])?;
+        Ok(self.drift)
+    }
+
+    /// Record the integral of a field and its inflow through groups of facets at a time
+    ///
+    /// # Arguments
+    ///
+    /// * `time`: the time of the record
+    /// * `function`: the field whose integral is conserved
+    /// * `reference_facets`: the facets of the reference element
+    /// * `groups`: the facets the field flows in through, the inflow being the flux of
+    ///   compute_flux with the conductivity of the cells
+    /// * `source`: the integral of the volume sources
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the drift or an error if the time does not increase or a cell is
+    ///   degenerate
+    pub fn record_field<CoordType, ElementT>(
+        &mut self,
+        time: DataType,
+        function: &FEFunction<'_, CoordType, DataType, ElementT>,
+        reference_facets: &ReferenceFacets<CoordType>,
+        groups: &[FacetGroup],
+        source: DataType,
+    ) -> Result<DataType, Error>
+    where
+        CoordType: Scalar,
+        DataType: From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let total = function.integrate()?;
+        let supply = groups.iter().try_fold(source, |supply, group| {
+            Ok::<DataType, Error>(supply + compute_flux(function, reference_facets, group)?)
+        })?;
+        self.record(time, total, supply)
+    }
+
+    /// Check that the drift of the last record stays below a tolerance relative to the initial
+    /// total
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the quantity drifted past the tolerance
+    pub fn check(&self, tolerance: DataType) -> Result<(), Error> {
+        if self.get_relative_drift().abs() > tolerance {
+            return Err(Error::InvalidArgument(
+                "Conserved quantity drifted past the tolerance",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConservationMonitor;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::boundary::FacetGroup;
+    use crate::post::function::FEFunction;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_balance() {
+        let mut monitor = ConservationMonitor::new("mass");
+        for step in 0..=10 {
+            let time = 0.1 * step as f64;
+            let drift = monitor
+                .record(time, 2.0 - time * time, -2.0 * time)
+                .unwrap();
+            assert!(drift.abs() < TOL, "Incorrect drift of a balanced quantity");
+        }
+        monitor.check(TOL).unwrap();
+        assert!(
+            monitor.record(1.0, 1.0, 0.0).is_err(),
+            "Past record accepted"
+        );
+        let mut leaking = ConservationMonitor::new("energy");
+        for step in 0..=4 {
+            leaking.record(0.5 * step as f64, 1.0, -0.5).unwrap();
+        }
+        assert!(
+            (leaking.get_drift() - 1.0).abs() < TOL
+                && (leaking.get_maximum_drift() - 1.0).abs() < TOL,
+            "Incorrect drift of a leaking quantity"
+        );
+        assert!(leaking.check(0.5).is_err(), "Drift not detected");
+        assert_eq!(
+            leaking.get_table().get_columns(),
+            &["energy", "energy_supply", "energy_drift"],
+            "Incorrect columns"
+        );
+    }
+
+    #[test]
+    fn test_field_balance() {
+        let (dofs, coords) = uniform_quadrilaterals(2);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let mut values = vec![0.0; 9];
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            values[dof] = x[0];
+        }
+        let u = FEFunction::new("u", &element, &block, values).unwrap();
+        let facets = quadrilateral_facets();
+        let right = FacetGroup::from_boundary("right", &facets, &block, |x| x[0] > 1.0 - TOL);
+        let left = FacetGroup::from_boundary("left", &facets, &block, |x| x[0] < TOL);
+        let mut monitor = ConservationMonitor::new("u");
+        let groups = [left, right];
+        monitor
+            .record_field(0.0, &u, &facets, &groups, 0.0)
+            .unwrap();
+        let drift = monitor
+            .record_field(0.5, &u, &facets, &groups, 0.0)
+            .unwrap();
+        assert!(drift.abs() < TOL, "Incorrect drift of a balanced field");
+        let drift = monitor
+            .record_field(1.0, &u, &facets, &groups[1..], 0.0)
+            .unwrap();
+        assert!(
+            (drift + 0.25).abs() < TOL,
+            "Incorrect drift of an unbalanced field"
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use crate::algebra::scalar::Scalar;
+use crate::element::element_traits::Element;
+use crate::error::Error;
+use crate::post::function::FEFunction;
+use crate::post::recovery::recover_nodal_values;
+use num::Float;
+
+/// Quantity derived from the real coordinates, values and gradients of the primary fields
+type Quantity<'q, DataType> =
+    Box<dyn Fn(&[DataType], &[DataType], &[DataType]) -> Vec<DataType> + 'q>;
+
+/// Quantity derived from primary fields, as the strain from the displacement
+///
+/// # Generics
+///
+/// * DataType: the type of unit the fields are encoded with
+///
+/// # Explanation
+///
+/// The quantity is a function of the real coordinates of a point, of the values of the primary
+/// fields there and of their gradients in AOS ordering, the derivative of the field `i` along the
+/// direction `j` being at `i * embedding + j`. It is evaluated at the integration points, where
+/// the gradients are the most accurate, and projected to the nodes by superconvergent patch
+/// recovery to build continuous fields for the outputs. Tensors are given in Voigt ordering, `xx,
+/// yy, xy` in 2D and `xx, yy, zz, yz, xz, xy` in 3D, with the tensorial shear components.
+pub struct DerivedField<'q, DataType> {
+    name: String,
+    number_of_components: usize,
+    quantity: Quantity<'q, DataType>,
+}
+
+impl<'q, DataType: Scalar + Float + 'q> DerivedField<'q, DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the name of the quantity, the components being named `<name>_<component>`
+    /// * `number_of_components`: the number of values returned by the quantity
+    /// * `quantity`: the quantity as a function of the real coordinates, the values of the primary
+    ///   fields and their gradients
+    pub fn new(
+        name: &str,
+        number_of_components: usize,
+        quantity: impl Fn(&[DataType], &[DataType], &[DataType]) -> Vec<DataType> + 'q,
+    ) -> DerivedField<'q, DataType> {
+        DerivedField {
+            name: name.to_string(),
+            number_of_components,
+            quantity: Box::new(quantity),
+        }
+    }
+
+    /// Constructor of the small strain `(grad u + grad u^T) / 2` of the components of a
+    /// displacement
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the quantity or an error if the dimension is not 2 or 3
+    pub fn strain(name: &str, dimension: usize) -> Result<DerivedField<'q, DataType>, Error> {
+        let pairs = voigt_pairs(dimension)?;
+        Ok(DerivedField::new(
+            name,
+            pairs.len(),
+            move |_, _, gradients| strain(gradients, dimension, pairs),
+        ))
+    }
+
+    /// Constructor of the linear isotropic stress `λ tr(ε) I + 2 μ ε` of the components of a
+    /// displacement, in plane strain in 2D
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the name of the quantity
+    /// * `dimension`: the number of components of the displacement
+    /// * `lambda`: the first Lamé coefficient
+    /// * `mu`: the shear modulus
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the quantity or an error if the dimension is not 2 or 3
+    pub fn stress(
+        name: &str,
+        dimension: usize,
+        lambda: DataType,
+        mu: DataType,
+    ) -> Result<DerivedField<'q, DataType>, Error> {
+        let pairs = voigt_pairs(dimension)?;
+        Ok(DerivedField::new(
+            name,
+            pairs.len(),
+            move |_, _, gradients| {
+                let strain = strain(gradients, dimension, pairs);
+                let trace = strain[..dimension]
+                    .iter()
+                    .fold(DataType::zero(), |trace, &e| trace + e);
+                let two = DataType::one() + DataType::one();
+                strain
+                    .iter()
+                    .enumerate()
+                    .map(|(c, &e)| {
+                        let diagonal = if c < dimension {
+                            lambda * trace
+                        } else {
+                            DataType::zero()
+                        };
+                        diagonal + two * mu * e
+                    })
+                    .collect()
+            },
+        ))
+    }
+
+    /// Constructor of the vorticity `curl v` of the components of a velocity, a scalar in 2D
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the quantity or an error if the dimension is not 2 or 3
+    pub fn vorticity(name: &str, dimension: usize) -> Result<DerivedField<'q, DataType>, Error> {
+        let d = move |gradients: &[DataType], i: usize, j: usize| gradients[i * dimension + j];
+        match dimension {
+            2 => Ok(DerivedField::new(name, 1, move |_, _, g| {
+                vec![d(g, 1, 0) - d(g, 0, 1)]
+            })),
+            3 => Ok(DerivedField::new(name, 3, move |_, _, g| {
+                vec![
+                    d(g, 2, 1) - d(g, 1, 2),
+                    d(g, 0, 2) - d(g, 2, 0),
+                    d(g, 1, 0) - d(g, 0, 1),
+                ]
+            })),
+            _ => Err(Error::InvalidArgument(
+                "Vorticity is only defined in 2D and 3D",
+            )),
+        }
+    }
+
+    /// Get the name of the quantity
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the number of components of the quantity
+    pub fn get_number_of_components(&self) -> usize {
+        self.number_of_components
+    }
+
+    /// Evaluate the quantity at the integration points of the cells
+    ///
+    /// # Arguments
+    ///
+    /// * `functions`: the primary fields, all defined on the same block of cells
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the components at the integration points of each cell in AOS
+    ///   ordering, or an error if the fields are not defined on the same block, the map of a cell
+    ///   is degenerate or the quantity does not return the number of components
+    pub fn evaluate_for_integration<CoordType, ElementT>(
+        &self,
+        functions: &[&FEFunction<'_, CoordType, DataType, ElementT>],
+    ) -> Result<Vec<Vec<DataType>>, Error>
+    where
+        CoordType: Scalar,
+        DataType: From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let first = functions
+            .first()
+            .ok_or(Error::InvalidArgument("No primary field"))?;
+        let block = first.get_block();
+        if functions
+            .iter()
+            .any(|function| !std::ptr::eq(function.get_block(), block))
+        {
+            return Err(Error::InvalidArgument(
+                "Primary fields are not defined on the same block",
+            ));
+        }
+        let embedding = first.get_embedding_dimension();
+        (0..block.get_number_of_cells())
+            .map(|cell| {
+                let points = first.get_integration_points(cell);
+                let values = functions
+                    .iter()
+                    .map(|function| function.evaluate_for_integration(cell))
+                    .collect::<Vec<_>>();
+                let gradients = functions
+                    .iter()
+                    .map(|function| {
+                        function
+                            .evaluate_gradients_for_integration(cell)
+                            .map(|(gradients, _)| gradients)
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let mut derived = Vec::new();
+                for (point_index, point) in points.chunks(embedding).enumerate() {
+                    let point_values: Vec<DataType> =
+                        values.iter().map(|v| v[point_index]).collect();
+                    let point_gradients: Vec<DataType> = gradients
+                        .iter()
+                        .flat_map(|g| &g[point_index * embedding..(point_index + 1) * embedding])
+                        .copied()
+                        .collect();
+                    let components = (self.quantity)(point, &point_values, &point_gradients);
+                    if components.len() != self.number_of_components {
+                        return Err(Error::SizeMismatch {
+                            context: "Quantity does not return the number of components",
+                            expected: self.number_of_components,
+                            actual: components.len(),
+                        });
+                    }
+                    derived.extend(components);
+                }
+                Ok(derived)
+            })
+            .collect()
+    }
+
+    /// Project the quantity to the nodes as one field per component
+    ///
+    /// # Arguments
+    ///
+    /// * `functions`: the primary fields, all defined on the same block of cells
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the fields, named `<name>` for a scalar quantity and
+    ///   `<name>_<component>` otherwise, or an error as for evaluate_for_integration
+    pub fn project<'a, CoordType, ElementT>(
+        &self,
+        functions: &[&FEFunction<'a, CoordType, DataType, ElementT>],
+    ) -> Result<Vec<FEFunction<'a, CoordType, DataType, ElementT>>, Error>
+    where
+        CoordType: Scalar,
+        DataType: From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let derived = self.evaluate_for_integration(functions)?;
+        let first = functions[0];
+        let samples: Vec<(Vec<DataType>, Vec<DataType>)> = derived
+            .into_iter()
+            .enumerate()
+            .map(|(cell, values)| (first.get_integration_points(cell), values))
+            .collect();
+        recover_nodal_values(first, &samples, self.number_of_components)
+            .into_iter()
+            .enumerate()
+            .map(|(c, coefficients)| {
+                let name = if self.number_of_components == 1 {
+                    self.name.clone()
+                } else {
+                    format!("{}_{}", self.name, c)
+                };
+                FEFunction::new(&name, first.get_element(), first.get_block(), coefficients)
+            })
+            .collect()
+    }
+}
+
+/// Get the pairs of directions of the components of symmetric tensors in Voigt ordering
+fn voigt_pairs(dimension: usize) -> Result<&'static [(usize, usize)], Error> {
+    match dimension {
+        2 => Ok(&[(0, 0), (1, 1), (0, 1)]),
+        3 => Ok(&[(0, 0), (1, 1), (2, 2), (1, 2), (0, 2), (0, 1)]),
+        _ => Err(Error::InvalidArgument(
+            "Tensors are only defined in 2D and 3D",
+        )),
+    }
+}
+
+/// Compute the small strain in Voigt ordering from the gradients of the displacement
+fn strain<DataType: Float>(
+    gradients: &[DataType],
+    dimension: usize,
+    pairs: &[(usize, usize)],
+) -> Vec<DataType> {
+    let half = DataType::from(0.5).unwrap();
+    pairs
+        .iter()
+        .map(|&(i, j)| half * (gradients[i * dimension + j] + gradients[j * dimension + i]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DerivedField;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::function::FEFunction;
+    use crate::test_utils::{uniform_quadrilaterals, BilinearQuadrilateralElement};
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_derived_fields() {
+        let n = 2;
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let mut ux = vec![0.0; 9];
+        let mut uy = vec![0.0; 9];
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            ux[dof] = x[0] + 2.0 * x[1];
+            uy[dof] = 3.0 * x[0] - x[1];
+        }
+        let ux = FEFunction::new("ux", &element, &block, ux).unwrap();
+        let uy = FEFunction::new("uy", &element, &block, uy).unwrap();
+        let strain = DerivedField::strain("strain", 2).unwrap();
+        let strains = strain.project(&[&ux, &uy]).unwrap();
+        assert_eq!(strains[2].get_name(), "strain_2", "Incorrect name");
+        for (component, expected) in strains.iter().zip([1.0, -1.0, 2.5]) {
+            assert!(
+                component
+                    .get_coefficients()
+                    .iter()
+                    .all(|&e| (e - expected).abs() < TOL),
+                "Incorrect strain"
+            );
+        }
+        let stress = DerivedField::stress("stress", 2, 1.0, 1.0).unwrap();
+        let stresses = stress.evaluate_for_integration(&[&ux, &uy]).unwrap();
+        assert!(
+            stresses
+                .iter()
+                .all(|cell| cell.chunks(3).all(|s| (s[0] - 2.0).abs() < TOL
+                    && (s[1] + 2.0).abs() < TOL
+                    && (s[2] - 5.0).abs() < TOL)),
+            "Incorrect stress"
+        );
+        let vorticity = DerivedField::vorticity("vorticity", 2).unwrap();
+        let vorticities = vorticity.project(&[&ux, &uy]).unwrap();
+        assert!(
+            vorticities.len() == 1
+                && vorticities[0].get_name() == "vorticity"
+                && vorticities[0]
+                    .get_coefficients()
+                    .iter()
+                    .all(|&w| (w - 1.0).abs() < TOL),
+            "Incorrect vorticity"
+        );
+        let sum = DerivedField::new("sum", 1, |x, values, _| {
+            vec![values[0] + values[1] - 4.0 * x[0] - x[1]]
+        });
+        assert!(
+            sum.evaluate_for_integration(&[&ux, &uy])
+                .unwrap()
+                .iter()
+                .flatten()
+                .all(|&s| s.abs() < TOL),
+            "Incorrect quantity of the values"
+        );
+        let wrong = DerivedField::new("wrong", 2, |_, _, _| vec![0.0]);
+        assert!(
+            wrong.evaluate_for_integration(&[&ux]).is_err(),
+            "Wrong number of components accepted"
+        );
+        assert!(
+            DerivedField::<f64>::strain("strain", 1).is_err(),
+            "Strain accepted in 1D"
+        );
+    }
+}
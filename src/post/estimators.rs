@@ -0,0 +1,272 @@
+use crate::algebra::scalar::Scalar;
+use crate::assembly::cell_block::CellBlock;
+use crate::element::element_traits::Element;
+use crate::error::Error;
+use crate::post::facets::{diameter, real_points, ReferenceFacets};
+use crate::post::function::FEFunction;
+use num::Float;
+use std::marker::PhantomData;
+
+/// Residual based a posteriori error estimator of diffusion problems
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the fields are encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// For a discrete solution `u_h` of `-div(κ grad u) = f` with a conductivity `κ` constant in each
+/// cell, the indicator of a cell `K` of diameter `h_K` is
+///
+/// `η_K^2 = h_K^2 ||f + div(κ grad u_h)||_K^2 + 1/2 h_K sum_F ||[κ grad u_h . n]||_F^2`
+///
+/// where the sum runs over the interior facets of the cell and the brackets denote the jump of the
+/// normal flux across the facet, shared with the neighbouring cell. The divergence of the discrete
+/// flux is neglected, which is exact for linear simplices and affine multilinear cells. The
+/// conductivity is read from the "conductivity" data of the cells, as the operators do, and is
+/// unit when absent. Boundary facets are taken as Dirichlet boundaries and do not contribute.
+pub struct ResidualEstimator<'a, CoordType, DataType, ElementT> {
+    element: &'a ElementT,
+    facets: ReferenceFacets<CoordType>,
+    phantom: PhantomData<DataType>,
+}
+
+impl<'a, CoordType, DataType, ElementT> ResidualEstimator<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `facets`: the facets of the reference element
+    pub fn new(
+        element: &'a ElementT,
+        facets: ReferenceFacets<CoordType>,
+    ) -> ResidualEstimator<'a, CoordType, DataType, ElementT> {
+        ResidualEstimator {
+            element,
+            facets,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Get the facets of the reference element
+    pub fn get_facets(&self) -> &ReferenceFacets<CoordType> {
+        &self.facets
+    }
+
+    /// Compute the error indicators of the cells
+    ///
+    /// # Arguments
+    ///
+    /// * `function`: the discrete solution
+    /// * `source`: the source term as a function of the real coordinates
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the indicator `η_K` of each cell, to be marked for refinement or
+    ///   written as cell data, or an error if the field is not described by the element, a facet
+    ///   is shared by more than two cells or a cell is degenerate
+    pub fn compute_indicators(
+        &self,
+        function: &FEFunction<'_, CoordType, DataType, ElementT>,
+        source: impl Fn(&[DataType]) -> DataType,
+    ) -> Result<Vec<DataType>, Error> {
+        if !std::ptr::eq(function.get_element(), self.element) {
+            return Err(Error::InvalidArgument(
+                "Field is not described by the element of the estimator",
+            ));
+        }
+        let block = function.get_block();
+        let number_of_cells = block.get_number_of_cells();
+        let embedding = function.get_embedding_dimension();
+        let conductivity = get_conductivity(block);
+        let diameters: Vec<DataType> = (0..number_of_cells)
+            .map(|cell| diameter(&real_points(block, cell, embedding)))
+            .collect();
+        let mut squared = Vec::with_capacity(number_of_cells);
+        for (cell, &h) in diameters.iter().enumerate() {
+            let points = function.get_integration_points(cell);
+            let weights = function.get_integration_weights(cell)?;
+            let residual = points.chunks(embedding).zip(weights).fold(
+                DataType::zero(),
+                |sum, (point, weight)| {
+                    let f = source(point);
+                    sum + weight * f * f
+                },
+            );
+            squared.push(h * h * residual);
+        }
+        let half: DataType = num::cast(0.5).unwrap();
+        for sides in self.facets.find_facets(block) {
+            match sides[..] {
+                [_] => {}
+                [(first, facet), (second, _)] => {
+                    let jump =
+                        self.integrate_jump(function, &conductivity, first, facet, second)?;
+                    squared[first] = squared[first] + half * diameters[first] * jump;
+                    squared[second] = squared[second] + half * diameters[second] * jump;
+                }
+                _ => {
+                    return Err(Error::InvalidArgument(
+                        "Facet shared by more than two cells",
+                    ))
+                }
+            }
+        }
+        Ok(squared.into_iter().map(Float::sqrt).collect())
+    }
+
+    /// Integrate the squared jump of the normal flux across a facet of a cell shared with a
+    /// neighbour
+    fn integrate_jump(
+        &self,
+        function: &FEFunction<'_, CoordType, DataType, ElementT>,
+        conductivity: &[DataType],
+        cell: usize,
+        facet: usize,
+        neighbour: usize,
+    ) -> Result<DataType, Error> {
+        let block = function.get_block();
+        let geometry = self
+            .facets
+            .compute_geometry(self.element, block, cell, facet)?;
+        let nodes = self.facets.get_facet(facet);
+        let neighbour_dofs = block.get_cell_dofs(neighbour);
+        let mut sum = DataType::zero();
+        for &node in nodes {
+            let dof = block.get_cell_dofs(cell)[node];
+            let other =
+                neighbour_dofs
+                    .iter()
+                    .position(|&d| d == dof)
+                    .ok_or(Error::InvalidArgument(
+                        "Facet dofs not shared by the neighbour",
+                    ))?;
+            let inner = function.evaluate_gradient(cell, self.facets.get_reference_node(node))?;
+            let outer =
+                function.evaluate_gradient(neighbour, self.facets.get_reference_node(other))?;
+            let jump = inner.iter().zip(&outer).zip(&geometry.normal).fold(
+                DataType::zero(),
+                |jump, ((&a, &b), &n)| {
+                    jump + (conductivity[cell] * a - conductivity[neighbour] * b) * n
+                },
+            );
+            sum = sum + jump * jump;
+        }
+        let count: DataType = num::cast(nodes.len()).unwrap();
+        Ok(geometry.measure * sum / count)
+    }
+}
+
+/// Compute the global estimate `sqrt(sum η_K^2)` from the indicators of the cells
+pub fn compute_global_estimate<DataType: Scalar + Float>(indicators: &[DataType]) -> DataType {
+    indicators
+        .iter()
+        .fold(DataType::zero(), |sum, &eta| sum + eta * eta)
+        .sqrt()
+}
+
+/// Get the conductivity of each cell of a block from its "conductivity" data, unit when absent
+pub(crate) fn get_conductivity<CoordType, DataType: Scalar>(
+    block: &CellBlock<CoordType, DataType>,
+) -> Vec<DataType> {
+    (0..block.get_number_of_cells())
+        .map(|cell| {
+            block
+                .get_cell_data(cell)
+                .get("conductivity")
+                .map_or(DataType::one(), |values| values[0])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_global_estimate, ResidualEstimator};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::facets::ReferenceFacets;
+    use crate::post::function::FEFunction;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, uniform_segments,
+        BilinearQuadrilateralElement, LinearSegmentElement,
+    };
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_segments() {
+        let n = 4;
+        let h = 1.0 / n as f64;
+        let (dofs, coords) = uniform_segments(n);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let element = LinearSegmentElement::new();
+        let facets = ReferenceFacets::new(&element, &[-1.0, 1.0], vec![vec![0], vec![1]]).unwrap();
+        let estimator = ResidualEstimator::new(&element, facets);
+        let solution = (0..=n)
+            .map(|i| {
+                let x = i as f64 * h;
+                x * (1.0 - x)
+            })
+            .collect();
+        let u = FEFunction::new("u", &element, &block, solution).unwrap();
+        let indicators = estimator.compute_indicators(&u, |_| 2.0).unwrap();
+        for (cell, eta) in indicators.iter().enumerate() {
+            let expected = if cell == 0 || cell == n - 1 { 6.0 } else { 8.0 };
+            assert!(
+                (eta * eta - expected * h.powi(3)).abs() < TOL,
+                "Incorrect indicator"
+            );
+        }
+        let global = compute_global_estimate(&indicators);
+        assert!(
+            (global * global - 28.0 * h.powi(3)).abs() < TOL,
+            "Incorrect global estimate"
+        );
+    }
+
+    #[test]
+    fn test_quadrilaterals() {
+        let n = 4;
+        let h = 1.0 / n as f64;
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let conductivity = vec![2.0; n * n];
+        let mut conducting = CellBlock::new(4, &dofs, &coords).unwrap();
+        conducting.add_field("conductivity", &conductivity).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let estimator = ResidualEstimator::new(&element, facets);
+        let nodal = |f: fn(f64, f64) -> f64| -> Vec<f64> {
+            (0..(n + 1) * (n + 1))
+                .map(|dof| f((dof % (n + 1)) as f64 * h, (dof / (n + 1)) as f64 * h))
+                .collect()
+        };
+        let u = FEFunction::new("u", &element, &block, nodal(|x, y| x - 2.0 * y)).unwrap();
+        let indicators = estimator.compute_indicators(&u, |_| 0.0).unwrap();
+        assert!(
+            indicators.iter().all(|&eta| eta < TOL),
+            "Incorrect indicators of an exact solution"
+        );
+        let quadratic = nodal(|x, _| x * x);
+        let u = FEFunction::new("u", &element, &block, quadratic.clone()).unwrap();
+        let indicators = estimator.compute_indicators(&u, |_| -2.0).unwrap();
+        let expected = (8.0 + 4.0 * 2.0_f64.sqrt()) * h.powi(4);
+        assert!(
+            (indicators[n + 1].powi(2) - expected).abs() < TOL,
+            "Incorrect indicator of an interior cell"
+        );
+        let u = FEFunction::new("u", &element, &conducting, quadratic).unwrap();
+        let scaled = estimator.compute_indicators(&u, |_| -4.0).unwrap();
+        assert!(
+            (scaled[n + 1] - 2.0 * indicators[n + 1]).abs() < TOL,
+            "Incorrect scaling with the conductivity"
+        );
+    }
+}
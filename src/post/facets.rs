@@ -0,0 +1,580 @@
+use crate::algebra::scalar::Scalar;
+use crate::assembly::cell_block::CellBlock;
+use crate::element::element_traits::{Element, ShapeBasis};
+use crate::error::Error;
+use crate::post::function::{compute_jacobian, get_embedding_dimension, map_to_physical};
+use num::Float;
+use std::collections::BTreeMap;
+
+/// Facets of a reference element
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the element space
+///
+/// # Explanation
+///
+/// The facets are described by the local indices of their vertices, in cyclic order for faces,
+/// along with the coordinates of the nodes of the shape basis on the reference element. Two cells
+/// of a block are neighbours across a facet when they share the dofs of its vertices, facets
+/// belonging to a single cell are on the boundary of the block. The facets of higher order
+/// elements list all the nodes lying on them. Facet integrals of the post-processing are computed
+/// with the vertices as integration points, which is exact for affine integrands on straight
+/// facets, while the boundary loads are integrated with a Gauss rule mapped through the shape
+/// functions of the cell, which follows curved facets.
+pub struct ReferenceFacets<CoordType> {
+    dimension: usize,
+    reference_nodes: Vec<CoordType>,
+    facets: Vec<Vec<usize>>,
+}
+
+/// Real geometry of the facet of a cell
+pub(crate) struct FacetGeometry<DataType> {
+    /// The real coordinates of the vertices
+    pub vertices: Vec<Vec<DataType>>,
+    /// The unit normal pointing out of the cell
+    pub normal: Vec<DataType>,
+    /// The measure of the facet
+    pub measure: DataType,
+}
+
+/// Integration point on the facet of a cell
+pub(crate) struct FacetPoint<DataType> {
+    /// The shape functions of the cell at the point
+    pub shapes: Vec<DataType>,
+    /// The real coordinates of the point
+    pub point: Vec<DataType>,
+    /// The integration weight scaled by the measure of the facet at the point
+    pub weight: DataType,
+}
+
+impl<CoordType: Scalar> ReferenceFacets<CoordType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element the facets belong to
+    /// * `reference_nodes`: the coordinates of the nodes of the shape basis on the reference
+    ///   element in AOS ordering
+    /// * `facets`: the local indices of the vertices of each facet
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the facets or an error if the nodes or the facets do not match
+    ///   the shape basis
+    pub fn new<DataType, ElementT>(
+        element: &ElementT,
+        reference_nodes: &[CoordType],
+        facets: Vec<Vec<usize>>,
+    ) -> Result<ReferenceFacets<CoordType>, Error>
+    where
+        DataType: Scalar,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let basis = element.get_shape_basis();
+        let dimension = basis.get_dimension();
+        if reference_nodes.len() != basis.get_number_of_bases() * dimension {
+            return Err(Error::SizeMismatch {
+                context: "Reference nodes do not match the shape basis",
+                expected: basis.get_number_of_bases() * dimension,
+                actual: reference_nodes.len(),
+            });
+        }
+        if facets.is_empty()
+            || facets.iter().any(|facet| {
+                facet.is_empty()
+                    || facet
+                        .iter()
+                        .any(|&node| node >= basis.get_number_of_bases())
+            })
+        {
+            return Err(Error::InvalidArgument(
+                "Facets do not match the nodes of the shape basis",
+            ));
+        }
+        Ok(ReferenceFacets {
+            dimension,
+            reference_nodes: reference_nodes.to_vec(),
+            facets,
+        })
+    }
+
+    /// Get the dimension of the reference element
+    pub fn get_dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Get the number of facets
+    pub fn get_number_of_facets(&self) -> usize {
+        self.facets.len()
+    }
+
+    /// Check that a `(cell, facet)` pair designates a facet of a block of cells
+    pub(crate) fn check_facet<DataType>(
+        &self,
+        block: &CellBlock<CoordType, DataType>,
+        cell: usize,
+        facet: usize,
+    ) -> Result<(), Error> {
+        if cell >= block.get_number_of_cells() {
+            return Err(Error::OutOfBounds {
+                context: "Facet cell out of the block",
+                index: cell,
+                bound: block.get_number_of_cells(),
+            });
+        }
+        if facet >= self.facets.len() {
+            return Err(Error::OutOfBounds {
+                context: "Facet out of the reference facets",
+                index: facet,
+                bound: self.facets.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the local indices of the vertices of a facet
+    pub fn get_facet(&self, facet: usize) -> &[usize] {
+        &self.facets[facet]
+    }
+
+    /// Get the reference coordinates of a node of the shape basis
+    pub fn get_reference_node(&self, node: usize) -> &[CoordType] {
+        &self.reference_nodes[node * self.dimension..(node + 1) * self.dimension]
+    }
+
+    /// Get the global dofs of the vertices of a facet of a cell
+    pub fn get_facet_dofs<DataType>(
+        &self,
+        block: &CellBlock<CoordType, DataType>,
+        cell: usize,
+        facet: usize,
+    ) -> Vec<usize> {
+        let dofs = block.get_cell_dofs(cell);
+        self.facets[facet].iter().map(|&node| dofs[node]).collect()
+    }
+
+    /// Find the distinct facets of a block
+    ///
+    /// # Returns
+    ///
+    /// * the `(cell, facet)` sides of each distinct facet, two for interior facets and one for
+    ///   boundary facets, ordered by the dofs of the facets
+    pub fn find_facets<DataType>(
+        &self,
+        block: &CellBlock<CoordType, DataType>,
+    ) -> Vec<Vec<(usize, usize)>> {
+        let mut sides: BTreeMap<Vec<usize>, Vec<(usize, usize)>> = BTreeMap::new();
+        for cell in 0..block.get_number_of_cells() {
+            for facet in 0..self.facets.len() {
+                let mut key = self.get_facet_dofs(block, cell, facet);
+                key.sort_unstable();
+                sides.entry(key).or_default().push((cell, facet));
+            }
+        }
+        sides.into_values().collect()
+    }
+
+    /// Find the `(cell, facet)` pairs on the boundary of a block
+    pub fn find_boundary_facets<DataType>(
+        &self,
+        block: &CellBlock<CoordType, DataType>,
+    ) -> Vec<(usize, usize)> {
+        self.find_facets(block)
+            .into_iter()
+            .filter(|sides| sides.len() == 1)
+            .map(|sides| sides[0])
+            .collect()
+    }
+
+    /// Check whether a point lies inside the reference element, up to a tolerance
+    ///
+    /// The reference element is convex: the point is inside when it is on the inner side of the
+    /// hyperplane of each facet.
+    pub fn contains<DataType>(&self, reference: &[DataType], tolerance: DataType) -> bool
+    where
+        DataType: Scalar + Float + From<CoordType>,
+    {
+        let nodes: Vec<Vec<DataType>> = self
+            .reference_nodes
+            .chunks(self.dimension)
+            .map(|node| node.iter().map(|&x| x.into()).collect())
+            .collect();
+        let center = centroid(&nodes);
+        let identity: Vec<DataType> = (0..self.dimension * self.dimension)
+            .map(|k| {
+                if k % (self.dimension + 1) == 0 {
+                    DataType::one()
+                } else {
+                    DataType::zero()
+                }
+            })
+            .collect();
+        self.facets.iter().all(|facet| {
+            let vertices: Vec<Vec<DataType>> =
+                facet.iter().map(|&node| nodes[node].clone()).collect();
+            facet_normal(&vertices, &identity, self.dimension).is_none_or(|normal| {
+                let side = dot(&normal, &difference(&vertices[0], &center)).signum();
+                side * dot(&normal, &difference(reference, &vertices[0])) <= tolerance
+            })
+        })
+    }
+
+    /// Compute the real geometry of the facet of a cell
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the geometry or an error if the facet is degenerate
+    pub(crate) fn compute_geometry<DataType, ElementT>(
+        &self,
+        element: &ElementT,
+        block: &CellBlock<CoordType, DataType>,
+        cell: usize,
+        facet: usize,
+    ) -> Result<FacetGeometry<DataType>, Error>
+    where
+        DataType: Scalar + Float + From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let embedding = get_embedding_dimension(element, block);
+        let nodes = &self.facets[facet];
+        let points = real_points(block, cell, embedding);
+        let vertices: Vec<Vec<DataType>> = nodes.iter().map(|&node| points[node].clone()).collect();
+        let derivatives = element
+            .get_shape_basis()
+            .interpolate_basis_derivative(self.get_reference_node(nodes[0]));
+        let jacobian = compute_jacobian(element, block, cell, &derivatives);
+        let mut normal = facet_normal(&vertices, &jacobian, self.dimension)
+            .ok_or(Error::DegenerateCell { cell })?;
+        let outward = difference(&centroid(&vertices), &centroid(&points));
+        if dot(&normal, &outward) < DataType::zero() {
+            normal.iter_mut().for_each(|n| *n = -*n);
+        }
+        let measure = facet_measure(&vertices, self.dimension - 1);
+        Ok(FacetGeometry {
+            vertices,
+            normal,
+            measure,
+        })
+    }
+
+    /// Compute the integration points of the facet of a cell
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the points or an error if the facet is degenerate
+    ///
+    /// # Explanation
+    ///
+    /// Edges are parametrized between their two furthest nodes and faces are split in a fan of
+    /// triangles, integrated with three Gauss points per direction collapsed on the triangles.
+    /// The points are mapped through the shape functions of the cell, the rule being exact for
+    /// the quintic integrands of straight edges, such as quadratic shape functions weighting
+    /// cubic fluxes.
+    pub(crate) fn compute_quadrature<DataType, ElementT>(
+        &self,
+        element: &ElementT,
+        block: &CellBlock<CoordType, DataType>,
+        cell: usize,
+        facet: usize,
+    ) -> Result<Vec<FacetPoint<DataType>>, Error>
+    where
+        CoordType: Float,
+        DataType: Scalar + Float + From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let basis = element.get_shape_basis();
+        let embedding = get_embedding_dimension(element, block);
+        let mut points = Vec::new();
+        for (reference, tangents, weight) in self.get_reference_quadrature(facet) {
+            let shapes = basis.interpolate_basis(&reference);
+            let derivatives = basis.interpolate_basis_derivative(&reference);
+            let jacobian = compute_jacobian(element, block, cell, &derivatives);
+            let real: Vec<Vec<DataType>> = tangents
+                .iter()
+                .map(|tangent| {
+                    (0..embedding)
+                        .map(|i| {
+                            tangent
+                                .iter()
+                                .enumerate()
+                                .fold(DataType::zero(), |x, (j, &t)| {
+                                    x + jacobian[i * self.dimension + j] * t.into()
+                                })
+                        })
+                        .collect()
+                })
+                .collect();
+            let measure = match real.as_slice() {
+                [] => DataType::one(),
+                [a] => norm(a),
+                [a, b, ..] => {
+                    let (aa, bb, ab) = (dot(a, a), dot(b, b), dot(a, b));
+                    (aa * bb - ab * ab).max(DataType::zero()).sqrt()
+                }
+            };
+            if !measure.is_finite() {
+                return Err(Error::DegenerateCell { cell });
+            }
+            let point = map_to_physical(element, block, cell, &shapes);
+            points.push(FacetPoint {
+                shapes,
+                point,
+                weight: measure * weight.into(),
+            });
+        }
+        Ok(points)
+    }
+
+    /// Get the integration points of a facet on the reference element along with the tangents of
+    /// their parametrization and their weights
+    fn get_reference_quadrature(
+        &self,
+        facet: usize,
+    ) -> Vec<(Vec<CoordType>, Vec<Vec<CoordType>>, CoordType)>
+    where
+        CoordType: Float,
+    {
+        let nodes: Vec<&[CoordType]> = self.facets[facet]
+            .iter()
+            .map(|&node| self.get_reference_node(node))
+            .collect();
+        let half = CoordType::from(0.5).unwrap();
+        let abscissa = CoordType::from(0.6).unwrap().sqrt();
+        let gauss: Vec<(CoordType, CoordType)> = [
+            (-abscissa, CoordType::from(5.0 / 9.0).unwrap()),
+            (CoordType::zero(), CoordType::from(8.0 / 9.0).unwrap()),
+            (abscissa, CoordType::from(5.0 / 9.0).unwrap()),
+        ]
+        .iter()
+        .map(|&(x, w)| (half * (x + CoordType::one()), half * w))
+        .collect();
+        let along = |origin: &[CoordType], tangents: &[Vec<CoordType>], steps: &[CoordType]| {
+            origin
+                .iter()
+                .enumerate()
+                .map(|(i, &x)| {
+                    tangents
+                        .iter()
+                        .zip(steps)
+                        .fold(x, |x, (tangent, &s)| x + s * tangent[i])
+                })
+                .collect::<Vec<CoordType>>()
+        };
+        match self.dimension {
+            0 | 1 => vec![(nodes[0].to_vec(), Vec::new(), CoordType::one())],
+            2 => {
+                let (mut first, mut last, mut length) = (0, 0, CoordType::zero());
+                for (a, x) in nodes.iter().enumerate() {
+                    for (b, y) in nodes.iter().enumerate().skip(a + 1) {
+                        let distance = norm(&difference(x, y));
+                        if distance > length {
+                            (first, last, length) = (a, b, distance);
+                        }
+                    }
+                }
+                let tangents = vec![difference(nodes[last], nodes[first])];
+                gauss
+                    .iter()
+                    .map(|&(s, w)| (along(nodes[first], &tangents, &[s]), tangents.clone(), w))
+                    .collect()
+            }
+            _ => nodes[1..]
+                .windows(2)
+                .flat_map(|pair| {
+                    let tangents =
+                        vec![difference(pair[0], nodes[0]), difference(pair[1], nodes[0])];
+                    let mut points = Vec::new();
+                    for &(u, wu) in &gauss {
+                        for &(v, wv) in &gauss {
+                            let steps = [u, v * (CoordType::one() - u)];
+                            let weight = wu * wv * (CoordType::one() - u);
+                            points.push((
+                                along(nodes[0], &tangents, &steps),
+                                tangents.clone(),
+                                weight,
+                            ));
+                        }
+                    }
+                    points
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Get the real coordinates of the nodes of a cell
+pub(crate) fn real_points<CoordType, DataType>(
+    block: &CellBlock<CoordType, DataType>,
+    cell: usize,
+    embedding: usize,
+) -> Vec<Vec<DataType>>
+where
+    CoordType: Scalar,
+    DataType: Scalar + From<CoordType>,
+{
+    block
+        .get_cell_coordinates(cell)
+        .chunks(embedding)
+        .map(|node| node.iter().map(|&x| x.into()).collect())
+        .collect()
+}
+
+/// Compute the largest distance between points
+pub(crate) fn diameter<DataType: Scalar + Float>(points: &[Vec<DataType>]) -> DataType {
+    let mut diameter = DataType::zero();
+    for (i, a) in points.iter().enumerate() {
+        for b in &points[i + 1..] {
+            diameter = diameter.max(norm(&difference(a, b)));
+        }
+    }
+    diameter
+}
+
+/// Compute the mean of points
+fn centroid<DataType: Scalar + Float>(points: &[Vec<DataType>]) -> Vec<DataType> {
+    let count = DataType::from(points.len()).unwrap();
+    let mut centroid = vec![DataType::zero(); points[0].len()];
+    for point in points {
+        for (c, &x) in centroid.iter_mut().zip(point) {
+            *c = *c + x / count;
+        }
+    }
+    centroid
+}
+
+/// Compute the measure of a facet of dimension at most 2 from its vertices, faces being split in a
+/// fan of triangles
+fn facet_measure<DataType: Scalar + Float>(
+    vertices: &[Vec<DataType>],
+    dimension: usize,
+) -> DataType {
+    match dimension {
+        0 => DataType::one(),
+        1 => diameter(vertices),
+        _ => {
+            let half = DataType::from(0.5).unwrap();
+            vertices[1..]
+                .windows(2)
+                .fold(DataType::zero(), |measure, pair| {
+                    let a = difference(&pair[0], &vertices[0]);
+                    let b = difference(&pair[1], &vertices[0]);
+                    let (aa, bb, ab) = (dot(&a, &a), dot(&b, &b), dot(&a, &b));
+                    measure + half * (aa * bb - ab * ab).max(DataType::zero()).sqrt()
+                })
+        }
+    }
+}
+
+/// Compute the unit normal of a facet in the tangent space of the cell, spanned by the columns of
+/// the jacobian
+fn facet_normal<DataType: Scalar + Float>(
+    vertices: &[Vec<DataType>],
+    jacobian: &[DataType],
+    dimension: usize,
+) -> Option<Vec<DataType>> {
+    let embedding = jacobian.len() / dimension;
+    let mut basis: Vec<Vec<DataType>> = Vec::new();
+    for vertex in &vertices[1..] {
+        if let Some(direction) = orthonormalize(difference(vertex, &vertices[0]), &basis) {
+            basis.push(direction);
+        }
+    }
+    (0..dimension)
+        .filter_map(|j| {
+            let tangent = (0..embedding)
+                .map(|i| jacobian[i * dimension + j])
+                .collect();
+            orthonormalize(tangent, &basis)
+        })
+        .next()
+}
+
+/// Remove the components of a vector along an orthonormal basis and normalize it, None if nothing
+/// remains
+fn orthonormalize<DataType: Scalar + Float>(
+    mut vector: Vec<DataType>,
+    basis: &[Vec<DataType>],
+) -> Option<Vec<DataType>> {
+    let length = norm(&vector);
+    for direction in basis {
+        let projection = dot(&vector, direction);
+        for (v, &d) in vector.iter_mut().zip(direction) {
+            *v = *v - projection * d;
+        }
+    }
+    let remainder = norm(&vector);
+    let tolerance = DataType::from(1e-10).unwrap();
+    if remainder <= tolerance * length {
+        return None;
+    }
+    Some(vector.into_iter().map(|v| v / remainder).collect())
+}
+
+fn difference<DataType: Scalar>(a: &[DataType], b: &[DataType]) -> Vec<DataType> {
+    a.iter().zip(b).map(|(&x, &y)| x - y).collect()
+}
+
+fn dot<DataType: Scalar>(a: &[DataType], b: &[DataType]) -> DataType {
+    a.iter()
+        .zip(b)
+        .fold(DataType::zero(), |sum, (&x, &y)| sum + x * y)
+}
+
+fn norm<DataType: Scalar + Float>(a: &[DataType]) -> DataType {
+    dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReferenceFacets;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::test_utils::{
+        quadrilateral_corners, quadrilateral_facets, uniform_quadrilaterals,
+        BilinearQuadrilateralElement,
+    };
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_facets() {
+        let (dofs, coords) = uniform_quadrilaterals(2);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let corners = quadrilateral_corners();
+        assert!(
+            ReferenceFacets::new(&element, &corners, vec![vec![0, 4]]).is_err(),
+            "Facet out of the nodes accepted"
+        );
+        let facets = quadrilateral_facets();
+        let distinct = facets.find_facets(&block);
+        assert_eq!(distinct.len(), 12, "Incorrect number of facets");
+        assert_eq!(
+            distinct.iter().filter(|sides| sides.len() == 2).count(),
+            4,
+            "Incorrect number of interior facets"
+        );
+        assert_eq!(
+            facets.find_boundary_facets(&block).len(),
+            8,
+            "Incorrect number of boundary facets"
+        );
+        let geometry = facets.compute_geometry(&element, &block, 0, 3).unwrap();
+        assert!(
+            facets.contains(&[1.0, -0.5], TOL) && !facets.contains(&[0.2, 1.1], TOL),
+            "Incorrect reference element"
+        );
+        assert!(
+            (geometry.normal[0] + 1.0).abs() < TOL
+                && geometry.normal[1].abs() < TOL
+                && (geometry.measure - 0.5).abs() < TOL,
+            "Incorrect facet geometry"
+        );
+        let points = facets.compute_quadrature(&element, &block, 0, 3).unwrap();
+        assert!(
+            (points.iter().map(|point| point.weight).sum::<f64>() - 0.5).abs() < TOL
+                && points.iter().all(|point| point.point[0].abs() < TOL),
+            "Incorrect facet quadrature"
+        );
+    }
+}
@@ -0,0 +1,797 @@
+use crate::algebra::dense::{determinant, invert_small, solve_dense};
+use crate::algebra::scalar::Scalar;
+use crate::assembly::cell_block::CellBlock;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::error::Error;
+use crate::parallel::Parallelism;
+use num::Float;
+
+/// Scalar finite element field over a block of cells
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the field is encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// The field is given by its values at the global degrees of freedom, weighting the shape
+/// functions of the element in each cell through the connectivity of the block. The cells are
+/// isoparametric: the coordinates of a cell are the ones of the nodes of the shape basis, so that
+/// the map from the reference element and its jacobian are interpolated with the same shape
+/// functions as the field. Cells of lower dimension than the embedding space (segments in the
+/// plane, surface cells) are handled through the metric of the jacobian.
+pub struct FEFunction<'a, CoordType, DataType, ElementT> {
+    name: String,
+    element: &'a ElementT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    coefficients: Vec<DataType>,
+}
+
+impl<'a, CoordType, DataType, ElementT> FEFunction<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the name of the field, used by the outputs
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells the field is defined on
+    /// * `coefficients`: the values of the field at the global degrees of freedom
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the field or an error if the block does not match the element
+    ///   or a dof of the block has no coefficient
+    pub fn new(
+        name: &str,
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        coefficients: Vec<DataType>,
+    ) -> Result<FEFunction<'a, CoordType, DataType, ElementT>, Error> {
+        check_block(element, block)?;
+        let number_of_dofs = (0..block.get_number_of_cells())
+            .flat_map(|cell| block.get_cell_dofs(cell).iter().copied())
+            .max()
+            .map_or(0, |dof| dof + 1);
+        if coefficients.len() < number_of_dofs {
+            return Err(Error::SizeMismatch {
+                context: "Coefficients missing for the dofs of the block",
+                expected: number_of_dofs,
+                actual: coefficients.len(),
+            });
+        }
+        Ok(FEFunction {
+            name: name.to_string(),
+            element,
+            block,
+            coefficients,
+        })
+    }
+
+    /// Get the name of the field
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the element describing the cells
+    pub fn get_element(&self) -> &'a ElementT {
+        self.element
+    }
+
+    /// Get the cells the field is defined on
+    pub fn get_block(&self) -> &'a CellBlock<'a, CoordType, DataType> {
+        self.block
+    }
+
+    /// Get the values of the field at the global degrees of freedom
+    pub fn get_coefficients(&self) -> &[DataType] {
+        &self.coefficients
+    }
+
+    /// Replace the values of the field at the global degrees of freedom, for instance at each time
+    /// step
+    pub fn set_coefficients(&mut self, coefficients: &[DataType]) -> Result<(), Error> {
+        if coefficients.len() != self.coefficients.len() {
+            return Err(Error::SizeMismatch {
+                context: "Number of coefficients does not match the field",
+                expected: self.coefficients.len(),
+                actual: coefficients.len(),
+            });
+        }
+        self.coefficients.copy_from_slice(coefficients);
+        Ok(())
+    }
+
+    /// Get the dimension of the space the cells are embedded in
+    pub fn get_embedding_dimension(&self) -> usize {
+        get_embedding_dimension(self.element, self.block)
+    }
+
+    /// Get the values of the field at the degrees of freedom of a cell
+    pub fn get_cell_coefficients(&self, cell: usize) -> Vec<DataType> {
+        self.block
+            .get_cell_dofs(cell)
+            .iter()
+            .map(|&dof| self.coefficients[dof])
+            .collect()
+    }
+
+    /// Map a point of the reference element to the real coordinates in a cell
+    pub fn map_to_physical(&self, cell: usize, reference: &[CoordType]) -> Vec<DataType> {
+        let shapes = self.element.get_shape_basis().interpolate_basis(reference);
+        map_to_physical(self.element, self.block, cell, &shapes)
+    }
+
+    /// Evaluate the field at a point of the reference element in a cell
+    pub fn evaluate(&self, cell: usize, reference: &[CoordType]) -> DataType {
+        let shapes = self.element.get_shape_basis().interpolate_basis(reference);
+        self.interpolate(cell, &shapes)
+    }
+
+    /// Evaluate the gradient of the field with respect to the real coordinates at a point of the
+    /// reference element in a cell
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the gradient or an error if the map of the cell is degenerate
+    pub fn evaluate_gradient(
+        &self,
+        cell: usize,
+        reference: &[CoordType],
+    ) -> Result<Vec<DataType>, Error> {
+        let derivatives = self
+            .element
+            .get_shape_basis()
+            .interpolate_basis_derivative(reference);
+        self.interpolate_gradient(cell, &derivatives)
+            .map(|(gradient, _)| gradient)
+    }
+
+    /// Get the real coordinates of the integration points of a cell in AOS ordering
+    pub fn get_integration_points(&self, cell: usize) -> Vec<DataType> {
+        let nbases = self.element.get_shape_basis().get_number_of_bases();
+        self.element
+            .get_shapes_for_integration()
+            .chunks(nbases)
+            .flat_map(|shapes| map_to_physical(self.element, self.block, cell, shapes))
+            .collect()
+    }
+
+    /// Get the integration weights of a cell, the reference weights scaled by the measure of the
+    /// map
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the weights or an error if the map of the cell is degenerate
+    pub fn get_integration_weights(&self, cell: usize) -> Result<Vec<DataType>, Error> {
+        let (_, weights) = self.evaluate_gradients_for_integration(cell)?;
+        Ok(weights)
+    }
+
+    /// Evaluate the field at the integration points of a cell
+    pub fn evaluate_for_integration(&self, cell: usize) -> Vec<DataType> {
+        let nbases = self.element.get_shape_basis().get_number_of_bases();
+        self.element
+            .get_shapes_for_integration()
+            .chunks(nbases)
+            .map(|shapes| self.interpolate(cell, shapes))
+            .collect()
+    }
+
+    /// Evaluate the gradient of the field at the integration points of a cell
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the gradients in AOS ordering along with the integration weights
+    ///   of the cell, or an error if the map of the cell is degenerate
+    pub fn evaluate_gradients_for_integration(
+        &self,
+        cell: usize,
+    ) -> Result<(Vec<DataType>, Vec<DataType>), Error> {
+        let basis = self.element.get_shape_basis();
+        let size = basis.get_number_of_bases() * basis.get_dimension();
+        let mut gradients = Vec::new();
+        let mut weights = Vec::new();
+        for (derivatives, &weight) in self
+            .element
+            .get_shape_derivatives_for_integration()
+            .chunks(size)
+            .zip(self.element.get_integrator().get_weights())
+        {
+            let (gradient, measure) = self.interpolate_gradient(cell, derivatives)?;
+            gradients.extend(gradient);
+            weights.push(weight * measure);
+        }
+        Ok((gradients, weights))
+    }
+
+    /// Integrate the field over the block
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the integral or an error if the map of a cell is degenerate
+    pub fn integrate(&self) -> Result<DataType, Error> {
+        let mut integral = DataType::zero();
+        for cell in 0..self.block.get_number_of_cells() {
+            let weights = self.get_integration_weights(cell)?;
+            for (value, weight) in self.evaluate_for_integration(cell).iter().zip(weights) {
+                integral = integral + *value * weight;
+            }
+        }
+        Ok(integral)
+    }
+
+    /// Same as integrate above but integrating the cells on several threads
+    ///
+    /// In deterministic mode the integrals of the chunks of cells are summed in the order of the
+    /// chunks, so that the integral is bitwise reproducible for a given chunk size, whatever the
+    /// number of threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `parallelism`: the configuration of the parallel loop
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the integral or an error if the map of a cell is degenerate
+    pub fn integrate_parallel(&self, parallelism: &Parallelism) -> Result<DataType, Error>
+    where
+        CoordType: Sync,
+        DataType: Send + Sync,
+        ElementT: Sync,
+    {
+        parallelism.fold(
+            self.block.get_number_of_cells(),
+            || Ok(DataType::zero()),
+            |integral: &mut Result<DataType, Error>, cells| {
+                for cell in cells {
+                    let Ok(sum) = integral else {
+                        return;
+                    };
+                    *integral = self.get_integration_weights(cell).map(|weights| {
+                        self.evaluate_for_integration(cell)
+                            .iter()
+                            .zip(weights)
+                            .fold(*sum, |sum, (value, weight)| sum + *value * weight)
+                    });
+                }
+            },
+            |first, second| Ok(first? + second?),
+        )
+    }
+
+    /// Interpolate the field in a cell from the values of the shape functions
+    fn interpolate(&self, cell: usize, shapes: &[DataType]) -> DataType {
+        shapes
+            .iter()
+            .zip(self.block.get_cell_dofs(cell))
+            .fold(DataType::zero(), |value, (&shape, &dof)| {
+                value + shape * self.coefficients[dof]
+            })
+    }
+
+    /// Interpolate the real gradient of the field in a cell from the reference derivatives of the
+    /// shape functions, along with the measure of the map
+    fn interpolate_gradient(
+        &self,
+        cell: usize,
+        derivatives: &[DataType],
+    ) -> Result<(Vec<DataType>, DataType), Error> {
+        let dimension = self.element.get_shape_basis().get_dimension();
+        let mut reference_gradient = vec![DataType::zero(); dimension];
+        for (shape_derivatives, &dof) in derivatives
+            .chunks(dimension)
+            .zip(self.block.get_cell_dofs(cell))
+        {
+            for (g, &d) in reference_gradient.iter_mut().zip(shape_derivatives) {
+                *g = *g + d * self.coefficients[dof];
+            }
+        }
+        let jacobian = compute_jacobian(self.element, self.block, cell, derivatives);
+        physical_gradient(&jacobian, dimension, &reference_gradient)
+            .ok_or(Error::DegenerateCell { cell })
+    }
+}
+
+/// Check that a block of cells is described by an element
+pub(crate) fn check_block<CoordType, DataType, ElementT>(
+    element: &ElementT,
+    block: &CellBlock<CoordType, DataType>,
+) -> Result<(), Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar,
+    ElementT: Element<CoordType, DataType>,
+{
+    let basis = element.get_shape_basis();
+    if basis.get_shape_cardinality() != 1 {
+        return Err(Error::DimensionMismatch {
+            context: "Shape basis should be scalar",
+            expected: 1,
+            actual: basis.get_shape_cardinality(),
+        });
+    }
+    if block.get_dofs_per_cell() != basis.get_number_of_bases() {
+        return Err(Error::SizeMismatch {
+            context: "Dofs per cell do not match the shape basis",
+            expected: basis.get_number_of_bases(),
+            actual: block.get_dofs_per_cell(),
+        });
+    }
+    let embedding = block.get_coordinates_per_cell() / basis.get_number_of_bases();
+    if embedding * basis.get_number_of_bases() != block.get_coordinates_per_cell()
+        || embedding < basis.get_dimension()
+    {
+        return Err(Error::InvalidArgument(
+            "Cell coordinates do not match the nodes of the shape basis",
+        ));
+    }
+    Ok(())
+}
+
+/// Get the dimension of the space the cells of a block are embedded in
+pub(crate) fn get_embedding_dimension<CoordType, DataType, ElementT>(
+    element: &ElementT,
+    block: &CellBlock<CoordType, DataType>,
+) -> usize
+where
+    CoordType: Scalar,
+    DataType: Scalar,
+    ElementT: Element<CoordType, DataType>,
+{
+    block.get_coordinates_per_cell() / element.get_shape_basis().get_number_of_bases()
+}
+
+/// Map the values of the shape functions at a reference point to the real coordinates in a cell
+pub(crate) fn map_to_physical<CoordType, DataType, ElementT>(
+    element: &ElementT,
+    block: &CellBlock<CoordType, DataType>,
+    cell: usize,
+    shapes: &[DataType],
+) -> Vec<DataType>
+where
+    CoordType: Scalar,
+    DataType: Scalar + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let embedding = get_embedding_dimension(element, block);
+    let mut point = vec![DataType::zero(); embedding];
+    for (node, &shape) in block
+        .get_cell_coordinates(cell)
+        .chunks(embedding)
+        .zip(shapes)
+    {
+        for (x, &coordinate) in point.iter_mut().zip(node) {
+            *x = *x + shape * DataType::from(coordinate);
+        }
+    }
+    point
+}
+
+/// Compute the jacobian `dx_i / dξ_j` of the map of a cell in row major ordering from the reference
+/// derivatives of the shape functions
+pub(crate) fn compute_jacobian<CoordType, DataType, ElementT>(
+    element: &ElementT,
+    block: &CellBlock<CoordType, DataType>,
+    cell: usize,
+    derivatives: &[DataType],
+) -> Vec<DataType>
+where
+    CoordType: Scalar,
+    DataType: Scalar + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    jacobian_from_coordinates(
+        block.get_cell_coordinates(cell),
+        get_embedding_dimension(element, block),
+        element.get_shape_basis().get_dimension(),
+        derivatives,
+    )
+}
+
+/// Same as compute_jacobian above from the real coordinates of the nodes of a cell in AOS ordering
+pub(crate) fn jacobian_from_coordinates<CoordType, DataType>(
+    coordinates: &[CoordType],
+    embedding: usize,
+    dimension: usize,
+    derivatives: &[DataType],
+) -> Vec<DataType>
+where
+    CoordType: Scalar,
+    DataType: Scalar + From<CoordType>,
+{
+    let mut jacobian = vec![DataType::zero(); embedding * dimension];
+    for (node, shape_derivatives) in coordinates
+        .chunks(embedding)
+        .zip(derivatives.chunks(dimension))
+    {
+        for (i, &coordinate) in node.iter().enumerate() {
+            for (j, &d) in shape_derivatives.iter().enumerate() {
+                jacobian[i * dimension + j] =
+                    jacobian[i * dimension + j] + DataType::from(coordinate) * d;
+            }
+        }
+    }
+    jacobian
+}
+
+/// Get the error of the first cell of a block with a degenerate map, to report the non finite
+/// values of an assembled system
+pub(crate) fn locate_degenerate_cell<CoordType, DataType, ElementT>(
+    element: &ElementT,
+    block: &CellBlock<CoordType, DataType>,
+) -> Error
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    (0..block.get_number_of_cells())
+        .find(|&cell| compute_shape_gradients(element, block.get_cell_coordinates(cell)).is_none())
+        .map_or(Error::NotFinite("Assembled system is not finite"), |cell| {
+            Error::DegenerateCell { cell }
+        })
+}
+
+/// Compute the real gradients of the shape functions at the integration points of a cell given by
+/// the real coordinates of its nodes
+///
+/// # Returns
+///
+/// * the gradients of all the shape functions in AOS ordering along with the integration weight
+///   scaled by the measure of the map for each integration point, or None if the map is
+///   degenerate
+pub(crate) fn compute_shape_gradients<CoordType, DataType, ElementT>(
+    element: &ElementT,
+    coordinates: &[CoordType],
+) -> Option<Vec<(Vec<DataType>, DataType)>>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let nips = element.get_integrator().get_number_of_points();
+    let mut gradients = vec![DataType::zero(); nips * coordinates.len()];
+    let mut weights = vec![DataType::zero(); nips];
+    if !compute_shape_gradients_into(element, coordinates, &mut gradients, &mut weights) {
+        return None;
+    }
+    Some(
+        gradients
+            .chunks(coordinates.len())
+            .map(|point_gradients| point_gradients.to_vec())
+            .zip(weights)
+            .collect(),
+    )
+}
+
+/// Same as compute_shape_gradients above but writing the gradients into gradients, of length
+/// `number_integration_points * number_coordinates`, and the scaled integration weights into
+/// weights, of length `number_integration_points`
+///
+/// The jacobians and their metrics are held on the stack, so that the computation does not
+/// allocate, which limits the embedding to three dimensions. The values are written converted to
+/// the type of the buffers, so that the complex buffers of a ComplexElement may hold them in their
+/// real parts.
+///
+/// # Returns
+///
+/// * false if the map is degenerate, in which case the buffers hold partial values
+pub(crate) fn compute_shape_gradients_into<CoordType, DataType, ElementT, ValueType>(
+    element: &ElementT,
+    coordinates: &[CoordType],
+    gradients: &mut [ValueType],
+    weights: &mut [ValueType],
+) -> bool
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+    ValueType: From<DataType>,
+{
+    let basis = element.get_shape_basis();
+    let dimension = basis.get_dimension();
+    let nbases = basis.get_number_of_bases();
+    let embedding = coordinates.len() / nbases;
+    for ((derivatives, &weight), (point_gradients, point_weight)) in element
+        .get_shape_derivatives_for_integration()
+        .chunks(nbases * dimension)
+        .zip(element.get_integrator().get_weights())
+        .zip(
+            gradients
+                .chunks_mut(nbases * embedding)
+                .zip(weights.iter_mut()),
+        )
+    {
+        let mut jacobian = [DataType::zero(); 9];
+        for (node, shape_derivatives) in coordinates
+            .chunks(embedding)
+            .zip(derivatives.chunks(dimension))
+        {
+            for (i, &coordinate) in node.iter().enumerate() {
+                for (j, &d) in shape_derivatives.iter().enumerate() {
+                    jacobian[i * dimension + j] =
+                        jacobian[i * dimension + j] + d * coordinate.into();
+                }
+            }
+        }
+        let mut metric = [DataType::zero(); 9];
+        for i in 0..dimension {
+            for j in 0..dimension {
+                metric[i * dimension + j] = (0..embedding).fold(DataType::zero(), |sum, k| {
+                    sum + jacobian[k * dimension + i] * jacobian[k * dimension + j]
+                });
+            }
+        }
+        let Some((inverse, det)) = invert_small(&metric[..dimension * dimension], dimension) else {
+            return false;
+        };
+        for (shape_derivatives, gradient) in derivatives
+            .chunks(dimension)
+            .zip(point_gradients.chunks_mut(embedding))
+        {
+            let mut y = [DataType::zero(); 3];
+            for (i, yi) in y.iter_mut().take(dimension).enumerate() {
+                *yi = (0..dimension).fold(DataType::zero(), |sum, j| {
+                    sum + inverse[i * dimension + j] * shape_derivatives[j]
+                });
+            }
+            for (k, g) in gradient.iter_mut().enumerate() {
+                *g = (0..dimension)
+                    .fold(DataType::zero(), |sum, j| {
+                        sum + jacobian[k * dimension + j] * y[j]
+                    })
+                    .into();
+            }
+        }
+        *point_weight = (weight * det.sqrt()).into();
+    }
+    true
+}
+
+/// Same as compute_shape_gradients_into above for a batch of `LANES` cells in SOA ordering
+///
+/// The jacobians and the gradients are accumulated with loops over the lanes, only the inversion
+/// of the metrics being done one lane at a time.
+///
+/// # Returns
+///
+/// * whether the map of each lane is regular, the values of the degenerate lanes being partial
+pub(crate) fn compute_shape_gradients_batch<const LANES: usize, CoordType, DataType, ElementT>(
+    element: &ElementT,
+    coordinates: &[[CoordType; LANES]],
+    gradients: &mut [[DataType; LANES]],
+    weights: &mut [[DataType; LANES]],
+) -> [bool; LANES]
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let basis = element.get_shape_basis();
+    let dimension = basis.get_dimension();
+    let nbases = basis.get_number_of_bases();
+    let embedding = coordinates.len() / nbases;
+    let mut regular = [true; LANES];
+    for ((derivatives, &weight), (point_gradients, point_weight)) in element
+        .get_shape_derivatives_for_integration()
+        .chunks(nbases * dimension)
+        .zip(element.get_integrator().get_weights())
+        .zip(
+            gradients
+                .chunks_mut(nbases * embedding)
+                .zip(weights.iter_mut()),
+        )
+    {
+        let mut jacobian = [[DataType::zero(); LANES]; 9];
+        for (node, shape_derivatives) in coordinates
+            .chunks(embedding)
+            .zip(derivatives.chunks(dimension))
+        {
+            for (i, coordinate) in node.iter().enumerate() {
+                for (j, &d) in shape_derivatives.iter().enumerate() {
+                    let entry = &mut jacobian[i * dimension + j];
+                    for lane in 0..LANES {
+                        entry[lane] = entry[lane] + d * coordinate[lane].into();
+                    }
+                }
+            }
+        }
+        let mut inverse = [[DataType::zero(); LANES]; 9];
+        for lane in 0..LANES {
+            let mut metric = [DataType::zero(); 9];
+            for i in 0..dimension {
+                for j in 0..dimension {
+                    metric[i * dimension + j] = (0..embedding).fold(DataType::zero(), |sum, k| {
+                        sum + jacobian[k * dimension + i][lane] * jacobian[k * dimension + j][lane]
+                    });
+                }
+            }
+            match invert_small(&metric[..dimension * dimension], dimension) {
+                Some((lane_inverse, det)) => {
+                    for (entry, value) in inverse.iter_mut().zip(lane_inverse) {
+                        entry[lane] = value;
+                    }
+                    point_weight[lane] = weight * det.sqrt();
+                }
+                None => regular[lane] = false,
+            }
+        }
+        for (shape_derivatives, gradient) in derivatives
+            .chunks(dimension)
+            .zip(point_gradients.chunks_mut(embedding))
+        {
+            let mut y = [[DataType::zero(); LANES]; 3];
+            for (i, yi) in y.iter_mut().take(dimension).enumerate() {
+                for (j, &d) in shape_derivatives.iter().enumerate() {
+                    let entry = &inverse[i * dimension + j];
+                    for lane in 0..LANES {
+                        yi[lane] = yi[lane] + entry[lane] * d;
+                    }
+                }
+            }
+            for (k, g) in gradient.iter_mut().enumerate() {
+                *g = [DataType::zero(); LANES];
+                for (j, yj) in y.iter().take(dimension).enumerate() {
+                    let entry = &jacobian[k * dimension + j];
+                    for lane in 0..LANES {
+                        g[lane] = g[lane] + entry[lane] * yj[lane];
+                    }
+                }
+            }
+        }
+    }
+    regular
+}
+
+/// Overwrite with NaN the values of the local matrices of a batch whose map is degenerate
+pub(crate) fn flag_degenerate_lanes<const LANES: usize, DataType: Float>(
+    local: &mut [[DataType; LANES]],
+    regular: [bool; LANES],
+) {
+    for (lane, _) in regular.iter().enumerate().filter(|(_, &r)| !r) {
+        for entry in local.iter_mut() {
+            entry[lane] = DataType::nan();
+        }
+    }
+}
+
+/// Compute the real gradient `J (J^T J)^{-1} g` of reference gradient `g` for a jacobian `J` of
+/// `dimension` columns, along with the measure `sqrt(det(J^T J))` of the map
+///
+/// # Returns
+///
+/// * the gradient and the measure, or None if the jacobian is degenerate
+pub(crate) fn physical_gradient<DataType: Scalar + Float>(
+    jacobian: &[DataType],
+    dimension: usize,
+    reference_gradient: &[DataType],
+) -> Option<(Vec<DataType>, DataType)> {
+    let embedding = jacobian.len() / dimension;
+    let mut metric = vec![DataType::zero(); dimension * dimension];
+    for i in 0..dimension {
+        for j in 0..dimension {
+            metric[i * dimension + j] = (0..embedding).fold(DataType::zero(), |sum, k| {
+                sum + jacobian[k * dimension + i] * jacobian[k * dimension + j]
+            });
+        }
+    }
+    let measure = determinant(&metric, dimension).sqrt();
+    let y = solve_dense(&metric, reference_gradient)?;
+    let gradient = (0..embedding)
+        .map(|k| {
+            (0..dimension).fold(DataType::zero(), |sum, j| {
+                sum + jacobian[k * dimension + j] * y[j]
+            })
+        })
+        .collect();
+    Some((gradient, measure))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FEFunction;
+    use crate::assembly::cell_block::CellBlock;
+    use crate::parallel::Parallelism;
+    use crate::test_utils::{
+        uniform_quadrilaterals, uniform_segments, BilinearQuadrilateralElement,
+        LinearSegmentElement,
+    };
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_evaluation() {
+        let (dofs, coords) = uniform_quadrilaterals(2);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let nodes: Vec<[f64; 2]> = coords.chunks(2).map(|x| [x[0], x[1]]).collect();
+        let mut coefficients = vec![0.0; 9];
+        for (&dof, x) in dofs.iter().zip(&nodes) {
+            coefficients[dof] = 1.0 + 2.0 * x[0] - 3.0 * x[1];
+        }
+        let u = FEFunction::new("u", &element, &block, coefficients).unwrap();
+        assert_eq!(u.get_embedding_dimension(), 2, "Incorrect embedding");
+        let x = u.map_to_physical(3, &[0.0, -1.0]);
+        assert!(
+            (x[0] - 0.75).abs() < TOL && (x[1] - 0.5).abs() < TOL,
+            "Incorrect physical point"
+        );
+        assert!(
+            (u.evaluate(3, &[0.0, -1.0]) - 1.0).abs() < TOL,
+            "Incorrect value"
+        );
+        let gradient = u.evaluate_gradient(1, &[0.3, 0.2]).unwrap();
+        assert!(
+            (gradient[0] - 2.0).abs() < TOL && (gradient[1] + 3.0).abs() < TOL,
+            "Incorrect gradient"
+        );
+        assert!(
+            (u.integrate().unwrap() - 0.5).abs() < TOL,
+            "Incorrect integral"
+        );
+        assert!(
+            FEFunction::new("u", &element, &block, vec![0.0; 4]).is_err(),
+            "Missing coefficients accepted"
+        );
+    }
+
+    #[test]
+    fn test_parallel_integration() {
+        let (dofs, coords) = uniform_quadrilaterals(12);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let coefficients: Vec<f64> =
+            coords
+                .chunks(2)
+                .zip(&dofs)
+                .fold(vec![0.0; 169], |mut c, (x, &dof)| {
+                    c[dof] = x[0] * x[1];
+                    c
+                });
+        let u = FEFunction::new("u", &element, &block, coefficients).unwrap();
+        let mut parallelism = Parallelism::new();
+        parallelism.set_number_of_threads(3);
+        parallelism.set_chunk_size(10);
+        assert!(
+            (u.integrate_parallel(&parallelism).unwrap() - 0.25).abs() < TOL,
+            "Incorrect parallel integral"
+        );
+        parallelism.set_deterministic(true);
+        let reference = u.integrate_parallel(&parallelism).unwrap();
+        parallelism.set_number_of_threads(5);
+        assert_eq!(
+            u.integrate_parallel(&parallelism).unwrap().to_bits(),
+            reference.to_bits(),
+            "Deterministic integral not reproducible"
+        );
+    }
+
+    #[test]
+    fn test_embedded_segments() {
+        let (dofs, coords) = uniform_segments(2);
+        let planar: Vec<f64> = coords.iter().flat_map(|&x| [x, 2.0 * x]).collect();
+        let block = CellBlock::new(2, &dofs, &planar).unwrap();
+        let element = LinearSegmentElement::new();
+        let u = FEFunction::new("u", &element, &block, vec![0.0, 1.0, 2.0]).unwrap();
+        let length = 5.0_f64.sqrt();
+        let weights = u.get_integration_weights(0).unwrap();
+        assert!(
+            (weights.iter().sum::<f64>() - 0.5 * length).abs() < TOL,
+            "Incorrect measure"
+        );
+        let gradient = u.evaluate_gradient(0, &[0.0]).unwrap();
+        let slope = 2.0 / length;
+        assert!(
+            (gradient[0] - slope / length).abs() < TOL
+                && (gradient[1] - 2.0 * slope / length).abs() < TOL,
+            "Incorrect tangential gradient"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::element::element_traits::Element;
+use crate::error::Error;
+use crate::post::estimators::ResidualEstimator;
+use crate::post::function::FEFunction;
+use crate::solver::solver_traits::{LinearSolver, Preconditioner};
+use num::Float;
+
+/// Solve the adjoint problem `A^T z = j` of a goal functional
+///
+/// # Arguments
+///
+/// * `matrix`: the matrix `A` of the primal problem, constrained as it was solved
+/// * `goal`: the discrete goal functional, the values `j_i = J(φ_i)` of the goal at the shape
+///   functions, zero at the constrained dofs
+/// * `solver`: the solver of the adjoint system
+/// * `preconditioner`: the preconditioner of the adjoint system, built on the transpose for non
+///   symmetric matrices
+///
+/// # Returns
+///
+/// * A result either holding the adjoint solution or an error if the sizes do not match or the
+///   solve did not converge
+pub fn solve_adjoint<DataType: Scalar + Float>(
+    matrix: &CsrMatrix<DataType>,
+    goal: &[DataType],
+    solver: &dyn LinearSolver<DataType>,
+    preconditioner: &dyn Preconditioner<DataType>,
+) -> Result<Vec<DataType>, Error> {
+    if goal.len() != matrix.get_number_of_columns() {
+        return Err(Error::SizeMismatch {
+            context: "Goal does not match the size of the matrix",
+            expected: matrix.get_number_of_columns(),
+            actual: goal.len(),
+        });
+    }
+    let transpose = matrix.transpose();
+    let mut adjoint = vec![DataType::zero(); goal.len()];
+    let result = solver.solve(&transpose, preconditioner, goal, &mut adjoint);
+    if !result.is_converged() {
+        return Err(Error::NotConverged(
+            "Linear solve of the adjoint problem did not converge",
+        ));
+    }
+    Ok(adjoint)
+}
+
+/// Compute goal oriented error indicators by weighting the primal residuals with the adjoint ones
+///
+/// # Arguments
+///
+/// * `estimator`: the residual estimator of the diffusion operator
+/// * `primal`: the discrete solution
+/// * `source`: the source term of the primal problem as a function of the real coordinates
+/// * `adjoint`: the discrete adjoint solution, as given by solve_adjoint
+/// * `goal_density`: the density `g` of the goal `J(u) = ∫ g u`, the source of the adjoint problem
+///
+/// # Returns
+///
+/// * A result either holding the indicator of each cell or an error if the primal or the adjoint
+///   indicators could not be computed
+///
+/// # Explanation
+///
+/// The error in the goal is the primal residual tested with the adjoint error, `J(u) - J(u_h) =
+/// r(u_h)(z - z_h)`. Bounding both factors cell by cell gives the indicator `η_K(u_h) η_K(z_h)`,
+/// the product of the residual indicators of the primal and adjoint problems: the cells are
+/// refined where the primal error is large and influences the goal. The sum of the indicators
+/// bounds the error in the goal up to a constant.
+pub fn compute_goal_indicators<CoordType, DataType, ElementT>(
+    estimator: &ResidualEstimator<'_, CoordType, DataType, ElementT>,
+    primal: &FEFunction<'_, CoordType, DataType, ElementT>,
+    source: impl Fn(&[DataType]) -> DataType,
+    adjoint: &FEFunction<'_, CoordType, DataType, ElementT>,
+    goal_density: impl Fn(&[DataType]) -> DataType,
+) -> Result<Vec<DataType>, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    if !std::ptr::eq(primal.get_block(), adjoint.get_block()) {
+        return Err(Error::InvalidArgument(
+            "Primal and adjoint fields are not defined on the same cells",
+        ));
+    }
+    let primal_indicators = estimator.compute_indicators(primal, source)?;
+    let adjoint_indicators = estimator.compute_indicators(adjoint, goal_density)?;
+    Ok(primal_indicators
+        .iter()
+        .zip(&adjoint_indicators)
+        .map(|(&rho, &omega)| rho * omega)
+        .collect())
+}
+
+/// Compute the goal oriented estimate, the sum of the goal oriented indicators
+pub fn compute_goal_estimate<DataType: Scalar + Float>(indicators: &[DataType]) -> DataType {
+    indicators
+        .iter()
+        .fold(DataType::zero(), |sum, &eta| sum + eta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_goal_estimate, compute_goal_indicators, solve_adjoint};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::estimators::ResidualEstimator;
+    use crate::post::facets::ReferenceFacets;
+    use crate::post::function::FEFunction;
+    use crate::solver::cg::ConjugateGradient;
+    use crate::solver::solver_traits::{IdentityPreconditioner, IterationControl};
+    use crate::test_utils::{
+        poisson_solution, poisson_system, uniform_segments, LinearSegmentElement,
+    };
+
+    const TOL: f64 = 1e-10;
+
+    /// Goal oriented estimate of the mean value of the solution of the poisson_system
+    fn estimate(number_of_cells: usize) -> f64 {
+        let (mat, rhs) = poisson_system(number_of_cells);
+        let (dofs, coords) = uniform_segments(number_of_cells);
+        let block = CellBlock::new(2, &dofs, &coords).unwrap();
+        let element = LinearSegmentElement::new();
+        let facets = ReferenceFacets::new(&element, &[-1.0, 1.0], vec![vec![0], vec![1]]).unwrap();
+        let estimator = ResidualEstimator::new(&element, facets);
+        let cg = ConjugateGradient::new(IterationControl::new(1e-14, 0.0, 200));
+        let adjoint = solve_adjoint(&mat, &rhs, &cg, &IdentityPreconditioner).unwrap();
+        for (z, u) in adjoint.iter().zip(poisson_solution(number_of_cells)) {
+            assert!(
+                (z - u).abs() < TOL,
+                "Incorrect adjoint of a symmetric problem"
+            );
+        }
+        let u = FEFunction::new("u", &element, &block, poisson_solution(number_of_cells)).unwrap();
+        let z = FEFunction::new("z", &element, &block, adjoint).unwrap();
+        let indicators = compute_goal_indicators(&estimator, &u, |_| 1.0, &z, |_| 1.0).unwrap();
+        let primal = estimator.compute_indicators(&u, |_| 1.0).unwrap();
+        assert!(
+            indicators
+                .iter()
+                .zip(&primal)
+                .all(|(eta, rho)| (eta - rho * rho).abs() < TOL),
+            "Incorrect goal indicators"
+        );
+        compute_goal_estimate(&indicators)
+    }
+
+    #[test]
+    fn test_goal_indicators() {
+        let coarse = estimate(4);
+        let fine = estimate(8);
+        assert!(
+            coarse > 1.0 / 192.0,
+            "Estimate below the error of the mean value"
+        );
+        let ratio = coarse / fine;
+        assert!(
+            ratio > 3.5 && ratio < 4.5,
+            "Incorrect order of the estimate"
+        );
+    }
+}
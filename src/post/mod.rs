@@ -0,0 +1,41 @@
+/// Module for the finite element fields defined over blocks of cells
+pub mod function;
+
+/// Module for the facets of the reference elements and their neighbours in blocks of cells
+pub mod facets;
+
+/// Module for the output of the fields in the VTK unstructured grid and XDMF formats
+pub mod vtu;
+
+/// Module for the output of transient computations as time series of files
+pub mod series;
+
+/// Module for the recovery of smoothed gradients of the fields
+pub mod recovery;
+
+/// Module for the residual based a posteriori error estimators
+pub mod estimators;
+
+/// Module for the goal oriented error estimation through adjoint problems
+pub mod goal;
+
+/// Module for the boundary integrals of the fields over tagged groups of facets
+pub mod boundary;
+
+/// Module for the probes sampling the fields at points and along lines over time
+pub mod probes;
+
+/// Module for the error norms of the fields against exact solutions
+pub mod norms;
+
+/// Module for the checkpoints saving the state of simulations to restart them
+pub mod checkpoint;
+
+/// Module for the statistics of the fields over tagged regions of cells and groups of facets
+pub mod statistics;
+
+/// Module for the diagnostics of the conservation of quantities over transient computations
+pub mod conservation;
+
+/// Module for the quantities derived from the fields, as strains, stresses and vorticities
+pub mod derived;
@@ -0,0 +1,236 @@
+use crate::algebra::scalar::Scalar;
+use crate::element::element_traits::Element;
+use crate::error::Error;
+use crate::post::function::FEFunction;
+use num::Float;
+
+/// Errors of a field against an exact solution, globally and per cell
+///
+/// # Generics
+///
+/// * DataType: the type of unit the errors are encoded with
+pub struct ErrorNorms<DataType> {
+    cell_l2: Vec<DataType>,
+    cell_h1_seminorm: Vec<DataType>,
+    cell_linf: Vec<DataType>,
+}
+
+impl<DataType: Scalar + Float> ErrorNorms<DataType> {
+    /// Get the L2 norm of the error
+    pub fn get_l2_error(&self) -> DataType {
+        sum_of_squares(&self.cell_l2).sqrt()
+    }
+
+    /// Get the H1 seminorm of the error, the L2 norm of its gradient
+    pub fn get_h1_seminorm_error(&self) -> DataType {
+        sum_of_squares(&self.cell_h1_seminorm).sqrt()
+    }
+
+    /// Get the H1 norm of the error
+    pub fn get_h1_error(&self) -> DataType {
+        (sum_of_squares(&self.cell_l2) + sum_of_squares(&self.cell_h1_seminorm)).sqrt()
+    }
+
+    /// Get the L∞ norm of the error
+    pub fn get_linf_error(&self) -> DataType {
+        self.cell_linf
+            .iter()
+            .fold(DataType::zero(), |max, &e| max.max(e))
+    }
+
+    /// Get the L2 norm of the error in each cell
+    pub fn get_cell_l2_errors(&self) -> &[DataType] {
+        &self.cell_l2
+    }
+
+    /// Get the H1 seminorm of the error in each cell
+    pub fn get_cell_h1_seminorm_errors(&self) -> &[DataType] {
+        &self.cell_h1_seminorm
+    }
+
+    /// Get the L∞ norm of the error in each cell
+    pub fn get_cell_linf_errors(&self) -> &[DataType] {
+        &self.cell_linf
+    }
+}
+
+/// Compute the errors of a field against an exact solution
+///
+/// # Arguments
+///
+/// * `function`: the discrete field
+/// * `exact`: the exact solution as a function of the real coordinates
+/// * `exact_gradient`: the gradient of the exact solution, with the components of the embedding
+///   space
+///
+/// # Returns
+///
+/// * A result either holding the errors or an error if the map of a cell is degenerate or the
+///   exact gradient does not match the embedding dimension
+///
+/// # Explanation
+///
+/// The integrals are computed with the integration rule of the element, which should be accurate
+/// enough for the exact solution. The L∞ error is the largest one sampled at the integration
+/// points and at the nodes of the cells.
+pub fn compute_errors<CoordType, DataType, ElementT>(
+    function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    exact: impl Fn(&[DataType]) -> DataType,
+    exact_gradient: impl Fn(&[DataType]) -> Vec<DataType>,
+) -> Result<ErrorNorms<DataType>, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let block = function.get_block();
+    let embedding = function.get_embedding_dimension();
+    let number_of_cells = block.get_number_of_cells();
+    let mut norms = ErrorNorms {
+        cell_l2: Vec::with_capacity(number_of_cells),
+        cell_h1_seminorm: Vec::with_capacity(number_of_cells),
+        cell_linf: Vec::with_capacity(number_of_cells),
+    };
+    for cell in 0..number_of_cells {
+        let points = function.get_integration_points(cell);
+        let values = function.evaluate_for_integration(cell);
+        let (gradients, weights) = function.evaluate_gradients_for_integration(cell)?;
+        let mut l2 = DataType::zero();
+        let mut h1 = DataType::zero();
+        let mut linf = DataType::zero();
+        for (((point, &value), gradient), &weight) in points
+            .chunks(embedding)
+            .zip(&values)
+            .zip(gradients.chunks(embedding))
+            .zip(&weights)
+        {
+            let error = exact(point) - value;
+            l2 = l2 + weight * error * error;
+            linf = linf.max(error.abs());
+            let exact_gradient = exact_gradient(point);
+            if exact_gradient.len() != embedding {
+                return Err(Error::DimensionMismatch {
+                    context: "Exact gradient does not match the embedding dimension",
+                    expected: embedding,
+                    actual: exact_gradient.len(),
+                });
+            }
+            for (&e, &g) in exact_gradient.iter().zip(gradient) {
+                h1 = h1 + weight * (e - g) * (e - g);
+            }
+        }
+        for (node, value) in block
+            .get_cell_coordinates(cell)
+            .chunks(embedding)
+            .zip(function.get_cell_coefficients(cell))
+        {
+            let point: Vec<DataType> = node.iter().map(|&x| x.into()).collect();
+            linf = linf.max((exact(&point) - value).abs());
+        }
+        norms.cell_l2.push(l2.sqrt());
+        norms.cell_h1_seminorm.push(h1.sqrt());
+        norms.cell_linf.push(linf);
+    }
+    Ok(norms)
+}
+
+/// Compute the observed convergence rate `log(e_1 / e_2) / log(h_1 / h_2)` between two errors
+///
+/// # Arguments
+///
+/// * `coarse_error`: the error on the coarse mesh
+/// * `fine_error`: the error on the fine mesh
+/// * `refinement_ratio`: the ratio `h_1 / h_2` of the mesh sizes
+pub fn compute_convergence_rate<DataType: Float>(
+    coarse_error: DataType,
+    fine_error: DataType,
+    refinement_ratio: DataType,
+) -> DataType {
+    (coarse_error / fine_error).ln() / refinement_ratio.ln()
+}
+
+/// Sum the squares of values
+fn sum_of_squares<DataType: Scalar + Float>(values: &[DataType]) -> DataType {
+    values
+        .iter()
+        .fold(DataType::zero(), |sum, &value| sum + value * value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_convergence_rate, compute_errors, ErrorNorms};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::function::FEFunction;
+    use crate::test_utils::{uniform_quadrilaterals, BilinearQuadrilateralElement};
+    use std::f64::consts::PI;
+
+    const TOL: f64 = 1e-12;
+
+    /// Errors of the nodal interpolant of `sin(πx) sin(πy)` on `n` by `n` quadrilaterals
+    fn interpolation_errors(n: usize) -> ErrorNorms<f64> {
+        let (dofs, coords) = uniform_quadrilaterals(n);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let exact = |x: &[f64]| (PI * x[0]).sin() * (PI * x[1]).sin();
+        let mut values = vec![0.0; (n + 1) * (n + 1)];
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            values[dof] = exact(x);
+        }
+        let u = FEFunction::new("u", &element, &block, values).unwrap();
+        compute_errors(&u, exact, |x| {
+            vec![
+                PI * (PI * x[0]).cos() * (PI * x[1]).sin(),
+                PI * (PI * x[0]).sin() * (PI * x[1]).cos(),
+            ]
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_errors() {
+        let (dofs, coords) = uniform_quadrilaterals(2);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let mut values = vec![0.0; 9];
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            values[dof] = x[0] + 2.0 * x[1];
+        }
+        let u = FEFunction::new("u", &element, &block, values).unwrap();
+        let exact = compute_errors(&u, |x| x[0] + 2.0 * x[1], |_| vec![1.0, 2.0]).unwrap();
+        assert!(
+            exact.get_h1_error() < TOL && exact.get_linf_error() < TOL,
+            "Non zero error of an exact field"
+        );
+        let shifted = compute_errors(&u, |x| x[0] + 2.0 * x[1] + 1.0, |_| vec![1.0, 2.0]).unwrap();
+        assert!(
+            (shifted.get_l2_error() - 1.0).abs() < TOL
+                && (shifted.get_linf_error() - 1.0).abs() < TOL
+                && shifted
+                    .get_cell_l2_errors()
+                    .iter()
+                    .all(|&e| (e - 0.5).abs() < TOL),
+            "Incorrect error of a shifted field"
+        );
+        assert!(
+            compute_errors(&u, |_| 0.0, |_| vec![0.0]).is_err(),
+            "Wrong gradient dimension accepted"
+        );
+    }
+
+    #[test]
+    fn test_convergence_rates() {
+        let coarse = interpolation_errors(8);
+        let fine = interpolation_errors(16);
+        let l2_rate = compute_convergence_rate(coarse.get_l2_error(), fine.get_l2_error(), 2.0);
+        let h1_rate = compute_convergence_rate(
+            coarse.get_h1_seminorm_error(),
+            fine.get_h1_seminorm_error(),
+            2.0,
+        );
+        let linf_rate =
+            compute_convergence_rate(coarse.get_linf_error(), fine.get_linf_error(), 2.0);
+        assert!((l2_rate - 2.0).abs() < 0.1, "Incorrect L2 rate");
+        assert!((h1_rate - 1.0).abs() < 0.1, "Incorrect H1 rate");
+        assert!((linf_rate - 2.0).abs() < 0.2, "Incorrect L∞ rate");
+    }
+}
@@ -0,0 +1,502 @@
+use crate::algebra::dense::solve_dense;
+use crate::algebra::scalar::Scalar;
+use crate::assembly::cell_block::CellBlock;
+use crate::element::element_traits::{Element, ShapeBasis};
+use crate::error::Error;
+use crate::post::facets::{diameter, real_points, ReferenceFacets};
+use crate::post::function::{
+    compute_jacobian, get_embedding_dimension, map_to_physical, FEFunction,
+};
+use num::Float;
+use std::io::Write;
+
+/// Relative tolerance of the location of points in the cells
+const LOCATION_TOLERANCE: f64 = 1e-10;
+
+/// Maximum number of Newton iterations inverting the map of a cell
+const MAXIMUM_ITERATIONS: usize = 50;
+
+/// Table of values recorded over time, one row per record and one column per quantity
+///
+/// # Generics
+///
+/// * DataType: the type of the recorded values
+pub struct ProbeTable<DataType> {
+    columns: Vec<String>,
+    times: Vec<DataType>,
+    rows: Vec<Vec<DataType>>,
+}
+
+impl<DataType: Scalar + Float> ProbeTable<DataType> {
+    /// Constructor of an empty table
+    ///
+    /// # Arguments
+    ///
+    /// * `columns`: the names of the recorded quantities
+    pub fn new(columns: Vec<String>) -> ProbeTable<DataType> {
+        ProbeTable {
+            columns,
+            times: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Get the names of the recorded quantities
+    pub fn get_columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Get the number of records
+    pub fn get_number_of_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Get the times of the records
+    pub fn get_times(&self) -> &[DataType] {
+        &self.times
+    }
+
+    /// Get the values of a record
+    pub fn get_row(&self, row: usize) -> &[DataType] {
+        &self.rows[row]
+    }
+
+    /// Get the history of a quantity, None if the table does not record it
+    pub fn get_column(&self, name: &str) -> Option<Vec<DataType>> {
+        let column = self.columns.iter().position(|c| c == name)?;
+        Some(self.rows.iter().map(|row| row[column]).collect())
+    }
+
+    /// Add a record
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the number of values does not match the columns
+    pub fn add_row(&mut self, time: DataType, values: Vec<DataType>) -> Result<(), Error> {
+        if values.len() != self.columns.len() {
+            return Err(Error::SizeMismatch {
+                context: "Number of values does not match the columns of the table",
+                expected: self.columns.len(),
+                actual: values.len(),
+            });
+        }
+        self.times.push(time);
+        self.rows.push(values);
+        Ok(())
+    }
+
+    /// Write the table in CSV format, the first column being the time
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the output could not be written
+    pub fn write_csv(&self, out: &mut impl Write) -> Result<(), Error> {
+        self.write_rows(out).map_err(|source| Error::Io {
+            context: "Could not write the probe table",
+            source,
+        })
+    }
+
+    /// Write the header and the records
+    fn write_rows(&self, out: &mut impl Write) -> std::io::Result<()> {
+        write!(out, "time")?;
+        for column in &self.columns {
+            write!(out, ",{}", column)?;
+        }
+        writeln!(out)?;
+        for (time, row) in self.times.iter().zip(&self.rows) {
+            write!(out, "{}", time.to_f64().unwrap_or(f64::NAN))?;
+            for value in row {
+                write!(out, ",{}", value.to_f64().unwrap_or(f64::NAN))?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Probes evaluating fields at fixed points of a block of cells
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the fields are encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// The points are located once at construction, by inverting the map of the cells with Newton
+/// iterations, so that recording the fields only costs the interpolation of their values. Each
+/// record appends a row to a table holding one column `<field>_<point>` per probed field and
+/// point, the fields of successive records being the same. Probes along a line sample it with
+/// evenly spaced points, the abscissae giving the distance along the line to plot the profiles.
+pub struct PointProbes<'a, CoordType, DataType, ElementT> {
+    element: &'a ElementT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    points: Vec<Vec<DataType>>,
+    locations: Vec<(usize, Vec<CoordType>)>,
+    table: Option<ProbeTable<DataType>>,
+}
+
+impl<'a, CoordType, DataType, ElementT> PointProbes<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar + Float,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells the fields are defined on
+    /// * `reference_facets`: the facets of the reference element, bounding it
+    /// * `points`: the real coordinates of the probes in AOS ordering
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the probes or an error if the points do not match the embedding
+    ///   dimension or a point is outside of the cells
+    pub fn new(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        reference_facets: &ReferenceFacets<CoordType>,
+        points: &[DataType],
+    ) -> Result<PointProbes<'a, CoordType, DataType, ElementT>, Error> {
+        let embedding = get_embedding_dimension(element, block);
+        if points.is_empty() || !points.len().is_multiple_of(embedding) {
+            return Err(Error::DimensionMismatch {
+                context: "Probe points do not match the embedding dimension",
+                expected: embedding,
+                actual: points.len(),
+            });
+        }
+        let locations = points
+            .chunks(embedding)
+            .map(|point| {
+                locate_point(element, block, reference_facets, point)
+                    .ok_or(Error::InvalidArgument("Probe point outside of the cells"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PointProbes {
+            element,
+            block,
+            points: points.chunks(embedding).map(|p| p.to_vec()).collect(),
+            locations,
+            table: None,
+        })
+    }
+
+    /// Constructor of probes sampling a segment
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells the fields are defined on
+    /// * `reference_facets`: the facets of the reference element, bounding it
+    /// * `start`: the real coordinates of the start of the line
+    /// * `end`: the real coordinates of the end of the line
+    /// * `number_of_points`: the number of samples, both ends included
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the probes or an error if there are less than two samples or a
+    ///   sample is outside of the cells
+    pub fn along_line(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        reference_facets: &ReferenceFacets<CoordType>,
+        start: &[DataType],
+        end: &[DataType],
+        number_of_points: usize,
+    ) -> Result<PointProbes<'a, CoordType, DataType, ElementT>, Error> {
+        if number_of_points < 2 {
+            return Err(Error::InvalidArgument(
+                "A line should be sampled by at least two points",
+            ));
+        }
+        if start.len() != end.len() {
+            return Err(Error::DimensionMismatch {
+                context: "Line ends do not have the same dimension",
+                expected: start.len(),
+                actual: end.len(),
+            });
+        }
+        let intervals: DataType = num::cast(number_of_points - 1).unwrap();
+        let points: Vec<DataType> = (0..number_of_points)
+            .flat_map(|i| {
+                let t = num::cast::<usize, DataType>(i).unwrap() / intervals;
+                start.iter().zip(end).map(move |(&a, &b)| a + t * (b - a))
+            })
+            .collect();
+        PointProbes::new(element, block, reference_facets, &points)
+    }
+
+    /// Get the real coordinates of the probes
+    pub fn get_points(&self) -> &[Vec<DataType>] {
+        &self.points
+    }
+
+    /// Get the cell and the reference coordinates of each probe
+    pub fn get_locations(&self) -> &[(usize, Vec<CoordType>)] {
+        &self.locations
+    }
+
+    /// Get the distance of each probe to the first one along the polyline they form
+    pub fn get_abscissae(&self) -> Vec<DataType> {
+        let mut distance = DataType::zero();
+        let mut abscissae = vec![distance];
+        for pair in self.points.windows(2) {
+            let length = pair[0]
+                .iter()
+                .zip(&pair[1])
+                .fold(DataType::zero(), |sum, (&a, &b)| sum + (b - a) * (b - a))
+                .sqrt();
+            distance = distance + length;
+            abscissae.push(distance);
+        }
+        abscissae
+    }
+
+    /// Evaluate a field at the probes
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the values or an error if the field is not defined on the cells
+    ///   of the probes
+    pub fn sample(
+        &self,
+        function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    ) -> Result<Vec<DataType>, Error> {
+        if !std::ptr::eq(function.get_block(), self.block)
+            || !std::ptr::eq(function.get_element(), self.element)
+        {
+            return Err(Error::InvalidArgument(
+                "Field is not defined on the cells of the probes",
+            ));
+        }
+        Ok(self
+            .locations
+            .iter()
+            .map(|(cell, reference)| function.evaluate(*cell, reference))
+            .collect())
+    }
+
+    /// Record the values of fields at the probes at a time
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if a field is not defined on the cells of the probes or the
+    ///   fields differ from the previous records
+    pub fn record(
+        &mut self,
+        time: DataType,
+        functions: &[&FEFunction<'_, CoordType, DataType, ElementT>],
+    ) -> Result<(), Error> {
+        let columns: Vec<String> = functions
+            .iter()
+            .flat_map(|function| {
+                (0..self.points.len()).map(move |i| format!("{}_{}", function.get_name(), i))
+            })
+            .collect();
+        let mut values = Vec::with_capacity(columns.len());
+        for function in functions {
+            values.extend(self.sample(function)?);
+        }
+        let table = self
+            .table
+            .get_or_insert_with(|| ProbeTable::new(columns.clone()));
+        if table.get_columns() != columns.as_slice() {
+            return Err(Error::InvalidArgument(
+                "Probed fields differ from the previous records",
+            ));
+        }
+        table.add_row(time, values)
+    }
+
+    /// Get the table of the records, None before the first record
+    pub fn get_table(&self) -> Option<&ProbeTable<DataType>> {
+        self.table.as_ref()
+    }
+}
+
+/// Locate a point in a block of cells
+///
+/// # Arguments
+///
+/// * `element`: the element describing the cells
+/// * `block`: the cells
+/// * `reference_facets`: the facets of the reference element, bounding it
+/// * `point`: the real coordinates of the point
+///
+/// # Returns
+///
+/// * the first cell containing the point and the reference coordinates of the point in it, or None
+///   if no cell contains it
+///
+/// # Explanation
+///
+/// The cells whose bounding box holds the point are tried in turn: the map of the cell is inverted
+/// by Gauss-Newton iterations from the center of the reference element, which handles cells
+/// embedded in a space of higher dimension, and the point is found when it is mapped back on
+/// itself from inside the reference element.
+pub fn locate_point<CoordType, DataType, ElementT>(
+    element: &ElementT,
+    block: &CellBlock<CoordType, DataType>,
+    reference_facets: &ReferenceFacets<CoordType>,
+    point: &[DataType],
+) -> Option<(usize, Vec<CoordType>)>
+where
+    CoordType: Scalar + Float,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    let embedding = get_embedding_dimension(element, block);
+    let basis = element.get_shape_basis();
+    let dimension = basis.get_dimension();
+    let nbases = basis.get_number_of_bases();
+    let tolerance: DataType = num::cast(LOCATION_TOLERANCE).unwrap();
+    let count: CoordType = num::cast(nbases).unwrap();
+    let mut center = vec![CoordType::zero(); dimension];
+    for node in 0..nbases {
+        for (c, &x) in center
+            .iter_mut()
+            .zip(reference_facets.get_reference_node(node))
+        {
+            *c = *c + x / count;
+        }
+    }
+    for cell in 0..block.get_number_of_cells() {
+        let nodes = real_points(block, cell, embedding);
+        let size = diameter(&nodes);
+        let outside = (0..embedding).any(|i| {
+            let (low, high) = nodes.iter().fold(
+                (DataType::infinity(), DataType::neg_infinity()),
+                |(low, high), node| (low.min(node[i]), high.max(node[i])),
+            );
+            point[i] < low - tolerance * size || point[i] > high + tolerance * size
+        });
+        if outside {
+            continue;
+        }
+        let mut reference = center.clone();
+        let mut distance = DataType::infinity();
+        for _ in 0..MAXIMUM_ITERATIONS {
+            let shapes = basis.interpolate_basis(&reference);
+            let mapped = map_to_physical(element, block, cell, &shapes);
+            let residual: Vec<DataType> = point.iter().zip(&mapped).map(|(&p, &x)| p - x).collect();
+            let derivatives = basis.interpolate_basis_derivative(&reference);
+            let jacobian = compute_jacobian(element, block, cell, &derivatives);
+            let mut metric = vec![DataType::zero(); dimension * dimension];
+            let mut rhs = vec![DataType::zero(); dimension];
+            for k in 0..embedding {
+                for i in 0..dimension {
+                    rhs[i] = rhs[i] + jacobian[k * dimension + i] * residual[k];
+                    for j in 0..dimension {
+                        metric[i * dimension + j] = metric[i * dimension + j]
+                            + jacobian[k * dimension + i] * jacobian[k * dimension + j];
+                    }
+                }
+            }
+            let step = match solve_dense(&metric, &rhs) {
+                Some(step) => step,
+                None => break,
+            };
+            for (r, &s) in reference.iter_mut().zip(&step) {
+                *r = *r + num::cast(s).unwrap();
+            }
+            let increment = step.iter().fold(DataType::zero(), |m, &s| m.max(s.abs()));
+            if increment <= tolerance {
+                let shapes = basis.interpolate_basis(&reference);
+                let mapped = map_to_physical(element, block, cell, &shapes);
+                distance = point
+                    .iter()
+                    .zip(&mapped)
+                    .fold(DataType::zero(), |m, (&p, &x)| m.max((p - x).abs()));
+                break;
+            }
+        }
+        let inside = reference_facets.contains(
+            &reference
+                .iter()
+                .map(|&x| x.into())
+                .collect::<Vec<DataType>>(),
+            num::cast(1e-8).unwrap(),
+        );
+        if distance <= tolerance * size.max(DataType::one()) && inside {
+            return Some((cell, reference));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{locate_point, PointProbes};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::function::FEFunction;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+
+    const TOL: f64 = 1e-10;
+
+    #[test]
+    fn test_probes() {
+        let n = 4;
+        let (dofs, mut coords) = uniform_quadrilaterals(n);
+        for x in coords.chunks_mut(2) {
+            x[0] += 0.1 * x[1] * x[0];
+        }
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let facets = quadrilateral_facets();
+        let (cell, reference) = locate_point(&element, &block, &facets, &[0.6, 0.3]).unwrap();
+        assert_eq!(cell, n + 2, "Incorrect cell");
+        let u = FEFunction::new("u", &element, &block, {
+            let mut values = vec![0.0; (n + 1) * (n + 1)];
+            for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+                values[dof] = 1.0 + x[0] - 2.0 * x[1];
+            }
+            values
+        })
+        .unwrap();
+        assert!(
+            (u.evaluate(cell, &reference) - 1.0).abs() < TOL,
+            "Incorrect location"
+        );
+        assert!(
+            locate_point(&element, &block, &facets, &[1.2, 0.5]).is_none(),
+            "Point outside of the cells located"
+        );
+        assert!(
+            PointProbes::new(&element, &block, &facets, &[0.5, 1.5]).is_err(),
+            "Probe outside of the cells accepted"
+        );
+        let mut line =
+            PointProbes::along_line(&element, &block, &facets, &[0.0, 0.5], &[1.0, 0.5], 5)
+                .unwrap();
+        let abscissae = line.get_abscissae();
+        assert!((abscissae[4] - 1.0).abs() < TOL, "Incorrect abscissae");
+        let profile = line.sample(&u).unwrap();
+        for (value, x) in profile.iter().zip(&abscissae) {
+            assert!((value - x).abs() < TOL, "Incorrect profile");
+        }
+        line.record(0.0, &[&u]).unwrap();
+        line.record(0.5, &[&u]).unwrap();
+        let table = line.get_table().unwrap();
+        assert_eq!(table.get_number_of_rows(), 2, "Incorrect number of records");
+        assert_eq!(table.get_columns()[4], "u_4", "Incorrect column name");
+        assert!(
+            (table.get_column("u_2").unwrap()[1] - 0.5).abs() < TOL,
+            "Incorrect recorded value"
+        );
+        let mut out = Vec::new();
+        table.write_csv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.starts_with("time,u_0,u_1,u_2,u_3,u_4\n0,") && text.lines().count() == 3,
+            "Incorrect CSV output"
+        );
+    }
+}
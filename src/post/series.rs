@@ -0,0 +1,386 @@
+use crate::algebra::scalar::Scalar;
+use crate::element::element_traits::Element;
+use crate::error::Error;
+use crate::post::vtu::VtuWriter;
+use num::Float;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// When the steps of a transient computation are written
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputPolicy<DataType> {
+    /// Write every given number of steps, starting with the first one
+    EverySteps(usize),
+    /// Write at the first step past each multiple of the interval after the first output
+    EveryInterval(DataType),
+}
+
+/// Manager of the output of a transient computation as a VTK time series
+///
+/// # Generics
+///
+/// * DataType: the type of the times
+///
+/// # Explanation
+///
+/// Each output step is written as a `.vtu` file named `<basename>_<output number>.vtu` in the
+/// output directory, and the collection `<basename>.pvd` indexing the files by their time is
+/// rewritten after each output so that it stays valid if the computation stops. A maximum number
+/// of files can be kept, the oldest ones being removed from the disk and from the collection.
+///
+/// The steps can also be written in the XDMF format, as `<basename>_<output number>.xmf` files
+/// next to the `.vtu` ones, indexed by the temporal collection `<basename>.xmf` which includes the
+/// grids of the step files, each of them holding its time.
+pub struct TimeSeriesWriter<DataType> {
+    directory: PathBuf,
+    basename: String,
+    policy: OutputPolicy<DataType>,
+    maximum_files: Option<usize>,
+    xdmf: bool,
+    entries: Vec<(DataType, String)>,
+    number_of_outputs: usize,
+    next_time: Option<DataType>,
+}
+
+impl<DataType: Scalar + Float> TimeSeriesWriter<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `directory`: the directory the files are written in, which should exist
+    /// * `basename`: the prefix of the names of the files
+    /// * `policy`: when the steps are written
+    pub fn new(
+        directory: impl AsRef<Path>,
+        basename: &str,
+        policy: OutputPolicy<DataType>,
+    ) -> TimeSeriesWriter<DataType> {
+        TimeSeriesWriter {
+            directory: directory.as_ref().to_path_buf(),
+            basename: basename.to_string(),
+            policy,
+            maximum_files: None,
+            xdmf: false,
+            entries: Vec::new(),
+            number_of_outputs: 0,
+            next_time: None,
+        }
+    }
+
+    /// Set the maximum number of step files kept on the disk, all of them by default
+    pub fn set_maximum_files(&mut self, maximum_files: usize) {
+        self.maximum_files = Some(maximum_files.max(1));
+    }
+
+    /// Set whether the steps are also written in the XDMF format along with their temporal
+    /// collection, which is not the case by default
+    pub fn set_xdmf_output(&mut self, xdmf: bool) {
+        self.xdmf = xdmf;
+    }
+
+    /// Whether the steps are also written in the XDMF format
+    pub fn is_xdmf_output(&self) -> bool {
+        self.xdmf
+    }
+
+    /// Get the policy of the output
+    pub fn get_policy(&self) -> &OutputPolicy<DataType> {
+        &self.policy
+    }
+
+    /// Get the times and file names of the steps in the collection
+    pub fn get_entries(&self) -> &[(DataType, String)] {
+        &self.entries
+    }
+
+    /// Get the path of the collection file
+    pub fn get_collection_path(&self) -> PathBuf {
+        self.directory.join(format!("{}.pvd", self.basename))
+    }
+
+    /// Get the path of the XDMF temporal collection file
+    pub fn get_xdmf_collection_path(&self) -> PathBuf {
+        self.directory.join(format!("{}.xmf", self.basename))
+    }
+
+    /// Check whether a step should be written according to the policy
+    ///
+    /// # Arguments
+    ///
+    /// * `step`: the number of the step
+    /// * `time`: the time of the step
+    pub fn is_output_due(&self, step: usize, time: DataType) -> bool {
+        match self.policy {
+            OutputPolicy::EverySteps(steps) => step.is_multiple_of(steps.max(1)),
+            OutputPolicy::EveryInterval(interval) => self
+                .next_time
+                .is_none_or(|next| time >= next - interval * DataType::from(1e-8).unwrap()),
+        }
+    }
+
+    /// Write a step if it is due according to the policy
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding whether the step was written or an error if the files could not
+    ///   be written
+    pub fn write_step<CoordType, ElementT>(
+        &mut self,
+        step: usize,
+        time: DataType,
+        writer: &VtuWriter<'_, CoordType, DataType, ElementT>,
+    ) -> Result<bool, Error>
+    where
+        CoordType: Scalar,
+        DataType: From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        if !self.is_output_due(step, time) {
+            return Ok(false);
+        }
+        self.write(time, writer)?;
+        Ok(true)
+    }
+
+    /// Write a step regardless of the policy and update the collection
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the files could not be written
+    pub fn write<CoordType, ElementT>(
+        &mut self,
+        time: DataType,
+        writer: &VtuWriter<'_, CoordType, DataType, ElementT>,
+    ) -> Result<(), Error>
+    where
+        CoordType: Scalar,
+        DataType: From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        let name = format!("{}_{:06}.vtu", self.basename, self.number_of_outputs);
+        writer.write_file(self.directory.join(&name))?;
+        if self.xdmf {
+            writer.write_xdmf_file(self.directory.join(get_xdmf_name(&name)), Some(time))?;
+        }
+        self.number_of_outputs += 1;
+        self.entries.push((time, name));
+        self.schedule_next(time);
+        if let Some(maximum) = self.maximum_files {
+            while self.entries.len() > maximum {
+                let (_, name) = self.entries.remove(0);
+                let mut names = vec![name.clone()];
+                if self.xdmf {
+                    names.push(get_xdmf_name(&name));
+                }
+                for name in names {
+                    std::fs::remove_file(self.directory.join(name)).map_err(|source| {
+                        Error::Io {
+                            context: "Could not remove an old step file",
+                            source,
+                        }
+                    })?;
+                }
+            }
+        }
+        self.write_collection()?;
+        if self.xdmf {
+            self.write_xdmf_collection()?;
+        }
+        Ok(())
+    }
+
+    /// Schedule the next output of the interval policy after an output at a time
+    fn schedule_next(&mut self, time: DataType) {
+        if let OutputPolicy::EveryInterval(interval) = self.policy {
+            let tolerance: DataType = num::cast(1e-8).unwrap();
+            let mut next = self.next_time.unwrap_or(time);
+            while next <= time + interval * tolerance {
+                next = next + interval;
+            }
+            self.next_time = Some(next);
+        }
+    }
+
+    /// Write the collection file indexing the steps
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the file could not be written
+    pub fn write_collection(&self) -> Result<(), Error> {
+        let file = File::create(self.get_collection_path()).map_err(|source| Error::Io {
+            context: "Could not create the PVD file",
+            source,
+        })?;
+        let mut out = BufWriter::new(file);
+        self.write_xml(&mut out)
+            .and_then(|_| out.flush())
+            .map_err(|source| Error::Io {
+                context: "Could not write the PVD output",
+                source,
+            })
+    }
+
+    /// Write the XDMF temporal collection file including the grids of the steps
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the file could not be written
+    pub fn write_xdmf_collection(&self) -> Result<(), Error> {
+        let file = File::create(self.get_xdmf_collection_path()).map_err(|source| Error::Io {
+            context: "Could not create the XDMF collection file",
+            source,
+        })?;
+        let mut out = BufWriter::new(file);
+        self.write_xdmf_xml(&mut out)
+            .and_then(|_| out.flush())
+            .map_err(|source| Error::Io {
+                context: "Could not write the XDMF collection output",
+                source,
+            })
+    }
+
+    /// Write the XDMF document of the temporal collection
+    fn write_xdmf_xml(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "<?xml version=\"1.0\"?>")?;
+        writeln!(
+            out,
+            "<Xdmf Version=\"3.0\" xmlns:xi=\"http://www.w3.org/2001/XInclude\">"
+        )?;
+        writeln!(out, "  <Domain>")?;
+        writeln!(
+            out,
+            "    <Grid Name=\"{}\" GridType=\"Collection\" CollectionType=\"Temporal\">",
+            self.basename
+        )?;
+        for (_, name) in &self.entries {
+            writeln!(
+                out,
+                "      <xi:include href=\"{}\" xpointer=\"xpointer(//Xdmf/Domain/Grid)\"/>",
+                get_xdmf_name(name)
+            )?;
+        }
+        writeln!(out, "    </Grid>")?;
+        writeln!(out, "  </Domain>")?;
+        writeln!(out, "</Xdmf>")
+    }
+
+    /// Write the XML document of the collection
+    fn write_xml(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "<?xml version=\"1.0\"?>")?;
+        writeln!(
+            out,
+            "<VTKFile type=\"Collection\" version=\"0.1\" byte_order=\"LittleEndian\">"
+        )?;
+        writeln!(out, "  <Collection>")?;
+        for (time, name) in &self.entries {
+            writeln!(
+                out,
+                "    <DataSet timestep=\"{}\" group=\"\" part=\"0\" file=\"{}\"/>",
+                time.to_f64().unwrap_or(f64::NAN),
+                name
+            )?;
+        }
+        writeln!(out, "  </Collection>")?;
+        writeln!(out, "</VTKFile>")
+    }
+}
+
+/// Get the name of the XDMF file of a step from the name of its VTU file
+fn get_xdmf_name(name: &str) -> String {
+    format!("{}.xmf", name.trim_end_matches(".vtu"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutputPolicy, TimeSeriesWriter};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::vtu::{VtkCellType, VtuWriter};
+    use crate::test_utils::{
+        quadrilateral_corners, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+
+    #[test]
+    fn test_interval_policy() {
+        let mut series = TimeSeriesWriter::new(".", "unused", OutputPolicy::EveryInterval(0.25));
+        let mut due = Vec::new();
+        for step in 0..=10 {
+            let time = 0.1 * step as f64;
+            if series.is_output_due(step, time) {
+                due.push(step);
+                series.schedule_next(time);
+            }
+        }
+        assert_eq!(due, vec![0, 3, 5, 8, 10], "Incorrect output steps");
+    }
+
+    #[test]
+    fn test_series() {
+        let directory =
+            std::env::temp_dir().join(format!("rustyfox_series_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let (dofs, coords) = uniform_quadrilaterals(1);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let corners = quadrilateral_corners();
+        let writer = VtuWriter::new(&element, &block, VtkCellType::Quad, &corners).unwrap();
+        let mut series = TimeSeriesWriter::new(&directory, "run", OutputPolicy::EverySteps(2));
+        series.set_maximum_files(2);
+        let written: Vec<bool> = (0..6)
+            .map(|step| series.write_step(step, 0.5 * step as f64, &writer).unwrap())
+            .collect();
+        assert_eq!(
+            written,
+            vec![true, false, true, false, true, false],
+            "Incorrect output steps"
+        );
+        assert!(
+            !directory.join("run_000000.vtu").exists() && directory.join("run_000002.vtu").exists(),
+            "Incorrect rotation of the files"
+        );
+        let collection = std::fs::read_to_string(series.get_collection_path()).unwrap();
+        assert!(
+            collection.contains("timestep=\"1\" group=\"\" part=\"0\" file=\"run_000001.vtu\"")
+                && collection.contains("file=\"run_000002.vtu\"")
+                && !collection.contains("run_000000.vtu"),
+            "Incorrect collection"
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_xdmf_series() {
+        let directory =
+            std::env::temp_dir().join(format!("rustyfox_xdmf_series_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let (dofs, coords) = uniform_quadrilaterals(1);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let corners = quadrilateral_corners();
+        let writer = VtuWriter::new(&element, &block, VtkCellType::Quad, &corners).unwrap();
+        let mut series = TimeSeriesWriter::new(&directory, "run", OutputPolicy::EverySteps(1));
+        series.set_xdmf_output(true);
+        series.set_maximum_files(2);
+        for step in 0..3 {
+            series.write_step(step, 0.5 * step as f64, &writer).unwrap();
+        }
+        assert!(
+            !directory.join("run_000000.xmf").exists() && directory.join("run_000002.xmf").exists(),
+            "Incorrect rotation of the XDMF files"
+        );
+        let step = std::fs::read_to_string(directory.join("run_000001.xmf")).unwrap();
+        assert!(
+            step.contains("<Time Value=\"0.5\"/>"),
+            "Incorrect time of the step"
+        );
+        let collection = std::fs::read_to_string(series.get_xdmf_collection_path()).unwrap();
+        assert!(
+            collection.contains("GridType=\"Collection\" CollectionType=\"Temporal\"")
+                && collection.contains("<xi:include href=\"run_000001.xmf\"")
+                && collection.contains("<xi:include href=\"run_000002.xmf\"")
+                && !collection.contains("run_000000.xmf"),
+            "Incorrect temporal collection"
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+use crate::algebra::scalar::Scalar;
+use crate::assembly::cell_block::CellBlock;
+use crate::element::element_traits::Element;
+use crate::error::Error;
+use crate::post::boundary::{compute_measure, integrate_over_facets, FacetGroup};
+use crate::post::facets::ReferenceFacets;
+use crate::post::function::FEFunction;
+use num::Float;
+
+/// Region of a block of cells tagged by a name
+pub struct CellRegion {
+    name: String,
+    cells: Vec<usize>,
+}
+
+impl CellRegion {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the tag of the region
+    /// * `cells`: the indices of the cells of the region in the block
+    pub fn new(name: &str, cells: Vec<usize>) -> CellRegion {
+        CellRegion {
+            name: name.to_string(),
+            cells,
+        }
+    }
+
+    /// Constructor of the region of all the cells of a block
+    pub fn from_block<CoordType, DataType>(
+        name: &str,
+        block: &CellBlock<CoordType, DataType>,
+    ) -> CellRegion {
+        CellRegion::new(name, (0..block.get_number_of_cells()).collect())
+    }
+
+    /// Constructor of the region of the cells whose first value of a data field equals a tag
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the tag of the region
+    /// * `block`: the cells
+    /// * `field`: the name of the data field holding the tags of the cells
+    /// * `tag`: the value of the tag of the region
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the region or an error if the block has no such field
+    pub fn from_tag<CoordType, DataType: PartialEq>(
+        name: &str,
+        block: &CellBlock<CoordType, DataType>,
+        field: &str,
+        tag: DataType,
+    ) -> Result<CellRegion, Error> {
+        let values = block
+            .get_field(field)
+            .ok_or(Error::InvalidArgument("No such field in the block"))?;
+        let stride = values.len() / block.get_number_of_cells();
+        let cells = values
+            .chunks(stride)
+            .enumerate()
+            .filter(|(_, value)| value[0] == tag)
+            .map(|(cell, _)| cell)
+            .collect();
+        Ok(CellRegion::new(name, cells))
+    }
+
+    /// Get the tag of the region
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the indices of the cells of the region
+    pub fn get_cells(&self) -> &[usize] {
+        &self.cells
+    }
+}
+
+/// Summary of a field over a region or a group of facets
+///
+/// # Generics
+///
+/// * DataType: the type of unit the field is encoded with
+pub struct FieldStatistics<DataType> {
+    name: String,
+    minimum: DataType,
+    maximum: DataType,
+    integral: DataType,
+    measure: DataType,
+}
+
+impl<DataType: Float> FieldStatistics<DataType> {
+    /// Get the tag of the region or group
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the smallest sampled value
+    pub fn get_minimum(&self) -> DataType {
+        self.minimum
+    }
+
+    /// Get the largest sampled value
+    pub fn get_maximum(&self) -> DataType {
+        self.maximum
+    }
+
+    /// Get the integral of the field
+    pub fn get_integral(&self) -> DataType {
+        self.integral
+    }
+
+    /// Get the measure of the region or group
+    pub fn get_measure(&self) -> DataType {
+        self.measure
+    }
+
+    /// Get the mean value of the field, the integral over the measure
+    pub fn get_mean(&self) -> DataType {
+        self.integral / self.measure
+    }
+}
+
+/// Compute the statistics of a field over a region of cells
+///
+/// # Returns
+///
+/// * A result either holding the statistics or an error if the region is empty, a cell is out of
+///   the block or the map of a cell is degenerate
+///
+/// # Explanation
+///
+/// The integrals use the integration rule of the element. The extrema are sampled at the
+/// integration points and at the nodes of the cells, which gives the exact extrema of linear
+/// simplices and multilinear cells.
+pub fn compute_region_statistics<CoordType, DataType, ElementT>(
+    function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    region: &CellRegion,
+) -> Result<FieldStatistics<DataType>, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    if region.get_cells().is_empty() {
+        return Err(Error::InvalidArgument("Cell region is empty"));
+    }
+    let mut statistics = FieldStatistics {
+        name: region.get_name().to_string(),
+        minimum: DataType::infinity(),
+        maximum: DataType::neg_infinity(),
+        integral: DataType::zero(),
+        measure: DataType::zero(),
+    };
+    for &cell in region.get_cells() {
+        if cell >= function.get_block().get_number_of_cells() {
+            return Err(Error::OutOfBounds {
+                context: "Cell out of bounds",
+                index: cell,
+                bound: function.get_block().get_number_of_cells(),
+            });
+        }
+        let weights = function.get_integration_weights(cell)?;
+        let values = function.evaluate_for_integration(cell);
+        for (&value, &weight) in values.iter().zip(&weights) {
+            statistics.integral = statistics.integral + weight * value;
+            statistics.measure = statistics.measure + weight;
+        }
+        for value in values
+            .into_iter()
+            .chain(function.get_cell_coefficients(cell))
+        {
+            statistics.minimum = statistics.minimum.min(value);
+            statistics.maximum = statistics.maximum.max(value);
+        }
+    }
+    Ok(statistics)
+}
+
+/// Compute the statistics of a field over a group of facets
+///
+/// # Returns
+///
+/// * A result either holding the statistics or an error if the group is empty or a facet is
+///   degenerate
+///
+/// # Explanation
+///
+/// The integrals are computed as in integrate_over_facets and the extrema are sampled at the
+/// vertices of the facets.
+pub fn compute_group_statistics<CoordType, DataType, ElementT>(
+    function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    reference_facets: &ReferenceFacets<CoordType>,
+    group: &FacetGroup,
+) -> Result<FieldStatistics<DataType>, Error>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    if group.get_facets().is_empty() {
+        return Err(Error::InvalidArgument("Facet group is empty"));
+    }
+    let mut minimum = DataType::infinity();
+    let mut maximum = DataType::neg_infinity();
+    for &(cell, facet) in group.get_facets() {
+        for &node in reference_facets.get_facet(facet) {
+            let value = function.evaluate(cell, reference_facets.get_reference_node(node));
+            minimum = minimum.min(value);
+            maximum = maximum.max(value);
+        }
+    }
+    Ok(FieldStatistics {
+        name: group.get_name().to_string(),
+        minimum,
+        maximum,
+        integral: integrate_over_facets(function, reference_facets, group, |_, value, _, _, _| {
+            value
+        })?,
+        measure: compute_measure(function, reference_facets, group)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_group_statistics, compute_region_statistics, CellRegion};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::boundary::FacetGroup;
+    use crate::post::function::FEFunction;
+    use crate::test_utils::{
+        quadrilateral_facets, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_statistics() {
+        let (dofs, coords) = uniform_quadrilaterals(2);
+        let tags = [1.0, 2.0, 1.0, 2.0];
+        let mut block = CellBlock::new(4, &dofs, &coords).unwrap();
+        block.add_field("region", &tags).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let mut values = vec![0.0; 9];
+        for (&dof, x) in dofs.iter().zip(coords.chunks(2)) {
+            values[dof] = x[0] + 2.0 * x[1];
+        }
+        let u = FEFunction::new("u", &element, &block, values).unwrap();
+        let all = compute_region_statistics(&u, &CellRegion::from_block("all", &block)).unwrap();
+        assert!(
+            all.get_minimum().abs() < TOL
+                && (all.get_maximum() - 3.0).abs() < TOL
+                && (all.get_measure() - 1.0).abs() < TOL
+                && (all.get_mean() - 1.5).abs() < TOL,
+            "Incorrect statistics over the block"
+        );
+        let left = CellRegion::from_tag("left", &block, "region", 1.0).unwrap();
+        assert_eq!(left.get_cells(), &[0, 2], "Incorrect tagged cells");
+        let left = compute_region_statistics(&u, &left).unwrap();
+        assert!(
+            left.get_name() == "left"
+                && (left.get_maximum() - 2.5).abs() < TOL
+                && (left.get_measure() - 0.5).abs() < TOL
+                && (left.get_integral() - 0.625).abs() < TOL,
+            "Incorrect statistics over the region"
+        );
+        assert!(
+            CellRegion::from_tag("none", &block, "material", 1.0).is_err(),
+            "Missing field accepted"
+        );
+        assert!(
+            compute_region_statistics(&u, &CellRegion::new("empty", vec![])).is_err(),
+            "Empty region accepted"
+        );
+        let facets = quadrilateral_facets();
+        let top = FacetGroup::from_boundary("top", &facets, &block, |x| x[1] > 1.0 - TOL);
+        let top = compute_group_statistics(&u, &facets, &top).unwrap();
+        assert!(
+            (top.get_minimum() - 2.0).abs() < TOL
+                && (top.get_maximum() - 3.0).abs() < TOL
+                && (top.get_measure() - 1.0).abs() < TOL
+                && (top.get_mean() - 2.5).abs() < TOL,
+            "Incorrect statistics over the facets"
+        );
+    }
+}
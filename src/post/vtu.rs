@@ -0,0 +1,919 @@
+use crate::algebra::scalar::Scalar;
+use crate::assembly::cell_block::CellBlock;
+use crate::element::element_traits::{Element, ShapeBasis};
+use crate::error::Error;
+use crate::post::function::{check_block, map_to_physical, FEFunction};
+use crate::timer::{scope, TimerReport};
+use num::Float;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The VTK cell types the output cells can be written as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VtkCellType {
+    Vertex = 1,
+    Line = 3,
+    Triangle = 5,
+    Quad = 9,
+    Tetra = 10,
+    Hexahedron = 12,
+    Wedge = 13,
+    QuadraticEdge = 21,
+    QuadraticTriangle = 22,
+    QuadraticQuad = 23,
+    QuadraticTetra = 24,
+    QuadraticHexahedron = 25,
+}
+
+impl VtkCellType {
+    /// Get the identifier of the cell type in the VTK file format
+    pub fn get_identifier(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Get the name of the topology of the cell type in the XDMF file format, the node orderings
+    /// of both formats being the same
+    pub fn get_xdmf_topology(&self) -> &'static str {
+        match self {
+            VtkCellType::Vertex => "Polyvertex",
+            VtkCellType::Line => "Polyline",
+            VtkCellType::Triangle => "Triangle",
+            VtkCellType::Quad => "Quadrilateral",
+            VtkCellType::Tetra => "Tetrahedron",
+            VtkCellType::Hexahedron => "Hexahedron",
+            VtkCellType::Wedge => "Wedge",
+            VtkCellType::QuadraticEdge => "Edge_3",
+            VtkCellType::QuadraticTriangle => "Triangle_6",
+            VtkCellType::QuadraticQuad => "Quadrilateral_8",
+            VtkCellType::QuadraticTetra => "Tetrahedron_10",
+            VtkCellType::QuadraticHexahedron => "Hexahedron_20",
+        }
+    }
+}
+
+/// Writer of finite element fields in the VTK unstructured grid format (`.vtu`)
+///
+/// # Generics
+///
+/// * CoordType: represents the unit type of the embedding space
+/// * DataType: the type of unit the fields are encoded with
+/// * ElementT: the element describing the cells
+///
+/// # Explanation
+///
+/// Each cell of a block is written as a VTK cell whose nodes are given on the reference element in
+/// the VTK ordering, so that the element conventions need not match the VTK ones. The nodes are
+/// mapped to the real coordinates and the fields evaluated at them, which samples high order
+/// fields at the output nodes. The nodes are not shared between cells: discontinuous fields are
+/// written as is, and viewers merge coincident points when needed. Point data are attached from
+/// `FEFunction`s or given per output node, cell data are given per cell, as error indicators or
+/// material identifiers.
+///
+/// High order cells can also be subdivided into a lattice of linear sub-cells, the fields being
+/// resampled at the nodes of the lattice, so that viewers interpolating linearly between the
+/// output nodes do not show faceted fields. The cell data of a cell are repeated on its sub-cells.
+///
+/// The same output can be written in the XDMF format (`.xmf`) with `write_xdmf`, the heavy data
+/// being given inline as XML data items rather than in an HDF5 file, which suits the moderate
+/// sizes the ASCII VTU output is meant for as well.
+pub struct VtuWriter<'a, CoordType, DataType, ElementT> {
+    element: &'a ElementT,
+    block: &'a CellBlock<'a, CoordType, DataType>,
+    cell_type: VtkCellType,
+    node_shapes: Vec<DataType>,
+    sub_cells: Vec<usize>,
+    sub_cell_nodes: usize,
+    points: Vec<DataType>,
+    point_data: Vec<(String, Vec<DataType>)>,
+    cell_data: Vec<(String, Vec<DataType>)>,
+    report: Option<TimerReport>,
+}
+
+impl<'a, CoordType, DataType, ElementT> VtuWriter<'a, CoordType, DataType, ElementT>
+where
+    CoordType: Scalar,
+    DataType: Scalar + Float + From<CoordType>,
+    ElementT: Element<CoordType, DataType>,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells to write
+    /// * `cell_type`: the VTK type of the output cells
+    /// * `reference_nodes`: the coordinates of the nodes of the output cells on the reference
+    ///   element, in the VTK ordering and AOS
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the writer or an error if the block does not match the element
+    pub fn new(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        cell_type: VtkCellType,
+        reference_nodes: &[CoordType],
+    ) -> Result<VtuWriter<'a, CoordType, DataType, ElementT>, Error> {
+        check_block(element, block)?;
+        let basis = element.get_shape_basis();
+        let dimension = basis.get_dimension();
+        if reference_nodes.is_empty() || !reference_nodes.len().is_multiple_of(dimension) {
+            return Err(Error::InvalidArgument(
+                "Reference nodes do not match the dimension of the element",
+            ));
+        }
+        let node_shapes: Vec<DataType> = reference_nodes
+            .chunks(dimension)
+            .flat_map(|node| basis.interpolate_basis(node))
+            .collect();
+        let nbases = basis.get_number_of_bases();
+        let points = (0..block.get_number_of_cells())
+            .flat_map(|cell| {
+                node_shapes
+                    .chunks(nbases)
+                    .flat_map(move |shapes| map_to_physical(element, block, cell, shapes))
+            })
+            .collect();
+        let nodes = node_shapes.len() / nbases;
+        Ok(VtuWriter {
+            element,
+            block,
+            cell_type,
+            node_shapes,
+            sub_cells: (0..nodes).collect(),
+            sub_cell_nodes: nodes,
+            points,
+            point_data: Vec::new(),
+            cell_data: Vec::new(),
+            report: None,
+        })
+    }
+
+    /// Constructor of a writer subdividing each cell into a lattice of linear sub-cells
+    ///
+    /// # Arguments
+    ///
+    /// * `element`: the element describing the cells
+    /// * `block`: the cells to write
+    /// * `cell_type`: the linear VTK type of the sub-cells, a line, triangle, quad, tetra or
+    ///   hexahedron
+    /// * `corners`: the coordinates of the corners of the reference element in the VTK ordering
+    ///   of the cell type and AOS
+    /// * `subdivisions`: the number of sub-cells along each edge of the cells
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the writer or an error if the cell type is not linear, the
+    ///   corners do not match it or the block does not match the element
+    pub fn subdivided(
+        element: &'a ElementT,
+        block: &'a CellBlock<'a, CoordType, DataType>,
+        cell_type: VtkCellType,
+        corners: &[CoordType],
+        subdivisions: usize,
+    ) -> Result<VtuWriter<'a, CoordType, DataType, ElementT>, Error> {
+        let lattice = Lattice::new(cell_type, subdivisions)?;
+        let dimension = lattice.dimension;
+        if corners.len() != lattice.corners * dimension {
+            return Err(Error::SizeMismatch {
+                context: "Corners do not match the cell type",
+                expected: lattice.corners * dimension,
+                actual: corners.len(),
+            });
+        }
+        let reference_nodes: Vec<CoordType> = lattice
+            .weights
+            .chunks(lattice.corners)
+            .flat_map(|weights| {
+                (0..dimension).map(move |i| {
+                    weights
+                        .iter()
+                        .zip(corners.chunks(dimension))
+                        .fold(CoordType::zero(), |x, (&w, corner)| {
+                            x + from_ratio::<CoordType>(w, lattice.scale) * corner[i]
+                        })
+                })
+            })
+            .collect();
+        let mut writer = VtuWriter::new(element, block, cell_type, &reference_nodes)?;
+        writer.sub_cell_nodes = lattice.corners;
+        writer.sub_cells = lattice.sub_cells;
+        Ok(writer)
+    }
+
+    /// Get the number of output nodes per cell
+    pub fn get_nodes_per_cell(&self) -> usize {
+        self.node_shapes.len() / self.element.get_shape_basis().get_number_of_bases()
+    }
+
+    /// Get the total number of output points
+    pub fn get_number_of_points(&self) -> usize {
+        self.block.get_number_of_cells() * self.get_nodes_per_cell()
+    }
+
+    /// Get the real coordinates of the output points in AOS ordering
+    pub fn get_points(&self) -> &[DataType] {
+        &self.points
+    }
+
+    /// Attach a field as point data, evaluated at the output nodes under the name of the field
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the field is not defined on the cells of the writer
+    pub fn add_function(
+        &mut self,
+        function: &FEFunction<'_, CoordType, DataType, ElementT>,
+    ) -> Result<(), Error> {
+        if !std::ptr::eq(function.get_block(), self.block) {
+            return Err(Error::InvalidArgument(
+                "Field is not defined on the cells of the writer",
+            ));
+        }
+        let nbases = self.element.get_shape_basis().get_number_of_bases();
+        let values = (0..self.block.get_number_of_cells())
+            .flat_map(|cell| {
+                let coefficients = function.get_cell_coefficients(cell);
+                self.node_shapes
+                    .chunks(nbases)
+                    .map(|shapes| {
+                        shapes
+                            .iter()
+                            .zip(&coefficients)
+                            .fold(DataType::zero(), |value, (&s, &u)| value + s * u)
+                    })
+                    .collect::<Vec<DataType>>()
+            })
+            .collect();
+        self.add_point_data(function.get_name(), values)
+    }
+
+    /// Attach point data given at each output node, cell after cell
+    pub fn add_point_data(&mut self, name: &str, values: Vec<DataType>) -> Result<(), Error> {
+        if values.len() != self.get_number_of_points() {
+            return Err(Error::SizeMismatch {
+                context: "Point data size does not match the number of points",
+                expected: self.get_number_of_points(),
+                actual: values.len(),
+            });
+        }
+        self.point_data.push((name.to_string(), values));
+        Ok(())
+    }
+
+    /// Attach cell data given for each cell
+    pub fn add_cell_data(&mut self, name: &str, values: Vec<DataType>) -> Result<(), Error> {
+        if values.len() != self.block.get_number_of_cells() {
+            return Err(Error::SizeMismatch {
+                context: "Cell data size does not match the number of cells",
+                expected: self.block.get_number_of_cells(),
+                actual: values.len(),
+            });
+        }
+        self.cell_data.push((name.to_string(), values));
+        Ok(())
+    }
+
+    /// Record the time spent writing the VTU and XDMF outputs in a timer report, as the
+    /// "output/vtu" and "output/xdmf" phases
+    pub fn set_timer_report(&mut self, report: &TimerReport) {
+        self.report = Some(report.clone());
+    }
+
+    /// Write the cells and their data in ASCII format
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the output could not be written
+    pub fn write(&self, out: &mut impl Write) -> Result<(), Error> {
+        let _timer = scope(&self.report, "output/vtu");
+        self.write_document(out)
+    }
+
+    /// Write the cells and their data in ASCII format to a file
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let _timer = scope(&self.report, "output/vtu");
+        let file = File::create(path).map_err(|source| Error::Io {
+            context: "Could not create the VTU file",
+            source,
+        })?;
+        let mut out = BufWriter::new(file);
+        self.write_document(&mut out)?;
+        out.flush().map_err(|source| Error::Io {
+            context: "Could not write the VTU output",
+            source,
+        })
+    }
+
+    /// Write the cells and their data in the XDMF format, with inline XML data items
+    ///
+    /// # Arguments
+    ///
+    /// * `out`: where to write the document
+    /// * `time`: the time of the grid, when it is a step of a transient computation
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the output could not be written
+    pub fn write_xdmf(&self, out: &mut impl Write, time: Option<DataType>) -> Result<(), Error> {
+        let _timer = scope(&self.report, "output/xdmf");
+        self.write_xdmf_document(out, time)
+    }
+
+    /// Write the cells and their data in the XDMF format to a file
+    pub fn write_xdmf_file(
+        &self,
+        path: impl AsRef<Path>,
+        time: Option<DataType>,
+    ) -> Result<(), Error> {
+        let _timer = scope(&self.report, "output/xdmf");
+        let file = File::create(path).map_err(|source| Error::Io {
+            context: "Could not create the XDMF file",
+            source,
+        })?;
+        let mut out = BufWriter::new(file);
+        self.write_xdmf_document(&mut out, time)?;
+        out.flush().map_err(|source| Error::Io {
+            context: "Could not write the XDMF output",
+            source,
+        })
+    }
+
+    /// Get the total number of output cells, the sub-cells of subdivided cells
+    pub fn get_number_of_output_cells(&self) -> usize {
+        self.block.get_number_of_cells() * self.sub_cells.len() / self.sub_cell_nodes
+    }
+
+    /// Get the coordinates of the output points padded to three components
+    fn get_padded_points(&self) -> impl Iterator<Item = f64> + '_ {
+        let embedding = self.points.len() / self.get_number_of_points();
+        self.points
+            .chunks(embedding)
+            .flat_map(|point| (0..3).map(move |i| point.get(i).map_or(0.0, to_f64)))
+    }
+
+    /// Get the output points of the output cells, cell after cell
+    fn get_connectivity(&self) -> impl Iterator<Item = usize> + '_ {
+        let nodes = self.get_nodes_per_cell();
+        (0..self.block.get_number_of_cells())
+            .flat_map(move |cell| self.sub_cells.iter().map(move |&node| cell * nodes + node))
+    }
+
+    /// Get the values of cell data on the output cells, repeated on the sub-cells
+    fn get_output_cell_values<'b>(&self, values: &'b [DataType]) -> impl Iterator<Item = f64> + 'b {
+        let sub_cells = self.sub_cells.len() / self.sub_cell_nodes;
+        values
+            .iter()
+            .flat_map(move |value| std::iter::repeat_n(to_f64(value), sub_cells))
+    }
+
+    /// Write the VTU document, without timing it
+    fn write_document(&self, out: &mut impl Write) -> Result<(), Error> {
+        self.write_xml(out).map_err(|source| Error::Io {
+            context: "Could not write the VTU output",
+            source,
+        })
+    }
+
+    /// Write the XDMF document, without timing it
+    fn write_xdmf_document(
+        &self,
+        out: &mut impl Write,
+        time: Option<DataType>,
+    ) -> Result<(), Error> {
+        self.write_xdmf_xml(out, time).map_err(|source| Error::Io {
+            context: "Could not write the XDMF output",
+            source,
+        })
+    }
+
+    /// Write the XML document
+    fn write_xml(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let number_of_cells = self.get_number_of_output_cells();
+        writeln!(out, "<?xml version=\"1.0\"?>")?;
+        writeln!(
+            out,
+            "<VTKFile type=\"UnstructuredGrid\" version=\"0.1\" byte_order=\"LittleEndian\">"
+        )?;
+        writeln!(out, "  <UnstructuredGrid>")?;
+        writeln!(
+            out,
+            "    <Piece NumberOfPoints=\"{}\" NumberOfCells=\"{}\">",
+            self.get_number_of_points(),
+            number_of_cells
+        )?;
+        writeln!(out, "      <PointData>")?;
+        for (name, values) in &self.point_data {
+            write_array(out, "Float64", name, 1, values.iter().map(to_f64))?;
+        }
+        writeln!(out, "      </PointData>")?;
+        writeln!(out, "      <CellData>")?;
+        for (name, values) in &self.cell_data {
+            let repeated = self.get_output_cell_values(values);
+            write_array(out, "Float64", name, 1, repeated)?;
+        }
+        writeln!(out, "      </CellData>")?;
+        writeln!(out, "      <Points>")?;
+        write_array(out, "Float64", "Points", 3, self.get_padded_points())?;
+        writeln!(out, "      </Points>")?;
+        writeln!(out, "      <Cells>")?;
+        write_array(out, "Int64", "connectivity", 1, self.get_connectivity())?;
+        write_array(
+            out,
+            "Int64",
+            "offsets",
+            1,
+            (1..=number_of_cells).map(|cell| cell * self.sub_cell_nodes),
+        )?;
+        let identifier = self.cell_type.get_identifier();
+        write_array(
+            out,
+            "UInt8",
+            "types",
+            1,
+            (0..number_of_cells).map(|_| identifier),
+        )?;
+        writeln!(out, "      </Cells>")?;
+        writeln!(out, "    </Piece>")?;
+        writeln!(out, "  </UnstructuredGrid>")?;
+        writeln!(out, "</VTKFile>")
+    }
+
+    /// Write the XDMF document
+    fn write_xdmf_xml(&self, out: &mut impl Write, time: Option<DataType>) -> std::io::Result<()> {
+        let number_of_points = self.get_number_of_points();
+        let number_of_cells = self.get_number_of_output_cells();
+        writeln!(out, "<?xml version=\"1.0\"?>")?;
+        writeln!(out, "<Xdmf Version=\"3.0\">")?;
+        writeln!(out, "  <Domain>")?;
+        writeln!(out, "    <Grid Name=\"mesh\" GridType=\"Uniform\">")?;
+        if let Some(time) = time {
+            writeln!(out, "      <Time Value=\"{}\"/>", to_f64(&time))?;
+        }
+        let nodes_per_element = match self.cell_type {
+            VtkCellType::Vertex | VtkCellType::Line => {
+                format!(" NodesPerElement=\"{}\"", self.sub_cell_nodes)
+            }
+            _ => String::new(),
+        };
+        writeln!(
+            out,
+            "      <Topology TopologyType=\"{}\" NumberOfElements=\"{}\"{}>",
+            self.cell_type.get_xdmf_topology(),
+            number_of_cells,
+            nodes_per_element
+        )?;
+        write_data_item(
+            out,
+            "Int",
+            [number_of_cells, self.sub_cell_nodes],
+            self.get_connectivity(),
+        )?;
+        writeln!(out, "      </Topology>")?;
+        writeln!(out, "      <Geometry GeometryType=\"XYZ\">")?;
+        write_data_item(
+            out,
+            "Float",
+            [number_of_points, 3],
+            self.get_padded_points(),
+        )?;
+        writeln!(out, "      </Geometry>")?;
+        for (name, values) in &self.point_data {
+            write_attribute(
+                out,
+                name,
+                "Node",
+                [number_of_points, 1],
+                values.iter().map(to_f64),
+            )?;
+        }
+        for (name, values) in &self.cell_data {
+            write_attribute(
+                out,
+                name,
+                "Cell",
+                [number_of_cells, 1],
+                self.get_output_cell_values(values),
+            )?;
+        }
+        writeln!(out, "    </Grid>")?;
+        writeln!(out, "  </Domain>")?;
+        writeln!(out, "</Xdmf>")
+    }
+}
+
+/// Lattice subdividing a linear reference cell into sub-cells of the same type
+///
+/// The nodes are given by integer barycentric or multilinear weights of the corners, to be
+/// divided by the scale, and the sub-cells by the indices of their nodes in the VTK ordering.
+struct Lattice {
+    dimension: usize,
+    corners: usize,
+    scale: usize,
+    weights: Vec<usize>,
+    sub_cells: Vec<usize>,
+}
+
+impl Lattice {
+    /// Constructor of the lattice of a cell type with a number of sub-cells along each edge
+    fn new(cell_type: VtkCellType, n: usize) -> Result<Lattice, Error> {
+        if n == 0 {
+            return Err(Error::InvalidArgument(
+                "Number of subdivisions is not positive",
+            ));
+        }
+        let side = n + 1;
+        let mut lattice = match cell_type {
+            VtkCellType::Line => Lattice::empty(1, 2, n),
+            VtkCellType::Quad => Lattice::empty(2, 4, n * n),
+            VtkCellType::Hexahedron => Lattice::empty(3, 8, n * n * n),
+            VtkCellType::Triangle => Lattice::empty(2, 3, n),
+            VtkCellType::Tetra => Lattice::empty(3, 4, n),
+            _ => return Err(Error::InvalidArgument("Cell type is not linear")),
+        };
+        let mut index = vec![usize::MAX; side.pow(lattice.dimension as u32)];
+        let mut number_of_nodes = 0;
+        let position = |i: usize, j: usize, k: usize| (k * side + j) * side + i;
+        let simplex = matches!(cell_type, VtkCellType::Triangle | VtkCellType::Tetra);
+        let depth = if lattice.dimension == 3 { side } else { 1 };
+        let height = if lattice.dimension >= 2 { side } else { 1 };
+        for k in 0..depth {
+            for j in 0..height {
+                for i in 0..side {
+                    if simplex && i + j + k > n {
+                        continue;
+                    }
+                    index[position(i, j, k)] = number_of_nodes;
+                    number_of_nodes += 1;
+                    let weights: Vec<usize> = match cell_type {
+                        VtkCellType::Line => vec![n - i, i],
+                        VtkCellType::Quad => {
+                            vec![(n - i) * (n - j), i * (n - j), i * j, (n - i) * j]
+                        }
+                        VtkCellType::Hexahedron => [n - k, k]
+                            .iter()
+                            .flat_map(|&w| {
+                                [(n - i) * (n - j), i * (n - j), i * j, (n - i) * j].map(|x| x * w)
+                            })
+                            .collect(),
+                        VtkCellType::Triangle => vec![n - i - j, i, j],
+                        _ => vec![n - i - j - k, i, j, k],
+                    };
+                    lattice.weights.extend(weights);
+                }
+            }
+        }
+        let node = |i: usize, j: usize, k: usize| index[position(i, j, k)];
+        for k in 0..depth.max(2) - 1 {
+            for j in 0..height.max(2) - 1 {
+                for i in 0..n {
+                    let cell: Vec<usize> = match cell_type {
+                        VtkCellType::Line => vec![node(i, 0, 0), node(i + 1, 0, 0)],
+                        VtkCellType::Quad | VtkCellType::Hexahedron => {
+                            let layers = if lattice.dimension == 3 { 2 } else { 1 };
+                            (0..layers)
+                                .flat_map(|dk| {
+                                    [(0, 0), (1, 0), (1, 1), (0, 1)]
+                                        .map(|(di, dj)| node(i + di, j + dj, k + dk))
+                                })
+                                .collect()
+                        }
+                        VtkCellType::Triangle => {
+                            let mut cells = Vec::new();
+                            if i + j < n {
+                                cells.extend([node(i, j, 0), node(i + 1, j, 0), node(i, j + 1, 0)]);
+                            }
+                            if i + j + 1 < n {
+                                cells.extend([
+                                    node(i + 1, j, 0),
+                                    node(i + 1, j + 1, 0),
+                                    node(i, j + 1, 0),
+                                ]);
+                            }
+                            cells
+                        }
+                        _ => {
+                            let mut cells = Vec::new();
+                            let level = i + j + k;
+                            if level < n {
+                                cells.extend([
+                                    node(i, j, k),
+                                    node(i + 1, j, k),
+                                    node(i, j + 1, k),
+                                    node(i, j, k + 1),
+                                ]);
+                            }
+                            if level + 1 < n {
+                                let (a, b) = (node(i + 1, j, k), node(i, j + 1, k + 1));
+                                let ring = [
+                                    node(i, j + 1, k),
+                                    node(i, j, k + 1),
+                                    node(i + 1, j, k + 1),
+                                    node(i + 1, j + 1, k),
+                                ];
+                                for r in 0..4 {
+                                    cells.extend([a, b, ring[r], ring[(r + 1) % 4]]);
+                                }
+                            }
+                            if level + 2 < n {
+                                cells.extend([
+                                    node(i + 1, j + 1, k),
+                                    node(i + 1, j, k + 1),
+                                    node(i, j + 1, k + 1),
+                                    node(i + 1, j + 1, k + 1),
+                                ]);
+                            }
+                            cells
+                        }
+                    };
+                    lattice.sub_cells.extend(cell);
+                }
+            }
+        }
+        Ok(lattice)
+    }
+
+    /// Constructor of a lattice without nodes nor sub-cells
+    fn empty(dimension: usize, corners: usize, scale: usize) -> Lattice {
+        Lattice {
+            dimension,
+            corners,
+            scale,
+            weights: Vec::new(),
+            sub_cells: Vec::new(),
+        }
+    }
+}
+
+/// Convert the ratio of two integers to a coordinate
+fn from_ratio<CoordType: Scalar>(numerator: usize, denominator: usize) -> CoordType {
+    let count = |n: usize| (0..n).fold(CoordType::zero(), |x, _| x + CoordType::one());
+    count(numerator) / count(denominator)
+}
+
+/// Convert a value for the output
+fn to_f64<DataType: Float>(value: &DataType) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+/// Write a data array in ASCII format
+fn write_array<ValueT: std::fmt::Display>(
+    out: &mut impl Write,
+    data_type: &str,
+    name: &str,
+    components: usize,
+    values: impl Iterator<Item = ValueT>,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "        <DataArray type=\"{}\" Name=\"{}\" NumberOfComponents=\"{}\" format=\"ascii\">",
+        data_type, name, components
+    )?;
+    write!(out, "         ")?;
+    for value in values {
+        write!(out, " {}", value)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "        </DataArray>")
+}
+
+/// Write a scalar attribute of an XDMF grid
+fn write_attribute(
+    out: &mut impl Write,
+    name: &str,
+    center: &str,
+    dimensions: [usize; 2],
+    values: impl Iterator<Item = f64>,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "      <Attribute Name=\"{}\" AttributeType=\"Scalar\" Center=\"{}\">",
+        name, center
+    )?;
+    write_data_item(out, "Float", dimensions, values)?;
+    writeln!(out, "      </Attribute>")
+}
+
+/// Write an XDMF data item holding its values inline, integers or double precision floats
+fn write_data_item<ValueT: std::fmt::Display>(
+    out: &mut impl Write,
+    number_type: &str,
+    dimensions: [usize; 2],
+    values: impl Iterator<Item = ValueT>,
+) -> std::io::Result<()> {
+    let precision = if number_type == "Float" { 8 } else { 4 };
+    writeln!(
+        out,
+        "        <DataItem Format=\"XML\" NumberType=\"{}\" Precision=\"{}\" Dimensions=\"{} {}\">",
+        number_type, precision, dimensions[0], dimensions[1]
+    )?;
+    write!(out, "         ")?;
+    for value in values {
+        write!(out, " {}", value)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "        </DataItem>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lattice, VtkCellType, VtuWriter};
+    use crate::assembly::cell_block::CellBlock;
+    use crate::post::function::FEFunction;
+    use crate::test_utils::{
+        quadrilateral_corners, uniform_quadrilaterals, BilinearQuadrilateralElement,
+    };
+    use crate::timer::TimerReport;
+
+    #[test]
+    fn test_write() {
+        let (dofs, coords) = uniform_quadrilaterals(2);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let u = FEFunction::new("u", &element, &block, (0..9).map(f64::from).collect()).unwrap();
+        let corners = quadrilateral_corners();
+        let mut writer = VtuWriter::new(&element, &block, VtkCellType::Quad, &corners).unwrap();
+        writer.add_function(&u).unwrap();
+        writer
+            .add_cell_data("indicator", vec![0.0, 1.0, 2.0, 3.0])
+            .unwrap();
+        assert!(
+            writer.add_cell_data("wrong", vec![0.0]).is_err(),
+            "Wrong cell data size accepted"
+        );
+        let mut out = Vec::new();
+        writer.write(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.contains("<Piece NumberOfPoints=\"16\" NumberOfCells=\"4\">"),
+            "Incorrect piece"
+        );
+        assert!(
+            text.contains("Name=\"u\"") && text.contains("Name=\"indicator\""),
+            "Missing data"
+        );
+        assert!(
+            text.contains(" 0 1 4 3 1 2 5 4 3 4 7 6 4 5 8 7\n"),
+            "Incorrect point data"
+        );
+        assert!(text.contains("  4 8 12 16\n"), "Incorrect offsets");
+        assert!(text.contains("  9 9 9 9\n"), "Incorrect cell types");
+        assert!(
+            text.contains("  0 0 0 0.5 0 0 0.5 0.5 0 0 0.5 0 0.5 0 0"),
+            "Incorrect points"
+        );
+    }
+
+    #[test]
+    fn test_write_xdmf() {
+        let (dofs, coords) = uniform_quadrilaterals(2);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let u = FEFunction::new("u", &element, &block, (0..9).map(f64::from).collect()).unwrap();
+        let corners = quadrilateral_corners();
+        let mut writer = VtuWriter::new(&element, &block, VtkCellType::Quad, &corners).unwrap();
+        writer.add_function(&u).unwrap();
+        writer
+            .add_cell_data("indicator", vec![0.0, 1.0, 2.0, 3.0])
+            .unwrap();
+        let mut out = Vec::new();
+        writer.write_xdmf(&mut out, Some(0.5)).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.contains("<Time Value=\"0.5\"/>"),
+            "Incorrect time of the grid"
+        );
+        assert!(
+            text.contains("<Topology TopologyType=\"Quadrilateral\" NumberOfElements=\"4\">")
+                && text.contains("Dimensions=\"4 4\">\n          0 1 2 3 4 5 6 7"),
+            "Incorrect topology"
+        );
+        assert!(
+            text.contains("Dimensions=\"16 3\">\n          0 0 0 0.5 0 0 0.5 0.5 0"),
+            "Incorrect geometry"
+        );
+        assert!(
+            text.contains("<Attribute Name=\"u\" AttributeType=\"Scalar\" Center=\"Node\">")
+                && text.contains(" 0 1 4 3 1 2 5 4 3 4 7 6 4 5 8 7\n"),
+            "Incorrect point data"
+        );
+        assert!(
+            text.contains(
+                "<Attribute Name=\"indicator\" AttributeType=\"Scalar\" Center=\"Cell\">"
+            ) && text.contains("Dimensions=\"4 1\">\n          0 1 2 3\n"),
+            "Incorrect cell data"
+        );
+        let report = TimerReport::new();
+        writer.set_timer_report(&report);
+        let mut out = Vec::new();
+        writer.write_xdmf(&mut out, None).unwrap();
+        assert!(
+            !String::from_utf8(out).unwrap().contains("<Time"),
+            "Time written for a steady grid"
+        );
+        writer.write(&mut Vec::new()).unwrap();
+        assert_eq!(
+            report.get_names(),
+            vec!["output/xdmf", "output/vtu"],
+            "Incorrect timed outputs"
+        );
+    }
+
+    #[test]
+    fn test_lattices() {
+        for (cell_type, n, nodes, cells) in [
+            (VtkCellType::Line, 3, 4, 3),
+            (VtkCellType::Quad, 2, 9, 4),
+            (VtkCellType::Hexahedron, 2, 27, 8),
+            (VtkCellType::Triangle, 3, 10, 9),
+            (VtkCellType::Tetra, 3, 20, 27),
+        ] {
+            let lattice = Lattice::new(cell_type, n).unwrap();
+            assert!(
+                lattice.weights.len() == nodes * lattice.corners
+                    && lattice.sub_cells.len() == cells * lattice.corners,
+                "Incorrect lattice size"
+            );
+            assert!(
+                lattice
+                    .weights
+                    .chunks(lattice.corners)
+                    .all(|w| w.iter().sum::<usize>() == lattice.scale),
+                "Incorrect lattice weights"
+            );
+        }
+        let tetra = Lattice::new(VtkCellType::Tetra, 3).unwrap();
+        let node = |index: usize| -> Vec<f64> {
+            tetra.weights[index * 4 + 1..index * 4 + 4]
+                .iter()
+                .map(|&w| w as f64 / 3.0)
+                .collect()
+        };
+        let volume: f64 = tetra
+            .sub_cells
+            .chunks(4)
+            .map(|cell| {
+                let origin = node(cell[0]);
+                let edges: Vec<Vec<f64>> = cell[1..]
+                    .iter()
+                    .map(|&c| node(c).iter().zip(&origin).map(|(x, o)| x - o).collect())
+                    .collect();
+                let (a, b, c) = (&edges[0], &edges[1], &edges[2]);
+                (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                    + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                    .abs()
+                    / 6.0
+            })
+            .sum();
+        assert!(
+            (volume - 1.0 / 6.0).abs() < 1e-12,
+            "Sub-cells do not tile the tetrahedron"
+        );
+        assert!(
+            Lattice::new(VtkCellType::QuadraticQuad, 2).is_err(),
+            "Quadratic lattice accepted"
+        );
+        assert!(
+            Lattice::new(VtkCellType::Quad, 0).is_err(),
+            "Empty lattice accepted"
+        );
+    }
+
+    #[test]
+    fn test_subdivided() {
+        let (dofs, coords) = uniform_quadrilaterals(1);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let u = FEFunction::new("u", &element, &block, vec![0.0, 0.0, 0.0, 1.0]).unwrap();
+        let corners = quadrilateral_corners();
+        let mut writer =
+            VtuWriter::subdivided(&element, &block, VtkCellType::Quad, &corners, 2).unwrap();
+        writer.add_function(&u).unwrap();
+        writer.add_cell_data("indicator", vec![7.0]).unwrap();
+        assert!(
+            writer.get_number_of_points() == 9 && writer.get_number_of_output_cells() == 4,
+            "Incorrect subdivision"
+        );
+        let mut out = Vec::new();
+        writer.write(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.contains("<Piece NumberOfPoints=\"9\" NumberOfCells=\"4\">"),
+            "Incorrect piece"
+        );
+        assert!(
+            text.contains(" 0 0 0 0 0.25 0.5 0 0.5 1\n"),
+            "Incorrect resampled point data"
+        );
+        assert!(
+            text.contains(" 0 1 4 3 1 2 5 4 3 4 7 6 4 5 8 7\n"),
+            "Incorrect connectivity"
+        );
+        assert!(text.contains("  7 7 7 7\n"), "Incorrect cell data");
+        assert!(
+            VtuWriter::subdivided(&element, &block, VtkCellType::Triangle, &corners, 2).is_err(),
+            "Wrong corners accepted"
+        );
+    }
+}
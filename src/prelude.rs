@@ -0,0 +1,92 @@
+//! Re-exports of the traits and types needed to write and assemble element kernels
+//!
+//! A single `use rustyfox::prelude::*;` brings in the element traits an operator or a residual
+//! kernel is written against, the blocks of cells, constraints and assembler they are assembled
+//! with, the sparse matrices and linear solver traits the assembled systems are solved with, the
+//! fields they are post-processed as, and the error type of the fallible operations. The `Scalar`
+//! trait bounding the generic `CoordType` and `DataType`, and the scalar types of the
+//! automatically differentiated residuals, is re-exported along with `num::Float`.
+
+pub use crate::algebra::csr::{CsrMatrix, Storage};
+pub use crate::algebra::scalar::Scalar;
+pub use crate::assembly::assembler::Assembler;
+pub use crate::assembly::cell_block::CellBlock;
+pub use crate::assembly::constraints::Constraints;
+pub use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+pub use crate::element::operator_trait::Operator;
+pub use crate::element::residual_trait::{AutomaticTangent, ResidualKernel};
+pub use crate::element::workspace::Workspace;
+pub use crate::error::Error;
+pub use crate::geometry::geometry_traits::Geometry;
+pub use crate::nonlinear::nonlinear_traits::{NonlinearControl, NonlinearProblem};
+pub use crate::post::boundary::FacetGroup;
+pub use crate::post::facets::ReferenceFacets;
+pub use crate::post::function::FEFunction;
+pub use crate::solver::registry::{SolverConfiguration, SolverRegistry};
+pub use crate::solver::solver_traits::{
+    IterationControl, LinearMap, LinearSolver, Preconditioner, SolverResult,
+};
+pub use crate::time::time_traits::RateFunction;
+pub use num::Float;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{uniform_quadrilaterals, BilinearQuadrilateralElement};
+    use std::collections::HashMap;
+
+    const TOL: f64 = 1e-12;
+
+    /// Mass operator of axis aligned quadrilaterals written with the prelude only
+    struct AlignedMass<'a, ElementT> {
+        element: &'a ElementT,
+    }
+
+    impl<CoordType, DataType, ElementT> Operator<CoordType, DataType> for AlignedMass<'_, ElementT>
+    where
+        CoordType: Scalar,
+        DataType: Scalar + Float + From<CoordType>,
+        ElementT: Element<CoordType, DataType>,
+    {
+        type ElementT = ElementT;
+
+        fn compute(
+            &self,
+            geometry: &[CoordType],
+            _data: &HashMap<String, &[DataType]>,
+        ) -> Vec<DataType> {
+            let n = self.element.get_shape_basis().get_number_of_bases();
+            let width: DataType = (geometry[2] - geometry[0]).into();
+            let height: DataType = (geometry[7] - geometry[1]).into();
+            let quarter: DataType = num::cast(0.25).unwrap();
+            let measure = quarter * width * height;
+            let mut local = vec![DataType::zero(); n * n];
+            for (shapes, &weight) in self
+                .element
+                .get_shapes_for_integration()
+                .chunks(n)
+                .zip(self.element.get_integrator().get_weights())
+            {
+                for (a, &shape_a) in shapes.iter().enumerate() {
+                    for (b, &shape_b) in shapes.iter().enumerate() {
+                        local[a * n + b] = local[a * n + b] + weight * measure * shape_a * shape_b;
+                    }
+                }
+            }
+            local
+        }
+    }
+
+    #[test]
+    fn test_prelude_kernel() {
+        let (dofs, coords) = uniform_quadrilaterals(3);
+        let block = CellBlock::new(4, &dofs, &coords).unwrap();
+        let element = BilinearQuadrilateralElement::new();
+        let matrix = Assembler::new(16)
+            .assemble(&AlignedMass { element: &element }, &block)
+            .unwrap();
+        let area: f64 = matrix.get_row_sums().iter().sum();
+        assert!((area - 1.0).abs() < TOL, "Incorrect assembled mass");
+        assert_eq!(matrix.get_storage(), Storage::General, "Incorrect storage");
+    }
+}
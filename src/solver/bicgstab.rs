@@ -0,0 +1,262 @@
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::{axpy, dot, norm, xpby};
+use crate::solver::solver_traits::{
+    IterationControl, LinearMap, LinearSolver, Preconditioner, SolverMonitor, SolverResult,
+};
+use num::Float;
+
+/// Stabilized bi-conjugate gradient solver with right preconditioning
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// BiCGStab solves a general non singular `A x = b` with short recurrences: contrary to GMRES its
+/// memory footprint does not grow with the iterations (eight vectors), at the cost of a non
+/// monotone residual. The preconditioner is applied on the right so that the monitored residual
+/// is the true residual.
+pub struct BiCgStab<'a, DataType> {
+    control: IterationControl<DataType>,
+    monitor: SolverMonitor<'a, DataType>,
+}
+
+impl<'a, DataType: Scalar + Float> BiCgStab<'a, DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `control`: the stopping criterion of the iterations
+    pub fn new(control: IterationControl<DataType>) -> BiCgStab<'a, DataType> {
+        BiCgStab {
+            control,
+            monitor: SolverMonitor::new(),
+        }
+    }
+
+    /// Set the monitoring of the iterations
+    pub fn set_monitor(&mut self, monitor: SolverMonitor<'a, DataType>) {
+        self.monitor = monitor;
+    }
+
+    /// Get the monitoring of the iterations
+    pub fn get_monitor(&self) -> &SolverMonitor<'a, DataType> {
+        &self.monitor
+    }
+
+    /// Get the stopping criterion of the iterations
+    pub fn get_control(&self) -> &IterationControl<DataType> {
+        &self.control
+    }
+
+    /// Solve a linear system
+    ///
+    /// # Arguments
+    ///
+    /// * `map`: the system operator `A`
+    /// * `preconditioner`: the preconditioner `M`
+    /// * `rhs`: the right hand side `b`
+    /// * `x`: the initial guess, overwritten by the solution
+    ///
+    /// # Returns
+    ///
+    /// * the convergence information of the solve, not converged on a breakdown of the recurrence
+    pub fn solve<MapT, PreconditionerT>(
+        &self,
+        map: &MapT,
+        preconditioner: &PreconditionerT,
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> SolverResult<DataType>
+    where
+        MapT: LinearMap<DataType> + ?Sized,
+        PreconditionerT: Preconditioner<DataType> + ?Sized,
+    {
+        let n = rhs.len();
+        let zero = DataType::zero();
+        let mut r = vec![zero; n];
+        map.apply(x, &mut r);
+        xpby(rhs, -DataType::one(), &mut r);
+        let initial_residual_norm = norm(&r);
+        let target = self.control.get_target(norm(rhs));
+        let mut history = Vec::new();
+        self.monitor
+            .record(&mut history, 0, initial_residual_norm, map, rhs, x);
+        if initial_residual_norm <= target {
+            return SolverResult::new(
+                true,
+                0,
+                initial_residual_norm,
+                initial_residual_norm,
+                history,
+            );
+        }
+        let shadow = r.clone();
+        let mut p = vec![zero; n];
+        let mut v = vec![zero; n];
+        let mut y = vec![zero; n];
+        let mut z = vec![zero; n];
+        let mut t = vec![zero; n];
+        let mut rho = DataType::one();
+        let mut alpha = DataType::one();
+        let mut omega = DataType::one();
+        let mut residual_norm = initial_residual_norm;
+        for iteration in 1..=self.control.get_maximum_iterations() {
+            let rho_next = dot(&shadow, &r);
+            if rho_next == zero || omega == zero {
+                return SolverResult::new(
+                    false,
+                    iteration,
+                    initial_residual_norm,
+                    residual_norm,
+                    history,
+                );
+            }
+            let beta = (rho_next / rho) * (alpha / omega);
+            axpy(-omega, &v, &mut p);
+            xpby(&r, beta, &mut p);
+            preconditioner.apply(&p, &mut y);
+            map.apply(&y, &mut v);
+            let shadow_v = dot(&shadow, &v);
+            if shadow_v == zero {
+                return SolverResult::new(
+                    false,
+                    iteration,
+                    initial_residual_norm,
+                    residual_norm,
+                    history,
+                );
+            }
+            alpha = rho_next / shadow_v;
+            axpy(alpha, &y, x);
+            axpy(-alpha, &v, &mut r);
+            residual_norm = norm(&r);
+            if residual_norm <= target {
+                self.monitor
+                    .record(&mut history, iteration, residual_norm, map, rhs, x);
+                return SolverResult::new(
+                    true,
+                    iteration,
+                    initial_residual_norm,
+                    residual_norm,
+                    history,
+                );
+            }
+            preconditioner.apply(&r, &mut z);
+            map.apply(&z, &mut t);
+            let tt = dot(&t, &t);
+            omega = if tt == zero { zero } else { dot(&t, &r) / tt };
+            axpy(omega, &z, x);
+            axpy(-omega, &t, &mut r);
+            residual_norm = norm(&r);
+            self.monitor
+                .record(&mut history, iteration, residual_norm, map, rhs, x);
+            if residual_norm <= target {
+                return SolverResult::new(
+                    true,
+                    iteration,
+                    initial_residual_norm,
+                    residual_norm,
+                    history,
+                );
+            }
+            rho = rho_next;
+        }
+        SolverResult::new(
+            false,
+            self.control.get_maximum_iterations(),
+            initial_residual_norm,
+            residual_norm,
+            history,
+        )
+    }
+}
+
+impl<DataType: Scalar + Float> LinearSolver<DataType> for BiCgStab<'_, DataType> {
+    fn solve(
+        &self,
+        map: &dyn LinearMap<DataType>,
+        preconditioner: &dyn Preconditioner<DataType>,
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> SolverResult<DataType> {
+        BiCgStab::solve(self, map, preconditioner, rhs, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BiCgStab;
+    use crate::solver::solver_traits::{IdentityPreconditioner, IterationControl};
+    use crate::test_utils::convection_diffusion_system;
+
+    const TOL: f64 = 1e-8;
+
+    #[test]
+    fn test_solve() {
+        let (mat, rhs, solution) = convection_diffusion_system(20);
+        let bicgstab = BiCgStab::new(IterationControl::new(1e-12, 0.0, 100));
+        let mut x = vec![0.0; rhs.len()];
+        let result = bicgstab.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        assert!(result.is_converged(), "BiCGStab did not converge");
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+
+    #[test]
+    fn test_right_preconditioning() {
+        let (mat, rhs, solution) = convection_diffusion_system(20);
+        let diagonal = mat.get_diagonal();
+        let jacobi = |r: &[f64], z: &mut [f64]| {
+            for ((z, r), d) in z.iter_mut().zip(r.iter()).zip(diagonal.iter()) {
+                *z = r / d;
+            }
+        };
+        let bicgstab = BiCgStab::new(IterationControl::new(1e-12, 0.0, 100));
+        let mut x = vec![0.0; rhs.len()];
+        let result = bicgstab.solve(&mat, &jacobi, &rhs, &mut x);
+        assert!(
+            result.is_converged(),
+            "Preconditioned BiCGStab did not converge"
+        );
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+
+    #[test]
+    fn test_not_converged() {
+        let (mat, rhs, _) = convection_diffusion_system(20);
+        let bicgstab = BiCgStab::new(IterationControl::new(1e-12, 0.0, 2));
+        let mut x = vec![0.0; rhs.len()];
+        let result = bicgstab.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        assert!(!result.is_converged(), "BiCGStab should not have converged");
+        assert_eq!(result.get_iterations(), 2, "Incorrect number of iterations");
+    }
+
+    #[test]
+    fn test_history() {
+        let (mat, rhs, _) = convection_diffusion_system(20);
+        let bicgstab = BiCgStab::new(IterationControl::new(1e-12, 0.0, 100));
+        let mut x = vec![0.0; rhs.len()];
+        let result = bicgstab.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        let history = result.get_history();
+        assert_eq!(
+            history.len(),
+            result.get_iterations() + 1,
+            "Incorrect history length"
+        );
+        assert_eq!(
+            history[0],
+            result.get_initial_residual_norm(),
+            "Incorrect first history entry"
+        );
+        assert_eq!(
+            *history.last().unwrap(),
+            result.get_residual_norm(),
+            "Incorrect last history entry"
+        );
+    }
+}
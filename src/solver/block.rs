@@ -0,0 +1,458 @@
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use crate::solver::solver_traits::{IdentityPreconditioner, LinearMap, Preconditioner};
+use std::ops::Range;
+
+/// Boxed preconditioner of a diagonal block
+type BlockPreconditioner<'a, DataType> = Box<dyn Preconditioner<DataType> + 'a>;
+
+/// Boxed operator of an off-diagonal block
+type BlockMap<'a, DataType> = Box<dyn LinearMap<DataType> + 'a>;
+
+/// Partition of the unknowns of a system into contiguous blocks
+///
+/// # Explanation
+///
+/// Mixed systems are numbered field by field, for instance all the velocity dofs followed by all
+/// the pressure dofs of a Stokes problem. The partition is given by the offsets of the blocks,
+/// `[0, number_of_velocity_dofs, number_of_dofs]` in that example.
+#[derive(Clone, Debug)]
+pub struct BlockStructure {
+    offsets: Vec<usize>,
+}
+
+impl BlockStructure {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `offsets`: the first unknown of each block followed by the total number of unknowns
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the structure or an error if the offsets do not start with 0 or
+    ///   are not increasing
+    pub fn new(offsets: Vec<usize>) -> Result<BlockStructure, Error> {
+        if offsets.len() < 2 || offsets[0] != 0 {
+            return Err(Error::InvalidArgument(
+                "Block offsets should start with 0 and hold at least one block",
+            ));
+        }
+        if offsets.windows(2).any(|bounds| bounds[0] >= bounds[1]) {
+            return Err(Error::InvalidArgument("Block offsets should be increasing"));
+        }
+        Ok(BlockStructure { offsets })
+    }
+
+    /// Get the number of blocks
+    pub fn get_number_of_blocks(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Get the total number of unknowns
+    pub fn get_size(&self) -> usize {
+        *self.offsets.last().unwrap()
+    }
+
+    /// Get the range of unknowns of a block
+    pub fn get_range(&self, block: usize) -> Range<usize> {
+        self.offsets[block]..self.offsets[block + 1]
+    }
+}
+
+/// Block diagonal preconditioner
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The preconditioner `P = diag(P_0, ..., P_n)` applies independent preconditioners to each
+/// block of the residual. For a saddle point system `[A B^T; B 0]`, taking an approximation of `A`
+/// and of the Schur complement `S = B A^{-1} B^T` (the pressure mass matrix scaled by the inverse
+/// viscosity for Stokes) gives a preconditioner whose quality does not depend on the mesh size,
+/// and which stays symmetric when the block preconditioners are. Unset blocks are left
+/// unpreconditioned.
+pub struct BlockDiagonalPreconditioner<'a, DataType> {
+    structure: BlockStructure,
+    diagonal: Vec<BlockPreconditioner<'a, DataType>>,
+}
+
+impl<'a, DataType: Scalar + 'a> BlockDiagonalPreconditioner<'a, DataType> {
+    /// Constructor of an unpreconditioned block structure
+    pub fn new(structure: BlockStructure) -> BlockDiagonalPreconditioner<'a, DataType> {
+        BlockDiagonalPreconditioner {
+            diagonal: identity_blocks(&structure),
+            structure,
+        }
+    }
+
+    /// Set the preconditioner of a diagonal block
+    ///
+    /// # Arguments
+    ///
+    /// * `block`: the index of the block
+    /// * `preconditioner`: the approximate inverse of the diagonal block
+    pub fn set_diagonal_block(
+        &mut self,
+        block: usize,
+        preconditioner: impl Preconditioner<DataType> + 'a,
+    ) -> Result<(), Error> {
+        if block >= self.structure.get_number_of_blocks() {
+            return Err(Error::OutOfBounds {
+                context: "Block index out of bounds",
+                index: block,
+                bound: self.structure.get_number_of_blocks(),
+            });
+        }
+        self.diagonal[block] = Box::new(preconditioner);
+        Ok(())
+    }
+
+    /// Get the block structure
+    pub fn get_structure(&self) -> &BlockStructure {
+        &self.structure
+    }
+}
+
+impl<'a, DataType: Scalar> Preconditioner<DataType> for BlockDiagonalPreconditioner<'a, DataType> {
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        for (block, preconditioner) in self.diagonal.iter().enumerate() {
+            let range = self.structure.get_range(block);
+            preconditioner.apply(&r[range.clone()], &mut z[range]);
+        }
+    }
+}
+
+/// Which triangle of a block triangular preconditioner holds the off-diagonal blocks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Triangle {
+    /// Blocks below the diagonal, applied by forward substitution
+    Lower,
+    /// Blocks above the diagonal, applied by backward substitution
+    Upper,
+}
+
+/// Block triangular preconditioner
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The preconditioner keeps the off-diagonal blocks of one triangle of the system, for instance
+/// `P = [A B^T; 0 -S]` for a saddle point system `[A B^T; B 0]`, and is applied by block
+/// substitution using the preconditioners of the diagonal blocks. With the exact `A` and Schur
+/// complement `S = B A^{-1} B^T`, GMRES converges in two iterations; with spectrally equivalent
+/// approximations the iteration count stays bounded under mesh refinement. The preconditioner
+/// is not symmetric and should be used with GMRES or BiCGStab. Note the sign of the Schur block is
+/// carried by its preconditioner.
+pub struct BlockTriangularPreconditioner<'a, DataType> {
+    structure: BlockStructure,
+    triangle: Triangle,
+    diagonal: Vec<BlockPreconditioner<'a, DataType>>,
+    off_diagonal: Vec<(usize, usize, BlockMap<'a, DataType>)>,
+}
+
+impl<'a, DataType: Scalar + 'a> BlockTriangularPreconditioner<'a, DataType> {
+    /// Constructor of an unpreconditioned block structure without off-diagonal blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `structure`: the block structure of the system
+    /// * `triangle`: the triangle holding the off-diagonal blocks
+    pub fn new(
+        structure: BlockStructure,
+        triangle: Triangle,
+    ) -> BlockTriangularPreconditioner<'a, DataType> {
+        BlockTriangularPreconditioner {
+            diagonal: identity_blocks(&structure),
+            structure,
+            triangle,
+            off_diagonal: Vec::new(),
+        }
+    }
+
+    /// Set the preconditioner of a diagonal block
+    ///
+    /// # Arguments
+    ///
+    /// * `block`: the index of the block
+    /// * `preconditioner`: the approximate inverse of the diagonal block
+    pub fn set_diagonal_block(
+        &mut self,
+        block: usize,
+        preconditioner: impl Preconditioner<DataType> + 'a,
+    ) -> Result<(), Error> {
+        if block >= self.structure.get_number_of_blocks() {
+            return Err(Error::OutOfBounds {
+                context: "Block index out of bounds",
+                index: block,
+                bound: self.structure.get_number_of_blocks(),
+            });
+        }
+        self.diagonal[block] = Box::new(preconditioner);
+        Ok(())
+    }
+
+    /// Add an off-diagonal block
+    ///
+    /// # Arguments
+    ///
+    /// * `row_block`: the block of rows
+    /// * `column_block`: the block of columns
+    /// * `map`: the operator of the block, typically extracted by `CsrMatrix::get_block`
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the block is not in the triangle or has the wrong size
+    pub fn add_off_diagonal_block(
+        &mut self,
+        row_block: usize,
+        column_block: usize,
+        map: impl LinearMap<DataType> + 'a,
+    ) -> Result<(), Error> {
+        let number_of_blocks = self.structure.get_number_of_blocks();
+        if row_block.max(column_block) >= number_of_blocks {
+            return Err(Error::OutOfBounds {
+                context: "Block index out of bounds",
+                index: row_block.max(column_block),
+                bound: number_of_blocks,
+            });
+        }
+        let in_triangle = match self.triangle {
+            Triangle::Lower => row_block > column_block,
+            Triangle::Upper => row_block < column_block,
+        };
+        if !in_triangle {
+            return Err(Error::InvalidArgument(
+                "Off-diagonal block outside of the triangle",
+            ));
+        }
+        if map.get_number_of_rows() != self.structure.get_range(row_block).len() {
+            return Err(Error::SizeMismatch {
+                context: "Off-diagonal block rows do not match the block structure",
+                expected: self.structure.get_range(row_block).len(),
+                actual: map.get_number_of_rows(),
+            });
+        }
+        if map.get_number_of_columns() != self.structure.get_range(column_block).len() {
+            return Err(Error::SizeMismatch {
+                context: "Off-diagonal block columns do not match the block structure",
+                expected: self.structure.get_range(column_block).len(),
+                actual: map.get_number_of_columns(),
+            });
+        }
+        self.off_diagonal
+            .push((row_block, column_block, Box::new(map)));
+        Ok(())
+    }
+
+    /// Get the block structure
+    pub fn get_structure(&self) -> &BlockStructure {
+        &self.structure
+    }
+
+    /// Get the triangle holding the off-diagonal blocks
+    pub fn get_triangle(&self) -> Triangle {
+        self.triangle
+    }
+}
+
+impl<'a, DataType: Scalar> Preconditioner<DataType>
+    for BlockTriangularPreconditioner<'a, DataType>
+{
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        let number_of_blocks = self.structure.get_number_of_blocks();
+        let order: Vec<usize> = match self.triangle {
+            Triangle::Lower => (0..number_of_blocks).collect(),
+            Triangle::Upper => (0..number_of_blocks).rev().collect(),
+        };
+        for block in order {
+            let range = self.structure.get_range(block);
+            let mut residual = r[range.clone()].to_vec();
+            for (_, column_block, map) in self
+                .off_diagonal
+                .iter()
+                .filter(|(row_block, _, _)| *row_block == block)
+            {
+                let mut product = vec![DataType::zero(); range.len()];
+                map.apply(&z[self.structure.get_range(*column_block)], &mut product);
+                residual
+                    .iter_mut()
+                    .zip(product.iter())
+                    .for_each(|(r, &p)| *r = *r - p);
+            }
+            self.diagonal[block].apply(&residual, &mut z[range]);
+        }
+    }
+}
+
+/// Identity preconditioners for every block of a structure
+fn identity_blocks<'a, DataType: Scalar + 'a>(
+    structure: &BlockStructure,
+) -> Vec<BlockPreconditioner<'a, DataType>> {
+    (0..structure.get_number_of_blocks())
+        .map(|_| Box::new(IdentityPreconditioner) as BlockPreconditioner<'a, DataType>)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BlockDiagonalPreconditioner, BlockStructure, BlockTriangularPreconditioner, Triangle,
+    };
+    use crate::algebra::csr::CsrMatrix;
+    use crate::solver::direct::SparseLu;
+    use crate::solver::gmres::Gmres;
+    use crate::solver::ilu::IncompleteLu;
+    use crate::solver::solver_traits::{IterationControl, Preconditioner};
+
+    const TOL: f64 = 1e-8;
+
+    /// Saddle point system `[A B^T; B 0]` with a tridiagonal `A` of size 8 and a `B` of rank 3,
+    /// and its exact Schur complement `B A^{-1} B^T`
+    fn saddle_point_system() -> (CsrMatrix<f64>, CsrMatrix<f64>) {
+        let mut triplets = Vec::new();
+        for i in 0..8 {
+            triplets.push((i, i, 2.0));
+            if i + 1 < 8 {
+                triplets.push((i, i + 1, -1.0));
+                triplets.push((i + 1, i, -1.0));
+            }
+        }
+        for p in 0..3 {
+            for (u, value) in [(2 * p, 1.0), (2 * p + 1, -1.0)] {
+                triplets.push((8 + p, u, value));
+                triplets.push((u, 8 + p, value));
+            }
+        }
+        let system = CsrMatrix::from_triplets(11, 11, &triplets).unwrap();
+        let a = system.get_block(0..8, 0..8).unwrap();
+        let b = system.get_block(8..11, 0..8).unwrap();
+        let bt = system.get_block(0..8, 8..11).unwrap();
+        let factor = SparseLu::new(&a).unwrap();
+        let mut schur = Vec::new();
+        for j in 0..3 {
+            let mut unit = vec![0.0; 3];
+            unit[j] = 1.0;
+            let column = b.apply(&factor.solve(&bt.apply(&unit)));
+            for (i, &value) in column.iter().enumerate() {
+                schur.push((i, j, value));
+            }
+        }
+        (system, CsrMatrix::from_triplets(3, 3, &schur).unwrap())
+    }
+
+    #[test]
+    fn test_structure() {
+        let structure = BlockStructure::new(vec![0, 8, 11]).unwrap();
+        assert_eq!(
+            structure.get_number_of_blocks(),
+            2,
+            "Incorrect number of blocks"
+        );
+        assert_eq!(structure.get_size(), 11, "Incorrect size");
+        assert_eq!(structure.get_range(1), 8..11, "Incorrect block range");
+        assert!(
+            BlockStructure::new(vec![1, 8]).is_err(),
+            "Offsets not starting at 0"
+        );
+        assert!(
+            BlockStructure::new(vec![0, 8, 8]).is_err(),
+            "Empty block accepted"
+        );
+    }
+
+    #[test]
+    fn test_block_diagonal() {
+        let (system, schur) = saddle_point_system();
+        assert!(
+            IncompleteLu::ilu0(&system).is_err(),
+            "ILU(0) should break down on the zero pressure block"
+        );
+        let a = system.get_block(0..8, 0..8).unwrap();
+        let mut preconditioner =
+            BlockDiagonalPreconditioner::new(BlockStructure::new(vec![0, 8, 11]).unwrap());
+        preconditioner
+            .set_diagonal_block(0, SparseLu::new(&a).unwrap())
+            .unwrap();
+        preconditioner
+            .set_diagonal_block(1, SparseLu::new(&schur).unwrap())
+            .unwrap();
+        assert!(
+            preconditioner
+                .set_diagonal_block(2, SparseLu::new(&schur).unwrap())
+                .is_err(),
+            "Out of bounds block accepted"
+        );
+        let solution: Vec<f64> = (0..11).map(|i| 1.0 + 0.1 * i as f64).collect();
+        let rhs = system.apply(&solution);
+        let gmres = Gmres::new(IterationControl::new(1e-12, 0.0, 50), 50);
+        let mut x = vec![0.0; 11];
+        let result = gmres.solve(&system, &preconditioner, &rhs, &mut x);
+        assert!(
+            result.is_converged(),
+            "Block diagonal GMRES did not converge"
+        );
+        assert!(
+            result.get_iterations() <= 3,
+            "Exact block diagonal preconditioning takes at most three iterations"
+        );
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+
+    #[test]
+    fn test_block_triangular() {
+        let (system, schur) = saddle_point_system();
+        let a = system.get_block(0..8, 0..8).unwrap();
+        let bt = system.get_block(0..8, 8..11).unwrap();
+        let schur_factor = SparseLu::new(&schur).unwrap();
+        let mut preconditioner = BlockTriangularPreconditioner::new(
+            BlockStructure::new(vec![0, 8, 11]).unwrap(),
+            Triangle::Upper,
+        );
+        preconditioner
+            .set_diagonal_block(0, SparseLu::new(&a).unwrap())
+            .unwrap();
+        preconditioner
+            .set_diagonal_block(1, |r: &[f64], z: &mut [f64]| {
+                schur_factor.apply(r, z);
+                z.iter_mut().for_each(|v| *v = -*v);
+            })
+            .unwrap();
+        assert!(
+            preconditioner
+                .add_off_diagonal_block(1, 0, bt.clone())
+                .is_err(),
+            "Block outside of the triangle accepted"
+        );
+        assert!(
+            preconditioner
+                .add_off_diagonal_block(0, 1, a.clone())
+                .is_err(),
+            "Block of the wrong size accepted"
+        );
+        preconditioner.add_off_diagonal_block(0, 1, bt).unwrap();
+        let solution: Vec<f64> = (0..11).map(|i| 1.0 - 0.2 * i as f64).collect();
+        let rhs = system.apply(&solution);
+        let gmres = Gmres::new(IterationControl::new(1e-12, 0.0, 50), 50);
+        let mut x = vec![0.0; 11];
+        let result = gmres.solve(&system, &preconditioner, &rhs, &mut x);
+        assert!(
+            result.is_converged(),
+            "Block triangular GMRES did not converge"
+        );
+        assert!(
+            result.get_iterations() <= 2,
+            "Exact block triangular preconditioning takes at most two iterations"
+        );
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+}
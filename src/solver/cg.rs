@@ -0,0 +1,247 @@
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::{axpy, dot, norm, xpby};
+use crate::solver::solver_traits::{
+    IterationControl, LinearMap, LinearSolver, Preconditioner, SolverMonitor, SolverResult,
+};
+use num::Float;
+
+/// Preconditioned conjugate gradient solver
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The conjugate gradient method solves `A x = b` for symmetric positive definite `A` by
+/// minimizing the energy norm of the error over growing Krylov spaces. The preconditioner should
+/// also be symmetric positive definite.
+pub struct ConjugateGradient<'a, DataType> {
+    control: IterationControl<DataType>,
+    monitor: SolverMonitor<'a, DataType>,
+}
+
+impl<'a, DataType: Scalar + Float> ConjugateGradient<'a, DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `control`: the stopping criterion of the iterations
+    pub fn new(control: IterationControl<DataType>) -> ConjugateGradient<'a, DataType> {
+        ConjugateGradient {
+            control,
+            monitor: SolverMonitor::new(),
+        }
+    }
+
+    /// Set the monitoring of the iterations
+    pub fn set_monitor(&mut self, monitor: SolverMonitor<'a, DataType>) {
+        self.monitor = monitor;
+    }
+
+    /// Get the monitoring of the iterations
+    pub fn get_monitor(&self) -> &SolverMonitor<'a, DataType> {
+        &self.monitor
+    }
+
+    /// Get the stopping criterion of the iterations
+    pub fn get_control(&self) -> &IterationControl<DataType> {
+        &self.control
+    }
+
+    /// Solve a linear system
+    ///
+    /// # Arguments
+    ///
+    /// * `map`: the system operator `A`
+    /// * `preconditioner`: the preconditioner `M`
+    /// * `rhs`: the right hand side `b`
+    /// * `x`: the initial guess, overwritten by the solution
+    ///
+    /// # Returns
+    ///
+    /// * the convergence information of the solve
+    pub fn solve<MapT, PreconditionerT>(
+        &self,
+        map: &MapT,
+        preconditioner: &PreconditionerT,
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> SolverResult<DataType>
+    where
+        MapT: LinearMap<DataType> + ?Sized,
+        PreconditionerT: Preconditioner<DataType> + ?Sized,
+    {
+        let n = rhs.len();
+        let mut r = vec![DataType::zero(); n];
+        map.apply(x, &mut r);
+        xpby(rhs, -DataType::one(), &mut r);
+        let initial_residual_norm = norm(&r);
+        let target = self.control.get_target(norm(rhs));
+        let mut history = Vec::new();
+        self.monitor
+            .record(&mut history, 0, initial_residual_norm, map, rhs, x);
+        if initial_residual_norm <= target {
+            return SolverResult::new(
+                true,
+                0,
+                initial_residual_norm,
+                initial_residual_norm,
+                history,
+            );
+        }
+        let mut z = vec![DataType::zero(); n];
+        preconditioner.apply(&r, &mut z);
+        let mut p = z.clone();
+        let mut q = vec![DataType::zero(); n];
+        let mut rz = dot(&r, &z);
+        let mut residual_norm = initial_residual_norm;
+        for iteration in 1..=self.control.get_maximum_iterations() {
+            map.apply(&p, &mut q);
+            let pq = dot(&p, &q);
+            if pq <= DataType::zero() {
+                return SolverResult::new(
+                    false,
+                    iteration,
+                    initial_residual_norm,
+                    residual_norm,
+                    history,
+                );
+            }
+            let alpha = rz / pq;
+            axpy(alpha, &p, x);
+            axpy(-alpha, &q, &mut r);
+            residual_norm = norm(&r);
+            self.monitor
+                .record(&mut history, iteration, residual_norm, map, rhs, x);
+            if residual_norm <= target {
+                return SolverResult::new(
+                    true,
+                    iteration,
+                    initial_residual_norm,
+                    residual_norm,
+                    history,
+                );
+            }
+            preconditioner.apply(&r, &mut z);
+            let rz_next = dot(&r, &z);
+            xpby(&z, rz_next / rz, &mut p);
+            rz = rz_next;
+        }
+        SolverResult::new(
+            false,
+            self.control.get_maximum_iterations(),
+            initial_residual_norm,
+            residual_norm,
+            history,
+        )
+    }
+}
+
+impl<DataType: Scalar + Float> LinearSolver<DataType> for ConjugateGradient<'_, DataType> {
+    fn solve(
+        &self,
+        map: &dyn LinearMap<DataType>,
+        preconditioner: &dyn Preconditioner<DataType>,
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> SolverResult<DataType> {
+        ConjugateGradient::solve(self, map, preconditioner, rhs, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConjugateGradient;
+    use crate::solver::solver_traits::{IdentityPreconditioner, IterationControl, SolverMonitor};
+    use crate::test_utils::{poisson_solution, poisson_system};
+    use std::cell::RefCell;
+
+    const TOL: f64 = 1e-8;
+
+    #[test]
+    fn test_poisson() {
+        let (mat, rhs) = poisson_system(16);
+        let cg = ConjugateGradient::new(IterationControl::new(1e-12, 0.0, 100));
+        let mut x = vec![0.0; rhs.len()];
+        let result = cg.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        assert!(result.is_converged(), "CG did not converge");
+        assert!(result.get_iterations() <= 16, "CG took too many iterations");
+        for (v, e) in x.iter().zip(poisson_solution(16).iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+
+    #[test]
+    fn test_preconditioner_hook() {
+        let (mat, rhs) = poisson_system(16);
+        let diagonal = mat.get_diagonal();
+        let jacobi = |r: &[f64], z: &mut [f64]| {
+            for ((z, r), d) in z.iter_mut().zip(r.iter()).zip(diagonal.iter()) {
+                *z = r / d;
+            }
+        };
+        let cg = ConjugateGradient::new(IterationControl::new(1e-12, 0.0, 100));
+        let mut x = vec![0.0; rhs.len()];
+        let result = cg.solve(&mat, &jacobi, &rhs, &mut x);
+        assert!(result.is_converged(), "Preconditioned CG did not converge");
+        for (v, e) in x.iter().zip(poisson_solution(16).iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+
+    #[test]
+    fn test_not_converged() {
+        let (mat, rhs) = poisson_system(16);
+        let cg = ConjugateGradient::new(IterationControl::new(1e-12, 0.0, 3));
+        let mut x = vec![0.0; rhs.len()];
+        let result = cg.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        assert!(!result.is_converged(), "CG should not have converged");
+        assert_eq!(result.get_iterations(), 3, "Incorrect number of iterations");
+        assert!(
+            result.get_residual_norm() > 1e-12 * result.get_initial_residual_norm(),
+            "Reported residual meets the tolerance"
+        );
+    }
+
+    #[test]
+    fn test_monitor() {
+        let (mat, rhs) = poisson_system(16);
+        let calls = RefCell::new(Vec::new());
+        let mut monitor = SolverMonitor::new();
+        monitor.set_true_residual(true);
+        monitor.set_iteration_callback(|iteration, residual_norm, true_residual_norm| {
+            calls
+                .borrow_mut()
+                .push((iteration, residual_norm, true_residual_norm))
+        });
+        let mut cg = ConjugateGradient::new(IterationControl::new(1e-12, 0.0, 100));
+        cg.set_monitor(monitor);
+        let mut x = vec![0.0; rhs.len()];
+        let result = cg.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        drop(cg);
+        let calls = calls.into_inner();
+        assert_eq!(
+            result.get_history().len(),
+            result.get_iterations() + 1,
+            "Incorrect history length"
+        );
+        assert_eq!(
+            calls.len(),
+            result.get_history().len(),
+            "Incorrect number of calls"
+        );
+        for (i, &(iteration, residual_norm, true_residual_norm)) in calls.iter().enumerate() {
+            assert_eq!(iteration, i, "Incorrect iteration count");
+            assert_eq!(
+                residual_norm,
+                result.get_history()[i],
+                "History does not match the callback"
+            );
+            assert!(
+                (true_residual_norm.unwrap() - residual_norm).abs() < 1e-10,
+                "Incorrect true residual"
+            );
+        }
+    }
+}
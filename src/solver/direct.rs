@@ -0,0 +1,442 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::ordering::reverse_cuthill_mckee;
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use crate::solver::solver_traits::Preconditioner;
+use num::Float;
+use std::collections::BTreeSet;
+
+/// Sparse columns of a factor as lists of `(index, value)` pairs
+type SparseColumns<DataType> = Vec<Vec<(usize, DataType)>>;
+
+/// Sparse LU factorization with partial pivoting
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The columns of the matrix are first reordered by reverse Cuthill-McKee to limit the fill-in,
+/// the factorization `P A Q = L U` is then computed column by column (left looking), choosing as
+/// pivot the entry of largest magnitude among the rows not yet eliminated. The factors are exact:
+/// once computed, `solve` returns the solution of the system up to round-off.
+pub struct SparseLu<DataType> {
+    column_permutation: Vec<usize>,
+    pivot_rows: Vec<usize>,
+    lower: SparseColumns<DataType>,
+    upper: SparseColumns<DataType>,
+    diagonal: Vec<DataType>,
+}
+
+impl<DataType: Scalar + Float> SparseLu<DataType> {
+    /// Constructor computing the factorization
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix`: the square system matrix
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the factorization or an error if the matrix is singular
+    pub fn new(matrix: &CsrMatrix<DataType>) -> Result<SparseLu<DataType>, Error> {
+        let n = matrix.get_number_of_rows();
+        if n != matrix.get_number_of_columns() {
+            return Err(Error::SizeMismatch {
+                context: "Matrix should be square",
+                expected: n,
+                actual: matrix.get_number_of_columns(),
+            });
+        }
+        let column_permutation = reverse_cuthill_mckee(matrix);
+        let columns = to_columns(&matrix.to_general());
+        let mut pivot_steps = vec![usize::MAX; n];
+        let mut pivot_rows = Vec::with_capacity(n);
+        let mut lower: SparseColumns<DataType> = Vec::with_capacity(n);
+        let mut upper: SparseColumns<DataType> = Vec::with_capacity(n);
+        let mut diagonal = Vec::with_capacity(n);
+        let mut work = vec![DataType::zero(); n];
+        let mut present = vec![false; n];
+        for (step, &column) in column_permutation.iter().enumerate() {
+            let mut eliminated = BTreeSet::new();
+            let mut remaining = Vec::new();
+            let register = |row: usize,
+                            present: &mut Vec<bool>,
+                            eliminated: &mut BTreeSet<usize>,
+                            remaining: &mut Vec<usize>| {
+                if !present[row] {
+                    present[row] = true;
+                    if pivot_steps[row] == usize::MAX {
+                        remaining.push(row);
+                    } else {
+                        eliminated.insert(pivot_steps[row]);
+                    }
+                }
+            };
+            for &(row, value) in columns[column].iter() {
+                register(row, &mut present, &mut eliminated, &mut remaining);
+                work[row] = work[row] + value;
+            }
+            let mut upper_column = Vec::new();
+            while let Some(previous) = eliminated.pop_first() {
+                let pivot = work[pivot_rows[previous]];
+                upper_column.push((previous, pivot));
+                for &(row, factor) in lower[previous].iter() {
+                    register(row, &mut present, &mut eliminated, &mut remaining);
+                    work[row] = work[row] - factor * pivot;
+                }
+            }
+            let pivot_row = remaining
+                .iter()
+                .cloned()
+                .max_by(|&a, &b| work[a].abs().partial_cmp(&work[b].abs()).unwrap())
+                .filter(|&row| work[row] != DataType::zero())
+                .ok_or(Error::Singular("Matrix is singular"))?;
+            let pivot = work[pivot_row];
+            pivot_steps[pivot_row] = step;
+            pivot_rows.push(pivot_row);
+            diagonal.push(pivot);
+            lower.push(
+                remaining
+                    .iter()
+                    .filter(|&&row| row != pivot_row && work[row] != DataType::zero())
+                    .map(|&row| (row, work[row] / pivot))
+                    .collect(),
+            );
+            upper.push(upper_column);
+            for &(previous, _) in upper[step].iter() {
+                let row = pivot_rows[previous];
+                work[row] = DataType::zero();
+                present[row] = false;
+            }
+            for &row in remaining.iter() {
+                work[row] = DataType::zero();
+                present[row] = false;
+            }
+        }
+        Ok(SparseLu {
+            column_permutation,
+            pivot_rows,
+            lower,
+            upper,
+            diagonal,
+        })
+    }
+
+    /// Get the number of entries stored in the factors, diagonal included
+    pub fn get_number_of_nonzeros(&self) -> usize {
+        self.diagonal.len()
+            + self.lower.iter().map(|c| c.len()).sum::<usize>()
+            + self.upper.iter().map(|c| c.len()).sum::<usize>()
+    }
+
+    /// Solve the factorized system
+    ///
+    /// # Arguments
+    ///
+    /// * `rhs`: the right hand side
+    ///
+    /// # Returns
+    ///
+    /// * the solution
+    pub fn solve(&self, rhs: &[DataType]) -> Vec<DataType> {
+        let mut x = vec![DataType::zero(); rhs.len()];
+        self.solve_into(rhs, &mut x);
+        x
+    }
+
+    /// Solve the factorized system for several right hand sides, reusing the factorization
+    pub fn solve_multiple(&self, rhs: &[Vec<DataType>]) -> Vec<Vec<DataType>> {
+        rhs.iter().map(|rhs| self.solve(rhs)).collect()
+    }
+
+    /// Solve the factorized system into an existing vector
+    pub fn solve_into(&self, rhs: &[DataType], x: &mut [DataType]) {
+        let n = self.diagonal.len();
+        let mut work = rhs.to_vec();
+        let mut steps = vec![DataType::zero(); n];
+        for step in 0..n {
+            let value = work[self.pivot_rows[step]];
+            steps[step] = value;
+            for &(row, factor) in self.lower[step].iter() {
+                work[row] = work[row] - factor * value;
+            }
+        }
+        for step in (0..n).rev() {
+            let value = steps[step] / self.diagonal[step];
+            steps[step] = value;
+            for &(previous, entry) in self.upper[step].iter() {
+                steps[previous] = steps[previous] - entry * value;
+            }
+            x[self.column_permutation[step]] = value;
+        }
+    }
+}
+
+impl<DataType: Scalar + Float> Preconditioner<DataType> for SparseLu<DataType> {
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        self.solve_into(r, z);
+    }
+}
+
+/// Sparse Cholesky factorization of a symmetric positive definite matrix
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The matrix is symmetrically reordered by reverse Cuthill-McKee and factorized as
+/// `P A P^T = L L^T`, computing one row of `L` at a time by a sparse triangular solve (up
+/// looking). Both `General` and `Upper` storages are accepted. The factorization fails on a non
+/// positive pivot, which happens when the matrix is not positive definite.
+pub struct SparseCholesky<DataType> {
+    permutation: Vec<usize>,
+    lower: SparseColumns<DataType>,
+    diagonal: Vec<DataType>,
+}
+
+impl<DataType: Scalar + Float> SparseCholesky<DataType> {
+    /// Constructor computing the factorization
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix`: the symmetric positive definite system matrix
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the factorization or an error if the matrix is not positive
+    ///   definite
+    pub fn new(matrix: &CsrMatrix<DataType>) -> Result<SparseCholesky<DataType>, Error> {
+        let n = matrix.get_number_of_rows();
+        if n != matrix.get_number_of_columns() {
+            return Err(Error::SizeMismatch {
+                context: "Matrix should be square",
+                expected: n,
+                actual: matrix.get_number_of_columns(),
+            });
+        }
+        let permutation = reverse_cuthill_mckee(matrix);
+        let mut positions = vec![0; n];
+        for (new, &old) in permutation.iter().enumerate() {
+            positions[old] = new;
+        }
+        // lower triangle of the permuted matrix, by rows
+        let general = matrix.to_general();
+        let offsets = general.get_row_offsets();
+        let column_indices = general.get_column_indices();
+        let values = general.get_values();
+        let mut rows: SparseColumns<DataType> = vec![Vec::new(); n];
+        for old_row in 0..n {
+            let row = positions[old_row];
+            for k in offsets[old_row]..offsets[old_row + 1] {
+                let column = positions[column_indices[k]];
+                if column <= row {
+                    rows[row].push((column, values[k]));
+                }
+            }
+        }
+        let mut lower: SparseColumns<DataType> = vec![Vec::new(); n];
+        let mut diagonal = Vec::with_capacity(n);
+        let mut work = vec![DataType::zero(); n];
+        let mut present = vec![false; n];
+        for (row, entries) in rows.iter().enumerate() {
+            let mut pattern = BTreeSet::new();
+            let mut pivot = DataType::zero();
+            for &(column, value) in entries.iter() {
+                if column == row {
+                    pivot = pivot + value;
+                } else {
+                    if !present[column] {
+                        present[column] = true;
+                        pattern.insert(column);
+                    }
+                    work[column] = work[column] + value;
+                }
+            }
+            let mut computed = Vec::new();
+            while let Some(column) = pattern.pop_first() {
+                let value = work[column] / diagonal[column];
+                work[column] = DataType::zero();
+                present[column] = false;
+                computed.push((column, value));
+                pivot = pivot - value * value;
+                for &(other, entry) in lower[column].iter() {
+                    if !present[other] {
+                        present[other] = true;
+                        pattern.insert(other);
+                    }
+                    work[other] = work[other] - entry * value;
+                }
+            }
+            if pivot <= DataType::zero() {
+                return Err(Error::Singular("Matrix is not positive definite"));
+            }
+            diagonal.push(pivot.sqrt());
+            for (column, value) in computed {
+                lower[column].push((row, value));
+            }
+        }
+        Ok(SparseCholesky {
+            permutation,
+            lower,
+            diagonal,
+        })
+    }
+
+    /// Get the number of entries stored in the factor, diagonal included
+    pub fn get_number_of_nonzeros(&self) -> usize {
+        self.diagonal.len() + self.lower.iter().map(|c| c.len()).sum::<usize>()
+    }
+
+    /// Solve the factorized system
+    ///
+    /// # Arguments
+    ///
+    /// * `rhs`: the right hand side
+    ///
+    /// # Returns
+    ///
+    /// * the solution
+    pub fn solve(&self, rhs: &[DataType]) -> Vec<DataType> {
+        let mut x = vec![DataType::zero(); rhs.len()];
+        self.solve_into(rhs, &mut x);
+        x
+    }
+
+    /// Solve the factorized system for several right hand sides, reusing the factorization
+    pub fn solve_multiple(&self, rhs: &[Vec<DataType>]) -> Vec<Vec<DataType>> {
+        rhs.iter().map(|rhs| self.solve(rhs)).collect()
+    }
+
+    /// Solve the factorized system into an existing vector
+    pub fn solve_into(&self, rhs: &[DataType], x: &mut [DataType]) {
+        let n = self.diagonal.len();
+        let mut work: Vec<DataType> = self.permutation.iter().map(|&old| rhs[old]).collect();
+        for column in 0..n {
+            let value = work[column] / self.diagonal[column];
+            work[column] = value;
+            for &(row, entry) in self.lower[column].iter() {
+                work[row] = work[row] - entry * value;
+            }
+        }
+        for column in (0..n).rev() {
+            let sum = self.lower[column]
+                .iter()
+                .fold(work[column], |sum, &(row, entry)| sum - entry * work[row]);
+            work[column] = sum / self.diagonal[column];
+        }
+        for (new, &old) in self.permutation.iter().enumerate() {
+            x[old] = work[new];
+        }
+    }
+}
+
+impl<DataType: Scalar + Float> Preconditioner<DataType> for SparseCholesky<DataType> {
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        self.solve_into(r, z);
+    }
+}
+
+/// Gather the entries of a general matrix by columns
+fn to_columns<DataType: Scalar>(matrix: &CsrMatrix<DataType>) -> SparseColumns<DataType> {
+    let offsets = matrix.get_row_offsets();
+    let column_indices = matrix.get_column_indices();
+    let values = matrix.get_values();
+    let mut columns = vec![Vec::new(); matrix.get_number_of_columns()];
+    for row in 0..matrix.get_number_of_rows() {
+        for k in offsets[row]..offsets[row + 1] {
+            columns[column_indices[k]].push((row, values[k]));
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SparseCholesky, SparseLu};
+    use crate::algebra::csr::CsrMatrix;
+    use crate::test_utils::{convection_diffusion_system, poisson_solution, poisson_system};
+
+    const TOL: f64 = 1e-10;
+
+    fn max_error(x: &[f64], y: &[f64]) -> f64 {
+        x.iter().zip(y).fold(0.0, |m, (a, b)| m.max((a - b).abs()))
+    }
+
+    #[test]
+    fn test_cholesky_poisson() {
+        let (mat, rhs) = poisson_system(20);
+        let factor = SparseCholesky::new(&mat).unwrap();
+        let x = factor.solve(&rhs);
+        assert!(
+            max_error(&x, &poisson_solution(20)) < TOL,
+            "Incorrect Cholesky solution"
+        );
+        assert!(
+            factor.get_number_of_nonzeros() <= 2 * 21,
+            "Unexpected fill-in on a tridiagonal matrix"
+        );
+    }
+
+    #[test]
+    fn test_cholesky_indefinite() {
+        let mat = CsrMatrix::from_triplets(2, 2, &[(0, 1, 1.0), (1, 0, 1.0)]).unwrap();
+        assert!(
+            SparseCholesky::new(&mat).is_err(),
+            "Indefinite matrix factorized"
+        );
+    }
+
+    #[test]
+    fn test_lu_convection_diffusion() {
+        let (mat, rhs, solution) = convection_diffusion_system(30);
+        let factor = SparseLu::new(&mat).unwrap();
+        let x = factor.solve(&rhs);
+        assert!(max_error(&x, &solution) < TOL, "Incorrect LU solution");
+    }
+
+    #[test]
+    fn test_lu_pivoting() {
+        // zero diagonal requiring row exchanges
+        let mat = CsrMatrix::from_triplets(
+            3,
+            3,
+            &[
+                (0, 1, 2.0),
+                (0, 2, 1.0),
+                (1, 0, 1.0),
+                (1, 2, 3.0),
+                (2, 0, 4.0),
+                (2, 1, 1.0),
+            ],
+        )
+        .unwrap();
+        let solution = [1.0, -2.0, 0.5];
+        let rhs = mat.apply(&solution);
+        let x = SparseLu::new(&mat).unwrap().solve(&rhs);
+        assert!(max_error(&x, &solution) < TOL, "Incorrect pivoted solution");
+    }
+
+    #[test]
+    fn test_lu_singular() {
+        let mat =
+            CsrMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (0, 1, 2.0), (1, 0, 2.0), (1, 1, 4.0)])
+                .unwrap();
+        assert!(SparseLu::new(&mat).is_err(), "Singular matrix factorized");
+    }
+
+    #[test]
+    fn test_multiple_rhs() {
+        let (mat, rhs, solution) = convection_diffusion_system(10);
+        let scaled: Vec<f64> = rhs.iter().map(|v| 2.0 * v).collect();
+        let factor = SparseLu::new(&mat).unwrap();
+        let x = factor.solve_multiple(&[rhs.clone(), scaled]);
+        assert_eq!(x.len(), 2, "Incorrect number of solutions");
+        for ((a, b), e) in x[0].iter().zip(x[1].iter()).zip(solution.iter()) {
+            assert!((a - e).abs() < TOL, "Incorrect first solution");
+            assert!((b - 2.0 * e).abs() < TOL, "Incorrect second solution");
+        }
+    }
+}
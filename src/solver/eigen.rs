@@ -0,0 +1,480 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::{axpy, dot};
+use crate::error::Error;
+use crate::solver::direct::{SparseCholesky, SparseLu};
+use crate::solver::solver_traits::IterationControl;
+use num::Float;
+use std::cell::RefCell;
+
+/// Linear operator applied at every Lanczos step
+type SpectralOperator<'a, DataType> = Box<dyn Fn(&[DataType], &mut [DataType]) + 'a>;
+
+/// Lanczos solver for the generalized symmetric eigenproblem `K x = λ M x`
+///
+/// # Generics
+///
+/// * DataType: the type of unit the matrices are encoded with
+///
+/// # Explanation
+///
+/// `K` should be symmetric and `M` symmetric positive definite (a stiffness and a mass matrix in
+/// modal analysis). The Lanczos process builds an `M` orthonormal basis of a Krylov space, fully
+/// reorthogonalized, of one of the two operators:
+///
+/// * `M^{-1} K` without shift, whose smallest Ritz values approximate the smallest eigenvalues
+/// * `(K - σ M)^{-1} M` with a shift `σ`, whose largest Ritz values `θ` approximate the
+///   eigenvalues `λ = σ + 1 / θ` closest to the shift. This shift-invert mode converges in far
+///   fewer iterations and is the one to use for the low modes of large problems (`σ = 0`)
+///
+/// The required inverse is computed once by a sparse direct factorization. The maximum number of
+/// iterations of the control bounds the dimension of the Krylov space and an eigenpair is
+/// converged when its residual estimate is below the relative tolerance times the Ritz value.
+pub struct LanczosEigenSolver<DataType> {
+    number_of_eigenpairs: usize,
+    shift: Option<DataType>,
+    control: IterationControl<DataType>,
+}
+
+/// Eigenpairs computed by an eigensolver
+#[derive(Clone, Debug)]
+pub struct EigenResult<DataType> {
+    eigenvalues: Vec<DataType>,
+    eigenvectors: Vec<Vec<DataType>>,
+    converged: bool,
+    iterations: usize,
+}
+
+impl<DataType: Scalar + Float> LanczosEigenSolver<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `number_of_eigenpairs`: the number of eigenpairs to compute
+    /// * `control`: the stopping criterion of the iterations
+    pub fn new(
+        number_of_eigenpairs: usize,
+        control: IterationControl<DataType>,
+    ) -> LanczosEigenSolver<DataType> {
+        LanczosEigenSolver {
+            number_of_eigenpairs,
+            shift: None,
+            control,
+        }
+    }
+
+    /// Set the shift of the shift-invert mode, `None` to compute the smallest eigenvalues without
+    pub fn set_shift(&mut self, shift: Option<DataType>) {
+        self.shift = shift;
+    }
+
+    /// Get the shift of the shift-invert mode
+    pub fn get_shift(&self) -> Option<DataType> {
+        self.shift
+    }
+
+    /// Get the number of eigenpairs to compute
+    pub fn get_number_of_eigenpairs(&self) -> usize {
+        self.number_of_eigenpairs
+    }
+
+    /// Get the stopping criterion of the iterations
+    pub fn get_control(&self) -> &IterationControl<DataType> {
+        &self.control
+    }
+
+    /// Compute the eigenpairs
+    ///
+    /// # Arguments
+    ///
+    /// * `stiffness`: the symmetric matrix `K`
+    /// * `mass`: the symmetric positive definite matrix `M`
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the eigenpairs by increasing eigenvalue, with `M` normalized
+    ///   eigenvectors, or an error if the required factorization failed
+    pub fn solve(
+        &self,
+        stiffness: &CsrMatrix<DataType>,
+        mass: &CsrMatrix<DataType>,
+    ) -> Result<EigenResult<DataType>, Error> {
+        let n = stiffness.get_number_of_rows();
+        if n != stiffness.get_number_of_columns() {
+            return Err(Error::SizeMismatch {
+                context: "Stiffness matrix should be square",
+                expected: n,
+                actual: stiffness.get_number_of_columns(),
+            });
+        }
+        if n != mass.get_number_of_rows() || n != mass.get_number_of_columns() {
+            return Err(Error::SizeMismatch {
+                context: "Mass matrix should be of the size of the stiffness matrix",
+                expected: n,
+                actual: mass.get_number_of_rows(),
+            });
+        }
+        if self.number_of_eigenpairs > n {
+            return Err(Error::SizeMismatch {
+                context: "More eigenpairs requested than the size of the problem",
+                expected: n,
+                actual: self.number_of_eigenpairs,
+            });
+        }
+        let operator: SpectralOperator<DataType> = match self.shift {
+            None => {
+                let factor = SparseCholesky::new(mass)?;
+                let work = RefCell::new(vec![DataType::zero(); n]);
+                Box::new(move |x, y| {
+                    let mut work = work.borrow_mut();
+                    stiffness.apply_into(x, &mut work);
+                    factor.solve_into(&work, y);
+                })
+            }
+            Some(shift) => {
+                let factor = SparseLu::new(&shifted(stiffness, mass, shift)?)?;
+                let work = RefCell::new(vec![DataType::zero(); n]);
+                Box::new(move |x, y| {
+                    let mut work = work.borrow_mut();
+                    mass.apply_into(x, &mut work);
+                    factor.solve_into(&work, y);
+                })
+            }
+        };
+        let maximum_dimension = self.control.get_maximum_iterations().min(n).max(1);
+        let tolerance = self.control.get_relative_tolerance();
+        // deterministic start vector with components along every eigenvector in general
+        let mut seed = 0x2545f4914f6cdd1d_u64;
+        let mut q: Vec<DataType> = (0..n)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                DataType::from((seed >> 11) as f64 / (1u64 << 53) as f64 + 0.5).unwrap()
+            })
+            .collect();
+        let mut basis: Vec<Vec<DataType>> = Vec::new();
+        let mut mass_basis: Vec<Vec<DataType>> = Vec::new();
+        let mut alphas = Vec::new();
+        let mut betas: Vec<DataType> = Vec::new();
+        let mut mq = mass.apply(&q);
+        let scale = dot(&q, &mq).sqrt();
+        q.iter_mut().for_each(|v| *v = *v / scale);
+        mq.iter_mut().for_each(|v| *v = *v / scale);
+        let mut w = vec![DataType::zero(); n];
+        loop {
+            let dimension = basis.len() + 1;
+            operator(&q, &mut w);
+            let alpha = dot(&w, &mq);
+            basis.push(q);
+            mass_basis.push(mq);
+            alphas.push(alpha);
+            // full reorthogonalization, twice for stability
+            for _ in 0..2 {
+                for (v, mv) in basis.iter().zip(mass_basis.iter()) {
+                    let projection = dot(&w, mv);
+                    axpy(-projection, v, &mut w);
+                }
+            }
+            let mw = mass.apply(&w);
+            let beta = dot(&w, &mw).max(DataType::zero()).sqrt();
+            let exhausted = dimension == maximum_dimension
+                || beta <= DataType::epsilon() * alphas.iter().fold(beta, |m, a| m.max(a.abs()));
+            if dimension >= self.number_of_eigenpairs && (exhausted || dimension.is_multiple_of(5))
+            {
+                let (thetas, vectors) = tridiagonal_eigen(&alphas, &betas);
+                let mut wanted: Vec<usize> = (0..dimension)
+                    .filter(|&i| self.shift.is_none() || thetas[i] != DataType::zero())
+                    .collect();
+                match self.shift {
+                    None => wanted.sort_by(|&a, &b| thetas[a].partial_cmp(&thetas[b]).unwrap()),
+                    Some(_) => wanted
+                        .sort_by(|&a, &b| thetas[b].abs().partial_cmp(&thetas[a].abs()).unwrap()),
+                }
+                wanted.truncate(self.number_of_eigenpairs);
+                let converged = wanted.len() == self.number_of_eigenpairs
+                    && wanted.iter().all(|&i| {
+                        (beta * vectors[(dimension - 1) * dimension + i]).abs()
+                            <= tolerance * thetas[i].abs()
+                    });
+                if converged || exhausted {
+                    let mut pairs: Vec<(DataType, Vec<DataType>)> = wanted
+                        .iter()
+                        .map(|&i| {
+                            let mut x = vec![DataType::zero(); n];
+                            for (j, v) in basis.iter().enumerate() {
+                                axpy(vectors[j * dimension + i], v, &mut x);
+                            }
+                            let eigenvalue = match self.shift {
+                                None => thetas[i],
+                                Some(shift) => shift + DataType::one() / thetas[i],
+                            };
+                            (eigenvalue, x)
+                        })
+                        .collect();
+                    pairs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                    let (eigenvalues, eigenvectors) = pairs.into_iter().unzip();
+                    return Ok(EigenResult {
+                        eigenvalues,
+                        eigenvectors,
+                        converged: converged
+                            || (beta == DataType::zero()
+                                && wanted.len() == self.number_of_eigenpairs),
+                        iterations: dimension,
+                    });
+                }
+            }
+            if exhausted {
+                return Ok(EigenResult {
+                    eigenvalues: Vec::new(),
+                    eigenvectors: Vec::new(),
+                    converged: false,
+                    iterations: dimension,
+                });
+            }
+            betas.push(beta);
+            q = w.iter().map(|&v| v / beta).collect();
+            mq = mw.iter().map(|&v| v / beta).collect();
+        }
+    }
+}
+
+impl<DataType: Copy> EigenResult<DataType> {
+    /// Get the eigenvalues by increasing value
+    pub fn get_eigenvalues(&self) -> &[DataType] {
+        &self.eigenvalues
+    }
+
+    /// Get the eigenvectors, normalized in the mass inner product
+    pub fn get_eigenvectors(&self) -> &[Vec<DataType>] {
+        &self.eigenvectors
+    }
+
+    /// Whether all the requested eigenpairs reached the tolerance
+    pub fn is_converged(&self) -> bool {
+        self.converged
+    }
+
+    /// Get the number of iterations, the dimension of the Krylov space
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+/// Build `K - σ M`
+fn shifted<DataType: Scalar>(
+    stiffness: &CsrMatrix<DataType>,
+    mass: &CsrMatrix<DataType>,
+    shift: DataType,
+) -> Result<CsrMatrix<DataType>, Error> {
+    let mut triplets = Vec::new();
+    for (matrix, factor) in [
+        (stiffness, DataType::one()),
+        (mass, DataType::zero() - shift),
+    ] {
+        let matrix = matrix.to_general();
+        let offsets = matrix.get_row_offsets();
+        let columns = matrix.get_column_indices();
+        let values = matrix.get_values();
+        for row in 0..matrix.get_number_of_rows() {
+            for k in offsets[row]..offsets[row + 1] {
+                triplets.push((row, columns[k], factor * values[k]));
+            }
+        }
+    }
+    CsrMatrix::from_triplets(
+        stiffness.get_number_of_rows(),
+        stiffness.get_number_of_columns(),
+        &triplets,
+    )
+}
+
+/// Compute the eigenpairs of a symmetric tridiagonal matrix by the cyclic Jacobi method
+///
+/// # Arguments
+///
+/// * `diagonal`: the diagonal of the matrix
+/// * `off_diagonal`: the sub-diagonal of the matrix
+///
+/// # Returns
+///
+/// * the eigenvalues and the row-major matrix holding the eigenvectors as columns
+fn tridiagonal_eigen<DataType: Scalar + Float>(
+    diagonal: &[DataType],
+    off_diagonal: &[DataType],
+) -> (Vec<DataType>, Vec<DataType>) {
+    let n = diagonal.len();
+    let mut a = vec![DataType::zero(); n * n];
+    for i in 0..n {
+        a[i * n + i] = diagonal[i];
+    }
+    for (i, &value) in off_diagonal.iter().enumerate() {
+        a[i * n + i + 1] = value;
+        a[(i + 1) * n + i] = value;
+    }
+    symmetric_eigen(a, n)
+}
+
+/// Compute the eigenpairs of a dense symmetric matrix by the cyclic Jacobi method
+///
+/// # Arguments
+///
+/// * `a`: the row-major symmetric matrix
+/// * `n`: the size of the matrix
+///
+/// # Returns
+///
+/// * the eigenvalues and the row-major matrix holding the eigenvectors as columns
+pub(crate) fn symmetric_eigen<DataType: Scalar + Float>(
+    mut a: Vec<DataType>,
+    n: usize,
+) -> (Vec<DataType>, Vec<DataType>) {
+    let mut v = vec![DataType::zero(); n * n];
+    for i in 0..n {
+        v[i * n + i] = DataType::one();
+    }
+    let two = DataType::one() + DataType::one();
+    let scale = a.iter().fold(DataType::zero(), |m, x| m.max(x.abs()));
+    for _ in 0..100 {
+        let off = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .fold(DataType::zero(), |s, (i, j)| {
+                s + a[i * n + j] * a[i * n + j]
+            });
+        if off.sqrt() <= DataType::epsilon() * scale {
+            break;
+        }
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let apq = a[p * n + q];
+                if apq == DataType::zero() {
+                    continue;
+                }
+                let tau = (a[q * n + q] - a[p * n + p]) / (two * apq);
+                let t = tau.signum() / (tau.abs() + (DataType::one() + tau * tau).sqrt());
+                let c = DataType::one() / (DataType::one() + t * t).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let akp = a[k * n + p];
+                    let akq = a[k * n + q];
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let apk = a[p * n + k];
+                    let aqk = a[q * n + k];
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let vkp = v[k * n + p];
+                    let vkq = v[k * n + q];
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i * n + i]).collect(), v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{symmetric_eigen, LanczosEigenSolver};
+    use crate::algebra::csr::CsrMatrix;
+    use crate::solver::solver_traits::IterationControl;
+    use std::f64::consts::PI;
+
+    const TOL: f64 = 1e-8;
+
+    /// Tridiagonal `K = tridiag(-1, 2, -1)` and `M = 2 I`, of eigenvalues `1 - cos(k π / (n + 1))`
+    fn system(n: usize) -> (CsrMatrix<f64>, CsrMatrix<f64>, Vec<f64>) {
+        let mut triplets = Vec::new();
+        for i in 0..n {
+            triplets.push((i, i, 2.0));
+            if i + 1 < n {
+                triplets.push((i, i + 1, -1.0));
+                triplets.push((i + 1, i, -1.0));
+            }
+        }
+        let stiffness = CsrMatrix::from_triplets(n, n, &triplets).unwrap();
+        let diagonal: Vec<(usize, usize, f64)> = (0..n).map(|i| (i, i, 2.0)).collect();
+        let mass = CsrMatrix::from_triplets(n, n, &diagonal).unwrap();
+        let eigenvalues = (1..=n)
+            .map(|k| 1.0 - (k as f64 * PI / (n as f64 + 1.0)).cos())
+            .collect();
+        (stiffness, mass, eigenvalues)
+    }
+
+    #[test]
+    fn test_dense_eigen() {
+        let (values, vectors) = symmetric_eigen(vec![2.0_f64, 1.0, 1.0, 2.0], 2);
+        let mut sorted = values.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((sorted[0] - 1.0).abs() < TOL, "Incorrect first eigenvalue");
+        assert!((sorted[1] - 3.0).abs() < TOL, "Incorrect second eigenvalue");
+        let first = [vectors[0], vectors[2]];
+        assert!(
+            (2.0 * first[0] + first[1] - values[0] * first[0]).abs() < TOL,
+            "Incorrect eigenvector"
+        );
+    }
+
+    #[test]
+    fn test_shift_invert() {
+        let (stiffness, mass, exact) = system(100);
+        let mut solver = LanczosEigenSolver::new(4, IterationControl::new(1e-10, 0.0, 100));
+        solver.set_shift(Some(0.0));
+        let result = solver.solve(&stiffness, &mass).unwrap();
+        assert!(
+            result.is_converged(),
+            "Shift-invert Lanczos did not converge"
+        );
+        assert!(result.get_iterations() < 50, "Too many iterations");
+        for (computed, expected) in result.get_eigenvalues().iter().zip(exact.iter()) {
+            assert!(
+                (computed - expected).abs() < TOL * expected.max(1.0),
+                "Incorrect eigenvalue"
+            );
+        }
+        let x = &result.get_eigenvectors()[0];
+        let kx = stiffness.apply(x);
+        let mx = mass.apply(x);
+        let lambda = result.get_eigenvalues()[0];
+        let mass_norm: f64 = x.iter().zip(mx.iter()).map(|(a, b)| a * b).sum();
+        assert!((mass_norm - 1.0).abs() < TOL, "Eigenvector not normalized");
+        assert!(
+            kx.iter()
+                .zip(mx.iter())
+                .all(|(a, b)| (a - lambda * b).abs() < 1e-6),
+            "Incorrect eigenvector"
+        );
+    }
+
+    #[test]
+    fn test_interior_shift() {
+        let (stiffness, mass, exact) = system(50);
+        let mut solver = LanczosEigenSolver::new(2, IterationControl::new(1e-10, 0.0, 50));
+        solver.set_shift(Some(1.0));
+        let result = solver.solve(&stiffness, &mass).unwrap();
+        assert!(result.is_converged(), "Interior Lanczos did not converge");
+        let mut closest = exact.clone();
+        closest.sort_by(|a, b| (a - 1.0).abs().partial_cmp(&(b - 1.0).abs()).unwrap());
+        let mut expected = closest[..2].to_vec();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (computed, expected) in result.get_eigenvalues().iter().zip(expected.iter()) {
+            assert!((computed - expected).abs() < TOL, "Incorrect eigenvalue");
+        }
+    }
+
+    #[test]
+    fn test_without_shift() {
+        let (stiffness, mass, exact) = system(30);
+        let solver = LanczosEigenSolver::new(2, IterationControl::new(1e-10, 0.0, 30));
+        let result = solver.solve(&stiffness, &mass).unwrap();
+        assert!(result.is_converged(), "Lanczos did not converge");
+        for (computed, expected) in result.get_eigenvalues().iter().zip(exact.iter()) {
+            assert!((computed - expected).abs() < TOL, "Incorrect eigenvalue");
+        }
+    }
+}
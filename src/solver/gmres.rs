@@ -0,0 +1,394 @@
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::{axpy, dot, norm, xpby};
+use crate::solver::solver_traits::{
+    IterationControl, LinearMap, LinearSolver, Preconditioner, SolverMonitor, SolverResult,
+};
+use num::Float;
+
+/// Orthogonalization procedures of the Arnoldi process
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orthogonalization {
+    /// Modified Gram-Schmidt, sequential projections on each basis vector
+    ModifiedGramSchmidt,
+    /// Classical Gram-Schmidt applied twice, all projections at once for better parallelism
+    ClassicalGramSchmidtTwice,
+}
+
+/// Restarted generalized minimal residual solver with right preconditioning
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// GMRES(m) solves a general non singular `A x = b` by minimizing the residual norm over Krylov
+/// spaces of dimension up to the restart length `m`, after which the Krylov basis is discarded and
+/// the process restarted from the current iterate. The preconditioner is applied on the right,
+/// `A M^{-1} y = b` with `x = M^{-1} y`, so that the minimized residual is the true residual.
+pub struct Gmres<'a, DataType> {
+    control: IterationControl<DataType>,
+    restart: usize,
+    orthogonalization: Orthogonalization,
+    monitor: SolverMonitor<'a, DataType>,
+}
+
+impl<'a, DataType: Scalar + Float> Gmres<'a, DataType> {
+    /// Constructor using modified Gram-Schmidt orthogonalization
+    ///
+    /// # Arguments
+    ///
+    /// * `control`: the stopping criterion of the iterations
+    /// * `restart`: the maximum dimension of the Krylov spaces (at least one)
+    pub fn new(control: IterationControl<DataType>, restart: usize) -> Gmres<'a, DataType> {
+        Gmres {
+            control,
+            restart: restart.max(1),
+            orthogonalization: Orthogonalization::ModifiedGramSchmidt,
+            monitor: SolverMonitor::new(),
+        }
+    }
+
+    /// Set the orthogonalization procedure
+    pub fn set_orthogonalization(&mut self, orthogonalization: Orthogonalization) {
+        self.orthogonalization = orthogonalization;
+    }
+
+    /// Set the monitoring of the iterations, the true residual requires forming the iterate at
+    /// each iteration
+    pub fn set_monitor(&mut self, monitor: SolverMonitor<'a, DataType>) {
+        self.monitor = monitor;
+    }
+
+    /// Get the monitoring of the iterations
+    pub fn get_monitor(&self) -> &SolverMonitor<'a, DataType> {
+        &self.monitor
+    }
+
+    /// Get the stopping criterion of the iterations
+    pub fn get_control(&self) -> &IterationControl<DataType> {
+        &self.control
+    }
+
+    /// Get the restart length
+    pub fn get_restart(&self) -> usize {
+        self.restart
+    }
+
+    /// Get the orthogonalization procedure
+    pub fn get_orthogonalization(&self) -> Orthogonalization {
+        self.orthogonalization
+    }
+
+    /// Solve a linear system
+    ///
+    /// # Arguments
+    ///
+    /// * `map`: the system operator `A`
+    /// * `preconditioner`: the preconditioner `M`
+    /// * `rhs`: the right hand side `b`
+    /// * `x`: the initial guess, overwritten by the solution
+    ///
+    /// # Returns
+    ///
+    /// * the convergence information of the solve
+    pub fn solve<MapT, PreconditionerT>(
+        &self,
+        map: &MapT,
+        preconditioner: &PreconditionerT,
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> SolverResult<DataType>
+    where
+        MapT: LinearMap<DataType> + ?Sized,
+        PreconditionerT: Preconditioner<DataType> + ?Sized,
+    {
+        let n = rhs.len();
+        let target = self.control.get_target(norm(rhs));
+        let maximum_iterations = self.control.get_maximum_iterations();
+        let mut r = vec![DataType::zero(); n];
+        let mut w = vec![DataType::zero(); n];
+        let mut z = vec![DataType::zero(); n];
+        // trial iterate and work vectors of the true residual, which must not alter the cycle
+        let trial_size = if self.monitor.is_computing_true_residual() {
+            n
+        } else {
+            0
+        };
+        let mut trial = vec![DataType::zero(); trial_size];
+        let mut trial_w = vec![DataType::zero(); trial_size];
+        let mut trial_z = vec![DataType::zero(); trial_size];
+        let mut history = Vec::new();
+        let mut iterations = 0;
+        let mut initial_residual_norm = None;
+        loop {
+            map.apply(x, &mut r);
+            xpby(rhs, -DataType::one(), &mut r);
+            let beta = norm(&r);
+            let initial = *initial_residual_norm.get_or_insert(beta);
+            if iterations == 0 {
+                self.monitor.record(&mut history, 0, beta, map, rhs, x);
+            }
+            if beta <= target {
+                return SolverResult::new(true, iterations, initial, beta, history);
+            }
+            if iterations >= maximum_iterations {
+                return SolverResult::new(false, iterations, initial, beta, history);
+            }
+            let mut basis = vec![r.iter().map(|&v| v / beta).collect::<Vec<DataType>>()];
+            let mut hessenberg: Vec<Vec<DataType>> = Vec::with_capacity(self.restart);
+            let mut rotations: Vec<(DataType, DataType)> = Vec::with_capacity(self.restart);
+            let mut g = vec![beta];
+            let mut residual_norm = beta;
+            while basis.len() <= self.restart && iterations < maximum_iterations {
+                let j = basis.len() - 1;
+                preconditioner.apply(&basis[j], &mut z);
+                map.apply(&z, &mut w);
+                let mut h = self.orthogonalize(&basis, &mut w);
+                let h_next = norm(&w);
+                h.push(h_next);
+                for (i, &(c, s)) in rotations.iter().enumerate() {
+                    let (a, b) = (h[i], h[i + 1]);
+                    h[i] = c * a + s * b;
+                    h[i + 1] = c * b - s * a;
+                }
+                let radius = h[j].hypot(h[j + 1]);
+                let (c, s) = if radius > DataType::zero() {
+                    (h[j] / radius, h[j + 1] / radius)
+                } else {
+                    (DataType::one(), DataType::zero())
+                };
+                h[j] = radius;
+                h[j + 1] = DataType::zero();
+                rotations.push((c, s));
+                g.push(-s * g[j]);
+                g[j] = c * g[j];
+                hessenberg.push(h);
+                iterations += 1;
+                residual_norm = g[j + 1].abs();
+                if self.monitor.is_computing_true_residual() {
+                    correction(
+                        &basis,
+                        &hessenberg,
+                        &g,
+                        preconditioner,
+                        &mut trial_w,
+                        &mut trial_z,
+                    );
+                    trial.copy_from_slice(x);
+                    axpy(DataType::one(), &trial_z, &mut trial);
+                }
+                self.monitor
+                    .record(&mut history, iterations, residual_norm, map, rhs, &trial);
+                if residual_norm <= target || h_next <= DataType::zero() {
+                    break;
+                }
+                basis.push(w.iter().map(|&v| v / h_next).collect());
+            }
+            correction(&basis, &hessenberg, &g, preconditioner, &mut w, &mut z);
+            axpy(DataType::one(), &z, x);
+            if residual_norm <= target {
+                return SolverResult::new(true, iterations, initial, residual_norm, history);
+            }
+        }
+    }
+
+    /// Orthogonalize w against the basis vectors
+    ///
+    /// # Returns
+    ///
+    /// * the projection coefficients of w on the basis vectors
+    fn orthogonalize(&self, basis: &[Vec<DataType>], w: &mut [DataType]) -> Vec<DataType> {
+        match self.orthogonalization {
+            Orthogonalization::ModifiedGramSchmidt => basis
+                .iter()
+                .map(|v| {
+                    let h = dot(v, w);
+                    axpy(-h, v, w);
+                    h
+                })
+                .collect(),
+            Orthogonalization::ClassicalGramSchmidtTwice => {
+                let mut h = vec![DataType::zero(); basis.len()];
+                for _ in 0..2 {
+                    let projections: Vec<DataType> = basis.iter().map(|v| dot(v, w)).collect();
+                    for ((v, &p), h) in basis.iter().zip(projections.iter()).zip(h.iter_mut()) {
+                        axpy(-p, v, w);
+                        *h = *h + p;
+                    }
+                }
+                h
+            }
+        }
+    }
+}
+
+/// Compute the correction `z = M^{-1} V y` of a cycle, `y` minimizing the residual norm in the
+/// Krylov space spanned by the columns of the Hessenberg matrix
+///
+/// # Arguments
+///
+/// * `basis`: the vectors `V` of the Krylov basis
+/// * `hessenberg`: the columns of the Hessenberg matrix, triangularized by the Givens rotations
+/// * `g`: the right hand side of the least squares problem, rotated
+/// * `preconditioner`: the right preconditioner `M`
+/// * `w`: a work vector
+/// * `z`: the correction
+fn correction<DataType, PreconditionerT>(
+    basis: &[Vec<DataType>],
+    hessenberg: &[Vec<DataType>],
+    g: &[DataType],
+    preconditioner: &PreconditionerT,
+    w: &mut [DataType],
+    z: &mut [DataType],
+) where
+    DataType: Scalar + Float,
+    PreconditionerT: Preconditioner<DataType> + ?Sized,
+{
+    let k = hessenberg.len();
+    let mut y = vec![DataType::zero(); k];
+    for i in (0..k).rev() {
+        let sum = (i + 1..k).fold(g[i], |sum, l| sum - hessenberg[l][i] * y[l]);
+        y[i] = sum / hessenberg[i][i];
+    }
+    w.iter_mut().for_each(|v| *v = DataType::zero());
+    for (v, &coefficient) in basis.iter().zip(y.iter()) {
+        axpy(coefficient, v, w);
+    }
+    preconditioner.apply(w, z);
+}
+
+impl<DataType: Scalar + Float> LinearSolver<DataType> for Gmres<'_, DataType> {
+    fn solve(
+        &self,
+        map: &dyn LinearMap<DataType>,
+        preconditioner: &dyn Preconditioner<DataType>,
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> SolverResult<DataType> {
+        Gmres::solve(self, map, preconditioner, rhs, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gmres, Orthogonalization};
+    use crate::solver::solver_traits::{IdentityPreconditioner, IterationControl, SolverMonitor};
+    use crate::test_utils::convection_diffusion_system;
+    use std::cell::RefCell;
+
+    const TOL: f64 = 1e-8;
+
+    #[test]
+    fn test_full() {
+        let (mat, rhs, solution) = convection_diffusion_system(20);
+        let gmres = Gmres::new(IterationControl::new(1e-12, 0.0, 100), 30);
+        let mut x = vec![0.0; rhs.len()];
+        let result = gmres.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        assert!(result.is_converged(), "GMRES did not converge");
+        assert!(
+            result.get_iterations() <= 20,
+            "GMRES took too many iterations"
+        );
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+
+    #[test]
+    fn test_restarted() {
+        let (mat, rhs, solution) = convection_diffusion_system(20);
+        for orthogonalization in [
+            Orthogonalization::ModifiedGramSchmidt,
+            Orthogonalization::ClassicalGramSchmidtTwice,
+        ] {
+            let mut gmres = Gmres::new(IterationControl::new(1e-12, 0.0, 1000), 5);
+            gmres.set_orthogonalization(orthogonalization);
+            let mut x = vec![0.0; rhs.len()];
+            let result = gmres.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+            assert!(result.is_converged(), "Restarted GMRES did not converge");
+            assert!(result.get_iterations() > 5, "GMRES did not restart");
+            for (v, e) in x.iter().zip(solution.iter()) {
+                assert!((v - e).abs() < TOL, "Incorrect solution value");
+            }
+        }
+    }
+
+    #[test]
+    fn test_right_preconditioning() {
+        let (mat, rhs, solution) = convection_diffusion_system(20);
+        let diagonal = mat.get_diagonal();
+        let jacobi = |r: &[f64], z: &mut [f64]| {
+            for ((z, r), d) in z.iter_mut().zip(r.iter()).zip(diagonal.iter()) {
+                *z = r / d;
+            }
+        };
+        let gmres = Gmres::new(IterationControl::new(1e-12, 0.0, 1000), 10);
+        let mut x = vec![0.0; rhs.len()];
+        let result = gmres.solve(&mat, &jacobi, &rhs, &mut x);
+        assert!(
+            result.is_converged(),
+            "Preconditioned GMRES did not converge"
+        );
+        let mut residual = mat.apply(&x);
+        residual
+            .iter_mut()
+            .zip(rhs.iter())
+            .for_each(|(r, b)| *r -= b);
+        let true_norm = residual.iter().map(|r| r * r).sum::<f64>().sqrt();
+        assert!(
+            (true_norm - result.get_residual_norm()).abs() < 1e-10,
+            "Reported residual is not the true residual"
+        );
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+
+    #[test]
+    fn test_not_converged() {
+        let (mat, rhs, _) = convection_diffusion_system(20);
+        let gmres = Gmres::new(IterationControl::new(1e-12, 0.0, 4), 3);
+        let mut x = vec![0.0; rhs.len()];
+        let result = gmres.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        assert!(!result.is_converged(), "GMRES should not have converged");
+        assert_eq!(result.get_iterations(), 4, "Incorrect number of iterations");
+    }
+
+    #[test]
+    fn test_monitor() {
+        let (mat, rhs, _) = convection_diffusion_system(20);
+        let calls = RefCell::new(Vec::new());
+        let mut monitor = SolverMonitor::new();
+        monitor.set_true_residual(true);
+        monitor.set_iteration_callback(|_, residual_norm, true_residual_norm: Option<f64>| {
+            calls
+                .borrow_mut()
+                .push((residual_norm, true_residual_norm.unwrap()))
+        });
+        let mut gmres = Gmres::new(IterationControl::new(1e-12, 0.0, 1000), 5);
+        gmres.set_monitor(monitor);
+        let mut x = vec![0.0; rhs.len()];
+        let result = gmres.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        drop(gmres);
+        assert!(result.is_converged(), "GMRES did not converge");
+        let calls = calls.into_inner();
+        assert_eq!(
+            calls.len(),
+            result.get_iterations() + 1,
+            "Incorrect number of calls"
+        );
+        for window in result.get_history().windows(2) {
+            assert!(
+                window[1] <= window[0] * (1.0 + 1e-12),
+                "GMRES residual should not increase"
+            );
+        }
+        for (residual_norm, true_residual_norm) in calls {
+            assert!(
+                (true_residual_norm - residual_norm).abs() < 1e-8 * (1.0 + residual_norm),
+                "Estimated and true residuals differ"
+            );
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::norm;
+use crate::error::Error;
+use crate::solver::solver_traits::Preconditioner;
+use num::Float;
+use std::collections::BTreeSet;
+
+/// Incomplete LU factorization preconditioner
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The system matrix is approximated by `L U` where `L` is unit lower triangular and `U` upper
+/// triangular, dropping fill-in during the Gaussian elimination:
+///
+/// * ILU(0) keeps the sparsity pattern of the matrix
+/// * ILUT drops entries smaller than a tolerance relative to the norm of their row and keeps at
+///   most a given number of the largest entries per row in each factor
+///
+/// The preconditioner is applied by a forward and a backward triangular solve.
+pub struct IncompleteLu<DataType> {
+    lower: CsrMatrix<DataType>,
+    upper: CsrMatrix<DataType>,
+    inverse_diagonal: Vec<DataType>,
+}
+
+impl<DataType: Scalar + Float> IncompleteLu<DataType> {
+    /// Constructor of the zero fill-in factorization ILU(0)
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix`: the square system matrix, with every diagonal entry stored
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the factorization or an error on a missing diagonal entry or a
+    ///   zero pivot
+    pub fn ilu0(matrix: &CsrMatrix<DataType>) -> Result<IncompleteLu<DataType>, Error> {
+        let matrix = matrix.to_general();
+        let n = matrix.get_number_of_rows();
+        if n != matrix.get_number_of_columns() {
+            return Err(Error::SizeMismatch {
+                context: "Matrix should be square",
+                expected: n,
+                actual: matrix.get_number_of_columns(),
+            });
+        }
+        let offsets = matrix.get_row_offsets();
+        let columns = matrix.get_column_indices();
+        let mut values = matrix.get_values().to_vec();
+        let diagonal_positions = (0..n)
+            .map(|i| {
+                matrix
+                    .get_position(i, i)
+                    .ok_or(Error::MissingEntry { row: i, column: i })
+            })
+            .collect::<Result<Vec<usize>, Error>>()?;
+        let mut markers = vec![usize::MAX; n];
+        for i in 0..n {
+            for k in offsets[i]..offsets[i + 1] {
+                markers[columns[k]] = k;
+            }
+            for k in offsets[i]..diagonal_positions[i] {
+                let pivot_row = columns[k];
+                values[k] = values[k] / values[diagonal_positions[pivot_row]];
+                let factor = values[k];
+                for m in diagonal_positions[pivot_row] + 1..offsets[pivot_row + 1] {
+                    let marker = markers[columns[m]];
+                    if marker != usize::MAX {
+                        values[marker] = values[marker] - factor * values[m];
+                    }
+                }
+            }
+            for k in offsets[i]..offsets[i + 1] {
+                markers[columns[k]] = usize::MAX;
+            }
+            if values[diagonal_positions[i]] == DataType::zero() {
+                return Err(Error::Singular("Zero pivot in the factorization"));
+            }
+        }
+        let rows = (0..n)
+            .map(|i| {
+                (offsets[i]..offsets[i + 1])
+                    .map(|k| (columns[k], values[k]))
+                    .collect()
+            })
+            .collect();
+        IncompleteLu::from_rows(rows)
+    }
+
+    /// Constructor of the threshold factorization ILUT
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix`: the square system matrix
+    /// * `maximum_fill`: the maximum number of off diagonal entries kept per row in each factor
+    /// * `drop_tolerance`: the tolerance, relative to the norm of the row, under which entries are
+    ///   dropped
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the factorization or an error on a zero pivot
+    pub fn ilut(
+        matrix: &CsrMatrix<DataType>,
+        maximum_fill: usize,
+        drop_tolerance: DataType,
+    ) -> Result<IncompleteLu<DataType>, Error> {
+        let matrix = matrix.to_general();
+        let n = matrix.get_number_of_rows();
+        if n != matrix.get_number_of_columns() {
+            return Err(Error::SizeMismatch {
+                context: "Matrix should be square",
+                expected: n,
+                actual: matrix.get_number_of_columns(),
+            });
+        }
+        let offsets = matrix.get_row_offsets();
+        let columns = matrix.get_column_indices();
+        let values = matrix.get_values();
+        let zero = DataType::zero();
+        let mut upper_rows: Vec<Vec<(usize, DataType)>> = Vec::with_capacity(n);
+        let mut rows = Vec::with_capacity(n);
+        let mut work = vec![zero; n];
+        let mut pattern = BTreeSet::new();
+        for i in 0..n {
+            let row_values = &values[offsets[i]..offsets[i + 1]];
+            let threshold = drop_tolerance * norm(row_values);
+            for k in offsets[i]..offsets[i + 1] {
+                work[columns[k]] = values[k];
+                pattern.insert(columns[k]);
+            }
+            let mut cursor = 0;
+            while let Some(&k) = pattern.range(cursor..i).next() {
+                cursor = k + 1;
+                work[k] = work[k] / upper_rows[k][0].1;
+                if work[k].abs() < threshold {
+                    work[k] = zero;
+                    continue;
+                }
+                for &(j, value) in upper_rows[k][1..].iter() {
+                    pattern.insert(j);
+                    work[j] = work[j] - work[k] * value;
+                }
+            }
+            let keep = |entries: Vec<(usize, DataType)>| -> Vec<(usize, DataType)> {
+                let mut entries: Vec<(usize, DataType)> = entries
+                    .into_iter()
+                    .filter(|(_, value)| *value != zero && value.abs() >= threshold)
+                    .collect();
+                entries.sort_by(|a, b| b.1.abs().partial_cmp(&a.1.abs()).unwrap());
+                entries.truncate(maximum_fill);
+                entries.sort_by_key(|&(j, _)| j);
+                entries
+            };
+            let lower_part = keep(pattern.range(..i).map(|&j| (j, work[j])).collect());
+            let upper_part = keep(pattern.range(i + 1..).map(|&j| (j, work[j])).collect());
+            if work[i] == zero {
+                return Err(Error::Singular("Zero pivot in the factorization"));
+            }
+            let mut upper_row = vec![(i, work[i])];
+            upper_row.extend(upper_part);
+            let mut row = lower_part;
+            row.extend(upper_row.iter().cloned());
+            upper_rows.push(upper_row);
+            rows.push(row);
+            for &j in pattern.iter() {
+                work[j] = zero;
+            }
+            pattern.clear();
+        }
+        IncompleteLu::from_rows(rows)
+    }
+
+    /// Build the factors from the sorted rows of the combined `L + U - I` matrix
+    fn from_rows(rows: Vec<Vec<(usize, DataType)>>) -> Result<IncompleteLu<DataType>, Error> {
+        let n = rows.len();
+        let mut lower = Vec::new();
+        let mut upper = Vec::new();
+        let mut inverse_diagonal = vec![DataType::zero(); n];
+        for (i, row) in rows.into_iter().enumerate() {
+            for (j, value) in row {
+                if j < i {
+                    lower.push((i, j, value));
+                } else {
+                    if j == i {
+                        inverse_diagonal[i] = value.recip();
+                    }
+                    upper.push((i, j, value));
+                }
+            }
+        }
+        Ok(IncompleteLu {
+            lower: CsrMatrix::from_triplets(n, n, &lower)?,
+            upper: CsrMatrix::from_triplets(n, n, &upper)?,
+            inverse_diagonal,
+        })
+    }
+
+    /// Get the number of stored entries of both factors
+    pub fn get_number_of_nonzeros(&self) -> usize {
+        self.lower.get_number_of_nonzeros() + self.upper.get_number_of_nonzeros()
+    }
+}
+
+impl<DataType: Scalar + Float> Preconditioner<DataType> for IncompleteLu<DataType> {
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        let n = self.inverse_diagonal.len();
+        let (offsets, columns, values) = (
+            self.lower.get_row_offsets(),
+            self.lower.get_column_indices(),
+            self.lower.get_values(),
+        );
+        for i in 0..n {
+            z[i] =
+                (offsets[i]..offsets[i + 1]).fold(r[i], |sum, k| sum - values[k] * z[columns[k]]);
+        }
+        let (offsets, columns, values) = (
+            self.upper.get_row_offsets(),
+            self.upper.get_column_indices(),
+            self.upper.get_values(),
+        );
+        for i in (0..n).rev() {
+            let sum = (offsets[i]..offsets[i + 1])
+                .filter(|&k| columns[k] > i)
+                .fold(z[i], |sum, k| sum - values[k] * z[columns[k]]);
+            z[i] = sum * self.inverse_diagonal[i];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IncompleteLu;
+    use crate::algebra::csr::CsrMatrix;
+    use crate::solver::gmres::Gmres;
+    use crate::solver::solver_traits::{IdentityPreconditioner, IterationControl, Preconditioner};
+    use crate::test_utils::convection_diffusion_system;
+
+    const TOL: f64 = 1e-8;
+
+    /// Five point convection-diffusion stencil on a `size x size` grid
+    fn grid_matrix(size: usize) -> CsrMatrix<f64> {
+        let mut triplets = Vec::new();
+        for i in 0..size {
+            for j in 0..size {
+                let row = i * size + j;
+                triplets.push((row, row, 4.0));
+                if i > 0 {
+                    triplets.push((row, row - size, -1.3));
+                }
+                if i + 1 < size {
+                    triplets.push((row, row + size, -0.7));
+                }
+                if j > 0 {
+                    triplets.push((row, row - 1, -1.2));
+                }
+                if j + 1 < size {
+                    triplets.push((row, row + 1, -0.8));
+                }
+            }
+        }
+        CsrMatrix::from_triplets(size * size, size * size, &triplets).unwrap()
+    }
+
+    #[test]
+    fn test_ilu0_exact_without_fill() {
+        let (mat, rhs, solution) = convection_diffusion_system(10);
+        let ilu = IncompleteLu::ilu0(&mat).unwrap();
+        assert_eq!(
+            ilu.get_number_of_nonzeros(),
+            mat.get_number_of_nonzeros(),
+            "Incorrect number of non zeros"
+        );
+        let mut z = vec![0.0; 10];
+        ilu.apply(&rhs, &mut z);
+        for (v, e) in z.iter().zip(solution.iter()) {
+            assert!(
+                (v - e).abs() < TOL,
+                "ILU(0) of a tridiagonal matrix is not exact"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ilut_exact_without_dropping() {
+        let mat = grid_matrix(5);
+        let solution: Vec<f64> = (0..25).map(|i| (i as f64 * 0.3).cos()).collect();
+        let rhs = mat.apply(&solution);
+        let ilut = IncompleteLu::ilut(&mat, 25, 0.0).unwrap();
+        let mut z = vec![0.0; 25];
+        ilut.apply(&rhs, &mut z);
+        for (v, e) in z.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "ILUT without dropping is not exact");
+        }
+    }
+
+    #[test]
+    fn test_preconditioned_gmres() {
+        let mat = grid_matrix(8);
+        let solution: Vec<f64> = (0..64).map(|i| (i as f64 * 0.3).sin()).collect();
+        let rhs = mat.apply(&solution);
+        let gmres = Gmres::new(IterationControl::new(1e-10, 0.0, 500), 20);
+        let mut x = vec![0.0; 64];
+        let plain = gmres.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        let ilu0 = IncompleteLu::ilu0(&mat).unwrap();
+        let mut x = vec![0.0; 64];
+        let with_ilu0 = gmres.solve(&mat, &ilu0, &rhs, &mut x);
+        assert!(with_ilu0.is_converged(), "ILU(0) GMRES did not converge");
+        assert!(
+            with_ilu0.get_iterations() < plain.get_iterations(),
+            "ILU(0) did not reduce the number of iterations"
+        );
+        let ilut = IncompleteLu::ilut(&mat, 10, 1e-3).unwrap();
+        let mut x = vec![0.0; 64];
+        let with_ilut = gmres.solve(&mat, &ilut, &rhs, &mut x);
+        assert!(with_ilut.is_converged(), "ILUT GMRES did not converge");
+        assert!(
+            with_ilut.get_iterations() <= with_ilu0.get_iterations(),
+            "ILUT did worse than ILU(0)"
+        );
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mat = CsrMatrix::from_triplets(2, 2, &[(0, 1, 1.0), (1, 0, 1.0), (1, 1, 1.0)]).unwrap();
+        assert!(
+            IncompleteLu::ilu0(&mat).is_err(),
+            "Missing diagonal accepted"
+        );
+        assert!(
+            IncompleteLu::ilut(&mat, 2, 0.0).is_err(),
+            "Zero pivot accepted"
+        );
+    }
+}
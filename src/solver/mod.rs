@@ -0,0 +1,32 @@
+/// Module for the traits shared by the solvers
+pub mod solver_traits;
+
+/// Module for the conjugate gradient solver
+pub mod cg;
+
+/// Module for the restarted generalized minimal residual solver
+pub mod gmres;
+
+/// Module for the stabilized bi-conjugate gradient solver
+pub mod bicgstab;
+
+/// Module for the Jacobi and SSOR preconditioners
+pub mod preconditioners;
+
+/// Module for the incomplete LU preconditioners
+pub mod ilu;
+
+/// Module for the sparse direct solvers
+pub mod direct;
+
+/// Module for the generalized symmetric eigensolver
+pub mod eigen;
+
+/// Module for the runtime selection of solvers and preconditioners
+pub mod registry;
+
+/// Module for the block preconditioners of mixed systems
+pub mod block;
+
+/// Module for the mixed precision solvers
+pub mod refinement;
@@ -0,0 +1,290 @@
+use crate::algebra::csr::{CsrMatrix, Storage};
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use crate::solver::solver_traits::{LinearMap, Preconditioner};
+use num::Float;
+
+/// Diagonal (Jacobi) preconditioner
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The preconditioner only needs the diagonal of the system matrix, it can then be built from an
+/// assembled matrix as well as from a diagonal assembled matrix free with
+/// `Assembler::assemble_diagonal`.
+pub struct JacobiPreconditioner<DataType> {
+    inverse_diagonal: Vec<DataType>,
+}
+
+impl<DataType: Scalar + Float> JacobiPreconditioner<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `diagonal`: the diagonal of the system matrix
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the preconditioner or an error if the diagonal has a zero entry
+    pub fn new(diagonal: &[DataType]) -> Result<JacobiPreconditioner<DataType>, Error> {
+        if diagonal.iter().any(|d| *d == DataType::zero()) {
+            return Err(Error::Singular("Zero entry on the diagonal"));
+        }
+        Ok(JacobiPreconditioner {
+            inverse_diagonal: diagonal.iter().map(|d| d.recip()).collect(),
+        })
+    }
+
+    /// Constructor from an assembled matrix
+    pub fn from_matrix(
+        matrix: &CsrMatrix<DataType>,
+    ) -> Result<JacobiPreconditioner<DataType>, Error> {
+        JacobiPreconditioner::new(&matrix.get_diagonal())
+    }
+
+    /// Constructor from any operator exposing its diagonal, such as a matrix free operator
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the preconditioner or an error if the diagonal is not available
+    ///   or has a zero entry
+    pub fn from_map<MapT: LinearMap<DataType> + ?Sized>(
+        map: &MapT,
+    ) -> Result<JacobiPreconditioner<DataType>, Error> {
+        JacobiPreconditioner::new(
+            &map.get_diagonal()
+                .ok_or(Error::InvalidArgument("Operator diagonal not available"))?,
+        )
+    }
+}
+
+impl<DataType: Scalar> Preconditioner<DataType> for JacobiPreconditioner<DataType> {
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        for ((z, &r), &d) in z.iter_mut().zip(r.iter()).zip(self.inverse_diagonal.iter()) {
+            *z = r * d;
+        }
+    }
+}
+
+/// Symmetric successive over-relaxation preconditioner
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// Splitting the matrix as `A = L + D + U`, the preconditioner is
+/// `M = (D + w L) D^{-1} (D + w U) / (w (2 - w))` for a relaxation factor `0 < w < 2`, applied by
+/// a forward and a backward triangular sweep over the assembled matrix. It is symmetric for
+/// symmetric matrices, which makes it usable with conjugate gradients, and reduces to the
+/// symmetric Gauss-Seidel preconditioner for `w = 1`. Both general and upper storages are
+/// supported.
+pub struct SsorPreconditioner<'a, DataType> {
+    matrix: &'a CsrMatrix<DataType>,
+    diagonal: Vec<DataType>,
+    omega: DataType,
+}
+
+impl<'a, DataType: Scalar + Float> SsorPreconditioner<'a, DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix`: the square system matrix
+    /// * `omega`: the relaxation factor (in `]0, 2[`)
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the preconditioner or an error if the arguments are not
+    ///   acceptable
+    pub fn new(
+        matrix: &'a CsrMatrix<DataType>,
+        omega: DataType,
+    ) -> Result<SsorPreconditioner<'a, DataType>, Error> {
+        let two = DataType::one() + DataType::one();
+        if omega <= DataType::zero() || omega >= two {
+            return Err(Error::InvalidArgument(
+                "Relaxation factor should be in ]0, 2[",
+            ));
+        }
+        if matrix.get_number_of_rows() != matrix.get_number_of_columns() {
+            return Err(Error::SizeMismatch {
+                context: "Matrix should be square",
+                expected: matrix.get_number_of_rows(),
+                actual: matrix.get_number_of_columns(),
+            });
+        }
+        let diagonal = matrix.get_diagonal();
+        if diagonal.iter().any(|d| *d == DataType::zero()) {
+            return Err(Error::Singular("Zero entry on the diagonal"));
+        }
+        Ok(SsorPreconditioner {
+            matrix,
+            diagonal,
+            omega,
+        })
+    }
+
+    /// Get the relaxation factor
+    pub fn get_omega(&self) -> DataType {
+        self.omega
+    }
+}
+
+impl<'a, DataType: Scalar + Float> Preconditioner<DataType> for SsorPreconditioner<'a, DataType> {
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        let n = self.diagonal.len();
+        let offsets = self.matrix.get_row_offsets();
+        let columns = self.matrix.get_column_indices();
+        let values = self.matrix.get_values();
+        let omega = self.omega;
+        // forward sweep (D + w L) y = r, y stored in z
+        match self.matrix.get_storage() {
+            Storage::General => {
+                for i in 0..n {
+                    let mut sum = r[i];
+                    for k in offsets[i]..offsets[i + 1] {
+                        if columns[k] < i {
+                            sum = sum - omega * values[k] * z[columns[k]];
+                        }
+                    }
+                    z[i] = sum / self.diagonal[i];
+                }
+            }
+            Storage::Upper => {
+                z.copy_from_slice(r);
+                for i in 0..n {
+                    z[i] = z[i] / self.diagonal[i];
+                    for k in offsets[i]..offsets[i + 1] {
+                        if columns[k] > i {
+                            z[columns[k]] = z[columns[k]] - omega * values[k] * z[i];
+                        }
+                    }
+                }
+            }
+        }
+        // y = D y and backward sweep (D + w U) z = y
+        z.iter_mut()
+            .zip(self.diagonal.iter())
+            .for_each(|(v, &d)| *v = *v * d);
+        for i in (0..n).rev() {
+            let mut sum = z[i];
+            for k in offsets[i]..offsets[i + 1] {
+                if columns[k] > i {
+                    sum = sum - omega * values[k] * z[columns[k]];
+                }
+            }
+            z[i] = sum / self.diagonal[i];
+        }
+        let scale = omega * (DataType::one() + DataType::one() - omega);
+        z.iter_mut().for_each(|v| *v = *v * scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JacobiPreconditioner, SsorPreconditioner};
+    use crate::algebra::csr::CsrMatrix;
+    use crate::solver::cg::ConjugateGradient;
+    use crate::solver::solver_traits::{IdentityPreconditioner, IterationControl, Preconditioner};
+    use crate::test_utils::{poisson_solution, poisson_system};
+
+    const TOL: f64 = 1e-8;
+
+    #[test]
+    fn test_jacobi() {
+        let mat = CsrMatrix::from_triplets(2, 2, &[(0, 0, 2.0), (1, 1, 4.0), (0, 1, 1.0)]).unwrap();
+        let jacobi = JacobiPreconditioner::from_matrix(&mat).unwrap();
+        let mut z = [0.0_f64; 2];
+        jacobi.apply(&[1.0, 1.0], &mut z);
+        assert!((z[0] - 0.5).abs() < TOL, "Incorrect first value");
+        assert!((z[1] - 0.25).abs() < TOL, "Incorrect second value");
+        assert!(
+            JacobiPreconditioner::new(&[1.0, 0.0]).is_err(),
+            "Zero diagonal accepted"
+        );
+    }
+
+    #[test]
+    fn test_ssor_exact_on_triangular() {
+        // for a lower triangular matrix (D + L) with w = 1, M = (D + L) D^{-1} D = A
+        let mat = CsrMatrix::from_triplets(
+            3,
+            3,
+            &[
+                (0, 0, 2.0),
+                (1, 0, 1.0),
+                (1, 1, 3.0),
+                (2, 1, -1.0),
+                (2, 2, 4.0),
+            ],
+        )
+        .unwrap();
+        let ssor = SsorPreconditioner::new(&mat, 1.0).unwrap();
+        let x = [1.0_f64, -2.0, 0.5];
+        let mut z = [0.0; 3];
+        ssor.apply(&mat.apply(&x), &mut z);
+        for (v, e) in z.iter().zip(x.iter()) {
+            assert!(
+                (v - e).abs() < TOL,
+                "SSOR is not exact on a lower triangular matrix"
+            );
+        }
+        assert!(
+            SsorPreconditioner::new(&mat, 2.0).is_err(),
+            "Relaxation factor of 2 accepted"
+        );
+    }
+
+    #[test]
+    fn test_ssor_storages() {
+        let (upper, _) = poisson_system(8);
+        let mut triplets = Vec::new();
+        for row in 0..9 {
+            for column in 0..9 {
+                let value = upper.get(row, column);
+                if value != 0.0 {
+                    triplets.push((row, column, value));
+                }
+            }
+        }
+        let general = CsrMatrix::from_triplets(9, 9, &triplets).unwrap();
+        let ssor_upper = SsorPreconditioner::new(&upper, 1.3).unwrap();
+        let ssor_general = SsorPreconditioner::new(&general, 1.3).unwrap();
+        let r: Vec<f64> = (0..9).map(|i| (i as f64).cos()).collect();
+        let mut z_upper = vec![0.0; 9];
+        let mut z_general = vec![0.0; 9];
+        ssor_upper.apply(&r, &mut z_upper);
+        ssor_general.apply(&r, &mut z_general);
+        for (u, g) in z_upper.iter().zip(z_general.iter()) {
+            assert!((u - g).abs() < TOL, "SSOR differs between storages");
+        }
+    }
+
+    #[test]
+    fn test_preconditioned_cg() {
+        let (mat, rhs) = poisson_system(32);
+        let cg = ConjugateGradient::new(IterationControl::new(1e-12, 0.0, 200));
+        let mut x = vec![0.0; rhs.len()];
+        let plain = cg.solve(&mat, &IdentityPreconditioner, &rhs, &mut x);
+        let jacobi = JacobiPreconditioner::from_matrix(&mat).unwrap();
+        let mut x = vec![0.0; rhs.len()];
+        let result = cg.solve(&mat, &jacobi, &rhs, &mut x);
+        assert!(result.is_converged(), "Jacobi CG did not converge");
+        let ssor = SsorPreconditioner::new(&mat, 1.5).unwrap();
+        let mut x = vec![0.0; rhs.len()];
+        let result = cg.solve(&mat, &ssor, &rhs, &mut x);
+        assert!(result.is_converged(), "SSOR CG did not converge");
+        assert!(
+            result.get_iterations() < plain.get_iterations(),
+            "SSOR did not reduce the number of iterations"
+        );
+        for (v, e) in x.iter().zip(poisson_solution(32).iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect solution value");
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::{norm, xpby};
+use crate::solver::solver_traits::{
+    IterationControl, LinearMap, LinearSolver, Preconditioner, SolverResult,
+};
+use crate::timer::{scope, TimerReport};
+use num::Float;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+/// Convert a slice between floating point precisions
+fn convert<FromType: Float, ToType: Float>(from: &[FromType], to: &mut [ToType]) {
+    for (to, &from) in to.iter_mut().zip(from.iter()) {
+        *to = ToType::from(from).unwrap();
+    }
+}
+
+/// Preconditioner applied in a lower precision than the solver
+///
+/// # Generics
+///
+/// * LowType: the precision the preconditioner is applied in, typically `f32`
+/// * PreconditionerT: the low precision preconditioner
+///
+/// # Explanation
+///
+/// The residual is rounded to the low precision, preconditioned and the result converted back, so
+/// that the factors (ILU, direct) take half the memory and bandwidth. The rounding makes the
+/// preconditioner slightly non linear, which does not slow down the convergence but limits the
+/// accuracy of the residual recurrences of the solver to about the unit roundoff of the low
+/// precision. To reach the full accuracy, use it in the inner solver of an `IterativeRefinement`,
+/// which recomputes the true residual in high precision.
+pub struct MixedPrecisionPreconditioner<LowType, PreconditionerT> {
+    preconditioner: PreconditionerT,
+    work: RefCell<(Vec<LowType>, Vec<LowType>)>,
+}
+
+impl<LowType: Scalar + Float, PreconditionerT: Preconditioner<LowType>>
+    MixedPrecisionPreconditioner<LowType, PreconditionerT>
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `preconditioner`: the preconditioner working in low precision
+    pub fn new(
+        preconditioner: PreconditionerT,
+    ) -> MixedPrecisionPreconditioner<LowType, PreconditionerT> {
+        MixedPrecisionPreconditioner {
+            preconditioner,
+            work: RefCell::new((Vec::new(), Vec::new())),
+        }
+    }
+}
+
+impl<HighType, LowType, PreconditionerT> Preconditioner<HighType>
+    for MixedPrecisionPreconditioner<LowType, PreconditionerT>
+where
+    HighType: Scalar + Float,
+    LowType: Scalar + Float,
+    PreconditionerT: Preconditioner<LowType>,
+{
+    fn apply(&self, r: &[HighType], z: &mut [HighType]) {
+        let mut work = self.work.borrow_mut();
+        let (low_r, low_z) = &mut *work;
+        low_r.resize(r.len(), LowType::zero());
+        low_z.resize(r.len(), LowType::zero());
+        convert(r, low_r);
+        self.preconditioner.apply(low_r, low_z);
+        convert(low_z, z);
+    }
+}
+
+/// Iterative refinement with inner solves in a lower precision
+///
+/// # Generics
+///
+/// * HighType: the precision of the solution and of the residual, typically `f64`
+/// * LowType: the precision of the inner solves, typically `f32`
+///
+/// # Explanation
+///
+/// Each outer iteration computes the residual `r = b - A x` in high precision, solves the
+/// correction equation `A d = r` to a loose tolerance in low precision with any `LinearSolver`,
+/// the operator and the preconditioner being given in low precision too, and updates
+/// `x = x + d`. The residual is scaled to unit norm before rounding so that it neither underflows
+/// nor overflows. As long as the inner solves reduce the residual by a fixed factor, the outer
+/// iterations converge to the accuracy of the high precision while the bulk of the work, and of
+/// the memory traffic, is done in low precision.
+pub struct IterativeRefinement<'a, HighType, LowType> {
+    control: IterationControl<HighType>,
+    solver: Box<dyn LinearSolver<LowType> + 'a>,
+    precision: PhantomData<LowType>,
+    report: Option<TimerReport>,
+}
+
+impl<'a, HighType, LowType> IterativeRefinement<'a, HighType, LowType>
+where
+    HighType: Scalar + Float,
+    LowType: Scalar + Float,
+{
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `control`: the stopping criterion of the outer iterations
+    /// * `solver`: the low precision solver of the correction equations, with its own control
+    pub fn new(
+        control: IterationControl<HighType>,
+        solver: impl LinearSolver<LowType> + 'a,
+    ) -> IterativeRefinement<'a, HighType, LowType> {
+        IterativeRefinement {
+            control,
+            solver: Box::new(solver),
+            precision: PhantomData,
+            report: None,
+        }
+    }
+
+    /// Record the time spent in the outer iterations in a timer report, as the
+    /// "refinement/residual" phase for the high precision residuals and the
+    /// "refinement/correction" phase for the low precision solves
+    pub fn set_timer_report(&mut self, report: &TimerReport) {
+        self.report = Some(report.clone());
+    }
+
+    /// Get the stopping criterion of the outer iterations
+    pub fn get_control(&self) -> &IterationControl<HighType> {
+        &self.control
+    }
+
+    /// Solve a linear system
+    ///
+    /// # Arguments
+    ///
+    /// * `map`: the system operator `A` in high precision
+    /// * `low_map`: the system operator in low precision, `CsrMatrix::map` converts matrices
+    /// * `preconditioner`: the preconditioner of the inner solves, in low precision
+    /// * `rhs`: the right hand side `b`
+    /// * `x`: the initial guess, overwritten by the solution
+    ///
+    /// # Returns
+    ///
+    /// * the convergence information of the outer iterations
+    pub fn solve(
+        &self,
+        map: &dyn LinearMap<HighType>,
+        low_map: &dyn LinearMap<LowType>,
+        preconditioner: &dyn Preconditioner<LowType>,
+        rhs: &[HighType],
+        x: &mut [HighType],
+    ) -> SolverResult<HighType> {
+        let n = rhs.len();
+        let target = self.control.get_target(norm(rhs));
+        let mut r = vec![HighType::zero(); n];
+        let mut low_r = vec![LowType::zero(); n];
+        let mut low_d = vec![LowType::zero(); n];
+        let mut history = Vec::new();
+        let mut initial_residual_norm = None;
+        let mut iteration = 0;
+        loop {
+            let timer = scope(&self.report, "refinement/residual");
+            map.apply(x, &mut r);
+            xpby(rhs, -HighType::one(), &mut r);
+            let residual_norm = norm(&r);
+            drop(timer);
+            let initial = *initial_residual_norm.get_or_insert(residual_norm);
+            history.push(residual_norm);
+            if residual_norm <= target {
+                return SolverResult::new(true, iteration, initial, residual_norm, history);
+            }
+            if iteration >= self.control.get_maximum_iterations() || !residual_norm.is_finite() {
+                return SolverResult::new(false, iteration, initial, residual_norm, history);
+            }
+            let _timer = scope(&self.report, "refinement/correction");
+            r.iter_mut().for_each(|v| *v = *v / residual_norm);
+            convert(&r, &mut low_r);
+            low_d.iter_mut().for_each(|v| *v = LowType::zero());
+            self.solver
+                .solve(low_map, preconditioner, &low_r, &mut low_d);
+            for (x, &d) in x.iter_mut().zip(low_d.iter()) {
+                *x = *x + residual_norm * HighType::from(d).unwrap();
+            }
+            iteration += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IterativeRefinement, MixedPrecisionPreconditioner};
+    use crate::solver::cg::ConjugateGradient;
+    use crate::solver::gmres::Gmres;
+    use crate::solver::ilu::IncompleteLu;
+    use crate::solver::preconditioners::JacobiPreconditioner;
+    use crate::solver::solver_traits::IterationControl;
+    use crate::test_utils::{convection_diffusion_system, poisson_solution, poisson_system};
+    use crate::timer::TimerReport;
+
+    #[test]
+    fn test_refinement() {
+        let (mat, rhs) = poisson_system(64);
+        let low_mat = mat.map(|v| v as f32);
+        let preconditioner = JacobiPreconditioner::from_matrix(&low_mat).unwrap();
+        let inner = ConjugateGradient::new(IterationControl::new(1e-4_f32, 0.0, 500));
+        let mut refinement = IterativeRefinement::new(IterationControl::new(1e-12, 0.0, 20), inner);
+        let report = TimerReport::new();
+        refinement.set_timer_report(&report);
+        let mut x = vec![0.0; rhs.len()];
+        let result = refinement.solve(&mat, &low_mat, &preconditioner, &rhs, &mut x);
+        assert!(
+            result.is_converged(),
+            "Iterative refinement did not converge"
+        );
+        assert!(
+            report.get_number_of_calls("refinement/residual") == result.get_iterations() + 1
+                && report.get_number_of_calls("refinement/correction") == result.get_iterations(),
+            "Incorrect timed outer iterations"
+        );
+        assert!(
+            result.get_iterations() <= 6,
+            "Iterative refinement took too many outer iterations"
+        );
+        for (v, e) in x.iter().zip(poisson_solution(64).iter()) {
+            assert!(
+                (v - e).abs() < 1e-10,
+                "Solution not recovered to double precision"
+            );
+        }
+    }
+
+    #[test]
+    fn test_low_precision_preconditioner() {
+        let (mat, rhs, solution) = convection_diffusion_system(20);
+        let low_mat = mat.map(|v| v as f32);
+        let preconditioner =
+            MixedPrecisionPreconditioner::new(IncompleteLu::ilu0(&low_mat).unwrap());
+        let inner = Gmres::new(IterationControl::new(1e-6, 0.0, 100), 30);
+        let refinement = IterativeRefinement::new(IterationControl::new(1e-13, 0.0, 20), inner);
+        let mut x = vec![0.0; rhs.len()];
+        let result = refinement.solve(&mat, &mat, &preconditioner, &rhs, &mut x);
+        assert!(
+            result.is_converged(),
+            "Iterative refinement did not converge"
+        );
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < 1e-8, "Incorrect solution value");
+        }
+    }
+}
@@ -0,0 +1,603 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::{norm, xpby};
+use crate::error::Error;
+use crate::solver::bicgstab::BiCgStab;
+use crate::solver::cg::ConjugateGradient;
+use crate::solver::direct::{SparseCholesky, SparseLu};
+use crate::solver::gmres::{Gmres, Orthogonalization};
+use crate::solver::ilu::IncompleteLu;
+use crate::solver::preconditioners::{JacobiPreconditioner, SsorPreconditioner};
+use crate::solver::solver_traits::{
+    IdentityPreconditioner, IterationControl, LinearMap, LinearSolver, Preconditioner, SolverResult,
+};
+use crate::timer::{scope, TimerReport};
+use num::Float;
+use std::collections::HashMap;
+
+/// Boxed solver built by a registry
+type BoxedSolver<DataType> = Box<dyn LinearSolver<DataType>>;
+
+/// Boxed preconditioner built by a registry, borrowing the system matrix
+type BoxedPreconditioner<'m, DataType> = Box<dyn Preconditioner<DataType> + 'm>;
+
+/// Constructor of a solver from a configuration
+type SolverFactory<DataType> =
+    Box<dyn Fn(&SolverConfiguration) -> Result<BoxedSolver<DataType>, Error>>;
+
+/// Constructor of a preconditioner from the system matrix and a configuration
+type PreconditionerFactory<DataType> = Box<
+    dyn for<'m> Fn(
+        &'m CsrMatrix<DataType>,
+        &SolverConfiguration,
+    ) -> Result<BoxedPreconditioner<'m, DataType>, Error>,
+>;
+
+/// Selection of a solver and a preconditioner with their parameters
+///
+/// # Explanation
+///
+/// A configuration can be parsed from a list of `key=value` pairs separated by commas or white
+/// spaces, as read from a command line or an input file, for instance
+/// `solver=gmres preconditioner=ilu0 restart=50 relative_tolerance=1e-10`. The `solver` and
+/// `preconditioner` keys name the entries of the registry, defaulting to `cg` and `none`. The
+/// keys of `NAMED_OPTIONS`, as `orthogonalization=cgs2`, are options taking a name, all the other
+/// keys are numerical parameters.
+#[derive(Clone, Debug)]
+pub struct SolverConfiguration {
+    solver: String,
+    preconditioner: String,
+    parameters: HashMap<String, f64>,
+    options: HashMap<String, String>,
+}
+
+/// Keys of the configuration entries whose values are names instead of numbers
+pub const NAMED_OPTIONS: [&str; 1] = ["orthogonalization"];
+
+impl SolverConfiguration {
+    /// Constructor without parameters
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the name of the solver
+    /// * `preconditioner`: the name of the preconditioner
+    pub fn new(solver: &str, preconditioner: &str) -> SolverConfiguration {
+        SolverConfiguration {
+            solver: solver.to_string(),
+            preconditioner: preconditioner.to_string(),
+            parameters: HashMap::new(),
+            options: HashMap::new(),
+        }
+    }
+
+    /// Parse a configuration from `key=value` pairs
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the configuration or an error on a malformed pair
+    pub fn parse(text: &str) -> Result<SolverConfiguration, Error> {
+        let mut configuration = SolverConfiguration::new("cg", "none");
+        for pair in text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|pair| !pair.is_empty())
+        {
+            let (key, value) = pair.split_once('=').ok_or(Error::InvalidArgument(
+                "Solver configuration entries should be key=value pairs",
+            ))?;
+            match key {
+                "solver" => configuration.solver = value.to_string(),
+                "preconditioner" => configuration.preconditioner = value.to_string(),
+                _ if NAMED_OPTIONS.contains(&key) => configuration.set_option(key, value),
+                _ => configuration.set_parameter(
+                    key,
+                    value.parse().map_err(|_| {
+                        Error::InvalidArgument("Solver parameters should be numbers")
+                    })?,
+                ),
+            }
+        }
+        Ok(configuration)
+    }
+
+    /// Set a numerical parameter
+    pub fn set_parameter(&mut self, name: &str, value: f64) {
+        self.parameters.insert(name.to_string(), value);
+    }
+
+    /// Set an option taking a name
+    pub fn set_option(&mut self, name: &str, value: &str) {
+        self.options.insert(name.to_string(), value.to_string());
+    }
+
+    /// Get the name of the solver
+    pub fn get_solver(&self) -> &str {
+        &self.solver
+    }
+
+    /// Get the name of the preconditioner
+    pub fn get_preconditioner(&self) -> &str {
+        &self.preconditioner
+    }
+
+    /// Get a numerical parameter, or a default value if it is not set
+    pub fn get_parameter(&self, name: &str, default: f64) -> f64 {
+        *self.parameters.get(name).unwrap_or(&default)
+    }
+
+    /// Get an option taking a name, or a default name if it is not set
+    pub fn get_option<'c>(&'c self, name: &str, default: &'c str) -> &'c str {
+        self.options
+            .get(name)
+            .map_or(default, |value| value.as_str())
+    }
+
+    /// Get the stopping criterion from the `relative_tolerance`, `absolute_tolerance` and
+    /// `maximum_iterations` parameters, the defaults being those of `IterationControl`
+    pub fn get_control<DataType: Scalar + Float>(&self) -> IterationControl<DataType> {
+        let default = IterationControl::<DataType>::default();
+        let get = |name: &str, value: DataType| {
+            DataType::from(self.get_parameter(name, value.to_f64().unwrap())).unwrap()
+        };
+        IterationControl::new(
+            get("relative_tolerance", default.get_relative_tolerance()),
+            get("absolute_tolerance", default.get_absolute_tolerance()),
+            self.get_parameter(
+                "maximum_iterations",
+                default.get_maximum_iterations() as f64,
+            ) as usize,
+        )
+    }
+}
+
+/// Solver applying the preconditioner once
+///
+/// # Explanation
+///
+/// Combined with a direct factorization as preconditioner this is a direct solve, which lets
+/// direct and iterative methods be selected through the same interface. The solve is reported as
+/// converged when the residual norm is finite and below the target of the stopping criterion, so
+/// that an inexact preconditioner or a factorization of a nearly singular matrix is not mistaken
+/// for a solution.
+pub struct PreconditionerOnly<DataType> {
+    control: IterationControl<DataType>,
+}
+
+impl<DataType: Scalar + Float> Default for PreconditionerOnly<DataType> {
+    fn default() -> Self {
+        PreconditionerOnly::new(IterationControl::default())
+    }
+}
+
+impl<DataType: Scalar + Float> PreconditionerOnly<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `control`: the stopping criterion, of which only the tolerances are used
+    pub fn new(control: IterationControl<DataType>) -> PreconditionerOnly<DataType> {
+        PreconditionerOnly { control }
+    }
+
+    /// Get the stopping criterion
+    pub fn get_control(&self) -> &IterationControl<DataType> {
+        &self.control
+    }
+}
+
+impl<DataType: Scalar + Float> LinearSolver<DataType> for PreconditionerOnly<DataType> {
+    fn solve(
+        &self,
+        map: &dyn LinearMap<DataType>,
+        preconditioner: &dyn Preconditioner<DataType>,
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> SolverResult<DataType> {
+        let mut r = vec![DataType::zero(); rhs.len()];
+        map.apply(x, &mut r);
+        xpby(rhs, -DataType::one(), &mut r);
+        let initial_residual_norm = norm(&r);
+        preconditioner.apply(rhs, x);
+        map.apply(x, &mut r);
+        xpby(rhs, -DataType::one(), &mut r);
+        let residual_norm = norm(&r);
+        let converged =
+            residual_norm.is_finite() && residual_norm <= self.control.get_target(norm(rhs));
+        SolverResult::new(
+            converged,
+            1,
+            initial_residual_norm,
+            residual_norm,
+            vec![initial_residual_norm, residual_norm],
+        )
+    }
+}
+
+/// Registry of the solvers and preconditioners selectable by name
+///
+/// # Generics
+///
+/// * DataType: the type of unit the systems are encoded with
+///
+/// # Explanation
+///
+/// The registry maps names to constructors taking a `SolverConfiguration`. It is created with the
+/// solvers and preconditioners of the crate and their parameters:
+///
+/// * solvers: `cg`, `gmres` (`restart`, default 30, and `orthogonalization`, `mgs` for the
+///   modified Gram-Schmidt by default or `cgs2` for the classical Gram-Schmidt twice),
+///   `bicgstab` and `preonly`
+/// * preconditioners: `none`, `jacobi`, `ssor` (`omega`, default 1), `ilu0`, `ilut`
+///   (`maximum_fill`, default 10, and `drop_tolerance`, default 1e-4), `lu` and `cholesky`
+///
+/// Applications can register their own entries under new names or replace the built-in ones.
+pub struct SolverRegistry<DataType> {
+    solvers: HashMap<String, SolverFactory<DataType>>,
+    preconditioners: HashMap<String, PreconditionerFactory<DataType>>,
+}
+
+impl<DataType: Scalar + Float + 'static> Default for SolverRegistry<DataType> {
+    fn default() -> Self {
+        SolverRegistry::new()
+    }
+}
+
+impl<DataType: Scalar + Float + 'static> SolverRegistry<DataType> {
+    /// Constructor of a registry holding the built-in solvers and preconditioners
+    pub fn new() -> SolverRegistry<DataType> {
+        let mut registry = SolverRegistry {
+            solvers: HashMap::new(),
+            preconditioners: HashMap::new(),
+        };
+        registry.register_solver("cg", |c| {
+            Ok(Box::new(ConjugateGradient::new(c.get_control())))
+        });
+        registry.register_solver("gmres", |c| {
+            let restart = c.get_parameter("restart", 30.0) as usize;
+            let mut gmres = Gmres::new(c.get_control(), restart);
+            gmres.set_orthogonalization(match c.get_option("orthogonalization", "mgs") {
+                "mgs" => Orthogonalization::ModifiedGramSchmidt,
+                "cgs2" => Orthogonalization::ClassicalGramSchmidtTwice,
+                _ => return Err(Error::InvalidArgument("Unknown orthogonalization")),
+            });
+            Ok(Box::new(gmres))
+        });
+        registry.register_solver("bicgstab", |c| Ok(Box::new(BiCgStab::new(c.get_control()))));
+        registry.register_solver("preonly", |c| {
+            Ok(Box::new(PreconditionerOnly::new(c.get_control())))
+        });
+        registry.register_preconditioner("none", |_, _| Ok(Box::new(IdentityPreconditioner)));
+        registry.register_preconditioner("jacobi", |m, _| {
+            Ok(Box::new(JacobiPreconditioner::from_matrix(m)?))
+        });
+        registry.register_preconditioner("ssor", |m, c| {
+            let omega = DataType::from(c.get_parameter("omega", 1.0)).unwrap();
+            Ok(Box::new(SsorPreconditioner::new(m, omega)?))
+        });
+        registry.register_preconditioner("ilu0", |m, _| Ok(Box::new(IncompleteLu::ilu0(m)?)));
+        registry.register_preconditioner("ilut", |m, c| {
+            let maximum_fill = c.get_parameter("maximum_fill", 10.0) as usize;
+            let drop_tolerance = DataType::from(c.get_parameter("drop_tolerance", 1e-4)).unwrap();
+            Ok(Box::new(IncompleteLu::ilut(
+                m,
+                maximum_fill,
+                drop_tolerance,
+            )?))
+        });
+        registry.register_preconditioner("lu", |m, _| Ok(Box::new(SparseLu::new(m)?)));
+        registry.register_preconditioner("cholesky", |m, _| Ok(Box::new(SparseCholesky::new(m)?)));
+        registry
+    }
+
+    /// Register a solver under a name, replacing any previous entry
+    pub fn register_solver(
+        &mut self,
+        name: &str,
+        factory: impl Fn(&SolverConfiguration) -> Result<BoxedSolver<DataType>, Error> + 'static,
+    ) {
+        self.solvers.insert(name.to_string(), Box::new(factory));
+    }
+
+    /// Register a preconditioner under a name, replacing any previous entry
+    pub fn register_preconditioner(
+        &mut self,
+        name: &str,
+        factory: impl for<'m> Fn(
+                &'m CsrMatrix<DataType>,
+                &SolverConfiguration,
+            ) -> Result<BoxedPreconditioner<'m, DataType>, Error>
+            + 'static,
+    ) {
+        self.preconditioners
+            .insert(name.to_string(), Box::new(factory));
+    }
+
+    /// Get the sorted names of the registered solvers
+    pub fn get_solver_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.solvers.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Get the sorted names of the registered preconditioners
+    pub fn get_preconditioner_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .preconditioners
+            .keys()
+            .map(|name| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Build the solver and preconditioner selected by a configuration for a system matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `configuration`: the selection of the solver and preconditioner
+    /// * `matrix`: the system matrix
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the configured solver or an error if a name is unknown or the
+    ///   construction failed
+    pub fn build<'m>(
+        &self,
+        configuration: &SolverConfiguration,
+        matrix: &'m CsrMatrix<DataType>,
+    ) -> Result<ConfiguredSolver<'m, DataType>, Error> {
+        let solver = self
+            .solvers
+            .get(configuration.get_solver())
+            .ok_or(Error::InvalidArgument("Unknown solver"))?(configuration)?;
+        let preconditioner = self
+            .preconditioners
+            .get(configuration.get_preconditioner())
+            .ok_or(Error::InvalidArgument("Unknown preconditioner"))?(
+            matrix, configuration
+        )?;
+        Ok(ConfiguredSolver {
+            matrix,
+            solver,
+            preconditioner,
+            report: None,
+        })
+    }
+}
+
+/// Solver and preconditioner bound to a system matrix
+pub struct ConfiguredSolver<'m, DataType> {
+    matrix: &'m CsrMatrix<DataType>,
+    solver: BoxedSolver<DataType>,
+    preconditioner: BoxedPreconditioner<'m, DataType>,
+    report: Option<TimerReport>,
+}
+
+impl<'m, DataType: Scalar + Float> ConfiguredSolver<'m, DataType> {
+    /// Record the time spent in the solves in a timer report, as the "solver/solve" phase
+    pub fn set_timer_report(&mut self, report: &TimerReport) {
+        self.report = Some(report.clone());
+    }
+
+    /// Solve the system for a right hand side
+    ///
+    /// # Arguments
+    ///
+    /// * `rhs`: the right hand side `b`
+    /// * `x`: the initial guess, overwritten by the solution
+    ///
+    /// # Returns
+    ///
+    /// * the convergence information of the solve
+    pub fn solve(&self, rhs: &[DataType], x: &mut [DataType]) -> SolverResult<DataType> {
+        let _timer = scope(&self.report, "solver/solve");
+        self.solver
+            .solve(self.matrix, self.preconditioner.as_ref(), rhs, x)
+    }
+
+    /// Solve the system for several right hand sides, the preconditioner being set up once
+    ///
+    /// # Arguments
+    ///
+    /// * `rhs`: the right hand sides
+    /// * `x`: the initial guesses, overwritten by the solutions
+    ///
+    /// # Returns
+    ///
+    /// * the convergence information of each solve
+    pub fn solve_multiple(
+        &self,
+        rhs: &[Vec<DataType>],
+        x: &mut [Vec<DataType>],
+    ) -> Vec<SolverResult<DataType>> {
+        let _timer = scope(&self.report, "solver/solve");
+        self.solver
+            .solve_multiple(self.matrix, self.preconditioner.as_ref(), rhs, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PreconditionerOnly, SolverConfiguration, SolverRegistry};
+    use crate::solver::solver_traits::{IterationControl, LinearSolver};
+    use crate::test_utils::{convection_diffusion_system, poisson_solution, poisson_system};
+    use crate::timer::TimerReport;
+
+    const TOL: f64 = 1e-6;
+
+    #[test]
+    fn test_parse() {
+        let configuration =
+            SolverConfiguration::parse("solver=gmres, preconditioner=ilut restart=5").unwrap();
+        assert_eq!(configuration.get_solver(), "gmres", "Incorrect solver");
+        assert_eq!(
+            configuration.get_preconditioner(),
+            "ilut",
+            "Incorrect preconditioner"
+        );
+        assert_eq!(
+            configuration.get_parameter("restart", 30.0),
+            5.0,
+            "Incorrect parameter"
+        );
+        assert_eq!(
+            configuration.get_control::<f64>().get_maximum_iterations(),
+            1000,
+            "Incorrect default control"
+        );
+        assert!(
+            SolverConfiguration::parse("restart").is_err(),
+            "Missing value accepted"
+        );
+        assert!(
+            SolverConfiguration::parse("restart=many").is_err(),
+            "Non numerical value accepted"
+        );
+        let configuration =
+            SolverConfiguration::parse("solver=gmres orthogonalization=cgs2").unwrap();
+        assert_eq!(
+            configuration.get_option("orthogonalization", "mgs"),
+            "cgs2",
+            "Incorrect option"
+        );
+    }
+
+    #[test]
+    fn test_combinations() {
+        let registry = SolverRegistry::<f64>::new();
+        let (mat, rhs) = poisson_system(20);
+        let solution = poisson_solution(20);
+        for text in [
+            "solver=cg preconditioner=none",
+            "solver=cg preconditioner=jacobi",
+            "solver=cg preconditioner=ssor omega=1.2",
+            "solver=gmres preconditioner=ilu0 restart=10",
+            "solver=gmres preconditioner=jacobi orthogonalization=cgs2",
+            "solver=bicgstab preconditioner=ilut",
+            "solver=preonly preconditioner=cholesky",
+            "solver=preonly preconditioner=lu",
+        ] {
+            let configuration = SolverConfiguration::parse(text).unwrap();
+            let solver = registry.build(&configuration, &mat).unwrap();
+            let mut x = vec![0.0; rhs.len()];
+            let result = solver.solve(&rhs, &mut x);
+            assert!(result.is_converged(), "{} did not converge", text);
+            for (v, e) in x.iter().zip(solution.iter()) {
+                assert!((v - e).abs() < TOL, "Incorrect solution for {}", text);
+            }
+        }
+        let (mat, rhs, solution) = convection_diffusion_system(20);
+        let configuration = SolverConfiguration::parse("solver=gmres preconditioner=ilu0").unwrap();
+        let mut x = vec![0.0; rhs.len()];
+        assert!(
+            registry
+                .build(&configuration, &mat)
+                .unwrap()
+                .solve(&rhs, &mut x)
+                .is_converged(),
+            "Non symmetric solve did not converge"
+        );
+        for (v, e) in x.iter().zip(solution.iter()) {
+            assert!((v - e).abs() < TOL, "Incorrect non symmetric solution");
+        }
+    }
+
+    #[test]
+    fn test_registration() {
+        let mut registry = SolverRegistry::<f64>::new();
+        let (mat, _) = poisson_system(4);
+        assert!(
+            registry
+                .build(&SolverConfiguration::new("minres", "none"), &mat)
+                .is_err(),
+            "Unknown solver accepted"
+        );
+        assert!(
+            registry
+                .build(&SolverConfiguration::new("cg", "amg"), &mat)
+                .is_err(),
+            "Unknown preconditioner accepted"
+        );
+        assert!(
+            registry
+                .build(
+                    &SolverConfiguration::parse("solver=gmres orthogonalization=householder")
+                        .unwrap(),
+                    &mat
+                )
+                .is_err(),
+            "Unknown orthogonalization accepted"
+        );
+        registry.register_preconditioner("scaled", |_, c| {
+            let factor = c.get_parameter("factor", 1.0);
+            Ok(Box::new(move |r: &[f64], z: &mut [f64]| {
+                z.iter_mut().zip(r).for_each(|(z, r)| *z = factor * r)
+            }))
+        });
+        assert!(
+            registry.get_preconditioner_names().contains(&"scaled"),
+            "Preconditioner not registered"
+        );
+        assert!(
+            registry
+                .build(&SolverConfiguration::new("cg", "scaled"), &mat)
+                .is_ok(),
+            "Registered preconditioner not built"
+        );
+    }
+
+    #[test]
+    fn test_preconditioner_only() {
+        let registry = SolverRegistry::<f64>::new();
+        let (mat, rhs) = poisson_system(20);
+        let configuration = SolverConfiguration::new("preonly", "jacobi");
+        let mut x = vec![0.0; rhs.len()];
+        assert!(
+            !registry
+                .build(&configuration, &mat)
+                .unwrap()
+                .solve(&rhs, &mut x)
+                .is_converged(),
+            "Inexact preconditioner reported as converged"
+        );
+        let solver = PreconditionerOnly::new(IterationControl::new(1e-8, 0.0, 1));
+        let nan = |_: &[f64], z: &mut [f64]| z.iter_mut().for_each(|z| *z = f64::NAN);
+        let mut x = vec![0.0; rhs.len()];
+        let result = solver.solve(&mat, &nan, &rhs, &mut x);
+        assert!(
+            !result.is_converged(),
+            "Non finite residual reported as converged"
+        );
+    }
+
+    #[test]
+    fn test_multiple_rhs() {
+        let registry = SolverRegistry::<f64>::new();
+        let (mat, rhs) = poisson_system(20);
+        let solution = poisson_solution(20);
+        let configuration = SolverConfiguration::parse("solver=cg preconditioner=ilu0").unwrap();
+        let mut solver = registry.build(&configuration, &mat).unwrap();
+        let report = TimerReport::new();
+        solver.set_timer_report(&report);
+        let loads: Vec<Vec<f64>> = (1..=3)
+            .map(|k| rhs.iter().map(|v| k as f64 * v).collect())
+            .collect();
+        let mut x = vec![vec![0.0; rhs.len()]; 3];
+        let results = solver.solve_multiple(&loads, &mut x);
+        assert_eq!(results.len(), 3, "Incorrect number of results");
+        assert_eq!(
+            report.get_number_of_calls("solver/solve"),
+            1,
+            "Incorrect timed solves"
+        );
+        for (k, (result, x)) in results.iter().zip(x.iter()).enumerate() {
+            assert!(result.is_converged(), "Load case {} did not converge", k);
+            for (v, e) in x.iter().zip(solution.iter()) {
+                assert!(
+                    (v - (k + 1) as f64 * e).abs() < TOL,
+                    "Incorrect solution of load case {}",
+                    k
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use num::Float;
+
+/// Provides the action of a linear operator on vectors
+///
+/// # Generics
+///
+/// * DataType: the type of unit the vectors are encoded with
+///
+/// # Explanation
+///
+/// Iterative solvers only need the product of the system matrix with vectors. Anything able to
+/// compute it, an assembled matrix as well as a matrix free operator, can be solved for. Operators
+/// able to compute their diagonal cheaply expose it for diagonal preconditioning.
+pub trait LinearMap<DataType> {
+    /// Get the number of rows of the operator
+    fn get_number_of_rows(&self) -> usize;
+
+    /// Get the number of columns of the operator
+    fn get_number_of_columns(&self) -> usize;
+
+    /// Compute `y = A x`
+    fn apply(&self, x: &[DataType], y: &mut [DataType]);
+
+    /// Get the diagonal of the operator if it is available
+    fn get_diagonal(&self) -> Option<Vec<DataType>> {
+        None
+    }
+}
+
+impl<DataType: Scalar> LinearMap<DataType> for CsrMatrix<DataType> {
+    fn get_number_of_rows(&self) -> usize {
+        CsrMatrix::get_number_of_rows(self)
+    }
+
+    fn get_number_of_columns(&self) -> usize {
+        CsrMatrix::get_number_of_columns(self)
+    }
+
+    fn apply(&self, x: &[DataType], y: &mut [DataType]) {
+        self.apply_into(x, y);
+    }
+
+    fn get_diagonal(&self) -> Option<Vec<DataType>> {
+        Some(CsrMatrix::get_diagonal(self))
+    }
+}
+
+/// Provides the application of an approximate inverse of a linear operator
+///
+/// # Generics
+///
+/// * DataType: the type of unit the vectors are encoded with
+///
+/// # Explanation
+///
+/// A preconditioner `M` approximates the system matrix `A` while being cheap to invert, solvers
+/// call it to compute `z = M^{-1} r`. Any closure `Fn(&[DataType], &mut [DataType])` can be used
+/// as a preconditioner.
+pub trait Preconditioner<DataType> {
+    /// Compute `z = M^{-1} r`
+    fn apply(&self, r: &[DataType], z: &mut [DataType]);
+}
+
+impl<DataType, F: Fn(&[DataType], &mut [DataType])> Preconditioner<DataType> for F {
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        self(r, z)
+    }
+}
+
+/// Solves linear systems from the action of their operator
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// This is the object safe interface shared by all the solvers, so that they can be stored and
+/// selected at runtime (see the `registry` module).
+pub trait LinearSolver<DataType> {
+    /// Solve `A x = b` preconditioned by `M`, `x` holding the initial guess and overwritten by the
+    /// solution
+    fn solve(
+        &self,
+        map: &dyn LinearMap<DataType>,
+        preconditioner: &dyn Preconditioner<DataType>,
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> SolverResult<DataType>;
+
+    /// Solve `A X = B` for several right hand sides, column by column
+    ///
+    /// The operator and the preconditioner are shared by all the solves, so that the setup of the
+    /// preconditioner (a factorization for instance) is only paid once for all the load cases.
+    ///
+    /// # Arguments
+    ///
+    /// * `map`: the system operator `A`
+    /// * `preconditioner`: the preconditioner `M`
+    /// * `rhs`: the right hand sides, the columns of `B`
+    /// * `x`: the initial guesses, overwritten by the solutions
+    ///
+    /// # Returns
+    ///
+    /// * the convergence information of each solve
+    fn solve_multiple(
+        &self,
+        map: &dyn LinearMap<DataType>,
+        preconditioner: &dyn Preconditioner<DataType>,
+        rhs: &[Vec<DataType>],
+        x: &mut [Vec<DataType>],
+    ) -> Vec<SolverResult<DataType>> {
+        rhs.iter()
+            .zip(x.iter_mut())
+            .map(|(rhs, x)| self.solve(map, preconditioner, rhs, x))
+            .collect()
+    }
+}
+
+/// The preconditioner doing nothing
+pub struct IdentityPreconditioner;
+
+impl<DataType: Copy> Preconditioner<DataType> for IdentityPreconditioner {
+    fn apply(&self, r: &[DataType], z: &mut [DataType]) {
+        z.copy_from_slice(r);
+    }
+}
+
+/// Controls the stopping criterion of iterative solvers
+///
+/// # Explanation
+///
+/// Iterations stop as soon as the residual norm is below the largest of the absolute tolerance and
+/// the relative tolerance times the norm of the right hand side, or when the maximum number of
+/// iterations is reached.
+#[derive(Clone, Copy, Debug)]
+pub struct IterationControl<DataType> {
+    relative_tolerance: DataType,
+    absolute_tolerance: DataType,
+    maximum_iterations: usize,
+}
+
+impl<DataType: Scalar + Float> Default for IterationControl<DataType> {
+    fn default() -> Self {
+        IterationControl::new(DataType::from(1e-8).unwrap(), DataType::zero(), 1000)
+    }
+}
+
+impl<DataType: Scalar + Float> IterationControl<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_tolerance`: the tolerance relative to the norm of the right hand side
+    /// * `absolute_tolerance`: the tolerance on the residual norm
+    /// * `maximum_iterations`: the maximum number of iterations
+    pub fn new(
+        relative_tolerance: DataType,
+        absolute_tolerance: DataType,
+        maximum_iterations: usize,
+    ) -> IterationControl<DataType> {
+        IterationControl {
+            relative_tolerance,
+            absolute_tolerance,
+            maximum_iterations,
+        }
+    }
+
+    /// Get the relative tolerance
+    pub fn get_relative_tolerance(&self) -> DataType {
+        self.relative_tolerance
+    }
+
+    /// Get the absolute tolerance
+    pub fn get_absolute_tolerance(&self) -> DataType {
+        self.absolute_tolerance
+    }
+
+    /// Get the maximum number of iterations
+    pub fn get_maximum_iterations(&self) -> usize {
+        self.maximum_iterations
+    }
+
+    /// Get the residual norm to reach for a right hand side of norm `rhs_norm`
+    pub fn get_target(&self, rhs_norm: DataType) -> DataType {
+        self.absolute_tolerance
+            .max(self.relative_tolerance * rhs_norm)
+    }
+}
+
+/// Outcome of an iterative solve
+#[derive(Clone, Debug)]
+pub struct SolverResult<DataType> {
+    converged: bool,
+    iterations: usize,
+    initial_residual_norm: DataType,
+    residual_norm: DataType,
+    history: Vec<DataType>,
+}
+
+impl<DataType: Copy> SolverResult<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `converged`: whether the stopping criterion was met
+    /// * `iterations`: the number of iterations performed
+    /// * `initial_residual_norm`: the norm of the residual of the initial guess
+    /// * `residual_norm`: the norm of the final residual
+    /// * `history`: the residual norms monitored at each iteration, starting with the initial one
+    pub fn new(
+        converged: bool,
+        iterations: usize,
+        initial_residual_norm: DataType,
+        residual_norm: DataType,
+        history: Vec<DataType>,
+    ) -> SolverResult<DataType> {
+        SolverResult {
+            converged,
+            iterations,
+            initial_residual_norm,
+            residual_norm,
+            history,
+        }
+    }
+
+    /// Whether the stopping criterion was met
+    pub fn is_converged(&self) -> bool {
+        self.converged
+    }
+
+    /// Get the number of iterations performed
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Get the norm of the residual of the initial guess
+    pub fn get_initial_residual_norm(&self) -> DataType {
+        self.initial_residual_norm
+    }
+
+    /// Get the norm of the final residual
+    pub fn get_residual_norm(&self) -> DataType {
+        self.residual_norm
+    }
+    /// Get the residual norms monitored at each iteration, starting with the initial one
+    pub fn get_history(&self) -> &[DataType] {
+        &self.history
+    }
+}
+
+/// Callback receiving the iteration, the monitored residual norm and the true residual norm if
+/// computed
+type IterationCallback<'a, DataType> = Box<dyn Fn(usize, DataType, Option<DataType>) + 'a>;
+
+/// Optional monitoring of the iterations of a solver
+///
+/// # Explanation
+///
+/// The iteration callback is called after each iteration, and once before the first one, with the
+/// residual norm the solver monitors for its stopping criterion. This residual is updated by
+/// recurrences, or estimated by GMRES, and may drift away from the true residual `b - A x` in
+/// finite precision: the true residual norm is also recomputed and passed to the callback when
+/// requested, at the cost of an extra product with the operator per iteration. Whether or not a
+/// callback is set, the monitored residual norms are recorded in the history of the
+/// `SolverResult`.
+pub struct SolverMonitor<'a, DataType> {
+    callback: Option<IterationCallback<'a, DataType>>,
+    true_residual: bool,
+}
+
+impl<'a, DataType: Scalar + Float> Default for SolverMonitor<'a, DataType> {
+    fn default() -> Self {
+        SolverMonitor::new()
+    }
+}
+
+impl<'a, DataType: Scalar + Float> SolverMonitor<'a, DataType> {
+    /// Constructor without callback
+    pub fn new() -> SolverMonitor<'a, DataType> {
+        SolverMonitor {
+            callback: None,
+            true_residual: false,
+        }
+    }
+
+    /// Set the callback receiving the iteration, the monitored and the true residual norms
+    pub fn set_iteration_callback(
+        &mut self,
+        callback: impl Fn(usize, DataType, Option<DataType>) + 'a,
+    ) {
+        self.callback = Some(Box::new(callback));
+    }
+
+    /// Set whether the true residual norm should be recomputed for the callback
+    pub fn set_true_residual(&mut self, true_residual: bool) {
+        self.true_residual = true_residual;
+    }
+
+    /// Whether the true residual norm is recomputed for the callback
+    pub fn is_computing_true_residual(&self) -> bool {
+        self.true_residual && self.callback.is_some()
+    }
+
+    /// Record an iteration in the history and report it to the callback
+    ///
+    /// # Arguments
+    ///
+    /// * `history`: the history the residual norm is appended to
+    /// * `iteration`: the iteration count
+    /// * `residual_norm`: the monitored residual norm
+    /// * `map`: the system operator, used for the true residual
+    /// * `rhs`: the right hand side, used for the true residual
+    /// * `x`: the current iterate, used for the true residual
+    pub fn record<MapT: LinearMap<DataType> + ?Sized>(
+        &self,
+        history: &mut Vec<DataType>,
+        iteration: usize,
+        residual_norm: DataType,
+        map: &MapT,
+        rhs: &[DataType],
+        x: &[DataType],
+    ) {
+        history.push(residual_norm);
+        if let Some(callback) = &self.callback {
+            let true_residual_norm = if self.true_residual {
+                let mut r = vec![DataType::zero(); rhs.len()];
+                map.apply(x, &mut r);
+                Some(
+                    r.iter()
+                        .zip(rhs)
+                        .fold(DataType::zero(), |sum, (&ax, &b)| sum + (b - ax) * (b - ax))
+                        .sqrt(),
+                )
+            } else {
+                None
+            };
+            callback(iteration, residual_norm, true_residual_norm);
+        }
+    }
+}
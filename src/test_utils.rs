@@ -0,0 +1,708 @@
+//! Elements and operators shared by the unit tests of the crate
+#![allow(dead_code)]
+
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::assembly::assembler::Assembler;
+use crate::assembly::cell_block::CellBlock;
+use crate::assembly::constraints::Constraints;
+use crate::element::element_traits::{Element, IntegrationRule, ShapeBasis};
+use crate::element::operator_trait::Operator;
+use crate::element::residual_trait::ResidualKernel;
+use crate::element::workspace::Workspace;
+use crate::geometry::geometry_traits::Geometry;
+use crate::post::facets::ReferenceFacets;
+use num::Float;
+use std::collections::HashMap;
+
+/// Reference segment `[-1, 1]`
+pub struct Segment;
+
+impl Geometry<f64> for Segment {
+    fn get_dimension(&self) -> usize {
+        1
+    }
+
+    fn get_number_of_elements(&self, dimension: usize) -> usize {
+        match dimension {
+            0 => 2,
+            1 => 1,
+            _ => 0,
+        }
+    }
+
+    fn get_coordinates(&self) {}
+
+    fn get_connectivity(&self, _target_dimension: usize, _represented_dimension: usize) {}
+}
+
+/// Two point Gauss-Legendre rule on the reference segment
+pub struct GaussSegment {
+    points: Vec<f64>,
+    weights: Vec<f64>,
+}
+
+impl GaussSegment {
+    pub fn new() -> GaussSegment {
+        let point = 1.0 / 3.0_f64.sqrt();
+        GaussSegment {
+            points: vec![-point, point],
+            weights: vec![1.0, 1.0],
+        }
+    }
+}
+
+impl IntegrationRule<f64, f64> for GaussSegment {
+    fn get_dimension(&self) -> usize {
+        1
+    }
+
+    fn get_weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    fn get_points(&self) -> &[f64] {
+        &self.points
+    }
+
+    fn get_number_of_points(&self) -> usize {
+        self.points.len()
+    }
+}
+
+/// Linear Lagrange basis on the reference segment
+pub struct LinearSegment;
+
+impl ShapeBasis<f64, f64> for LinearSegment {
+    fn get_dimension(&self) -> usize {
+        1
+    }
+
+    fn get_number_of_bases(&self) -> usize {
+        2
+    }
+
+    fn interpolate_basis(&self, coord: &[f64]) -> Vec<f64> {
+        vec![0.5 * (1.0 - coord[0]), 0.5 * (1.0 + coord[0])]
+    }
+
+    fn interpolate_basis_derivative(&self, _coord: &[f64]) -> Vec<f64> {
+        vec![-0.5, 0.5]
+    }
+}
+
+/// Linear Lagrange segment element
+pub struct LinearSegmentElement {
+    geometry: Segment,
+    integrator: GaussSegment,
+    basis: LinearSegment,
+    shapes: Vec<f64>,
+    shape_derivatives: Vec<f64>,
+}
+
+impl LinearSegmentElement {
+    pub fn new() -> LinearSegmentElement {
+        let integrator = GaussSegment::new();
+        let basis = LinearSegment;
+        let shapes = integrator
+            .get_points()
+            .iter()
+            .flat_map(|p| basis.interpolate_basis(&[*p]))
+            .collect();
+        let shape_derivatives = integrator
+            .get_points()
+            .iter()
+            .flat_map(|p| basis.interpolate_basis_derivative(&[*p]))
+            .collect();
+        LinearSegmentElement {
+            geometry: Segment,
+            integrator,
+            basis,
+            shapes,
+            shape_derivatives,
+        }
+    }
+}
+
+impl Element<f64, f64> for LinearSegmentElement {
+    type GeometryT = Segment;
+    type IntegratorT = GaussSegment;
+    type ShapeBasisT = LinearSegment;
+
+    fn get_geometry(&self) -> &Segment {
+        &self.geometry
+    }
+
+    fn get_integrator(&self) -> &GaussSegment {
+        &self.integrator
+    }
+
+    fn get_shape_basis(&self) -> &LinearSegment {
+        &self.basis
+    }
+
+    fn get_shapes_for_integration(&self) -> &[f64] {
+        &self.shapes
+    }
+
+    fn get_shape_derivatives_for_integration(&self) -> &[f64] {
+        &self.shape_derivatives
+    }
+
+    fn get_geometry_derivatives_for_integration(&self, coords: &[f64]) -> Vec<f64> {
+        vec![0.5 * (coords[1] - coords[0]); self.integrator.get_number_of_points()]
+    }
+}
+
+/// Reference square `[-1, 1]^2`
+pub struct Square;
+
+impl Geometry<f64> for Square {
+    fn get_dimension(&self) -> usize {
+        2
+    }
+
+    fn get_number_of_elements(&self, dimension: usize) -> usize {
+        match dimension {
+            0 | 1 => 4,
+            2 => 1,
+            _ => 0,
+        }
+    }
+
+    fn get_coordinates(&self) {}
+
+    fn get_connectivity(&self, _target_dimension: usize, _represented_dimension: usize) {}
+}
+
+/// Tensor product of two point Gauss-Legendre rules on the reference square
+pub struct GaussSquare {
+    points: Vec<f64>,
+    weights: Vec<f64>,
+}
+
+impl GaussSquare {
+    pub fn new() -> GaussSquare {
+        let point = 1.0 / 3.0_f64.sqrt();
+        GaussSquare {
+            points: vec![-point, -point, point, -point, point, point, -point, point],
+            weights: vec![1.0; 4],
+        }
+    }
+}
+
+impl IntegrationRule<f64, f64> for GaussSquare {
+    fn get_dimension(&self) -> usize {
+        2
+    }
+
+    fn get_weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    fn get_points(&self) -> &[f64] {
+        &self.points
+    }
+
+    fn get_number_of_points(&self) -> usize {
+        4
+    }
+}
+
+/// Bilinear Lagrange basis on the reference square, nodes ordered counterclockwise from `(-1, -1)`
+pub struct BilinearQuadrilateral;
+
+impl BilinearQuadrilateral {
+    pub const NODES: [[f64; 2]; 4] = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+}
+
+impl ShapeBasis<f64, f64> for BilinearQuadrilateral {
+    fn get_dimension(&self) -> usize {
+        2
+    }
+
+    fn get_number_of_bases(&self) -> usize {
+        4
+    }
+
+    fn interpolate_basis(&self, coord: &[f64]) -> Vec<f64> {
+        BilinearQuadrilateral::NODES
+            .iter()
+            .map(|n| 0.25 * (1.0 + n[0] * coord[0]) * (1.0 + n[1] * coord[1]))
+            .collect()
+    }
+
+    fn interpolate_basis_derivative(&self, coord: &[f64]) -> Vec<f64> {
+        BilinearQuadrilateral::NODES
+            .iter()
+            .flat_map(|n| {
+                [
+                    0.25 * n[0] * (1.0 + n[1] * coord[1]),
+                    0.25 * n[1] * (1.0 + n[0] * coord[0]),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Bilinear Lagrange quadrilateral element
+pub struct BilinearQuadrilateralElement {
+    geometry: Square,
+    integrator: GaussSquare,
+    basis: BilinearQuadrilateral,
+    shapes: Vec<f64>,
+    shape_derivatives: Vec<f64>,
+}
+
+impl BilinearQuadrilateralElement {
+    pub fn new() -> BilinearQuadrilateralElement {
+        let integrator = GaussSquare::new();
+        let basis = BilinearQuadrilateral;
+        let shapes = integrator
+            .get_points()
+            .chunks(2)
+            .flat_map(|p| basis.interpolate_basis(p))
+            .collect();
+        let shape_derivatives = integrator
+            .get_points()
+            .chunks(2)
+            .flat_map(|p| basis.interpolate_basis_derivative(p))
+            .collect();
+        BilinearQuadrilateralElement {
+            geometry: Square,
+            integrator,
+            basis,
+            shapes,
+            shape_derivatives,
+        }
+    }
+}
+
+impl Element<f64, f64> for BilinearQuadrilateralElement {
+    type GeometryT = Square;
+    type IntegratorT = GaussSquare;
+    type ShapeBasisT = BilinearQuadrilateral;
+
+    fn get_geometry(&self) -> &Square {
+        &self.geometry
+    }
+
+    fn get_integrator(&self) -> &GaussSquare {
+        &self.integrator
+    }
+
+    fn get_shape_basis(&self) -> &BilinearQuadrilateral {
+        &self.basis
+    }
+
+    fn get_shapes_for_integration(&self) -> &[f64] {
+        &self.shapes
+    }
+
+    fn get_shape_derivatives_for_integration(&self) -> &[f64] {
+        &self.shape_derivatives
+    }
+
+    fn get_geometry_derivatives_for_integration(&self, coords: &[f64]) -> Vec<f64> {
+        let jacobian = [
+            0.5 * (coords[2] - coords[0]),
+            0.5 * (coords[6] - coords[0]),
+            0.5 * (coords[3] - coords[1]),
+            0.5 * (coords[7] - coords[1]),
+        ];
+        jacobian.repeat(4)
+    }
+}
+
+/// Tensor product of three point Gauss-Legendre rules on the reference square
+pub struct Gauss3Square {
+    points: Vec<f64>,
+    weights: Vec<f64>,
+}
+
+impl Gauss3Square {
+    pub fn new() -> Gauss3Square {
+        let abscissae = [-(0.6_f64.sqrt()), 0.0, 0.6_f64.sqrt()];
+        let weights = [5.0 / 9.0, 8.0 / 9.0, 5.0 / 9.0];
+        let mut rule = Gauss3Square {
+            points: Vec::new(),
+            weights: Vec::new(),
+        };
+        for (&y, &wy) in abscissae.iter().zip(&weights) {
+            for (&x, &wx) in abscissae.iter().zip(&weights) {
+                rule.points.extend([x, y]);
+                rule.weights.push(wx * wy);
+            }
+        }
+        rule
+    }
+}
+
+impl IntegrationRule<f64, f64> for Gauss3Square {
+    fn get_dimension(&self) -> usize {
+        2
+    }
+
+    fn get_weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    fn get_points(&self) -> &[f64] {
+        &self.points
+    }
+
+    fn get_number_of_points(&self) -> usize {
+        9
+    }
+}
+
+/// Biquadratic Lagrange basis on the reference square, the corners ordered counterclockwise from
+/// `(-1, -1)` followed by the midpoints of the edges from the bottom one and by the centre
+pub struct BiquadraticQuadrilateral;
+
+impl BiquadraticQuadrilateral {
+    pub const NODES: [[f64; 2]; 9] = [
+        [-1.0, -1.0],
+        [1.0, -1.0],
+        [1.0, 1.0],
+        [-1.0, 1.0],
+        [0.0, -1.0],
+        [1.0, 0.0],
+        [0.0, 1.0],
+        [-1.0, 0.0],
+        [0.0, 0.0],
+    ];
+
+    /// Quadratic Lagrange polynomial of the node `node` of `{-1, 0, 1}` and its derivative
+    fn lagrange(node: f64, x: f64) -> (f64, f64) {
+        if node < -0.5 {
+            (0.5 * x * (x - 1.0), x - 0.5)
+        } else if node > 0.5 {
+            (0.5 * x * (x + 1.0), x + 0.5)
+        } else {
+            (1.0 - x * x, -2.0 * x)
+        }
+    }
+}
+
+impl ShapeBasis<f64, f64> for BiquadraticQuadrilateral {
+    fn get_dimension(&self) -> usize {
+        2
+    }
+
+    fn get_number_of_bases(&self) -> usize {
+        9
+    }
+
+    fn interpolate_basis(&self, coord: &[f64]) -> Vec<f64> {
+        BiquadraticQuadrilateral::NODES
+            .iter()
+            .map(|n| {
+                BiquadraticQuadrilateral::lagrange(n[0], coord[0]).0
+                    * BiquadraticQuadrilateral::lagrange(n[1], coord[1]).0
+            })
+            .collect()
+    }
+
+    fn interpolate_basis_derivative(&self, coord: &[f64]) -> Vec<f64> {
+        BiquadraticQuadrilateral::NODES
+            .iter()
+            .flat_map(|n| {
+                let (lx, dx) = BiquadraticQuadrilateral::lagrange(n[0], coord[0]);
+                let (ly, dy) = BiquadraticQuadrilateral::lagrange(n[1], coord[1]);
+                [dx * ly, lx * dy]
+            })
+            .collect()
+    }
+}
+
+/// Biquadratic Lagrange quadrilateral element
+pub struct BiquadraticQuadrilateralElement {
+    geometry: Square,
+    integrator: Gauss3Square,
+    basis: BiquadraticQuadrilateral,
+    shapes: Vec<f64>,
+    shape_derivatives: Vec<f64>,
+}
+
+impl BiquadraticQuadrilateralElement {
+    pub fn new() -> BiquadraticQuadrilateralElement {
+        let integrator = Gauss3Square::new();
+        let basis = BiquadraticQuadrilateral;
+        let shapes = integrator
+            .get_points()
+            .chunks(2)
+            .flat_map(|p| basis.interpolate_basis(p))
+            .collect();
+        let shape_derivatives = integrator
+            .get_points()
+            .chunks(2)
+            .flat_map(|p| basis.interpolate_basis_derivative(p))
+            .collect();
+        BiquadraticQuadrilateralElement {
+            geometry: Square,
+            integrator,
+            basis,
+            shapes,
+            shape_derivatives,
+        }
+    }
+}
+
+impl Element<f64, f64> for BiquadraticQuadrilateralElement {
+    type GeometryT = Square;
+    type IntegratorT = Gauss3Square;
+    type ShapeBasisT = BiquadraticQuadrilateral;
+
+    fn get_geometry(&self) -> &Square {
+        &self.geometry
+    }
+
+    fn get_integrator(&self) -> &Gauss3Square {
+        &self.integrator
+    }
+
+    fn get_shape_basis(&self) -> &BiquadraticQuadrilateral {
+        &self.basis
+    }
+
+    fn get_shapes_for_integration(&self) -> &[f64] {
+        &self.shapes
+    }
+
+    fn get_shape_derivatives_for_integration(&self) -> &[f64] {
+        &self.shape_derivatives
+    }
+
+    fn get_geometry_derivatives_for_integration(&self, coords: &[f64]) -> Vec<f64> {
+        let jacobian = [
+            0.5 * (coords[2] - coords[0]),
+            0.5 * (coords[6] - coords[0]),
+            0.5 * (coords[3] - coords[1]),
+            0.5 * (coords[7] - coords[1]),
+        ];
+        jacobian.repeat(9)
+    }
+}
+
+/// Stiffness matrix of `-d/dx(k du/dx)` on linear segments, `k` read from the "conductivity" data
+/// when present
+pub struct Laplacian;
+
+impl Operator<f64, f64> for Laplacian {
+    type ElementT = LinearSegmentElement;
+
+    fn compute(&self, geometry: &[f64], data: &HashMap<String, &[f64]>) -> Vec<f64> {
+        let mut local = Vec::new();
+        self.compute_into(geometry, data, &mut Workspace::new(), &mut local);
+        local
+    }
+
+    fn compute_into(
+        &self,
+        geometry: &[f64],
+        data: &HashMap<String, &[f64]>,
+        _workspace: &mut Workspace<f64>,
+        local: &mut Vec<f64>,
+    ) {
+        let k = data.get("conductivity").map_or(1.0, |values| values[0]);
+        let stiffness = k / (geometry[1] - geometry[0]);
+        local.clear();
+        local.extend_from_slice(&[stiffness, -stiffness, -stiffness, stiffness]);
+    }
+
+    fn compute_batch<const LANES: usize>(
+        &self,
+        geometry: &[[f64; LANES]],
+        data: &HashMap<String, &[[f64; LANES]]>,
+    ) -> Vec<[f64; LANES]> {
+        let k = data
+            .get("conductivity")
+            .map_or([1.0; LANES], |values| values[0]);
+        let mut stiffness = [0.0; LANES];
+        for lane in 0..LANES {
+            stiffness[lane] = k[lane] / (geometry[1][lane] - geometry[0][lane]);
+        }
+        let opposite = stiffness.map(|s| -s);
+        vec![stiffness, opposite, opposite, stiffness]
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Residual of `-d/dx(du/dx) + u^3` on linear segments with a one point quadrature, the tangent
+/// at the null state being the Laplacian above
+pub struct CubicReaction;
+
+impl ResidualKernel<f64, f64> for CubicReaction {
+    type ElementT = LinearSegmentElement;
+
+    fn get_number_of_dofs(&self) -> usize {
+        2
+    }
+
+    fn compute_residual<ScalarT: Scalar + Float + From<f64>>(
+        &self,
+        geometry: &[f64],
+        _data: &HashMap<String, &[f64]>,
+        state: &[ScalarT],
+    ) -> Vec<ScalarT> {
+        let h: ScalarT = (geometry[1] - geometry[0]).into();
+        let half: ScalarT = 0.5.into();
+        let flux = (state[1] - state[0]) / h;
+        let source = half * h * (half * (state[0] + state[1])).powi(3);
+        vec![source - flux, source + flux]
+    }
+}
+
+/// Upwinded advection matrix of `du/dx` on linear segments
+pub struct Advection;
+
+impl Operator<f64, f64> for Advection {
+    type ElementT = LinearSegmentElement;
+
+    fn compute(&self, _geometry: &[f64], _data: &HashMap<String, &[f64]>) -> Vec<f64> {
+        vec![0.0, 0.0, -1.0, 1.0]
+    }
+}
+
+/// Uniform mesh of `number_of_cells` linear segments on `[0, 1]`
+///
+/// # Returns
+///
+/// * the cell to dof connectivity and the cell coordinates, both flattened
+pub fn uniform_segments(number_of_cells: usize) -> (Vec<usize>, Vec<f64>) {
+    let h = 1.0 / number_of_cells as f64;
+    let dofs = (0..number_of_cells).flat_map(|c| [c, c + 1]).collect();
+    let coords = (0..number_of_cells)
+        .flat_map(|c| [c as f64 * h, (c + 1) as f64 * h])
+        .collect();
+    (dofs, coords)
+}
+
+/// Uniform mesh of `n` by `n` bilinear quadrilaterals on `[0, 1]^2`, the node `(i, j)` being the
+/// dof `j (n + 1) + i` and the cells ordered row after row
+///
+/// # Returns
+///
+/// * the cell to dof connectivity and the cell coordinates, both flattened
+pub fn uniform_quadrilaterals(n: usize) -> (Vec<usize>, Vec<f64>) {
+    let h = 1.0 / n as f64;
+    let corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+    let mut dofs = Vec::new();
+    let mut coords = Vec::new();
+    for j in 0..n {
+        for i in 0..n {
+            for (di, dj) in corners {
+                dofs.push((j + dj) * (n + 1) + i + di);
+                coords.extend([(i + di) as f64 * h, (j + dj) as f64 * h]);
+            }
+        }
+    }
+    (dofs, coords)
+}
+
+/// Uniform mesh of `n` by `n` biquadratic quadrilaterals on `[0, 1]^2`, the node `(i, j)` of the
+/// grid of `2 n + 1` by `2 n + 1` nodes being the dof `j (2 n + 1) + i` and the cells ordered row
+/// after row as in uniform_quadrilaterals
+///
+/// # Returns
+///
+/// * the cell to dof connectivity and the cell coordinates, both flattened
+pub fn uniform_biquadratic_quadrilaterals(n: usize) -> (Vec<usize>, Vec<f64>) {
+    let h = 0.5 / n as f64;
+    let mut dofs = Vec::new();
+    let mut coords = Vec::new();
+    for j in 0..n {
+        for i in 0..n {
+            for node in BiquadraticQuadrilateral::NODES {
+                let di = (node[0] + 1.0) as usize;
+                let dj = (node[1] + 1.0) as usize;
+                dofs.push((2 * j + dj) * (2 * n + 1) + 2 * i + di);
+                coords.extend([(2 * i + di) as f64 * h, (2 * j + dj) as f64 * h]);
+            }
+        }
+    }
+    (dofs, coords)
+}
+
+/// Reference coordinates of the nodes of the bilinear quadrilateral in AOS ordering
+pub fn quadrilateral_corners() -> Vec<f64> {
+    BilinearQuadrilateral::NODES.concat()
+}
+
+/// Reference facets of the bilinear quadrilateral, its edges counterclockwise from the bottom one
+pub fn quadrilateral_facets() -> ReferenceFacets<f64> {
+    let edges = vec![vec![0, 1], vec![1, 2], vec![2, 3], vec![3, 0]];
+    ReferenceFacets::new(
+        &BilinearQuadrilateralElement::new(),
+        &quadrilateral_corners(),
+        edges,
+    )
+    .unwrap()
+}
+
+/// Reference facets of the biquadratic quadrilateral, its edges listing their midpoint between
+/// their vertices counterclockwise from the bottom one
+pub fn biquadratic_quadrilateral_facets() -> ReferenceFacets<f64> {
+    let edges = vec![vec![0, 4, 1], vec![1, 5, 2], vec![2, 6, 3], vec![3, 7, 0]];
+    ReferenceFacets::new(
+        &BiquadraticQuadrilateralElement::new(),
+        &BiquadraticQuadrilateral::NODES.concat(),
+        edges,
+    )
+    .unwrap()
+}
+
+/// Condensed system of `-u'' = 1` on `[0, 1]` with `u(0) = u(1) = 0` on uniform linear segments
+///
+/// # Returns
+///
+/// * the symmetric matrix stored in upper storage and the right hand side
+pub fn poisson_system(number_of_cells: usize) -> (CsrMatrix<f64>, Vec<f64>) {
+    let (dofs, coords) = uniform_segments(number_of_cells);
+    let block = CellBlock::new(2, &dofs, &coords).unwrap();
+    let mut constraints = Constraints::new();
+    constraints.add_dirichlet(0, 0.0).unwrap();
+    constraints.add_dirichlet(number_of_cells, 0.0).unwrap();
+    let (mat, _) = Assembler::new(number_of_cells + 1)
+        .assemble_constrained(&Laplacian, &block, &constraints)
+        .unwrap();
+    let mut rhs = vec![1.0 / number_of_cells as f64; number_of_cells + 1];
+    constraints.condense(&mut rhs);
+    (mat, rhs)
+}
+
+/// Nodal values of the solution `x (1 - x) / 2` of the poisson_system above
+pub fn poisson_solution(number_of_cells: usize) -> Vec<f64> {
+    (0..=number_of_cells)
+        .map(|i| {
+            let x = i as f64 / number_of_cells as f64;
+            0.5 * x * (1.0 - x)
+        })
+        .collect()
+}
+
+/// Non symmetric tridiagonal system of a centered convection-diffusion discretization
+///
+/// # Returns
+///
+/// * the matrix, the right hand side and the solution of the system
+pub fn convection_diffusion_system(size: usize) -> (CsrMatrix<f64>, Vec<f64>, Vec<f64>) {
+    let mut triplets = Vec::new();
+    for i in 0..size {
+        triplets.push((i, i, 2.0));
+        if i > 0 {
+            triplets.push((i, i - 1, -1.4));
+        }
+        if i + 1 < size {
+            triplets.push((i, i + 1, -0.6));
+        }
+    }
+    let mat = CsrMatrix::from_triplets(size, size, &triplets).unwrap();
+    let solution: Vec<f64> = (0..size).map(|i| (i as f64 * 0.7).sin() + 1.0).collect();
+    let rhs = mat.apply(&solution);
+    (mat, rhs, solution)
+}
@@ -0,0 +1,607 @@
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use crate::time::explicit::{ButcherTableau, ExplicitRungeKutta};
+use crate::time::time_traits::RateFunction;
+use crate::timer::{scope, TimerReport};
+use num::Float;
+
+/// Tolerances and bounds of an adaptive time integration
+///
+/// # Explanation
+///
+/// The local error estimate `e` of a step from `u` to `û` is measured with the weighted root mean
+/// square norm `sqrt(1/n sum_i (e_i / (atol + rtol max(|u_i|, |û_i|)))²)`, a step being accepted
+/// when the norm is at most one. The time steps are kept between the minimum and maximum steps,
+/// the integration failing if the controller requests a step below the minimum or if the maximum
+/// number of steps is exceeded.
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveControl<DataType> {
+    relative_tolerance: DataType,
+    absolute_tolerance: DataType,
+    initial_step: DataType,
+    minimum_step: DataType,
+    maximum_step: DataType,
+    maximum_steps: usize,
+}
+
+impl<DataType: Scalar + Float> AdaptiveControl<DataType> {
+    /// Constructor without step bounds
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_tolerance`: the tolerance on the local error relative to the state
+    /// * `absolute_tolerance`: the tolerance on the local error
+    /// * `initial_step`: the first time step attempted
+    pub fn new(
+        relative_tolerance: DataType,
+        absolute_tolerance: DataType,
+        initial_step: DataType,
+    ) -> AdaptiveControl<DataType> {
+        AdaptiveControl {
+            relative_tolerance,
+            absolute_tolerance,
+            initial_step,
+            minimum_step: DataType::zero(),
+            maximum_step: DataType::infinity(),
+            maximum_steps: 100000,
+        }
+    }
+
+    /// Set the bounds of the time step
+    pub fn set_step_bounds(&mut self, minimum_step: DataType, maximum_step: DataType) {
+        self.minimum_step = minimum_step;
+        self.maximum_step = maximum_step;
+    }
+
+    /// Set the maximum number of accepted steps
+    pub fn set_maximum_steps(&mut self, maximum_steps: usize) {
+        self.maximum_steps = maximum_steps;
+    }
+
+    /// Get the tolerance on the local error relative to the state
+    pub fn get_relative_tolerance(&self) -> DataType {
+        self.relative_tolerance
+    }
+
+    /// Get the tolerance on the local error
+    pub fn get_absolute_tolerance(&self) -> DataType {
+        self.absolute_tolerance
+    }
+
+    /// Get the first time step attempted
+    pub fn get_initial_step(&self) -> DataType {
+        self.initial_step
+    }
+
+    /// Get the bounds `(minimum, maximum)` of the time step
+    pub fn get_step_bounds(&self) -> (DataType, DataType) {
+        (self.minimum_step, self.maximum_step)
+    }
+
+    /// Get the maximum number of accepted steps
+    pub fn get_maximum_steps(&self) -> usize {
+        self.maximum_steps
+    }
+
+    /// Compute the weighted norm of a local error estimate
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: the local error estimate
+    /// * `previous`: the state at the beginning of the step
+    /// * `next`: the state at the end of the step
+    pub fn get_error_norm(
+        &self,
+        error: &[DataType],
+        previous: &[DataType],
+        next: &[DataType],
+    ) -> DataType {
+        if error.is_empty() {
+            return DataType::zero();
+        }
+        let sum = error.iter().zip(previous.iter().zip(next)).fold(
+            DataType::zero(),
+            |sum, (&e, (&u, &v))| {
+                let scale =
+                    self.absolute_tolerance + self.relative_tolerance * u.abs().max(v.abs());
+                let weighted = if e == DataType::zero() {
+                    DataType::zero()
+                } else {
+                    e / scale
+                };
+                sum + weighted * weighted
+            },
+        );
+        (sum / DataType::from(error.len()).unwrap()).sqrt()
+    }
+}
+
+/// Proportional-integral controller of the time step
+///
+/// # Explanation
+///
+/// After an accepted step of error norm `e_n`, the next step is scaled by
+/// `safety * e_n^{-(k_I + k_P) / k} * e_{n-1}^{k_P / k}`, where `k` is the order of the error
+/// estimate plus one. The proportional term, using the error of the previous accepted step,
+/// smooths the step sequence and avoids the oscillations of the purely integral (elementary)
+/// controller on stiff problems. After a rejected step the step is reduced with the integral term
+/// only. The factors are clamped between the minimum and maximum factors.
+#[derive(Clone, Copy, Debug)]
+pub struct PiController<DataType> {
+    safety: DataType,
+    minimum_factor: DataType,
+    maximum_factor: DataType,
+    integral_gain: DataType,
+    proportional_gain: DataType,
+}
+
+impl<DataType: Scalar + Float> Default for PiController<DataType> {
+    fn default() -> Self {
+        PiController::new()
+    }
+}
+
+impl<DataType: Scalar + Float> PiController<DataType> {
+    /// Constructor with the gains `k_I = 0.3` and `k_P = 0.4`, a safety factor of 0.9 and factors
+    /// between 0.2 and 5
+    pub fn new() -> PiController<DataType> {
+        let convert = |value: f64| DataType::from(value).unwrap();
+        PiController {
+            safety: convert(0.9),
+            minimum_factor: convert(0.2),
+            maximum_factor: convert(5.0),
+            integral_gain: convert(0.3),
+            proportional_gain: convert(0.4),
+        }
+    }
+
+    /// Set the gains of the controller, a zero proportional gain giving the elementary controller
+    pub fn set_gains(&mut self, integral_gain: DataType, proportional_gain: DataType) {
+        self.integral_gain = integral_gain;
+        self.proportional_gain = proportional_gain;
+    }
+
+    /// Set the safety factor applied to the optimal step
+    pub fn set_safety(&mut self, safety: DataType) {
+        self.safety = safety;
+    }
+
+    /// Set the bounds of the factor between two consecutive steps
+    pub fn set_factor_bounds(&mut self, minimum_factor: DataType, maximum_factor: DataType) {
+        self.minimum_factor = minimum_factor;
+        self.maximum_factor = maximum_factor;
+    }
+
+    /// Get the factor of the next step after an accepted step
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: the error norm of the accepted step
+    /// * `previous_error`: the error norm of the previous accepted step
+    /// * `order`: the order of the error estimate
+    pub fn get_factor(&self, error: DataType, previous_error: DataType, order: usize) -> DataType {
+        let k = DataType::from(order + 1).unwrap();
+        let factor = self.safety
+            * error.powf(-(self.integral_gain + self.proportional_gain) / k)
+            * previous_error.powf(self.proportional_gain / k);
+        factor.max(self.minimum_factor).min(self.maximum_factor)
+    }
+
+    /// Get the factor of the retried step after a rejected step
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: the error norm of the rejected step
+    /// * `order`: the order of the error estimate
+    pub fn get_rejection_factor(&self, error: DataType, order: usize) -> DataType {
+        let k = DataType::from(order + 1).unwrap();
+        let factor = self.safety * error.powf(-DataType::one() / k);
+        factor.max(self.minimum_factor).min(DataType::one())
+    }
+}
+
+/// Outcome of an adaptive time integration
+#[derive(Clone, Debug)]
+pub struct AdaptiveResult<DataType> {
+    accepted_steps: usize,
+    rejected_steps: usize,
+    next_step: DataType,
+}
+
+impl<DataType: Copy> AdaptiveResult<DataType> {
+    /// Get the number of accepted steps
+    pub fn get_number_of_accepted_steps(&self) -> usize {
+        self.accepted_steps
+    }
+
+    /// Get the number of rejected steps
+    pub fn get_number_of_rejected_steps(&self) -> usize {
+        self.rejected_steps
+    }
+
+    /// Get the step proposed by the controller to continue the integration
+    pub fn get_next_step(&self) -> DataType {
+        self.next_step
+    }
+}
+
+/// Explicit Runge-Kutta time integrator with embedded error control
+///
+/// # Generics
+///
+/// * DataType: the type of unit the state is encoded with
+///
+/// # Explanation
+///
+/// Each step computes the solution of the method and the local error estimate
+/// `dt sum_i (b_i - b̂_i) k_i` from the embedded weights of the tableau, and continues with the
+/// higher order solution (local extrapolation).
+pub struct AdaptiveRungeKutta<DataType> {
+    integrator: ExplicitRungeKutta<DataType>,
+    control: AdaptiveControl<DataType>,
+    controller: PiController<DataType>,
+    report: Option<TimerReport>,
+}
+
+impl<DataType: Scalar + Float> AdaptiveRungeKutta<DataType> {
+    /// Constructor with the default controller
+    ///
+    /// # Arguments
+    ///
+    /// * `tableau`: the coefficients of an explicit embedded pair
+    /// * `control`: the tolerances and bounds of the integration
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the integrator or an error if the method is not explicit or has
+    ///   no embedded weights
+    pub fn new(
+        tableau: ButcherTableau<DataType>,
+        control: AdaptiveControl<DataType>,
+    ) -> Result<AdaptiveRungeKutta<DataType>, Error> {
+        if tableau.get_embedded_weights().is_none() {
+            return Err(Error::InvalidArgument(
+                "Runge-Kutta method should have embedded weights",
+            ));
+        }
+        Ok(AdaptiveRungeKutta {
+            integrator: ExplicitRungeKutta::new(tableau)?,
+            control,
+            controller: PiController::new(),
+            report: None,
+        })
+    }
+
+    /// Set the step size controller
+    pub fn set_controller(&mut self, controller: PiController<DataType>) {
+        self.controller = controller;
+    }
+
+    /// Record the time spent in each attempted step in a timer report, as the "adaptive/step"
+    /// phase
+    pub fn set_timer_report(&mut self, report: &TimerReport) {
+        self.report = Some(report.clone());
+    }
+
+    /// Get the tolerances and bounds of the integration
+    pub fn get_control(&self) -> &AdaptiveControl<DataType> {
+        &self.control
+    }
+
+    /// Advance the state over an interval with adaptive time steps
+    ///
+    /// # Arguments
+    ///
+    /// * `rate`: the rate of the system
+    /// * `start`: the initial time
+    /// * `end`: the final time
+    /// * `u`: the initial state, overwritten by the final state
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the step statistics or an error if the time step fell below the
+    ///   minimum step or the maximum number of steps was exceeded
+    pub fn integrate<RateT: RateFunction<DataType> + ?Sized>(
+        &self,
+        rate: &RateT,
+        start: DataType,
+        end: DataType,
+        u: &mut [DataType],
+    ) -> Result<AdaptiveResult<DataType>, Error> {
+        let tableau = self.integrator.get_tableau();
+        let weights = tableau.get_b();
+        let embedded = tableau.get_embedded_weights().unwrap();
+        let order = tableau.get_embedded_order().unwrap();
+        let mut error = vec![DataType::zero(); u.len()];
+        integrate_adaptive(
+            &self.control,
+            &self.controller,
+            order,
+            start,
+            end,
+            u,
+            |time, dt, u, next| {
+                let _timer = scope(&self.report, "adaptive/step");
+                let stages = self.integrator.compute_stages(rate, time, dt, u);
+                next.copy_from_slice(u);
+                error.fill(DataType::zero());
+                for ((k, &b), &embedded) in stages.iter().zip(weights).zip(embedded) {
+                    for ((v, e), &k) in next.iter_mut().zip(error.iter_mut()).zip(k) {
+                        *v = *v + dt * b * k;
+                        *e = *e + dt * (b - embedded) * k;
+                    }
+                }
+                Ok(self.control.get_error_norm(&error, u, next))
+            },
+        )
+    }
+}
+
+/// Time integrator with error control by step doubling
+///
+/// # Generics
+///
+/// * DataType: the type of unit the state is encoded with
+///
+/// # Explanation
+///
+/// Each step is computed twice, once with the full step and once with two half steps, the
+/// difference `(u_half - u_full) / (2^p - 1)` estimating the local error of the half steps for a
+/// method of order `p`; the integration continues with the half steps. Any one step method can
+/// be controlled, in particular the implicit Euler and Crank-Nicolson schemes of an
+/// `ImplicitIntegrator` for stiff problems, through a closure advancing the state. Methods keeping
+/// a state between the steps (BDF2, generalized-α) are not suited as rejected steps would pollute
+/// it. The shifted systems of implicit schemes change at every step size, an integrator rebuilding
+/// its preconditioner for each of them.
+pub struct StepDoubling<DataType> {
+    order: usize,
+    control: AdaptiveControl<DataType>,
+    controller: PiController<DataType>,
+    report: Option<TimerReport>,
+}
+
+impl<DataType: Scalar + Float> StepDoubling<DataType> {
+    /// Constructor with the default controller
+    ///
+    /// # Arguments
+    ///
+    /// * `order`: the order of the controlled method
+    /// * `control`: the tolerances and bounds of the integration
+    pub fn new(order: usize, control: AdaptiveControl<DataType>) -> StepDoubling<DataType> {
+        StepDoubling {
+            order,
+            control,
+            controller: PiController::new(),
+            report: None,
+        }
+    }
+
+    /// Set the step size controller
+    pub fn set_controller(&mut self, controller: PiController<DataType>) {
+        self.controller = controller;
+    }
+
+    /// Record the time spent in each attempted step in a timer report, as the "adaptive/step"
+    /// phase
+    pub fn set_timer_report(&mut self, report: &TimerReport) {
+        self.report = Some(report.clone());
+    }
+
+    /// Get the tolerances and bounds of the integration
+    pub fn get_control(&self) -> &AdaptiveControl<DataType> {
+        &self.control
+    }
+
+    /// Advance the state over an interval with adaptive time steps
+    ///
+    /// # Arguments
+    ///
+    /// * `step`: advances the state `u` from the time `t` by the step `dt` with the signature
+    ///   `(t, dt, u)`
+    /// * `start`: the initial time
+    /// * `end`: the final time
+    /// * `u`: the initial state, overwritten by the final state
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the step statistics or an error if a step failed, the time step
+    ///   fell below the minimum step or the maximum number of steps was exceeded
+    pub fn integrate(
+        &self,
+        mut step: impl FnMut(DataType, DataType, &mut [DataType]) -> Result<(), Error>,
+        start: DataType,
+        end: DataType,
+        u: &mut [DataType],
+    ) -> Result<AdaptiveResult<DataType>, Error> {
+        let one = DataType::one();
+        let denominator = DataType::from(2.0).unwrap().powi(self.order as i32) - one;
+        let mut full = vec![DataType::zero(); u.len()];
+        let mut error = vec![DataType::zero(); u.len()];
+        integrate_adaptive(
+            &self.control,
+            &self.controller,
+            self.order,
+            start,
+            end,
+            u,
+            |time, dt, u, next| {
+                let _timer = scope(&self.report, "adaptive/step");
+                let half = dt / (one + one);
+                full.copy_from_slice(u);
+                step(time, dt, &mut full)?;
+                next.copy_from_slice(u);
+                step(time, half, next)?;
+                step(time + half, half, next)?;
+                for ((e, &v), &w) in error.iter_mut().zip(next.iter()).zip(&full) {
+                    *e = (v - w) / denominator;
+                }
+                Ok(self.control.get_error_norm(&error, u, next))
+            },
+        )
+    }
+}
+
+/// Advance the state with the steps proposed by a controller
+///
+/// The attempt computes a candidate state from the time, the step and the state and returns the
+/// error norm of the candidate.
+fn integrate_adaptive<DataType: Scalar + Float>(
+    control: &AdaptiveControl<DataType>,
+    controller: &PiController<DataType>,
+    order: usize,
+    start: DataType,
+    end: DataType,
+    u: &mut [DataType],
+    mut attempt: impl FnMut(DataType, DataType, &[DataType], &mut [DataType]) -> Result<DataType, Error>,
+) -> Result<AdaptiveResult<DataType>, Error> {
+    let (minimum_step, maximum_step) = control.get_step_bounds();
+    let mut time = start;
+    let mut dt = control.get_initial_step().min(maximum_step);
+    let mut previous_error = DataType::one();
+    let mut next = vec![DataType::zero(); u.len()];
+    let (mut accepted_steps, mut rejected_steps) = (0, 0);
+    while time < end {
+        if accepted_steps == control.get_maximum_steps() {
+            return Err(Error::NotConverged("Maximum number of time steps exceeded"));
+        }
+        let last = time + dt >= end;
+        let step = if last { end - time } else { dt };
+        let error = attempt(time, step, u, &mut next)?;
+        let factor = if error <= DataType::one() {
+            u.copy_from_slice(&next);
+            time = if last { end } else { time + step };
+            accepted_steps += 1;
+            let factor = controller.get_factor(error, previous_error, order);
+            previous_error = error.max(DataType::from(1e-4).unwrap());
+            factor
+        } else {
+            rejected_steps += 1;
+            controller.get_rejection_factor(error, order)
+        };
+        dt = (step * factor).min(maximum_step);
+        if time < end && (dt < minimum_step || time + dt == time) {
+            return Err(Error::NotConverged("Time step below the minimum step"));
+        }
+    }
+    Ok(AdaptiveResult {
+        accepted_steps,
+        rejected_steps,
+        next_step: dt,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdaptiveControl, AdaptiveRungeKutta, PiController, StepDoubling};
+    use crate::algebra::csr::CsrMatrix;
+    use crate::time::explicit::ButcherTableau;
+    use crate::time::implicit::{ImplicitIntegrator, ImplicitScheme};
+    use crate::timer::TimerReport;
+
+    #[test]
+    fn test_embedded() {
+        // u' = -u + t with u(0) = 1, of solution t - 1 + 2 exp(-t)
+        let rate = |t: f64, u: &[f64], k: &mut [f64]| k[0] = t - u[0];
+        let exact = 2.0 + 2.0 * (-3.0_f64).exp();
+        for tableau in [
+            ButcherTableau::bogacki_shampine(),
+            ButcherTableau::dormand_prince(),
+        ] {
+            let mut errors = Vec::new();
+            for tolerance in [1e-5, 1e-8] {
+                let integrator = AdaptiveRungeKutta::new(
+                    tableau.clone(),
+                    AdaptiveControl::new(tolerance, tolerance, 1e-3),
+                )
+                .unwrap();
+                let mut u = [1.0];
+                let result = integrator.integrate(&rate, 0.0, 3.0, &mut u).unwrap();
+                assert!(
+                    result.get_number_of_accepted_steps() < 2000,
+                    "Too many steps for a smooth solution"
+                );
+                errors.push((u[0] - exact).abs());
+            }
+            assert!(
+                errors[0] < 1e-4,
+                "Incorrect solution with a loose tolerance"
+            );
+            assert!(
+                errors[1] < 1e-7,
+                "Incorrect solution with a tight tolerance"
+            );
+        }
+        assert!(
+            AdaptiveRungeKutta::new(
+                ButcherTableau::<f64>::rk4(),
+                AdaptiveControl::new(1e-6, 1e-6, 0.1)
+            )
+            .is_err(),
+            "Tableau without embedded weights accepted"
+        );
+    }
+
+    #[test]
+    fn test_step_doubling() {
+        // Stiff transient u' + 1000 (u - cos t) = 0 with u(0) = 0, of solution
+        // l² / (l² + 1) (cos t + sin t / l - exp(-l t)) with l = 1000
+        let mass = CsrMatrix::from_triplets(1, 1, &[(0, 0, 1.0)]).unwrap();
+        let stiffness = CsrMatrix::from_triplets(1, 1, &[(0, 0, 1000.0)]).unwrap();
+        let mut integrator =
+            ImplicitIntegrator::new(ImplicitScheme::ImplicitEuler, &mass, &stiffness).unwrap();
+        let mut adaptive = StepDoubling::new(1, AdaptiveControl::new(1e-4, 1e-6, 1e-6));
+        let report = TimerReport::new();
+        adaptive.set_timer_report(&report);
+        let mut u = [0.0];
+        let result = adaptive
+            .integrate(
+                |t, dt, u| {
+                    integrator
+                        .step(|t, f: &mut [f64]| f[0] = 1000.0 * t.cos(), t, dt, u)
+                        .map(|_| ())
+                },
+                0.0,
+                1.0,
+                &mut u,
+            )
+            .unwrap();
+        let l = 1000.0_f64;
+        let exact = l * l / (l * l + 1.0) * (1.0_f64.cos() + 1.0_f64.sin() / l);
+        assert!((u[0] - exact).abs() < 1e-3, "Incorrect stiff solution");
+        assert!(
+            result.get_number_of_accepted_steps() < 500,
+            "Steps not increased after the transient"
+        );
+        assert!(
+            result.get_next_step() > 1e-3,
+            "Incorrect step proposed after the transient"
+        );
+        assert_eq!(
+            report.get_number_of_calls("adaptive/step"),
+            result.get_number_of_accepted_steps() + result.get_number_of_rejected_steps(),
+            "Incorrect timed steps"
+        );
+    }
+
+    #[test]
+    fn test_controller() {
+        let controller = PiController::<f64>::new();
+        assert!(
+            (controller.get_factor(1e-12, 1.0, 2) - 5.0).abs() < 1e-12,
+            "Factor not bounded above"
+        );
+        assert!(
+            (controller.get_rejection_factor(1e12, 2) - 0.2).abs() < 1e-12,
+            "Factor not bounded below"
+        );
+        assert!(
+            controller.get_rejection_factor(1.5, 2) < 1.0,
+            "Step not reduced after a rejection"
+        );
+        let control = AdaptiveControl::new(0.0_f64, 1e-2, 1.0);
+        assert!(
+            (control.get_error_norm(&[1e-2, 1e-2], &[0.0, 0.0], &[1.0, 1.0]) - 1.0).abs() < 1e-12,
+            "Incorrect error norm"
+        );
+    }
+}
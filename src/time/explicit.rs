@@ -0,0 +1,440 @@
+use crate::algebra::scalar::Scalar;
+use crate::algebra::vector::axpy;
+use crate::error::Error;
+use crate::time::time_traits::RateFunction;
+use num::Float;
+
+/// Coefficients of a Runge-Kutta method
+///
+/// # Generics
+///
+/// * DataType: the type of unit the coefficients are encoded with
+///
+/// # Explanation
+///
+/// A method of `s` stages computes the stage rates `k_i = f(t + c_i dt, u + dt sum_j a_ij k_j)`
+/// and the new state `u + dt sum_i b_i k_i`. The matrix `a` is stored in row major ordering and is
+/// strictly lower triangular for explicit methods. Embedded pairs carry a second set of weights
+/// `b̂` of lower order, the difference between both solutions estimating the local error for the
+/// adaptive time stepping.
+#[derive(Clone, Debug)]
+pub struct ButcherTableau<DataType> {
+    a: Vec<DataType>,
+    b: Vec<DataType>,
+    c: Vec<DataType>,
+    embedded: Option<(Vec<DataType>, usize)>,
+}
+
+impl<DataType: Scalar + Float> ButcherTableau<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `a`: the stage coefficients in row major ordering
+    /// * `b`: the weights of the stages
+    /// * `c`: the nodes of the stages
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the tableau or an error if the sizes do not match
+    pub fn new(
+        a: Vec<DataType>,
+        b: Vec<DataType>,
+        c: Vec<DataType>,
+    ) -> Result<ButcherTableau<DataType>, Error> {
+        let s = b.len();
+        if s == 0 {
+            return Err(Error::InvalidArgument(
+                "Tableau should hold at least one stage",
+            ));
+        }
+        if c.len() != s {
+            return Err(Error::SizeMismatch {
+                context: "Tableau nodes do not match the number of stages",
+                expected: s,
+                actual: c.len(),
+            });
+        }
+        if a.len() != s * s {
+            return Err(Error::SizeMismatch {
+                context: "Tableau coefficients do not match the number of stages",
+                expected: s * s,
+                actual: a.len(),
+            });
+        }
+        Ok(ButcherTableau {
+            a,
+            b,
+            c,
+            embedded: None,
+        })
+    }
+
+    /// Set the weights of the embedded method
+    ///
+    /// # Arguments
+    ///
+    /// * `weights`: the weights `b̂` of the stages for the embedded method
+    /// * `order`: the order of the embedded method
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if the number of weights does not match the number of stages
+    pub fn set_embedded(&mut self, weights: Vec<DataType>, order: usize) -> Result<(), Error> {
+        if weights.len() != self.b.len() {
+            return Err(Error::SizeMismatch {
+                context: "Embedded weights do not match the number of stages",
+                expected: self.b.len(),
+                actual: weights.len(),
+            });
+        }
+        self.embedded = Some((weights, order));
+        Ok(())
+    }
+
+    /// The first order forward Euler method
+    pub fn forward_euler() -> ButcherTableau<DataType> {
+        ButcherTableau::from_f64(&[0.0], &[1.0], &[0.0])
+    }
+
+    /// The third order strong stability preserving method of Shu and Osher
+    ///
+    /// It is a convex combination of forward Euler steps and preserves the monotonicity (TVD)
+    /// properties of the spatial discretization under the forward Euler time step limit.
+    pub fn ssp_rk3() -> ButcherTableau<DataType> {
+        ButcherTableau::from_f64(
+            &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.25, 0.25, 0.0],
+            &[1.0 / 6.0, 1.0 / 6.0, 2.0 / 3.0],
+            &[0.0, 1.0, 0.5],
+        )
+    }
+
+    /// The classic fourth order method
+    pub fn rk4() -> ButcherTableau<DataType> {
+        ButcherTableau::from_f64(
+            &[
+                0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+            ],
+            &[1.0 / 6.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 6.0],
+            &[0.0, 0.5, 0.5, 1.0],
+        )
+    }
+
+    /// The third order method of Bogacki and Shampine with an embedded second order method
+    pub fn bogacki_shampine() -> ButcherTableau<DataType> {
+        let mut tableau = ButcherTableau::from_f64(
+            &[
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                0.5,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                0.75,
+                0.0,
+                0.0,
+                2.0 / 9.0,
+                1.0 / 3.0,
+                4.0 / 9.0,
+                0.0,
+            ],
+            &[2.0 / 9.0, 1.0 / 3.0, 4.0 / 9.0, 0.0],
+            &[0.0, 0.5, 0.75, 1.0],
+        );
+        tableau.embedded = Some((convert(&[7.0 / 24.0, 0.25, 1.0 / 3.0, 0.125]), 2));
+        tableau
+    }
+
+    /// The fifth order method of Dormand and Prince with an embedded fourth order method
+    pub fn dormand_prince() -> ButcherTableau<DataType> {
+        let mut a = vec![0.0; 49];
+        let rows: [&[f64]; 6] = [
+            &[0.2],
+            &[3.0 / 40.0, 9.0 / 40.0],
+            &[44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0],
+            &[
+                19372.0 / 6561.0,
+                -25360.0 / 2187.0,
+                64448.0 / 6561.0,
+                -212.0 / 729.0,
+            ],
+            &[
+                9017.0 / 3168.0,
+                -355.0 / 33.0,
+                46732.0 / 5247.0,
+                49.0 / 176.0,
+                -5103.0 / 18656.0,
+            ],
+            &[
+                35.0 / 384.0,
+                0.0,
+                500.0 / 1113.0,
+                125.0 / 192.0,
+                -2187.0 / 6784.0,
+                11.0 / 84.0,
+            ],
+        ];
+        for (i, row) in rows.iter().enumerate() {
+            a[(i + 1) * 7..(i + 1) * 7 + row.len()].copy_from_slice(row);
+        }
+        let mut tableau = ButcherTableau::from_f64(
+            &a,
+            &[
+                35.0 / 384.0,
+                0.0,
+                500.0 / 1113.0,
+                125.0 / 192.0,
+                -2187.0 / 6784.0,
+                11.0 / 84.0,
+                0.0,
+            ],
+            &[0.0, 0.2, 0.3, 0.8, 8.0 / 9.0, 1.0, 1.0],
+        );
+        tableau.embedded = Some((
+            convert(&[
+                5179.0 / 57600.0,
+                0.0,
+                7571.0 / 16695.0,
+                393.0 / 640.0,
+                -92097.0 / 339200.0,
+                187.0 / 2100.0,
+                0.025,
+            ]),
+            4,
+        ));
+        tableau
+    }
+
+    /// Build a tableau from double precision coefficients
+    pub(crate) fn from_f64(a: &[f64], b: &[f64], c: &[f64]) -> ButcherTableau<DataType> {
+        ButcherTableau::new(convert(a), convert(b), convert(c)).unwrap()
+    }
+
+    /// Get the number of stages
+    pub fn get_number_of_stages(&self) -> usize {
+        self.b.len()
+    }
+
+    /// Get the stage coefficient `a_ij`
+    pub fn get_a(&self, i: usize, j: usize) -> DataType {
+        self.a[i * self.b.len() + j]
+    }
+
+    /// Get the weights of the stages
+    pub fn get_b(&self) -> &[DataType] {
+        &self.b
+    }
+
+    /// Get the nodes of the stages
+    pub fn get_c(&self) -> &[DataType] {
+        &self.c
+    }
+
+    /// Get the weights of the embedded method if any
+    pub fn get_embedded_weights(&self) -> Option<&[DataType]> {
+        self.embedded
+            .as_ref()
+            .map(|(weights, _)| weights.as_slice())
+    }
+
+    /// Get the order of the embedded method if any
+    pub fn get_embedded_order(&self) -> Option<usize> {
+        self.embedded.as_ref().map(|&(_, order)| order)
+    }
+
+    /// Whether the method is explicit, `a` being strictly lower triangular
+    pub fn is_explicit(&self) -> bool {
+        let s = self.b.len();
+        (0..s).all(|i| (i..s).all(|j| self.a[i * s + j] == DataType::zero()))
+    }
+
+    /// Whether the method is diagonally implicit, `a` being lower triangular
+    pub fn is_diagonally_implicit(&self) -> bool {
+        let s = self.b.len();
+        (0..s).all(|i| (i + 1..s).all(|j| self.a[i * s + j] == DataType::zero()))
+    }
+}
+
+/// Convert double precision coefficients
+fn convert<DataType: Float>(values: &[f64]) -> Vec<DataType> {
+    values.iter().map(|&v| DataType::from(v).unwrap()).collect()
+}
+
+/// Explicit Runge-Kutta time integrator
+///
+/// # Generics
+///
+/// * DataType: the type of unit the state is encoded with
+///
+/// # Explanation
+///
+/// Advances `du/dt = f(t, u)` with fixed time steps, for hyperbolic and explicit dynamics
+/// problems where the time step is limited by stability rather than accuracy. Semi-discrete
+/// systems `M du/dt = F(t, u)` are integrated through a `MassRate`.
+pub struct ExplicitRungeKutta<DataType> {
+    tableau: ButcherTableau<DataType>,
+}
+
+impl<DataType: Scalar + Float> ExplicitRungeKutta<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `tableau`: the coefficients of the method
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the integrator or an error if the method is not explicit
+    pub fn new(tableau: ButcherTableau<DataType>) -> Result<ExplicitRungeKutta<DataType>, Error> {
+        if !tableau.is_explicit() {
+            return Err(Error::InvalidArgument(
+                "Runge-Kutta method should be explicit",
+            ));
+        }
+        Ok(ExplicitRungeKutta { tableau })
+    }
+
+    /// Get the coefficients of the method
+    pub fn get_tableau(&self) -> &ButcherTableau<DataType> {
+        &self.tableau
+    }
+
+    /// Advance the state by one time step
+    ///
+    /// # Arguments
+    ///
+    /// * `rate`: the rate of the system
+    /// * `time`: the time at the beginning of the step
+    /// * `dt`: the time step
+    /// * `u`: the state, overwritten by the state at the end of the step
+    pub fn step<RateT: RateFunction<DataType> + ?Sized>(
+        &self,
+        rate: &RateT,
+        time: DataType,
+        dt: DataType,
+        u: &mut [DataType],
+    ) {
+        let stages = self.compute_stages(rate, time, dt, u);
+        for (k, &b) in stages.iter().zip(self.tableau.get_b().iter()) {
+            axpy(dt * b, k, u);
+        }
+    }
+
+    /// Advance the state over an interval with constant time steps
+    ///
+    /// # Arguments
+    ///
+    /// * `rate`: the rate of the system
+    /// * `start`: the initial time
+    /// * `end`: the final time
+    /// * `number_of_steps`: the number of time steps
+    /// * `u`: the initial state, overwritten by the final state
+    pub fn integrate<RateT: RateFunction<DataType> + ?Sized>(
+        &self,
+        rate: &RateT,
+        start: DataType,
+        end: DataType,
+        number_of_steps: usize,
+        u: &mut [DataType],
+    ) {
+        let dt = (end - start) / DataType::from(number_of_steps).unwrap();
+        for step in 0..number_of_steps {
+            self.step(rate, start + DataType::from(step).unwrap() * dt, dt, u);
+        }
+    }
+
+    /// Compute the stage rates of a step
+    pub(crate) fn compute_stages<RateT: RateFunction<DataType> + ?Sized>(
+        &self,
+        rate: &RateT,
+        time: DataType,
+        dt: DataType,
+        u: &[DataType],
+    ) -> Vec<Vec<DataType>> {
+        let mut stages: Vec<Vec<DataType>> =
+            Vec::with_capacity(self.tableau.get_number_of_stages());
+        let mut state = vec![DataType::zero(); u.len()];
+        for i in 0..self.tableau.get_number_of_stages() {
+            state.copy_from_slice(u);
+            for (j, k) in stages.iter().enumerate() {
+                let a = self.tableau.get_a(i, j);
+                if a != DataType::zero() {
+                    axpy(dt * a, k, &mut state);
+                }
+            }
+            let mut k = vec![DataType::zero(); u.len()];
+            rate.evaluate(time + self.tableau.get_c()[i] * dt, &state, &mut k);
+            stages.push(k);
+        }
+        stages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ButcherTableau, ExplicitRungeKutta};
+    use crate::solver::preconditioners::JacobiPreconditioner;
+    use crate::time::time_traits::MassRate;
+
+    /// Error at `t = 1` of `u' = -u + t` with `u(0) = 1`, of solution `t - 1 + 2 exp(-t)`
+    fn error(tableau: ButcherTableau<f64>, number_of_steps: usize) -> f64 {
+        let integrator = ExplicitRungeKutta::new(tableau).unwrap();
+        let rate = |t: f64, u: &[f64], k: &mut [f64]| k[0] = t - u[0];
+        let mut u = [1.0];
+        integrator.integrate(&rate, 0.0, 1.0, number_of_steps, &mut u);
+        (u[0] - 2.0 * (-1.0_f64).exp()).abs()
+    }
+
+    #[test]
+    fn test_orders() {
+        for (tableau, order) in [
+            (ButcherTableau::forward_euler(), 1.0),
+            (ButcherTableau::ssp_rk3(), 3.0),
+            (ButcherTableau::rk4(), 4.0),
+        ] {
+            let rate = (error(tableau.clone(), 20) / error(tableau, 40)).log2();
+            assert!(
+                (rate - order).abs() < 0.15,
+                "Incorrect convergence order {} instead of {}",
+                rate,
+                order
+            );
+        }
+    }
+
+    #[test]
+    fn test_implicit_rejected() {
+        let tableau = ButcherTableau::new(vec![1.0], vec![1.0], vec![1.0]).unwrap();
+        assert!(
+            ExplicitRungeKutta::new(tableau).is_err(),
+            "Implicit tableau accepted"
+        );
+        assert!(
+            ButcherTableau::new(vec![0.0], vec![0.5, 0.5], vec![0.0]).is_err(),
+            "Inconsistent tableau accepted"
+        );
+    }
+
+    #[test]
+    fn test_mass_rate() {
+        // 2 du/dt = -2 u, of solution exp(-t)
+        let mass_inverse = JacobiPreconditioner::new(&[2.0, 2.0]).unwrap();
+        let rate = MassRate::new(&mass_inverse, |_: f64, u: &[f64], f: &mut [f64]| {
+            f.iter_mut().zip(u).for_each(|(f, u)| *f = -2.0 * u)
+        });
+        let integrator = ExplicitRungeKutta::new(ButcherTableau::rk4()).unwrap();
+        let mut u = [1.0, 3.0];
+        integrator.integrate(&rate, 0.0, 1.0, 50, &mut u);
+        assert!(
+            (u[0] - (-1.0_f64).exp()).abs() < 1e-8,
+            "Incorrect first component"
+        );
+        assert!(
+            (u[1] - 3.0 * (-1.0_f64).exp()).abs() < 1e-8,
+            "Incorrect second component"
+        );
+    }
+}
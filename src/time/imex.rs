@@ -0,0 +1,462 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use crate::solver::solver_traits::LinearSolver;
+use crate::time::explicit::ButcherTableau;
+use crate::time::shifted::{BoxedPreconditioner, MatrixCombination, ShiftedSolver};
+use crate::time::time_traits::RateFunction;
+use num::Float;
+
+/// Pair of Runge-Kutta tableaux of an implicit-explicit method
+///
+/// # Generics
+///
+/// * DataType: the type of unit the coefficients are encoded with
+///
+/// # Explanation
+///
+/// The explicit tableau integrates the nonstiff part of the system and the diagonally implicit one
+/// the stiff part, both sharing the same stages and nodes. The methods of Ascher, Ruuth and
+/// Spiteri are provided, padded with an explicit first stage and stiffly accurate: the state at
+/// the end of the step is the last stage, which spares a solve with the mass matrix.
+#[derive(Clone, Debug)]
+pub struct ImexTableau<DataType> {
+    explicit: ButcherTableau<DataType>,
+    implicit: ButcherTableau<DataType>,
+}
+
+impl<DataType: Scalar + Float> ImexTableau<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `explicit`: the tableau of the nonstiff part, explicit
+    /// * `implicit`: the tableau of the stiff part, diagonally implicit
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the pair or an error if the tableaux are not explicit and
+    ///   diagonally implicit or do not share their stages
+    pub fn new(
+        explicit: ButcherTableau<DataType>,
+        implicit: ButcherTableau<DataType>,
+    ) -> Result<ImexTableau<DataType>, Error> {
+        if !explicit.is_explicit() {
+            return Err(Error::InvalidArgument(
+                "Runge-Kutta method of the nonstiff part should be explicit",
+            ));
+        }
+        if !implicit.is_diagonally_implicit() {
+            return Err(Error::InvalidArgument(
+                "Runge-Kutta method of the stiff part should be diagonally implicit",
+            ));
+        }
+        if explicit.get_c() != implicit.get_c() {
+            return Err(Error::InvalidArgument(
+                "Tableaux should share the same stages",
+            ));
+        }
+        Ok(ImexTableau { explicit, implicit })
+    }
+
+    /// The first order forward-backward Euler method, ARS(1,1,1)
+    pub fn forward_backward_euler() -> ImexTableau<DataType> {
+        ImexTableau::from_f64(
+            (&[0.0, 0.0, 1.0, 0.0], &[1.0, 0.0]),
+            (&[0.0, 0.0, 0.0, 1.0], &[0.0, 1.0]),
+            &[0.0, 1.0],
+        )
+    }
+
+    /// The second order method ARS(2,2,2), L-stable in its implicit part
+    pub fn ars_222() -> ImexTableau<DataType> {
+        let gamma = 1.0 - 0.5_f64.sqrt();
+        let delta = 1.0 - 0.5 / gamma;
+        ImexTableau::from_f64(
+            (
+                &[0.0, 0.0, 0.0, gamma, 0.0, 0.0, delta, 1.0 - delta, 0.0],
+                &[delta, 1.0 - delta, 0.0],
+            ),
+            (
+                &[0.0, 0.0, 0.0, 0.0, gamma, 0.0, 0.0, 1.0 - gamma, gamma],
+                &[0.0, 1.0 - gamma, gamma],
+            ),
+            &[0.0, gamma, 1.0],
+        )
+    }
+
+    /// The third order method ARS(4,4,3), L-stable in its implicit part
+    pub fn ars_443() -> ImexTableau<DataType> {
+        #[rustfmt::skip]
+        let explicit = [
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.5, 0.0, 0.0, 0.0, 0.0,
+            11.0 / 18.0, 1.0 / 18.0, 0.0, 0.0, 0.0,
+            5.0 / 6.0, -5.0 / 6.0, 0.5, 0.0, 0.0,
+            0.25, 1.75, 0.75, -1.75, 0.0,
+        ];
+        #[rustfmt::skip]
+        let implicit = [
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.5, 0.0, 0.0, 0.0,
+            0.0, 1.0 / 6.0, 0.5, 0.0, 0.0,
+            0.0, -0.5, 0.5, 0.5, 0.0,
+            0.0, 1.5, -1.5, 0.5, 0.5,
+        ];
+        ImexTableau::from_f64(
+            (&explicit, &explicit[20..]),
+            (&implicit, &implicit[20..]),
+            &[0.0, 0.5, 2.0 / 3.0, 0.5, 1.0],
+        )
+    }
+
+    /// Build a pair from double precision coefficients `(a, b)` of each tableau and the nodes
+    fn from_f64(
+        explicit: (&[f64], &[f64]),
+        implicit: (&[f64], &[f64]),
+        c: &[f64],
+    ) -> ImexTableau<DataType> {
+        ImexTableau {
+            explicit: ButcherTableau::from_f64(explicit.0, explicit.1, c),
+            implicit: ButcherTableau::from_f64(implicit.0, implicit.1, c),
+        }
+    }
+
+    /// Get the tableau of the nonstiff part
+    pub fn get_explicit(&self) -> &ButcherTableau<DataType> {
+        &self.explicit
+    }
+
+    /// Get the tableau of the stiff part
+    pub fn get_implicit(&self) -> &ButcherTableau<DataType> {
+        &self.implicit
+    }
+
+    /// Get the number of stages
+    pub fn get_number_of_stages(&self) -> usize {
+        self.explicit.get_number_of_stages()
+    }
+
+    /// Whether the weights of both tableaux are their last rows, the state at the end of the step
+    /// then being the last stage
+    pub fn is_stiffly_accurate(&self) -> bool {
+        let s = self.get_number_of_stages();
+        [&self.explicit, &self.implicit]
+            .iter()
+            .all(|tableau| (0..s).all(|j| tableau.get_a(s - 1, j) == tableau.get_b()[j]))
+    }
+}
+
+/// Implicit-explicit Runge-Kutta integrator of semi-discrete systems `M du/dt + K u = F(t, u)`
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The stiff linear operator `K`, typically a diffusion, is treated implicitly and the nonstiff
+/// right hand side `F(t, u)`, typically an advection with the sources, explicitly, so that the
+/// time step is only limited by the nonstiff part. Each stage solves a shifted system
+/// `(M + dt a_ii K) U_i = r` with the frozen pattern and cached preconditioner of the implicit
+/// integrators: the diagonally implicit methods with a constant diagonal pay for a single
+/// factorization at constant time steps. The right hand side is given as a `RateFunction` filling
+/// `F(t, u)`, not divided by the mass matrix. Stages without implicit coefficient and methods
+/// that are not stiffly accurate solve with the mass matrix, through a solver of its own.
+pub struct ImexRungeKutta<'a, DataType: Clone> {
+    tableau: ImexTableau<DataType>,
+    combination: MatrixCombination<'a, DataType>,
+    solver: ShiftedSolver<'a, DataType>,
+    mass_combination: MatrixCombination<'a, DataType>,
+    mass_solver: ShiftedSolver<'a, DataType>,
+}
+
+impl<'a, DataType: Scalar + Float + 'a> ImexRungeKutta<'a, DataType> {
+    /// Constructor using sparse direct solves
+    ///
+    /// # Arguments
+    ///
+    /// * `tableau`: the coefficients of the method
+    /// * `mass`: the mass matrix `M`
+    /// * `stiffness`: the matrix `K` of the stiff part
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the integrator or an error if the matrices are not square of the
+    ///   same size
+    pub fn new(
+        tableau: ImexTableau<DataType>,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+    ) -> Result<ImexRungeKutta<'a, DataType>, Error> {
+        Ok(ImexRungeKutta {
+            tableau,
+            combination: MatrixCombination::new(&[mass, stiffness])?,
+            solver: ShiftedSolver::new(),
+            mass_combination: MatrixCombination::new(&[mass])?,
+            mass_solver: ShiftedSolver::new(),
+        })
+    }
+
+    /// Set the solver of the shifted systems of the stages
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the linear solver
+    /// * `preconditioner_factory`: builds the preconditioner from the shifted system matrix, only
+    ///   called when the shift changes
+    pub fn set_linear_solver(
+        &mut self,
+        solver: impl LinearSolver<DataType> + 'a,
+        preconditioner_factory: impl Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error>
+            + 'a,
+    ) {
+        self.solver
+            .set_linear_solver(solver, preconditioner_factory);
+    }
+
+    /// Set the solver of the systems with the mass matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the linear solver
+    /// * `preconditioner_factory`: builds the preconditioner from the mass matrix, only called once
+    ///   unless the matrices are replaced
+    pub fn set_mass_solver(
+        &mut self,
+        solver: impl LinearSolver<DataType> + 'a,
+        preconditioner_factory: impl Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error>
+            + 'a,
+    ) {
+        self.mass_solver
+            .set_linear_solver(solver, preconditioner_factory);
+    }
+
+    /// Replace the mass and stiffness matrices, keeping the frozen patterns when they fit in them
+    ///
+    /// The preconditioners are rebuilt at the next step.
+    pub fn set_matrices(
+        &mut self,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+    ) -> Result<(), Error> {
+        self.combination.set_matrices(&[mass, stiffness])?;
+        self.mass_combination.set_matrices(&[mass])?;
+        self.solver.invalidate();
+        self.mass_solver.invalidate();
+        Ok(())
+    }
+
+    /// Get the coefficients of the method
+    pub fn get_tableau(&self) -> &ImexTableau<DataType> {
+        &self.tableau
+    }
+
+    /// Get the number of times the preconditioner of the shifted systems was built
+    pub fn get_number_of_setups(&self) -> usize {
+        self.solver.get_number_of_setups()
+    }
+
+    /// Advance the state by one time step
+    ///
+    /// # Arguments
+    ///
+    /// * `force`: computes the nonstiff right hand side `F(t, u)`
+    /// * `time`: the time at the beginning of the step
+    /// * `dt`: the time step
+    /// * `u`: the state, overwritten by the state at the end of the step
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if a preconditioner could not be built or a linear solve did
+    ///   not converge
+    pub fn step<ForceT: RateFunction<DataType> + ?Sized>(
+        &mut self,
+        force: &ForceT,
+        time: DataType,
+        dt: DataType,
+        u: &mut [DataType],
+    ) -> Result<(), Error> {
+        let n = u.len();
+        let one = DataType::one();
+        let zero = DataType::zero();
+        let explicit = self.tableau.get_explicit();
+        let implicit = self.tableau.get_implicit();
+        let s = self.tableau.get_number_of_stages();
+        let mass_u = self.combination.get_matrix(0).apply(u);
+        let mut forces: Vec<Vec<DataType>> = Vec::with_capacity(s);
+        let mut stiff: Vec<Vec<DataType>> = Vec::with_capacity(s);
+        let mut stage = u.to_vec();
+        for i in 0..s {
+            let mut rhs = mass_u.clone();
+            for (j, (f, ku)) in forces.iter().zip(&stiff).enumerate() {
+                let (a_explicit, a_implicit) =
+                    (dt * explicit.get_a(i, j), dt * implicit.get_a(i, j));
+                for ((r, &f), &ku) in rhs.iter_mut().zip(f).zip(ku) {
+                    *r = *r + a_explicit * f - a_implicit * ku;
+                }
+            }
+            let diagonal = implicit.get_a(i, i);
+            if diagonal != zero {
+                self.solver.solve(
+                    &mut self.combination,
+                    &[one, dt * diagonal],
+                    &rhs,
+                    &mut stage,
+                )?;
+            } else if (0..i).all(|j| explicit.get_a(i, j) == zero && implicit.get_a(i, j) == zero) {
+                stage.copy_from_slice(u);
+            } else {
+                self.mass_solver
+                    .solve(&mut self.mass_combination, &[one], &rhs, &mut stage)?;
+            }
+            let mut f = vec![zero; n];
+            force.evaluate(time + explicit.get_c()[i] * dt, &stage, &mut f);
+            forces.push(f);
+            stiff.push(self.combination.get_matrix(1).apply(&stage));
+        }
+        if self.tableau.is_stiffly_accurate() {
+            u.copy_from_slice(&stage);
+            return Ok(());
+        }
+        let mut rhs = mass_u;
+        for (j, (f, ku)) in forces.iter().zip(&stiff).enumerate() {
+            let (b_explicit, b_implicit) = (dt * explicit.get_b()[j], dt * implicit.get_b()[j]);
+            for ((r, &f), &ku) in rhs.iter_mut().zip(f).zip(ku) {
+                *r = *r + b_explicit * f - b_implicit * ku;
+            }
+        }
+        self.mass_solver
+            .solve(&mut self.mass_combination, &[one], &rhs, u)?;
+        Ok(())
+    }
+
+    /// Advance the state over an interval with constant time steps
+    ///
+    /// # Arguments
+    ///
+    /// * `force`: computes the nonstiff right hand side `F(t, u)`
+    /// * `start`: the initial time
+    /// * `end`: the final time
+    /// * `number_of_steps`: the number of time steps
+    /// * `u`: the initial state, overwritten by the final state
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if a step failed
+    pub fn integrate<ForceT: RateFunction<DataType> + ?Sized>(
+        &mut self,
+        force: &ForceT,
+        start: DataType,
+        end: DataType,
+        number_of_steps: usize,
+        u: &mut [DataType],
+    ) -> Result<(), Error> {
+        let dt = (end - start) / DataType::from(number_of_steps).unwrap();
+        for step in 0..number_of_steps {
+            self.step(force, start + DataType::from(step).unwrap() * dt, dt, u)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImexRungeKutta, ImexTableau};
+    use crate::algebra::csr::CsrMatrix;
+    use crate::time::explicit::{ButcherTableau, ExplicitRungeKutta};
+
+    /// Rotation `F(t, u) = (u_1, -u_0) + (sin t, 0)`, the nonstiff part
+    fn force(t: f64, u: &[f64], f: &mut [f64]) {
+        f[0] = u[1] + t.sin();
+        f[1] = -u[0];
+    }
+
+    /// `diag(1, 2)` and `diag(1, 40)`, the stiff part
+    fn system() -> (CsrMatrix<f64>, CsrMatrix<f64>) {
+        let mass = CsrMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (1, 1, 2.0)]).unwrap();
+        let stiffness = CsrMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (1, 1, 40.0)]).unwrap();
+        (mass, stiffness)
+    }
+
+    /// Reference solution at `t = 1` from `u(0) = (1, 1)` with a fine explicit integration
+    fn reference() -> [f64; 2] {
+        let rate = |t: f64, u: &[f64], rate: &mut [f64]| {
+            force(t, u, rate);
+            rate[0] -= u[0];
+            rate[1] = (rate[1] - 40.0 * u[1]) / 2.0;
+        };
+        let mut u = [1.0, 1.0];
+        ExplicitRungeKutta::new(ButcherTableau::rk4())
+            .unwrap()
+            .integrate(&rate, 0.0, 1.0, 4000, &mut u);
+        u
+    }
+
+    fn error(tableau: ImexTableau<f64>, number_of_steps: usize, exact: &[f64; 2]) -> f64 {
+        let (mass, stiffness) = system();
+        let mut integrator = ImexRungeKutta::new(tableau, &mass, &stiffness).unwrap();
+        let mut u = [1.0, 1.0];
+        integrator
+            .integrate(&force, 0.0, 1.0, number_of_steps, &mut u)
+            .unwrap();
+        assert_eq!(
+            integrator.get_number_of_setups(),
+            1,
+            "Incorrect number of factorizations"
+        );
+        (u[0] - exact[0]).abs().max((u[1] - exact[1]).abs())
+    }
+
+    #[test]
+    fn test_orders() {
+        let exact = reference();
+        let heun_trapezoidal = ImexTableau::new(
+            ButcherTableau::new(vec![0.0, 0.0, 1.0, 0.0], vec![0.5, 0.5], vec![0.0, 1.0]).unwrap(),
+            ButcherTableau::new(vec![0.0, 0.0, 0.5, 0.5], vec![0.5, 0.5], vec![0.0, 1.0]).unwrap(),
+        )
+        .unwrap();
+        assert!(
+            !heun_trapezoidal.is_stiffly_accurate(),
+            "Incorrect stiff accuracy"
+        );
+        for (name, tableau, order) in [
+            (
+                "forward-backward Euler",
+                ImexTableau::forward_backward_euler(),
+                1.0,
+            ),
+            ("ARS(2,2,2)", ImexTableau::ars_222(), 2.0),
+            ("ARS(4,4,3)", ImexTableau::ars_443(), 3.0),
+            ("Heun-trapezoidal", heun_trapezoidal, 2.0),
+        ] {
+            let coarse = error(tableau.clone(), 160, &exact);
+            let fine = error(tableau, 320, &exact);
+            let rate = (coarse / fine).log2();
+            assert!(
+                (rate - order).abs() < 0.3,
+                "Incorrect order {} of {}",
+                rate,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_stiff_stability() {
+        let (mass, stiffness) = system();
+        let mut integrator =
+            ImexRungeKutta::new(ImexTableau::ars_222(), &mass, &stiffness).unwrap();
+        let mut u = [1.0, 1.0];
+        integrator.integrate(&force, 0.0, 10.0, 20, &mut u).unwrap();
+        assert!(
+            u.iter().all(|v| v.abs() < 10.0),
+            "Unstable integration of the stiff part"
+        );
+        let explicit = ButcherTableau::new(vec![0.5], vec![1.0], vec![0.5]).unwrap();
+        assert!(
+            ImexTableau::new(explicit, ButcherTableau::forward_euler()).is_err(),
+            "Implicit nonstiff tableau accepted"
+        );
+    }
+}
@@ -0,0 +1,578 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use crate::solver::solver_traits::{LinearSolver, SolverResult};
+use crate::time::shifted::{BoxedPreconditioner, MatrixCombination, ShiftedSolver};
+use num::Float;
+
+/// The implicit schemes for first order systems
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImplicitScheme {
+    /// First order, L-stable backward Euler scheme
+    ImplicitEuler,
+    /// Second order, A-stable trapezoidal scheme (the theta scheme for theta = 1/2)
+    CrankNicolson,
+    /// Second order, L-stable backward differentiation formula with variable steps, started by
+    /// an implicit Euler step
+    Bdf2,
+}
+
+/// Implicit time integrator of linear semi-discrete systems `M du/dt + K u = f(t)`
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// Each step solves the shifted system `(s M + θ K) u_{n+1} = r`, where the shift `s` is `1 / dt`
+/// for the theta schemes and `(1 + 2 ω) / ((1 + ω) dt)` for BDF2 with the step ratio
+/// `ω = dt_n / dt_{n - 1}`. The union of the sparsity patterns of `M` and `K` is computed once and
+/// frozen, the values of the shifted system are only refilled and its preconditioner (by default
+/// a sparse LU factorization used as a direct solver) only rebuilt when the shift changes, so that
+/// constant steps pay for a single factorization. Matrices updated in place (values changed with
+/// the same patterns) are taken into account with `set_matrices`.
+pub struct ImplicitIntegrator<'a, DataType: Clone> {
+    scheme: ImplicitScheme,
+    combination: MatrixCombination<'a, DataType>,
+    solver: ShiftedSolver<'a, DataType>,
+    previous: Option<(DataType, Vec<DataType>)>,
+}
+
+impl<'a, DataType: Scalar + Float + 'a> ImplicitIntegrator<'a, DataType> {
+    /// Constructor using a sparse direct solve of the shifted systems
+    ///
+    /// # Arguments
+    ///
+    /// * `scheme`: the time integration scheme
+    /// * `mass`: the mass matrix `M`
+    /// * `stiffness`: the stiffness matrix `K`
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the integrator or an error if the matrices are not square of the
+    ///   same size
+    pub fn new(
+        scheme: ImplicitScheme,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+    ) -> Result<ImplicitIntegrator<'a, DataType>, Error> {
+        Ok(ImplicitIntegrator {
+            scheme,
+            combination: MatrixCombination::new(&[mass, stiffness])?,
+            solver: ShiftedSolver::new(),
+            previous: None,
+        })
+    }
+
+    /// Set the solver of the shifted systems
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the linear solver
+    /// * `preconditioner_factory`: builds the preconditioner from the shifted system matrix, only
+    ///   called when the shift changes
+    pub fn set_linear_solver(
+        &mut self,
+        solver: impl LinearSolver<DataType> + 'a,
+        preconditioner_factory: impl Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error>
+            + 'a,
+    ) {
+        self.solver
+            .set_linear_solver(solver, preconditioner_factory);
+    }
+
+    /// Replace the mass and stiffness matrices, keeping the frozen pattern when they fit in it
+    ///
+    /// The preconditioner is rebuilt at the next step.
+    pub fn set_matrices(
+        &mut self,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+    ) -> Result<(), Error> {
+        self.combination.set_matrices(&[mass, stiffness])?;
+        self.solver.invalidate();
+        Ok(())
+    }
+
+    /// Replace the values of the mass (index 0) or stiffness (index 1) matrix, its sparsity
+    /// pattern being unchanged, as for a stiffness reassembled at each step with reassemble_values
+    ///
+    /// The preconditioner is rebuilt at the next step.
+    pub fn set_matrix_values(&mut self, index: usize, values: &[DataType]) -> Result<(), Error> {
+        self.combination.set_matrix_values(index, values)?;
+        self.solver.invalidate();
+        Ok(())
+    }
+
+    /// Forget the previous steps, to restart a multistep scheme after a discontinuity
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// Get the time integration scheme
+    pub fn get_scheme(&self) -> ImplicitScheme {
+        self.scheme
+    }
+
+    /// Get the number of times the preconditioner of the shifted system was built
+    pub fn get_number_of_setups(&self) -> usize {
+        self.solver.get_number_of_setups()
+    }
+
+    /// Advance the state by one time step
+    ///
+    /// # Arguments
+    ///
+    /// * `force`: computes the right hand side `f(t)` at a given time
+    /// * `time`: the time at the beginning of the step
+    /// * `dt`: the time step
+    /// * `u`: the state, overwritten by the state at the end of the step
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the convergence information of the linear solve or an error if
+    ///   the preconditioner could not be built or the solve did not converge
+    pub fn step(
+        &mut self,
+        force: impl Fn(DataType, &mut [DataType]),
+        time: DataType,
+        dt: DataType,
+        u: &mut [DataType],
+    ) -> Result<SolverResult<DataType>, Error> {
+        let n = u.len();
+        let one = DataType::one();
+        let end = time + dt;
+        let mass = self.combination.get_matrix(0);
+        let stiffness = self.combination.get_matrix(1);
+        let mut rhs = vec![DataType::zero(); n];
+        let mut work = vec![DataType::zero(); n];
+        let (shift, theta) = match (self.scheme, &self.previous) {
+            (ImplicitScheme::Bdf2, Some((previous_dt, previous))) => {
+                let omega = dt / *previous_dt;
+                let mut history = vec![DataType::zero(); n];
+                for ((h, &current), &old) in history.iter_mut().zip(u.iter()).zip(previous) {
+                    *h = ((one + omega) * current - omega * omega / (one + omega) * old) / dt;
+                }
+                mass.apply_into(&history, &mut rhs);
+                force(end, &mut work);
+                rhs.iter_mut().zip(&work).for_each(|(r, &f)| *r = *r + f);
+                ((one + omega + omega) / ((one + omega) * dt), one)
+            }
+            _ => {
+                let theta = match self.scheme {
+                    ImplicitScheme::CrankNicolson => one / (one + one),
+                    _ => one,
+                };
+                let scaled: Vec<DataType> = u.iter().map(|&v| v / dt).collect();
+                mass.apply_into(&scaled, &mut rhs);
+                if theta < one {
+                    stiffness.apply_into(u, &mut work);
+                    rhs.iter_mut()
+                        .zip(&work)
+                        .for_each(|(r, &ku)| *r = *r - (one - theta) * ku);
+                    force(time, &mut work);
+                    rhs.iter_mut()
+                        .zip(&work)
+                        .for_each(|(r, &f)| *r = *r + (one - theta) * f);
+                }
+                force(end, &mut work);
+                rhs.iter_mut()
+                    .zip(&work)
+                    .for_each(|(r, &f)| *r = *r + theta * f);
+                (one / dt, theta)
+            }
+        };
+        let mut x = u.to_vec();
+        let result = self
+            .solver
+            .solve(&mut self.combination, &[shift, theta], &rhs, &mut x)?;
+        if self.scheme == ImplicitScheme::Bdf2 {
+            self.previous = Some((dt, u.to_vec()));
+        }
+        u.copy_from_slice(&x);
+        Ok(result)
+    }
+}
+
+/// Generalized-α integrator of linear first order systems `M du/dt + K u = f(t)`
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// The scheme of Jansen, Whiting and Hulbert enforces `M w_{n+α_m} + K u_{n+α_f} = f(t_{n+α_f})`,
+/// where `x_{n+α} = x_n + α (x_{n+1} - x_n)` and `w` is the rate `du/dt`, with the update
+/// `u_{n+1} = u_n + dt ((1 - γ) w_n + γ w_{n+1})`. The parameters are chosen from the spectral
+/// radius `ρ∞` of the amplification matrix at infinite time step, so that the scheme is second
+/// order and unconditionally stable while damping the high frequencies to `ρ∞`: a radius of one
+/// gives the midpoint rule, a radius of zero annihilates the highest frequencies in a single step.
+/// Each step solves `(α_m M + α_f γ dt K) w_{n+1} = r` with the systems and preconditioner reuse
+/// of the `ImplicitIntegrator`. The rate is kept between the steps and computed at the first step
+/// from `M w_0 = f(t_0) - K u_0`.
+pub struct GeneralizedAlphaIntegrator<'a, DataType: Clone> {
+    spectral_radius: DataType,
+    alpha_m: DataType,
+    alpha_f: DataType,
+    gamma: DataType,
+    combination: MatrixCombination<'a, DataType>,
+    solver: ShiftedSolver<'a, DataType>,
+    rate: Option<Vec<DataType>>,
+}
+
+impl<'a, DataType: Scalar + Float + 'a> GeneralizedAlphaIntegrator<'a, DataType> {
+    /// Constructor using a sparse direct solve of the shifted systems
+    ///
+    /// # Arguments
+    ///
+    /// * `spectral_radius`: the spectral radius `ρ∞` at infinite time step, between zero and one
+    /// * `mass`: the mass matrix `M`
+    /// * `stiffness`: the stiffness matrix `K`
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the integrator or an error if the radius is out of bounds or the
+    ///   matrices are not square of the same size
+    pub fn new(
+        spectral_radius: DataType,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+    ) -> Result<GeneralizedAlphaIntegrator<'a, DataType>, Error> {
+        let one = DataType::one();
+        if spectral_radius < DataType::zero() || spectral_radius > one {
+            return Err(Error::InvalidArgument(
+                "Spectral radius should be between zero and one",
+            ));
+        }
+        let half = DataType::from(0.5).unwrap();
+        let alpha_m = half * (one + one + one - spectral_radius) / (one + spectral_radius);
+        let alpha_f = one / (one + spectral_radius);
+        Ok(GeneralizedAlphaIntegrator {
+            spectral_radius,
+            alpha_m,
+            alpha_f,
+            gamma: half + alpha_m - alpha_f,
+            combination: MatrixCombination::new(&[mass, stiffness])?,
+            solver: ShiftedSolver::new(),
+            rate: None,
+        })
+    }
+
+    /// Set the solver of the shifted systems
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the linear solver
+    /// * `preconditioner_factory`: builds the preconditioner from the shifted system matrix, only
+    ///   called when the time step changes
+    pub fn set_linear_solver(
+        &mut self,
+        solver: impl LinearSolver<DataType> + 'a,
+        preconditioner_factory: impl Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error>
+            + 'a,
+    ) {
+        self.solver
+            .set_linear_solver(solver, preconditioner_factory);
+    }
+
+    /// Replace the mass and stiffness matrices, keeping the frozen pattern when they fit in it
+    ///
+    /// The preconditioner is rebuilt at the next step.
+    pub fn set_matrices(
+        &mut self,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+    ) -> Result<(), Error> {
+        self.combination.set_matrices(&[mass, stiffness])?;
+        self.solver.invalidate();
+        Ok(())
+    }
+
+    /// Forget the rate, to restart the scheme after a discontinuity
+    pub fn reset(&mut self) {
+        self.rate = None;
+    }
+
+    /// Get the spectral radius at infinite time step
+    pub fn get_spectral_radius(&self) -> DataType {
+        self.spectral_radius
+    }
+
+    /// Get the parameters `(α_m, α_f, γ)` of the scheme
+    pub fn get_parameters(&self) -> (DataType, DataType, DataType) {
+        (self.alpha_m, self.alpha_f, self.gamma)
+    }
+
+    /// Get the number of times the preconditioner of the shifted system was built
+    pub fn get_number_of_setups(&self) -> usize {
+        self.solver.get_number_of_setups()
+    }
+
+    /// Advance the state by one time step
+    ///
+    /// # Arguments
+    ///
+    /// * `force`: computes the right hand side `f(t)` at a given time
+    /// * `time`: the time at the beginning of the step
+    /// * `dt`: the time step
+    /// * `u`: the state, overwritten by the state at the end of the step
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the convergence information of the linear solve or an error if
+    ///   the preconditioner could not be built or the solve did not converge
+    pub fn step(
+        &mut self,
+        force: impl Fn(DataType, &mut [DataType]),
+        time: DataType,
+        dt: DataType,
+        u: &mut [DataType],
+    ) -> Result<SolverResult<DataType>, Error> {
+        let n = u.len();
+        let (zero, one) = (DataType::zero(), DataType::one());
+        let mut rhs = vec![zero; n];
+        let mut work = vec![zero; n];
+        let rate = match self.rate.take() {
+            Some(rate) => rate,
+            None => {
+                force(time, &mut rhs);
+                self.combination.get_matrix(1).apply_into(u, &mut work);
+                rhs.iter_mut().zip(&work).for_each(|(r, &ku)| *r = *r - ku);
+                let mut rate = vec![zero; n];
+                self.solver
+                    .solve(&mut self.combination, &[one, zero], &rhs, &mut rate)?;
+                rate
+            }
+        };
+        let (alpha_m, alpha_f, gamma) = (self.alpha_m, self.alpha_f, self.gamma);
+        let predicted: Vec<DataType> = u
+            .iter()
+            .zip(&rate)
+            .map(|(&u, &w)| u + alpha_f * dt * (one - gamma) * w)
+            .collect();
+        force(time + alpha_f * dt, &mut rhs);
+        self.combination
+            .get_matrix(1)
+            .apply_into(&predicted, &mut work);
+        rhs.iter_mut().zip(&work).for_each(|(r, &ku)| *r = *r - ku);
+        let scaled: Vec<DataType> = rate.iter().map(|&w| (one - alpha_m) * w).collect();
+        self.combination
+            .get_matrix(0)
+            .apply_into(&scaled, &mut work);
+        rhs.iter_mut().zip(&work).for_each(|(r, &mw)| *r = *r - mw);
+        let mut next = rate.clone();
+        let result = self.solver.solve(
+            &mut self.combination,
+            &[alpha_m, alpha_f * gamma * dt],
+            &rhs,
+            &mut next,
+        );
+        if result.is_err() {
+            self.rate = Some(rate);
+            return result;
+        }
+        for ((u, &w), &next) in u.iter_mut().zip(&rate).zip(&next) {
+            *u = *u + dt * ((one - gamma) * w + gamma * next);
+        }
+        self.rate = Some(next);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GeneralizedAlphaIntegrator, ImplicitIntegrator, ImplicitScheme};
+    use crate::algebra::csr::{CsrMatrix, Storage};
+    use crate::solver::cg::ConjugateGradient;
+    use crate::solver::preconditioners::JacobiPreconditioner;
+    use crate::solver::solver_traits::IterationControl;
+    use std::cell::Cell;
+
+    /// `diag(1, 2) u' + diag(1, 4) u = (t, 0)` with `u(0) = (1, 1)`, of solution
+    /// `(t - 1 + 2 exp(-t), exp(-2 t))`
+    fn system() -> (CsrMatrix<f64>, CsrMatrix<f64>) {
+        let mass = CsrMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (1, 1, 2.0)]).unwrap();
+        let stiffness = CsrMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (1, 1, 4.0)]).unwrap();
+        (mass, stiffness)
+    }
+
+    fn error(scheme: ImplicitScheme, number_of_steps: usize) -> f64 {
+        let (mass, stiffness) = system();
+        let mut integrator = ImplicitIntegrator::new(scheme, &mass, &stiffness).unwrap();
+        let dt = 1.0 / number_of_steps as f64;
+        let mut u = [1.0, 1.0];
+        for step in 0..number_of_steps {
+            integrator
+                .step(
+                    |t, f: &mut [f64]| {
+                        f[0] = t;
+                        f[1] = 0.0
+                    },
+                    step as f64 * dt,
+                    dt,
+                    &mut u,
+                )
+                .unwrap();
+        }
+        let exact = [2.0 * (-1.0_f64).exp(), (-2.0_f64).exp()];
+        (u[0] - exact[0]).abs().max((u[1] - exact[1]).abs())
+    }
+
+    #[test]
+    fn test_orders() {
+        for (scheme, order) in [
+            (ImplicitScheme::ImplicitEuler, 1.0),
+            (ImplicitScheme::CrankNicolson, 2.0),
+            (ImplicitScheme::Bdf2, 2.0),
+        ] {
+            let rate = (error(scheme, 40) / error(scheme, 80)).log2();
+            assert!(
+                (rate - order).abs() < 0.15,
+                "Incorrect convergence order {} instead of {} for {:?}",
+                rate,
+                order,
+                scheme
+            );
+        }
+    }
+
+    #[test]
+    fn test_factorization_reuse() {
+        let builds = Cell::new(0);
+        let (mass, stiffness) = system();
+        let mut integrator =
+            ImplicitIntegrator::new(ImplicitScheme::ImplicitEuler, &mass, &stiffness).unwrap();
+        integrator.set_linear_solver(
+            ConjugateGradient::new(IterationControl::new(1e-12, 0.0, 10)),
+            |matrix| {
+                builds.set(builds.get() + 1);
+                Ok(Box::new(JacobiPreconditioner::from_matrix(matrix)?))
+            },
+        );
+        let mut u = [1.0, 1.0];
+        for step in 0..5 {
+            integrator
+                .step(
+                    |_, f: &mut [f64]| f.fill(0.0),
+                    step as f64 * 0.1,
+                    0.1,
+                    &mut u,
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            builds.get(),
+            1,
+            "Preconditioner rebuilt with a constant step"
+        );
+        integrator
+            .step(|_, f: &mut [f64]| f.fill(0.0), 0.5, 0.05, &mut u)
+            .unwrap();
+        assert_eq!(
+            integrator.get_number_of_setups(),
+            2,
+            "Preconditioner not rebuilt after a step change"
+        );
+        assert!(
+            (u[0] - 1.0 / (1.1_f64.powi(5) * 1.05)).abs() < 1e-12,
+            "Incorrect implicit Euler value"
+        );
+    }
+
+    #[test]
+    fn test_pattern_union() {
+        let mut upper =
+            CsrMatrix::from_pattern(2, vec![0, 2, 3], vec![0, 1, 1], Storage::Upper).unwrap();
+        upper.get_values_mut().copy_from_slice(&[1.0, -1.0, 1.0]);
+        let mass = CsrMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (1, 1, 1.0)]).unwrap();
+        let stiffness = CsrMatrix::from_triplets(
+            2,
+            2,
+            &[(0, 0, 1.0), (0, 1, -1.0), (1, 0, -1.0), (1, 1, 1.0)],
+        )
+        .unwrap();
+        let mut integrator =
+            ImplicitIntegrator::new(ImplicitScheme::ImplicitEuler, &mass, &stiffness).unwrap();
+        let mut u = [1.0, 0.0];
+        integrator
+            .step(|_, f: &mut [f64]| f.fill(0.0), 0.0, 1.0, &mut u)
+            .unwrap();
+        // (I + K) u = (1, 0)
+        assert!((u[0] - 2.0 / 3.0).abs() < 1e-12, "Incorrect first value");
+        assert!((u[1] - 1.0 / 3.0).abs() < 1e-12, "Incorrect second value");
+        integrator.set_matrices(&mass, &upper).unwrap();
+        integrator.reset();
+        let mut v = [1.0, 0.0];
+        integrator
+            .step(|_, f: &mut [f64]| f.fill(0.0), 0.0, 1.0, &mut v)
+            .unwrap();
+        assert!(
+            (v[0] - u[0]).abs() < 1e-12 && (v[1] - u[1]).abs() < 1e-12,
+            "Incorrect solution with mixed storages"
+        );
+        let wrong = CsrMatrix::from_triplets(3, 3, &[(0, 0, 1.0)]).unwrap();
+        assert!(
+            ImplicitIntegrator::new(ImplicitScheme::Bdf2, &mass, &wrong).is_err(),
+            "Matrices of different sizes accepted"
+        );
+    }
+
+    #[test]
+    fn test_generalized_alpha() {
+        let (mass, stiffness) = system();
+        let error = |spectral_radius: f64, number_of_steps: usize| {
+            let mut integrator =
+                GeneralizedAlphaIntegrator::new(spectral_radius, &mass, &stiffness).unwrap();
+            let dt = 1.0 / number_of_steps as f64;
+            let mut u = [1.0, 1.0];
+            for step in 0..number_of_steps {
+                integrator
+                    .step(
+                        |t, f: &mut [f64]| {
+                            f[0] = t;
+                            f[1] = 0.0
+                        },
+                        step as f64 * dt,
+                        dt,
+                        &mut u,
+                    )
+                    .unwrap();
+            }
+            let exact = [2.0 * (-1.0_f64).exp(), (-2.0_f64).exp()];
+            (u[0] - exact[0]).abs().max((u[1] - exact[1]).abs())
+        };
+        for spectral_radius in [0.0, 0.5, 1.0] {
+            let rate = (error(spectral_radius, 40) / error(spectral_radius, 80)).log2();
+            assert!(
+                (rate - 2.0).abs() < 0.15,
+                "Incorrect convergence order {} for a spectral radius of {}",
+                rate,
+                spectral_radius
+            );
+        }
+        // Stiff mode u' + 1e6 u = 0 with a unit time step
+        let unit = CsrMatrix::from_triplets(1, 1, &[(0, 0, 1.0)]).unwrap();
+        let stiff = CsrMatrix::from_triplets(1, 1, &[(0, 0, 1e6)]).unwrap();
+        for (spectral_radius, bound) in [(0.0, 1e-5), (0.5, 1e-2)] {
+            let mut integrator =
+                GeneralizedAlphaIntegrator::new(spectral_radius, &unit, &stiff).unwrap();
+            let mut u = [1.0];
+            for step in 0..10 {
+                integrator
+                    .step(|_, f: &mut [f64]| f.fill(0.0), step as f64, 1.0, &mut u)
+                    .unwrap();
+            }
+            assert!(
+                u[0].abs() < bound,
+                "Stiff mode not damped with a spectral radius of {}",
+                spectral_radius
+            );
+        }
+        assert!(
+            GeneralizedAlphaIntegrator::new(1.5, &unit, &stiff).is_err(),
+            "Spectral radius above one accepted"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+/// Module for the traits shared by the time integrators
+pub mod time_traits;
+
+/// Module for the explicit Runge-Kutta integrators
+pub mod explicit;
+
+/// Module for the implicit integrators of linear systems
+pub mod implicit;
+
+/// Module for the linear combinations of matrices solved by the implicit integrators
+pub mod shifted;
+
+/// Module for the Newmark and generalized-α integrators of second order systems
+pub mod newmark;
+
+/// Module for the adaptive time stepping with error control
+pub mod adaptive;
+
+/// Module for implicit-explicit Runge-Kutta integrators of split systems
+pub mod imex;
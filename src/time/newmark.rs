@@ -0,0 +1,604 @@
+use crate::algebra::csr::CsrMatrix;
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use crate::solver::solver_traits::{LinearSolver, SolverResult};
+use crate::time::shifted::{BoxedPreconditioner, MatrixCombination, ShiftedSolver};
+use num::Float;
+
+/// Parameters `β`, `γ`, `α_m` and `α_f` of a scheme of the Newmark family
+///
+/// # Generics
+///
+/// * DataType: the type of unit the parameters are encoded with
+///
+/// # Explanation
+///
+/// The displacement and the velocity are updated with `u_{n+1} = u_n + dt v_n + dt² ((1/2 - β)
+/// a_n + β a_{n+1})` and `v_{n+1} = v_n + dt ((1 - γ) a_n + γ a_{n+1})`. The generalized-α
+/// schemes enforce the equilibrium at intermediate points, the inertia at `a_{n+1-α_m}` and the
+/// other forces at `t_{n+1-α_f}`, where `x_{n+1-α} = (1 - α) x_{n+1} + α x_n`; the Newmark schemes
+/// are the particular case `α_m = α_f = 0`. The scheme is second order for `γ = 1/2 - α_m + α_f`
+/// and first order otherwise, and unconditionally stable for `α_m ≤ α_f ≤ 1/2`,
+/// `γ ≥ 1/2 - α_m + α_f` and `2 β ≥ γ`.
+#[derive(Clone, Copy, Debug)]
+pub struct NewmarkParameters<DataType> {
+    beta: DataType,
+    gamma: DataType,
+    alpha_m: DataType,
+    alpha_f: DataType,
+}
+
+impl<DataType: Scalar + Float> NewmarkParameters<DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `beta`: the weight of the new acceleration in the displacement update
+    /// * `gamma`: the weight of the new acceleration in the velocity update
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the parameters or an error if they are negative
+    pub fn new(beta: DataType, gamma: DataType) -> Result<NewmarkParameters<DataType>, Error> {
+        if beta < DataType::zero() || gamma < DataType::zero() {
+            return Err(Error::InvalidArgument(
+                "Newmark parameters should be non negative",
+            ));
+        }
+        Ok(NewmarkParameters {
+            beta,
+            gamma,
+            alpha_m: DataType::zero(),
+            alpha_f: DataType::zero(),
+        })
+    }
+
+    /// The generalized-α scheme of Chung and Hulbert
+    ///
+    /// The scheme is second order and unconditionally stable, with the high frequencies damped to
+    /// the spectral radius of the amplification matrix at infinite time step while the low
+    /// frequencies are barely affected. A radius of one gives the average acceleration scheme, a
+    /// radius of zero annihilates the highest frequencies in a single step.
+    ///
+    /// # Arguments
+    ///
+    /// * `spectral_radius`: the spectral radius `ρ∞` at infinite time step, between zero and one
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the parameters or an error if the radius is out of bounds
+    pub fn generalized_alpha(
+        spectral_radius: DataType,
+    ) -> Result<NewmarkParameters<DataType>, Error> {
+        let one = DataType::one();
+        if spectral_radius < DataType::zero() || spectral_radius > one {
+            return Err(Error::InvalidArgument(
+                "Spectral radius should be between zero and one",
+            ));
+        }
+        let alpha_m = (spectral_radius + spectral_radius - one) / (spectral_radius + one);
+        let alpha_f = spectral_radius / (spectral_radius + one);
+        let shift = one - alpha_m + alpha_f;
+        let quarter = DataType::from(0.25).unwrap();
+        Ok(NewmarkParameters {
+            beta: quarter * shift * shift,
+            gamma: DataType::from(0.5).unwrap() - alpha_m + alpha_f,
+            alpha_m,
+            alpha_f,
+        })
+    }
+
+    /// The unconditionally stable, energy conserving, average acceleration (trapezoidal) scheme
+    pub fn average_acceleration() -> NewmarkParameters<DataType> {
+        NewmarkParameters::from_f64(0.25, 0.5)
+    }
+
+    /// The conditionally stable linear acceleration scheme
+    pub fn linear_acceleration() -> NewmarkParameters<DataType> {
+        NewmarkParameters::from_f64(1.0 / 6.0, 0.5)
+    }
+
+    /// The explicit central difference scheme, explicit when the mass matrix is lumped and there
+    /// is no damping
+    pub fn central_difference() -> NewmarkParameters<DataType> {
+        NewmarkParameters::from_f64(0.0, 0.5)
+    }
+
+    /// Build parameters from double precision values
+    fn from_f64(beta: f64, gamma: f64) -> NewmarkParameters<DataType> {
+        NewmarkParameters {
+            beta: DataType::from(beta).unwrap(),
+            gamma: DataType::from(gamma).unwrap(),
+            alpha_m: DataType::zero(),
+            alpha_f: DataType::zero(),
+        }
+    }
+
+    /// Get the weight of the new acceleration in the displacement update
+    pub fn get_beta(&self) -> DataType {
+        self.beta
+    }
+
+    /// Get the weight of the new acceleration in the velocity update
+    pub fn get_gamma(&self) -> DataType {
+        self.gamma
+    }
+
+    /// Get the weight of the previous acceleration in the inertia
+    pub fn get_alpha_m(&self) -> DataType {
+        self.alpha_m
+    }
+
+    /// Get the weight of the previous state in the other forces
+    pub fn get_alpha_f(&self) -> DataType {
+        self.alpha_f
+    }
+
+    /// Whether the scheme is stable for any time step on undamped linear systems
+    pub fn is_unconditionally_stable(&self) -> bool {
+        let half = DataType::from(0.5).unwrap();
+        self.alpha_m <= self.alpha_f
+            && self.alpha_f <= half
+            && self.gamma >= half - self.alpha_m + self.alpha_f
+            && self.beta + self.beta >= self.gamma
+    }
+}
+
+/// Newmark and generalized-α integrator of linear second order systems `M a + C v + K u = f(t)`
+///
+/// # Generics
+///
+/// * DataType: the type of unit the system is encoded with
+///
+/// # Explanation
+///
+/// Each step predicts the displacement and velocity `u*` and `v*` from the known state, then
+/// solves for the new acceleration `((1 - α_m) M + (1 - α_f) (γ dt C + β dt² K)) a_{n+1} =
+/// f(t_{n+1-α_f}) - α_m M a_n - C v_α - K u_α`, with `x_α = (1 - α_f) x* + α_f x_n`, and corrects
+/// the predictions. The damping is the sum of an optional damping matrix and of the Rayleigh damping
+/// `α_M M + α_K K`, which is folded into the coefficients of `M` and `K` and needs no assembled
+/// matrix. The system matrix is combined in a frozen pattern and its preconditioner (by default a
+/// sparse LU factorization used as a direct solver) only rebuilt when the time step changes, as for
+/// the `ImplicitIntegrator`. The initial acceleration, if not known, is computed from the
+/// equilibrium at the initial time with `compute_initial_acceleration`.
+pub struct NewmarkIntegrator<'a, DataType: Clone> {
+    parameters: NewmarkParameters<DataType>,
+    mass: &'a CsrMatrix<DataType>,
+    stiffness: &'a CsrMatrix<DataType>,
+    damping: Option<&'a CsrMatrix<DataType>>,
+    rayleigh: (DataType, DataType),
+    combination: MatrixCombination<'a, DataType>,
+    solver: ShiftedSolver<'a, DataType>,
+}
+
+impl<'a, DataType: Scalar + Float + 'a> NewmarkIntegrator<'a, DataType> {
+    /// Constructor of an undamped integrator using a sparse direct solve
+    ///
+    /// # Arguments
+    ///
+    /// * `parameters`: the Newmark parameters
+    /// * `mass`: the mass matrix `M`
+    /// * `stiffness`: the stiffness matrix `K`
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the integrator or an error if the matrices are not square of the
+    ///   same size
+    pub fn new(
+        parameters: NewmarkParameters<DataType>,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+    ) -> Result<NewmarkIntegrator<'a, DataType>, Error> {
+        Ok(NewmarkIntegrator {
+            parameters,
+            mass,
+            stiffness,
+            damping: None,
+            rayleigh: (DataType::zero(), DataType::zero()),
+            combination: MatrixCombination::new(&[mass, stiffness])?,
+            solver: ShiftedSolver::new(),
+        })
+    }
+
+    /// Set the solver of the acceleration systems
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the linear solver
+    /// * `preconditioner_factory`: builds the preconditioner from the system matrix, only called
+    ///   when the time step changes
+    pub fn set_linear_solver(
+        &mut self,
+        solver: impl LinearSolver<DataType> + 'a,
+        preconditioner_factory: impl Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error>
+            + 'a,
+    ) {
+        self.solver
+            .set_linear_solver(solver, preconditioner_factory);
+    }
+
+    /// Replace the mass and stiffness matrices, keeping the frozen pattern when they fit in it
+    pub fn set_matrices(
+        &mut self,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+    ) -> Result<(), Error> {
+        self.set_combination(mass, stiffness, self.damping)
+    }
+
+    /// Set the damping matrix `C`, added to the Rayleigh damping
+    pub fn set_damping(&mut self, damping: &'a CsrMatrix<DataType>) -> Result<(), Error> {
+        self.set_combination(self.mass, self.stiffness, Some(damping))
+    }
+
+    /// Set the Rayleigh damping `α_M M + α_K K`, added to the damping matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `mass_coefficient`: the coefficient `α_M` of the mass matrix
+    /// * `stiffness_coefficient`: the coefficient `α_K` of the stiffness matrix
+    pub fn set_rayleigh_damping(
+        &mut self,
+        mass_coefficient: DataType,
+        stiffness_coefficient: DataType,
+    ) {
+        self.rayleigh = (mass_coefficient, stiffness_coefficient);
+        self.solver.invalidate();
+    }
+
+    /// Get the Newmark parameters
+    pub fn get_parameters(&self) -> NewmarkParameters<DataType> {
+        self.parameters
+    }
+
+    /// Get the coefficients `(α_M, α_K)` of the Rayleigh damping
+    pub fn get_rayleigh_damping(&self) -> (DataType, DataType) {
+        self.rayleigh
+    }
+
+    /// Get the number of times the preconditioner of the system was built
+    pub fn get_number_of_setups(&self) -> usize {
+        self.solver.get_number_of_setups()
+    }
+
+    /// Compute the acceleration in equilibrium `M a = f(t) - C v - K u` with the state
+    ///
+    /// # Arguments
+    ///
+    /// * `force`: computes the right hand side `f(t)` at a given time
+    /// * `time`: the time of the state
+    /// * `u`: the displacement
+    /// * `v`: the velocity
+    /// * `a`: the acceleration, holding the initial guess and overwritten by the solution
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the convergence information of the linear solve or an error if
+    ///   the preconditioner could not be built or the solve did not converge
+    pub fn compute_initial_acceleration(
+        &mut self,
+        force: impl Fn(DataType, &mut [DataType]),
+        time: DataType,
+        u: &[DataType],
+        v: &[DataType],
+        a: &mut [DataType],
+    ) -> Result<SolverResult<DataType>, Error> {
+        let mut rhs = vec![DataType::zero(); u.len()];
+        force(time, &mut rhs);
+        self.subtract_internal_forces(u, v, &mut rhs);
+        let (zero, one) = (DataType::zero(), DataType::one());
+        let coefficients = [one, zero, zero];
+        let number_of_matrices = self.combination.get_number_of_matrices();
+        self.solver.solve(
+            &mut self.combination,
+            &coefficients[..number_of_matrices],
+            &rhs,
+            a,
+        )
+    }
+
+    /// Advance the state by one time step
+    ///
+    /// # Arguments
+    ///
+    /// * `force`: computes the right hand side `f(t)` at a given time
+    /// * `time`: the time at the beginning of the step
+    /// * `dt`: the time step
+    /// * `u`: the displacement, overwritten by the displacement at the end of the step
+    /// * `v`: the velocity, overwritten by the velocity at the end of the step
+    /// * `a`: the acceleration, overwritten by the acceleration at the end of the step
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the convergence information of the linear solve or an error if
+    ///   the preconditioner could not be built or the solve did not converge
+    pub fn step(
+        &mut self,
+        force: impl Fn(DataType, &mut [DataType]),
+        time: DataType,
+        dt: DataType,
+        u: &mut [DataType],
+        v: &mut [DataType],
+        a: &mut [DataType],
+    ) -> Result<SolverResult<DataType>, Error> {
+        let one = DataType::one();
+        let half = DataType::from(0.5).unwrap();
+        let NewmarkParameters {
+            beta,
+            gamma,
+            alpha_m,
+            alpha_f,
+        } = self.parameters;
+        let mut rhs = vec![DataType::zero(); u.len()];
+        force(time + (one - alpha_f) * dt, &mut rhs);
+        if alpha_m != DataType::zero() {
+            let mut work = vec![DataType::zero(); u.len()];
+            self.mass.apply_into(a, &mut work);
+            rhs.iter_mut()
+                .zip(&work)
+                .for_each(|(r, &ma)| *r = *r - alpha_m * ma);
+        }
+        let (mut u_alpha, mut v_alpha) = (u.to_vec(), v.to_vec());
+        for (((u, v), (u_alpha, v_alpha)), &a) in u
+            .iter_mut()
+            .zip(v.iter_mut())
+            .zip(u_alpha.iter_mut().zip(v_alpha.iter_mut()))
+            .zip(a.iter())
+        {
+            *u = *u + dt * *v + dt * dt * (half - beta) * a;
+            *v = *v + dt * (one - gamma) * a;
+            *u_alpha = (one - alpha_f) * *u + alpha_f * *u_alpha;
+            *v_alpha = (one - alpha_f) * *v + alpha_f * *v_alpha;
+        }
+        self.subtract_internal_forces(&u_alpha, &v_alpha, &mut rhs);
+        let (mass_coefficient, stiffness_coefficient) = self.rayleigh;
+        let weight = one - alpha_f;
+        let coefficients = [
+            one - alpha_m + weight * gamma * dt * mass_coefficient,
+            weight * (beta * dt * dt + gamma * dt * stiffness_coefficient),
+            weight * gamma * dt,
+        ];
+        let number_of_matrices = self.combination.get_number_of_matrices();
+        let result = self.solver.solve(
+            &mut self.combination,
+            &coefficients[..number_of_matrices],
+            &rhs,
+            a,
+        )?;
+        for ((u, v), &a) in u.iter_mut().zip(v.iter_mut()).zip(a.iter()) {
+            *u = *u + beta * dt * dt * a;
+            *v = *v + gamma * dt * a;
+        }
+        Ok(result)
+    }
+
+    /// Subtract `C v + K u` from the right hand side
+    fn subtract_internal_forces(&self, u: &[DataType], v: &[DataType], rhs: &mut [DataType]) {
+        let (mass_coefficient, stiffness_coefficient) = self.rayleigh;
+        let mut work = vec![DataType::zero(); u.len()];
+        let mut subtract = |matrix: &CsrMatrix<DataType>, x: &[DataType], scale: DataType| {
+            matrix.apply_into(x, &mut work);
+            rhs.iter_mut()
+                .zip(&work)
+                .for_each(|(r, &w)| *r = *r - scale * w);
+        };
+        let displacement: Vec<DataType> = u
+            .iter()
+            .zip(v)
+            .map(|(&u, &v)| u + stiffness_coefficient * v)
+            .collect();
+        subtract(self.stiffness, &displacement, DataType::one());
+        if mass_coefficient != DataType::zero() {
+            subtract(self.mass, v, mass_coefficient);
+        }
+        if let Some(damping) = self.damping {
+            subtract(damping, v, DataType::one());
+        }
+    }
+
+    /// Combine new matrices and drop the preconditioner
+    fn set_combination(
+        &mut self,
+        mass: &'a CsrMatrix<DataType>,
+        stiffness: &'a CsrMatrix<DataType>,
+        damping: Option<&'a CsrMatrix<DataType>>,
+    ) -> Result<(), Error> {
+        match damping {
+            Some(damping) => self.combination.set_matrices(&[mass, stiffness, damping])?,
+            None => self.combination.set_matrices(&[mass, stiffness])?,
+        }
+        self.mass = mass;
+        self.stiffness = stiffness;
+        self.damping = damping;
+        self.solver.invalidate();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NewmarkIntegrator, NewmarkParameters};
+    use crate::algebra::csr::CsrMatrix;
+
+    const TOL: f64 = 1e-10;
+
+    /// Undamped oscillators `diag(1, 2) a + diag(4, 18) u = 0` with `u(0) = (1, 1)` and
+    /// `v(0) = 0`, of solution `(cos(2 t), cos(3 t))`
+    fn error(parameters: NewmarkParameters<f64>, number_of_steps: usize) -> f64 {
+        let mass = CsrMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (1, 1, 2.0)]).unwrap();
+        let stiffness = CsrMatrix::from_triplets(2, 2, &[(0, 0, 4.0), (1, 1, 18.0)]).unwrap();
+        let mut integrator = NewmarkIntegrator::new(parameters, &mass, &stiffness).unwrap();
+        let (mut u, mut v, mut a) = ([1.0, 1.0], [0.0, 0.0], [0.0, 0.0]);
+        let zero = |_, f: &mut [f64]| f.fill(0.0);
+        integrator
+            .compute_initial_acceleration(zero, 0.0, &u, &v, &mut a)
+            .unwrap();
+        let dt = 1.0 / number_of_steps as f64;
+        for step in 0..number_of_steps {
+            integrator
+                .step(zero, step as f64 * dt, dt, &mut u, &mut v, &mut a)
+                .unwrap();
+        }
+        (u[0] - 2.0_f64.cos())
+            .abs()
+            .max((u[1] - 3.0_f64.cos()).abs())
+    }
+
+    #[test]
+    fn test_orders() {
+        for (parameters, order) in [
+            (NewmarkParameters::average_acceleration(), 2.0),
+            (NewmarkParameters::linear_acceleration(), 2.0),
+            (NewmarkParameters::central_difference(), 2.0),
+            (NewmarkParameters::new(0.3025, 0.6).unwrap(), 1.0),
+        ] {
+            let rate = (error(parameters, 100) / error(parameters, 200)).log2();
+            assert!(
+                (rate - order).abs() < 0.15,
+                "Incorrect convergence order {} instead of {} for {:?}",
+                rate,
+                order,
+                parameters
+            );
+        }
+        assert!(
+            NewmarkParameters::<f64>::average_acceleration().is_unconditionally_stable(),
+            "Average acceleration should be unconditionally stable"
+        );
+        assert!(
+            !NewmarkParameters::<f64>::central_difference().is_unconditionally_stable(),
+            "Central difference should be conditionally stable"
+        );
+        assert!(
+            NewmarkParameters::new(-0.1, 0.5).is_err(),
+            "Negative parameter accepted"
+        );
+    }
+
+    #[test]
+    fn test_energy_conservation() {
+        let mass = CsrMatrix::from_triplets(2, 2, &[(0, 0, 2.0), (1, 1, 1.0)]).unwrap();
+        let stiffness = CsrMatrix::from_triplets(
+            2,
+            2,
+            &[(0, 0, 2.0), (0, 1, -1.0), (1, 0, -1.0), (1, 1, 1.0)],
+        )
+        .unwrap();
+        let mut integrator =
+            NewmarkIntegrator::new(NewmarkParameters::average_acceleration(), &mass, &stiffness)
+                .unwrap();
+        let energy = |u: &[f64], v: &[f64]| {
+            let (mv, ku) = (mass.apply(v), stiffness.apply(u));
+            0.5 * (v[0] * mv[0] + v[1] * mv[1] + u[0] * ku[0] + u[1] * ku[1])
+        };
+        let (mut u, mut v, mut a) = ([1.0, 0.0], [0.0, 1.0], [0.0, 0.0]);
+        let zero = |_, f: &mut [f64]| f.fill(0.0);
+        integrator
+            .compute_initial_acceleration(zero, 0.0, &u, &v, &mut a)
+            .unwrap();
+        let initial = energy(&u, &v);
+        for step in 0..50 {
+            integrator
+                .step(zero, step as f64, 1.0, &mut u, &mut v, &mut a)
+                .unwrap();
+        }
+        assert!(
+            (energy(&u, &v) - initial).abs() < TOL,
+            "Energy not conserved by the average acceleration scheme"
+        );
+        assert_eq!(
+            integrator.get_number_of_setups(),
+            2,
+            "Preconditioner rebuilt with a constant step"
+        );
+    }
+
+    #[test]
+    fn test_rayleigh_damping() {
+        // a + 0.2 v + 4 u = 0 with u(0) = 1 and v(0) = 0, of solution
+        // exp(-0.1 t) (cos(w t) + 0.1 / w sin(w t)) with w = sqrt(3.99)
+        let mass = CsrMatrix::from_triplets(1, 1, &[(0, 0, 1.0)]).unwrap();
+        let stiffness = CsrMatrix::from_triplets(1, 1, &[(0, 0, 4.0)]).unwrap();
+        let damping = CsrMatrix::from_triplets(1, 1, &[(0, 0, 0.2)]).unwrap();
+        let zero = |_, f: &mut [f64]| f.fill(0.0);
+        let solve = |integrator: &mut NewmarkIntegrator<f64>| {
+            let (mut u, mut v, mut a) = ([1.0], [0.0], [0.0]);
+            integrator
+                .compute_initial_acceleration(zero, 0.0, &u, &v, &mut a)
+                .unwrap();
+            for step in 0..1000 {
+                integrator
+                    .step(zero, step as f64 * 1e-3, 1e-3, &mut u, &mut v, &mut a)
+                    .unwrap();
+            }
+            u[0]
+        };
+        let parameters = NewmarkParameters::average_acceleration();
+        let mut rayleigh = NewmarkIntegrator::new(parameters, &mass, &stiffness).unwrap();
+        rayleigh.set_rayleigh_damping(0.1, 0.025);
+        let mut matrix = NewmarkIntegrator::new(parameters, &mass, &stiffness).unwrap();
+        matrix.set_damping(&damping).unwrap();
+        let (first, second) = (solve(&mut rayleigh), solve(&mut matrix));
+        assert!(
+            (first - second).abs() < TOL,
+            "Rayleigh damping differs from the equivalent damping matrix"
+        );
+        let w = 3.99_f64.sqrt();
+        let exact = (-0.1_f64).exp() * (w.cos() + 0.1 / w * w.sin());
+        assert!((first - exact).abs() < 1e-6, "Incorrect damped solution");
+    }
+
+    #[test]
+    fn test_generalized_alpha() {
+        for spectral_radius in [0.0, 0.5, 0.9] {
+            let parameters = NewmarkParameters::generalized_alpha(spectral_radius).unwrap();
+            assert!(
+                parameters.is_unconditionally_stable(),
+                "Generalized-α should be unconditionally stable"
+            );
+            let rate = (error(parameters, 100) / error(parameters, 200)).log2();
+            assert!(
+                (rate - 2.0).abs() < 0.15,
+                "Incorrect convergence order {} for a spectral radius of {}",
+                rate,
+                spectral_radius
+            );
+        }
+        let conservative = NewmarkParameters::<f64>::generalized_alpha(1.0).unwrap();
+        assert!(
+            (conservative.get_beta() - 0.25).abs() < TOL
+                && (conservative.get_gamma() - 0.5).abs() < TOL
+                && (conservative.get_alpha_m() - 0.5).abs() < TOL
+                && (conservative.get_alpha_f() - 0.5).abs() < TOL,
+            "Incorrect parameters for a unit spectral radius"
+        );
+        // Stiff mode a + 1e8 u = 0 with a unit time step
+        let mass = CsrMatrix::from_triplets(1, 1, &[(0, 0, 1.0)]).unwrap();
+        let stiffness = CsrMatrix::from_triplets(1, 1, &[(0, 0, 1e8)]).unwrap();
+        let mut amplitudes = Vec::new();
+        for spectral_radius in [0.0, 1.0] {
+            let parameters = NewmarkParameters::generalized_alpha(spectral_radius).unwrap();
+            let mut integrator = NewmarkIntegrator::new(parameters, &mass, &stiffness).unwrap();
+            let (mut u, mut v, mut a) = ([1.0], [0.0], [0.0]);
+            let zero = |_, f: &mut [f64]| f.fill(0.0);
+            integrator
+                .compute_initial_acceleration(zero, 0.0, &u, &v, &mut a)
+                .unwrap();
+            for step in 0..10 {
+                integrator
+                    .step(zero, step as f64, 1.0, &mut u, &mut v, &mut a)
+                    .unwrap();
+            }
+            amplitudes.push(u[0].abs());
+        }
+        assert!(amplitudes[0] < 1e-6, "Stiff mode not damped");
+        assert!(
+            (amplitudes[1] - 1.0).abs() < 1e-2,
+            "Stiff mode damped with a unit spectral radius"
+        );
+        assert!(
+            NewmarkParameters::generalized_alpha(-0.5).is_err(),
+            "Negative spectral radius accepted"
+        );
+    }
+}
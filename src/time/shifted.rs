@@ -0,0 +1,387 @@
+use crate::algebra::csr::{CsrMatrix, Storage};
+use crate::algebra::scalar::Scalar;
+use crate::error::Error;
+use crate::solver::direct::SparseLu;
+use crate::solver::registry::PreconditionerOnly;
+use crate::solver::solver_traits::{LinearSolver, Preconditioner, SolverResult};
+use num::Float;
+use std::borrow::Cow;
+
+/// Boxed preconditioner built for a shifted system
+pub type BoxedPreconditioner<'a, DataType> = Box<dyn Preconditioner<DataType> + 'a>;
+
+/// Constructor of the preconditioner of a shifted system
+type PreconditionerFactory<'a, DataType> =
+    Box<dyn Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error> + 'a>;
+
+/// Union of sparsity patterns with the positions of the entries of each matrix in it
+type PatternUnion<DataType> = (CsrMatrix<DataType>, Vec<Vec<usize>>);
+
+/// Linear combination `sum_i c_i A_i` of matrices in a frozen sparsity pattern
+///
+/// # Generics
+///
+/// * DataType: the type of unit the matrices are encoded with
+///
+/// # Explanation
+///
+/// The implicit integrators solve systems combining the mass, damping and stiffness matrices with
+/// coefficients depending on the time step. The union of the sparsity patterns of the matrices is
+/// computed once, together with the positions of the entries of each matrix in it, so that a new
+/// combination only refills the values. Matrices of different storages are expanded to
+/// `Storage::General`.
+pub struct MatrixCombination<'a, DataType: Clone> {
+    matrices: Vec<Cow<'a, CsrMatrix<DataType>>>,
+    system: CsrMatrix<DataType>,
+    positions: Vec<Vec<usize>>,
+}
+
+impl<'a, DataType: Scalar> MatrixCombination<'a, DataType> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `matrices`: the combined matrices
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the combination or an error if the matrices are not square of
+    ///   the same size
+    pub fn new(
+        matrices: &[&'a CsrMatrix<DataType>],
+    ) -> Result<MatrixCombination<'a, DataType>, Error> {
+        let matrices = common_storage(matrices);
+        let (system, positions) = union_pattern(&matrices)?;
+        Ok(MatrixCombination {
+            matrices,
+            system,
+            positions,
+        })
+    }
+
+    /// Replace the matrices, keeping the frozen pattern when they fit in it
+    pub fn set_matrices(&mut self, matrices: &[&'a CsrMatrix<DataType>]) -> Result<(), Error> {
+        let matrices = common_storage(matrices);
+        let positions: Option<Vec<Vec<usize>>> = if matrices.len() == self.matrices.len() {
+            matrices
+                .iter()
+                .map(|matrix| entry_positions(&self.system, matrix))
+                .collect()
+        } else {
+            None
+        };
+        match positions {
+            Some(positions) => self.positions = positions,
+            None => {
+                let (system, positions) = union_pattern(&matrices)?;
+                self.system = system;
+                self.positions = positions;
+            }
+        }
+        self.matrices = matrices;
+        Ok(())
+    }
+
+    /// Replace the values of a combined matrix, its sparsity pattern being unchanged
+    ///
+    /// This takes a private copy of the matrix on the first call, so that matrices reassembled in
+    /// place (as a stiffness depending on the state) can be handed over without borrowing them.
+    ///
+    /// # Returns
+    ///
+    /// * A result holding an error if there is no such matrix or the values do not match its
+    ///   pattern
+    pub fn set_matrix_values(&mut self, index: usize, values: &[DataType]) -> Result<(), Error> {
+        let number_of_matrices = self.matrices.len();
+        let matrix = self.matrices.get_mut(index).ok_or(Error::OutOfBounds {
+            context: "Matrix index out of bounds",
+            index,
+            bound: number_of_matrices,
+        })?;
+        if matrix.get_values().len() != values.len() {
+            return Err(Error::SizeMismatch {
+                context: "Values do not match the pattern of the matrix",
+                expected: matrix.get_values().len(),
+                actual: values.len(),
+            });
+        }
+        matrix.to_mut().get_values_mut().copy_from_slice(values);
+        Ok(())
+    }
+
+    /// Get the number of combined matrices
+    pub fn get_number_of_matrices(&self) -> usize {
+        self.matrices.len()
+    }
+
+    /// Get a combined matrix
+    pub fn get_matrix(&self, index: usize) -> &CsrMatrix<DataType> {
+        &self.matrices[index]
+    }
+
+    /// Get the last filled combination
+    pub fn get_system(&self) -> &CsrMatrix<DataType> {
+        &self.system
+    }
+
+    /// Fill the values of the frozen pattern with `sum_i c_i A_i`
+    pub fn fill(&mut self, coefficients: &[DataType]) {
+        self.system.set_zero();
+        let values = self.system.get_values_mut();
+        for ((matrix, positions), &coefficient) in
+            self.matrices.iter().zip(&self.positions).zip(coefficients)
+        {
+            for (&position, &value) in positions.iter().zip(matrix.get_values()) {
+                values[position] = values[position] + coefficient * value;
+            }
+        }
+    }
+}
+
+/// Solver of the linear combinations of a `MatrixCombination`, caching the preconditioner
+///
+/// # Generics
+///
+/// * DataType: the type of unit the systems are encoded with
+///
+/// # Explanation
+///
+/// The preconditioner (by default a sparse LU factorization used as a direct solver) is only
+/// rebuilt when the coefficients of the combination change, so that constant time steps pay for a
+/// single setup.
+pub struct ShiftedSolver<'a, DataType> {
+    solver: Box<dyn LinearSolver<DataType> + 'a>,
+    preconditioner_factory: PreconditionerFactory<'a, DataType>,
+    preconditioner: Option<(Vec<DataType>, BoxedPreconditioner<'a, DataType>)>,
+    number_of_setups: usize,
+}
+
+impl<'a, DataType: Scalar + Float + 'a> Default for ShiftedSolver<'a, DataType> {
+    fn default() -> Self {
+        ShiftedSolver::new()
+    }
+}
+
+impl<'a, DataType: Scalar + Float + 'a> ShiftedSolver<'a, DataType> {
+    /// Constructor using a sparse direct solve
+    pub fn new() -> ShiftedSolver<'a, DataType> {
+        ShiftedSolver {
+            solver: Box::new(PreconditionerOnly::default()),
+            preconditioner_factory: Box::new(|matrix| Ok(Box::new(SparseLu::new(matrix)?))),
+            preconditioner: None,
+            number_of_setups: 0,
+        }
+    }
+
+    /// Set the linear solver
+    ///
+    /// # Arguments
+    ///
+    /// * `solver`: the linear solver
+    /// * `preconditioner_factory`: builds the preconditioner from the system matrix, only called
+    ///   when the coefficients change
+    pub fn set_linear_solver(
+        &mut self,
+        solver: impl LinearSolver<DataType> + 'a,
+        preconditioner_factory: impl Fn(&CsrMatrix<DataType>) -> Result<BoxedPreconditioner<'a, DataType>, Error>
+            + 'a,
+    ) {
+        self.solver = Box::new(solver);
+        self.preconditioner_factory = Box::new(preconditioner_factory);
+        self.preconditioner = None;
+    }
+
+    /// Drop the preconditioner, to rebuild it at the next solve
+    pub fn invalidate(&mut self) {
+        self.preconditioner = None;
+    }
+
+    /// Get the number of times the preconditioner was built
+    pub fn get_number_of_setups(&self) -> usize {
+        self.number_of_setups
+    }
+
+    /// Solve `(sum_i c_i A_i) x = b`
+    ///
+    /// # Arguments
+    ///
+    /// * `combination`: the combined matrices, refilled when the coefficients change
+    /// * `coefficients`: the coefficients `c_i` of the combination
+    /// * `rhs`: the right hand side
+    /// * `x`: the initial guess, overwritten by the solution
+    ///
+    /// # Returns
+    ///
+    /// * A result either holding the convergence information or an error if the preconditioner
+    ///   could not be built or the solve did not converge
+    pub fn solve(
+        &mut self,
+        combination: &mut MatrixCombination<'_, DataType>,
+        coefficients: &[DataType],
+        rhs: &[DataType],
+        x: &mut [DataType],
+    ) -> Result<SolverResult<DataType>, Error> {
+        if self
+            .preconditioner
+            .as_ref()
+            .is_none_or(|(cached, _)| cached.as_slice() != coefficients)
+        {
+            combination.fill(coefficients);
+            let preconditioner = (self.preconditioner_factory)(combination.get_system())?;
+            self.preconditioner = Some((coefficients.to_vec(), preconditioner));
+            self.number_of_setups += 1;
+        }
+        let (_, preconditioner) = self.preconditioner.as_ref().unwrap();
+        let result = self
+            .solver
+            .solve(combination.get_system(), preconditioner.as_ref(), rhs, x);
+        if !result.is_converged() {
+            return Err(Error::NotConverged(
+                "Linear solve of the time step did not converge",
+            ));
+        }
+        Ok(result)
+    }
+}
+
+/// Expand the matrices in upper storage unless all of them are
+fn common_storage<'a, DataType: Scalar>(
+    matrices: &[&'a CsrMatrix<DataType>],
+) -> Vec<Cow<'a, CsrMatrix<DataType>>> {
+    let storage = matrices.first().map(|matrix| matrix.get_storage());
+    if matrices
+        .iter()
+        .all(|matrix| Some(matrix.get_storage()) == storage)
+    {
+        return matrices
+            .iter()
+            .map(|&matrix| Cow::Borrowed(matrix))
+            .collect();
+    }
+    matrices
+        .iter()
+        .map(|&matrix| match matrix.get_storage() {
+            Storage::General => Cow::Borrowed(matrix),
+            Storage::Upper => Cow::Owned(matrix.to_general()),
+        })
+        .collect()
+}
+
+/// Build the union of the patterns of matrices of the same storage and the positions of their
+/// entries in it
+fn union_pattern<DataType: Scalar>(
+    matrices: &[Cow<'_, CsrMatrix<DataType>>],
+) -> Result<PatternUnion<DataType>, Error> {
+    let first = matrices
+        .first()
+        .ok_or(Error::InvalidArgument("No matrix to combine"))?;
+    let n = first.get_number_of_rows();
+    if let Some(matrix) = matrices
+        .iter()
+        .find(|matrix| n != matrix.get_number_of_rows() || n != matrix.get_number_of_columns())
+    {
+        return Err(Error::SizeMismatch {
+            context: "Matrices should be square of the same size",
+            expected: n,
+            actual: if n != matrix.get_number_of_rows() {
+                matrix.get_number_of_rows()
+            } else {
+                matrix.get_number_of_columns()
+            },
+        });
+    }
+    if matrices
+        .iter()
+        .any(|matrix| matrix.get_storage() != first.get_storage())
+    {
+        return Err(Error::InvalidArgument(
+            "Matrices should have the same storage",
+        ));
+    }
+    let mut rows = vec![Vec::new(); n];
+    for matrix in matrices {
+        let offsets = matrix.get_row_offsets();
+        for (row, columns) in rows.iter_mut().enumerate() {
+            columns.extend_from_slice(&matrix.get_column_indices()[offsets[row]..offsets[row + 1]]);
+        }
+    }
+    let mut row_offsets = vec![0];
+    let mut column_indices = Vec::new();
+    for mut columns in rows {
+        columns.sort_unstable();
+        columns.dedup();
+        column_indices.extend(columns);
+        row_offsets.push(column_indices.len());
+    }
+    let union = CsrMatrix::from_pattern(n, row_offsets, column_indices, first.get_storage())?;
+    let positions = matrices
+        .iter()
+        .map(|matrix| {
+            entry_positions(&union, matrix)
+                .ok_or(Error::InvalidArgument("Entry missing from the union"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((union, positions))
+}
+
+/// Get the positions of the stored entries of a matrix in a pattern of the same storage
+fn entry_positions<DataType: Scalar>(
+    pattern: &CsrMatrix<DataType>,
+    matrix: &CsrMatrix<DataType>,
+) -> Option<Vec<usize>> {
+    if pattern.get_storage() != matrix.get_storage()
+        || pattern.get_number_of_rows() != matrix.get_number_of_rows()
+    {
+        return None;
+    }
+    let offsets = matrix.get_row_offsets();
+    let columns = matrix.get_column_indices();
+    (0..matrix.get_number_of_rows())
+        .flat_map(|row| (offsets[row]..offsets[row + 1]).map(move |k| (row, columns[k])))
+        .map(|(row, column)| pattern.get_position(row, column))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MatrixCombination;
+    use crate::algebra::csr::{CsrMatrix, Storage};
+
+    const TOL: f64 = 1e-12;
+
+    #[test]
+    fn test_fill() {
+        let mut upper =
+            CsrMatrix::from_pattern(2, vec![0, 2, 3], vec![0, 1, 1], Storage::Upper).unwrap();
+        upper.get_values_mut().copy_from_slice(&[2.0, -1.0, 2.0]);
+        let diagonal = CsrMatrix::from_triplets(2, 2, &[(0, 0, 1.0), (1, 1, 3.0)]).unwrap();
+        let mut combination = MatrixCombination::new(&[&diagonal, &upper]).unwrap();
+        combination.fill(&[2.0, 1.0]);
+        let system = combination.get_system();
+        assert_eq!(system.get_storage(), Storage::General, "Incorrect storage");
+        for (row, column, value) in [(0, 0, 4.0_f64), (0, 1, -1.0), (1, 0, -1.0), (1, 1, 8.0)] {
+            assert!(
+                (system.get(row, column) - value).abs() < TOL,
+                "Incorrect combination entry ({}, {})",
+                row,
+                column
+            );
+        }
+        combination.set_matrix_values(0, &[2.0, 5.0]).unwrap();
+        combination.fill(&[1.0, 0.0]);
+        assert!(
+            (combination.get_system().get(1, 1) - 5.0).abs() < TOL
+                && (diagonal.get(1, 1) - 3.0).abs() < TOL,
+            "Incorrect replaced values"
+        );
+        assert!(
+            combination.set_matrix_values(1, &[1.0]).is_err(),
+            "Values of another pattern accepted"
+        );
+        let wrong = CsrMatrix::from_triplets(3, 3, &[(0, 0, 1.0)]).unwrap();
+        assert!(
+            combination.set_matrices(&[&diagonal, &wrong]).is_err(),
+            "Matrices of different sizes accepted"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::algebra::scalar::Scalar;
+use crate::solver::solver_traits::Preconditioner;
+use std::cell::RefCell;
+
+/// Provides the rate of a first order system `du/dt = f(t, u)`
+///
+/// # Generics
+///
+/// * DataType: the type of unit the state is encoded with
+///
+/// # Explanation
+///
+/// Explicit integrators only need the rate of the system. Any closure
+/// `Fn(DataType, &[DataType], &mut [DataType])` taking the time, the state and the rate to fill
+/// can be used as a rate function.
+pub trait RateFunction<DataType> {
+    /// Compute the rate `f(t, u)`
+    fn evaluate(&self, time: DataType, u: &[DataType], rate: &mut [DataType]);
+}
+
+impl<DataType, F: Fn(DataType, &[DataType], &mut [DataType])> RateFunction<DataType> for F {
+    fn evaluate(&self, time: DataType, u: &[DataType], rate: &mut [DataType]) {
+        self(time, u, rate)
+    }
+}
+
+/// Rate `M^{-1} F(t, u)` of a semi-discrete system `M du/dt = F(t, u)`
+///
+/// # Generics
+///
+/// * DataType: the type of unit the state is encoded with
+/// * ForceT: the rate function computing the right hand side `F(t, u)`
+///
+/// # Explanation
+///
+/// The inverse of the mass matrix is given as a `Preconditioner`: a `JacobiPreconditioner` of the
+/// lumped mass (`CsrMatrix::get_row_sums`) for the usual explicit dynamics, a direct factorization
+/// of the consistent mass matrix for accuracy.
+pub struct MassRate<'a, DataType, ForceT> {
+    mass_inverse: &'a dyn Preconditioner<DataType>,
+    force: ForceT,
+    work: RefCell<Vec<DataType>>,
+}
+
+impl<'a, DataType: Scalar, ForceT: RateFunction<DataType>> MassRate<'a, DataType, ForceT> {
+    /// Constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `mass_inverse`: the application of the inverse of the mass matrix
+    /// * `force`: the right hand side of the system
+    pub fn new(
+        mass_inverse: &'a dyn Preconditioner<DataType>,
+        force: ForceT,
+    ) -> MassRate<'a, DataType, ForceT> {
+        MassRate {
+            mass_inverse,
+            force,
+            work: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl<'a, DataType: Scalar, ForceT: RateFunction<DataType>> RateFunction<DataType>
+    for MassRate<'a, DataType, ForceT>
+{
+    fn evaluate(&self, time: DataType, u: &[DataType], rate: &mut [DataType]) {
+        let mut work = self.work.borrow_mut();
+        work.resize(u.len(), DataType::zero());
+        self.force.evaluate(time, u, &mut work);
+        self.mass_inverse.apply(&work, rate);
+    }
+}
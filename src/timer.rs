@@ -0,0 +1,216 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Accumulated wall time of a named phase
+struct PhaseTime {
+    name: String,
+    total: Duration,
+    calls: usize,
+}
+
+/// Report of the wall time spent in the named phases of a simulation
+///
+/// # Explanation
+///
+/// The report accumulates, for each phase name, the total wall time and the number of times the
+/// phase was recorded, in the order the phases were first recorded. Clones of a report share its
+/// phases, so that a single report can be handed to the instrumented parts of the library while
+/// the caller keeps timing its own phases with `scope`. Scopes may be nested, in which case the
+/// time of the inner phases is also counted in the outer one. The report is printed as a table
+/// with `Display`.
+///
+/// The library records the phases below once a report is set with `set_timer_report`:
+///
+/// * "assembly/pattern", "assembly/compute", "assembly/scatter": the phases of the assembly loops
+///   (see `AssemblyOptions`)
+/// * "solver/solve": the solves of a `ConfiguredSolver`
+/// * "refinement/residual", "refinement/correction": the high precision residuals and the low
+///   precision correction solves of an `IterativeRefinement`
+/// * "adaptive/step": the attempted steps of the adaptive time integrators, rejected ones included
+/// * "output/vtu", "output/xdmf", "output/checkpoint": the writes of the VTU and XDMF files and of
+///   the checkpoints
+#[derive(Clone, Default)]
+pub struct TimerReport {
+    phases: Arc<Mutex<Vec<PhaseTime>>>,
+}
+
+impl TimerReport {
+    /// Constructor of an empty report
+    pub fn new() -> TimerReport {
+        TimerReport::default()
+    }
+
+    /// Record the time spent once in a phase
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the name of the phase
+    /// * `duration`: the wall time spent in the phase
+    pub fn add(&self, name: &str, duration: Duration) {
+        let mut phases = self.lock();
+        match phases.iter_mut().find(|phase| phase.name == name) {
+            Some(phase) => {
+                phase.total += duration;
+                phase.calls += 1;
+            }
+            None => phases.push(PhaseTime {
+                name: name.to_string(),
+                total: duration,
+                calls: 1,
+            }),
+        }
+    }
+
+    /// Start timing a phase, the time is recorded when the returned timer is dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the name of the phase
+    pub fn scope<'a>(&'a self, name: &'a str) -> Timer<'a> {
+        Timer {
+            report: self,
+            name,
+            start: Instant::now(),
+        }
+    }
+
+    /// Get the names of the recorded phases in the order they were first recorded
+    pub fn get_names(&self) -> Vec<String> {
+        self.lock().iter().map(|phase| phase.name.clone()).collect()
+    }
+
+    /// Get the total time spent in a phase, zero if it was never recorded
+    pub fn get_total(&self, name: &str) -> Duration {
+        self.lock()
+            .iter()
+            .find(|phase| phase.name == name)
+            .map_or(Duration::ZERO, |phase| phase.total)
+    }
+
+    /// Get the number of times a phase was recorded
+    pub fn get_number_of_calls(&self, name: &str) -> usize {
+        self.lock()
+            .iter()
+            .find(|phase| phase.name == name)
+            .map_or(0, |phase| phase.calls)
+    }
+
+    /// Forget all the recorded phases
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Lock the phases, recovering them if a thread panicked while recording
+    fn lock(&self) -> MutexGuard<'_, Vec<PhaseTime>> {
+        self.phases
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl fmt::Display for TimerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases = self.lock();
+        let width = phases
+            .iter()
+            .map(|phase| phase.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Phase".len());
+        writeln!(
+            f,
+            "{:<width$} {:>8} {:>14} {:>14}",
+            "Phase",
+            "Calls",
+            "Total (s)",
+            "Mean (s)",
+            width = width
+        )?;
+        for phase in phases.iter() {
+            let total = phase.total.as_secs_f64();
+            writeln!(
+                f,
+                "{:<width$} {:>8} {:>14.6} {:>14.6}",
+                phase.name,
+                phase.calls,
+                total,
+                total / phase.calls as f64,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Running timer of a phase, recording its time in the report when dropped
+pub struct Timer<'a> {
+    report: &'a TimerReport,
+    name: &'a str,
+    start: Instant,
+}
+
+impl Timer<'_> {
+    /// Stop the timer before the end of its scope
+    pub fn stop(self) {}
+}
+
+impl Drop for Timer<'_> {
+    fn drop(&mut self) {
+        self.report.add(self.name, self.start.elapsed());
+    }
+}
+
+/// Time a phase in an optional report
+///
+/// # Returns
+///
+/// * the running timer, recording the phase when dropped, if a report is set
+pub(crate) fn scope<'a>(report: &'a Option<TimerReport>, name: &'a str) -> Option<Timer<'a>> {
+    report.as_ref().map(|report| report.scope(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimerReport;
+    use std::time::Duration;
+
+    #[test]
+    fn test_report() {
+        let report = TimerReport::new();
+        report.add("solve", Duration::from_millis(3));
+        report.add("assembly", Duration::from_millis(2));
+        report.add("solve", Duration::from_millis(5));
+        {
+            let _timer = report.scope("output");
+        }
+        report.scope("output").stop();
+        assert_eq!(
+            report.get_names(),
+            vec!["solve", "assembly", "output"],
+            "Incorrect phases"
+        );
+        assert_eq!(
+            report.get_total("solve"),
+            Duration::from_millis(8),
+            "Incorrect total time"
+        );
+        assert_eq!(report.get_number_of_calls("solve"), 2, "Incorrect calls");
+        assert_eq!(report.get_number_of_calls("output"), 2, "Incorrect calls");
+        assert_eq!(
+            report.get_total("refinement"),
+            Duration::ZERO,
+            "Incorrect unrecorded phase"
+        );
+        let table = report.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4, "Incorrect number of rows");
+        assert!(
+            lines[1].starts_with("solve") && lines[1].contains("0.008000"),
+            "Incorrect row {}",
+            lines[1]
+        );
+        report.clear();
+        assert!(report.get_names().is_empty(), "Report not cleared");
+    }
+}